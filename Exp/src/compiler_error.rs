@@ -2,22 +2,26 @@ use std::{error::Error, fmt};
 
 #[derive(Debug)]
 pub enum CompilerError {
-    OpenError(std::io::Error),
-    RegexError(regex::Error),
+    SyntaxError(String, u16, u16),
     InvalidSyntax(u16),
-    InvalidToken(String, u16),
+    InvalidToken(String, u16, u16),
     NoSuchVar(String, u16),
     MissmatchedTypes(String, u16),
+    Redeclaration(String, u16),
+    InvalidCondition(u16),
+    MissingReturn(String, u16),
+    MisplacedJump(String, u16),
 }
 
 impl fmt::Display for CompilerError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            CompilerError::OpenError(err) => write!(f, "Captured Underlying Error: {}", err),
-            CompilerError::RegexError(err) => write!(f, "Captured Underlying Regex Error: {}", err),
+            CompilerError::SyntaxError(message, line, column) => {
+                write!(f, "Error: {} at line {}, column {}", message, line, column)
+            }
             CompilerError::InvalidSyntax(line) => write!(f, "Invalid syntax at line: {}", line),
-            CompilerError::InvalidToken(token, line) => {
-                write!(f, "Invalid token \"{}\" at line: {}", token, line)
+            CompilerError::InvalidToken(token, line, column) => {
+                write!(f, "Invalid token \"{}\" at line: {}, column: {}", token, line, column)
             }
             CompilerError::NoSuchVar(token, line) => {
                 write!(f, "Invalid Var \"{}\" at line: {}", token, line)
@@ -25,19 +29,18 @@ impl fmt::Display for CompilerError {
             CompilerError::MissmatchedTypes(token, line) => {
                 write!(f, "Missmatched Var \"{}\" at line: {}", token, line)
             }
+            CompilerError::Redeclaration(token, line) => {
+                write!(f, "Redeclared Var \"{}\" at line: {}", token, line)
+            }
+            CompilerError::InvalidCondition(line) => write!(f, "Invalid condition at line: {}", line),
+            CompilerError::MissingReturn(token, line) => {
+                write!(f, "Missing return in function \"{}\" at line: {}", token, line)
+            }
+            CompilerError::MisplacedJump(token, line) => {
+                write!(f, "Misplaced \"{}\" outside of a loop at line: {}", token, line)
+            }
         }
     }
 }
 
-impl Error for CompilerError {
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
-        match self {
-            CompilerError::OpenError(err) => Some(err),
-            _ => None,
-        }
-    }
-}
-
-pub fn handle_error<T>(result: Result<T, std::io::Error>, _line: u16) -> Result<T, CompilerError> {
-    result.map_err(|e| CompilerError::OpenError(e))
-}
\ No newline at end of file
+impl Error for CompilerError {}
\ No newline at end of file
@@ -0,0 +1,590 @@
+use crate::parser::ASTNode;
+use crate::token::Token;
+use std::fmt;
+
+// A three-address instruction, unused fields are empty
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quadruple {
+    pub op: String,
+    pub arg1: String,
+    pub arg2: String,
+    pub result: String,
+}
+
+impl fmt::Display for Quadruple {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Quadruple { op, arg1, arg2, result } = self;
+        match op.as_str() {
+            "func" => write!(f, "func {}:", arg1),
+            "endfunc" => write!(f, "endfunc {}", arg1),
+            "label" => write!(f, "{}:", result),
+            "goto" => write!(f, "goto {}", result),
+            "=" => write!(f, "{} = {}", result, arg1),
+            "minus" => write!(f, "{} = -{}", result, arg1),
+            "=[]" => write!(f, "{} = {}[{}]", result, arg1, arg2),
+            "[]=" => write!(f, "{}[{}] = {}", result, arg2, arg1),
+            "param" => write!(f, "param {}", arg1),
+            "call" => write!(f, "{} = call {}, {}", result, arg1, arg2),
+            "return" if arg1.is_empty() => write!(f, "return"),
+            "return" => write!(f, "return {}", arg1),
+            // conditional jumps are stored as `if<`, `if==`, ...
+            _ if op.starts_with("if") => write!(f, "if {} {} {} goto {}", arg1, &op[2..], arg2, result),
+            _ => write!(f, "{} = {} {} {}", result, arg1, op, arg2),
+        }
+    }
+}
+
+// Prints every quadruple as a row of `(op, arg1, arg2, result)`
+pub fn table(quadruples: &[Quadruple]) -> String {
+    let width = |column: fn(&Quadruple) -> &String, header: &str| {
+        quadruples.iter().map(|q| column(q).len()).chain([header.len()]).max().unwrap_or(0)
+    };
+    let widths = [
+        quadruples.len().to_string().len().max(1),
+        width(|q| &q.op, "op"),
+        width(|q| &q.arg1, "arg1"),
+        width(|q| &q.arg2, "arg2"),
+        width(|q| &q.result, "result"),
+    ];
+    let row = |cells: [&str; 5]| {
+        let cells: Vec<String> =
+            cells.iter().zip(widths.iter()).map(|(cell, width)| format!("{:<width$}", cell, width = width)).collect();
+        format!("| {} |\n", cells.join(" | "))
+    };
+
+    let mut output = row(["#", "op", "arg1", "arg2", "result"]);
+    let separator: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+    output.push_str(&format!("|-{}-|\n", separator.join("-|-")));
+    for (i, q) in quadruples.iter().enumerate() {
+        output.push_str(&row([&i.to_string(), &q.op, &q.arg1, &q.arg2, &q.result]));
+    }
+    output
+}
+
+// Prints the quadruples as three-address code, labels aren't indented
+pub fn text(quadruples: &[Quadruple]) -> String {
+    let mut output = String::new();
+    for q in quadruples {
+        if q.op == "label" || q.op == "func" || q.op == "endfunc" {
+            output.push_str(&format!("{}\n", q));
+        } else {
+            output.push_str(&format!("    {}\n", q));
+        }
+    }
+    output
+}
+
+pub struct IntermediateCodeGenerator {
+    quadruples: Vec<Quadruple>,
+    temporaries: usize,
+    labels: usize,
+    // targets of `break` and `continue` in the enclosing statements
+    breaks: Vec<String>,
+    continues: Vec<String>,
+}
+
+impl IntermediateCodeGenerator {
+    pub fn new() -> Self {
+        IntermediateCodeGenerator {
+            quadruples: Vec::new(),
+            temporaries: 0,
+            labels: 0,
+            breaks: Vec::new(),
+            continues: Vec::new(),
+        }
+    }
+
+    // Expects an AST that passed the semantic analysis
+    pub fn generate(&mut self, nodes: &Vec<ASTNode>) -> Vec<Quadruple> {
+        for node in nodes {
+            self.statement(node);
+        }
+        std::mem::take(&mut self.quadruples)
+    }
+
+    fn statement(&mut self, node: &ASTNode) {
+        match node {
+            ASTNode::Function { name, body, .. } => {
+                self.emit("func", name, "", "");
+                for node in body {
+                    self.statement(node);
+                }
+                self.emit("endfunc", name, "", "");
+            }
+            ASTNode::Return { value, .. } => {
+                let value = match value {
+                    Some(value) => self.expression(value),
+                    None => String::new(),
+                };
+                self.emit("return", &value, "", "");
+            }
+            ASTNode::VariableDeclaration { name, array_size: Some(_), value: Some(value), .. } => {
+                if let ASTNode::InitializerList(elements) = value.as_ref() {
+                    for (i, element) in elements.iter().enumerate() {
+                        let element = self.expression(element);
+                        self.emit("[]=", &element, &i.to_string(), name);
+                    }
+                }
+            }
+            ASTNode::Assignment { name, index: Some(index), value, .. } => {
+                let index = self.expression(index);
+                let value = self.expression(value);
+                self.emit("[]=", &value, &index, name);
+            }
+            ASTNode::VariableDeclaration { name, value: Some(value), .. }
+            | ASTNode::Assignment { name, index: None, value, .. } => {
+                let value = self.expression(value);
+                self.emit("=", &value, "", name);
+            }
+            ASTNode::VariableDeclaration { value: None, .. } => (),
+            ASTNode::If { condition, body, else_body, .. } => {
+                let (true_list, false_list) = self.condition(condition);
+                self.place_label(true_list);
+                for node in body {
+                    self.statement(node);
+                }
+
+                match else_body {
+                    Some(else_body) => {
+                        let end = self.new_label();
+                        self.emit("goto", "", "", &end);
+                        self.place_label(false_list);
+                        for node in else_body {
+                            self.statement(node);
+                        }
+                        self.emit("label", "", "", &end);
+                    }
+                    None => self.place_label(false_list),
+                }
+            }
+            ASTNode::While { condition, body, .. } => {
+                let begin = self.new_label();
+                let end = self.new_label();
+                self.emit("label", "", "", &begin);
+                let (true_list, false_list) = self.condition(condition);
+                self.place_label(true_list);
+                self.loop_body(body, &end, &begin);
+                self.emit("goto", "", "", &begin);
+                self.backpatch(&false_list, &end);
+                self.emit("label", "", "", &end);
+            }
+            ASTNode::DoWhile { body, condition, .. } => {
+                let begin = self.new_label();
+                let next = self.new_label();
+                let end = self.new_label();
+                self.emit("label", "", "", &begin);
+                self.loop_body(body, &end, &next);
+                self.emit("label", "", "", &next);
+                let (true_list, false_list) = self.condition(condition);
+                self.backpatch(&true_list, &begin);
+                self.backpatch(&false_list, &end);
+                self.emit("label", "", "", &end);
+            }
+            ASTNode::For { init, condition, update, body, .. } => {
+                for node in init {
+                    self.statement(node);
+                }
+                let begin = self.new_label();
+                let next = self.new_label();
+                let end = self.new_label();
+                self.emit("label", "", "", &begin);
+                // without a condition the loop only ends by `break`
+                let false_list = match condition {
+                    Some(condition) => {
+                        let (true_list, false_list) = self.condition(condition);
+                        self.place_label(true_list);
+                        false_list
+                    }
+                    None => Vec::new(),
+                };
+                self.loop_body(body, &end, &next);
+                self.emit("label", "", "", &next);
+                if let Some(update) = update {
+                    self.statement(update);
+                }
+                self.emit("goto", "", "", &begin);
+                self.backpatch(&false_list, &end);
+                self.emit("label", "", "", &end);
+            }
+            ASTNode::Switch { value, cases, .. } => {
+                let value = self.expression(value);
+                let end = self.new_label();
+                let labels: Vec<String> = cases.iter().map(|_| self.new_label()).collect();
+
+                // compare against every case first, so the bodies can fall through into each other
+                let mut default = None;
+                for ((case_value, _), label) in cases.iter().zip(labels.iter()) {
+                    match case_value {
+                        Some(case_value) => {
+                            let case_value = self.expression(case_value);
+                            self.emit("if==", &value, &case_value, label);
+                        }
+                        None => default = Some(label.clone()),
+                    }
+                }
+                self.emit("goto", "", "", default.as_ref().unwrap_or(&end));
+
+                self.breaks.push(end.clone());
+                for ((_, body), label) in cases.iter().zip(labels.iter()) {
+                    self.emit("label", "", "", label);
+                    for node in body {
+                        self.statement(node);
+                    }
+                }
+                self.breaks.pop();
+                self.emit("label", "", "", &end);
+            }
+            ASTNode::Break { .. } => {
+                let target = self.breaks.last().expect("the semantic analysis rejects misplaced breaks").clone();
+                self.emit("goto", "", "", &target);
+            }
+            ASTNode::Continue { .. } => {
+                let target = self.continues.last().expect("the semantic analysis rejects misplaced continues").clone();
+                self.emit("goto", "", "", &target);
+            }
+            expression => {
+                self.expression(expression);
+            }
+        }
+    }
+
+    // `break` jumps to `end` and `continue` to `next` in the body
+    fn loop_body(&mut self, body: &Vec<ASTNode>, end: &str, next: &str) {
+        self.breaks.push(end.to_string());
+        self.continues.push(next.to_string());
+        for node in body {
+            self.statement(node);
+        }
+        self.breaks.pop();
+        self.continues.pop();
+    }
+
+    // Returns the temporary, variable or constant holding the value of the expression
+    fn expression(&mut self, node: &ASTNode) -> String {
+        match node {
+            ASTNode::Literal(lit) => lit.clone(),
+            ASTNode::StringLiteral(string) => format!("\"{}\"", string),
+            ASTNode::CharLiteral(chr) => format!("'{}'", chr),
+            ASTNode::Identifier(name) => name.clone(),
+            ASTNode::Binary { operator, left, right } if Self::is_arithmetic(operator) => {
+                let left = self.expression(left);
+                let right = self.expression(right);
+                let result = self.new_temporary();
+                self.emit(Self::operator(operator), &left, &right, &result);
+                result
+            }
+            ASTNode::Unary { operator: Token::Minus, operand } => {
+                let operand = self.expression(operand);
+                let result = self.new_temporary();
+                self.emit("minus", &operand, "", &result);
+                result
+            }
+            // comparisons and logical operators are evaluated by jumping to the assignment of 1 or 0
+            ASTNode::Binary { .. } | ASTNode::Unary { .. } => {
+                let (true_list, false_list) = self.condition(node);
+                let result = self.new_temporary();
+                let end = self.new_label();
+                self.place_label(true_list);
+                self.emit("=", "1", "", &result);
+                self.emit("goto", "", "", &end);
+                self.place_label(false_list);
+                self.emit("=", "0", "", &result);
+                self.emit("label", "", "", &end);
+                result
+            }
+            ASTNode::Call { name, arguments, .. } => {
+                let arguments: Vec<String> = arguments.iter().map(|argument| self.expression(argument)).collect();
+                for argument in arguments.iter() {
+                    self.emit("param", argument, "", "");
+                }
+                let result = self.new_temporary();
+                self.emit("call", name, &arguments.len().to_string(), &result);
+                result
+            }
+            ASTNode::Index { array, index } => {
+                let array = self.expression(array);
+                let index = self.expression(index);
+                let result = self.new_temporary();
+                self.emit("=[]", &array, &index, &result);
+                result
+            }
+            ASTNode::Error { .. } => unreachable!("the semantic analysis rejects syntax errors"),
+            statement => unreachable!("statement {:?} used as expression", statement),
+        }
+    }
+
+    // Emits jumping code for a condition, returning the jumps taken when it is true and when it is false.
+    // Their targets are left empty until they are backpatched.
+    fn condition(&mut self, node: &ASTNode) -> (Vec<usize>, Vec<usize>) {
+        match node {
+            ASTNode::Binary { operator: Token::And, left, right } => {
+                let (left_true, left_false) = self.condition(left);
+                self.place_label(left_true);
+                let (right_true, right_false) = self.condition(right);
+                (right_true, [left_false, right_false].concat())
+            }
+            ASTNode::Binary { operator: Token::Or, left, right } => {
+                let (left_true, left_false) = self.condition(left);
+                self.place_label(left_false);
+                let (right_true, right_false) = self.condition(right);
+                ([left_true, right_true].concat(), right_false)
+            }
+            ASTNode::Unary { operator: Token::Not, operand } => {
+                let (true_list, false_list) = self.condition(operand);
+                (false_list, true_list)
+            }
+            ASTNode::Binary { operator, left, right } if !Self::is_arithmetic(operator) => {
+                let left = self.expression(left);
+                let right = self.expression(right);
+                self.jumps(&format!("if{}", Self::operator(operator)), &left, &right)
+            }
+            _ => {
+                let value = self.expression(node);
+                self.jumps("if!=", &value, "0")
+            }
+        }
+    }
+
+    // A conditional jump followed by the jump for the false case
+    fn jumps(&mut self, op: &str, arg1: &str, arg2: &str) -> (Vec<usize>, Vec<usize>) {
+        self.emit(op, arg1, arg2, "");
+        self.emit("goto", "", "", "");
+        (vec![self.quadruples.len() - 2], vec![self.quadruples.len() - 1])
+    }
+
+    // Emits a new label here and makes all jumps in `list` target it
+    fn place_label(&mut self, list: Vec<usize>) {
+        let label = self.new_label();
+        self.backpatch(&list, &label);
+        self.emit("label", "", "", &label);
+    }
+
+    fn backpatch(&mut self, list: &[usize], label: &str) {
+        for &i in list {
+            self.quadruples[i].result = label.to_string();
+        }
+    }
+
+    fn emit(&mut self, op: &str, arg1: &str, arg2: &str, result: &str) {
+        self.quadruples.push(Quadruple {
+            op: op.to_string(),
+            arg1: arg1.to_string(),
+            arg2: arg2.to_string(),
+            result: result.to_string(),
+        });
+    }
+
+    fn new_temporary(&mut self) -> String {
+        self.temporaries += 1;
+        format!("t{}", self.temporaries)
+    }
+
+    fn new_label(&mut self) -> String {
+        self.labels += 1;
+        format!("L{}", self.labels)
+    }
+
+    fn is_arithmetic(operator: &Token) -> bool {
+        matches!(operator, Token::Plus | Token::Minus | Token::Multiply | Token::Divide | Token::Modulo)
+    }
+
+    fn operator(operator: &Token) -> &'static str {
+        match operator {
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Multiply => "*",
+            Token::Divide => "/",
+            Token::Modulo => "%",
+            Token::LessThan => "<",
+            Token::GreaterThan => ">",
+            Token::LessEquals => "<=",
+            Token::GreaterEquals => ">=",
+            Token::Equals => "==",
+            Token::NotEquals => "!=",
+            operator => unreachable!("{:?} is not a binary operator", operator),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn generate(source: &str) -> Vec<String> {
+        let ast = Parser::new(Lexer::new(source).tokenize()).parse().unwrap();
+        IntermediateCodeGenerator::new().generate(&ast).iter().map(|q| q.to_string()).collect()
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(generate("x = a + b * -c;"), ["t1 = -c", "t2 = b * t1", "t3 = a + t2", "x = t3"]);
+        assert_eq!(generate("v[i] = v[0] * 2;"), ["t1 = v[0]", "t2 = t1 * 2", "v[i] = t2"]);
+    }
+
+    #[test]
+    fn and_condition() {
+        assert_eq!(generate("if (a < b && c != 0) { x = 1; }"), [
+            "if a < b goto L1",
+            "goto L3",
+            "L1:",
+            "if c != 0 goto L2",
+            "goto L3",
+            "L2:",
+            "x = 1",
+            "L3:",
+        ]);
+    }
+
+    #[test]
+    fn or_and_not_condition() {
+        assert_eq!(generate("if (a == 1 || !(b > 2)) { x = 1; } else { x = 2; }"), [
+            "if a == 1 goto L2",
+            "goto L1",
+            "L1:",
+            "if b > 2 goto L4",
+            "goto L2",
+            "L2:",
+            "x = 1",
+            "goto L3",
+            "L4:",
+            "x = 2",
+            "L3:",
+        ]);
+    }
+
+    #[test]
+    fn condition_value() {
+        assert_eq!(generate("x = a < b && !c;"), [
+            "if a < b goto L1",
+            "goto L4",
+            "L1:",
+            "if c != 0 goto L4",
+            "goto L3",
+            "L3:",
+            "t1 = 1",
+            "goto L2",
+            "L4:",
+            "t1 = 0",
+            "L2:",
+            "x = t1",
+        ]);
+    }
+
+    #[test]
+    fn while_loop() {
+        assert_eq!(generate("while (i < 10) { i = i + 1; }"), [
+            "L1:",
+            "if i < 10 goto L3",
+            "goto L2",
+            "L3:",
+            "t1 = i + 1",
+            "i = t1",
+            "goto L1",
+            "L2:",
+        ]);
+    }
+
+    #[test]
+    fn calls() {
+        let source = "int f(int a, int b) { return a + b; }
+            int main() { int r = f(1, f(2, 3)); printf(\"%d\", r); return 0; }";
+        assert_eq!(generate(source), [
+            "func f:",
+            "t1 = a + b",
+            "return t1",
+            "endfunc f",
+            "func main:",
+            "param 2",
+            "param 3",
+            "t2 = call f, 2",
+            "param 1",
+            "param t2",
+            "t3 = call f, 2",
+            "r = t3",
+            "param \"%d\"",
+            "param r",
+            "t4 = call printf, 2",
+            "return 0",
+            "endfunc main",
+        ]);
+    }
+
+    #[test]
+    fn while_break_continue() {
+        assert_eq!(generate("while (1) { if (i > 5) { break; } i++; continue; }"), [
+            "L1:",
+            "if 1 != 0 goto L3",
+            "goto L2",
+            "L3:",
+            "if i > 5 goto L4",
+            "goto L5",
+            "L4:",
+            "goto L2",
+            "L5:",
+            "t1 = i + 1",
+            "i = t1",
+            "goto L1",
+            "goto L1",
+            "L2:",
+        ]);
+    }
+
+    #[test]
+    fn for_break_continue() {
+        // `continue` jumps to the update and `break` past the loop
+        let source = "for (int i = 0; i < 3; i++) { if (i == 1) { continue; } if (i == 2) { break; } s = s + i; }";
+        assert_eq!(generate(source), [
+            "i = 0",
+            "L1:",
+            "if i < 3 goto L4",
+            "goto L3",
+            "L4:",
+            "if i == 1 goto L5",
+            "goto L6",
+            "L5:",
+            "goto L2",
+            "L6:",
+            "if i == 2 goto L7",
+            "goto L8",
+            "L7:",
+            "goto L3",
+            "L8:",
+            "t1 = s + i",
+            "s = t1",
+            "L2:",
+            "t2 = i + 1",
+            "i = t2",
+            "goto L1",
+            "L3:",
+        ]);
+    }
+
+    #[test]
+    fn do_while() {
+        assert_eq!(generate("do { i--; } while (i > 0);"), [
+            "L1:",
+            "t1 = i - 1",
+            "i = t1",
+            "L2:",
+            "if i > 0 goto L1",
+            "goto L3",
+            "L3:",
+        ]);
+    }
+
+    #[test]
+    fn switch() {
+        assert_eq!(generate("switch (x) { case 1: y = 1; break; default: y = 0; }"), [
+            "if x == 1 goto L2",
+            "goto L3",
+            "L2:",
+            "y = 1",
+            "goto L1",
+            "L3:",
+            "y = 0",
+            "L1:",
+        ]);
+    }
+}
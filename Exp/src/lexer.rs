@@ -1,8 +1,14 @@
-use crate::token::Token;
+use crate::compiler_error::CompilerError;
+use crate::token::{Span, Token};
 
 pub struct Lexer {
     input: Vec<char>,
     position: usize,
+    errors: Vec<CompilerError>,
+    // span of the character at `span_position`
+    line: usize,
+    column: usize,
+    span_position: usize,
 }
 
 impl Lexer {
@@ -10,22 +16,52 @@ impl Lexer {
         Lexer {
             input: input.chars().collect(),
             position: 0,
+            errors: Vec::new(),
+            line: 1,
+            column: 1,
+            span_position: 0,
         }
     }
 
-    pub fn tokenize(&mut self) -> Vec<Token> {
+    // Unknown characters are reported in `errors` and left out of the tokens
+    pub fn tokenize(&mut self) -> Vec<(Token, Span)> {
         let mut tokens = Vec::new();
-        while let Some(token) = self.next_token() {
-            if token != Token::Whitespace {
-                tokens.push(token);
+        loop {
+            self.skip_whitespace_and_comments();
+            let span = self.span_at(self.position);
+
+            match self.next_token() {
+                Some(Token::Unknown(chr)) => self.errors.push(CompilerError::InvalidToken(
+                    chr.to_string(),
+                    span.line as u16,
+                    span.column as u16,
+                )),
+                Some(token) => tokens.push((token, span)),
+                None => break,
             }
         }
         tokens
     }
 
-    fn next_token(&mut self) -> Option<Token> {
-        self.skip_whitespace();
+    pub fn errors(&self) -> &Vec<CompilerError> {
+        &self.errors
+    }
 
+    // tokens are read in order so the characters before them only have to be counted once
+    fn span_at(&mut self, position: usize) -> Span {
+        while self.span_position < position.min(self.input.len()) {
+            if self.input[self.span_position] == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+            self.span_position += 1;
+        }
+        Span { line: self.line, column: self.column }
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
         if self.position >= self.input.len() {
             return None;
         }
@@ -41,42 +77,42 @@ impl Lexer {
         }
 
         if current_char == '"' {
-            return Some(self.read_string_literal());
+            return self.read_string_literal();
         }
 
         if current_char == '\'' {
             return Some(self.read_char_literal());
         }
 
-        if current_char == '/' {
-            if self.position + 1 < self.input.len() {
-                match self.input[self.position + 1] {
-                    '/' => {
-                        self.position += 2;
-                        self.skip_single_line_comment();
-                        return self.next_token();
-                    }
-                    '*' => {
-                        self.position += 2;
-                        self.skip_multi_line_comment();
-                        return self.next_token();
-                    }
-                    _ => {}
-                }
-            }
-        }
-
-        if "+-*/=<>!&|".contains(current_char) {
+        if "+-*/%=<>!&|".contains(current_char) {
             return Some(self.read_operator());
         }
 
         self.position += 1;
-        Some(Token::Symbol(current_char))
+        if "(){}[];,:".contains(current_char) {
+            Some(Token::Symbol(current_char))
+        } else {
+            Some(Token::Unknown(current_char))
+        }
     }
 
-    fn skip_whitespace(&mut self) {
-        while self.position < self.input.len() && self.input[self.position].is_whitespace() {
-            self.position += 1;
+    fn skip_whitespace_and_comments(&mut self) {
+        loop {
+            while self.position < self.input.len() && self.input[self.position].is_whitespace() {
+                self.position += 1;
+            }
+
+            match (self.input.get(self.position), self.input.get(self.position + 1)) {
+                (Some('/'), Some('/')) => {
+                    self.position += 2;
+                    self.skip_single_line_comment();
+                }
+                (Some('/'), Some('*')) => {
+                    self.position += 2;
+                    self.skip_multi_line_comment();
+                }
+                _ => break,
+            }
         }
     }
 
@@ -104,7 +140,9 @@ impl Lexer {
         let identifier: String = self.input[start..self.position].iter().collect();
         match identifier.as_str() {
             "int" | "char" | "float" | "double" | "void" => Token::DataType(identifier),
-            "if" | "else" | "while" | "for" | "break" | "continue" => Token::ControlStructure(identifier),
+            "if" | "else" | "while" | "do" | "for" | "switch" | "case" | "default" | "break" | "continue" | "return" => {
+                Token::ControlStructure(identifier)
+            }
             _ => Token::Identifier(identifier),
         }
     }
@@ -114,18 +152,38 @@ impl Lexer {
         while self.position < self.input.len() && self.input[self.position].is_numeric() {
             self.position += 1;
         }
+        // fractional part of a float literal
+        if self.position + 1 < self.input.len()
+            && self.input[self.position] == '.'
+            && self.input[self.position + 1].is_numeric()
+        {
+            self.position += 1;
+            while self.position < self.input.len() && self.input[self.position].is_numeric() {
+                self.position += 1;
+            }
+        }
         Token::Number(self.input[start..self.position].iter().collect())
     }
 
-    fn read_string_literal(&mut self) -> Token {
+    // Returns None if the file ends before the closing quote, which is reported as an error
+    fn read_string_literal(&mut self) -> Option<Token> {
+        let span = self.span_at(self.position);
         self.position += 1; // Skip the opening quote
         let start = self.position;
         while self.position < self.input.len() && self.input[self.position] != '"' {
             self.position += 1;
         }
         let string_literal: String = self.input[start..self.position].iter().collect();
+        if self.position == self.input.len() {
+            self.errors.push(CompilerError::InvalidToken(
+                format!("\"{}", string_literal),
+                span.line as u16,
+                span.column as u16,
+            ));
+            return None;
+        }
         self.position += 1; // Skip the closing quote
-        Token::StringLiteral(string_literal)
+        Some(Token::StringLiteral(string_literal))
     }
 
     fn read_char_literal(&mut self) -> Token {
@@ -155,6 +213,22 @@ impl Lexer {
                     self.position += 1;
                     return Token::NotEquals;
                 }
+                ('<', '=') => {
+                    self.position += 1;
+                    return Token::LessEquals;
+                }
+                ('>', '=') => {
+                    self.position += 1;
+                    return Token::GreaterEquals;
+                }
+                ('+', '+') => {
+                    self.position += 1;
+                    return Token::Increment;
+                }
+                ('-', '-') => {
+                    self.position += 1;
+                    return Token::Decrement;
+                }
                 ('&', '&') => {
                     self.position += 1;
                     return Token::And;
@@ -172,10 +246,53 @@ impl Lexer {
             '-' => Token::Minus,
             '*' => Token::Multiply,
             '/' => Token::Divide,
+            '%' => Token::Modulo,
             '=' => Token::Assign,
+            '!' => Token::Not,
             '<' => Token::LessThan,
             '>' => Token::GreaterThan,
             _ => Token::Unknown(current_char),
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spans() {
+        let tokens = Lexer::new("int a;\n/* comment\n */  a = 'x' + 2.5;").tokenize();
+        let spans: Vec<(usize, usize)> = tokens.iter().map(|(_, span)| (span.line, span.column)).collect();
+        assert_eq!(spans, [(1, 1), (1, 5), (1, 6), (3, 6), (3, 8), (3, 10), (3, 14), (3, 16), (3, 19)]);
+        assert_eq!(tokens[5].0, Token::CharLiteral('x'));
+        assert_eq!(tokens[7].0, Token::Number("2.5".to_string()));
+    }
+
+    #[test]
+    fn unknown_characters() {
+        let mut lexer = Lexer::new("int a = 1 @ 2;\n  b = #;");
+        let tokens = lexer.tokenize();
+        let errors: Vec<String> = lexer.errors().iter().map(|error| error.to_string()).collect();
+        assert_eq!(errors, [
+            "Invalid token \"@\" at line: 1, column: 11",
+            "Invalid token \"#\" at line: 2, column: 7",
+        ]);
+        // the other tokens are kept
+        assert_eq!(tokens.len(), 9);
+    }
+
+    #[test]
+    fn unterminated_literals() {
+        let mut lexer = Lexer::new("a = \"abc");
+        assert_eq!(lexer.tokenize().len(), 2);
+        let errors: Vec<String> = lexer.errors().iter().map(|error| error.to_string()).collect();
+        assert_eq!(errors, ["Invalid token \"\"abc\" at line: 1, column: 5"]);
+
+        for source in ["a = '", "a = 'b"] {
+            let mut lexer = Lexer::new(source);
+            lexer.tokenize();
+            let errors: Vec<String> = lexer.errors().iter().map(|error| error.to_string()).collect();
+            assert_eq!(errors, ["Invalid token \"'\" at line: 1, column: 5"]);
+        }
+    }
+}
@@ -10,6 +10,9 @@ mod semantic_analyzer;
 
 mod compiler_error;
 
+mod intermediate_code;
+use intermediate_code::IntermediateCodeGenerator;
+
 use std::env;
 use std::fs::File;
 use std::io::{self, Read};
@@ -28,19 +31,37 @@ fn main() -> io::Result<()> {
 
     let mut lexer = Lexer::new(&contents);
     let tokens = lexer.tokenize();
+    for e in lexer.errors() {
+        println!("Lexical error: {}", e);
+    }
 
     let mut parser = Parser::new(tokens);
-    let ast = parser.parse();
-    println!("AST: {:?}", ast);
-
-    if let Some(ast) = ast {
-        let mut semantic_analyzer = semantic_analyzer::SemanticAnalyzer::new();
-        match semantic_analyzer.analyze(&ast) {
-            Ok(_) => println!("Semantic analysis passed"),
-            Err(e) => println!("Semantic analysis error: {}", e),
+    match parser.parse() {
+        Ok(ast) => {
+            println!("AST: {:?}", ast);
+
+            // the semantic analysis would only repeat the errors of invalid tokens
+            if lexer.errors().is_empty() {
+                let mut semantic_analyzer = semantic_analyzer::SemanticAnalyzer::new();
+                let errors = semantic_analyzer.analyze(&ast);
+                if errors.is_empty() {
+                    println!("Semantic analysis passed");
+
+                    let quadruples = IntermediateCodeGenerator::new().generate(&ast);
+                    println!("Quadruples:\n{}", intermediate_code::table(&quadruples));
+                    println!("Three-address code:\n{}", intermediate_code::text(&quadruples));
+                }
+                for e in errors {
+                    println!("Semantic analysis error: {}", e);
+                }
+            }
+        }
+        Err(errors) => {
+            for e in &errors {
+                println!("{}", e);
+            }
+            println!("Failed to parse input: {} syntax errors", errors.len());
         }
-    } else {
-        println!("Failed to parse input");
     }
 
     Ok(())
@@ -1,130 +1,241 @@
-use crate::token::Token;
+use crate::compiler_error::CompilerError;
+use crate::token::{Span, Token};
 
 #[derive(Debug, Clone)]
 pub enum ASTNode {
     Function {
+        return_type: String,
         name: String,
+        parameters: Vec<(String, String)>,
         body: Vec<ASTNode>,
+        line: usize,
     },
     Return {
-        value: Box<ASTNode>,
+        value: Option<Box<ASTNode>>,
+        line: usize,
     },
     VariableDeclaration {
         data_type: String,
         name: String,
+        // number of elements if the variable is an array of `data_type`
+        array_size: Option<usize>,
         value: Option<Box<ASTNode>>,
+        line: usize,
+    },
+    Binary {
+        operator: Token,
+        left: Box<ASTNode>,
+        right: Box<ASTNode>,
     },
-    Expression(String),
+    Unary {
+        operator: Token,
+        operand: Box<ASTNode>,
+    },
+    Call {
+        name: String,
+        arguments: Vec<ASTNode>,
+        line: usize,
+    },
+    Index {
+        array: Box<ASTNode>,
+        index: Box<ASTNode>,
+    },
+    Identifier(String),
     Literal(String),
+    StringLiteral(String),
     CharLiteral(char),
+    // `{a, b, ...}` initializing an array
+    InitializerList(Vec<ASTNode>),
+    Error {
+        line: usize,
+    },
     If {
         condition: Box<ASTNode>,
         body: Vec<ASTNode>,
         else_body: Option<Vec<ASTNode>>,
+        line: usize,
     },
     While {
         condition: Box<ASTNode>,
         body: Vec<ASTNode>,
+        line: usize,
+    },
+    DoWhile {
+        body: Vec<ASTNode>,
+        condition: Box<ASTNode>,
+        line: usize,
+    },
+    For {
+        init: Vec<ASTNode>,
+        condition: Option<Box<ASTNode>>,
+        update: Option<Box<ASTNode>>,
+        body: Vec<ASTNode>,
+        line: usize,
+    },
+    Switch {
+        value: Box<ASTNode>,
+        // in source order, the value of `default` is `None`
+        cases: Vec<(Option<ASTNode>, Vec<ASTNode>)>,
+        line: usize,
+    },
+    Break {
+        line: usize,
+    },
+    Continue {
+        line: usize,
     },
     Assignment {
         name: String,
+        // set when assigning to an element of the array `name`
+        index: Option<Box<ASTNode>>,
         value: Box<ASTNode>,
+        line: usize,
     },
 }
 
 pub struct Parser {
     tokens: Vec<Token>,
+    // position of every token in the source file
+    spans: Vec<Span>,
     position: usize,
-    errors: Vec<String>,
+    errors: Vec<CompilerError>,
     ast: Vec<ASTNode>,
 }
 
 impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
-        Parser { tokens, position: 0, errors: Vec::new(), ast: Vec::new() }
+    pub fn new(tokens: Vec<(Token, Span)>) -> Self {
+        let (tokens, spans) = tokens.into_iter().unzip();
+        Parser { tokens, spans, position: 0, errors: Vec::new(), ast: Vec::new() }
     }
 
-    pub fn parse(&mut self) -> Option<Vec<ASTNode>> {
+    // Returns every syntax error of the file, parsing continues after each one at the next statement
+    pub fn parse(&mut self) -> Result<Vec<ASTNode>, Vec<CompilerError>> {
         while self.position < self.tokens.len() {
-            if let Some(node) = self.parse_node() {
-                self.ast.push(node);
+            if let Some(nodes) = self.parse_node() {
+                self.ast.extend(nodes);
             } else {
-                self.position += 1;
+                // recovery stops in front of a stray closing brace, which was already reported
+                self.match_symbol('}');
             }
         }
 
         if !self.errors.is_empty() {
-            for error in &self.errors {
-                println!("{}", error);
-            }
-            println!("AST: None");
-            println!("Failed to parse input");
-            None
+            Err(std::mem::take(&mut self.errors))
         } else {
-            println!("AST: {:?}", self.ast);
-            Some(self.ast.clone())
+            Ok(self.ast.clone())
         }
     }
 
-    fn parse_node(&mut self) -> Option<ASTNode> {
+    fn parse_node(&mut self) -> Option<Vec<ASTNode>> {
+        let errors = self.errors.len();
         if let Some(node) = self.parse_function() {
-            return Some(node);
-        } else if let Some(node) = self.parse_variable_declaration() {
-            return Some(node);
-        } else if let Some(node) = self.parse_statement() {
-            return Some(node);
+            return Some(vec![node]);
+        } else if self.errors.len() > errors {
+            return None;
         }
-        None
+        self.parse_declaration_or_statement()
+    }
+
+    // A declaration can declare multiple variables, so it returns a node for each of them
+    fn parse_declaration_or_statement(&mut self) -> Option<Vec<ASTNode>> {
+        let errors = self.errors.len();
+        if let Some(declarations) = self.parse_variable_declaration() {
+            return Some(declarations);
+        } else if self.errors.len() > errors {
+            self.synchronize();
+            return None;
+        }
+        self.parse_statement().map(|statement| vec![statement])
     }
 
     fn parse_function(&mut self) -> Option<ASTNode> {
         let start_pos = self.position;
+        let line = self.get_current_line();
 
-        if self.match_data_type() {
-            let identifier_name = if let Some(Token::Identifier(name)) = self.tokens.get(self.position) {
-                name.clone()
-            } else {
+        let return_type = if let Some(Token::DataType(data_type)) = self.tokens.get(self.position) {
+            data_type.clone()
+        } else {
+            return None;
+        };
+        self.position += 1;
+
+        let identifier_name = if let Some(Token::Identifier(name)) = self.tokens.get(self.position) {
+            name.clone()
+        } else {
+            self.position = start_pos;
+            return None;
+        };
+        self.position += 1;
+
+        if !self.match_symbol('(') {
+            self.position = start_pos;
+            return None;
+        }
+        let parameters = match self.parse_parameters() {
+            Some(parameters) => parameters,
+            None => {
                 self.position = start_pos;
                 return None;
-            };
+            }
+        };
 
-            self.position += 1;
+        if self.match_symbol('{') {
+            let body = self.parse_body();
 
-            if self.match_symbol('(') {
-                while self.position < self.tokens.len() && self.tokens.get(self.position) != Some(&Token::Symbol(')')) {
-                    self.position += 1;
-                }
+            if self.match_symbol('}') {
+                return Some(ASTNode::Function {
+                    return_type,
+                    name: identifier_name,
+                    parameters,
+                    body,
+                    line,
+                });
+            }
+            self.error("Unmatched opening brace in function body");
+            return None;
+        }
+        self.position = start_pos;
+        None
+    }
 
-                if self.match_symbol(')') {
-                    if self.match_symbol('{') {
-                        let body = self.parse_body();
+    // Parses `(void)`, `()` or `(type name, type name[], ...)` after the opening parenthesis
+    fn parse_parameters(&mut self) -> Option<Vec<(String, String)>> {
+        let mut parameters = Vec::new();
+        if self.match_symbol(')') {
+            return Some(parameters);
+        }
+        if self.tokens.get(self.position) == Some(&Token::DataType("void".to_string()))
+            && self.tokens.get(self.position + 1) == Some(&Token::Symbol(')'))
+        {
+            self.position += 2;
+            return Some(parameters);
+        }
 
-                        if self.match_symbol('}') {
-                            return Some(ASTNode::Function {
-                                name: identifier_name,
-                                body,
-                            });
-                        } else {
-                            self.position = start_pos;
+        loop {
+            match (self.tokens.get(self.position), self.tokens.get(self.position + 1)) {
+                (Some(Token::DataType(data_type)), Some(Token::Identifier(name))) => {
+                    let (mut data_type, name) = (data_type.clone(), name.clone());
+                    self.position += 2;
+                    if self.match_symbol('[') {
+                        if !self.match_symbol(']') {
                             return None;
                         }
-                    } else {
-                        self.position = start_pos;
-                        return None;
+                        data_type.push_str("[]");
                     }
-                } else {
-                    self.position = start_pos;
-                    return None;
+                    parameters.push((data_type, name));
                 }
-            } else {
-                self.position = start_pos;
-                None
+                _ => return None,
+            }
+            if self.match_symbol(')') {
+                return Some(parameters);
+            }
+            if !self.match_symbol(',') {
+                return None;
             }
-        } else {
-            None
         }
     }
 
+    // Parses statements up to the closing brace, which is matched by the caller
     fn parse_body(&mut self) -> Vec<ASTNode> {
         let mut body = Vec::new();
         while self.position < self.tokens.len() {
@@ -132,71 +243,98 @@ impl Parser {
                 break;
             }
 
-            if let Some(statement) = self.parse_statement() {
-                body.push(statement);
-            } else {
-                self.position += 1;
+            if let Some(nodes) = self.parse_declaration_or_statement() {
+                body.extend(nodes);
             }
         }
-        if self.position >= self.tokens.len() || self.tokens.get(self.position) != Some(&Token::Symbol('}')) {
-            let line = self.get_current_line();
-            self.errors.push(format!("Error: Unmatched opening brace, missing '}}' at line {}", line));
-        }
         body
     }
 
+    // Parses the braces and body of a `kind` statement
+    fn parse_block(&mut self, kind: &str) -> Option<Vec<ASTNode>> {
+        if !self.match_symbol('{') {
+            self.error(&format!("Expected '{{' before {} block", kind));
+            return None;
+        }
+        let body = self.parse_body();
+        if !self.match_symbol('}') {
+            self.error(&format!("Unmatched opening brace in {} block", kind));
+            return None;
+        }
+        Some(body)
+    }
+
     fn parse_statement(&mut self) -> Option<ASTNode> {
-        if let Some(return_statement) = self.parse_return() {
-            return Some(return_statement);
-        } else if let Some(if_statement) = self.parse_if() {
-            return Some(if_statement);
-        } else if let Some(while_statement) = self.parse_while() {
-            return Some(while_statement);
-        } else if let Some(assignment) = self.parse_assignment() {
-            return Some(assignment);
-        } else if let Some(expression) = self.parse_expression() {
-            return Some(expression);
-        } else {
-            let line = self.get_current_line();
-            self.errors.push(format!("Error: Unexpected token at line {}", line));
+        let errors = self.errors.len();
+        let parsers: [fn(&mut Self) -> Option<ASTNode>; 10] = [
+            Self::parse_return,
+            Self::parse_if,
+            Self::parse_while,
+            Self::parse_do_while,
+            Self::parse_for,
+            Self::parse_switch,
+            Self::parse_break,
+            Self::parse_continue,
+            Self::parse_assignment,
+            Self::parse_expression_statement,
+        ];
+
+        for parse in parsers {
+            if let Some(statement) = parse(self) {
+                return Some(statement);
+            } else if self.errors.len() > errors {
+                self.synchronize();
+                return None;
+            }
+        }
+        self.error("Unexpected token");
+        self.synchronize();
+        None
+    }
+
+    // Skips past the next ';' or up to the next '}', so parsing can continue after a syntax error
+    fn synchronize(&mut self) {
+        while let Some(token) = self.tokens.get(self.position) {
+            if *token == Token::Symbol('}') {
+                return;
+            }
             self.position += 1;
-            None
+            if *token == Token::Symbol(';') {
+                return;
+            }
         }
     }
 
     fn parse_return(&mut self) -> Option<ASTNode> {
-        if let Some(Token::ControlStructure(keyword)) = self.tokens.get(self.position) {
-            if keyword == "return" {
-                self.position += 1;
-                if let Some(value) = self.parse_literal_or_identifier() {
-                    if self.match_symbol(';') {
-                        return Some(ASTNode::Return {
-                            value: Box::new(value),
-                        });
-                    } else {
-                        let line = self.get_current_line();
-                        self.errors.push(format!("Error: Expected ';' after return value at line {}", line));
-                    }
-                }
+        let line = self.get_current_line();
+        if self.match_control_structure("return") {
+            let value = self.parse_expression().map(Box::new);
+            if self.match_symbol(';') {
+                return Some(ASTNode::Return { value, line });
+            } else {
+                self.error("Expected ';' after return value");
             }
         }
         None
     }
 
     fn parse_if(&mut self) -> Option<ASTNode> {
+        let line = self.get_current_line();
         if self.match_control_structure("if") && self.match_symbol('(') {
             if let Some(condition) = self.parse_expression() {
-                if self.match_symbol(')') && self.match_symbol('{') {
+                if !self.match_symbol(')') {
+                    self.error("Expected ')' after if condition");
+                    return None;
+                }
+                if self.match_symbol('{') {
                     let body = self.parse_body();
                     if self.match_symbol('}') {
-                        let else_body = if self.match_control_structure("else") && self.match_symbol('{') {
-                            let else_body = self.parse_body();
-                            if self.match_symbol('}') {
-                                Some(else_body)
+                        let else_body = if self.match_control_structure("else") {
+                            // `else if` is an else-block containing only the nested if
+                            if self.tokens.get(self.position) == Some(&Token::ControlStructure("if".to_string())) {
+                                Some(vec![self.parse_if()?])
                             } else {
-                                let line = self.get_current_line();
-                                self.errors.push(format!("Error: Unmatched opening brace in else block at line {}", line));
-                                None
+                                Some(self.parse_block("else")?)
                             }
                         } else {
                             None
@@ -205,20 +343,18 @@ impl Parser {
                             condition: Box::new(condition),
                             body,
                             else_body,
+                            line,
                         });
                     } else {
-                        let line = self.get_current_line();
-                        self.errors.push(format!("Error: Unmatched opening brace in if block at line {}", line));
+                        self.error("Unmatched opening brace in if block");
                         return None;
                     }
                 } else {
-                    let line = self.get_current_line();
-                    self.errors.push(format!("Error: Expected '{{' after if condition at line {}", line));
+                    self.error("Expected '{' after if condition");
                     return None;
                 }
             } else {
-                let line = self.get_current_line();
-                self.errors.push(format!("Error: Expected ')' after if condition at line {}", line));
+                self.error("Expected if condition");
                 return None;
             }
         }
@@ -226,143 +362,497 @@ impl Parser {
     }
 
     fn parse_while(&mut self) -> Option<ASTNode> {
+        let line = self.get_current_line();
         if self.match_control_structure("while") && self.match_symbol('(') {
             if let Some(condition) = self.parse_expression() {
-                if self.match_symbol(')') && self.match_symbol('{') {
+                if !self.match_symbol(')') {
+                    self.error("Expected ')' after while condition");
+                    return None;
+                }
+                if self.match_symbol('{') {
                     let body = self.parse_body();
                     if self.match_symbol('}') {
                         return Some(ASTNode::While {
                             condition: Box::new(condition),
                             body,
+                            line,
                         });
                     } else {
-                        let line = self.get_current_line();
-                        self.errors.push(format!("Error: Unmatched opening brace in while block at line {}", line));
+                        self.error("Unmatched opening brace in while block");
                         return None;
                     }
                 } else {
-                    let line = self.get_current_line();
-                    self.errors.push(format!("Error: Expected '{{' after while condition at line {}", line));
+                    self.error("Expected '{' after while condition");
                     return None;
                 }
             } else {
-                let line = self.get_current_line();
-                self.errors.push(format!("Error: Expected ')' after while condition at line {}", line));
+                self.error("Expected while condition");
                 return None;
             }
         }
         None
     }
 
-    fn parse_variable_declaration(&mut self) -> Option<ASTNode> {
+    fn parse_do_while(&mut self) -> Option<ASTNode> {
+        let line = self.get_current_line();
+        if !self.match_control_structure("do") {
+            return None;
+        }
+        let body = self.parse_block("do")?;
+
+        if !self.match_control_structure("while") || !self.match_symbol('(') {
+            self.error("Expected 'while (' after do block");
+            return None;
+        }
+        let condition = self.expect_expression();
+        if !self.match_symbol(')') || !self.match_symbol(';') {
+            self.error("Expected ');' after do-while condition");
+            return None;
+        }
+        Some(ASTNode::DoWhile {
+            body,
+            condition: Box::new(condition),
+            line,
+        })
+    }
+
+    fn parse_for(&mut self) -> Option<ASTNode> {
+        let line = self.get_current_line();
+        if !self.match_control_structure("for") {
+            return None;
+        }
+        if !self.match_symbol('(') {
+            self.error("Expected '(' after for");
+            return None;
+        }
+
+        let errors = self.errors.len();
+        let init = match self.parse_declarators() {
+            Some(declarations) => declarations,
+            None if self.errors.len() > errors => return None,
+            None => self.parse_assignment_expression().into_iter().collect(),
+        };
+        if !self.match_symbol(';') {
+            self.error("Expected ';' after for initialization");
+            return None;
+        }
+        let condition = self.parse_expression().map(Box::new);
+        if !self.match_symbol(';') {
+            self.error("Expected ';' after for condition");
+            return None;
+        }
+        let update = match self.parse_assignment_expression() {
+            Some(assignment) => Some(Box::new(assignment)),
+            None => self.parse_expression().map(Box::new),
+        };
+        if !self.match_symbol(')') {
+            self.error("Expected ')' after for update");
+            return None;
+        }
+
+        let body = self.parse_block("for")?;
+        Some(ASTNode::For { init, condition, update, body, line })
+    }
+
+    fn parse_switch(&mut self) -> Option<ASTNode> {
+        let line = self.get_current_line();
+        if !self.match_control_structure("switch") {
+            return None;
+        }
+        if !self.match_symbol('(') {
+            self.error("Expected '(' after switch");
+            return None;
+        }
+        let value = self.expect_expression();
+        if !self.match_symbol(')') || !self.match_symbol('{') {
+            self.error("Expected ') {' after switch value");
+            return None;
+        }
+
+        let mut cases = Vec::new();
+        let mut closed = false;
+        while self.position < self.tokens.len() {
+            if self.match_symbol('}') {
+                closed = true;
+                break;
+            }
+            let case_value = if self.match_control_structure("case") {
+                Some(self.expect_expression())
+            } else if self.match_control_structure("default") {
+                None
+            } else {
+                self.error("Expected 'case' or 'default' in switch block");
+                return None;
+            };
+            if !self.match_symbol(':') {
+                self.error("Expected ':' after case");
+                return None;
+            }
+
+            // the statements of a case go up to the next case
+            let mut body = Vec::new();
+            while !matches!(self.tokens.get(self.position), None | Some(Token::Symbol('}'))) && !self.at_case_label() {
+                if let Some(nodes) = self.parse_declaration_or_statement() {
+                    body.extend(nodes);
+                }
+            }
+            cases.push((case_value, body));
+        }
+        if !closed {
+            self.error("Unmatched opening brace in switch block");
+            return None;
+        }
+
+        Some(ASTNode::Switch {
+            value: Box::new(value),
+            cases,
+            line,
+        })
+    }
+
+    fn at_case_label(&self) -> bool {
+        matches!(
+            self.tokens.get(self.position),
+            Some(Token::ControlStructure(keyword)) if keyword == "case" || keyword == "default"
+        )
+    }
+
+    fn parse_break(&mut self) -> Option<ASTNode> {
+        let line = self.get_current_line();
+        if !self.match_control_structure("break") {
+            return None;
+        }
+        if !self.match_symbol(';') {
+            self.error("Expected ';' after break");
+            return None;
+        }
+        Some(ASTNode::Break { line })
+    }
+
+    fn parse_continue(&mut self) -> Option<ASTNode> {
+        let line = self.get_current_line();
+        if !self.match_control_structure("continue") {
+            return None;
+        }
+        if !self.match_symbol(';') {
+            self.error("Expected ';' after continue");
+            return None;
+        }
+        Some(ASTNode::Continue { line })
+    }
+
+    fn parse_variable_declaration(&mut self) -> Option<Vec<ASTNode>> {
         let start_pos = self.position;
-        if let Some(Token::DataType(data_type)) = self.tokens.get(self.position) {
-            let data_type = data_type.clone();
+        let declarations = self.parse_declarators()?;
+        if self.match_symbol(';') {
+            Some(declarations)
+        } else {
+            self.error("Expected ';' after variable declaration");
+            self.position = start_pos;
+            None
+        }
+    }
+
+    // Parses `type name = value, name[size] = {...}, ...` up to the `;`, which a for-loop matches itself
+    fn parse_declarators(&mut self) -> Option<Vec<ASTNode>> {
+        let start_pos = self.position;
+        let data_type = if let Some(Token::DataType(data_type)) = self.tokens.get(self.position) {
+            data_type.clone()
+        } else {
+            return None;
+        };
+        self.position += 1;
+
+        let mut declarations = Vec::new();
+        loop {
+            let line = self.get_current_line();
+            let name = match self.tokens.get(self.position) {
+                Some(Token::Identifier(name)) => name.clone(),
+                // without a name it isn't a declaration, unless it declares multiple variables
+                _ if declarations.is_empty() => {
+                    self.position = start_pos;
+                    return None;
+                }
+                _ => {
+                    self.error("Expected identifier after ',' in variable declaration");
+                    return None;
+                }
+            };
             self.position += 1;
-            if let Some(Token::Identifier(name)) = self.tokens.get(self.position) {
-                let name = name.clone();
-                self.position += 1;
-                let value = if self.match_symbol('=') {
-                    self.parse_literal_or_identifier().map(Box::new)
-                } else {
-                    None
+
+            let array_size = if self.match_symbol('[') {
+                let size = match self.tokens.get(self.position) {
+                    Some(Token::Number(size)) => {
+                        self.position += 1;
+                        size.parse::<usize>().ok()
+                    }
+                    _ => None,
                 };
-                if self.match_symbol(';') {
-                    return Some(ASTNode::VariableDeclaration {
-                        data_type,
-                        name,
-                        value,
-                    });
+                if !self.match_symbol(']') {
+                    self.error("Expected ']' after array size");
+                    return None;
+                }
+                Some(size)
+            } else {
+                None
+            };
+
+            let value = if self.match_token(&Token::Assign) {
+                if self.match_symbol('{') {
+                    Some(ASTNode::InitializerList(self.parse_expression_list('}', "Expected '}' after initializer list")))
                 } else {
-                    let line = self.get_current_line();
-                    self.errors.push(format!("Error: Expected ';' after variable declaration at line {}", line));
-                    self.position = start_pos;
+                    Some(self.expect_expression())
+                }
+            } else {
+                None
+            };
+
+            // the size of `a[] = {...}` is the length of the initializer list
+            let array_size = match (array_size, &value) {
+                (None, _) => None,
+                (Some(Some(size)), _) => Some(size),
+                (Some(None), Some(ASTNode::InitializerList(elements))) => Some(elements.len()),
+                (Some(None), _) => {
+                    self.error("Expected array size");
                     return None;
                 }
+            };
+
+            declarations.push(ASTNode::VariableDeclaration {
+                data_type: data_type.clone(),
+                name,
+                array_size,
+                value: value.map(Box::new),
+                line,
+            });
+            if !self.match_symbol(',') {
+                return Some(declarations);
             }
         }
-        None
     }
 
     fn parse_assignment(&mut self) -> Option<ASTNode> {
-        if let Some(Token::Identifier(name)) = self.tokens.get(self.position) {
-            let name = name.clone();
+        let assignment = self.parse_assignment_expression()?;
+        if self.match_symbol(';') {
+            Some(assignment)
+        } else {
+            self.error("Expected ';' after assignment");
+            None
+        }
+    }
+
+    // Parses `name = value`, `name[index] = value`, `name++` or `name--` without the `;`
+    fn parse_assignment_expression(&mut self) -> Option<ASTNode> {
+        let start_pos = self.position;
+        let line = self.get_current_line();
+        let name = if let Some(Token::Identifier(name)) = self.tokens.get(self.position) {
+            name.clone()
+        } else {
+            return None;
+        };
+        self.position += 1;
+
+        let index = if self.match_symbol('[') {
+            let index = self.expect_expression();
+            if !self.match_symbol(']') {
+                self.error("Expected ']' after index");
+            }
+            Some(Box::new(index))
+        } else {
+            None
+        };
+
+        let value = if self.match_token(&Token::Assign) {
+            self.expect_expression()
+        } else if let Some(operator) = self
+            .tokens
+            .get(self.position)
+            .and_then(|token| match token {
+                Token::Increment => Some(Token::Plus),
+                Token::Decrement => Some(Token::Minus),
+                _ => None,
+            })
+        {
             self.position += 1;
-            if self.match_symbol('=') {
-                if let Some(value) = self.parse_literal_or_identifier() {
-                    if self.match_symbol(';') {
-                        return Some(ASTNode::Assignment {
-                            name,
-                            value: Box::new(value),
-                        });
-                    } else {
-                        let line = self.get_current_line();
-                        self.errors.push(format!("Error: Expected ';' after assignment at line {}", line));
-                        return None;
-                    }
-                }
+            let target = match &index {
+                Some(index) => ASTNode::Index {
+                    array: Box::new(ASTNode::Identifier(name.clone())),
+                    index: index.clone(),
+                },
+                None => ASTNode::Identifier(name.clone()),
+            };
+            ASTNode::Binary {
+                operator,
+                left: Box::new(target),
+                right: Box::new(ASTNode::Literal("1".to_string())),
             }
+        } else {
+            self.position = start_pos;
+            return None;
+        };
+
+        Some(ASTNode::Assignment {
+            name,
+            index,
+            value: Box::new(value),
+            line,
+        })
+    }
+
+    fn parse_expression_statement(&mut self) -> Option<ASTNode> {
+        let expression = self.parse_expression()?;
+        if self.match_symbol(';') {
+            Some(expression)
+        } else {
+            self.error("Expected ';' after expression");
+            None
         }
-        None
     }
 
+    // Parses an expression by precedence climbing, returns None if the current token can't start one
     fn parse_expression(&mut self) -> Option<ASTNode> {
-        if let Some(identifier) = self.parse_identifier() {
-            if self.match_symbol('(') {
-                while !self.match_symbol(')') && self.position < self.tokens.len() {
-                    self.position += 1;
-                }
-                if self.match_symbol(')') && self.match_symbol(';') {
-                    return Some(ASTNode::Expression(identifier));
-                } else {
-                    let line = self.get_current_line();
-                    self.errors.push(format!("Error: Expected ';' after function call at line {}", line));
-                    return None;
-                }
-            } else if self.match_symbol(';') {
-                return Some(ASTNode::Expression(identifier));
-            } else {
-                let line = self.get_current_line();
-                self.errors.push(format!("Error: Expected ';' after expression at line {}", line));
-                return None;
+        self.parse_binary(1)
+    }
+
+    // Same as parse_expression but reports a missing expression as an error node
+    fn expect_expression(&mut self) -> ASTNode {
+        match self.parse_expression() {
+            Some(expression) => expression,
+            None => self.error_node("Expected expression"),
+        }
+    }
+
+    fn parse_binary(&mut self, min_precedence: u8) -> Option<ASTNode> {
+        let mut left = self.parse_unary()?;
+
+        while let Some(operator) = self.tokens.get(self.position).cloned() {
+            let precedence = match Self::binary_precedence(&operator) {
+                Some(precedence) if precedence >= min_precedence => precedence,
+                _ => break,
+            };
+            self.position += 1;
+
+            // all binary operators are left-associative so the right side only takes tighter ones
+            let right = match self.parse_binary(precedence + 1) {
+                Some(right) => right,
+                None => self.error_node("Expected expression after operator"),
+            };
+            left = ASTNode::Binary {
+                operator,
+                left: Box::new(left),
+                right: Box::new(right),
+            };
+        }
+        Some(left)
+    }
+
+    fn binary_precedence(token: &Token) -> Option<u8> {
+        match token {
+            Token::Or => Some(1),
+            Token::And => Some(2),
+            Token::Equals | Token::NotEquals => Some(3),
+            Token::LessThan | Token::GreaterThan | Token::LessEquals | Token::GreaterEquals => Some(4),
+            Token::Plus | Token::Minus => Some(5),
+            Token::Multiply | Token::Divide | Token::Modulo => Some(6),
+            _ => None,
+        }
+    }
+
+    fn parse_unary(&mut self) -> Option<ASTNode> {
+        match self.tokens.get(self.position) {
+            Some(Token::Minus) | Some(Token::Not) => {
+                let operator = self.tokens[self.position].clone();
+                self.position += 1;
+                let operand = match self.parse_unary() {
+                    Some(operand) => operand,
+                    None => self.error_node("Expected operand after unary operator"),
+                };
+                Some(ASTNode::Unary {
+                    operator,
+                    operand: Box::new(operand),
+                })
             }
+            _ => self.parse_postfix(),
         }
-        None
     }
 
-    fn parse_literal_or_identifier(&mut self) -> Option<ASTNode> {
-        if let Some(token) = self.tokens.get(self.position) {
-            match token {
-                Token::StringLiteral(value) => {
-                    self.position += 1;
-                    Some(ASTNode::Literal(value.clone()))
-                }
-                Token::CharLiteral(value) => {
-                    self.position += 1;
-                    Some(ASTNode::CharLiteral(*value))
-                }
-                Token::Number(value) => {
-                    self.position += 1;
-                    Some(ASTNode::Literal(value.clone()))
+    fn parse_postfix(&mut self) -> Option<ASTNode> {
+        let line = self.get_current_line();
+        let mut expression = self.parse_primary()?;
+
+        loop {
+            if let ASTNode::Identifier(name) = &expression {
+                if self.match_symbol('(') {
+                    let name = name.clone();
+                    let arguments = self.parse_expression_list(')', "Expected ')' after function arguments");
+                    expression = ASTNode::Call { name, arguments, line };
+                    continue;
                 }
-                Token::Identifier(name) => {
-                    self.position += 1;
-                    Some(ASTNode::Expression(name.clone()))
+            }
+            if self.match_symbol('[') {
+                let index = self.expect_expression();
+                if !self.match_symbol(']') {
+                    self.error("Expected ']' after index");
                 }
-                _ => None,
+                expression = ASTNode::Index {
+                    array: Box::new(expression),
+                    index: Box::new(index),
+                };
+                continue;
             }
-        } else {
-            None
+            break;
         }
+        Some(expression)
     }
 
-    fn match_data_type(&mut self) -> bool {
-        if let Some(Token::DataType(_)) = self.tokens.get(self.position) {
-            self.position += 1;
-            true
-        } else {
-            false
+    // Parses comma-separated expressions after the opening bracket up to `closing`
+    fn parse_expression_list(&mut self, closing: char, error: &str) -> Vec<ASTNode> {
+        let mut expressions = Vec::new();
+        if self.match_symbol(closing) {
+            return expressions;
         }
+        loop {
+            expressions.push(self.expect_expression());
+            if self.match_symbol(',') {
+                continue;
+            }
+            if !self.match_symbol(closing) {
+                self.error(error);
+            }
+            break;
+        }
+        expressions
+    }
+
+    fn parse_primary(&mut self) -> Option<ASTNode> {
+        let node = match self.tokens.get(self.position)? {
+            Token::Number(value) => ASTNode::Literal(value.clone()),
+            Token::StringLiteral(value) => ASTNode::StringLiteral(value.clone()),
+            Token::CharLiteral(value) => ASTNode::CharLiteral(*value),
+            Token::Identifier(name) => ASTNode::Identifier(name.clone()),
+            Token::Symbol('(') => {
+                self.position += 1;
+                let expression = self.expect_expression();
+                if !self.match_symbol(')') {
+                    self.error("Expected ')' after expression");
+                }
+                return Some(expression);
+            }
+            _ => return None,
+        };
+        self.position += 1;
+        Some(node)
+    }
+
+    // Records the error and returns a node standing in for the missing expression
+    fn error_node(&mut self, message: &str) -> ASTNode {
+        self.error(message);
+        ASTNode::Error { line: self.get_current_line() }
+    }
+
+    fn error(&mut self, message: &str) {
+        let span = self.current_span();
+        self.errors.push(CompilerError::SyntaxError(message.to_string(), span.line as u16, span.column as u16));
     }
 
     fn match_control_structure(&mut self, keyword: &str) -> bool {
@@ -378,14 +868,10 @@ impl Parser {
         }
     }
 
-    fn match_identifier(&mut self, identifier: &str) -> bool {
-        if let Some(Token::Identifier(id)) = self.tokens.get(self.position) {
-            if id == identifier {
-                self.position += 1;
-                true
-            } else {
-                false
-            }
+    fn match_token(&mut self, token: &Token) -> bool {
+        if self.tokens.get(self.position) == Some(token) {
+            self.position += 1;
+            true
         } else {
             false
         }
@@ -404,26 +890,149 @@ impl Parser {
         }
     }
 
-    fn parse_identifier(&mut self) -> Option<String> {
-        if let Some(Token::Identifier(id)) = self.tokens.get(self.position) {
-            self.position += 1;
-            Some(id.clone())
-        } else {
-            None
-        }
+    fn get_current_line(&self) -> usize {
+        self.current_span().line
     }
 
-    fn get_current_line(&self) -> usize {
-        let mut line = 1;
-        for i in 0..self.position {
-            if let Some(token) = self.tokens.get(i) {
-                if let Token::Symbol(sym) = token {
-                    if *sym == '\n' {
-                        line += 1;
-                    }
-                }
+    fn current_span(&self) -> Span {
+        // past the end of the input errors are reported at the last token
+        self.spans
+            .get(self.position)
+            .or(self.spans.last())
+            .copied()
+            .unwrap_or(Span { line: 1, column: 1 })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    fn parse(source: &str) -> Result<Vec<ASTNode>, Vec<String>> {
+        Parser::new(Lexer::new(source).tokenize())
+            .parse()
+            .map_err(|errors| errors.iter().map(|error| error.to_string()).collect())
+    }
+
+    // Renders an expression with explicit parentheses, like `(Plus a (Multiply b c))`
+    fn render(node: &ASTNode) -> String {
+        match node {
+            ASTNode::Binary { operator, left, right } => {
+                format!("({:?} {} {})", operator, render(left), render(right))
             }
+            ASTNode::Unary { operator, operand } => format!("({:?} {})", operator, render(operand)),
+            ASTNode::Call { name, arguments, .. } => {
+                let arguments: Vec<String> = arguments.iter().map(render).collect();
+                format!("{}({})", name, arguments.join(", "))
+            }
+            ASTNode::Index { array, index } => format!("{}[{}]", render(array), render(index)),
+            ASTNode::Identifier(name) | ASTNode::Literal(name) => name.clone(),
+            ASTNode::StringLiteral(string) => format!("{:?}", string),
+            ASTNode::CharLiteral(chr) => format!("{:?}", chr),
+            node => format!("{:?}", node),
         }
-        line
     }
-}
\ No newline at end of file
+
+    fn expression(source: &str) -> String {
+        let ast = parse(&format!("{};", source)).unwrap();
+        assert_eq!(ast.len(), 1);
+        render(&ast[0])
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(expression("a + b * c"), "(Plus a (Multiply b c))");
+        assert_eq!(expression("a * b + c % d"), "(Plus (Multiply a b) (Modulo c d))");
+        assert_eq!(expression("a < b + 1 == c"), "(Equals (LessThan a (Plus b 1)) c)");
+        assert_eq!(expression("a || b && c != d"), "(Or a (And b (NotEquals c d)))");
+    }
+
+    #[test]
+    fn left_associative() {
+        assert_eq!(expression("a - b - c"), "(Minus (Minus a b) c)");
+        assert_eq!(expression("a / b * c"), "(Multiply (Divide a b) c)");
+        assert_eq!(expression("a && b && c"), "(And (And a b) c)");
+    }
+
+    #[test]
+    fn unary_and_parentheses() {
+        assert_eq!(expression("-a * b"), "(Multiply (Minus a) b)");
+        assert_eq!(expression("!a && b"), "(And (Not a) b)");
+        assert_eq!(expression("- -a"), "(Minus (Minus a))");
+        assert_eq!(expression("(a + b) * c"), "(Multiply (Plus a b) c)");
+        assert_eq!(expression("!(a < b)"), "(Not (LessThan a b))");
+        assert_eq!(expression("((a))"), "a");
+    }
+
+    #[test]
+    fn calls_and_indexing() {
+        assert_eq!(expression("f()"), "f()");
+        assert_eq!(expression("f(a, b + 1) * 2"), "(Multiply f(a, (Plus b 1)) 2)");
+        assert_eq!(expression("v[i + 1] - g(v[0])"), "(Minus v[(Plus i 1)] g(v[0]))");
+        assert_eq!(expression("'x' == \"s\""), "(Equals 'x' \"s\")");
+    }
+
+    #[test]
+    fn assignments() {
+        let ast = parse("x = a + b * 2; v[1] = -x; i++;").unwrap();
+        let rendered: Vec<String> = ast
+            .iter()
+            .map(|node| match node {
+                ASTNode::Assignment { name, index, value, .. } => match index {
+                    Some(index) => format!("{}[{}] = {}", name, render(index), render(value)),
+                    None => format!("{} = {}", name, render(value)),
+                },
+                node => panic!("expected an assignment, found {:?}", node),
+            })
+            .collect();
+        assert_eq!(rendered, ["x = (Plus a (Multiply b 2))", "v[1] = (Minus x)", "i = (Plus i 1)"]);
+    }
+
+    #[test]
+    fn multiple_errors() {
+        let errors = parse("int a = ;\nint b = 2 +;\nc = (1;").unwrap_err();
+        assert_eq!(errors, [
+            "Error: Expected expression at line 1, column 9",
+            "Error: Expected expression after operator at line 2, column 12",
+            "Error: Expected ')' after expression at line 3, column 7",
+        ]);
+    }
+
+    #[test]
+    fn error_nodes() {
+        let mut parser = Parser::new(Lexer::new("int a = ;\nint b = 1 + ;").tokenize());
+        assert_eq!(parser.parse().unwrap_err().len(), 2);
+
+        // missing expressions are replaced so that the rest of the statement is still parsed
+        let values: Vec<String> = parser
+            .ast
+            .iter()
+            .map(|node| match node {
+                ASTNode::VariableDeclaration { name, value: Some(value), .. } => format!("{} = {}", name, render(value)),
+                node => panic!("expected a declaration, found {:?}", node),
+            })
+            .collect();
+        assert_eq!(values, ["a = Error { line: 1 }", "b = (Plus 1 Error { line: 2 })"]);
+    }
+
+    #[test]
+    fn recovery() {
+        // parsing continues at the next statement, also inside of blocks
+        let source = "int main() {\n  if (a { x = 1; }\n  y = 2;\n  z = * 3;\n}";
+        assert_eq!(parse(source).unwrap_err(), [
+            "Error: Expected ')' after if condition at line 2, column 9",
+            "Error: Expected expression at line 4, column 7",
+            "Error: Expected ';' after assignment at line 4, column 7",
+        ]);
+        assert_eq!(parse("int main() {\n  int a = 1\n  return a;\n}").unwrap_err(), [
+            "Error: Expected ';' after variable declaration at line 3, column 3",
+        ]);
+        assert_eq!(parse("while (a { }\nwhile (b) c = 1;").unwrap_err(), [
+            "Error: Expected ')' after while condition at line 1, column 10",
+            "Error: Expected '{' after while condition at line 2, column 11",
+        ]);
+        // a stray closing brace is reported once
+        assert_eq!(parse("}\nint a = 1;").unwrap_err(), ["Error: Unexpected token at line 1, column 1"]);
+    }
+}
@@ -1,62 +1,516 @@
+use crate::compiler_error::CompilerError;
 use crate::parser::ASTNode;
+use crate::token::Token;
+use std::collections::{HashMap, HashSet};
 
-pub struct SemanticAnalyzer;
+enum SymbolKind {
+    Variable,
+    // `None` for builtins that take any number of arguments
+    Function(Option<Vec<String>>),
+}
+
+struct Symbol {
+    data_type: String,
+    kind: SymbolKind,
+}
+
+pub struct SemanticAnalyzer {
+    // innermost scope is the last one
+    scopes: Vec<HashMap<String, Symbol>>,
+    errors: Vec<CompilerError>,
+    // return type of the function being analyzed
+    return_type: Option<String>,
+    // line of the statement being analyzed, since expressions don't store their own
+    line: u16,
+    // number of enclosing statements that `continue` and `break` can jump out of
+    loops: usize,
+    switches: usize,
+}
 
 impl SemanticAnalyzer {
     pub fn new() -> Self {
-        SemanticAnalyzer
+        let mut globals = HashMap::new();
+        for builtin in ["printf", "scanf"] {
+            globals.insert(
+                builtin.to_string(),
+                Symbol { data_type: "int".to_string(), kind: SymbolKind::Function(None) },
+            );
+        }
+
+        SemanticAnalyzer {
+            scopes: vec![globals],
+            errors: Vec::new(),
+            return_type: None,
+            line: 1,
+            loops: 0,
+            switches: 0,
+        }
+    }
+
+    pub fn analyze(&mut self, nodes: &Vec<ASTNode>) -> Vec<CompilerError> {
+        for node in nodes {
+            self.analyze_node(node);
+        }
+        std::mem::take(&mut self.errors)
     }
 
-    pub fn analyze(&mut self, nodes: &Vec<ASTNode>) -> Result<(), String> {
+    fn analyze_block(&mut self, nodes: &Vec<ASTNode>) {
+        self.scopes.push(HashMap::new());
         for node in nodes {
-            self.analyze_node(node)?;
+            self.analyze_node(node);
         }
-        Ok(())
+        self.scopes.pop();
+    }
+
+    fn analyze_loop_body(&mut self, body: &Vec<ASTNode>) {
+        self.loops += 1;
+        self.analyze_block(body);
+        self.loops -= 1;
     }
 
-    fn analyze_node(&mut self, node: &ASTNode) -> Result<(), String> {
+    fn analyze_node(&mut self, node: &ASTNode) {
         match node {
-            ASTNode::Function { name, body } => {
-                println!("Analyzing function: {}", name);
-                self.analyze(body)?;
+            ASTNode::Function { return_type, name, parameters, body, line } => {
+                self.line = *line as u16;
+                let parameter_types = parameters.iter().map(|(data_type, _)| data_type.clone()).collect();
+                self.declare(
+                    name,
+                    Symbol { data_type: return_type.clone(), kind: SymbolKind::Function(Some(parameter_types)) },
+                );
+
+                // parameters share the scope of the function body
+                self.scopes.push(HashMap::new());
+                for (data_type, parameter) in parameters {
+                    self.declare(parameter, Symbol { data_type: data_type.clone(), kind: SymbolKind::Variable });
+                }
+                self.return_type = Some(return_type.clone());
+                for node in body {
+                    self.analyze_node(node);
+                }
+                self.return_type = None;
+                self.scopes.pop();
+
+                if return_type != "void" && !Self::returns(body) {
+                    self.errors.push(CompilerError::MissingReturn(name.clone(), *line as u16));
+                }
+            }
+            ASTNode::Return { value, line } => {
+                self.line = *line as u16;
+                let value_type = match value {
+                    Some(value) => self.expression_type(value),
+                    None => Some("void".to_string()),
+                };
+                if let (Some(return_type), Some(value_type)) = (self.return_type.clone(), value_type) {
+                    if !Self::compatible(&return_type, &value_type) {
+                        self.errors.push(CompilerError::MissmatchedTypes("return".to_string(), self.line));
+                    }
+                }
+            }
+            ASTNode::VariableDeclaration { data_type, name, array_size, value, line } => {
+                self.line = *line as u16;
+                // the initializer can't refer to the variable it declares
+                match (array_size, value.as_deref()) {
+                    (_, None) => (),
+                    (Some(size), Some(ASTNode::InitializerList(elements))) => {
+                        if elements.len() > *size {
+                            self.errors.push(CompilerError::MissmatchedTypes(name.clone(), self.line));
+                        }
+                        for element in elements {
+                            self.check_initializer(data_type, name, element);
+                        }
+                    }
+                    // arrays are only initialized by lists and lists only initialize arrays
+                    (Some(_), Some(_)) | (None, Some(ASTNode::InitializerList(_))) => {
+                        self.errors.push(CompilerError::MissmatchedTypes(name.clone(), self.line));
+                    }
+                    (None, Some(value)) => self.check_initializer(data_type, name, value),
+                }
+
+                let data_type = match array_size {
+                    Some(_) => format!("{}[]", data_type),
+                    None => data_type.clone(),
+                };
+                self.declare(name, Symbol { data_type, kind: SymbolKind::Variable });
+            }
+            ASTNode::If { condition, body, else_body, line } => {
+                self.line = *line as u16;
+                self.check_condition(condition);
+                self.analyze_block(body);
+                if let Some(else_body) = else_body {
+                    self.analyze_block(else_body);
+                }
+            }
+            ASTNode::While { condition, body, line } => {
+                self.line = *line as u16;
+                self.check_condition(condition);
+                self.analyze_loop_body(body);
+            }
+            ASTNode::DoWhile { body, condition, line } => {
+                self.line = *line as u16;
+                self.analyze_loop_body(body);
+                self.line = *line as u16;
+                self.check_condition(condition);
+            }
+            ASTNode::For { init, condition, update, body, line } => {
+                self.line = *line as u16;
+                // variables declared in the initialization are only visible in the loop
+                self.scopes.push(HashMap::new());
+                for node in init {
+                    self.analyze_node(node);
+                }
+                if let Some(condition) = condition {
+                    self.check_condition(condition);
+                }
+                if let Some(update) = update {
+                    self.analyze_node(update);
+                }
+                self.analyze_loop_body(body);
+                self.scopes.pop();
+            }
+            ASTNode::Switch { value, cases, line } => {
+                self.line = *line as u16;
+                if self.expression_type(value).is_some_and(|value_type| !Self::is_numeric(&value_type)) {
+                    self.errors.push(CompilerError::MissmatchedTypes("switch".to_string(), self.line));
+                }
+
+                // all cases share the scope of the switch block
+                let mut labels = HashSet::new();
+                self.scopes.push(HashMap::new());
+                self.switches += 1;
+                for (case_value, body) in cases {
+                    let label = match case_value {
+                        Some(case_value) => {
+                            if self.expression_type(case_value).is_some_and(|case_type| !Self::is_numeric(&case_type)) {
+                                self.errors.push(CompilerError::MissmatchedTypes("case".to_string(), self.line));
+                            }
+                            // only constant cases can be compared with each other
+                            Self::case_constant(case_value).map(|value| (Some(value), Self::describe("case", case_value)))
+                        }
+                        None => Some((None, "default".to_string())),
+                    };
+                    if let Some((key, label)) = label {
+                        if !labels.insert(key) {
+                            self.errors.push(CompilerError::Redeclaration(label, self.line));
+                        }
+                    }
+                    for node in body {
+                        self.analyze_node(node);
+                    }
+                }
+                self.switches -= 1;
+                self.scopes.pop();
             }
-            ASTNode::Return { value } => {
-                println!("Analyzing return statement");
-                self.analyze_node(value)?;
+            ASTNode::Break { line } => {
+                self.line = *line as u16;
+                if self.loops == 0 && self.switches == 0 {
+                    self.errors.push(CompilerError::MisplacedJump("break".to_string(), self.line));
+                }
+            }
+            ASTNode::Continue { line } => {
+                self.line = *line as u16;
+                if self.loops == 0 {
+                    self.errors.push(CompilerError::MisplacedJump("continue".to_string(), self.line));
+                }
             }
-            ASTNode::VariableDeclaration { data_type, name, value } => {
-                println!("Analyzing variable declaration: {} {}", data_type, name);
-                if let Some(value) = value {
-                    self.analyze_node(value)?;
+            ASTNode::Assignment { name, index, value, line } => {
+                self.line = *line as u16;
+                let target_type = match (self.variable_type(name), index) {
+                    (Some(array_type), Some(index)) => self.element_type(&array_type, index),
+                    // arrays can only be assigned element by element
+                    (Some(target_type), None) if target_type.ends_with("[]") => {
+                        self.errors.push(CompilerError::MissmatchedTypes(name.clone(), self.line));
+                        None
+                    }
+                    (target_type, _) => target_type,
+                };
+                let value_type = self.expression_type(value);
+                if let (Some(target_type), Some(value_type)) = (target_type, value_type) {
+                    if !Self::compatible(&target_type, &value_type) {
+                        self.errors.push(CompilerError::MissmatchedTypes(Self::describe(name, value), self.line));
+                    }
                 }
             }
-            ASTNode::Expression(expr) => {
-                println!("Analyzing expression: {}", expr);
+            expression => {
+                self.expression_type(expression);
             }
+        }
+    }
+
+    // Returns the type of the expression or `None` if it contains an error that was already reported
+    fn expression_type(&mut self, node: &ASTNode) -> Option<String> {
+        match node {
             ASTNode::Literal(lit) => {
-                println!("Analyzing literal: {}", lit);
+                if lit.contains('.') {
+                    Some("double".to_string())
+                } else {
+                    Some("int".to_string())
+                }
             }
-            ASTNode::CharLiteral(chr) => {
-                println!("Analyzing char literal: {}", chr);
+            ASTNode::StringLiteral(_) => Some("string".to_string()),
+            ASTNode::CharLiteral(_) => Some("char".to_string()),
+            ASTNode::Identifier(name) => self.variable_type(name),
+            ASTNode::Binary { operator, left, right } => {
+                // both operands are checked so that the errors of each of them are reported
+                let (left, right) = (self.expression_type(left), self.expression_type(right));
+                let (left, right) = (left?, right?);
+                if !Self::is_numeric(&left) || !Self::is_numeric(&right) {
+                    self.errors.push(CompilerError::MissmatchedTypes(format!("{:?}", operator), self.line));
+                    return None;
+                }
+                match operator {
+                    Token::Plus | Token::Minus | Token::Multiply | Token::Divide | Token::Modulo => {
+                        Some(Self::promote(&left, &right))
+                    }
+                    // comparisons and logical operators evaluate to 0 or 1
+                    _ => Some("int".to_string()),
+                }
             }
-            ASTNode::If { condition, body, else_body } => {
-                println!("Analyzing if statement");
-                self.analyze_node(condition)?;
-                self.analyze(body)?;
-                if let Some(else_body) = else_body {
-                    self.analyze(else_body)?;
+            ASTNode::Unary { operator, operand } => {
+                let operand = self.expression_type(operand)?;
+                if !Self::is_numeric(&operand) {
+                    self.errors.push(CompilerError::MissmatchedTypes(format!("{:?}", operator), self.line));
+                    return None;
                 }
+                match operator {
+                    Token::Not => Some("int".to_string()),
+                    _ => Some(operand),
+                }
+            }
+            ASTNode::Call { name, arguments, line } => {
+                self.line = *line as u16;
+                let argument_types: Vec<Option<String>> =
+                    arguments.iter().map(|argument| self.expression_type(argument)).collect();
+
+                let (data_type, parameters) = match self.lookup(name) {
+                    Some(Symbol { data_type, kind: SymbolKind::Function(parameters) }) => {
+                        (data_type.clone(), parameters.clone())
+                    }
+                    Some(Symbol { kind: SymbolKind::Variable, .. }) => {
+                        self.errors.push(CompilerError::MissmatchedTypes(name.clone(), self.line));
+                        return None;
+                    }
+                    None => {
+                        self.errors.push(CompilerError::NoSuchVar(name.clone(), self.line));
+                        return None;
+                    }
+                };
+
+                if let Some(parameters) = parameters {
+                    let matches = parameters.len() == argument_types.len()
+                        && parameters.iter().zip(argument_types.iter()).all(|(parameter, argument)| {
+                            argument.as_ref().is_none_or(|argument| Self::compatible(parameter, argument))
+                        });
+                    if !matches {
+                        self.errors.push(CompilerError::MissmatchedTypes(name.clone(), self.line));
+                    }
+                }
+                Some(data_type)
+            }
+            ASTNode::Index { array, index } => {
+                let array_type = self.expression_type(array)?;
+                self.element_type(&array_type, index)
+            }
+            ASTNode::InitializerList(_) => unreachable!("initializer lists only appear in declarations"),
+            ASTNode::Error { line, .. } => {
+                self.errors.push(CompilerError::InvalidSyntax(*line as u16));
+                None
+            }
+            statement => {
+                self.analyze_node(statement);
+                None
+            }
+        }
+    }
+
+    fn check_initializer(&mut self, data_type: &str, name: &str, value: &ASTNode) {
+        if let Some(value_type) = self.expression_type(value) {
+            if !Self::compatible(data_type, &value_type) {
+                self.errors.push(CompilerError::MissmatchedTypes(Self::describe(name, value), self.line));
+            }
+        }
+    }
+
+    // Type of `array[index]`, the index has to be a number
+    fn element_type(&mut self, array_type: &str, index: &ASTNode) -> Option<String> {
+        if self.expression_type(index).is_some_and(|index_type| !Self::is_numeric(&index_type)) {
+            self.errors.push(CompilerError::MissmatchedTypes("[]".to_string(), self.line));
+        }
+        match array_type.strip_suffix("[]") {
+            Some(element_type) => Some(element_type.to_string()),
+            None => {
+                self.errors.push(CompilerError::MissmatchedTypes("[]".to_string(), self.line));
+                None
             }
-            ASTNode::While { condition, body } => {
-                println!("Analyzing while statement");
-                self.analyze_node(condition)?;
-                self.analyze(body)?;
+        }
+    }
+
+    fn variable_type(&mut self, name: &str) -> Option<String> {
+        match self.lookup(name) {
+            Some(Symbol { data_type, kind: SymbolKind::Variable }) => Some(data_type.clone()),
+            Some(Symbol { kind: SymbolKind::Function(_), .. }) => {
+                self.errors.push(CompilerError::MissmatchedTypes(name.to_string(), self.line));
+                None
             }
-            ASTNode::Assignment { name, value } => {
-                println!("Analyzing assignment: {}", name);
-                self.analyze_node(value)?;
+            None => {
+                self.errors.push(CompilerError::NoSuchVar(name.to_string(), self.line));
+                None
+            }
+        }
+    }
+
+    fn check_condition(&mut self, condition: &ASTNode) {
+        if let Some(condition_type) = self.expression_type(condition) {
+            if !Self::is_numeric(&condition_type) {
+                self.errors.push(CompilerError::InvalidCondition(self.line));
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &str, symbol: Symbol) {
+        let scope = self.scopes.last_mut().expect("global scope is never popped");
+        if scope.contains_key(name) {
+            self.errors.push(CompilerError::Redeclaration(name.to_string(), self.line));
+        } else {
+            scope.insert(name.to_string(), symbol);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Symbol> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    // Literals are reported by their value, everything else by the variable they're assigned to
+    fn describe(name: &str, value: &ASTNode) -> String {
+        match value {
+            ASTNode::Literal(lit) | ASTNode::StringLiteral(lit) => lit.clone(),
+            ASTNode::CharLiteral(chr) => chr.to_string(),
+            _ => name.to_string(),
+        }
+    }
+
+    // The value of a case label made of an integer or char literal, like `3`, `-1` or `'a'`
+    fn case_constant(value: &ASTNode) -> Option<i64> {
+        match value {
+            ASTNode::Literal(lit) => lit.parse().ok(),
+            ASTNode::CharLiteral(chr) => Some(*chr as i64),
+            ASTNode::Unary { operator: Token::Minus, operand } => Self::case_constant(operand).map(|value| -value),
+            _ => None,
+        }
+    }
+
+    // A body returns if its last statement does on every path
+    fn returns(body: &[ASTNode]) -> bool {
+        match body.last() {
+            Some(ASTNode::Return { .. }) => true,
+            Some(ASTNode::DoWhile { body, .. }) => Self::returns(body),
+            Some(ASTNode::If { body, else_body: Some(else_body), .. }) => {
+                Self::returns(body) && Self::returns(else_body)
             }
+            _ => false,
+        }
+    }
+
+    fn is_numeric(data_type: &str) -> bool {
+        matches!(data_type, "char" | "int" | "float" | "double")
+    }
+
+    fn compatible(expected: &str, found: &str) -> bool {
+        expected == found || (Self::is_numeric(expected) && Self::is_numeric(found))
+    }
+
+    fn promote(left: &str, right: &str) -> String {
+        let rank = |data_type: &str| ["char", "int", "float", "double"].iter().position(|t| *t == data_type);
+        if rank(left) >= rank(right) {
+            left.to_string()
+        } else {
+            right.to_string()
         }
-        Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn analyze(source: &str) -> Vec<String> {
+        let ast = Parser::new(Lexer::new(source).tokenize()).parse().unwrap();
+        SemanticAnalyzer::new().analyze(&ast).iter().map(|error| error.to_string()).collect::<Vec<String>>()
+    }
+
+    #[test]
+    fn valid_program() {
+        let source = "
+            int add(int a, int b) { return a + b; }
+            int main() {
+                int v[3] = {1, 2, 3};
+                double d = 1.5 * add(v[0], 2);
+                for (int i = 0; i < 3; i++) { v[i] = i; }
+                printf(\"%d\", d);
+                return 0;
+            }";
+        assert_eq!(analyze(source), Vec::<String>::new());
+    }
+
+    #[test]
+    fn undeclared() {
+        assert_eq!(analyze("int a = b;\nc = 1;"), [
+            "Invalid Var \"b\" at line: 1",
+            "Invalid Var \"c\" at line: 2",
+        ]);
+        // variables are only visible in the block that declares them
+        assert_eq!(analyze("int main() {\nif (1) { int x = 1; }\nreturn x;\n}"), ["Invalid Var \"x\" at line: 3"]);
+        assert_eq!(analyze("int main() { return f(1); }"), ["Invalid Var \"f\" at line: 1"]);
+    }
+
+    #[test]
+    fn redeclared() {
+        assert_eq!(analyze("int a;\nchar a;"), ["Redeclared Var \"a\" at line: 2"]);
+        assert_eq!(analyze("void f(int a) {\nint a = 1;\n}"), ["Redeclared Var \"a\" at line: 2"]);
+        // an inner block can shadow the outer declaration
+        assert_eq!(analyze("int a;\nint main() { int a = 2; return a; }"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn type_mismatch() {
+        assert_eq!(analyze("int a = \"text\";"), ["Missmatched Var \"text\" at line: 1"]);
+        assert_eq!(analyze("int a;\na = 'c' + \"s\";"), ["Missmatched Var \"Plus\" at line: 2"]);
+        assert_eq!(analyze("int v[2] = {1, 2, 3};"), ["Missmatched Var \"v\" at line: 1"]);
+        assert_eq!(analyze("int f(int a) { return a; }\nint b = f(1, 2);"), ["Missmatched Var \"f\" at line: 2"]);
+        assert_eq!(analyze("void f() { return 1; }"), ["Missmatched Var \"return\" at line: 1"]);
+        assert_eq!(analyze("int f() {\nint a = 1;\n}"), ["Missing return in function \"f\" at line: 1"]);
+    }
+
+    #[test]
+    fn both_operands_checked() {
+        assert_eq!(analyze("int a = b + c;"), [
+            "Invalid Var \"b\" at line: 1",
+            "Invalid Var \"c\" at line: 1",
+        ]);
+    }
+
+    #[test]
+    fn switch_cases() {
+        let source = |cases: &str| format!("int main() {{ int x = 1; int y = 2; switch (x) {{ {} }} return 0; }}", cases);
+        assert_eq!(analyze(&source("case 1: break; case 2: break; default: break;")), Vec::<String>::new());
+        assert_eq!(analyze(&source("case 1: break; case 1: break;")), ["Redeclared Var \"1\" at line: 1"]);
+        assert_eq!(analyze(&source("case -1: break; case 1: break;")), Vec::<String>::new());
+        assert_eq!(analyze(&source("case 'a': break; case 97: break;")), ["Redeclared Var \"97\" at line: 1"]);
+        assert_eq!(analyze(&source("default: break; default: break;")), ["Redeclared Var \"default\" at line: 1"]);
+        // cases that aren't constant can't be told apart
+        assert_eq!(analyze(&source("case x: break; case y: break; case x + 1: break;")), Vec::<String>::new());
+    }
+
+    #[test]
+    fn misplaced_jumps() {
+        assert_eq!(analyze("void f() {\nbreak;\ncontinue;\n}"), [
+            "Misplaced \"break\" outside of a loop at line: 2",
+            "Misplaced \"continue\" outside of a loop at line: 3",
+        ]);
+        assert_eq!(
+            analyze("void f(int x) { switch (x) {\ncase 1: continue;\n} }"),
+            ["Misplaced \"continue\" outside of a loop at line: 2"]
+        );
+    }
+}
@@ -11,13 +11,25 @@ pub enum Token {
     Equals,
     Plus,
     Minus,
+    Increment,
+    Decrement,
     Multiply,
     Divide,
+    Modulo,
     LessThan,
     GreaterThan,
+    LessEquals,
+    GreaterEquals,
     NotEquals,
+    Not,
     And,
     Or,
-    Whitespace,
     Unknown(char),
-}
\ No newline at end of file
+}
+
+// Position of the first character of a token, both starting at 1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
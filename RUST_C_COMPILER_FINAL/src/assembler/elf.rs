@@ -0,0 +1,381 @@
+//! Writes an [ObjectFile] as a 64bit little-endian ELF relocatable object-file
+//! that can be linked with the system linker
+
+use crate::assembler::*;
+
+// section-header types
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+const SHT_RELA: u32 = 4;
+const SHT_NOBITS: u32 = 8;
+
+// section-header flags
+const SHF_WRITE: u64 = 0x1;
+const SHF_ALLOC: u64 = 0x2;
+const SHF_EXECINSTR: u64 = 0x4;
+const SHF_INFO_LINK: u64 = 0x40;
+
+// symbol binding and types
+const STB_LOCAL: u8 = 0;
+const STB_GLOBAL: u8 = 1;
+const STT_NOTYPE: u8 = 0;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+const STT_SECTION: u8 = 3;
+
+const ELF_HEADER_SIZE: usize = 64;
+const SECTION_HEADER_SIZE: usize = 64;
+const SYMBOL_SIZE: usize = 24;
+const RELA_SIZE: usize = 24;
+
+impl RelocKind {
+    fn elf_type(&self) -> u32 {
+        match self {
+            RelocKind::Abs64 => 1,    // R_X86_64_64
+            RelocKind::Pc32 => 2,     // R_X86_64_PC32
+            RelocKind::Plt32 => 4,    // R_X86_64_PLT32
+            RelocKind::GotPcRel => 9, // R_X86_64_GOTPCREL
+            RelocKind::Abs32 => 10,   // R_X86_64_32
+        }
+    }
+}
+impl SectionKind {
+    fn name(&self) -> &'static str {
+        match self {
+            SectionKind::Text => ".text",
+            SectionKind::Data => ".data",
+            SectionKind::Bss => ".bss",
+            SectionKind::Rodata => ".rodata",
+        }
+    }
+    fn header_type(&self) -> u32 {
+        match self {
+            SectionKind::Bss => SHT_NOBITS,
+            _ => SHT_PROGBITS,
+        }
+    }
+    fn flags(&self) -> u64 {
+        match self {
+            SectionKind::Text => SHF_ALLOC | SHF_EXECINSTR,
+            SectionKind::Data | SectionKind::Bss => SHF_ALLOC | SHF_WRITE,
+            SectionKind::Rodata => SHF_ALLOC,
+        }
+    }
+    fn alignment(&self) -> u64 {
+        match self {
+            SectionKind::Text => 16,
+            _ => 8,
+        }
+    }
+}
+
+struct StringTable(Vec<u8>);
+impl StringTable {
+    // index 0 is always the empty string
+    fn new() -> Self {
+        StringTable(vec![0])
+    }
+    fn add(&mut self, s: &str) -> u32 {
+        let index = self.0.len() as u32;
+        self.0.extend(s.bytes());
+        self.0.push(0);
+        index
+    }
+}
+
+struct SectionHeader {
+    name: u32,
+    kind: u32,
+    flags: u64,
+    offset: u64,
+    size: u64,
+    link: u32,
+    info: u32,
+    alignment: u64,
+    entry_size: u64,
+}
+
+/// Serializes the object-file into its ELF representation
+pub fn write(object: ObjectFile) -> Vec<u8> {
+    let mut out = vec![0; ELF_HEADER_SIZE];
+    let mut headers = Vec::new();
+    let mut shstrtab = StringTable::new();
+
+    // section-index of the four data-sections, followed by their relocation-sections
+    let section_count = object.sections.len();
+    let symtab_index = 1 + 2 * section_count;
+    let strtab_index = symtab_index + 1;
+
+    // local symbols have to precede global ones
+    let mut strtab = StringTable::new();
+    let mut symtab = vec![0; SYMBOL_SIZE];
+    for i in 0..section_count {
+        write_symbol(&mut symtab, 0, STT_SECTION, STB_LOCAL, 1 + i as u16, 0);
+    }
+    let mut symbol_indices = vec![0; object.symbols.len()];
+    let mut first_global = 0;
+    for is_global in [false, true] {
+        if is_global {
+            first_global = symtab.len() / SYMBOL_SIZE;
+        }
+        for (i, symbol) in object.symbols.iter().enumerate() {
+            if symbol.is_global != is_global {
+                continue;
+            }
+            symbol_indices[i] = symtab.len() / SYMBOL_SIZE;
+
+            let (section_index, value) = match symbol.location {
+                Some((kind, offset)) => (section_index(&object, kind) as u16, offset as u64),
+                None => (0, 0),
+            };
+            let symbol_type = match symbol.kind {
+                SymbolKind::Func => STT_FUNC,
+                SymbolKind::Object => STT_OBJECT,
+                SymbolKind::NoType => STT_NOTYPE,
+            };
+            let binding = if is_global { STB_GLOBAL } else { STB_LOCAL };
+            let name = strtab.add(&symbol.name);
+
+            write_symbol(&mut symtab, name, symbol_type, binding, section_index, value);
+        }
+    }
+
+    for (kind, section, _) in &object.sections {
+        let offset = align(&mut out, kind.alignment() as usize);
+        if *kind != SectionKind::Bss {
+            out.extend(&section.bytes);
+        }
+        headers.push(SectionHeader {
+            name: shstrtab.add(kind.name()),
+            kind: kind.header_type(),
+            flags: kind.flags(),
+            offset,
+            size: section.bytes.len() as u64,
+            link: 0,
+            info: 0,
+            alignment: kind.alignment(),
+            entry_size: 0,
+        });
+    }
+    for (i, (kind, _, relocations)) in object.sections.iter().enumerate() {
+        let offset = align(&mut out, 8);
+        for reloc in relocations {
+            let symbol = match reloc.target {
+                RelocTarget::Section(kind) => section_index(&object, kind),
+                RelocTarget::Symbol(index) => symbol_indices[index],
+            };
+            out.extend((reloc.offset as u64).to_le_bytes());
+            out.extend(((symbol as u64) << 32 | reloc.kind.elf_type() as u64).to_le_bytes());
+            out.extend(reloc.addend.to_le_bytes());
+        }
+        headers.push(SectionHeader {
+            name: shstrtab.add(&format!(".rela{}", kind.name())),
+            kind: SHT_RELA,
+            flags: SHF_INFO_LINK,
+            offset,
+            size: (relocations.len() * RELA_SIZE) as u64,
+            link: symtab_index as u32,
+            info: 1 + i as u32,
+            alignment: 8,
+            entry_size: RELA_SIZE as u64,
+        });
+    }
+
+    let offset = align(&mut out, 8);
+    out.extend(&symtab);
+    headers.push(SectionHeader {
+        name: shstrtab.add(".symtab"),
+        kind: SHT_SYMTAB,
+        flags: 0,
+        offset,
+        size: symtab.len() as u64,
+        link: strtab_index as u32,
+        info: first_global as u32,
+        alignment: 8,
+        entry_size: SYMBOL_SIZE as u64,
+    });
+
+    let offset = out.len() as u64;
+    out.extend(&strtab.0);
+    headers.push(SectionHeader {
+        name: shstrtab.add(".strtab"),
+        kind: SHT_STRTAB,
+        flags: 0,
+        offset,
+        size: strtab.0.len() as u64,
+        link: 0,
+        info: 0,
+        alignment: 1,
+        entry_size: 0,
+    });
+
+    // marks the stack as non-executable
+    headers.push(SectionHeader {
+        name: shstrtab.add(".note.GNU-stack"),
+        kind: SHT_PROGBITS,
+        flags: 0,
+        offset: out.len() as u64,
+        size: 0,
+        link: 0,
+        info: 0,
+        alignment: 1,
+        entry_size: 0,
+    });
+
+    let shstrtab_index = headers.len() + 1;
+    let name = shstrtab.add(".shstrtab");
+    let offset = out.len() as u64;
+    out.extend(&shstrtab.0);
+    headers.push(SectionHeader {
+        name,
+        kind: SHT_STRTAB,
+        flags: 0,
+        offset,
+        size: shstrtab.0.len() as u64,
+        link: 0,
+        info: 0,
+        alignment: 1,
+        entry_size: 0,
+    });
+
+    let header_offset = align(&mut out, 8);
+    // null section-header
+    out.extend([0; SECTION_HEADER_SIZE]);
+    for header in headers.iter() {
+        out.extend(header.name.to_le_bytes());
+        out.extend(header.kind.to_le_bytes());
+        out.extend(header.flags.to_le_bytes());
+        out.extend(0u64.to_le_bytes());
+        out.extend(header.offset.to_le_bytes());
+        out.extend(header.size.to_le_bytes());
+        out.extend(header.link.to_le_bytes());
+        out.extend(header.info.to_le_bytes());
+        out.extend(header.alignment.to_le_bytes());
+        out.extend(header.entry_size.to_le_bytes());
+    }
+
+    write_header(&mut out, header_offset, headers.len() + 1, shstrtab_index);
+    out
+}
+
+fn write_header(out: &mut [u8], header_offset: u64, section_count: usize, shstrtab_index: usize) {
+    let mut header = Vec::with_capacity(ELF_HEADER_SIZE);
+    // magic, 64bit, little-endian, current version, System V ABI
+    header.extend([0x7f, b'E', b'L', b'F', 2, 1, 1, 0]);
+    header.extend([0; 8]);
+    // relocatable file for x86-64
+    header.extend(1u16.to_le_bytes());
+    header.extend(62u16.to_le_bytes());
+    header.extend(1u32.to_le_bytes());
+    // no entry-point and no program-headers
+    header.extend(0u64.to_le_bytes());
+    header.extend(0u64.to_le_bytes());
+    header.extend(header_offset.to_le_bytes());
+    header.extend(0u32.to_le_bytes());
+    header.extend((ELF_HEADER_SIZE as u16).to_le_bytes());
+    header.extend(0u16.to_le_bytes());
+    header.extend(0u16.to_le_bytes());
+    header.extend((SECTION_HEADER_SIZE as u16).to_le_bytes());
+    header.extend((section_count as u16).to_le_bytes());
+    header.extend((shstrtab_index as u16).to_le_bytes());
+
+    out[..ELF_HEADER_SIZE].copy_from_slice(&header);
+}
+
+fn write_symbol(symtab: &mut Vec<u8>, name: u32, symbol_type: u8, binding: u8, section: u16, value: u64) {
+    symtab.extend(name.to_le_bytes());
+    symtab.push(binding << 4 | symbol_type);
+    symtab.push(0);
+    symtab.extend(section.to_le_bytes());
+    symtab.extend(value.to_le_bytes());
+    // size is unknown since there is no `.size` directive
+    symtab.extend(0u64.to_le_bytes());
+}
+
+fn section_index(object: &ObjectFile, kind: SectionKind) -> usize {
+    1 + object
+        .sections
+        .iter()
+        .position(|(k, ..)| *k == kind)
+        .expect("all sections are always written")
+}
+
+// pads output so that next section starts aligned and returns its offset
+fn align(out: &mut Vec<u8>, alignment: usize) -> u64 {
+    out.resize(align_by(out.len(), alignment), 0);
+    out.len() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elf_layout() {
+        let text = Section {
+            bytes: vec![0xe8, 0, 0, 0, 0, 0xc3],
+            ..Default::default()
+        };
+        let object = ObjectFile {
+            sections: vec![
+                (
+                    SectionKind::Text,
+                    text,
+                    vec![Relocation {
+                        offset: 1,
+                        target: RelocTarget::Symbol(1),
+                        kind: RelocKind::Plt32,
+                        addend: -4,
+                    }],
+                ),
+                (
+                    SectionKind::Bss,
+                    Section {
+                        bytes: vec![0; 16],
+                        ..Default::default()
+                    },
+                    vec![],
+                ),
+            ],
+            symbols: vec![
+                Symbol {
+                    name: "main".to_string(),
+                    location: Some((SectionKind::Text, 0)),
+                    is_global: true,
+                    kind: SymbolKind::Func,
+                },
+                Symbol {
+                    name: "puts".to_string(),
+                    location: None,
+                    is_global: true,
+                    kind: SymbolKind::NoType,
+                },
+            ],
+        };
+        let actual = write(object);
+        let read_u16 = |offset: usize| u16::from_le_bytes(actual[offset..offset + 2].try_into().unwrap());
+        let read_u64 = |offset: usize| u64::from_le_bytes(actual[offset..offset + 8].try_into().unwrap());
+
+        assert_eq!(&actual[..4], b"\x7fELF");
+        // null, 2 sections, 2 relocation-sections, symtab, strtab, GNU-stack, shstrtab
+        assert_eq!(read_u16(60), 9);
+        assert_eq!(read_u16(62), 8);
+
+        let section_headers = read_u64(40) as usize;
+        assert_eq!(actual.len(), section_headers + 9 * SECTION_HEADER_SIZE);
+
+        // text is written directly after the header, bss takes up no space
+        let text_header = section_headers + SECTION_HEADER_SIZE;
+        assert_eq!(read_u64(text_header + 24), ELF_HEADER_SIZE as u64);
+        assert_eq!(&actual[64..70], &[0xe8, 0, 0, 0, 0, 0xc3]);
+        let rela_header = section_headers + 3 * SECTION_HEADER_SIZE;
+        let rela = read_u64(rela_header + 24) as usize;
+        assert!(rela < 80);
+
+        // relocation refers to `puts` which is the last symbol after null and two section-symbols
+        assert_eq!(read_u64(rela), 1);
+        assert_eq!(read_u64(rela + 8), 4 << 32 | 4);
+        assert_eq!(read_u64(rela + 16) as i64, -4);
+    }
+}
@@ -0,0 +1,568 @@
+//! Encodes single x86-64 instructions into machine-code
+
+use crate::assembler::*;
+
+/// 64bit register names in the order of their encoding
+static REGISTERS: [&str; 16] = [
+    "%rax", "%rcx", "%rdx", "%rbx", "%rsp", "%rbp", "%rsi", "%rdi", "%r8", "%r9", "%r10", "%r11", "%r12",
+    "%r13", "%r14", "%r15",
+];
+pub const RAX: u8 = 0;
+pub const RCX: u8 = 1;
+pub const RDX: u8 = 2;
+pub const RSP: u8 = 4;
+pub const RBP: u8 = 5;
+
+pub fn reg_number(base_name: &str) -> u8 {
+    REGISTERS
+        .iter()
+        .position(|name| *name == base_name)
+        .unwrap_or_else(|| unreachable!("not a 64bit register: '{}'", base_name)) as u8
+}
+
+/// Operands of a single machine-instruction
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Reg(u8),
+    // base-register, index-register with its scale, displacement
+    Mem {
+        base: u8,
+        index: Option<(u8, u8)>,
+        disp: i32,
+    },
+    // address relative to instruction-pointer, if address is retrieved from GlobalOffsetTable
+    Rip(Target, bool),
+    Imm(i64),
+}
+
+/// Opcode-extensions of the arithmetic instructions that share the same encoding-scheme
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AluOp {
+    Add = 0,
+    Or = 1,
+    And = 4,
+    Sub = 5,
+    Xor = 6,
+    Cmp = 7,
+}
+
+/// Returns the condition-code used in `jcc` and `setcc` given its mnemonic-suffix
+pub fn condition_code(cond: &str) -> u8 {
+    match cond {
+        "o" => 0x0,
+        "no" => 0x1,
+        "b" | "c" | "nae" => 0x2,
+        "ae" | "nb" | "nc" => 0x3,
+        "e" | "z" => 0x4,
+        "ne" | "nz" => 0x5,
+        "be" | "na" => 0x6,
+        "a" | "nbe" => 0x7,
+        "s" => 0x8,
+        "ns" => 0x9,
+        "p" | "pe" => 0xa,
+        "np" | "po" => 0xb,
+        "l" | "nge" => 0xc,
+        "ge" | "nl" => 0xd,
+        "le" | "ng" => 0xe,
+        "g" | "nle" => 0xf,
+        _ => unreachable!("unknown condition '{}'", cond),
+    }
+}
+
+// sign-extends the immediate from the operand-size so that it can be checked if it fits into a smaller encoding
+fn sign_extend(value: i64, size: usize) -> i64 {
+    match size {
+        1 => value as i8 as i64,
+        2 => value as i16 as i64,
+        4 => value as i32 as i64,
+        _ => value,
+    }
+}
+
+// 8bit registers %spl, %bpl, %sil and %dil can only be accessed using a REX-prefix
+fn needs_rex(size: usize, operands: &[&Operand]) -> bool {
+    size == 1
+        && operands
+            .iter()
+            .any(|op| matches!(op, Operand::Reg(n) if (RSP..=7).contains(n)))
+}
+
+impl Section {
+    fn emit(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+    pub fn emit_imm(&mut self, value: i64, size: usize) {
+        self.emit(&value.to_le_bytes()[..size]);
+    }
+    fn emit_rex(&mut self, is_wide: bool, reg: u8, rm: &Operand, force: bool) {
+        let (index, base) = match rm {
+            Operand::Reg(n) => (0, *n),
+            Operand::Mem { base, index, .. } => (index.map_or(0, |(index, _)| index), *base),
+            Operand::Rip(..) | Operand::Imm(_) => (0, 0),
+        };
+        let rex = 0x40 | (is_wide as u8) << 3 | (reg >> 3) << 2 | (index >> 3) << 1 | (base >> 3);
+
+        if rex != 0x40 || force {
+            self.emit(&[rex]);
+        }
+    }
+    // emits ModRM-byte followed by optional SIB-byte and displacement
+    fn emit_modrm(&mut self, reg: u8, rm: &Operand, imm_size: usize) {
+        let reg = (reg & 7) << 3;
+        match rm {
+            Operand::Reg(n) => self.emit(&[0xc0 | reg | (n & 7)]),
+            Operand::Mem { base, index, disp } => {
+                // base-pointer without displacement would be interpreted as rip-relative
+                let mode = match disp {
+                    0 if base & 7 != RBP => 0x00,
+                    -128..=127 => 0x40,
+                    _ => 0x80,
+                };
+                if index.is_some() || base & 7 == RSP {
+                    let (index, scale) = index.unwrap_or((RSP, 1));
+                    self.emit(&[
+                        mode | reg | 0b100,
+                        (scale.trailing_zeros() as u8) << 6 | (index & 7) << 3 | (base & 7),
+                    ]);
+                } else {
+                    self.emit(&[mode | reg | (base & 7)]);
+                }
+                match mode {
+                    0x40 => self.emit_imm(*disp as i64, 1),
+                    0x80 => self.emit_imm(*disp as i64, 4),
+                    _ => (),
+                }
+            }
+            Operand::Rip(target, from_got) => {
+                self.emit(&[reg | 0b101]);
+                // displacement is relative to the end of the instruction
+                self.fixup(
+                    target.clone(),
+                    if *from_got {
+                        RelocKind::GotPcRel
+                    } else {
+                        RelocKind::Pc32
+                    },
+                    -4 - imm_size as i64,
+                );
+            }
+            Operand::Imm(_) => unreachable!("immediate can't be addressed"),
+        }
+    }
+    /// Emits an instruction of the form `[0x66] [REX] opcode ModRM [SIB] [disp] [imm]`
+    pub fn emit_instr(
+        &mut self,
+        size: usize,
+        opcode: &[u8],
+        reg: u8,
+        rm: &Operand,
+        imm: Option<(i64, usize)>,
+        force_rex: bool,
+    ) {
+        if size == 2 {
+            self.emit(&[0x66]);
+        }
+        self.emit_rex(size == 8, reg, rm, force_rex);
+        self.emit(opcode);
+
+        let imm_size = imm.map_or(0, |(_, size)| size);
+        self.emit_modrm(reg, rm, imm_size);
+
+        if let Some((value, size)) = imm {
+            self.emit_imm(value, size);
+        }
+    }
+    // reserves space for a 32bit value that is filled in once all labels are known
+    fn fixup(&mut self, target: Target, kind: RelocKind, addend: i64) {
+        self.fixups.push(Fixup {
+            offset: self.bytes.len(),
+            target,
+            kind,
+            addend,
+        });
+        self.emit(&[0; 4]);
+    }
+
+    pub fn mov(&mut self, size: usize, from: Operand, to: Operand) {
+        let force_rex = needs_rex(size, &[&from, &to]);
+        match (from, to) {
+            (Operand::Imm(value), Operand::Reg(n)) => {
+                if size == 8 && i32::try_from(value).is_ok() {
+                    self.emit_instr(size, &[0xc7], 0, &Operand::Reg(n), Some((value, 4)), false);
+                } else {
+                    // `movabs` for 64bit values, shorter encoding for all other sizes
+                    if size == 2 {
+                        self.emit(&[0x66]);
+                    }
+                    self.emit_rex(size == 8, 0, &Operand::Reg(n), force_rex);
+                    self.emit(&[if size == 1 { 0xb0 } else { 0xb8 } + (n & 7)]);
+                    self.emit_imm(value, size);
+                }
+            }
+            (Operand::Imm(value), to) => {
+                assert!(
+                    i32::try_from(sign_extend(value, size)).is_ok(),
+                    "64bit immediate can only be moved into register"
+                );
+                let opcode = if size == 1 { 0xc6 } else { 0xc7 };
+                self.emit_instr(size, &[opcode], 0, &to, Some((value, size.min(4))), force_rex);
+            }
+            (Operand::Reg(n), to) => {
+                let opcode = if size == 1 { 0x88 } else { 0x89 };
+                self.emit_instr(size, &[opcode], n, &to, None, force_rex);
+            }
+            (from, Operand::Reg(n)) => {
+                let opcode = if size == 1 { 0x8a } else { 0x8b };
+                self.emit_instr(size, &[opcode], n, &from, None, force_rex);
+            }
+            (from, to) => unreachable!("invalid operands for mov: {:?}, {:?}", from, to),
+        }
+    }
+    // sign- or zero-extends `from` into the bigger register `to`
+    pub fn extend(&mut self, is_signed: bool, from_size: usize, size: usize, from: Operand, to: Operand) {
+        let Operand::Reg(n) = to else {
+            unreachable!("extension-destination has to be register")
+        };
+        let force_rex = needs_rex(from_size, &[&from]);
+        let opcode: &[u8] = match (is_signed, from_size) {
+            (true, 1) => &[0x0f, 0xbe],
+            (true, 2) => &[0x0f, 0xbf],
+            (true, 4) => &[0x63],
+            (false, 1) => &[0x0f, 0xb6],
+            (false, 2) => &[0x0f, 0xb7],
+            _ => unreachable!("invalid extension from {} bytes", from_size),
+        };
+        self.emit_instr(size, opcode, n, &from, None, force_rex);
+    }
+    pub fn alu(&mut self, op: AluOp, size: usize, left: Operand, right: Operand) {
+        let force_rex = needs_rex(size, &[&left, &right]);
+        let op = op as u8;
+        let is_byte = (size == 1) as u8;
+        match (left, right) {
+            (Operand::Imm(value), right) => {
+                let value = sign_extend(value, size);
+                assert!(i32::try_from(value).is_ok(), "immediate has to fit into 32 bits");
+
+                if size == 1 {
+                    self.emit_instr(size, &[0x80], op, &right, Some((value, 1)), force_rex);
+                } else if i8::try_from(value).is_ok() {
+                    self.emit_instr(size, &[0x83], op, &right, Some((value, 1)), force_rex);
+                } else {
+                    self.emit_instr(size, &[0x81], op, &right, Some((value, size.min(4))), force_rex);
+                }
+            }
+            (Operand::Reg(n), right) => {
+                self.emit_instr(size, &[op << 3 | (1 - is_byte)], n, &right, None, force_rex)
+            }
+            (left, Operand::Reg(n)) => {
+                self.emit_instr(size, &[op << 3 | 2 | (1 - is_byte)], n, &left, None, force_rex)
+            }
+            (left, right) => unreachable!("invalid operands for alu-op: {:?}, {:?}", left, right),
+        }
+    }
+    pub fn imul(&mut self, size: usize, left: Operand, right: Operand) {
+        let Operand::Reg(n) = right else {
+            unreachable!("imul-destination has to be register")
+        };
+        match left {
+            Operand::Imm(value) => {
+                let value = sign_extend(value, size);
+                if i8::try_from(value).is_ok() {
+                    self.emit_instr(size, &[0x6b], n, &Operand::Reg(n), Some((value, 1)), false)
+                } else {
+                    self.emit_instr(
+                        size,
+                        &[0x69],
+                        n,
+                        &Operand::Reg(n),
+                        Some((value, size.min(4))),
+                        false,
+                    )
+                }
+            }
+            left => self.emit_instr(size, &[0x0f, 0xaf], n, &left, None, false),
+        }
+    }
+    // instructions with a single operand that is encoded using an opcode-extension
+    pub fn unary(&mut self, extension: u8, size: usize, operand: Operand) {
+        let force_rex = needs_rex(size, &[&operand]);
+        let opcode = if size == 1 { 0xf6 } else { 0xf7 };
+        self.emit_instr(size, &[opcode], extension, &operand, None, force_rex);
+    }
+    pub fn shift(&mut self, extension: u8, size: usize, amount: Operand, operand: Operand) {
+        let force_rex = needs_rex(size, &[&operand]);
+        let is_byte = (size == 1) as u8;
+        match amount {
+            Operand::Imm(value) => self.emit_instr(
+                size,
+                &[0xc1 - is_byte],
+                extension,
+                &operand,
+                Some((value, 1)),
+                force_rex,
+            ),
+            Operand::Reg(RCX) => {
+                self.emit_instr(size, &[0xd3 - is_byte], extension, &operand, None, force_rex)
+            }
+            amount => unreachable!("shift-amount has to be in %cl or immediate: {:?}", amount),
+        }
+    }
+    pub fn lea(&mut self, size: usize, from: Operand, to: Operand) {
+        let Operand::Reg(n) = to else {
+            unreachable!("lea-destination has to be register")
+        };
+        self.emit_instr(size, &[0x8d], n, &from, None, false);
+    }
+    pub fn set(&mut self, cond: &str, operand: Operand) {
+        let force_rex = needs_rex(1, &[&operand]);
+        self.emit_instr(
+            1,
+            &[0x0f, 0x90 + condition_code(cond)],
+            0,
+            &operand,
+            None,
+            force_rex,
+        );
+    }
+    pub fn push(&mut self, operand: Operand) {
+        match operand {
+            Operand::Reg(n) => {
+                self.emit_rex(false, 0, &Operand::Reg(n), false);
+                self.emit(&[0x50 + (n & 7)]);
+            }
+            Operand::Imm(value) if i8::try_from(value).is_ok() => {
+                self.emit(&[0x6a]);
+                self.emit_imm(value, 1);
+            }
+            Operand::Imm(value) => {
+                self.emit(&[0x68]);
+                self.emit_imm(value, 4);
+            }
+            operand => self.emit_instr(4, &[0xff], 6, &operand, None, false),
+        }
+    }
+    pub fn pop(&mut self, operand: Operand) {
+        match operand {
+            Operand::Reg(n) => {
+                self.emit_rex(false, 0, &Operand::Reg(n), false);
+                self.emit(&[0x58 + (n & 7)]);
+            }
+            operand => self.emit_instr(4, &[0x8f], 0, &operand, None, false),
+        }
+    }
+    // direct calls and jumps go through the procedure-linkage-table so that functions can be
+    // defined in shared-libraries
+    pub fn call(&mut self, target: Target) {
+        self.emit(&[0xe8]);
+        self.fixup(target, RelocKind::Plt32, -4);
+    }
+    pub fn tail_jmp(&mut self, target: Target) {
+        self.emit(&[0xe9]);
+        self.fixup(target, RelocKind::Plt32, -4);
+    }
+    pub fn call_indirect(&mut self, operand: Operand) {
+        self.emit_instr(4, &[0xff], 2, &operand, None, false);
+    }
+    pub fn jmp_indirect(&mut self, operand: Operand) {
+        self.emit_instr(4, &[0xff], 4, &operand, None, false);
+    }
+    pub fn jmp(&mut self, label: usize) {
+        self.emit(&[0xe9]);
+        self.fixup(Target::Label(label), RelocKind::Pc32, -4);
+    }
+    pub fn jmp_cond(&mut self, cond: &str, label: usize) {
+        self.emit(&[0x0f, 0x80 + condition_code(cond)]);
+        self.fixup(Target::Label(label), RelocKind::Pc32, -4);
+    }
+    pub fn ret(&mut self) {
+        self.emit(&[0xc3]);
+    }
+    pub fn rep_stosb(&mut self) {
+        self.emit(&[0xf3, 0xaa]);
+    }
+    // sign-extends %eax/%rax into %edx/%rdx
+    pub fn sign_extend_rax(&mut self, size: usize) {
+        if size == 8 {
+            self.emit(&[0x48]);
+        }
+        self.emit(&[0x99]);
+    }
+    // difference between two labels used as jump-table entry
+    pub fn label_offset(&mut self, label: usize, table_label: usize) {
+        let table_offset = self.labels[&Target::Label(table_label)] as i64;
+        let addend = self.bytes.len() as i64 - table_offset;
+        self.fixup(Target::Label(label), RelocKind::Pc32, addend);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mem(base: &str, disp: i32) -> Operand {
+        Operand::Mem {
+            base: reg_number(base),
+            index: None,
+            disp,
+        }
+    }
+    fn reg(name: &str) -> Operand {
+        Operand::Reg(reg_number(name))
+    }
+    fn assert_encoding(f: impl FnOnce(&mut Section), expected: &[u8]) {
+        let mut section = Section::default();
+        f(&mut section);
+        assert_eq!(section.bytes, expected);
+    }
+
+    #[test]
+    fn movs() {
+        // movl %r10d, %eax
+        assert_encoding(|s| s.mov(4, reg("%r10"), reg("%rax")), &[0x44, 0x89, 0xd0]);
+        // movq -8(%rbp), %r11
+        assert_encoding(
+            |s| s.mov(8, mem("%rbp", -8), reg("%r11")),
+            &[0x4c, 0x8b, 0x5d, 0xf8],
+        );
+        // movb %sil, -1(%rbp)
+        assert_encoding(
+            |s| s.mov(1, reg("%rsi"), mem("%rbp", -1)),
+            &[0x40, 0x88, 0x75, 0xff],
+        );
+        // movl $3, -200(%rbp)
+        assert_encoding(
+            |s| s.mov(4, Operand::Imm(3), mem("%rbp", -200)),
+            &[0xc7, 0x85, 0x38, 0xff, 0xff, 0xff, 0x03, 0x00, 0x00, 0x00],
+        );
+        // movq $10000000000, %r10
+        assert_encoding(
+            |s| s.mov(8, Operand::Imm(10000000000), reg("%r10")),
+            &[0x49, 0xba, 0x00, 0xe4, 0x0b, 0x54, 0x02, 0x00, 0x00, 0x00],
+        );
+        // movw %ax, (%r10)
+        assert_encoding(
+            |s| s.mov(2, reg("%rax"), mem("%r10", 0)),
+            &[0x66, 0x41, 0x89, 0x02],
+        );
+        // movslq %edi, %r10
+        assert_encoding(
+            |s| s.extend(true, 4, 8, reg("%rdi"), reg("%r10")),
+            &[0x4c, 0x63, 0xd7],
+        );
+        // movzbl %dil, %r11d
+        assert_encoding(
+            |s| s.extend(false, 1, 4, reg("%rdi"), reg("%r11")),
+            &[0x44, 0x0f, 0xb6, 0xdf],
+        );
+    }
+
+    #[test]
+    fn arithmetic() {
+        // addl %r10d, %r11d
+        assert_encoding(
+            |s| s.alu(AluOp::Add, 4, reg("%r10"), reg("%r11")),
+            &[0x45, 0x01, 0xd3],
+        );
+        // subq $16, %rsp
+        assert_encoding(
+            |s| s.alu(AluOp::Sub, 8, Operand::Imm(16), reg("%rsp")),
+            &[0x48, 0x83, 0xec, 0x10],
+        );
+        // cmpl $1000, -4(%rbp)
+        assert_encoding(
+            |s| s.alu(AluOp::Cmp, 4, Operand::Imm(1000), mem("%rbp", -4)),
+            &[0x81, 0x7d, 0xfc, 0xe8, 0x03, 0x00, 0x00],
+        );
+        // xorb (%rsp), %cl
+        assert_encoding(
+            |s| s.alu(AluOp::Xor, 1, mem("%rsp", 0), reg("%rcx")),
+            &[0x32, 0x0c, 0x24],
+        );
+        // imull $3, %r10d
+        assert_encoding(
+            |s| s.imul(4, Operand::Imm(3), reg("%r10")),
+            &[0x45, 0x6b, 0xd2, 0x03],
+        );
+        // idivq %r11
+        assert_encoding(|s| s.unary(7, 8, reg("%r11")), &[0x49, 0xf7, 0xfb]);
+        // sarl %cl, %r10d
+        assert_encoding(|s| s.shift(7, 4, reg("%rcx"), reg("%r10")), &[0x41, 0xd3, 0xfa]);
+        // sete %al
+        assert_encoding(|s| s.set("e", reg("%rax")), &[0x0f, 0x94, 0xc0]);
+    }
+
+    #[test]
+    fn addressing() {
+        // leaq -16(%rbp), %rdi
+        assert_encoding(
+            |s| s.lea(8, mem("%rbp", -16), reg("%rdi")),
+            &[0x48, 0x8d, 0x7d, 0xf0],
+        );
+        // movslq (%r10,%r11,4), %r11
+        assert_encoding(
+            |s| {
+                s.extend(
+                    true,
+                    4,
+                    8,
+                    Operand::Mem {
+                        base: reg_number("%r10"),
+                        index: Some((reg_number("%r11"), 4)),
+                        disp: 0,
+                    },
+                    reg("%r11"),
+                )
+            },
+            &[0x4f, 0x63, 0x1c, 0x9a],
+        );
+        // pushq %r10; popq %rbp
+        assert_encoding(
+            |s| {
+                s.push(reg("%r10"));
+                s.pop(reg("%rbp"))
+            },
+            &[0x41, 0x52, 0x5d],
+        );
+        // call *%r10
+        assert_encoding(|s| s.call_indirect(reg("%r10")), &[0x41, 0xff, 0xd2]);
+    }
+
+    #[test]
+    fn rip_relative_fixups() {
+        let mut section = Section::default();
+        // movl $5, a(%rip)
+        section.mov(
+            4,
+            Operand::Imm(5),
+            Operand::Rip(Target::Symbol("a".to_string()), false),
+        );
+        // movq b@GOTPCREL(%rip), %r10
+        section.mov(
+            8,
+            Operand::Rip(Target::Symbol("b".to_string()), true),
+            reg("%r10"),
+        );
+
+        assert_eq!(
+            section.bytes,
+            &[0xc7, 0x05, 0, 0, 0, 0, 0x05, 0, 0, 0, 0x4c, 0x8b, 0x15, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            section.fixups,
+            vec![
+                Fixup {
+                    offset: 2,
+                    target: Target::Symbol("a".to_string()),
+                    kind: RelocKind::Pc32,
+                    addend: -8
+                },
+                Fixup {
+                    offset: 13,
+                    target: Target::Symbol("b".to_string()),
+                    kind: RelocKind::GotPcRel,
+                    addend: -4
+                },
+            ]
+        );
+    }
+}
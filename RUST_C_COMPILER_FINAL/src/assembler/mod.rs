@@ -0,0 +1,736 @@
+//! Integrated assembler that encodes [LIR](crate::compiler::codegen::lir::Lir) directly into x86-64
+//! machine-code and writes it as an [ELF](elf) relocatable object-file, so that no external assembler is needed
+
+pub mod elf;
+pub mod encoder;
+
+use crate::compiler::codegen::{lir::*, register::*};
+use crate::compiler::common::{token::TokenKind, types::*};
+use crate::compiler::typechecker::{align_by, mir::expr::ValueKind};
+use encoder::*;
+
+use std::collections::HashMap;
+
+/// Anything that can be referenced by an instruction or data-directive
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Target {
+    /// Local jump-label `L{n}`
+    Label(usize),
+    /// String-literal label `LS{n}`
+    String(usize),
+    /// Named symbol that might be defined in another object-file
+    Symbol(String),
+}
+
+/// How a reference is filled in by the linker
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RelocKind {
+    Abs32,
+    Abs64,
+    Pc32,
+    Plt32,
+    GotPcRel,
+}
+
+/// A placeholder in a section that has to be filled in once the address of its target is known
+#[derive(Debug, PartialEq)]
+pub struct Fixup {
+    pub offset: usize,
+    pub target: Target,
+    pub kind: RelocKind,
+    pub addend: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SectionKind {
+    Text,
+    Data,
+    Bss,
+    Rodata,
+}
+
+#[derive(Debug, Default)]
+pub struct Section {
+    pub bytes: Vec<u8>,
+    pub fixups: Vec<Fixup>,
+
+    /// Offsets of all local labels defined in this section
+    pub labels: HashMap<Target, usize>,
+}
+impl Section {
+    fn align(&mut self, alignment: usize) {
+        self.bytes.resize(align_by(self.bytes.len(), alignment), 0);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolKind {
+    Func,
+    Object,
+    NoType,
+}
+
+/// Named symbol that ends up in the symbol-table of the object-file
+#[derive(Debug, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    /// Section and offset of the symbol, `None` if only referenced but not defined
+    pub location: Option<(SectionKind, usize)>,
+    pub is_global: bool,
+    pub kind: SymbolKind,
+}
+
+/// What a relocation refers to once all local labels are resolved
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RelocTarget {
+    Section(SectionKind),
+    Symbol(usize),
+}
+
+/// Reference that can only be resolved by the linker
+#[derive(Debug, PartialEq)]
+pub struct Relocation {
+    pub offset: usize,
+    pub target: RelocTarget,
+    pub kind: RelocKind,
+    pub addend: i64,
+}
+
+/// The assembled sections with all references to other sections or symbols turned into relocations
+pub struct ObjectFile {
+    pub sections: Vec<(SectionKind, Section, Vec<Relocation>)>,
+    pub symbols: Vec<Symbol>,
+}
+
+// global variable whose initializers are collected to decide if it belongs into `.bss`
+struct GlobalVar {
+    name: String,
+    is_pointer: bool,
+    is_static: bool,
+    inits: Vec<(Type, StaticRegister)>,
+}
+
+struct Assembler {
+    sections: HashMap<SectionKind, Section>,
+    current: SectionKind,
+    symbols: Vec<Symbol>,
+    pending_global: Option<GlobalVar>,
+}
+
+/// Assembles register-allocated LIR into the bytes of an ELF relocatable object-file
+pub fn assemble(lir: Vec<Lir>) -> Vec<u8> {
+    let mut assembler = Assembler::new();
+
+    for instr in lir {
+        assembler.visit(instr);
+    }
+
+    elf::write(assembler.finish())
+}
+
+impl Assembler {
+    fn new() -> Self {
+        Assembler {
+            sections: [
+                SectionKind::Text,
+                SectionKind::Data,
+                SectionKind::Bss,
+                SectionKind::Rodata,
+            ]
+            .into_iter()
+            .map(|kind| (kind, Section::default()))
+            .collect(),
+            current: SectionKind::Text,
+            symbols: Vec::new(),
+            pending_global: None,
+        }
+    }
+    fn section(&mut self) -> &mut Section {
+        self.sections.get_mut(&self.current).unwrap()
+    }
+    fn define_symbol(&mut self, name: String, is_global: bool, kind: SymbolKind) {
+        let location = Some((self.current, self.section().bytes.len()));
+        self.symbols.push(Symbol {
+            name,
+            location,
+            is_global,
+            kind,
+        });
+    }
+    fn define_label(&mut self, label: Target) {
+        let section = self.section();
+        section.labels.insert(label, section.bytes.len());
+    }
+
+    fn visit(&mut self, instr: Lir) {
+        if let Lir::GlobalInit(ty, value) = instr {
+            return self
+                .pending_global
+                .as_mut()
+                .expect("initializer always follows declaration")
+                .inits
+                .push((ty, value));
+        }
+        self.declare_pending_global();
+
+        match instr {
+            Lir::GlobalDeclaration(name, is_pointer, is_static) => {
+                self.pending_global = Some(GlobalVar {
+                    name,
+                    is_pointer,
+                    is_static,
+                    inits: Vec::new(),
+                })
+            }
+            Lir::GlobalInit(..) => unreachable!("handled above"),
+            Lir::StringDeclaration(label_index, s) => {
+                let rodata = self.sections.get_mut(&SectionKind::Rodata).unwrap();
+                rodata
+                    .labels
+                    .insert(Target::String(label_index), rodata.bytes.len());
+                rodata.bytes.extend(s.bytes());
+                rodata.bytes.push(0);
+            }
+            Lir::LabelDefinition(label_index) => self.define_label(Target::Label(label_index)),
+            Lir::Jmp(label_index) => self.section().jmp(label_index),
+            Lir::JmpCond(cond, label_index) => self.section().jmp_cond(cond, label_index),
+            Lir::JmpTable(index, table, label_index) => {
+                let (index, table) = (operand(&index), operand(&table));
+                let section = self.section();
+
+                section.lea(8, Operand::Rip(Target::Label(label_index), false), table.clone());
+                let Operand::Reg(base) = table else {
+                    unreachable!("table-address has to be in register")
+                };
+                let Operand::Reg(index_reg) = index else {
+                    unreachable!("table-index has to be in register")
+                };
+                section.extend(
+                    true,
+                    4,
+                    8,
+                    Operand::Mem {
+                        base,
+                        index: Some((index_reg, 4)),
+                        disp: 0,
+                    },
+                    index.clone(),
+                );
+                section.alu(AluOp::Add, 8, table, index.clone());
+                section.jmp_indirect(index);
+            }
+            Lir::JumpTableDeclaration(label_index, labels) => {
+                let rodata = self.sections.get_mut(&SectionKind::Rodata).unwrap();
+                rodata.align(4);
+                rodata
+                    .labels
+                    .insert(Target::Label(label_index), rodata.bytes.len());
+
+                for label in labels {
+                    rodata.label_offset(label, label_index);
+                }
+            }
+            Lir::Push(reg) => {
+                let reg = operand(&reg);
+                self.section().push(reg)
+            }
+            Lir::Pop(reg) => {
+                let reg = operand(&reg);
+                self.section().pop(reg)
+            }
+            Lir::Call(reg) => match reg {
+                Register::Label(label) => self.section().call(target(&label)),
+                mut reg => {
+                    reg.set_value_kind(ValueKind::Rvalue);
+                    let reg = operand(&reg);
+                    self.section().call_indirect(reg)
+                }
+            },
+            Lir::TailJmp(reg) => match reg {
+                Register::Label(label) => self.section().tail_jmp(target(&label)),
+                mut reg => {
+                    reg.set_value_kind(ValueKind::Rvalue);
+                    let reg = operand(&reg);
+                    self.section().jmp_indirect(reg)
+                }
+            },
+            Lir::FuncSetup(name, stack_size, is_static) => {
+                self.current = SectionKind::Text;
+                self.define_symbol(name, !is_static, SymbolKind::Func);
+
+                let section = self.section();
+                section.push(Operand::Reg(RBP));
+                section.mov(8, Operand::Reg(RSP), Operand::Reg(RBP));
+                // have to keep stack 16B aligned
+                if stack_size > 0 {
+                    let size = align_by(stack_size, 16) as i64;
+                    section.alu(AluOp::Sub, 8, Operand::Imm(size), Operand::Reg(RSP));
+                }
+            }
+            Lir::FuncTeardown(_, true) => {
+                let section = self.section();
+                section.mov(8, Operand::Reg(RBP), Operand::Reg(RSP));
+                section.pop(Operand::Reg(RBP));
+            }
+            Lir::FuncTeardown(stack_size, false) => {
+                let section = self.section();
+                if stack_size > 0 {
+                    let size = align_by(stack_size, 16) as i64;
+                    section.alu(AluOp::Add, 8, Operand::Imm(size), Operand::Reg(RSP));
+                }
+                section.pop(Operand::Reg(RBP));
+                section.ret();
+            }
+            Lir::SubSp(value) => {
+                self.section()
+                    .alu(AluOp::Sub, 8, Operand::Imm(value as i64), Operand::Reg(RSP))
+            }
+            Lir::AddSp(value) => {
+                self.section()
+                    .alu(AluOp::Add, 8, Operand::Imm(value as i64), Operand::Reg(RSP))
+            }
+            Lir::Mov(from, to) => {
+                let size = size(&to.get_type());
+                self.section().mov(size, operand(&from), operand(&to))
+            }
+            Lir::Movs(from, to) => {
+                let (from_size, to_size) = (size(&from.get_type()), size(&to.get_type()));
+                self.section()
+                    .extend(true, from_size, to_size, operand(&from), operand(&to))
+            }
+            Lir::Movz(from, to) => {
+                let (from_size, to_size) = (size(&from.get_type()), size(&to.get_type()));
+                self.section()
+                    .extend(false, from_size, to_size, operand(&from), operand(&to))
+            }
+            Lir::Cmp(left, right) => self.alu(AluOp::Cmp, left, right),
+            Lir::Sub(left, right) => self.alu(AluOp::Sub, left, right),
+            Lir::Add(left, right) => self.alu(AluOp::Add, left, right),
+            Lir::Xor(left, right) => self.alu(AluOp::Xor, left, right),
+            Lir::Or(left, right) => self.alu(AluOp::Or, left, right),
+            Lir::And(left, right) => self.alu(AluOp::And, left, right),
+            Lir::Imul(left, right) => {
+                let size = size(&right.get_type());
+                self.section().imul(size, operand(&left), operand(&right))
+            }
+            Lir::Div(reg) => {
+                let ty = reg.get_type();
+                let size = size(&ty);
+                let section = self.section();
+                if ty.is_unsigned() {
+                    section.mov(4, Operand::Imm(0), Operand::Reg(RDX));
+                } else {
+                    section.sign_extend_rax(size);
+                }
+                section.unary(if ty.is_unsigned() { 6 } else { 7 }, size, operand(&reg));
+            }
+            Lir::Shift(direction, left, right) => {
+                let size = size(&right.get_type());
+                let extension = if direction == "l" { 4 } else { 7 };
+                self.section()
+                    .shift(extension, size, operand(&left), operand(&right))
+            }
+            Lir::Load(from, to) => {
+                let size = size(&to.get_type());
+                self.section().lea(size, operand(&from), operand(&to))
+            }
+            Lir::Set(operator) => self.section().set(&operator[3..], Operand::Reg(RAX)),
+            Lir::Rep => self.section().rep_stosb(),
+            Lir::Not(reg) => {
+                let size = size(&reg.get_type());
+                self.section().unary(2, size, operand(&reg))
+            }
+            Lir::Neg(reg) => {
+                let size = size(&reg.get_type());
+                self.section().unary(3, size, operand(&reg))
+            }
+            Lir::FileDeclaration(..) | Lir::Loc(..) | Lir::DebugInfo(_) => {
+                unreachable!("debug-information is only emitted as assembly")
+            }
+            Lir::SaveRegs | Lir::RestoreRegs => unreachable!("will be replaced in register-allocation"),
+        }
+    }
+    fn alu(&mut self, op: AluOp, left: Register, right: Register) {
+        let size = size(&right.get_type());
+        self.section().alu(op, size, operand(&left), operand(&right))
+    }
+
+    // global variables that are only zero-initialized don't take up space in the object-file
+    fn declare_pending_global(&mut self) {
+        let Some(GlobalVar {
+            name,
+            is_pointer,
+            is_static,
+            inits,
+        }) = self.pending_global.take()
+        else {
+            return;
+        };
+        let is_zero = inits
+            .iter()
+            .all(|(ty, value)| matches!(value, StaticRegister::Literal(n, _) if ty.is_void() || n.is_zero()));
+
+        self.current = if is_zero {
+            SectionKind::Bss
+        } else {
+            SectionKind::Data
+        };
+        if is_pointer {
+            self.section().align(4);
+        }
+        self.define_symbol(name, !is_static, SymbolKind::Object);
+
+        for (ty, value) in inits {
+            self.global_init(ty, value)
+        }
+    }
+    fn global_init(&mut self, ty: Type, value: StaticRegister) {
+        let section = self.section();
+        match value {
+            // `.zero n`
+            StaticRegister::Literal(n, _) if ty.is_void() => {
+                let len = section.bytes.len() + n.try_i64().expect("size is positive") as usize;
+                section.bytes.resize(len, 0);
+            }
+            StaticRegister::Literal(n, _) => section.emit_imm(literal_value(&n), size(&ty)),
+            StaticRegister::Label(label) => section.data_reloc(target(&label), size(&ty), 0),
+            StaticRegister::LabelOffset(label, offset, op) => {
+                let addend = match op {
+                    TokenKind::Plus => offset,
+                    TokenKind::Minus => -offset,
+                    _ => unreachable!(),
+                };
+                section.data_reloc(target(&label), size(&ty), addend)
+            }
+        }
+    }
+
+    /// Resolves all references to local labels and turns the remaining ones into relocations
+    fn finish(mut self) -> ObjectFile {
+        self.declare_pending_global();
+
+        let labels: HashMap<Target, (SectionKind, usize)> = self
+            .sections
+            .iter()
+            .flat_map(|(kind, section)| {
+                section
+                    .labels
+                    .iter()
+                    .map(|(label, offset)| (label.clone(), (*kind, *offset)))
+            })
+            .collect();
+
+        let mut sections = Vec::new();
+        for kind in [
+            SectionKind::Text,
+            SectionKind::Data,
+            SectionKind::Bss,
+            SectionKind::Rodata,
+        ] {
+            let mut section = self.sections.remove(&kind).unwrap();
+            let mut relocations = Vec::new();
+
+            for fixup in std::mem::take(&mut section.fixups) {
+                let (target, addend) = match fixup.target {
+                    Target::Symbol(name) => (RelocTarget::Symbol(self.symbol_index(name)), fixup.addend),
+                    label => {
+                        let (label_section, offset) = *labels
+                            .get(&label)
+                            .unwrap_or_else(|| unreachable!("label {:?} is never defined", label));
+
+                        // pc-relative references inside the same section are known at assembly-time
+                        if label_section == kind && fixup.kind == RelocKind::Pc32 {
+                            let value = offset as i64 + fixup.addend - fixup.offset as i64;
+                            section.bytes[fixup.offset..fixup.offset + 4]
+                                .copy_from_slice(&(value as i32).to_le_bytes());
+                            continue;
+                        }
+                        (RelocTarget::Section(label_section), offset as i64 + fixup.addend)
+                    }
+                };
+                relocations.push(Relocation {
+                    offset: fixup.offset,
+                    target,
+                    kind: fixup.kind,
+                    addend,
+                });
+            }
+            sections.push((kind, section, relocations));
+        }
+
+        ObjectFile {
+            sections,
+            symbols: self.symbols,
+        }
+    }
+    // returns index of symbol, declaring it as undefined if not defined in this file
+    fn symbol_index(&mut self, name: String) -> usize {
+        if let Some(index) = self.symbols.iter().position(|symbol| symbol.name == name) {
+            index
+        } else {
+            self.symbols.push(Symbol {
+                name,
+                location: None,
+                is_global: true,
+                kind: SymbolKind::NoType,
+            });
+            self.symbols.len() - 1
+        }
+    }
+}
+
+impl Section {
+    // address of target stored in data
+    fn data_reloc(&mut self, target: Target, size: usize, addend: i64) {
+        let kind = match size {
+            8 => RelocKind::Abs64,
+            4 => RelocKind::Abs32,
+            _ => unreachable!("addresses have to be 4 or 8 bytes"),
+        };
+        self.fixups.push(Fixup {
+            offset: self.bytes.len(),
+            target,
+            kind,
+            addend,
+        });
+        self.bytes.resize(self.bytes.len() + size, 0);
+    }
+}
+
+// size of operands in bytes depending on the instruction-suffix of the type
+fn size(ty: &Type) -> usize {
+    match ty.suffix().as_str() {
+        "b" => 1,
+        "w" => 2,
+        "l" => 4,
+        "q" => 8,
+        suffix => unreachable!("invalid suffix '{}'", suffix),
+    }
+}
+
+fn literal_value(n: &LiteralKind) -> i64 {
+    match n {
+        LiteralKind::Signed(n) => *n,
+        LiteralKind::Unsigned(n) => *n as i64,
+    }
+}
+
+fn target(label: &LabelRegister) -> Target {
+    match label {
+        LabelRegister::String(index) => Target::String(*index),
+        LabelRegister::Var(name, ..) => Target::Symbol(name.clone()),
+    }
+}
+
+// converts register into an instruction-operand the same way as `Register::name()` does
+fn operand(reg: &Register) -> Operand {
+    match reg {
+        Register::Temp(temp) => match (&temp.reg, &temp.value_kind) {
+            (Some(TempKind::Scratch(scratch)), ValueKind::Rvalue) => {
+                Operand::Reg(reg_number(scratch.base_name()))
+            }
+            (Some(TempKind::Scratch(scratch)), ValueKind::Lvalue) => Operand::Mem {
+                base: reg_number(scratch.base_name()),
+                index: None,
+                disp: 0,
+            },
+            (Some(TempKind::Spilled(stack)), _) => stack_operand(stack),
+            _ => unreachable!("register should always be filled by allocator"),
+        },
+        Register::Stack(stack) => stack_operand(stack),
+        Register::Label(label) => Operand::Rip(target(label), matches!(label, LabelRegister::Var(.., true))),
+        Register::Arg(arg) => Operand::Reg(reg_number(arg.reg.base_name())),
+        Register::Return(_) => Operand::Reg(RAX),
+        Register::Literal(n, ty) => Operand::Imm(literal_value(&n.wrap(ty))),
+        Register::Void => unreachable!(),
+    }
+}
+fn stack_operand(stack: &StackRegister) -> Operand {
+    Operand::Mem {
+        base: RBP,
+        index: None,
+        disp: stack.offset() as i32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    fn setup(input: &str) -> ObjectFile {
+        let mut session = Session::new(CompileOptions::new());
+        session.add_file("", input);
+        let lir = session.lir(std::path::Path::new("")).unwrap();
+
+        let mut assembler = Assembler::new();
+        for instr in lir {
+            assembler.visit(instr);
+        }
+        assembler.finish()
+    }
+    fn section(object: &ObjectFile, kind: SectionKind) -> &(SectionKind, Section, Vec<Relocation>) {
+        object.sections.iter().find(|(k, ..)| *k == kind).unwrap()
+    }
+
+    #[test]
+    fn sections_and_symbols() {
+        let actual = setup(
+            "
+int zero;
+static int three = 3;
+char *s = \"hi\";
+int main() { return zero + three; }",
+        );
+
+        let (_, bss, _) = section(&actual, SectionKind::Bss);
+        let (_, data, data_relocs) = section(&actual, SectionKind::Data);
+        let (_, rodata, _) = section(&actual, SectionKind::Rodata);
+
+        assert_eq!(bss.bytes, vec![0; 4]);
+        assert_eq!(rodata.bytes, b"hi\0");
+        // `three` followed by 4-byte aligned pointer to the string-literal
+        assert_eq!(data.bytes, vec![3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            data_relocs,
+            &vec![Relocation {
+                offset: 4,
+                target: RelocTarget::Section(SectionKind::Rodata),
+                kind: RelocKind::Abs64,
+                addend: 0
+            }]
+        );
+
+        assert_eq!(
+            actual
+                .symbols
+                .iter()
+                .map(|s| (s.name.as_str(), s.location, s.is_global))
+                .collect::<Vec<_>>(),
+            vec![
+                ("zero", Some((SectionKind::Bss, 0)), true),
+                ("three.0", Some((SectionKind::Data, 0)), false),
+                ("s", Some((SectionKind::Data, 4)), true),
+                ("main", Some((SectionKind::Text, 0)), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn resolves_local_labels() {
+        let actual = setup(
+            "
+int puts(char *);
+int foo(int n) {
+    for (int i = 0; i < 3; i++) puts(\"hi\");
+    switch (n) { case 1: case 2: case 3: case 4: return 1; }
+    return 0;
+}",
+        );
+        let (_, text, text_relocs) = section(&actual, SectionKind::Text);
+        let (_, _, rodata_relocs) = section(&actual, SectionKind::Rodata);
+
+        // only references that leave the text-section are kept as relocations:
+        // the string-literal, the jump-table and the undefined function
+        assert_eq!(text_relocs.len(), 3);
+        assert!(text_relocs.iter().all(|reloc| match reloc.target {
+            RelocTarget::Section(kind) => kind == SectionKind::Rodata && reloc.kind == RelocKind::Pc32,
+            RelocTarget::Symbol(i) =>
+                actual.symbols[i].name == "puts" && actual.symbols[i].location.is_none(),
+        }));
+
+        // jump-table entries point into the text-section
+        assert_eq!(rodata_relocs.len(), 4);
+        assert!(rodata_relocs
+            .iter()
+            .all(|reloc| reloc.target == RelocTarget::Section(SectionKind::Text)
+                && (reloc.addend as usize) < text.bytes.len() + 16));
+    }
+
+    fn has_tool(name: &str) -> bool {
+        std::process::Command::new(name).arg("--version").output().is_ok()
+    }
+
+    // links the object-file with `cc` and returns the exit-code and output of running it
+    fn link_and_run(object_file: &std::path::Path, name: &str) -> (Option<i32>, String) {
+        let exe_file = std::env::temp_dir().join(name);
+        let status = std::process::Command::new("cc")
+            .arg("-Wl,-z,noexecstack")
+            .arg(object_file)
+            .arg("-o")
+            .arg(&exe_file)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let output = std::process::Command::new(&exe_file).output().unwrap();
+        (output.status.code(), String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    // only runs where a native toolchain is installed
+    #[test]
+    fn matches_system_assembler() {
+        if cfg!(target_os = "macos") || !has_tool("cc") || !has_tool("as") {
+            return;
+        }
+        let programs = [
+            "
+int printf(const char *fmt, ...);
+int fib(int n) { return n < 2 ? n : fib(n - 1) + fib(n - 2); }
+int main() {
+    for (int i = 0; i < 10; i++) printf(\"%d \", fib(i));
+    printf(\"\\n\");
+    return fib(7);
+}",
+            "
+int printf(const char *fmt, ...);
+static char *names[] = {\"zero\", \"one\", \"two\"};
+long total;
+struct pair { int a; long b; } pairs[3] = {{1, 2}, {3, 4}, {5, 6}};
+int main() {
+    for (int i = 0; i < 3; i++) {
+        total += pairs[i].a * pairs[i].b;
+        printf(\"%s %ld\\n\", names[i], total);
+    }
+    return total > 40 ? 3 : 4;
+}",
+            "
+int puts(const char *);
+int classify(int n) {
+    switch (n) { case 0: return 10; case 1: case 2: return 20; case 3: return 30; case 4: return 40; default: return -1; }
+}
+int main() {
+    int sum = 0;
+    unsigned char c = 250;
+    while (c != 4) { c++; sum += classify(c % 6); }
+    if (sum == 70) puts(\"ok\");
+    return sum & 0xff;
+}",
+        ];
+
+        let dir = std::env::temp_dir();
+        for (i, program) in programs.iter().enumerate() {
+            let mut session = Session::new(CompileOptions::new());
+            session.add_file("main.c", *program);
+            let file = std::path::Path::new("main.c");
+
+            let asm_file = dir.join(format!("wrecc_as_test_{}.s", i));
+            let system_object = dir.join(format!("wrecc_as_test_{}.o", i));
+            std::fs::write(&asm_file, session.assembly(file).unwrap() + "\n").unwrap();
+            let status = std::process::Command::new("as")
+                .arg(&asm_file)
+                .arg("-o")
+                .arg(&system_object)
+                .status()
+                .unwrap();
+            assert!(status.success());
+
+            let integrated_object = dir.join(format!("wrecc_integrated_as_test_{}.o", i));
+            std::fs::write(&integrated_object, assemble(session.lir(file).unwrap())).unwrap();
+
+            let expected = link_and_run(&system_object, &format!("wrecc_as_test_{}", i));
+            let actual = link_and_run(&integrated_object, &format!("wrecc_integrated_as_test_{}", i));
+            assert_eq!(actual, expected, "program {}", i);
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! Formats C source-files in a consistent style using the [formatter](wrecc_compiler::formatter).<br>
+//! Prints the formatted files to stdout unless they should be overwritten or only checked.
+
+use wrecc_compiler::compiler::common::error::WreccError;
+use wrecc_compiler::formatter::{format, BraceStyle, FormatOptions};
+
+use std::path::PathBuf;
+
+static USAGE: &str = "\
+usage: wrecc-fmt [--indent=<n>] [--use-tabs] [--brace-style=<style>] [--line-width=<n>]
+                 [--write | --check] [--no-color] <files>";
+
+static HELP: &str = "
+options:
+  --indent=<n>             Indent by <n> columns per level (default: 4)
+  --use-tabs               Indent with tabs instead of spaces
+  --brace-style=<style>    Place opening braces on the 'same-line' or the 'next-line' (default: same-line)
+  --line-width=<n>         Break lines longer than <n> columns if possible (default: 80)
+  -w | --write             Overwrite the files with their formatted source
+  --check                  Only list the files that aren't formatted, exit with 1 if there are any
+  --no-color               Print errors without color
+  -h | --help              Print this message";
+
+#[derive(PartialEq)]
+enum Mode {
+    Print,
+    Write,
+    Check,
+}
+
+struct Options {
+    format: FormatOptions,
+    mode: Mode,
+    no_color: bool,
+    files: Vec<PathBuf>,
+}
+
+fn parse_number(arg: &str, value: &str) -> Result<usize, String> {
+    value.parse::<usize>().ok().filter(|n| *n > 0).ok_or(format!(
+        "invalid value '{}' for '{}', expected a positive number",
+        value, arg
+    ))
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Options, Vec<String>> {
+    let mut options = Options {
+        format: FormatOptions::new(),
+        mode: Mode::Print,
+        no_color: false,
+        files: Vec::new(),
+    };
+    let mut errors = Vec::new();
+
+    for arg in args {
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (arg.as_str(), None),
+        };
+        let result = match (name, value) {
+            ("--indent", Some(value)) => {
+                parse_number(name, value).map(|n| options.format = options.format.clone().indent_width(n))
+            }
+            ("--line-width", Some(value)) => {
+                parse_number(name, value).map(|n| options.format = options.format.clone().line_width(n))
+            }
+            ("--brace-style", Some(value)) => match BraceStyle::from_name(value) {
+                Some(style) => {
+                    options.format = options.format.clone().brace_style(style);
+                    Ok(())
+                }
+                None => Err(format!(
+                    "invalid brace-style '{}', expected 'same-line' or 'next-line'",
+                    value
+                )),
+            },
+            ("--use-tabs", None) => {
+                options.format = options.format.clone().use_tabs(true);
+                Ok(())
+            }
+            ("-w" | "--write", None) => {
+                options.mode = Mode::Write;
+                Ok(())
+            }
+            ("--check", None) => {
+                options.mode = Mode::Check;
+                Ok(())
+            }
+            ("--no-color", None) => {
+                options.no_color = true;
+                Ok(())
+            }
+            ("-h" | "--help", None) => {
+                println!("{}\n{}", USAGE, HELP);
+                std::process::exit(0);
+            }
+            _ if arg.starts_with('-') => Err(format!("unrecognized option '{}'", arg)),
+            _ => {
+                options.files.push(PathBuf::from(arg));
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            errors.push(e);
+        }
+    }
+
+    if options.files.is_empty() {
+        errors.push("no input files".to_string());
+    }
+    if errors.is_empty() {
+        Ok(options)
+    } else {
+        Err(errors)
+    }
+}
+
+// Formats all files, returns whether every file was already formatted
+fn run(options: &Options) -> Result<bool, Vec<WreccError>> {
+    let mut errors = Vec::new();
+    let mut formatted = true;
+
+    for file in &options.files {
+        let result = std::fs::read_to_string(file)
+            .map_err(|_| WreccError::Sys(format!("could not find file: '{}'", file.display())))
+            .and_then(|source| Ok((format(file, &source, &options.format)?, source)));
+
+        match result {
+            Ok((output, source)) => match options.mode {
+                Mode::Print => print!("{}", output),
+                Mode::Check if output != source => {
+                    println!("{}", file.display());
+                    formatted = false;
+                }
+                Mode::Write if output != source => {
+                    if std::fs::write(file, output).is_err() {
+                        errors.push(WreccError::Sys(format!(
+                            "could not write to file '{}'",
+                            file.display()
+                        )));
+                    }
+                }
+                Mode::Check | Mode::Write => (),
+            },
+            Err(e) => errors.push(e),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(formatted)
+    } else {
+        Err(errors)
+    }
+}
+
+fn main() {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(errors) => {
+            for e in errors {
+                eprintln!("wrecc-fmt: {}", e);
+            }
+            eprintln!("{}", USAGE);
+            std::process::exit(1);
+        }
+    };
+
+    match run(&options) {
+        Ok(true) => (),
+        Ok(false) => std::process::exit(1),
+        Err(errors) => {
+            for e in errors {
+                e.print(options.no_color);
+            }
+            std::process::exit(1);
+        }
+    }
+}
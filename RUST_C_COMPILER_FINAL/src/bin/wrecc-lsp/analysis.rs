@@ -0,0 +1,645 @@
+//! Runs the compiler-pipeline up to the typechecker on a document and answers the editor's
+//! queries using the recorded [SymbolIndex]
+
+use wrecc_compiler::compiler::codegen::register::Register;
+use wrecc_compiler::compiler::common::{environment::*, error::*, token::*, types::*};
+use wrecc_compiler::compiler::typechecker::TypeChecker;
+use wrecc_compiler::{CompileOptions, Session};
+
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// A zero-based position in a document, characters are counted in bytes
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+/// A range of characters in a single line
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Range {
+    pub start: Position,
+    pub length: usize,
+}
+impl Range {
+    fn of(token: &Token) -> Range {
+        Range {
+            start: Position {
+                line: token.line_index.max(1) as usize - 1,
+                character: token.column.max(1) as usize - 1,
+            },
+            length: match &token.kind {
+                TokenKind::Ident(name) => name.len(),
+                kind => kind.to_string().len(),
+            },
+        }
+    }
+    fn contains(&self, pos: Position) -> bool {
+        self.start.line == pos.line
+            && self.start.character <= pos.character
+            && pos.character <= self.start.character + self.length
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    pub range: Range,
+    pub message: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SymbolKind {
+    Function,
+    Struct,
+    Union,
+    Enum,
+    Field,
+    EnumMember,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct DocumentSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub range: Range,
+    pub detail: String,
+    pub children: Vec<DocumentSymbol>,
+}
+
+pub struct Analysis {
+    pub diagnostics: Vec<Diagnostic>,
+    // only exists if the document could be parsed
+    pub index: Option<Index>,
+}
+
+/// The identifiers of an analyzed document
+pub struct Index(SymbolIndex);
+
+/// Preprocesses, parses and typechecks the document as if it was the file at `path`
+pub fn analyze(path: &Path, text: &str, include_dirs: &[PathBuf]) -> Analysis {
+    let options = include_dirs
+        .iter()
+        .fold(CompileOptions::new(), |options, dir| options.include_dir(dir));
+    let mut session = Session::new(options);
+    session.add_file(path, text);
+
+    let errors = match session.hir(path) {
+        Ok(parse_tree) => {
+            let (result, index) = TypeChecker::new().check_indexed(parse_tree);
+
+            return Analysis {
+                diagnostics: diagnostics(path, text, result.err().unwrap_or_default()),
+                index: Some(Index(index)),
+            };
+        }
+        Err(WreccError::Comp(errors)) => errors,
+        Err(WreccError::Sys(message)) => {
+            return Analysis {
+                diagnostics: vec![Diagnostic {
+                    range: start_of_file(),
+                    message,
+                }],
+                index: None,
+            }
+        }
+        Err(WreccError::Cli(messages)) => {
+            return Analysis {
+                diagnostics: messages
+                    .into_iter()
+                    .map(|message| Diagnostic {
+                        range: start_of_file(),
+                        message,
+                    })
+                    .collect(),
+                index: None,
+            }
+        }
+    };
+
+    Analysis {
+        diagnostics: diagnostics(path, text, errors),
+        index: None,
+    }
+}
+
+fn start_of_file() -> Range {
+    Range {
+        start: Position {
+            line: 0,
+            character: 0,
+        },
+        length: 0,
+    }
+}
+
+fn diagnostics(path: &Path, text: &str, errors: Vec<Error>) -> Vec<Diagnostic> {
+    errors
+        .into_iter()
+        .flat_map(Error::flatten_multiple)
+        .map(|error| {
+            let message = error.kind.message();
+
+            if error.line_index < 1 {
+                // errors at the end of file don't have a location
+                let line = text.lines().count().saturating_sub(1);
+                let character = text.lines().last().map_or(0, str::len);
+
+                Diagnostic {
+                    range: Range {
+                        start: Position { line, character },
+                        length: 0,
+                    },
+                    message,
+                }
+            } else if error.filename != path {
+                Diagnostic {
+                    range: start_of_file(),
+                    message: format!("in included file '{}': {}", error.filename.display(), message),
+                }
+            } else {
+                let column = error.column.max(1) as usize - 1;
+                let word = error.line_string.get(column..).map_or(0, |rest| {
+                    rest.find(|c: char| !c.is_alphanumeric() && c != '_')
+                        .unwrap_or(rest.len())
+                });
+
+                Diagnostic {
+                    range: Range {
+                        start: Position {
+                            line: error.line_index as usize - 1,
+                            character: column,
+                        },
+                        length: word.max(1),
+                    },
+                    message,
+                }
+            }
+        })
+        .collect()
+}
+
+/// The name of the struct, union or enum a tag was declared with
+fn tag_kind(tag: &Tags) -> &'static str {
+    match tag.get_kind() {
+        TokenKind::Struct => "struct",
+        TokenKind::Union => "union",
+        _ => "enum",
+    }
+}
+
+fn symbol_kind(symbol: &Symbol) -> &'static str {
+    if symbol.is_typedef() {
+        "typedef"
+    } else if symbol.qtype.ty.is_func() {
+        "function"
+    } else if matches!(symbol.reg, Some(Register::Literal(..))) {
+        // only enum-constants already have their register when being declared
+        "enum constant"
+    } else {
+        "variable"
+    }
+}
+
+// the members of a struct or union with their types, or the constants of an enum with their values
+fn members(tag: &Tags) -> Vec<(Token, String)> {
+    match tag {
+        Tags::Aggregate(s) => s
+            .get_members()
+            .iter()
+            .map(|(qtype, name)| (name.clone(), qtype.to_string()))
+            .collect(),
+        Tags::Enum(constants) => constants
+            .iter()
+            .map(|(name, value)| (name.clone(), value.to_string()))
+            .collect(),
+    }
+}
+
+impl Index {
+    fn in_file<'a, T>(
+        entries: &'a [IndexEntry<T>],
+        path: &'a Path,
+    ) -> impl Iterator<Item = &'a IndexEntry<T>> + 'a {
+        entries.iter().filter(move |entry| entry.token.filename == path)
+    }
+    fn symbol_at(&self, path: &Path, pos: Position) -> Option<&IndexEntry<Symbol>> {
+        self.0
+            .symbols
+            .iter()
+            .find(|entry| entry.token.filename == path && Range::of(&entry.token).contains(pos))
+    }
+    fn tag_at(&self, path: &Path, pos: Position) -> Option<&IndexEntry<Tags>> {
+        self.0
+            .tags
+            .iter()
+            .find(|entry| entry.token.filename == path && Range::of(&entry.token).contains(pos))
+    }
+    // the last declaration is the definition if the tag was defined
+    fn tag_definition(&self, tag: &Rc<RefCell<Tags>>) -> Option<&Token> {
+        self.0
+            .tags
+            .iter()
+            .filter(|entry| entry.is_declaration && Rc::ptr_eq(&entry.target, tag))
+            .map(|entry| &entry.token)
+            .next_back()
+    }
+
+    /// Returns the location where the identifier at the position was declared
+    pub fn definition(&self, path: &Path, pos: Position) -> Option<(PathBuf, Range)> {
+        if let Some(entry) = self.symbol_at(path, pos) {
+            let token = &entry.target.borrow().token;
+            return Some((token.filename.clone(), Range::of(token)));
+        }
+        self.tag_at(path, pos)
+            .and_then(|entry| self.tag_definition(&entry.target))
+            .map(|token| (token.filename.clone(), Range::of(token)))
+    }
+
+    /// Describes the type of the identifier at the position as markdown
+    pub fn hover(&self, path: &Path, pos: Position) -> Option<String> {
+        if let Some(entry) = self.symbol_at(path, pos) {
+            let symbol = entry.target.borrow();
+            let kind = symbol_kind(&symbol);
+            let name = entry.token.unwrap_string();
+
+            return Some(match &symbol.reg {
+                Some(Register::Literal(value, _)) => {
+                    format!(
+                        "{} `{}`\n\n`{} = {}`",
+                        kind,
+                        name,
+                        name,
+                        value.try_i64().unwrap_or_default()
+                    )
+                }
+                _ => format!("{} `{}`\n\n```c\n{}\n```", kind, name, symbol.qtype),
+            });
+        }
+
+        self.tag_at(path, pos).map(|entry| {
+            let tag = entry.target.borrow();
+            let kind = tag_kind(&tag);
+            let separator = if kind == "enum" { " =" } else { ":" };
+            let body: String = members(&tag)
+                .into_iter()
+                .map(|(name, ty)| format!("    {}{} {}\n", name.unwrap_string(), separator, ty))
+                .collect();
+
+            format!(
+                "{} `{}`\n\n```c\n{} {} {{\n{}}}\n```",
+                kind,
+                entry.token.unwrap_string(),
+                kind,
+                entry.token.unwrap_string(),
+                body
+            )
+        })
+    }
+
+    /// Lists all functions, structs, unions and enums declared at file-scope of the document
+    pub fn document_symbols(&self, path: &Path) -> Vec<DocumentSymbol> {
+        let mut symbols = Vec::new();
+        let mut seen_functions: Vec<&SymbolRef> = Vec::new();
+        let mut seen_tags: Vec<&Rc<RefCell<Tags>>> = Vec::new();
+
+        for entry in Self::in_file(&self.0.symbols, path) {
+            let symbol = entry.target.borrow();
+            if !entry.is_declaration
+                || !entry.is_global
+                || !symbol.qtype.ty.is_func()
+                || symbol.is_typedef()
+                || seen_functions.iter().any(|seen| Rc::ptr_eq(seen, &entry.target))
+            {
+                continue;
+            }
+            seen_functions.push(&entry.target);
+
+            // the symbol's token is its definition, which is preferred over prototypes
+            let token = if symbol.token.filename == path {
+                &symbol.token
+            } else {
+                &entry.token
+            };
+            symbols.push(DocumentSymbol {
+                name: token.unwrap_string(),
+                kind: SymbolKind::Function,
+                range: Range::of(token),
+                detail: symbol.qtype.to_string(),
+                children: Vec::new(),
+            });
+        }
+
+        for entry in Self::in_file(&self.0.tags, path) {
+            if !entry.is_declaration
+                || !entry.is_global
+                || seen_tags.iter().any(|seen| Rc::ptr_eq(seen, &entry.target))
+            {
+                continue;
+            }
+            seen_tags.push(&entry.target);
+
+            let token = self.tag_definition(&entry.target).unwrap_or(&entry.token);
+            let tag = entry.target.borrow();
+            let (kind, member_kind) = match tag_kind(&tag) {
+                "struct" => (SymbolKind::Struct, SymbolKind::Field),
+                "union" => (SymbolKind::Union, SymbolKind::Field),
+                _ => (SymbolKind::Enum, SymbolKind::EnumMember),
+            };
+
+            symbols.push(DocumentSymbol {
+                name: token.unwrap_string(),
+                kind,
+                range: Range::of(token),
+                detail: tag_kind(&tag).to_string(),
+                children: members(&tag)
+                    .into_iter()
+                    .map(|(name, detail)| DocumentSymbol {
+                        name: name.unwrap_string(),
+                        kind: member_kind,
+                        range: Range::of(&name),
+                        detail,
+                        children: Vec::new(),
+                    })
+                    .collect(),
+            });
+        }
+
+        symbols.sort_by(|a, b| a.range.start.partial_cmp(&b.range.start).unwrap());
+        symbols
+    }
+
+    /// Returns the members (name and type) of the struct or union accessed by the `.` or `->`
+    /// right before the position, filtered by the partially typed member-name
+    pub fn completion(&self, path: &Path, text: &str, pos: Position) -> Vec<(String, String)> {
+        let Some(line) = text.lines().nth(pos.line) else {
+            return Vec::new();
+        };
+        let before = &line[..pos.character.min(line.len())];
+        let Some((base, accesses, prefix)) = member_chain(before) else {
+            return Vec::new();
+        };
+
+        // the closest declaration before the cursor that is still in scope is most likely the
+        // one the identifier refers to
+        let symbol = Self::in_file(&self.0.symbols, path)
+            .filter(|entry| entry.token.unwrap_string() == base)
+            .filter(|entry| Range::of(&entry.token).start <= pos)
+            .last()
+            .or_else(|| {
+                self.0
+                    .symbols
+                    .iter()
+                    .find(|entry| entry.is_global && entry.token.unwrap_string() == base)
+            });
+        let Some(symbol) = symbol else {
+            return Vec::new();
+        };
+
+        let mut qtype = symbol.target.borrow().qtype.clone();
+        for access in accesses {
+            qtype = match access {
+                Access::Deref => match qtype.ty {
+                    Type::Pointer(to) | Type::Array(to, _) => *to,
+                    _ => return Vec::new(),
+                },
+                Access::Member(name) => match &qtype.ty {
+                    Type::Struct(s) | Type::Union(s) => {
+                        match s
+                            .members()
+                            .iter()
+                            .find(|(_, member)| member.unwrap_string() == name)
+                        {
+                            Some((member_type, _)) => member_type.clone(),
+                            None => return Vec::new(),
+                        }
+                    }
+                    _ => return Vec::new(),
+                },
+            }
+        }
+
+        match &qtype.ty {
+            Type::Struct(s) | Type::Union(s) => s
+                .members()
+                .iter()
+                .map(|(qtype, name)| (name.unwrap_string(), qtype.to_string()))
+                .filter(|(name, _)| name.starts_with(&prefix))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Access {
+    // `->` and `[]`
+    Deref,
+    Member(String),
+}
+
+// splits `a[i].b->c` from the end of the line into the base identifier and the accesses applied
+// to it, with the partially typed member at the very end
+fn member_chain(before: &str) -> Option<(String, Vec<Access>, String)> {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let mut rest = before;
+
+    let prefix_start = rest.trim_end_matches(is_ident).len();
+    let prefix = rest[prefix_start..].to_string();
+    rest = &rest[..prefix_start];
+
+    let mut accesses = Vec::new();
+    loop {
+        rest = rest.trim_end();
+        if let Some(r) = rest.strip_suffix('.') {
+            rest = r;
+        } else if let Some(r) = rest.strip_suffix("->") {
+            accesses.push(Access::Deref);
+            rest = r;
+        } else {
+            break;
+        }
+        rest = rest.trim_end();
+
+        // skip over indices
+        while rest.ends_with(']') {
+            let mut depth = 0;
+            let open = rest.char_indices().rev().find(|(_, c)| {
+                match c {
+                    ']' => depth += 1,
+                    '[' => depth -= 1,
+                    _ => (),
+                }
+                depth == 0
+            })?;
+            accesses.push(Access::Deref);
+            rest = rest[..open.0].trim_end();
+        }
+
+        let start = rest.trim_end_matches(is_ident).len();
+        let name = &rest[start..];
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        accesses.push(Access::Member(name.to_string()));
+        rest = &rest[..start];
+    }
+
+    // the first identifier is the variable itself and not a member
+    match accesses.pop() {
+        Some(Access::Member(base)) => {
+            accesses.reverse();
+            Some((base, accesses, prefix))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_analysis(text: &str) -> (PathBuf, Index) {
+        let path = PathBuf::from("/test.c");
+        let analysis = analyze(&path, text, &Vec::new());
+
+        assert_eq!(analysis.diagnostics, Vec::new());
+        (path, analysis.index.unwrap())
+    }
+    fn pos(line: usize, character: usize) -> Position {
+        Position { line, character }
+    }
+
+    #[test]
+    fn diagnostics() {
+        let path = PathBuf::from("/test.c");
+        let analysis = analyze(&path, "int main() {\n  return foo;\n}", &Vec::new());
+
+        assert!(analysis.index.is_some());
+        assert_eq!(
+            analysis.diagnostics,
+            vec![Diagnostic {
+                range: Range {
+                    start: pos(1, 9),
+                    length: 3
+                },
+                message: ErrorKind::UndeclaredSymbol("foo".to_string()).message(),
+            }]
+        );
+
+        let analysis = analyze(&path, "int main() {\n  return 1\n}", &Vec::new());
+        assert!(analysis.index.is_none());
+        assert_eq!(analysis.diagnostics.len(), 1);
+        assert_eq!(analysis.diagnostics[0].range.start, pos(2, 0));
+    }
+
+    #[test]
+    fn definition_and_hover() {
+        let (path, index) = setup_analysis(
+            "struct P { int x; long y[2]; };
+enum E { A, B = 4 };
+int add(int a, int b);
+int add(int a, int b) { struct P p; return a + b + B; }",
+        );
+        let definition = |line, character| {
+            index
+                .definition(&path, pos(line, character))
+                .map(|(_, range)| (range.start.line, range.start.character, range.length))
+        };
+
+        assert_eq!(definition(3, 51), Some((1, 12, 1)));
+        assert_eq!(definition(3, 43), Some((3, 12, 1)));
+        assert_eq!(definition(3, 31), Some((0, 7, 1)));
+        assert_eq!(definition(2, 5), Some((3, 4, 3)));
+        assert_eq!(definition(3, 0), None);
+
+        assert_eq!(
+            index.hover(&path, pos(3, 43)).unwrap(),
+            "variable `a`\n\n```c\nint\n```"
+        );
+        assert_eq!(
+            index.hover(&path, pos(2, 5)).unwrap(),
+            "function `add`\n\n```c\nint (int, int)\n```"
+        );
+        assert_eq!(
+            index.hover(&path, pos(3, 51)).unwrap(),
+            "enum constant `B`\n\n`B = 4`"
+        );
+        assert_eq!(
+            index.hover(&path, pos(3, 31)).unwrap(),
+            "struct `P`\n\n```c\nstruct P {\n    x: int\n    y: long [2]\n}\n```"
+        );
+    }
+
+    #[test]
+    fn document_symbols() {
+        let (path, index) = setup_analysis(
+            "int f(void);
+union U { char c; };
+int f(void) { struct Local { int l; }; return 0; }
+enum E { A };",
+        );
+        let symbols = index.document_symbols(&path);
+
+        assert_eq!(
+            symbols
+                .iter()
+                .map(|s| (s.name.as_str(), &s.kind, s.range.start, s.children.len()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("U", &SymbolKind::Union, pos(1, 6), 1),
+                ("f", &SymbolKind::Function, pos(2, 4), 0),
+                ("E", &SymbolKind::Enum, pos(3, 5), 1),
+            ]
+        );
+        assert_eq!(symbols[0].children[0].name, "c");
+        assert_eq!(symbols[0].children[0].detail, "char");
+        assert_eq!(symbols[2].children[0].kind, SymbolKind::EnumMember);
+    }
+
+    #[test]
+    fn completion() {
+        let text = "struct In { int value; int valid; };
+struct Out { struct In in[2]; struct In *next; long other; };
+struct Out global;
+int main() {
+  struct Out out;
+  struct Out *p = &out;
+  return 0;
+}";
+        let (path, index) = setup_analysis(text);
+        let names = |line: &str| {
+            let text = text.replacen("return 0;", line, 1);
+            let pos = pos(6, 2 + line.len());
+            index
+                .completion(&path, &text, pos)
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names("out."), vec!["in", "next", "other"]);
+        assert_eq!(names("p->ne"), vec!["next"]);
+        assert_eq!(names("out.in[p->other].val"), vec!["value", "valid"]);
+        assert_eq!(names("p->next->"), vec!["value", "valid"]);
+        assert_eq!(names("global.o"), vec!["other"]);
+        assert_eq!(names("out.other."), Vec::<String>::new());
+        assert_eq!(names("unknown."), Vec::<String>::new());
+        assert_eq!(names("return 1."), Vec::<String>::new());
+    }
+
+    #[test]
+    fn chains() {
+        assert_eq!(
+            member_chain("x = a[i[0]]->b . c"),
+            Some((
+                "a".to_string(),
+                vec![Access::Deref, Access::Deref, Access::Member("b".to_string())],
+                "c".to_string()
+            ))
+        );
+        assert_eq!(member_chain("a"), None);
+        assert_eq!(member_chain("1."), None);
+    }
+}
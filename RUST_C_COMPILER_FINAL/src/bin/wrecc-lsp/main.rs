@@ -0,0 +1,123 @@
+//! A language-server for editors speaking the language-server-protocol over stdin/stdout.<br>
+//! Every time a document changes it's run through the [compiler](wrecc_compiler::compiler) up to
+//! the typechecker to publish its errors as diagnostics. The identifiers recorded during
+//! typechecking are used for go-to-definition, hover, document-symbols and completion of members.
+
+mod analysis;
+mod json;
+mod server;
+
+use json::Json;
+use server::Server;
+
+use std::io::{self, BufRead, Write};
+
+/// Reads the next message framed by its `Content-Length` header, returns `None` at the end of input
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let Some(length) = length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "missing Content-Length header",
+        ));
+    };
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "message isn't valid utf-8"))
+}
+
+fn write_message(output: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+fn serve(input: &mut impl BufRead, output: &mut impl Write) -> io::Result<i32> {
+    let mut server = Server::new();
+
+    while let Some(message) = read_message(input)? {
+        match Json::parse(&message) {
+            Ok(message) => {
+                for response in server.handle(message) {
+                    write_message(output, &response)?;
+                }
+            }
+            Err(error) => eprintln!("wrecc-lsp: invalid message: {}", error),
+        }
+
+        if let Some(code) = server.exit_code() {
+            return Ok(code);
+        }
+    }
+
+    // the client closed the connection without asking the server to exit
+    Ok(1)
+}
+
+fn main() {
+    match serve(&mut io::stdin().lock(), &mut io::stdout().lock()) {
+        Ok(code) => std::process::exit(code),
+        Err(error) => {
+            eprintln!("wrecc-lsp: {}", error);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn framing() {
+        let messages = [
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"shutdown"}"#,
+            r#"{"jsonrpc":"2.0","method":"exit"}"#,
+        ];
+        let input: String = messages
+            .iter()
+            .map(|m| {
+                format!(
+                    "Content-Length: {}\r\nContent-Type: application/vscode-jsonrpc\r\n\r\n{}",
+                    m.len(),
+                    m
+                )
+            })
+            .collect();
+        let mut output = Vec::new();
+
+        assert_eq!(serve(&mut input.as_bytes(), &mut output).unwrap(), 0);
+
+        let mut output = output.as_slice();
+        let initialize = Json::parse(&read_message(&mut output).unwrap().unwrap()).unwrap();
+        let shutdown = read_message(&mut output).unwrap().unwrap();
+
+        assert_eq!(
+            initialize
+                .path(&["result", "serverInfo", "name"])
+                .and_then(Json::as_str),
+            Some("wrecc-lsp")
+        );
+        assert_eq!(shutdown, r#"{"jsonrpc":"2.0","id":2,"result":null}"#);
+        assert_eq!(read_message(&mut output).unwrap(), None);
+    }
+}
@@ -0,0 +1,466 @@
+//! Dispatches the language-server-protocol messages and keeps track of the open documents
+
+use crate::analysis::*;
+use crate::json::Json;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+// error-codes defined by JSON-RPC
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+struct Document {
+    path: PathBuf,
+    text: String,
+    // the last successful index is kept while the document doesn't parse, which is most of the
+    // time while typing
+    index: Option<Index>,
+}
+
+pub struct Server {
+    documents: HashMap<String, Document>,
+    include_dirs: Vec<PathBuf>,
+    shutdown: bool,
+    exit_code: Option<i32>,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Server {
+            documents: HashMap::new(),
+            include_dirs: Vec::new(),
+            shutdown: false,
+            exit_code: None,
+        }
+    }
+
+    /// Returns the exit-code once the client asked the server to exit
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    /// Handles a single request or notification and returns the messages to send to the client
+    pub fn handle(&mut self, message: Json) -> Vec<Json> {
+        let method = message.get("method").and_then(Json::as_str).unwrap_or_default();
+        let params = message.get("params").cloned().unwrap_or(Json::Null);
+
+        let Some(id) = message.get("id").cloned() else {
+            return self.notification(method, &params);
+        };
+        let response = match method {
+            "initialize" => Ok(self.initialize(&params)),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Json::Null)
+            }
+            "textDocument/definition" => self.definition(&params),
+            "textDocument/hover" => self.hover(&params),
+            "textDocument/documentSymbol" => self.document_symbols(&params),
+            "textDocument/completion" => self.completion(&params),
+            _ => Err((METHOD_NOT_FOUND, format!("unsupported method '{}'", method))),
+        };
+
+        vec![match response {
+            Ok(result) => Json::object([("jsonrpc", "2.0".into()), ("id", id), ("result", result)]),
+            Err((code, message)) => Json::object([
+                ("jsonrpc", "2.0".into()),
+                ("id", id),
+                (
+                    "error",
+                    Json::object([("code", code.into()), ("message", message.into())]),
+                ),
+            ]),
+        }]
+    }
+
+    fn notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let uri = params
+            .path(&["textDocument", "uri"])
+            .and_then(Json::as_str)
+            .unwrap_or_default()
+            .to_string();
+
+        match method {
+            "exit" => {
+                self.exit_code = Some(if self.shutdown { 0 } else { 1 });
+                Vec::new()
+            }
+            "textDocument/didOpen" => {
+                let text = params.path(&["textDocument", "text"]).and_then(Json::as_str);
+                self.update(uri, text.unwrap_or_default().to_string())
+            }
+            "textDocument/didChange" => {
+                // documents are always synced in full so the last change contains the whole text
+                let text = params
+                    .get("contentChanges")
+                    .and_then(Json::as_array)
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change.get("text"))
+                    .and_then(Json::as_str);
+
+                match text {
+                    Some(text) => self.update(uri, text.to_string()),
+                    None => Vec::new(),
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                vec![publish_diagnostics(&uri, Vec::new())]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn initialize(&mut self, params: &Json) -> Json {
+        if let Some(dirs) = params
+            .path(&["initializationOptions", "includeDirs"])
+            .and_then(Json::as_array)
+        {
+            self.include_dirs = dirs.iter().filter_map(Json::as_str).map(PathBuf::from).collect();
+        }
+
+        Json::object([
+            (
+                "capabilities",
+                Json::object([
+                    ("textDocumentSync", 1.into()),
+                    ("definitionProvider", true.into()),
+                    ("hoverProvider", true.into()),
+                    ("documentSymbolProvider", true.into()),
+                    (
+                        "completionProvider",
+                        Json::object([("triggerCharacters", Json::Array(vec![".".into(), ">".into()]))]),
+                    ),
+                ]),
+            ),
+            ("serverInfo", Json::object([("name", "wrecc-lsp".into())])),
+        ])
+    }
+
+    // analyzes the new text of the document and publishes its diagnostics
+    fn update(&mut self, uri: String, text: String) -> Vec<Json> {
+        let path = uri_to_path(&uri);
+        let analysis = analyze(&path, &text, &self.include_dirs);
+        let previous = self.documents.remove(&uri).and_then(|document| document.index);
+
+        let message = publish_diagnostics(&uri, analysis.diagnostics);
+        self.documents.insert(
+            uri,
+            Document {
+                path,
+                text,
+                index: analysis.index.or(previous),
+            },
+        );
+
+        vec![message]
+    }
+
+    // returns the document and the position the request refers to
+    fn document(&self, params: &Json) -> Result<(&Document, &Index, Position), (i64, String)> {
+        let uri = params.path(&["textDocument", "uri"]).and_then(Json::as_str);
+        let line = params.path(&["position", "line"]).and_then(Json::as_i64);
+        let character = params.path(&["position", "character"]).and_then(Json::as_i64);
+
+        let (Some(uri), Some(line), Some(character)) = (uri, line, character) else {
+            return Err((INVALID_PARAMS, "expected document and position".to_string()));
+        };
+        let Some(document) = self.documents.get(uri) else {
+            return Err((INVALID_PARAMS, format!("document '{}' isn't open", uri)));
+        };
+        let Some(index) = &document.index else {
+            return Err((
+                INVALID_PARAMS,
+                format!("document '{}' couldn't be parsed yet", uri),
+            ));
+        };
+
+        Ok((
+            document,
+            index,
+            Position {
+                line: line as usize,
+                character: character as usize,
+            },
+        ))
+    }
+
+    fn definition(&self, params: &Json) -> Result<Json, (i64, String)> {
+        let (document, index, pos) = self.document(params)?;
+
+        Ok(match index.definition(&document.path, pos) {
+            Some((path, range)) => {
+                Json::object([("uri", path_to_uri(&path).into()), ("range", range_json(range))])
+            }
+            None => Json::Null,
+        })
+    }
+
+    fn hover(&self, params: &Json) -> Result<Json, (i64, String)> {
+        let (document, index, pos) = self.document(params)?;
+
+        Ok(match index.hover(&document.path, pos) {
+            Some(markdown) => Json::object([(
+                "contents",
+                Json::object([("kind", "markdown".into()), ("value", markdown.into())]),
+            )]),
+            None => Json::Null,
+        })
+    }
+
+    fn document_symbols(&self, params: &Json) -> Result<Json, (i64, String)> {
+        let uri = params.path(&["textDocument", "uri"]).and_then(Json::as_str);
+
+        Ok(
+            match uri.and_then(|uri| self.documents.get(uri)).and_then(|document| {
+                document
+                    .index
+                    .as_ref()
+                    .map(|index| index.document_symbols(&document.path))
+            }) {
+                Some(symbols) => Json::Array(symbols.into_iter().map(symbol_json).collect()),
+                None => Json::Array(Vec::new()),
+            },
+        )
+    }
+
+    fn completion(&self, params: &Json) -> Result<Json, (i64, String)> {
+        let (document, index, pos) = self.document(params)?;
+
+        Ok(Json::Array(
+            index
+                .completion(&document.path, &document.text, pos)
+                .into_iter()
+                .map(|(name, detail)| {
+                    // 5 is the completion-kind of fields
+                    Json::object([
+                        ("label", name.into()),
+                        ("kind", 5.into()),
+                        ("detail", detail.into()),
+                    ])
+                })
+                .collect(),
+        ))
+    }
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Diagnostic>) -> Json {
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("method", "textDocument/publishDiagnostics".into()),
+        (
+            "params",
+            Json::object([
+                ("uri", uri.into()),
+                (
+                    "diagnostics",
+                    Json::Array(
+                        diagnostics
+                            .into_iter()
+                            .map(|diagnostic| {
+                                Json::object([
+                                    ("range", range_json(diagnostic.range)),
+                                    // all compiler-errors are errors
+                                    ("severity", 1.into()),
+                                    ("source", "wrecc".into()),
+                                    ("message", diagnostic.message.into()),
+                                ])
+                            })
+                            .collect(),
+                    ),
+                ),
+            ]),
+        ),
+    ])
+}
+
+fn position_json(pos: Position) -> Json {
+    Json::object([
+        ("line", (pos.line as i64).into()),
+        ("character", (pos.character as i64).into()),
+    ])
+}
+fn range_json(range: Range) -> Json {
+    let end = Position {
+        line: range.start.line,
+        character: range.start.character + range.length,
+    };
+    Json::object([("start", position_json(range.start)), ("end", position_json(end))])
+}
+
+fn symbol_json(symbol: DocumentSymbol) -> Json {
+    // the numeric symbol-kinds defined by the protocol
+    let kind: i64 = match symbol.kind {
+        SymbolKind::Function => 12,
+        SymbolKind::Struct => 23,
+        SymbolKind::Union => 23,
+        SymbolKind::Enum => 10,
+        SymbolKind::Field => 8,
+        SymbolKind::EnumMember => 22,
+    };
+
+    Json::object([
+        ("name", symbol.name.into()),
+        ("detail", symbol.detail.into()),
+        ("kind", kind.into()),
+        ("range", range_json(symbol.range)),
+        ("selectionRange", range_json(symbol.range)),
+        (
+            "children",
+            Json::Array(symbol.children.into_iter().map(symbol_json).collect()),
+        ),
+    ])
+}
+
+fn uri_to_path(uri: &str) -> PathBuf {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    let mut bytes = Vec::new();
+    let mut chars = path.bytes();
+
+    while let Some(c) = chars.next() {
+        if c == b'%' {
+            let hex: String = chars.by_ref().take(2).map(char::from).collect();
+            if let Ok(byte) = u8::from_str_radix(&hex, 16) {
+                bytes.push(byte);
+                continue;
+            }
+        }
+        bytes.push(c);
+    }
+
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+fn path_to_uri(path: &std::path::Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(id: i64, method: &str, params: Json) -> Json {
+        Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("id", id.into()),
+            ("method", method.into()),
+            ("params", params),
+        ])
+    }
+    fn notification(method: &str, params: Json) -> Json {
+        Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("method", method.into()),
+            ("params", params),
+        ])
+    }
+    fn at(line: i64, character: i64) -> Json {
+        Json::object([
+            (
+                "textDocument",
+                Json::object([("uri", "file:///src/my%20file.c".into())]),
+            ),
+            (
+                "position",
+                Json::object([("line", line.into()), ("character", character.into())]),
+            ),
+        ])
+    }
+
+    #[test]
+    fn session() {
+        let mut server = Server::new();
+
+        let init = server.handle(request(1, "initialize", Json::object([])));
+        assert_eq!(
+            init[0].path(&["result", "capabilities", "textDocumentSync"]),
+            Some(&Json::from(1))
+        );
+
+        let open = server.handle(notification(
+            "textDocument/didOpen",
+            Json::object([(
+                "textDocument",
+                Json::object([
+                    ("uri", "file:///src/my%20file.c".into()),
+                    ("text", "int x;\nint main() { return y; }".into()),
+                ]),
+            )]),
+        ));
+        let diagnostics = open[0]
+            .path(&["params", "diagnostics"])
+            .and_then(Json::as_array)
+            .unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].path(&["range", "start"]).map(Json::to_string),
+            Some(r#"{"line":1,"character":20}"#.to_string())
+        );
+
+        // the index is kept while the document can't be parsed
+        let change = server.handle(notification(
+            "textDocument/didChange",
+            Json::object([
+                (
+                    "textDocument",
+                    Json::object([("uri", "file:///src/my%20file.c".into())]),
+                ),
+                (
+                    "contentChanges",
+                    Json::Array(vec![Json::object([(
+                        "text",
+                        "int x;\nint main() { return x".into(),
+                    )])]),
+                ),
+            ]),
+        ));
+        assert_eq!(
+            change[0]
+                .path(&["params", "diagnostics"])
+                .and_then(Json::as_array)
+                .map(<[Json]>::len),
+            Some(1)
+        );
+
+        let definition = server.handle(request(2, "textDocument/definition", at(1, 4)));
+        assert_eq!(
+            definition[0].path(&["result", "uri"]).and_then(Json::as_str),
+            Some("file:///src/my%20file.c")
+        );
+        let hover = server.handle(request(3, "textDocument/hover", at(0, 4)));
+        assert_eq!(
+            hover[0]
+                .path(&["result", "contents", "value"])
+                .and_then(Json::as_str),
+            Some("variable `x`\n\n```c\nint\n```")
+        );
+
+        let unknown = server.handle(request(4, "textDocument/rename", at(0, 4)));
+        assert_eq!(
+            unknown[0].path(&["error", "code"]).and_then(Json::as_i64),
+            Some(METHOD_NOT_FOUND)
+        );
+
+        server.handle(request(5, "shutdown", Json::Null));
+        assert_eq!(server.exit_code(), None);
+        server.handle(notification("exit", Json::Null));
+        assert_eq!(server.exit_code(), Some(0));
+    }
+
+    #[test]
+    fn uris() {
+        let path = uri_to_path("file:///home/user/my%20file%2B.c");
+
+        assert_eq!(path, PathBuf::from("/home/user/my file+.c"));
+        assert_eq!(path_to_uri(&path), "file:///home/user/my%20file%2B.c");
+    }
+}
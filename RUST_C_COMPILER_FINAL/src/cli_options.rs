@@ -1,6 +1,8 @@
 //! Handles parsing cli-arguments without library.
 
-use crate::WreccError;
+use crate::compiler::codegen::arch::Target;
+use crate::dump::{Format, Stage};
+use crate::{CompileOptions, WreccError};
 use std::path::PathBuf;
 
 // TODO: add license information
@@ -14,8 +16,10 @@ const VERSION: &str = concat!(
 
 const USAGE: &str = "\
 usage: wrecc [-o <file>] [-I <dir>] [-D <name>=<value>]
-             [-L <dir>] [-l <name>] [-E] [-S] [-c] [--dump-ast]
-             [--no-color] [-h | --help] [-v] <file>";
+             [-L <dir>] [-l <name>] [-O<level>] [-g] [-E] [-S] [-c] [--integrated-as]
+             [--target=<triple>] [--emit=llvm|dot-ast|dot-cfg] [--run] [--analyze]
+             [--dump=<stages>] [--dump-format=<format>]
+             [--dump-ast] [--no-color] [-h | --help] [-v] <file>";
 
 const HELP: &str = "usage: wrecc [options] <file>
 options:
@@ -24,9 +28,23 @@ options:
     -D | --define <macro-name>=<value>  Defines a new object-like macro
     -L | --library-path <dir>           Adds <dir> to the directories to the library search paths (passed as -L<dir> to linker)
     -l | --library <name>               Looks for shared libraries with <name> in library search paths (passed as -l<name> to linker)
+    -O0 | -O1 | -O2 | -O3               Sets the optimization level (-O is the same as -O1)
+    -g                                  Generates DWARF debug-information
     -E | --preprocess-only              Stops evaluation after preprocessing printing the preprocessed source
     -S | --compile-only                 Stops evaluation after compiling resulting in a .s file
     -c | --no-link                      Stops evaluation after assembling resulting in a .o file
+         --integrated-as                Writes ELF object-files directly instead of invoking the system assembler
+         --target=<triple>              Generates code for <triple> (x86_64-linux, aarch64-linux, riscv64-linux, wasm32), defaults to x86_64-linux
+         --emit=llvm                    Stops evaluation after compiling resulting in a .ll file containing LLVM IR
+         --emit=dot-ast                 Stops evaluation after typechecking resulting in a .dot file of the syntax-tree
+         --emit=dot-cfg                 Stops evaluation after typechecking resulting in a .dot file of every function's
+                                        control-flow-graph
+         --run                          Interprets the program instead of compiling it, reporting undefined behaviour
+         --analyze                      Warns about likely bugs like uninitialized variables or null-pointer
+                                        dereferences while also compiling program as usual
+         --dump=<stages>                Writes the output of the comma-separated <stages> (tokens, pp, hir, mir, lir, asm)
+                                        to <file>.<stage>.<format> while also compiling program as usual
+         --dump-format=<format>         Sets the format of the dumped stages (text, json, sexp), defaults to text
          --dump-ast                     Displays the AST produced by the parser while also compiling program as usual
          --no-color                     Errors are printed without color
     -h                                  Prints usage information
@@ -41,6 +59,17 @@ fn sys_info(msg: &str) -> ! {
     std::process::exit(0);
 }
 
+/// Formats selected with `--emit=<format>` that are written instead of assembly
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Emit {
+    /// Textual LLVM IR in a .ll file
+    Llvm,
+    /// Graphviz diagram of the syntax-tree in a .dot file
+    DotAst,
+    /// Graphviz diagram of every function's control-flow-graph in a .dot file
+    DotCfg,
+}
+
 /// Struct holding all possible cli-args to be passed when running `wrecc`
 pub struct CliOptions {
     /// Required argument specifying file to compile
@@ -58,12 +87,40 @@ pub struct CliOptions {
     /// Stops evaluation after assembling resulting in an .o file
     pub no_link: bool,
 
+    /// Encodes machine-code and writes ELF object-files directly instead of invoking `as`
+    pub integrated_as: bool,
+
+    /// Writes the program in another format instead of generating assembly
+    pub emit: Option<Emit>,
+
+    /// Runs the program in the interpreter instead of compiling it, exiting with its exit-code
+    pub run: bool,
+
+    /// Prints the warnings of the static analyzer while also compiling program as usual
+    pub analyze: bool,
+
     /// Displays AST while also compiling program as usual
     pub dump_ast: bool,
 
+    /// Stages whose output is written into a file next to the input-file
+    pub dump_stages: Vec<Stage>,
+
+    /// Format the stages are dumped in
+    pub dump_format: Format,
+
     /// Errors are printed without color
     pub no_color: bool,
 
+    /// Optimization level, enables tail-call optimization at `-O1` and above
+    pub opt_level: u8,
+
+    /// Emits DWARF line-information and descriptions of functions, variables and types
+    pub debug_info: bool,
+
+    /// Architecture to generate code for, cross-targets are assembled and linked using their
+    /// prefixed toolchain (eg. `aarch64-linux-gnu-as`)
+    pub target: Target,
+
     /// Directories specified by user to be searched after `#include "..."` and before `#include <...>`
     pub user_include_dirs: Vec<PathBuf>,
 
@@ -89,10 +146,34 @@ impl CliOptions {
             preprocess_only: false,
             compile_only: false,
             no_link: false,
+            integrated_as: false,
+            emit: None,
+            run: false,
+            analyze: false,
             dump_ast: false,
+            dump_stages: Vec::new(),
+            dump_format: Format::Text,
             no_color: false,
+            opt_level: 0,
+            debug_info: false,
+            target: Target::X86_64,
         }
     }
+    /// The options of the library's [Session](crate::Session) every input-file is compiled with
+    pub fn compile_options(&self) -> CompileOptions {
+        let options = self
+            .user_include_dirs
+            .iter()
+            .fold(CompileOptions::new(), |options, dir| options.include_dir(dir));
+
+        self.defines
+            .iter()
+            .fold(options, |options, (name, value)| options.define(name, value))
+            .opt_level(self.opt_level)
+            .target(self.target)
+            .debug_info(self.debug_info)
+            .dump_ast(self.dump_ast)
+    }
     /// Parses all passed cli-args and builds [CliOptions] with them.<br>
     /// INFO: every argument needs to be seperated by whitespace meaning that options requiring a
     /// following argument like in `wrecc -L` have to be seperated by a whitespace:<br>
@@ -166,8 +247,53 @@ impl CliOptions {
                     "-E" | "--preprocess-only" => cli_options.preprocess_only = true,
                     "-S" | "--compile-only" => cli_options.compile_only = true,
                     "-c" | "--no-link" => cli_options.no_link = true,
+                    "--integrated-as" => cli_options.integrated_as = true,
                     "--dump-ast" => cli_options.dump_ast = true,
                     "--no-color" => cli_options.no_color = true,
+                    "-O" => cli_options.opt_level = 1,
+                    "-O0" | "-O1" | "-O2" | "-O3" => {
+                        cli_options.opt_level = arg[2..].parse().expect("matched digit")
+                    }
+                    "-g" => cli_options.debug_info = true,
+                    _ if arg.starts_with("--target=") => {
+                        let triple = &arg["--target=".len()..];
+                        let Some(target) = Target::from_triple(triple) else {
+                            return Err(WreccError::Cli(vec![format!("unknown target '{}'", triple)]));
+                        };
+                        cli_options.target = target;
+                    }
+                    "--emit=llvm" => cli_options.emit = Some(Emit::Llvm),
+                    "--emit=dot-ast" => cli_options.emit = Some(Emit::DotAst),
+                    "--emit=dot-cfg" => cli_options.emit = Some(Emit::DotCfg),
+                    _ if arg.starts_with("--emit=") => {
+                        return Err(WreccError::Cli(vec![format!(
+                            "unknown output-format '{}', expected '--emit=llvm', '--emit=dot-ast' or '--emit=dot-cfg'",
+                            &arg["--emit=".len()..]
+                        )]));
+                    }
+                    _ if arg.starts_with("--dump=") => {
+                        for name in arg["--dump=".len()..].split(',') {
+                            let Some(stage) = Stage::from_name(name) else {
+                                return Err(WreccError::Cli(vec![format!(
+                                    "unknown stage '{}', expected one of 'tokens', 'pp', 'hir', 'mir', 'lir' or 'asm'",
+                                    name
+                                )]));
+                            };
+                            cli_options.dump_stages.push(stage);
+                        }
+                    }
+                    _ if arg.starts_with("--dump-format=") => {
+                        let name = &arg["--dump-format=".len()..];
+                        let Some(format) = Format::from_name(name) else {
+                            return Err(WreccError::Cli(vec![format!(
+                                "unknown dump-format '{}', expected 'text', 'json' or 'sexp'",
+                                name
+                            )]));
+                        };
+                        cli_options.dump_format = format;
+                    }
+                    "--run" => cli_options.run = true,
+                    "--analyze" => cli_options.analyze = true,
                     "-h" => sys_info(USAGE),
                     "--help" => sys_info(HELP),
                     "-v" | "--version" => sys_info(VERSION),
@@ -179,14 +305,72 @@ impl CliOptions {
         }
 
         if cli_options.output_path.is_some() && cli_options.files.len() > 1 {
-            if cli_options.preprocess_only || cli_options.compile_only || cli_options.no_link {
+            if cli_options.preprocess_only
+                || cli_options.compile_only
+                || cli_options.no_link
+                || cli_options.emit.is_some()
+            {
                 return Err(WreccError::Cli(vec![
-                    "cannot specify '-o' with '-E', '-S' or '-c' when compiling multiple files"
+                    "cannot specify '-o' with '-E', '-S', '-c' or '--emit' when compiling multiple files"
                         .to_string(),
                 ]));
             }
         }
 
+        if cli_options.integrated_as && cfg!(target_os = "macos") {
+            return Err(WreccError::Cli(vec![
+                "'--integrated-as' can only write ELF object-files".to_string(),
+            ]));
+        }
+
+        if cli_options.debug_info {
+            if cfg!(target_os = "macos") {
+                return Err(WreccError::Cli(vec![
+                    "'-g' can only emit debug-information for ELF object-files".to_string(),
+                ]));
+            } else if cli_options.integrated_as {
+                return Err(WreccError::Cli(vec![
+                    "cannot specify '-g' with '--integrated-as'".to_string(),
+                ]));
+            }
+        }
+
+        if cli_options.emit.is_some() && (cli_options.integrated_as || cli_options.debug_info) {
+            return Err(WreccError::Cli(vec![
+                "cannot specify '--integrated-as' or '-g' with '--emit'".to_string(),
+            ]));
+        }
+
+        if cli_options.run {
+            if cli_options.files.len() > 1 {
+                return Err(WreccError::Cli(vec!["'--run' can only run a single file".to_string()]));
+            } else if cli_options.output_path.is_some()
+                || cli_options.compile_only
+                || cli_options.no_link
+                || cli_options.emit.is_some()
+            {
+                return Err(WreccError::Cli(vec![
+                    "cannot specify '-o', '-S', '-c' or '--emit' with '--run'".to_string(),
+                ]));
+            }
+        }
+
+        if cli_options.target != Target::X86_64 {
+            if cli_options.integrated_as {
+                return Err(WreccError::Cli(vec![
+                    "'--integrated-as' can only encode x86-64 instructions".to_string(),
+                ]));
+            } else if cli_options.debug_info {
+                return Err(WreccError::Cli(vec![
+                    "'-g' is only supported when targeting x86-64".to_string(),
+                ]));
+            } else if cli_options.dump_stages.contains(&Stage::Lir) {
+                return Err(WreccError::Cli(vec![
+                    "'--dump=lir' is only supported when targeting x86-64".to_string(),
+                ]));
+            }
+        }
+
         if cli_options.files.is_empty() {
             Err(WreccError::Cli(vec!["no input files given".to_string()]))
         } else if let Some(file) = cli_options
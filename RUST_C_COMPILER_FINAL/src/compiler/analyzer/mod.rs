@@ -0,0 +1,1073 @@
+//! Statically analyzes the typechecked [MIR](crate::compiler::typechecker::mir) for likely bugs,
+//! which are reported as warnings without failing compilation.<br>
+//! Every function is walked along its control-flow, keeping track of which automatic scalar
+//! variables are initialized and which values they are known to have. Loops are iterated until
+//! the tracked state doesn't change anymore, so that warnings are only reported once the state
+//! holds for every iteration. Variables whose address is taken are not tracked since they could be
+//! modified through a pointer.<br>
+//! The analyzer reports:
+//! - reading variables that haven't been initialized on all paths
+//! - dereferencing pointers that are known to be null, eg. after a null-check
+//! - indexing arrays of known size with constant indices that are out of bounds
+//! - dividing by variables that are known to be zero
+//! - statements that can never be executed
+//! - format-strings of printf-family calls that don't match their arguments
+
+use crate::compiler::common::{environment::*, error::*, token::*, types::*};
+use crate::compiler::typechecker::mir::{decl::*, expr::*, stmt::*};
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+// variables are identified by the address of their entry in the symbol-table
+type SymbolKey = *const RefCell<Symbol>;
+
+/// Returns the warnings for all functions in the program
+pub fn analyze(external_decls: &[ExternalDeclaration]) -> Vec<Error> {
+    let mut analyzer = Analyzer::new();
+
+    for decl in external_decls {
+        if let ExternalDeclaration::Function(func, _, body) = decl {
+            analyzer.function(func, body);
+        }
+    }
+
+    analyzer.warnings
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Initialized {
+    Yes,
+    No,
+    Maybe,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Value {
+    Unknown,
+    Known(i64),
+    NonZero,
+}
+impl Value {
+    fn join(self, other: Value) -> Value {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Value::Known(n), Value::NonZero) | (Value::NonZero, Value::Known(n)) if n != 0 => Value::NonZero,
+            (Value::Known(a), Value::Known(b)) if a != 0 && b != 0 => Value::NonZero,
+            _ => Value::Unknown,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Var {
+    init: Initialized,
+    value: Value,
+}
+
+// what is known about the tracked variables at a point in the function
+#[derive(Clone, PartialEq)]
+struct State {
+    reachable: bool,
+    vars: HashMap<SymbolKey, Var>,
+}
+impl State {
+    fn unreachable() -> Self {
+        State {
+            reachable: false,
+            vars: HashMap::new(),
+        }
+    }
+    // merges the state of another path flowing into the same point
+    fn join(&mut self, other: &State) {
+        if !other.reachable {
+            return;
+        }
+        if !self.reachable {
+            *self = other.clone();
+            return;
+        }
+        for (key, other_var) in &other.vars {
+            match self.vars.get_mut(key) {
+                Some(var) => {
+                    var.init = if var.init == other_var.init {
+                        var.init
+                    } else {
+                        Initialized::Maybe
+                    };
+                    var.value = var.value.join(other_var.value);
+                }
+                None => {
+                    self.vars.insert(*key, *other_var);
+                }
+            }
+        }
+    }
+}
+
+// conversions in a printf format-string and the arguments they expect
+#[derive(Clone, Copy)]
+enum Expected {
+    Int(usize),
+    String,
+    Pointer,
+    Double,
+}
+impl Expected {
+    fn to_string(self) -> &'static str {
+        match self {
+            Expected::Int(8) => "long",
+            Expected::Int(_) => "int",
+            Expected::String => "char *",
+            Expected::Pointer => "void *",
+            Expected::Double => "double",
+        }
+    }
+    fn matches(self, ty: &Type) -> bool {
+        match self {
+            Expected::Int(size) => ty.is_integer() && ty.size() == size,
+            Expected::String => {
+                matches!(ty, Type::Pointer(to) if matches!(to.ty, Type::Primitive(Primitive::Char(_))))
+            }
+            Expected::Pointer => ty.is_ptr(),
+            Expected::Double => false,
+        }
+    }
+}
+
+struct Analyzer {
+    warnings: Vec<Error>,
+
+    // warnings are only reported once loops have reached their fixed-point
+    reporting: bool,
+
+    // variables whose address is taken in the current function
+    escaped: HashSet<SymbolKey>,
+
+    // variables that were already reported as uninitialized
+    reported: HashSet<SymbolKey>,
+
+    // states at the `break` and `continue` statements of enclosing loops and switches
+    breaks: Vec<State>,
+    continues: Vec<State>,
+
+    // states at the start of enclosing switches and whether they have a default-case
+    switches: Vec<(State, bool)>,
+
+    // start of the current statement, used for warnings that don't have a more precise location
+    location: Token,
+}
+
+impl Analyzer {
+    fn new() -> Self {
+        Analyzer {
+            warnings: Vec::new(),
+            reporting: true,
+            escaped: HashSet::new(),
+            reported: HashSet::new(),
+            breaks: Vec::new(),
+            continues: Vec::new(),
+            switches: Vec::new(),
+            location: Token::default(TokenKind::Semicolon),
+        }
+    }
+    fn warn(&mut self, location: &Token, kind: ErrorKind) {
+        let warning = Error::new(location, kind);
+        if self.reporting && !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+
+    fn function(&mut self, func: &Function, body: &[Stmt]) {
+        self.escaped.clear();
+
+        let mut state = State {
+            reachable: true,
+            vars: HashMap::new(),
+        };
+        for param in &func.params {
+            if param.borrow().qtype.ty.is_scalar() {
+                state.vars.insert(
+                    Rc::as_ptr(param),
+                    Var {
+                        init: Initialized::Yes,
+                        value: Value::Unknown,
+                    },
+                );
+            }
+        }
+
+        self.block(&mut state, body);
+    }
+
+    fn block(&mut self, state: &mut State, stmts: &[Stmt]) {
+        for stmt in stmts {
+            self.stmt(state, stmt);
+        }
+    }
+    fn stmt(&mut self, state: &mut State, stmt: &Stmt) {
+        match stmt {
+            Stmt::Located(token, body) => {
+                // a `break` after a `return` is common in switches and harmless
+                if !state.reachable && !is_jump_target(body) && !matches!(**body, Stmt::Break) {
+                    self.warn(token, ErrorKind::UnreachableCode);
+                    // only report the first statement of unreachable code
+                    state.reachable = true;
+                }
+                self.location = token.clone();
+                self.stmt(state, body);
+            }
+            Stmt::Declaration(decls) => self.declaration(state, decls),
+            Stmt::Expr(expr) => {
+                self.expr(state, expr);
+            }
+            Stmt::Block(body) => self.block(state, body),
+            Stmt::If(cond, then_branch, else_branch) => {
+                self.expr(state, cond);
+
+                let mut else_state = state.clone();
+                self.assume(state, cond, true);
+                self.assume(&mut else_state, cond, false);
+
+                self.stmt(state, then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.stmt(&mut else_state, else_branch);
+                }
+                state.join(&else_state);
+            }
+            Stmt::While(cond, body) => self.repeat(state, |analyzer, state| {
+                analyzer.expr(state, cond);
+                let exit = analyzer.leave_loop(state, Some(cond));
+
+                analyzer.loop_body(state, body);
+                exit
+            }),
+            Stmt::Do(body, cond) => self.repeat(state, |analyzer, state| {
+                analyzer.loop_body(state, body);
+
+                analyzer.expr(state, cond);
+                analyzer.leave_loop(state, Some(cond))
+            }),
+            Stmt::For(init, cond, inc, body) => {
+                if let Some(init) = init {
+                    self.stmt(state, init);
+                }
+                self.repeat(state, |analyzer, state| {
+                    if let Some(cond) = cond {
+                        analyzer.expr(state, cond);
+                    }
+                    let exit = analyzer.leave_loop(state, cond.as_ref());
+
+                    analyzer.loop_body(state, body);
+                    if let Some(inc) = inc {
+                        analyzer.expr(state, inc);
+                    }
+                    exit
+                })
+            }
+            Stmt::Return(expr) => {
+                if let Some(expr) = expr {
+                    self.expr(state, expr);
+                }
+                state.reachable = false;
+            }
+            Stmt::Break => {
+                if let Some(exit) = self.breaks.last_mut() {
+                    exit.join(state);
+                }
+                state.reachable = false;
+            }
+            Stmt::Continue => {
+                if let Some(head) = self.continues.last_mut() {
+                    head.join(state);
+                }
+                state.reachable = false;
+            }
+            Stmt::Switch(cond, body) => {
+                self.expr(state, cond);
+
+                self.switches.push((state.clone(), false));
+                self.breaks.push(State::unreachable());
+                *state = State::unreachable();
+
+                self.stmt(state, body);
+
+                let (entry, has_default) = self.switches.pop().unwrap();
+                state.join(&self.breaks.pop().unwrap());
+                if !has_default {
+                    state.join(&entry);
+                }
+            }
+            Stmt::Case(body) | Stmt::Default(body) => {
+                if let Some((entry, has_default)) = self.switches.last_mut() {
+                    *has_default |= matches!(stmt, Stmt::Default(_));
+                    state.join(entry);
+                }
+                self.stmt(state, body);
+            }
+            Stmt::Goto(_) => state.reachable = false,
+            Stmt::Label(_, body) => {
+                // labels can be jumped to from anywhere so nothing is known about the variables
+                state.reachable = true;
+                for var in state.vars.values_mut() {
+                    *var = Var {
+                        init: Initialized::Yes,
+                        value: Value::Unknown,
+                    };
+                }
+                self.stmt(state, body);
+            }
+        }
+    }
+
+    // analyzes a loop-iteration until the state at the start of the loop doesn't change anymore,
+    // `iteration` returns the state when exiting the loop through its condition
+    fn repeat(&mut self, state: &mut State, iteration: impl Fn(&mut Self, &mut State) -> State) {
+        let reporting = self.reporting;
+        self.reporting = false;
+
+        let mut head = state.clone();
+        loop {
+            let mut next = head.clone();
+            self.continues.push(State::unreachable());
+            self.breaks.push(State::unreachable());
+            iteration(self, &mut next);
+            self.breaks.pop();
+            self.continues.pop();
+
+            next.join(&head);
+            if next == head {
+                break;
+            }
+            head = next;
+        }
+
+        self.reporting = reporting;
+        *state = head;
+        self.continues.push(State::unreachable());
+        self.breaks.push(State::unreachable());
+
+        let mut exit = iteration(self, state);
+
+        self.continues.pop();
+        exit.join(&self.breaks.pop().unwrap());
+        *state = exit;
+    }
+    // returns the state when the loop-condition is false and assumes it is true for the body
+    fn leave_loop(&mut self, state: &mut State, cond: Option<&Expr>) -> State {
+        let Some(cond) = cond else {
+            return State::unreachable();
+        };
+        if let Some(value) = self.constant(state, cond) {
+            if value != 0 {
+                return State::unreachable();
+            }
+        }
+        let mut exit = state.clone();
+        self.assume(&mut exit, cond, false);
+        self.assume(state, cond, true);
+
+        exit
+    }
+    // the body flows into the next iteration together with all `continue` statements
+    fn loop_body(&mut self, state: &mut State, body: &Stmt) {
+        self.stmt(state, body);
+
+        let continues = std::mem::replace(self.continues.last_mut().unwrap(), State::unreachable());
+        state.join(&continues);
+    }
+
+    fn declaration(&mut self, state: &mut State, decls: &[Declarator]) {
+        for decl in decls {
+            let value = match &decl.init {
+                Some(Init::Scalar(expr)) => Some(self.expr(state, expr)),
+                Some(Init::Aggr(exprs)) => {
+                    for (expr, _) in exprs {
+                        self.expr(state, expr);
+                    }
+                    Some(Value::Unknown)
+                }
+                None => None,
+            };
+
+            let symbol = decl.entry.borrow();
+            let key = Rc::as_ptr(&decl.entry);
+            if symbol.qtype.ty.is_scalar()
+                && !symbol.is_static()
+                && !symbol.is_extern()
+                && !self.escaped.contains(&key)
+            {
+                let var = match value {
+                    Some(value) => Var {
+                        init: Initialized::Yes,
+                        value,
+                    },
+                    None => Var {
+                        init: Initialized::No,
+                        value: Value::Unknown,
+                    },
+                };
+                state.vars.insert(key, var);
+            }
+        }
+    }
+
+    // checks the expression and returns its value if it is known
+    fn expr(&mut self, state: &mut State, expr: &Expr) -> Value {
+        match &expr.kind {
+            ExprKind::Binary { left, token, right } => {
+                self.expr(state, left);
+                self.expr(state, right);
+                self.check_divisor(state, token, right);
+
+                Value::Unknown
+            }
+            ExprKind::Unary { token, right } => match token.kind {
+                TokenKind::Amp => {
+                    // pointing one past the end of an array is allowed, but not when the
+                    // sub-array only decays to be indexed again
+                    let decays = right.qtype.ty.is_array()
+                        && !matches!(expr.qtype.deref_at().map(|qtype| qtype.ty), Some(Type::Array(..)));
+                    self.address(state, right, !decays);
+                    Value::NonZero
+                }
+                TokenKind::Star => {
+                    self.expr(state, right);
+                    self.check_deref(state, token, right, false);
+                    Value::Unknown
+                }
+                _ => {
+                    self.expr(state, right);
+                    Value::Unknown
+                }
+            },
+            ExprKind::Assign { l_expr, r_expr } => {
+                let value = self.expr(state, r_expr);
+
+                match self.variable(state, l_expr) {
+                    Some(key) if matches!(l_expr.kind, ExprKind::Ident(_)) => {
+                        state.vars.insert(
+                            key,
+                            Var {
+                                init: Initialized::Yes,
+                                value,
+                            },
+                        );
+                    }
+                    _ => self.lvalue(state, l_expr),
+                }
+                value
+            }
+            ExprKind::CompoundAssign { expr, .. } => {
+                match compound_parts(expr) {
+                    Some((target, token, operand)) => {
+                        self.lvalue(state, target);
+                        self.read(state, target);
+                        self.expr(state, operand);
+                        self.check_divisor(state, token, operand);
+
+                        if let Some(key) = self.variable(state, target) {
+                            state.vars.insert(
+                                key,
+                                Var {
+                                    init: Initialized::Yes,
+                                    value: Value::Unknown,
+                                },
+                            );
+                        }
+                    }
+                    None => {
+                        self.expr(state, expr);
+                    }
+                }
+                Value::Unknown
+            }
+            ExprKind::Logical { left, token, right } => {
+                self.expr(state, left);
+
+                // the right operand is only evaluated if the left one doesn't decide the result
+                let is_and = token.kind == TokenKind::AmpAmp;
+                let mut right_state = state.clone();
+                self.assume(&mut right_state, left, is_and);
+                self.assume(state, left, !is_and);
+
+                self.expr(&mut right_state, right);
+                state.join(&right_state);
+
+                Value::Unknown
+            }
+            ExprKind::Comparison { left, right, .. } => {
+                self.expr(state, left);
+                self.expr(state, right);
+                Value::Unknown
+            }
+            ExprKind::Call { caller, args } => {
+                self.expr(state, caller);
+                for arg in args {
+                    self.expr(state, arg);
+                }
+                if let Some(name) = direct_callee(caller) {
+                    self.check_format(&name, args);
+                }
+                Value::Unknown
+            }
+            ExprKind::Cast { expr, direction, .. } => {
+                let value = self.expr(state, expr);
+                match direction {
+                    CastDirection::Down => Value::Unknown,
+                    _ => value,
+                }
+            }
+            ExprKind::Scale { expr, .. } => {
+                self.expr(state, expr);
+                Value::Unknown
+            }
+            ExprKind::MemberAccess { expr, .. } => {
+                self.address(state, expr, false);
+                Value::Unknown
+            }
+            ExprKind::Ternary {
+                cond,
+                true_expr,
+                false_expr,
+            } => {
+                self.expr(state, cond);
+
+                let mut false_state = state.clone();
+                self.assume(state, cond, true);
+                self.assume(&mut false_state, cond, false);
+
+                let value = self.expr(state, true_expr);
+                let false_value = self.expr(&mut false_state, false_expr);
+                state.join(&false_state);
+
+                value.join(false_value)
+            }
+            ExprKind::Comma { left, right } => {
+                self.expr(state, left);
+                self.expr(state, right)
+            }
+            ExprKind::String(_) => Value::NonZero,
+            ExprKind::Literal(literal) => Value::Known(literal_value(literal)),
+            ExprKind::Ident(_) => self.read(state, expr),
+            ExprKind::Nop => Value::Unknown,
+        }
+    }
+    // checks an expression whose address is used without reading its value
+    fn address(&mut self, state: &mut State, expr: &Expr, one_past: bool) {
+        match &expr.kind {
+            ExprKind::Ident(symbol) => {
+                let key = Rc::as_ptr(symbol);
+                if state.vars.contains_key(&key) {
+                    // the variable could now be modified through the pointer
+                    self.escaped.insert(key);
+                }
+            }
+            ExprKind::Unary { token, right } if token.kind == TokenKind::Star => {
+                self.expr(state, right);
+                self.check_deref(state, token, right, one_past);
+            }
+            ExprKind::MemberAccess { expr, .. } => self.address(state, expr, false),
+            _ => {
+                self.expr(state, expr);
+            }
+        }
+    }
+    // checks an expression that is assigned to
+    fn lvalue(&mut self, state: &mut State, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Ident(_) => (),
+            ExprKind::Unary { token, right } if token.kind == TokenKind::Star => {
+                self.expr(state, right);
+                self.check_deref(state, token, right, false);
+            }
+            ExprKind::MemberAccess { expr, .. } => self.address(state, expr, false),
+            _ => {
+                self.expr(state, expr);
+            }
+        }
+    }
+    // returns the value of a variable, reporting it once if it isn't initialized
+    fn read(&mut self, state: &mut State, expr: &Expr) -> Value {
+        let (Some(key), ExprKind::Ident(symbol)) = (self.variable(state, expr), &expr.kind) else {
+            return Value::Unknown;
+        };
+        let var = state.vars[&key];
+        let name = symbol.borrow().token.unwrap_string();
+
+        if state.reachable && self.reporting && !self.reported.contains(&key) {
+            let location = self.location.clone();
+            match var.init {
+                Initialized::No => self.warn(&location, ErrorKind::UninitializedUse(name)),
+                Initialized::Maybe => self.warn(&location, ErrorKind::MaybeUninitializedUse(name)),
+                Initialized::Yes => return var.value,
+            }
+            self.reported.insert(key);
+        }
+        var.value
+    }
+
+    // the tracked variable the expression refers to, looking through casts that keep its value
+    fn variable(&self, state: &State, expr: &Expr) -> Option<SymbolKey> {
+        match &expr.kind {
+            ExprKind::Ident(symbol) => {
+                let key = Rc::as_ptr(symbol);
+                (state.vars.contains_key(&key) && !self.escaped.contains(&key)).then_some(key)
+            }
+            ExprKind::Cast { expr, direction, .. } if *direction != CastDirection::Down => {
+                self.variable(state, expr)
+            }
+            _ => None,
+        }
+    }
+    fn constant(&self, state: &State, expr: &Expr) -> Option<i64> {
+        match &expr.kind {
+            ExprKind::Literal(literal) => Some(literal_value(literal)),
+            ExprKind::Cast { expr, .. } => self.constant(state, expr),
+            _ => match state.vars.get(&self.variable(state, expr)?)?.value {
+                Value::Known(n) => Some(n),
+                _ => None,
+            },
+        }
+    }
+    fn name(expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Ident(symbol) => symbol.borrow().token.unwrap_string(),
+            ExprKind::Cast { expr, .. } => Self::name(expr),
+            _ => unreachable!("only called on variables"),
+        }
+    }
+
+    // refines the state with the knowledge that the condition has the given truth-value
+    fn assume(&self, state: &mut State, cond: &Expr, truth: bool) {
+        match &cond.kind {
+            ExprKind::Unary { token, right } if token.kind == TokenKind::Bang => {
+                self.assume(state, right, !truth)
+            }
+            ExprKind::Logical { left, token, right } if (token.kind == TokenKind::AmpAmp) == truth => {
+                self.assume(state, left, truth);
+                self.assume(state, right, truth);
+            }
+            ExprKind::Comparison { left, token, right }
+                if matches!(token.kind, TokenKind::EqualEqual | TokenKind::BangEqual) =>
+            {
+                let (key, n) = match (self.variable(state, left), self.variable(state, right)) {
+                    (Some(key), _) if is_literal(right) => (key, self.constant(state, right)),
+                    (_, Some(key)) if is_literal(left) => (key, self.constant(state, left)),
+                    _ => return,
+                };
+                let (Some(var), Some(n)) = (state.vars.get_mut(&key), n) else {
+                    return;
+                };
+
+                if (token.kind == TokenKind::EqualEqual) == truth {
+                    var.value = Value::Known(n);
+                } else if n == 0 {
+                    var.value = Value::NonZero;
+                }
+            }
+            _ => {
+                if let Some(key) = self.variable(state, cond) {
+                    if let Some(var) = state.vars.get_mut(&key) {
+                        var.value = if truth { Value::NonZero } else { Value::Known(0) };
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_divisor(&mut self, state: &State, token: &Token, divisor: &Expr) {
+        if !matches!(token.kind, TokenKind::Slash | TokenKind::Mod) || !state.reachable {
+            return;
+        }
+        if let Some(key) = self.variable(state, divisor) {
+            if state.vars[&key].value == Value::Known(0) {
+                self.warn(token, ErrorKind::ZeroDivisor(Self::name(divisor)));
+            }
+        }
+    }
+    // checks the pointer that is dereferenced at `token` for being null or out of bounds
+    fn check_deref(&mut self, state: &State, token: &Token, pointer: &Expr, one_past: bool) {
+        if !state.reachable {
+            return;
+        }
+        let (base, offset) = match &pointer.kind {
+            ExprKind::Binary { left, token, right } if token.kind == TokenKind::Plus => {
+                if left.qtype.ty.is_ptr() {
+                    (left.as_ref(), Some(right.as_ref()))
+                } else {
+                    (right.as_ref(), Some(left.as_ref()))
+                }
+            }
+            _ => (pointer, None),
+        };
+
+        if let Some(key) = self.variable(state, base) {
+            if state.vars[&key].value == Value::Known(0) {
+                self.warn(token, ErrorKind::NullPointerDeref(Self::name(base)));
+            }
+        }
+
+        // arrays decay into a pointer to their first element
+        let (ExprKind::Unary { right: array, .. }, Some(offset)) = (&base.kind, offset) else {
+            return;
+        };
+        let Type::Array(of, ArraySize::Known(length)) = &array.qtype.ty else {
+            return;
+        };
+        let index = match &strip_casts(offset).kind {
+            ExprKind::Scale { expr, .. } => self.constant(state, expr),
+            // constant indices are already scaled into an offset in bytes
+            _ => self
+                .constant(state, offset)
+                .filter(|bytes| bytes % of.ty.size() as i64 == 0)
+                .map(|bytes| bytes / of.ty.size() as i64),
+        };
+
+        if let Some(index) = index {
+            let length = *length as i64;
+            if index < 0 || index > length || (index == length && !one_past) {
+                self.warn(token, ErrorKind::IndexOutOfBounds(index, length as usize));
+            }
+        }
+    }
+
+    fn check_format(&mut self, name: &str, args: &[Expr]) {
+        let format_index = match name {
+            "printf" => 0,
+            "fprintf" | "dprintf" | "sprintf" => 1,
+            "snprintf" => 2,
+            _ => return,
+        };
+        let Some(ExprKind::Unary { right, .. }) = args.get(format_index).map(|arg| &strip_casts(arg).kind)
+        else {
+            return;
+        };
+        let ExprKind::String(format) = &right.kind else {
+            return;
+        };
+
+        let location = self.location.clone();
+        let conversions = match parse_format(format) {
+            Ok(conversions) => conversions,
+            Err(conversion) => {
+                self.warn(&location, ErrorKind::UnknownConversion(conversion));
+                return;
+            }
+        };
+
+        let values = &args[format_index + 1..];
+        for (i, ((conversion, expected), arg)) in conversions.iter().zip(values).enumerate() {
+            if !expected.matches(&arg.qtype.ty) {
+                self.warn(
+                    &location,
+                    ErrorKind::FormatArgType(
+                        conversion.clone(),
+                        expected.to_string(),
+                        format_index + i + 2,
+                        arg.qtype.clone(),
+                    ),
+                );
+            }
+        }
+        if conversions.len() != values.len() {
+            self.warn(
+                &location,
+                ErrorKind::FormatArgCount(name.to_string(), conversions.len(), values.len()),
+            );
+        }
+    }
+}
+
+// returns the conversions in a printf format-string together with the arguments they expect,
+// `*` as width or precision expects an additional int
+fn parse_format(format: &str) -> Result<Vec<(String, Expected)>, String> {
+    let mut conversions = Vec::new();
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            continue;
+        }
+        let mut conversion = String::from("%");
+        let mut stars = 0;
+        let mut size = 4;
+
+        // flags, width and precision
+        while let Some(c) = chars.next_if(|c| "-+ #0123456789.*".contains(*c)) {
+            if c == '*' {
+                stars += 1;
+            }
+            conversion.push(c);
+        }
+        // length-modifier
+        while let Some(c) = chars.next_if(|c| "hlLjzt".contains(*c)) {
+            if "ljzt".contains(c) {
+                size = 8;
+            }
+            conversion.push(c);
+        }
+
+        let Some(c) = chars.next() else {
+            return Err(conversion);
+        };
+        conversion.push(c);
+
+        let expected = match c {
+            '%' => continue,
+            'd' | 'i' | 'u' | 'o' | 'x' | 'X' => Expected::Int(size),
+            'c' => Expected::Int(4),
+            's' => Expected::String,
+            'p' | 'n' => Expected::Pointer,
+            'f' | 'F' | 'e' | 'E' | 'g' | 'G' | 'a' | 'A' => Expected::Double,
+            _ => return Err(conversion),
+        };
+        for _ in 0..stars {
+            conversions.push((conversion.clone(), Expected::Int(4)));
+        }
+        conversions.push((conversion, expected));
+    }
+
+    Ok(conversions)
+}
+
+// case- and default-statements and labels can be reached even if the code before them can't
+fn is_jump_target(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::Case(_) | Stmt::Default(_) | Stmt::Label(..) => true,
+        Stmt::Located(_, body) => is_jump_target(body),
+        _ => false,
+    }
+}
+
+// compound-assignments `A op= B` are desugared to `tmp = &A, *tmp = *tmp op B`,
+// returns `A`, `op` and `B`
+fn compound_parts(expr: &Expr) -> Option<(&Expr, &Token, &Expr)> {
+    let ExprKind::Comma { left, right } = &expr.kind else {
+        return None;
+    };
+    let (ExprKind::Assign { r_expr: address, .. }, ExprKind::Assign { r_expr: value, .. }) =
+        (&left.kind, &right.kind)
+    else {
+        return None;
+    };
+    let (
+        ExprKind::Unary { right: target, .. },
+        ExprKind::Binary {
+            token,
+            right: operand,
+            ..
+        },
+    ) = (&address.kind, &strip_casts(value).kind)
+    else {
+        return None;
+    };
+
+    Some((target, token, operand))
+}
+
+fn strip_casts(expr: &Expr) -> &Expr {
+    match &expr.kind {
+        ExprKind::Cast { expr, .. } => strip_casts(expr),
+        _ => expr,
+    }
+}
+
+fn is_literal(expr: &Expr) -> bool {
+    matches!(strip_casts(expr).kind, ExprKind::Literal(_))
+}
+
+fn literal_value(literal: &LiteralKind) -> i64 {
+    match literal {
+        LiteralKind::Signed(n) => *n,
+        LiteralKind::Unsigned(n) => *n as i64,
+    }
+}
+
+// functions that are called by name
+fn direct_callee(caller: &Expr) -> Option<String> {
+    match &caller.kind {
+        ExprKind::Ident(symbol) if caller.qtype.ty.is_func() => Some(symbol.borrow().token.unwrap_string()),
+        ExprKind::Unary { token, right } if token.kind == TokenKind::Amp => direct_callee(right),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parser::tests::setup;
+    use crate::compiler::typechecker::TypeChecker;
+
+    fn setup_analyze(input: &str) -> Vec<(i32, ErrorKind)> {
+        let parse_tree = setup(input).parse().unwrap();
+        let (mir, _) = TypeChecker::new().check(parse_tree).unwrap();
+
+        analyze(&mir)
+            .into_iter()
+            .map(|w| (w.line_index, w.kind))
+            .collect()
+    }
+
+    #[test]
+    fn uninitialized() {
+        assert_eq!(
+            setup_analyze("int main() {\nint a;\nint b = a;\nreturn a + b;\n}"),
+            vec![(3, ErrorKind::UninitializedUse("a".to_string()))]
+        );
+        assert_eq!(
+            setup_analyze("int f(int c) {\nint a;\nif (c) a = 1;\nreturn a;\n}"),
+            vec![(4, ErrorKind::MaybeUninitializedUse("a".to_string()))]
+        );
+        assert_eq!(
+            setup_analyze("int f(int c) {\nint a;\nif (c) a = 1;\nelse a = 2;\nreturn a;\n}"),
+            vec![]
+        );
+        // the increment reads the variable which is only initialized in later iterations
+        assert_eq!(
+            setup_analyze("int main() {\nint i;\nfor (;;) {\ni++;\nif (i > 3) break;\n}\nreturn i;\n}"),
+            vec![(4, ErrorKind::MaybeUninitializedUse("i".to_string()))]
+        );
+        // address taken so it could be initialized through the pointer
+        assert_eq!(
+            setup_analyze("void init(int *);\nint main() {\nint a;\ninit(&a);\nreturn a;\n}"),
+            vec![]
+        );
+        assert_eq!(
+            setup_analyze("int main() {\nint a;\nint i;\nfor (i = 0; i < 3; i++) a = i;\nreturn a;\n}"),
+            vec![(5, ErrorKind::MaybeUninitializedUse("a".to_string()))]
+        );
+    }
+
+    #[test]
+    fn null_deref() {
+        assert_eq!(
+            setup_analyze("int f(int *p) {\nif (!p) {\nreturn *p;\n}\nreturn *p;\n}"),
+            vec![(3, ErrorKind::NullPointerDeref("p".to_string()))]
+        );
+        assert_eq!(
+            setup_analyze(
+                "struct S { int x; };\nint f(struct S *s) {\nif (s == 0) return s->x;\nreturn 0;\n}"
+            ),
+            vec![(3, ErrorKind::NullPointerDeref("s".to_string()))]
+        );
+        // short-circuiting guards the dereference
+        assert_eq!(
+            setup_analyze("int f(int *p) {\nif (p != 0 && *p) return 1;\nreturn p && p[1];\n}"),
+            vec![]
+        );
+        assert_eq!(
+            setup_analyze("int f(int *p, int *q) {\nif (p) {\nq = 0;\n} else {\nq = p;\n}\nreturn *q;\n}"),
+            vec![(7, ErrorKind::NullPointerDeref("q".to_string()))]
+        );
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        assert_eq!(
+            setup_analyze(
+                "int main() {\nint a[4];\nchar c[3];\na[3] = 1;\na[4] = 2;\nc[-1] = 3;\nreturn c[3];\n}"
+            ),
+            vec![
+                (5, ErrorKind::IndexOutOfBounds(4, 4)),
+                (6, ErrorKind::IndexOutOfBounds(-1, 3)),
+                (7, ErrorKind::IndexOutOfBounds(3, 3)),
+            ]
+        );
+        assert_eq!(
+            setup_analyze(
+                "int main() {\nint a[2][3];\nint i = 3;\nint *end = &a[1][3];\nreturn a[1][i] + a[2][0];\n}"
+            ),
+            vec![
+                (5, ErrorKind::IndexOutOfBounds(3, 3)),
+                (5, ErrorKind::IndexOutOfBounds(2, 2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn zero_divisor() {
+        assert_eq!(
+            setup_analyze("int f(int a, int b) {\nif (b == 0) {\nreturn a / b;\n}\nreturn a % b;\n}"),
+            vec![(3, ErrorKind::ZeroDivisor("b".to_string()))]
+        );
+        assert_eq!(
+            setup_analyze("int f(int a) {\nint n = 0;\na /= n;\nn = 2;\nreturn a / n;\n}"),
+            vec![(3, ErrorKind::ZeroDivisor("n".to_string()))]
+        );
+    }
+
+    #[test]
+    fn unreachable() {
+        assert_eq!(
+            setup_analyze("int f(int a) {\nreturn a;\na = 2;\nreturn a;\n}"),
+            vec![(3, ErrorKind::UnreachableCode)]
+        );
+        assert_eq!(
+            setup_analyze(
+                "int f(int a) {\nswitch (a) {\ncase 1: return 1;\nbreak;\ndefault: return 2;\n}\nreturn 3;\n}"
+            ),
+            vec![(7, ErrorKind::UnreachableCode)]
+        );
+        assert_eq!(
+            setup_analyze("int f(int a) {\nwhile (1) {\nif (a) break;\ncontinue;\na++;\n}\nreturn a;\n}"),
+            vec![(5, ErrorKind::UnreachableCode)]
+        );
+        assert_eq!(
+            setup_analyze("int f(int a) {\nfor (;;) {}\nreturn a;\n}"),
+            vec![(3, ErrorKind::UnreachableCode)]
+        );
+        assert_eq!(
+            setup_analyze("int f(int a) {\ngoto end;\na = 1;\nend:\nreturn a;\n}"),
+            vec![(3, ErrorKind::UnreachableCode)]
+        );
+    }
+
+    #[test]
+    fn format_strings() {
+        let printf = "int printf(char *, ...);\nint sprintf(char *, char *, ...);\n";
+        assert_eq!(
+            setup_analyze(&format!(
+                "{}int main() {{\nprintf(\"%d %s %%\\n\", 1, \"a\");\nreturn 0;\n}}",
+                printf
+            )),
+            vec![]
+        );
+        assert_eq!(
+            setup_analyze(&format!(
+                "{}int main() {{\nchar buf[8];\nsprintf(buf, \"%s %d\", 1);\nprintf(\"%ld %*d\", 2, 3);\nreturn 0;\n}}",
+                printf
+            )),
+            vec![
+                (
+                    5,
+                    ErrorKind::FormatArgType(
+                        "%s".to_string(),
+                        "char *",
+                        3,
+                        QualType::new(Type::Primitive(Primitive::Int(false)))
+                    )
+                ),
+                (5, ErrorKind::FormatArgCount("sprintf".to_string(), 2, 1)),
+                (
+                    6,
+                    ErrorKind::FormatArgType(
+                        "%ld".to_string(),
+                        "long",
+                        2,
+                        QualType::new(Type::Primitive(Primitive::Int(false)))
+                    )
+                ),
+                (6, ErrorKind::FormatArgCount("printf".to_string(), 3, 2)),
+            ]
+        );
+        assert_eq!(
+            setup_analyze(&format!(
+                "{}int main() {{\nprintf(\"%q\", 1);\nreturn 0;\n}}",
+                printf
+            )),
+            vec![(4, ErrorKind::UnknownConversion("%q".to_string()))]
+        );
+    }
+}
@@ -0,0 +1,711 @@
+//! Code-generation for 64-bit ARM following the procedure call standard
+//! [AAPCS64](https://github.com/ARM-software/abi-aa/blob/main/aapcs64/aapcs64.rst).<br>
+//! The accumulator is `x0`, so that return-values don't have to be moved, the secondary register is
+//! `x1`. Locals are addressed relative to the frame-pointer `x29`.
+
+use crate::compiler::codegen::arch::*;
+use crate::compiler::common::{token::TokenKind, types::*};
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Reg {
+    // 64bit general-purpose register
+    X(u8),
+    // lower 32bit of general-purpose register
+    W(u8),
+    Sp,
+    Wzr,
+}
+impl Reg {
+    // registers are 32bit wide unless the value needs 64bit
+    fn new(index: u8, ty: &Type) -> Reg {
+        if is_wide(ty) {
+            Reg::X(index)
+        } else {
+            Reg::W(index)
+        }
+    }
+}
+impl fmt::Display for Reg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Reg::X(n) => write!(f, "x{}", n),
+            Reg::W(n) => write!(f, "w{}", n),
+            Reg::Sp => write!(f, "sp"),
+            Reg::Wzr => write!(f, "wzr"),
+        }
+    }
+}
+
+const ACC: u8 = 0;
+const SECONDARY: u8 = 1;
+// used for intermediate values that don't outlive a single operation
+const SCRATCH: u8 = 9;
+// intra-procedure-call register, holds the address of indirect calls
+const IP0: u8 = 16;
+const IP1: u8 = 17;
+const FP: Reg = Reg::X(29);
+const LR: Reg = Reg::X(30);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Reg(Reg),
+    Imm(i64),
+    // [base, #offset]
+    Mem(Reg, i64),
+    // [base, index]
+    MemIndex(Reg, Reg),
+    // [base, #offset]!
+    PreIndex(Reg, i64),
+    // [base], #offset
+    PostIndex(Reg, i64),
+    Label(String),
+    // :lo12:label
+    Lo12(String),
+    // :got:label
+    Got(String),
+    // [base, :got_lo12:label]
+    MemGotLo12(Reg, String),
+    // lsl #amount
+    Lsl(u8),
+    Cond(&'static str),
+}
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operand::Reg(reg) => write!(f, "{}", reg),
+            Operand::Imm(n) => write!(f, "#{}", n),
+            Operand::Mem(base, 0) => write!(f, "[{}]", base),
+            Operand::Mem(base, offset) => write!(f, "[{}, #{}]", base, offset),
+            Operand::MemIndex(base, index) => write!(f, "[{}, {}]", base, index),
+            Operand::PreIndex(base, offset) => write!(f, "[{}, #{}]!", base, offset),
+            Operand::PostIndex(base, offset) => write!(f, "[{}], #{}", base, offset),
+            Operand::Label(label) => write!(f, "{}", label),
+            Operand::Lo12(label) => write!(f, ":lo12:{}", label),
+            Operand::Got(label) => write!(f, ":got:{}", label),
+            Operand::MemGotLo12(base, label) => write!(f, "[{}, :got_lo12:{}]", base, label),
+            Operand::Lsl(amount) => write!(f, "lsl #{}", amount),
+            Operand::Cond(cond) => write!(f, "{}", cond),
+        }
+    }
+}
+impl From<Reg> for Operand {
+    fn from(reg: Reg) -> Self {
+        Operand::Reg(reg)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    // switches to section if not already in it, separated by an empty line: `.text`
+    Section(&'static str),
+    // assembler directive: `.globl main`
+    Directive(String),
+    Label(String),
+    // mnemonic with its operands in assembler order
+    Op(&'static str, Vec<Operand>),
+}
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instruction::Section(section) => write!(f, "\n\t{}", section),
+            Instruction::Directive(directive) => write!(f, "\t{}", directive),
+            Instruction::Label(label) => write!(f, "{}:", label),
+            Instruction::Op(mnemonic, operands) if operands.is_empty() => write!(f, "\t{}", mnemonic),
+            Instruction::Op(mnemonic, operands) => write!(
+                f,
+                "\t{:<8}{}",
+                mnemonic,
+                operands
+                    .iter()
+                    .map(|op| op.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+macro_rules! op {
+    ($mnemonic:expr $(, $operand:expr)*) => {
+        Instruction::Op($mnemonic, vec![$(Operand::from($operand)),*])
+    };
+}
+
+#[derive(Default)]
+pub struct Aarch64 {
+    output: Vec<Instruction>,
+
+    // name of the current function, needed for its `.size` directive
+    function: String,
+
+    // section the following instructions are written to
+    section: &'static str,
+}
+impl Aarch64 {
+    fn emit(&mut self, instruction: Instruction) {
+        self.output.push(instruction)
+    }
+    fn section(&mut self, section: &'static str) {
+        if self.section != section {
+            self.section = section;
+            self.emit(Instruction::Section(section));
+        }
+    }
+    fn directive(&mut self, directive: impl Into<String>) {
+        self.emit(Instruction::Directive(directive.into()))
+    }
+
+    // materializes any 64bit constant, using as few instructions as possible
+    fn mov_imm(&mut self, dest: Reg, value: i64) {
+        let wide = matches!(dest, Reg::X(_));
+        let bits = if wide { value as u64 } else { value as u32 as u64 };
+        let inverted = if wide { !bits } else { !bits & 0xffff_ffff };
+
+        if bits <= 0xffff {
+            self.emit(op!("mov", dest, Operand::Imm(bits as i64)));
+        } else if inverted <= 0xffff {
+            // encoded as `movn`
+            let value = if wide {
+                bits as i64
+            } else {
+                bits as u32 as i32 as i64
+            };
+            self.emit(op!("mov", dest, Operand::Imm(value)));
+        } else {
+            let chunks = if wide { 4 } else { 2 };
+            let mut first = true;
+
+            for i in 0..chunks {
+                let chunk = (bits >> (16 * i)) & 0xffff;
+                if chunk == 0 {
+                    continue;
+                }
+                let mnemonic = if first { "movz" } else { "movk" };
+                self.emit(op!(
+                    mnemonic,
+                    dest,
+                    Operand::Imm(chunk as i64),
+                    Operand::Lsl(16 * i)
+                ));
+                first = false;
+            }
+        }
+    }
+    // `add`/`sub` immediates only have 12 bits, bigger ones need a register
+    fn add_imm(&mut self, dest: Reg, src: Reg, value: i64) {
+        let (mnemonic, n) = if value < 0 {
+            ("sub", -value)
+        } else {
+            ("add", value)
+        };
+
+        if n <= 4095 {
+            self.emit(op!(mnemonic, dest, src, Operand::Imm(n)));
+        } else {
+            self.mov_imm(Reg::X(IP1), n);
+            self.emit(op!(mnemonic, dest, src, Reg::X(IP1)));
+        }
+    }
+    fn address_of(&mut self, dest: Reg, label: String) {
+        self.emit(op!("adrp", dest, Operand::Label(label.clone())));
+        self.emit(op!("add", dest, dest, Operand::Lo12(label)));
+    }
+    fn load_from(&mut self, dest: u8, address: Operand, ty: &Type) {
+        let mnemonic = match ty {
+            Type::Primitive(Primitive::Char(false)) => "ldrsb",
+            Type::Primitive(Primitive::Char(true)) => "ldrb",
+            Type::Primitive(Primitive::Short(false)) => "ldrsh",
+            Type::Primitive(Primitive::Short(true)) => "ldrh",
+            _ => "ldr",
+        };
+        self.emit(op!(mnemonic, Reg::new(dest, ty), address));
+    }
+    fn store_to(&mut self, src: u8, address: Operand, ty: &Type) {
+        let mnemonic = match ty.size() {
+            1 => "strb",
+            2 => "strh",
+            _ => "str",
+        };
+        self.emit(op!(mnemonic, Reg::new(src, ty), address));
+    }
+    // extends the lower bits of the accumulator according to the type, since AAPCS64 leaves
+    // unused bits of arguments and return values unspecified
+    fn extend(&mut self, ty: &Type) {
+        let mnemonic = match ty {
+            Type::Primitive(Primitive::Char(false)) => "sxtb",
+            Type::Primitive(Primitive::Char(true)) => "uxtb",
+            Type::Primitive(Primitive::Short(false)) => "sxth",
+            Type::Primitive(Primitive::Short(true)) => "uxth",
+            _ => return,
+        };
+        self.emit(op!(mnemonic, Reg::W(ACC), Reg::W(ACC)));
+    }
+    // copies `size` bytes, starting at the highest address
+    fn byte_loop(&mut self, size: usize, body: &[Instruction]) {
+        if size == 0 {
+            return;
+        }
+        self.mov_imm(Reg::X(2), size as i64);
+        self.emit(Instruction::Label("1".to_string()));
+        self.emit(op!("subs", Reg::X(2), Reg::X(2), Operand::Imm(1)));
+        self.output.extend_from_slice(body);
+        self.emit(op!("b.ne", Operand::Label("1b".to_string())));
+    }
+}
+
+// pointers, longs and aggregates (which are represented by their address) need 64bit registers
+fn is_wide(ty: &Type) -> bool {
+    match ty {
+        Type::Primitive(Primitive::Long(_)) => true,
+        Type::Primitive(_) | Type::Enum(..) => false,
+        _ => true,
+    }
+}
+
+fn label(index: usize) -> String {
+    format!(".L{}", index)
+}
+
+impl Machine for Aarch64 {
+    const ARG_REGS: usize = 8;
+
+    fn string(&mut self, label: usize, value: &str) {
+        self.section(".section .rodata");
+        self.emit(Instruction::Label(string_label(label)));
+        // INFO: use {:?} so escapes aren't applied, same as for x86-64
+        self.directive(format!(".string {:?}", value));
+    }
+    fn global(&mut self, name: &str, is_static: bool, data: Vec<Data>) {
+        self.section(".data");
+        if !is_static {
+            self.directive(format!(".globl {}", name));
+        }
+        self.directive(".p2align 3");
+        self.emit(Instruction::Label(name.to_string()));
+
+        for value in data {
+            let directive = match value {
+                Data::Int(n, size) => {
+                    let (directive, n) = match size {
+                        1 => ("byte", n as u8 as i64),
+                        2 => ("hword", n as u16 as i64),
+                        4 => ("word", n as u32 as i64),
+                        _ => ("xword", n),
+                    };
+                    format!(".{} {}", directive, n)
+                }
+                Data::Zero(size) => format!(".zero {}", size),
+                Data::Address(label, 0) => format!(".xword {}", label),
+                Data::Address(label, offset) => format!(".xword {}{:+}", label, offset),
+            };
+            self.directive(directive);
+        }
+    }
+
+    fn function(&mut self, name: &str, is_static: bool, stack_size: usize) {
+        self.function = name.to_string();
+
+        self.section(".text");
+        if !is_static {
+            self.directive(format!(".globl {}", name));
+        }
+        self.directive(".p2align 2");
+        self.directive(format!(".type {}, %function", name));
+        self.emit(Instruction::Label(name.to_string()));
+
+        // frame-record of frame-pointer and link-register
+        self.emit(op!("stp", FP, LR, Operand::PreIndex(Reg::Sp, -16)));
+        self.emit(op!("mov", FP, Reg::Sp));
+        if stack_size > 0 {
+            self.add_imm(Reg::Sp, Reg::Sp, -(stack_size as i64));
+        }
+    }
+    fn param(&mut self, index: usize, offset: isize, ty: &Type) {
+        if ty.is_struct() {
+            unimplemented!("currently can't pass structs as arguments")
+        }
+        self.add_imm(Reg::X(SCRATCH), FP, offset as i64);
+
+        if index < Self::ARG_REGS {
+            self.store_to(index as u8, Operand::Mem(Reg::X(SCRATCH), 0), ty);
+        } else {
+            // arguments that don't fit into registers are above the frame-record, each in an 8-byte slot
+            let stack_offset = 16 + (index - Self::ARG_REGS) as i64 * 8;
+
+            self.emit(op!("ldr", Reg::X(SCRATCH + 1), Operand::Mem(FP, stack_offset)));
+            self.store_to(SCRATCH + 1, Operand::Mem(Reg::X(SCRATCH), 0), ty);
+        }
+    }
+    fn epilogue(&mut self, index: usize) {
+        self.label(index);
+        self.emit(op!("mov", Reg::Sp, FP));
+        self.emit(op!("ldp", FP, LR, Operand::PostIndex(Reg::Sp, 16)));
+        self.emit(op!("ret"));
+
+        let function = std::mem::take(&mut self.function);
+        self.directive(format!(".size {}, .-{}", function, function));
+    }
+
+    fn literal(&mut self, value: i64, ty: &Type) {
+        self.mov_imm(Reg::new(ACC, ty), value);
+    }
+    fn local_address(&mut self, offset: isize) {
+        self.add_imm(Reg::X(ACC), FP, offset as i64);
+    }
+    fn global_address(&mut self, name: &str, is_extern: bool) {
+        if is_extern {
+            // address is only known at runtime and has to be loaded from the global-offset-table
+            self.emit(op!("adrp", Reg::X(ACC), Operand::Got(name.to_string())));
+            self.emit(op!(
+                "ldr",
+                Reg::X(ACC),
+                Operand::MemGotLo12(Reg::X(ACC), name.to_string())
+            ));
+        } else {
+            self.address_of(Reg::X(ACC), name.to_string());
+        }
+    }
+    fn string_address(&mut self, label: usize) {
+        self.address_of(Reg::X(ACC), string_label(label));
+    }
+    fn add_offset(&mut self, offset: usize) {
+        self.add_imm(Reg::X(ACC), Reg::X(ACC), offset as i64);
+    }
+
+    fn load(&mut self, ty: &Type) {
+        if is_value(ty) {
+            self.load_from(ACC, Operand::Mem(Reg::X(ACC), 0), ty);
+        }
+    }
+    fn store(&mut self, ty: &Type) {
+        if is_value(ty) {
+            self.store_to(ACC, Operand::Mem(Reg::X(SECONDARY), 0), ty);
+        } else {
+            self.byte_loop(
+                ty.size(),
+                &[
+                    op!("ldrb", Reg::W(3), Operand::MemIndex(Reg::X(ACC), Reg::X(2))),
+                    op!("strb", Reg::W(3), Operand::MemIndex(Reg::X(SECONDARY), Reg::X(2))),
+                ],
+            );
+            self.emit(op!("mov", Reg::X(ACC), Reg::X(SECONDARY)));
+        }
+    }
+    fn clear(&mut self, size: usize) {
+        self.byte_loop(
+            size,
+            &[op!("strb", Reg::Wzr, Operand::MemIndex(Reg::X(ACC), Reg::X(2)))],
+        );
+    }
+
+    fn push(&mut self) {
+        self.emit(op!("str", Reg::X(ACC), Operand::PreIndex(Reg::Sp, -16)));
+    }
+    fn pop(&mut self) {
+        self.emit(op!("ldr", Reg::X(SECONDARY), Operand::PostIndex(Reg::Sp, 16)));
+    }
+
+    fn binary(&mut self, op: &TokenKind, ty: &Type) {
+        let (acc, left) = (Reg::new(ACC, ty), Reg::new(SECONDARY, ty));
+        let is_unsigned = ty.is_unsigned();
+
+        let mnemonic = match op {
+            TokenKind::Plus => "add",
+            TokenKind::Minus => "sub",
+            TokenKind::Star => "mul",
+            TokenKind::Slash if is_unsigned => "udiv",
+            TokenKind::Slash => "sdiv",
+            TokenKind::Amp => "and",
+            TokenKind::Pipe => "orr",
+            TokenKind::Xor => "eor",
+            TokenKind::LessLess => "lsl",
+            TokenKind::GreaterGreater if is_unsigned => "lsr",
+            TokenKind::GreaterGreater => "asr",
+            TokenKind::Mod => {
+                // a % b = a - (a / b) * b
+                let quotient = Reg::new(2, ty);
+                let div = if is_unsigned { "udiv" } else { "sdiv" };
+
+                self.emit(op!(div, quotient, left, acc));
+                self.emit(op!("msub", acc, quotient, acc, left));
+                return;
+            }
+            _ => unreachable!("not a binary operator: {:?}", op),
+        };
+        self.emit(op!(mnemonic, acc, left, acc));
+    }
+    fn compare(&mut self, op: &TokenKind, ty: &Type) {
+        let is_unsigned = ty.is_unsigned();
+        let cond = match op {
+            TokenKind::EqualEqual => "eq",
+            TokenKind::BangEqual => "ne",
+            TokenKind::Less if is_unsigned => "lo",
+            TokenKind::Less => "lt",
+            TokenKind::LessEqual if is_unsigned => "ls",
+            TokenKind::LessEqual => "le",
+            TokenKind::Greater if is_unsigned => "hi",
+            TokenKind::Greater => "gt",
+            TokenKind::GreaterEqual if is_unsigned => "hs",
+            TokenKind::GreaterEqual => "ge",
+            _ => unreachable!("not a comparison operator: {:?}", op),
+        };
+
+        self.emit(op!("cmp", Reg::new(SECONDARY, ty), Reg::new(ACC, ty)));
+        self.emit(op!("cset", Reg::W(ACC), Operand::Cond(cond)));
+    }
+    fn unary(&mut self, op: &TokenKind, ty: &Type) {
+        let acc = Reg::new(ACC, ty);
+
+        match op {
+            TokenKind::Minus => self.emit(op!("neg", acc, acc)),
+            TokenKind::Tilde => self.emit(op!("mvn", acc, acc)),
+            TokenKind::Bang => {
+                self.emit(op!("cmp", acc, Operand::Imm(0)));
+                self.emit(op!("cset", Reg::W(ACC), Operand::Cond("eq")));
+            }
+            TokenKind::Plus => (),
+            _ => unreachable!("not a unary operator: {:?}", op),
+        }
+    }
+    fn convert(&mut self, from: &Type, to: &Type) {
+        if to.is_void() || !is_value(from) {
+            return;
+        }
+
+        match to.size() {
+            1 | 2 if from.size() != to.size() || from.is_unsigned() != to.is_unsigned() => self.extend(to),
+            8 if !is_wide(from) => {
+                if from.is_unsigned() {
+                    // writing a 32bit register clears the upper half
+                    self.emit(op!("mov", Reg::W(ACC), Reg::W(ACC)));
+                } else {
+                    self.emit(op!("sxtw", Reg::X(ACC), Reg::W(ACC)));
+                }
+            }
+            // only the lower bits are used after truncation
+            _ => (),
+        }
+    }
+
+    fn label(&mut self, index: usize) {
+        self.emit(Instruction::Label(label(index)));
+    }
+    fn jump(&mut self, index: usize) {
+        self.emit(op!("b", Operand::Label(label(index))));
+    }
+    fn branch_zero(&mut self, ty: &Type, index: usize) {
+        self.emit(op!("cbz", Reg::new(ACC, ty), Operand::Label(label(index))));
+    }
+    fn branch_equal(&mut self, value: i64, ty: &Type, index: usize) {
+        let acc = Reg::new(ACC, ty);
+
+        if (0..=4095).contains(&value) {
+            self.emit(op!("cmp", acc, Operand::Imm(value)));
+        } else {
+            let value_reg = Reg::new(SECONDARY, ty);
+            self.mov_imm(value_reg, value);
+            self.emit(op!("cmp", acc, value_reg));
+        }
+        self.emit(op!("b.eq", Operand::Label(label(index))));
+    }
+
+    fn call(&mut self, callee: Callee, args: usize, return_type: &Type) {
+        if let Callee::Indirect = callee {
+            self.emit(op!("mov", Reg::X(IP0), Reg::X(ACC)));
+        }
+
+        let reg_args = args.min(Self::ARG_REGS);
+        for i in 0..reg_args {
+            self.emit(op!("ldr", Reg::X(i as u8), Operand::PostIndex(Reg::Sp, 16)));
+        }
+
+        // remaining arguments were pushed into 16-byte slots but AAPCS64 expects them in 8-byte
+        // slots, so move them closer together; the stack-pointer stays 16-byte aligned
+        let stack_args = args - reg_args;
+        for i in 1..stack_args as i64 {
+            self.emit(op!("ldr", Reg::X(SCRATCH), Operand::Mem(Reg::Sp, i * 16)));
+            self.emit(op!("str", Reg::X(SCRATCH), Operand::Mem(Reg::Sp, i * 8)));
+        }
+
+        match callee {
+            Callee::Direct(name) => self.emit(op!("bl", Operand::Label(name))),
+            Callee::Indirect => self.emit(op!("blr", Reg::X(IP0))),
+        }
+
+        if stack_args > 0 {
+            self.add_imm(Reg::Sp, Reg::Sp, stack_args as i64 * 16);
+        }
+        if return_type.is_struct() {
+            unimplemented!("currently can't return structs")
+        }
+        self.extend(return_type);
+    }
+
+    fn finish(self) -> String {
+        self.output
+            .into_iter()
+            .map(|instr| instr.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parser::tests::setup;
+    use crate::compiler::typechecker::TypeChecker;
+
+    fn setup_asm(input: &str) -> String {
+        let parse_tree = setup(input).parse().unwrap();
+        let (mir, const_labels) = TypeChecker::new().check(parse_tree).unwrap();
+
+        translate(Target::Aarch64, mir, const_labels)
+    }
+
+    fn has_tool(name: &str) -> bool {
+        std::process::Command::new(name).arg("--version").output().is_ok()
+    }
+
+    #[test]
+    fn function_frame() {
+        let actual = setup_asm("int add(int a, int b) { return a + b; }");
+        let expected = "
+\t.text
+\t.globl add
+\t.p2align 2
+\t.type add, %function
+add:
+\tstp     x29, x30, [sp, #-16]!
+\tmov     x29, sp
+\tsub     sp, sp, #16
+\tsub     x9, x29, #4
+\tstr     w0, [x9]
+\tsub     x9, x29, #8
+\tstr     w1, [x9]
+\tsub     x0, x29, #4
+\tldr     w0, [x0]
+\tstr     x0, [sp, #-16]!
+\tsub     x0, x29, #8
+\tldr     w0, [x0]
+\tldr     x1, [sp], #16
+\tadd     w0, w1, w0
+\tb       .L0
+.L0:
+\tmov     sp, x29
+\tldp     x29, x30, [sp], #16
+\tret
+\t.size add, .-add";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn stack_arguments() {
+        let actual = setup_asm(
+            "
+long ten(long a, long b, long c, long d, long e, long f, long g, long h, long i, long j);
+long foo() { return ten(1, 2, 3, 4, 5, 6, 7, 8, 9, 10); }
+long ten(long a, long b, long c, long d, long e, long f, long g, long h, long i, long j) { return j; }",
+        );
+
+        for expected in [
+            "\tldr     x7, [sp], #16",
+            "\tldr     x9, [sp, #16]\n\tstr     x9, [sp, #8]\n\tbl      ten\n\tadd     sp, sp, #32",
+            "\tldr     x10, [x29, #24]",
+        ] {
+            assert!(actual.contains(expected), "missing:\n{}", expected);
+        }
+    }
+
+    #[test]
+    fn signedness() {
+        let actual = setup_asm(
+            "
+unsigned char narrow(unsigned a, unsigned b) { return a / b > a >> 2; }
+int wide(long a, int b) { return a < b % 3; }",
+        );
+
+        for expected in [
+            "\tudiv    w0, w1, w0",
+            "\tlsr     w0, w1, w0",
+            "\tcset    w0, hi",
+            "\tuxtb    w0, w0",
+            "\tsxtw    x0, w0",
+            "\tmsub    w0, w2, w0, w1",
+            "\tcset    w0, lt",
+        ] {
+            assert!(actual.contains(expected), "missing:\n{}", expected);
+        }
+    }
+
+    #[test]
+    fn global_addressing() {
+        let actual = setup_asm(
+            "
+extern int ext;
+int arr[3] = {1, 2};
+int *p = &arr[1];
+int foo() { static long count = -2; return ext + arr[0] + count; }",
+        );
+
+        for expected in [
+            "arr:\n\t.word 1\n\t.word 2\n\t.zero 4",
+            "p:\n\t.xword arr+4",
+            "count.0:\n\t.xword -2",
+            "\tadrp    x0, :got:ext\n\tldr     x0, [x0, :got_lo12:ext]",
+            "\tadrp    x0, arr\n\tadd     x0, x0, :lo12:arr",
+        ] {
+            assert!(actual.contains(expected), "missing:\n{}", expected);
+        }
+        assert!(!actual.contains(".globl count"));
+    }
+
+    // only runs where a cross-toolchain and qemu-user are installed
+    #[test]
+    fn run_under_qemu() {
+        if !has_tool("aarch64-linux-gnu-gcc") || !has_tool("qemu-aarch64") {
+            return;
+        }
+        let asm = setup_asm(
+            "
+int printf(const char *fmt, ...);
+struct P { int x; long y; char c; };
+int sum(int n) { return n ? n + sum(n - 1) : 0; }
+long many(long a, long b, long c, long d, long e, long f, long g, long h, long i, long j) {
+    return a - j;
+}
+int main() {
+    struct P p = {1, 2, 'c'};
+    struct P q;
+    unsigned u = -1;
+    int (*fp)(int) = sum;
+    q = p;
+    printf(\"%d %ld %c %d %ld %u %d\\n\", q.x, q.y, q.c, fp(10), many(1,2,3,4,5,6,7,8,9,10), u >> 28, -7 / 2);
+    return 0;
+}",
+        );
+
+        let dir = std::env::temp_dir();
+        let asm_file = dir.join("wrecc_aarch64_test.s");
+        let exe_file = dir.join("wrecc_aarch64_test");
+        std::fs::write(&asm_file, asm).unwrap();
+
+        let status = std::process::Command::new("aarch64-linux-gnu-gcc")
+            .arg("-static")
+            .arg(&asm_file)
+            .arg("-o")
+            .arg(&exe_file)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let output = std::process::Command::new("qemu-aarch64")
+            .arg(&exe_file)
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "1 2 c 55 -9 15 -3\n");
+    }
+}
@@ -0,0 +1,1404 @@
+//! Prints the [MIR](crate::compiler::typechecker::mir) as textual LLVM IR, so that programs can
+//! be compared with, optimized and profiled using `opt` and `llc`.<br>
+//! Just like clang at `-O0` every variable is an `alloca` in the entry-block which is loaded from
+//! and stored to on every access, leaving SSA-construction to `mem2reg`.
+//! Structs are laid out without padding so they are printed as packed types and all memory is
+//! accessed with an alignment of 1.<br>
+//! The IR uses opaque pointers, which `llvm-as` before version 15 only accepts with
+//! `-opaque-pointers`.
+
+use crate::compiler::codegen::arch::*;
+use crate::compiler::codegen::StaticLabels;
+use crate::compiler::common::{environment::*, token::*};
+use crate::compiler::typechecker::mir::stmt::*;
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::rc::Rc;
+
+// locals are identified by the address of their entry in the symbol-table
+type SymbolKey = *const RefCell<Symbol>;
+
+/// Generates the LLVM IR module for the given target
+pub fn translate(
+    target: Target,
+    external_decls: Vec<ExternalDeclaration>,
+    const_labels: ConstLabels,
+) -> String {
+    Llvm::new(const_labels).translate(target, external_decls)
+}
+
+struct Llvm {
+    // named struct- and union-types and their definition
+    structs: Vec<(StructRef, String, String)>,
+
+    // global variables, string-literals and function-definitions
+    globals: Vec<String>,
+    functions: Vec<String>,
+
+    // map containing strings and their corresponding label-index
+    const_labels: ConstLabels,
+
+    // same as in the x86-64 codegen, static variables in different scopes get an index appended
+    static_labels: StaticLabels,
+
+    // types of all declared functions and global variables
+    declarations: HashMap<String, Type>,
+    defined: HashSet<String>,
+
+    // declarations that are used and have to be defined or declared externally
+    referenced: Vec<String>,
+    // declarations of the llvm-intrinsics used for copying and zeroing memory
+    intrinsics: Vec<String>,
+
+    // state of the function currently being printed
+    func: FuncState,
+}
+
+#[derive(Default)]
+struct FuncState {
+    allocas: Vec<String>,
+    body: Vec<String>,
+
+    // pointers to the allocas of all locals
+    locals: HashMap<SymbolKey, String>,
+    names: HashSet<String>,
+
+    temp_index: usize,
+    label_index: usize,
+
+    // name of the basic-block instructions are currently added to
+    current_block: String,
+    // the current block already ended with a terminator
+    terminated: bool,
+
+    // type of the returned value
+    return_type: String,
+
+    // loop labels saved so that break and continue jump to them
+    jump_labels: Vec<(usize, usize)>,
+
+    // case/default-labels get defined in each switch and then popped in order of appearance
+    switch_labels: Vec<usize>,
+}
+
+impl Llvm {
+    fn new(const_labels: ConstLabels) -> Self {
+        Llvm {
+            structs: Vec::new(),
+            globals: Vec::new(),
+            functions: Vec::new(),
+            const_labels,
+            static_labels: StaticLabels(HashMap::new()),
+            declarations: HashMap::new(),
+            defined: HashSet::new(),
+            referenced: Vec::new(),
+            intrinsics: Vec::new(),
+            func: FuncState::default(),
+        }
+    }
+
+    fn translate(mut self, target: Target, external_decls: Vec<ExternalDeclaration>) -> String {
+        let mut strings: Vec<(String, usize)> = self.const_labels.clone().into_iter().collect();
+        strings.sort_by_key(|(_, label)| *label);
+        for (data, label) in strings {
+            self.globals.push(format!(
+                "@{} = private unnamed_addr constant [{} x i8] c\"{}\\00\", align 1",
+                string_label(label),
+                data.len() + 1,
+                escape(data.as_bytes())
+            ));
+        }
+
+        for decl in external_decls {
+            match decl {
+                ExternalDeclaration::Declaration(decls) => self.global_declaration(decls),
+                ExternalDeclaration::Function(func, func_symbol, stmts) => {
+                    self.function_definition(func, func_symbol, stmts)
+                }
+            }
+        }
+
+        // sections are separated by an empty line
+        let mut sections = Vec::new();
+        if let Some(triple) = triple(target) {
+            sections.push(format!("target triple = \"{}\"\n", triple));
+        }
+        let structs: String = self
+            .structs
+            .iter()
+            .map(|(_, name, definition)| format!("%{} = type {}\n", name, definition))
+            .collect();
+        let globals: String = self
+            .globals
+            .iter()
+            .map(|global| format!("{}\n", global))
+            .collect();
+        sections.extend([structs, globals]);
+        sections.append(&mut self.functions);
+
+        // everything used but not defined in this file is resolved by the linker
+        let mut externals = String::new();
+        for name in std::mem::take(&mut self.referenced) {
+            if self.defined.contains(&name) {
+                continue;
+            }
+            match self.declarations[&name].clone() {
+                Type::Function(func_type) => {
+                    let params = self.params(&func_type);
+                    writeln!(
+                        externals,
+                        "declare {} @{}({})",
+                        self.value_type(&func_type.return_type.ty),
+                        name,
+                        params
+                    )
+                    .unwrap();
+                }
+                ty => writeln!(externals, "@{} = external global {}", name, self.ll_type(&ty)).unwrap(),
+            }
+        }
+        for intrinsic in self.intrinsics.iter() {
+            writeln!(externals, "{}", intrinsic).unwrap();
+        }
+        sections.push(externals);
+
+        sections.retain(|section| !section.is_empty());
+        sections.join("\n")
+    }
+
+    fn global_declaration(&mut self, declarators: Vec<Declarator>) {
+        for declarator in declarators {
+            let var_symbol = declarator.entry.borrow().clone();
+            let ty = var_symbol.qtype.ty.clone();
+
+            if declarator.name == var_symbol.token && !var_symbol.is_extern() {
+                let name = declarator.name.unwrap_string();
+                let label_name = if var_symbol.is_static() {
+                    self.static_labels.update(name)
+                } else {
+                    name
+                };
+
+                self.declare_global_var(
+                    label_name,
+                    var_symbol.is_static(),
+                    declarator.entry,
+                    declarator.init,
+                )
+            } else {
+                let name = declarator.name.unwrap_string();
+                self.declarations.entry(name.clone()).or_insert(ty.clone());
+
+                // still needs a register when used before its definition
+                if var_symbol.reg.is_none() {
+                    declarator
+                        .entry
+                        .borrow_mut()
+                        .set_reg(Register::Label(LabelRegister::Var(
+                            name,
+                            ty,
+                            var_symbol.is_extern(),
+                        )))
+                }
+            }
+        }
+    }
+    fn declare_global_var(
+        &mut self,
+        label_name: String,
+        is_static: bool,
+        var_symbol: SymbolRef,
+        init: Option<Init>,
+    ) {
+        let ty = var_symbol.borrow().qtype.ty.clone();
+        var_symbol
+            .borrow_mut()
+            .set_reg(Register::Label(LabelRegister::Var(
+                label_name.clone(),
+                ty.clone(),
+                false,
+            )));
+
+        // tentative definitions can be followed by the actual definition
+        if !self.defined.insert(label_name.clone()) {
+            if init.is_none() {
+                return;
+            }
+            self.globals
+                .retain(|global| !global.starts_with(&format!("@{} =", label_name)));
+        }
+        self.declarations.insert(label_name.clone(), ty.clone());
+
+        let data = static_data(&ty, init, &self.const_labels);
+        let (ll_type, value) = self.constant(&ty, data);
+        self.globals.push(format!(
+            "@{} = {}global {} {}, align 1",
+            label_name,
+            if is_static { "internal " } else { "" },
+            ll_type,
+            value
+        ));
+    }
+    // prints the static data as a constant of the variable's type if possible, otherwise as a
+    // packed struct holding each piece of data
+    fn constant(&mut self, ty: &Type, data: Vec<Data>) -> (String, String) {
+        if data.iter().all(|data| matches!(data, Data::Zero(_))) {
+            return (self.ll_type(ty), "zeroinitializer".to_string());
+        }
+
+        let mut fields = Vec::new();
+        for data in data {
+            fields.push(match data {
+                Data::Int(value, _) if ty.is_ptr() => ("ptr".to_string(), pointer_constant(value)),
+                Data::Int(value, size) => (format!("i{}", size * 8), int_constant(value, size)),
+                Data::Zero(size) => (format!("[{} x i8]", size), "zeroinitializer".to_string()),
+                Data::Address(name, offset) => ("ptr".to_string(), self.address_constant(name, offset)),
+            });
+        }
+
+        if fields.len() == 1 && is_value(ty) {
+            fields.remove(0)
+        } else {
+            let (types, values): (Vec<String>, Vec<String>) = fields
+                .into_iter()
+                .map(|(ll_type, value)| (ll_type.clone(), format!("{} {}", ll_type, value)))
+                .unzip();
+            (
+                format!("<{{ {} }}>", types.join(", ")),
+                format!("<{{ {} }}>", values.join(", ")),
+            )
+        }
+    }
+    fn address_constant(&mut self, name: String, offset: i64) -> String {
+        // string-literals are always defined
+        if !self
+            .const_labels
+            .values()
+            .any(|label| string_label(*label) == name)
+        {
+            self.reference(&name);
+        }
+        if offset == 0 {
+            format!("@{}", name)
+        } else {
+            format!("getelementptr (i8, ptr @{}, i64 {})", name, offset)
+        }
+    }
+    // records that the declaration has to be defined or declared externally
+    fn reference(&mut self, name: &str) {
+        if !self.referenced.iter().any(|referenced| referenced == name) {
+            self.referenced.push(name.to_string());
+        }
+    }
+
+    fn function_definition(&mut self, mut func: Function, func_symbol: SymbolRef, stmts: Vec<Stmt>) {
+        func_symbol
+            .borrow_mut()
+            .set_reg(Register::Label(LabelRegister::Var(
+                func.name.clone(),
+                func.return_type.ty.clone(),
+                false,
+            )));
+
+        let func_type = func_symbol.borrow().qtype.ty.clone();
+        self.declarations.insert(func.name.clone(), func_type);
+        self.defined.insert(func.name.clone());
+
+        self.func = FuncState {
+            return_type: self.value_type(&func.return_type.ty),
+            current_block: "entry".to_string(),
+            ..FuncState::default()
+        };
+        for value in func.labels.values_mut() {
+            *value = self.label();
+        }
+
+        let mut params = Vec::new();
+        for param_symbol in func.params.clone() {
+            let ty = param_symbol.borrow().qtype.ty.clone();
+            let name = self.local_name(&symbol_name(&param_symbol));
+            let slot = self.declare_var(param_symbol);
+
+            // aggregates are passed by their address but have to be copied
+            if ty.is_aggregate() {
+                self.copy(&slot, &format!("%{}", name), ty.size());
+            } else {
+                let ll_type = self.ll_type(&ty);
+                self.emit(format!("store {} %{}, ptr {}, align 1", ll_type, name, slot));
+            }
+            params.push(format!("{} %{}", self.value_type(&ty), name));
+        }
+        if func.variadic {
+            params.push("...".to_string());
+        }
+
+        for stmt in stmts {
+            self.stmt(&mut func, stmt);
+        }
+
+        // falling off the end of a function returns zero, which is only defined for `main`
+        if !self.func.terminated {
+            let ret = match self.func.return_type.as_str() {
+                "void" => "ret void".to_string(),
+                "ptr" => "ret ptr null".to_string(),
+                ll_type => format!("ret {} 0", ll_type),
+            };
+            self.terminate(ret);
+        }
+
+        let is_static = {
+            let symbol = func_symbol.borrow();
+            symbol.is_static() || (!symbol.is_extern() && func.is_inline)
+        };
+        let mut output = format!(
+            "define {}{} @{}({}) {{\nentry:\n",
+            if is_static { "internal " } else { "" },
+            self.value_type(&func.return_type.ty),
+            func.name,
+            params.join(", ")
+        );
+        for line in self.func.allocas.iter().chain(self.func.body.iter()) {
+            if line.ends_with(':') {
+                writeln!(output, "{}", line).unwrap();
+            } else {
+                writeln!(output, "  {}", line).unwrap();
+            }
+        }
+        output.push_str("}\n");
+        self.functions.push(output);
+
+        // declare all statically linked declarations that are declared inside of function-body
+        for (label_name, declarator) in std::mem::take(&mut func.static_declarations) {
+            let var_symbol = declarator.entry.borrow().clone();
+
+            if declarator.name == var_symbol.token && !var_symbol.is_extern() {
+                self.declare_global_var(label_name, true, declarator.entry, declarator.init)
+            }
+        }
+    }
+
+    fn temp(&mut self) -> String {
+        self.func.temp_index += 1;
+        format!("%{}", self.func.temp_index - 1)
+    }
+    fn label(&mut self) -> usize {
+        self.func.label_index += 1;
+        self.func.label_index
+    }
+    // adds an instruction to the current block, starting a new block if it is unreachable
+    fn emit(&mut self, instr: String) {
+        if self.func.terminated {
+            let label = self.label();
+            self.place(label);
+        }
+        self.func.body.push(instr);
+    }
+    fn emit_value(&mut self, instr: String) -> String {
+        let temp = self.temp();
+        self.emit(format!("{} = {}", temp, instr));
+        temp
+    }
+    fn terminate(&mut self, instr: String) {
+        self.emit(instr);
+        self.func.terminated = true;
+    }
+    fn jump(&mut self, label: usize) {
+        self.terminate(format!("br label %L{}", label));
+    }
+    // starts a new block, falling through from the previous one
+    fn place(&mut self, label: usize) {
+        if !self.func.terminated {
+            self.jump(label);
+        }
+        self.func.body.push(format!("L{}:", label));
+        self.func.current_block = format!("L{}", label);
+        self.func.terminated = false;
+    }
+    fn branch(&mut self, cond: String, true_label: usize, false_label: usize) {
+        self.terminate(format!(
+            "br i1 {}, label %L{}, label %L{}",
+            cond, true_label, false_label
+        ));
+    }
+
+    fn stmt(&mut self, func: &mut Function, statement: Stmt) {
+        match statement {
+            Stmt::Expr(expr) => {
+                self.expr(func, expr);
+            }
+            Stmt::Declaration(decls) => self.declaration(func, decls),
+            Stmt::Block(statements) => {
+                for stmt in statements {
+                    self.stmt(func, stmt)
+                }
+            }
+            Stmt::If(cond, then_branch, else_branch) => {
+                let then_label = self.label();
+                let done_label = self.label();
+                let else_label = if else_branch.is_some() {
+                    self.label()
+                } else {
+                    done_label
+                };
+
+                let cond = self.condition(func, cond);
+                self.branch(cond, then_label, else_label);
+
+                self.place(then_label);
+                self.stmt(func, *then_branch);
+
+                if let Some(else_branch) = else_branch {
+                    self.jump(done_label);
+                    self.place(else_label);
+                    self.stmt(func, *else_branch);
+                }
+                self.place(done_label);
+            }
+            Stmt::While(cond, body) => {
+                let cond_label = self.label();
+                let body_label = self.label();
+                let end_label = self.label();
+
+                self.place(cond_label);
+                let cond = self.condition(func, cond);
+                self.branch(cond, body_label, end_label);
+
+                self.place(body_label);
+                self.func.jump_labels.push((end_label, cond_label));
+                self.stmt(func, *body);
+                self.func.jump_labels.pop();
+
+                self.jump(cond_label);
+                self.place(end_label);
+            }
+            Stmt::Do(body, cond) => {
+                let body_label = self.label();
+                let cond_label = self.label();
+                let end_label = self.label();
+
+                self.place(body_label);
+                self.func.jump_labels.push((end_label, cond_label));
+                self.stmt(func, *body);
+                self.func.jump_labels.pop();
+
+                self.place(cond_label);
+                let cond = self.condition(func, cond);
+                self.branch(cond, body_label, end_label);
+                self.place(end_label);
+            }
+            Stmt::For(init, cond, inc, body) => {
+                let cond_label = self.label();
+                let body_label = self.label();
+                let inc_label = self.label();
+                let end_label = self.label();
+
+                if let Some(init) = init {
+                    self.stmt(func, *init);
+                }
+
+                self.place(cond_label);
+                if let Some(cond) = cond {
+                    let cond = self.condition(func, cond);
+                    self.branch(cond, body_label, end_label);
+                }
+
+                self.place(body_label);
+                self.func.jump_labels.push((end_label, inc_label));
+                self.stmt(func, *body);
+                self.func.jump_labels.pop();
+
+                self.place(inc_label);
+                if let Some(inc) = inc {
+                    self.expr(func, inc);
+                }
+                self.jump(cond_label);
+                self.place(end_label);
+            }
+            Stmt::Return(expr) => {
+                let ret = match expr {
+                    Some(expr) => {
+                        let value = self.expr(func, expr);
+                        format!("ret {} {}", self.func.return_type, value)
+                    }
+                    None => "ret void".to_string(),
+                };
+                self.terminate(ret);
+            }
+            Stmt::Break => self.jump(self.func.jump_labels.last().expect("typechecker").0),
+            Stmt::Continue => self.jump(self.func.jump_labels.last().expect("typechecker").1),
+            Stmt::Switch(cond, body) => self.switch_statement(func, cond, *body),
+            Stmt::Case(body) | Stmt::Default(body) => {
+                let label = self.func.switch_labels.pop().unwrap();
+                self.place(label);
+                self.stmt(func, *body);
+            }
+            Stmt::Goto(label) => self.jump(func.labels[&label]),
+            Stmt::Label(name, body) => {
+                self.place(func.labels[&name]);
+                self.stmt(func, *body);
+            }
+            // debug-information is only emitted for x86-64
+            Stmt::Located(_, body) => self.stmt(func, *body),
+        }
+    }
+    fn switch_statement(&mut self, func: &mut Function, cond: Expr, body: Stmt) {
+        let cases = func.switches.pop_front().unwrap();
+        let ll_type = self.value_type(&cond.qtype.ty);
+        let size = cond.qtype.ty.size();
+        let value = self.expr(func, cond);
+
+        let end_label = self.label();
+        let mut default_label = end_label;
+        let mut jump_table = Vec::new();
+        let mut labels = Vec::new();
+
+        for kind in cases.borrow().iter() {
+            let label = self.label();
+            match kind {
+                CaseKind::Case(literal) => jump_table.push(format!(
+                    "{} {}, label %L{}",
+                    ll_type,
+                    int_constant(literal_value(literal), size),
+                    label
+                )),
+                CaseKind::Default => default_label = label,
+            }
+            labels.push(label);
+        }
+        self.terminate(format!(
+            "switch {} {}, label %L{} [{}]",
+            ll_type,
+            value,
+            default_label,
+            jump_table.join(" ")
+        ));
+
+        let continue_label = self
+            .func
+            .jump_labels
+            .last()
+            .map_or(end_label, |(_, label)| *label);
+        self.func.jump_labels.push((end_label, continue_label));
+        self.func
+            .switch_labels
+            .append(&mut labels.into_iter().rev().collect());
+
+        self.stmt(func, body);
+        self.place(end_label);
+
+        self.func.jump_labels.pop();
+    }
+
+    fn declaration(&mut self, func: &mut Function, declarators: Vec<Declarator>) {
+        for declarator in declarators {
+            let var_symbol = declarator.entry.borrow().clone();
+
+            match var_symbol.storage_class {
+                Some(StorageClass::Extern | StorageClass::Static) => {
+                    let name = declarator.name.unwrap_string();
+                    let label_name = if var_symbol.is_static() {
+                        self.static_labels.update(name)
+                    } else {
+                        name
+                    };
+                    self.declarations
+                        .entry(label_name.clone())
+                        .or_insert(var_symbol.qtype.ty.clone());
+
+                    declarator
+                        .entry
+                        .borrow_mut()
+                        .set_reg(Register::Label(LabelRegister::Var(
+                            label_name.clone(),
+                            var_symbol.qtype.ty.clone(),
+                            var_symbol.is_extern(),
+                        )));
+                    func.static_declarations.push((label_name, declarator));
+                }
+                None | Some(StorageClass::Auto | StorageClass::Register)
+                    if declarator.name == var_symbol.token && !var_symbol.qtype.ty.is_func() =>
+                {
+                    let ty = var_symbol.qtype.ty;
+                    let slot = self.declare_var(Rc::clone(&declarator.entry));
+
+                    match declarator.init {
+                        Some(Init::Scalar(expr)) => {
+                            self.store(func, &slot, expr);
+                        }
+                        Some(Init::Aggr(list)) => {
+                            // first overwrite all entries with 0
+                            self.emit(format!(
+                                "call void @llvm.memset.p0.i64(ptr {}, i8 0, i64 {}, i1 false)",
+                                slot,
+                                ty.size()
+                            ));
+                            self.intrinsic("declare void @llvm.memset.p0.i64(ptr, i8, i64, i1)");
+
+                            for (expr, offset) in list {
+                                let address = self.byte_offset(&slot, offset);
+                                self.store(func, &address, expr);
+                            }
+                        }
+                        None => (),
+                    }
+                }
+                // only function-declarations can be redeclared inside of a function body
+                _ if var_symbol.reg.is_none() => {
+                    let name = declarator.name.unwrap_string();
+                    self.declarations
+                        .entry(name.clone())
+                        .or_insert(var_symbol.qtype.ty.clone());
+
+                    declarator
+                        .entry
+                        .borrow_mut()
+                        .set_reg(Register::Label(LabelRegister::Var(
+                            name,
+                            var_symbol.qtype.ty.clone(),
+                            var_symbol.is_extern(),
+                        )))
+                }
+                _ => (),
+            }
+        }
+    }
+    fn intrinsic(&mut self, declaration: &str) {
+        if !self.intrinsics.iter().any(|intrinsic| intrinsic == declaration) {
+            self.intrinsics.push(declaration.to_string());
+        }
+    }
+    // returns a name for a value that is unique inside of the function
+    fn local_name(&mut self, name: &str) -> String {
+        let mut unique = name.to_string();
+        let mut index = 0;
+        while !self.func.names.insert(unique.clone()) {
+            index += 1;
+            unique = format!("{}{}", name, index);
+        }
+        unique
+    }
+    // allocates the variable in the entry-block and returns the pointer to it
+    fn declare_var(&mut self, var_symbol: SymbolRef) -> String {
+        let ty = var_symbol.borrow().qtype.ty.clone();
+        let name = symbol_name(&var_symbol);
+        let slot = format!("%{}", self.local_name(&format!("{}.addr", name)));
+
+        let ll_type = self.ll_type(&ty);
+        self.func
+            .allocas
+            .push(format!("{} = alloca {}, align 1", slot, ll_type));
+        self.func.locals.insert(Rc::as_ptr(&var_symbol), slot.clone());
+
+        slot
+    }
+    fn byte_offset(&mut self, address: &str, offset: usize) -> String {
+        if offset == 0 {
+            address.to_string()
+        } else {
+            self.emit_value(format!(
+                "getelementptr inbounds i8, ptr {}, i64 {}",
+                address, offset
+            ))
+        }
+    }
+    fn copy(&mut self, dest: &str, src: &str, size: usize) {
+        self.emit(format!(
+            "call void @llvm.memcpy.p0.p0.i64(ptr {}, ptr {}, i64 {}, i1 false)",
+            dest, src, size
+        ));
+        self.intrinsic("declare void @llvm.memcpy.p0.p0.i64(ptr, ptr, i64, i1)");
+    }
+    // stores the value of the expression at the address, aggregates are copied
+    fn store(&mut self, func: &mut Function, address: &str, expr: Expr) -> String {
+        let ty = expr.qtype.ty.clone();
+        let value = self.expr(func, expr);
+
+        if ty.is_aggregate() {
+            self.copy(address, &value, ty.size());
+        } else {
+            let ll_type = self.ll_type(&ty);
+            self.emit(format!("store {} {}, ptr {}, align 1", ll_type, value, address));
+        }
+        value
+    }
+
+    // returns the pointer to an lvalue
+    fn address(&mut self, func: &mut Function, expr: Expr) -> String {
+        match expr.kind {
+            ExprKind::Ident(var_symbol) => {
+                if let Some(slot) = self.func.locals.get(&Rc::as_ptr(&var_symbol)) {
+                    return slot.clone();
+                }
+                match var_symbol.borrow().get_reg() {
+                    Register::Label(LabelRegister::Var(name, ..)) => {
+                        self.reference(&name);
+                        format!("@{}", name)
+                    }
+                    reg => unreachable!("identifier can't be stored in {:?}", reg),
+                }
+            }
+            ExprKind::Unary { token, right } if token.kind == TokenKind::Star => self.expr(func, *right),
+            ExprKind::MemberAccess { expr, member } => {
+                let ty = expr.qtype.ty.clone();
+                let base = self.address(func, *expr);
+
+                match &ty {
+                    Type::Struct(s) => {
+                        let index = s
+                            .members()
+                            .iter()
+                            .position(|(_, name)| name.unwrap_string() == member)
+                            .unwrap();
+                        let ll_type = self.ll_type(&ty);
+                        self.emit_value(format!(
+                            "getelementptr inbounds {}, ptr {}, i32 0, i32 {}",
+                            ll_type, base, index
+                        ))
+                    }
+                    // all members of a union start at its address
+                    _ => base,
+                }
+            }
+            ExprKind::String(name) => format!("@{}", string_label(self.const_labels[&name])),
+            // value of aggregate expressions is already their address
+            _ if expr.qtype.ty.is_aggregate() => self.expr(func, expr),
+            _ => unreachable!("not an lvalue: {:?}", expr.kind),
+        }
+    }
+    fn load(&mut self, func: &mut Function, expr: Expr) -> String {
+        let ty = expr.qtype.ty.clone();
+        let address = self.address(func, expr);
+
+        if is_value(&ty) {
+            let ll_type = self.ll_type(&ty);
+            self.emit_value(format!("load {}, ptr {}, align 1", ll_type, address))
+        } else {
+            address
+        }
+    }
+
+    // returns the operand holding the value of the expression, aggregates evaluate to their address
+    fn expr(&mut self, func: &mut Function, expr: Expr) -> String {
+        let ty = expr.qtype.ty.clone();
+
+        match expr.kind {
+            ExprKind::Binary { left, token, right } => self.binary(func, *left, token.kind, *right, &ty),
+            ExprKind::Comparison { .. } | ExprKind::Logical { .. } => {
+                let cond = self.condition(func, expr);
+                let ll_type = self.ll_type(&ty);
+                self.emit_value(format!("zext i1 {} to {}", cond, ll_type))
+            }
+            ExprKind::Unary { token, right } => match token.kind {
+                TokenKind::Amp => self.address(func, *right),
+                // dereferenced function-pointers are still called through the pointer
+                TokenKind::Star if ty.is_func() => self.expr(func, *right),
+                TokenKind::Star => self.load(
+                    func,
+                    Expr {
+                        kind: ExprKind::Unary { token, right },
+                        ..expr
+                    },
+                ),
+                TokenKind::Bang => {
+                    let cond = self.condition(func, *right);
+                    let negated = self.emit_value(format!("xor i1 {}, true", cond));
+                    let ll_type = self.ll_type(&ty);
+                    self.emit_value(format!("zext i1 {} to {}", negated, ll_type))
+                }
+                TokenKind::Minus => {
+                    let value = self.expr(func, *right);
+                    let ll_type = self.ll_type(&ty);
+                    self.emit_value(format!("sub {} 0, {}", ll_type, value))
+                }
+                TokenKind::Tilde => {
+                    let value = self.expr(func, *right);
+                    let ll_type = self.ll_type(&ty);
+                    self.emit_value(format!("xor {} {}, -1", ll_type, value))
+                }
+                _ => self.expr(func, *right),
+            },
+            ExprKind::Assign { l_expr, r_expr } => {
+                let address = self.address(func, *l_expr);
+                let value = self.store(func, &address, *r_expr);
+
+                if ty.is_aggregate() {
+                    address
+                } else {
+                    value
+                }
+            }
+            ExprKind::CompoundAssign { expr, tmp_symbol } => {
+                self.declare_var(tmp_symbol);
+                self.expr(func, *expr)
+            }
+            ExprKind::Ident(symbol) if ty.is_func() => {
+                let Register::Label(LabelRegister::Var(name, ..)) = symbol.borrow().get_reg() else {
+                    unreachable!("functions are labels")
+                };
+                self.reference(&name);
+                format!("@{}", name)
+            }
+            ExprKind::Ident(_) | ExprKind::MemberAccess { .. } => self.load(func, expr),
+            ExprKind::Call { caller, args } => self.call(func, *caller, args),
+            ExprKind::Cast { expr, new_type, .. } => {
+                let old_type = expr.qtype.ty.clone();
+                let value = self.expr(func, *expr);
+
+                self.convert(value, &old_type, &new_type)
+            }
+            ExprKind::Scale {
+                expr,
+                direction,
+                by_amount,
+                ..
+            } => {
+                let value = self.expr(func, *expr);
+                let op = match direction {
+                    ScaleDirection::Up => "mul",
+                    ScaleDirection::Down => "sdiv",
+                };
+                let ll_type = self.ll_type(&ty);
+                self.emit_value(format!("{} {} {}, {}", op, ll_type, value, by_amount))
+            }
+            ExprKind::Ternary {
+                cond,
+                true_expr,
+                false_expr,
+            } => {
+                let true_label = self.label();
+                let false_label = self.label();
+                let end_label = self.label();
+
+                let cond = self.condition(func, *cond);
+                self.branch(cond, true_label, false_label);
+
+                self.place(true_label);
+                let true_value = self.expr(func, *true_expr);
+                let true_block = self.func.current_block.clone();
+                self.jump(end_label);
+
+                self.place(false_label);
+                let false_value = self.expr(func, *false_expr);
+                let false_block = self.func.current_block.clone();
+                self.place(end_label);
+
+                if ty.is_void() {
+                    String::new()
+                } else {
+                    let value_type = self.value_type(&ty);
+                    self.emit_value(format!(
+                        "phi {} [ {}, %{} ], [ {}, %{} ]",
+                        value_type, true_value, true_block, false_value, false_block
+                    ))
+                }
+            }
+            ExprKind::Comma { left, right } => {
+                self.expr(func, *left);
+                self.expr(func, *right)
+            }
+            ExprKind::String(name) => format!("@{}", string_label(self.const_labels[&name])),
+            ExprKind::Literal(literal) if ty.is_ptr() => pointer_constant(literal_value(&literal)),
+            ExprKind::Literal(literal) => int_constant(literal_value(&literal), ty.size()),
+            ExprKind::Nop => String::new(),
+        }
+    }
+    fn binary(&mut self, func: &mut Function, left: Expr, op: TokenKind, right: Expr, ty: &Type) -> String {
+        // pointer-arithmetic indexes the pointer with the unscaled offset
+        if matches!(op, TokenKind::Plus | TokenKind::Minus)
+            && (left.qtype.ty.is_ptr() || right.qtype.ty.is_ptr())
+        {
+            let (pointer, offset) = if left.qtype.ty.is_ptr() {
+                (left, right)
+            } else {
+                (right, left)
+            };
+            let pointee = pointer.qtype.deref_at().unwrap().ty;
+
+            // subscripts convert the offset to the pointer-type
+            let mut offset = offset;
+            while let ExprKind::Cast { expr, .. } = offset.kind {
+                offset = *expr;
+            }
+
+            let (element_type, offset) = match offset.kind {
+                ExprKind::Scale {
+                    expr,
+                    direction: ScaleDirection::Up,
+                    by_amount,
+                    ..
+                } if pointee.size() == by_amount => (self.ll_type(&pointee), *expr),
+                _ => ("i8".to_string(), offset),
+            };
+            let pointer = self.expr(func, pointer);
+            let mut index = match offset.kind {
+                // constant offsets can have the pointer-type
+                ExprKind::Literal(literal) => literal_value(&literal).to_string(),
+                _ => {
+                    let offset_type = offset.qtype.ty.clone();
+                    let index = self.expr(func, offset);
+                    self.convert(index, &offset_type, &Type::Primitive(Primitive::Long(false)))
+                }
+            };
+            if op == TokenKind::Minus {
+                index = self.emit_value(format!("sub i64 0, {}", index));
+            }
+
+            let result = self.emit_value(format!(
+                "getelementptr inbounds {}, ptr {}, i64 {}",
+                element_type, pointer, index
+            ));
+            return if ty.is_ptr() {
+                result
+            } else {
+                let ll_type = self.ll_type(ty);
+                self.emit_value(format!("ptrtoint ptr {} to {}", result, ll_type))
+            };
+        }
+
+        let right_type = right.qtype.ty.clone();
+        let left = self.expr(func, left);
+        let right = self.expr(func, right);
+        // shifts don't convert their right operand
+        let right = self.convert(right, &right_type, ty);
+
+        let is_unsigned = ty.is_unsigned();
+        let instr = match op {
+            TokenKind::Plus => "add",
+            TokenKind::Minus => "sub",
+            TokenKind::Star => "mul",
+            TokenKind::Slash if is_unsigned => "udiv",
+            TokenKind::Slash => "sdiv",
+            TokenKind::Mod if is_unsigned => "urem",
+            TokenKind::Mod => "srem",
+            TokenKind::Amp => "and",
+            TokenKind::Pipe => "or",
+            TokenKind::Xor => "xor",
+            TokenKind::LessLess => "shl",
+            TokenKind::GreaterGreater if is_unsigned => "lshr",
+            TokenKind::GreaterGreater => "ashr",
+            _ => unreachable!("not a binary operator: {:?}", op),
+        };
+        let ll_type = self.ll_type(ty);
+        self.emit_value(format!("{} {} {}, {}", instr, ll_type, left, right))
+    }
+    // returns an `i1` which is true if the expression isn't zero
+    fn condition(&mut self, func: &mut Function, expr: Expr) -> String {
+        match expr.kind {
+            ExprKind::Comparison { left, token, right } => {
+                let operand_type = left.qtype.ty.clone();
+                let right_type = right.qtype.ty.clone();
+                let is_unsigned = operand_type.is_unsigned() || operand_type.is_ptr();
+
+                let left = self.expr(func, *left);
+                let right = self.expr(func, *right);
+                let right = self.convert(right, &right_type, &operand_type);
+
+                let predicate = match (token.kind, is_unsigned) {
+                    (TokenKind::EqualEqual, _) => "eq",
+                    (TokenKind::BangEqual, _) => "ne",
+                    (TokenKind::Less, false) => "slt",
+                    (TokenKind::Less, true) => "ult",
+                    (TokenKind::Greater, false) => "sgt",
+                    (TokenKind::Greater, true) => "ugt",
+                    (TokenKind::LessEqual, false) => "sle",
+                    (TokenKind::LessEqual, true) => "ule",
+                    (TokenKind::GreaterEqual, false) => "sge",
+                    (TokenKind::GreaterEqual, true) => "uge",
+                    (op, _) => unreachable!("not a comparison: {:?}", op),
+                };
+                let value_type = self.value_type(&operand_type);
+                self.emit_value(format!("icmp {} {} {}, {}", predicate, value_type, left, right))
+            }
+            ExprKind::Logical { left, token, right } => {
+                let right_label = self.label();
+                let end_label = self.label();
+
+                let left = self.condition(func, *left);
+                let left_block = self.func.current_block.clone();
+                let short_circuit = match token.kind {
+                    TokenKind::AmpAmp => {
+                        self.branch(left, right_label, end_label);
+                        "false"
+                    }
+                    TokenKind::PipePipe => {
+                        self.branch(left, end_label, right_label);
+                        "true"
+                    }
+                    _ => unreachable!(),
+                };
+
+                self.place(right_label);
+                let right = self.condition(func, *right);
+                let right_block = self.func.current_block.clone();
+                self.place(end_label);
+
+                self.emit_value(format!(
+                    "phi i1 [ {}, %{} ], [ {}, %{} ]",
+                    short_circuit, left_block, right, right_block
+                ))
+            }
+            _ => {
+                let ty = expr.qtype.ty.clone();
+                let value = self.expr(func, expr);
+
+                if ty.is_ptr() {
+                    self.emit_value(format!("icmp ne ptr {}, null", value))
+                } else {
+                    let ll_type = self.ll_type(&ty);
+                    self.emit_value(format!("icmp ne {} {}, 0", ll_type, value))
+                }
+            }
+        }
+    }
+    fn convert(&mut self, value: String, from: &Type, to: &Type) -> String {
+        let (old_type, new_type) = (self.value_type(from), self.value_type(to));
+
+        if new_type == "void" {
+            return String::new();
+        }
+        if old_type == new_type {
+            return value;
+        }
+        if new_type == "ptr" && value == "0" {
+            return "null".to_string();
+        }
+
+        let instr = match (old_type.as_str(), new_type.as_str()) {
+            ("ptr", _) => "ptrtoint",
+            (_, "ptr") => "inttoptr",
+            _ if from.size() > to.size() => "trunc",
+            _ if from.is_unsigned() => "zext",
+            _ => "sext",
+        };
+        self.emit_value(format!("{} {} {} to {}", instr, old_type, value, new_type))
+    }
+    fn call(&mut self, func: &mut Function, caller: Expr, args: Vec<Expr>) -> String {
+        let func_type = function_type(&caller.qtype.ty).clone();
+
+        let mut operands = Vec::new();
+        for arg in args {
+            let ty = arg.qtype.ty.clone();
+            let value = self.expr(func, arg);
+            operands.push(format!("{} {}", self.value_type(&ty), value));
+        }
+
+        let callee = match direct_callee(&caller) {
+            Some(name) => {
+                self.declarations
+                    .entry(name.clone())
+                    .or_insert(Type::Function(func_type.clone()));
+                self.reference(&name);
+                format!("@{}", name)
+            }
+            None => self.expr(func, caller),
+        };
+
+        let return_type = self.value_type(&func_type.return_type.ty);
+        let call = format!(
+            "call {} ({}) {}({})",
+            return_type,
+            self.params(&func_type),
+            callee,
+            operands.join(", ")
+        );
+        if return_type == "void" {
+            self.emit(call);
+            String::new()
+        } else {
+            self.emit_value(call)
+        }
+    }
+    fn params(&mut self, func_type: &FuncType) -> String {
+        let mut params: Vec<String> = func_type
+            .params
+            .iter()
+            .map(|param| self.value_type(&param.ty))
+            .collect();
+        if func_type.variadic {
+            params.push("...".to_string());
+        }
+        params.join(", ")
+    }
+
+    // type of the value an expression evaluates to
+    fn value_type(&mut self, ty: &Type) -> String {
+        if ty.is_aggregate() || ty.is_func() {
+            "ptr".to_string()
+        } else {
+            self.ll_type(ty)
+        }
+    }
+    // type of an object in memory
+    fn ll_type(&mut self, ty: &Type) -> String {
+        match ty {
+            Type::Primitive(Primitive::Void) => "void".to_string(),
+            Type::Primitive(_) | Type::Enum(..) => format!("i{}", ty.size() * 8),
+            Type::Pointer(_) | Type::Function(_) => "ptr".to_string(),
+            Type::Array(of, size) => {
+                let amount = match size {
+                    ArraySize::Known(amount) => *amount,
+                    // tentative array assumed to have one element
+                    ArraySize::Unknown => 1,
+                };
+                format!("[{} x {}]", amount, self.ll_type(&of.ty))
+            }
+            Type::Struct(s) | Type::Union(s) => match s {
+                StructKind::Named(name, struct_ref) => self.named_struct(name, struct_ref, ty),
+                StructKind::Unnamed(..) => self.struct_body(ty),
+            },
+        }
+    }
+    fn struct_body(&mut self, ty: &Type) -> String {
+        match ty {
+            Type::Struct(s) => {
+                let members: Vec<String> = s
+                    .members()
+                    .iter()
+                    .map(|(member_type, _)| self.ll_type(&member_type.ty))
+                    .collect();
+                format!("<{{ {} }}>", members.join(", "))
+            }
+            _ => format!("<{{ [{} x i8] }}>", ty.size()),
+        }
+    }
+    // named types are defined once and then referred to by name
+    fn named_struct(&mut self, name: &str, struct_ref: &StructRef, ty: &Type) -> String {
+        if let Some((_, ll_name, _)) = self.structs.iter().find(|(s, ..)| s == struct_ref) {
+            return format!("%{}", ll_name);
+        }
+
+        let prefix = if matches!(ty, Type::Struct(_)) {
+            "struct"
+        } else {
+            "union"
+        };
+        let mut ll_name = format!("{}.{}", prefix, name);
+        let mut index = 0;
+        while self.structs.iter().any(|(_, existing, _)| *existing == ll_name) {
+            index += 1;
+            ll_name = format!("{}.{}.{}", prefix, name, index);
+        }
+
+        // members can refer to the struct itself
+        self.structs
+            .push((struct_ref.clone(), ll_name.clone(), String::new()));
+        let definition = if struct_ref.is_complete() {
+            self.struct_body(ty)
+        } else {
+            "opaque".to_string()
+        };
+        self.structs.iter_mut().find(|(s, ..)| s == struct_ref).unwrap().2 = definition;
+
+        format!("%{}", ll_name)
+    }
+}
+
+// temporaries of compound-assignments are named after their operator
+fn symbol_name(symbol: &SymbolRef) -> String {
+    match &symbol.borrow().token.kind {
+        TokenKind::Ident(name, ..) => name.clone(),
+        _ => "tmp".to_string(),
+    }
+}
+
+fn triple(target: Target) -> Option<&'static str> {
+    match target {
+        Target::X86_64 => Some("x86_64-unknown-linux-gnu"),
+        Target::Aarch64 => Some("aarch64-unknown-linux-gnu"),
+        Target::Riscv64 => Some("riscv64-unknown-linux-gnu"),
+        // the MIR is laid out for LP64 which doesn't match the wasm32 data-layout
+        Target::Wasm32 => None,
+    }
+}
+
+fn function_type(ty: &Type) -> &FuncType {
+    match ty {
+        Type::Function(func_type) => func_type,
+        Type::Pointer(to) => function_type(&to.ty),
+        _ => unreachable!("not a function: {:?}", ty),
+    }
+}
+
+// constants are printed as signed values of their width
+fn int_constant(value: i64, size: usize) -> String {
+    match size {
+        1 => (value as i8).to_string(),
+        2 => (value as i16).to_string(),
+        4 => (value as i32).to_string(),
+        _ => value.to_string(),
+    }
+}
+fn pointer_constant(value: i64) -> String {
+    if value == 0 {
+        "null".to_string()
+    } else {
+        format!("inttoptr (i64 {} to ptr)", value)
+    }
+}
+
+fn escape(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| match *byte {
+            b'"' | b'\\' => format!("\\{:02X}", byte),
+            0x20..=0x7e => (*byte as char).to_string(),
+            _ => format!("\\{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parser::tests::setup;
+    use crate::compiler::typechecker::TypeChecker;
+
+    fn setup_llvm(input: &str) -> String {
+        let parse_tree = setup(input).parse().unwrap();
+        let (mir, const_labels) = TypeChecker::new().check(parse_tree).unwrap();
+
+        translate(Target::X86_64, mir, const_labels)
+    }
+
+    #[test]
+    fn function() {
+        let actual = setup_llvm("int add(int a, int b) { return a + b; }");
+        let expected = "target triple = \"x86_64-unknown-linux-gnu\"
+
+define i32 @add(i32 %a, i32 %b) {
+entry:
+  %a.addr = alloca i32, align 1
+  %b.addr = alloca i32, align 1
+  store i32 %a, ptr %a.addr, align 1
+  store i32 %b, ptr %b.addr, align 1
+  %0 = load i32, ptr %a.addr, align 1
+  %1 = load i32, ptr %b.addr, align 1
+  %2 = add i32 %0, %1
+  ret i32 %2
+}
+";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn struct_and_index() {
+        let actual = setup_llvm(
+            "
+struct S { char c; long l; };
+long get(struct S *s) { return s->l; }
+int idx(int *p, long i) { return p[i]; }",
+        );
+
+        assert!(actual.contains("%struct.S = type <{ i8, i64 }>\n"));
+        assert!(actual.contains("  %1 = getelementptr inbounds %struct.S, ptr %0, i32 0, i32 1\n"));
+        assert!(actual.contains("  %2 = getelementptr inbounds i32, ptr %0, i64 %1\n"));
+    }
+
+    #[test]
+    fn globals() {
+        let actual = setup_llvm(
+            "
+int x = 3;
+int *px = &x;
+static char *s = \"hi\\n\";
+int arr[4] = {1, 2};
+int z[2];
+extern int e;
+int printf(const char *, ...);
+int main() { printf(s, e); }",
+        );
+
+        for global in [
+            "@.LS0 = private unnamed_addr constant [4 x i8] c\"hi\\0A\\00\", align 1\n",
+            "@x = global i32 3, align 1\n",
+            "@px = global ptr @x, align 1\n",
+            "@s.0 = internal global ptr @.LS0, align 1\n",
+            "@arr = global <{ i32, i32, [8 x i8] }> <{ i32 1, i32 2, [8 x i8] zeroinitializer }>, align 1\n",
+            "@z = global [2 x i32] zeroinitializer, align 1\n",
+            "@e = external global i32\n",
+            "declare i32 @printf(ptr, ...)\n",
+        ] {
+            assert!(actual.contains(global), "missing '{}' in:\n{}", global, actual);
+        }
+    }
+
+    #[test]
+    fn control_flow() {
+        let actual = setup_llvm(
+            "
+int f(int a, int b) {
+    int r = a && b;
+    switch (a) {
+    case 1: r = 2;
+    default: break;
+    }
+    return r ? 1 : 2;
+}",
+        );
+
+        assert!(actual.contains("switch i32 "));
+        assert!(actual.contains(" = phi i1 "));
+        assert!(actual.contains(" = phi i32 [ 1, "));
+        assert!(!actual.contains("target triple = \"wasm"));
+    }
+
+    // checks that the output is accepted by llvm-as if it's installed
+    #[test]
+    fn llvm_as_accepts() {
+        let program = "
+int printf(const char *fmt, ...);
+struct Node { int value; struct Node *next; };
+struct Node n2 = {2, 0};
+struct Node n1 = {1, &n2};
+union U { int i; char c[8]; };
+int counter;
+int sum(struct Node *n) {
+    int total = 0;
+    for (; n; n = n->next) total += n->value;
+    return total;
+}
+int main() {
+    union U u;
+    struct Node copy = n1;
+    char s[] = \"x\\\"y\\n\";
+    long diff = &s[3] - s;
+    u.i = 65;
+    ++counter;
+    while (counter < 10 || !diff) counter *= 2;
+    printf(\"%d %d %c %ld %s\", sum(&copy), counter, u.c[0], diff, s);
+    return 0;
+}";
+        let dir = std::env::temp_dir().join(format!("wrecc-llvm-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("program.ll");
+        std::fs::write(&file, setup_llvm(program)).unwrap();
+
+        for llvm_as in ["llvm-as", "llvm-as-14"] {
+            let run = |args: &[&str]| {
+                std::process::Command::new(llvm_as)
+                    .args(args)
+                    .arg(&file)
+                    .arg("-o")
+                    .arg(dir.join("program.bc"))
+                    .output()
+            };
+            let Ok(output) = run(&[]) else { continue };
+
+            // older versions only accept opaque pointers when asked to
+            let output = if output.status.success() {
+                output
+            } else {
+                run(&["-opaque-pointers"]).unwrap()
+            };
+            assert!(
+                output.status.success(),
+                "{}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
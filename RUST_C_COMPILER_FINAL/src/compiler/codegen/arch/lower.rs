@@ -0,0 +1,572 @@
+//! Lowers the [MIR](crate::compiler::typechecker::mir) into the instructions of a [Machine].<br>
+//! Doesn't do any register-allocation: every expression ends up in the accumulator and the left
+//! operand of binary operations is pushed onto the stack while evaluating the right one.
+
+use crate::compiler::codegen::arch::*;
+use crate::compiler::codegen::register::*;
+use crate::compiler::codegen::StaticLabels;
+use crate::compiler::common::{environment::SymbolRef, token::*, types::*};
+use crate::compiler::typechecker::mir::{decl::*, expr::*, stmt::*};
+use crate::compiler::typechecker::{align_by, create_label, ConstLabels};
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+pub struct Lower<M: Machine> {
+    machine: M,
+
+    // index of current label
+    label_index: usize,
+
+    // map containing strings and their corresponding label-index
+    const_labels: ConstLabels,
+
+    // loop labels saved so that break and continue jump to them
+    jump_labels: Vec<(usize, usize)>,
+
+    // same as in the x86-64 codegen, static variables in different scopes get an index appended
+    static_labels: StaticLabels,
+
+    // case/default-labels get defined in each switch and then popped in order of appearance
+    switch_labels: Vec<usize>,
+}
+impl<M: Machine> Lower<M> {
+    pub fn new(machine: M, const_labels: ConstLabels) -> Self {
+        Lower {
+            machine,
+            const_labels,
+            label_index: 0,
+            jump_labels: Vec::new(),
+            static_labels: StaticLabels(HashMap::new()),
+            switch_labels: Vec::new(),
+        }
+    }
+
+    pub fn translate(mut self, external_decls: Vec<ExternalDeclaration>) -> String {
+        let mut strings: Vec<(String, usize)> = self.const_labels.clone().into_iter().collect();
+        strings.sort_by_key(|(_, label)| *label);
+        for (data, label) in strings {
+            self.machine.string(label, &data);
+        }
+
+        for decl in external_decls {
+            match decl {
+                ExternalDeclaration::Declaration(decls) => self.global_declaration(decls),
+                ExternalDeclaration::Function(func, func_symbol, stmts) => {
+                    self.function_definition(func, func_symbol, stmts)
+                }
+            }
+        }
+
+        self.machine.finish()
+    }
+
+    fn global_declaration(&mut self, declarators: Vec<Declarator>) {
+        for declarator in declarators {
+            let var_symbol = declarator.entry.borrow().clone();
+            let ty = var_symbol.qtype.ty.clone();
+
+            if declarator.name == var_symbol.token && !var_symbol.is_extern() {
+                let name = declarator.name.unwrap_string();
+                let label_name = if var_symbol.is_static() {
+                    self.static_labels.update(name)
+                } else {
+                    name
+                };
+
+                self.declare_global_var(label_name, ty, declarator.entry, declarator.init)
+            }
+            // still needs a register when used before its definition
+            else if var_symbol.reg.is_none() {
+                declarator
+                    .entry
+                    .borrow_mut()
+                    .set_reg(Register::Label(LabelRegister::Var(
+                        declarator.name.unwrap_string(),
+                        ty,
+                        var_symbol.is_extern(),
+                    )))
+            }
+        }
+    }
+    fn declare_global_var(
+        &mut self,
+        label_name: String,
+        ty: Type,
+        var_symbol: SymbolRef,
+        init: Option<Init>,
+    ) {
+        let is_static = var_symbol.borrow().is_static();
+
+        // register has to be set before initializer is evaluated: `void *p = &p;`
+        var_symbol
+            .borrow_mut()
+            .set_reg(Register::Label(LabelRegister::Var(
+                label_name.clone(),
+                ty.clone(),
+                false,
+            )));
+
+        let data = static_data(&ty, init, &self.const_labels);
+
+        self.machine.global(&label_name, is_static, data);
+    }
+    fn function_definition(&mut self, mut func: Function, func_symbol: SymbolRef, stmts: Vec<Stmt>) {
+        func_symbol
+            .borrow_mut()
+            .set_reg(Register::Label(LabelRegister::Var(
+                func.name.clone(),
+                func.return_type.ty.clone(),
+                false,
+            )));
+
+        func.epilogue_index = create_label(&mut self.label_index);
+        for value in func.labels.values_mut() {
+            *value = create_label(&mut self.label_index);
+        }
+
+        let is_static = {
+            let symbol = func_symbol.borrow();
+            symbol.is_static() || (!symbol.is_extern() && func.is_inline)
+        };
+        self.machine
+            .function(&func.name, is_static, align_by(func.stack_size, 16));
+
+        for (i, param_symbol) in func.params.clone().into_iter().enumerate() {
+            let ty = param_symbol.borrow().qtype.ty.clone();
+            let offset = self.declare_var(&mut func, param_symbol);
+
+            self.machine.param(i, offset, &ty);
+        }
+
+        for stmt in stmts {
+            self.visit_stmt(&mut func, stmt);
+        }
+        self.machine.epilogue(func.epilogue_index);
+
+        // declare all statically linked declarations that are declared inside of function-body
+        for (label_name, declarator) in std::mem::take(&mut func.static_declarations) {
+            let var_symbol = declarator.entry.borrow().clone();
+
+            if declarator.name == var_symbol.token && !var_symbol.is_extern() {
+                self.declare_global_var(label_name, var_symbol.qtype.ty, declarator.entry, declarator.init)
+            }
+        }
+    }
+
+    fn visit_stmt(&mut self, func: &mut Function, statement: Stmt) {
+        match statement {
+            Stmt::Expr(expr) => self.expr(func, expr),
+            Stmt::Declaration(decls) => self.declaration(func, decls),
+            Stmt::Block(statements) => {
+                for stmt in statements {
+                    self.visit_stmt(func, stmt)
+                }
+            }
+            Stmt::Return(expr) => {
+                if let Some(expr) = expr {
+                    self.expr(func, expr);
+                }
+                self.machine.jump(func.epilogue_index);
+            }
+            Stmt::If(cond, then_branch, else_branch) => {
+                self.if_statement(func, cond, *then_branch, else_branch)
+            }
+            Stmt::While(cond, body) => self.while_statement(func, cond, *body),
+            Stmt::Do(body, cond) => self.do_statement(func, *body, cond),
+            Stmt::For(init, cond, inc, body) => self.for_statement(func, init, cond, inc, *body),
+            Stmt::Break => self.machine.jump(self.jump_labels.last().expect("typechecker").0),
+            Stmt::Continue => self.machine.jump(self.jump_labels.last().expect("typechecker").1),
+            Stmt::Switch(cond, body) => self.switch_statement(func, cond, *body),
+            Stmt::Case(body) | Stmt::Default(body) => {
+                let label = self.switch_labels.pop().unwrap();
+                self.machine.label(label);
+                self.visit_stmt(func, *body);
+            }
+            Stmt::Goto(label) => self.machine.jump(func.labels[&label]),
+            Stmt::Label(name, body) => {
+                self.machine.label(func.labels[&name]);
+                self.visit_stmt(func, *body);
+            }
+            // debug-information is only emitted for x86-64
+            Stmt::Located(_, body) => self.visit_stmt(func, *body),
+        }
+    }
+    fn if_statement(
+        &mut self,
+        func: &mut Function,
+        cond: Expr,
+        then_branch: Stmt,
+        else_branch: Option<Box<Stmt>>,
+    ) {
+        let done_label = create_label(&mut self.label_index);
+        let else_label = if else_branch.is_some() {
+            create_label(&mut self.label_index)
+        } else {
+            done_label
+        };
+
+        self.condition(func, cond, else_label);
+        self.visit_stmt(func, then_branch);
+
+        if let Some(else_branch) = else_branch {
+            self.machine.jump(done_label);
+            self.machine.label(else_label);
+            self.visit_stmt(func, *else_branch);
+        }
+        self.machine.label(done_label);
+    }
+    fn while_statement(&mut self, func: &mut Function, cond: Expr, body: Stmt) {
+        let cond_label = create_label(&mut self.label_index);
+        let end_label = create_label(&mut self.label_index);
+
+        self.jump_labels.push((end_label, cond_label));
+
+        self.machine.label(cond_label);
+        self.condition(func, cond, end_label);
+        self.visit_stmt(func, body);
+        self.machine.jump(cond_label);
+        self.machine.label(end_label);
+
+        self.jump_labels.pop();
+    }
+    fn do_statement(&mut self, func: &mut Function, body: Stmt, cond: Expr) {
+        let body_label = create_label(&mut self.label_index);
+        let cond_label = create_label(&mut self.label_index);
+        let end_label = create_label(&mut self.label_index);
+
+        self.jump_labels.push((end_label, cond_label));
+
+        self.machine.label(body_label);
+        self.visit_stmt(func, body);
+
+        self.machine.label(cond_label);
+        self.condition(func, cond, end_label);
+        self.machine.jump(body_label);
+        self.machine.label(end_label);
+
+        self.jump_labels.pop();
+    }
+    fn for_statement(
+        &mut self,
+        func: &mut Function,
+        init: Option<Box<Stmt>>,
+        cond: Option<Expr>,
+        inc: Option<Expr>,
+        body: Stmt,
+    ) {
+        let cond_label = create_label(&mut self.label_index);
+        let inc_label = create_label(&mut self.label_index);
+        let end_label = create_label(&mut self.label_index);
+
+        self.jump_labels.push((end_label, inc_label));
+        if let Some(init) = init {
+            self.visit_stmt(func, *init);
+        }
+
+        self.machine.label(cond_label);
+        if let Some(cond) = cond {
+            self.condition(func, cond, end_label);
+        }
+        self.visit_stmt(func, body);
+
+        self.machine.label(inc_label);
+        if let Some(inc) = inc {
+            self.expr(func, inc);
+        }
+        self.machine.jump(cond_label);
+        self.machine.label(end_label);
+
+        self.jump_labels.pop();
+    }
+    // jumps to `false_label` if the condition is zero
+    fn condition(&mut self, func: &mut Function, cond: Expr, false_label: usize) {
+        let ty = cond.qtype.ty.clone();
+
+        self.expr(func, cond);
+        self.machine.branch_zero(&ty, false_label);
+    }
+    // compares the condition against every case one after another
+    fn switch_statement(&mut self, func: &mut Function, cond: Expr, body: Stmt) {
+        let switch_labels = func.switches.pop_front().unwrap();
+        let switch_jump_labels: Vec<usize> = (0..switch_labels.borrow().len())
+            .map(|_| create_label(&mut self.label_index))
+            .collect();
+        let end_label = create_label(&mut self.label_index);
+
+        let ty = cond.qtype.ty.clone();
+        self.expr(func, cond);
+
+        let mut default_label = end_label;
+        for (kind, label) in switch_labels.borrow().iter().zip(switch_jump_labels.iter()) {
+            match kind {
+                CaseKind::Case(value) => self.machine.branch_equal(literal_value(value), &ty, *label),
+                CaseKind::Default => default_label = *label,
+            }
+        }
+        self.machine.jump(default_label);
+
+        self.jump_labels.push((end_label, 0));
+        self.switch_labels
+            .append(&mut switch_jump_labels.into_iter().rev().collect());
+
+        self.visit_stmt(func, body);
+        self.machine.label(end_label);
+
+        self.jump_labels.pop();
+    }
+
+    fn declaration(&mut self, func: &mut Function, declarators: Vec<Declarator>) {
+        for declarator in declarators {
+            let var_symbol = declarator.entry.borrow().clone();
+
+            match var_symbol.storage_class {
+                Some(StorageClass::Extern | StorageClass::Static) => {
+                    let name = declarator.name.unwrap_string();
+                    let label_name = if var_symbol.is_static() {
+                        self.static_labels.update(name)
+                    } else {
+                        name
+                    };
+
+                    declarator
+                        .entry
+                        .borrow_mut()
+                        .set_reg(Register::Label(LabelRegister::Var(
+                            label_name.clone(),
+                            var_symbol.qtype.ty.clone(),
+                            var_symbol.is_extern(),
+                        )));
+                    func.static_declarations.push((label_name, declarator));
+                }
+                None | Some(StorageClass::Auto | StorageClass::Register)
+                    if declarator.name == var_symbol.token && !var_symbol.qtype.ty.is_func() =>
+                {
+                    let offset = self.declare_var(func, Rc::clone(&declarator.entry));
+
+                    match declarator.init {
+                        Some(Init::Scalar(expr)) => self.init_scalar(func, offset, expr),
+                        Some(Init::Aggr(list)) => {
+                            // first overwrite all entries with 0
+                            self.machine.local_address(offset);
+                            self.machine.clear(var_symbol.qtype.ty.size());
+
+                            for (expr, member_offset) in list {
+                                self.init_scalar(func, offset + member_offset as isize, expr)
+                            }
+                        }
+                        None => (),
+                    }
+                }
+                // only function-declarations can be redeclared inside of a function body
+                _ if var_symbol.reg.is_none() => {
+                    declarator
+                        .entry
+                        .borrow_mut()
+                        .set_reg(Register::Label(LabelRegister::Var(
+                            declarator.name.unwrap_string(),
+                            var_symbol.qtype.ty.clone(),
+                            var_symbol.is_extern(),
+                        )))
+                }
+                _ => (),
+            }
+        }
+    }
+    // allocates a stack-slot for the variable and returns its offset from the frame-pointer
+    fn declare_var(&mut self, func: &mut Function, var_symbol: SymbolRef) -> isize {
+        let ty = var_symbol.borrow().qtype.ty.clone();
+        let stack_reg = StackRegister::new(&mut func.current_bp_offset, ty);
+        let offset = stack_reg.offset();
+
+        var_symbol.borrow_mut().set_reg(Register::Stack(stack_reg));
+
+        offset
+    }
+    fn init_scalar(&mut self, func: &mut Function, offset: isize, expr: Expr) {
+        let ty = expr.qtype.ty.clone();
+
+        self.machine.local_address(offset);
+        self.machine.push();
+        self.expr(func, expr);
+        self.machine.pop();
+        self.machine.store(&ty);
+    }
+
+    // puts the address of an lvalue into the accumulator
+    fn address(&mut self, func: &mut Function, expr: Expr) {
+        match expr.kind {
+            ExprKind::Ident(var_symbol) => match var_symbol.borrow().get_reg() {
+                Register::Stack(stack_reg) => self.machine.local_address(stack_reg.offset()),
+                Register::Label(LabelRegister::Var(name, _, is_extern)) => {
+                    self.machine.global_address(&name, is_extern)
+                }
+                reg => unreachable!("identifier can't be stored in {:?}", reg),
+            },
+            ExprKind::Unary { token, right } if token.kind == TokenKind::Star => self.expr(func, *right),
+            ExprKind::MemberAccess { expr, member } => {
+                let offset = match &expr.qtype.ty {
+                    Type::Struct(s) => s.member_offset(&member),
+                    _ => 0,
+                };
+                self.address(func, *expr);
+                if offset != 0 {
+                    self.machine.add_offset(offset);
+                }
+            }
+            ExprKind::String(name) => self.machine.string_address(self.const_labels[&name]),
+            // value of aggregate expressions is already their address
+            _ if expr.qtype.ty.is_aggregate() => self.expr(func, expr),
+            _ => unreachable!("not an lvalue: {:?}", expr.kind),
+        }
+    }
+
+    fn expr(&mut self, func: &mut Function, expr: Expr) {
+        let ty = expr.qtype.ty.clone();
+
+        match expr.kind {
+            ExprKind::Binary { left, token, right } => {
+                self.expr(func, *left);
+                self.machine.push();
+                self.expr(func, *right);
+                self.machine.pop();
+                self.machine.binary(&token.kind, &ty);
+            }
+            ExprKind::Comparison { left, token, right } => {
+                let operand_type = left.qtype.ty.clone();
+
+                self.expr(func, *left);
+                self.machine.push();
+                self.expr(func, *right);
+                self.machine.pop();
+                self.machine.compare(&token.kind, &operand_type);
+            }
+            ExprKind::Logical { left, token, right } => self.logical(func, *left, token.kind, *right),
+            ExprKind::Unary { token, right } => match token.kind {
+                TokenKind::Amp => self.address(func, *right),
+                TokenKind::Star => {
+                    self.expr(func, *right);
+                    self.machine.load(&ty);
+                }
+                _ => {
+                    let operand_type = right.qtype.ty.clone();
+
+                    self.expr(func, *right);
+                    self.machine.unary(&token.kind, &operand_type);
+                }
+            },
+            ExprKind::Assign { l_expr, r_expr } => {
+                self.address(func, *l_expr);
+                self.machine.push();
+                self.expr(func, *r_expr);
+                self.machine.pop();
+                self.machine.store(&ty);
+            }
+            ExprKind::CompoundAssign { expr, tmp_symbol } => {
+                // only have to declare tmp var, since compound assign is only syntax sugar
+                self.declare_var(func, tmp_symbol);
+                self.expr(func, *expr);
+            }
+            ExprKind::Ident(_) | ExprKind::MemberAccess { .. } => {
+                self.address(func, expr);
+                self.machine.load(&ty);
+            }
+            ExprKind::Call { caller, args } => self.call(func, *caller, args, ty),
+            ExprKind::Cast { expr, new_type, .. } => {
+                let old_type = expr.qtype.ty.clone();
+
+                self.expr(func, *expr);
+                self.machine.convert(&old_type, &new_type);
+            }
+            ExprKind::Scale {
+                expr,
+                direction,
+                by_amount,
+                ..
+            } => {
+                let value_type = expr.qtype.ty.clone();
+                let op = match direction {
+                    ScaleDirection::Up => TokenKind::Star,
+                    ScaleDirection::Down => TokenKind::Slash,
+                };
+
+                self.expr(func, *expr);
+                self.machine.push();
+                self.machine.literal(by_amount as i64, &value_type);
+                self.machine.pop();
+                self.machine.binary(&op, &value_type);
+            }
+            ExprKind::Ternary {
+                cond,
+                true_expr,
+                false_expr,
+            } => {
+                let else_label = create_label(&mut self.label_index);
+                let done_label = create_label(&mut self.label_index);
+
+                self.condition(func, *cond, else_label);
+                self.expr(func, *true_expr);
+                self.machine.jump(done_label);
+
+                self.machine.label(else_label);
+                self.expr(func, *false_expr);
+                self.machine.label(done_label);
+            }
+            ExprKind::Comma { left, right } => {
+                self.expr(func, *left);
+                self.expr(func, *right);
+            }
+            ExprKind::String(name) => self.machine.string_address(self.const_labels[&name]),
+            ExprKind::Literal(literal) => self.machine.literal(literal_value(&literal), &ty),
+            ExprKind::Nop => (),
+        }
+    }
+    fn logical(&mut self, func: &mut Function, left: Expr, operator: TokenKind, right: Expr) {
+        let int_type = Type::Primitive(Primitive::Int(false));
+        let false_label = create_label(&mut self.label_index);
+        let done_label = create_label(&mut self.label_index);
+
+        match operator {
+            TokenKind::AmpAmp => {
+                self.condition(func, left, false_label);
+                self.condition(func, right, false_label);
+            }
+            TokenKind::PipePipe => {
+                let right_label = create_label(&mut self.label_index);
+                let true_label = create_label(&mut self.label_index);
+
+                self.condition(func, left, right_label);
+                self.machine.jump(true_label);
+
+                self.machine.label(right_label);
+                self.condition(func, right, false_label);
+                self.machine.label(true_label);
+            }
+            _ => unreachable!(),
+        }
+        self.machine.literal(1, &int_type);
+        self.machine.jump(done_label);
+
+        self.machine.label(false_label);
+        self.machine.literal(0, &int_type);
+        self.machine.label(done_label);
+    }
+    fn call(&mut self, func: &mut Function, caller: Expr, args: Vec<Expr>, return_type: Type) {
+        let args_len = args.len();
+
+        // pushed in reverse so that the first argument is at the top of the stack
+        for expr in args.into_iter().rev() {
+            self.expr(func, expr);
+            self.machine.push();
+        }
+
+        let callee = match direct_callee(&caller) {
+            Some(name) => Callee::Direct(name),
+            None => {
+                self.expr(func, caller);
+                Callee::Indirect
+            }
+        };
+        self.machine.call(callee, args_len, &return_type);
+    }
+}
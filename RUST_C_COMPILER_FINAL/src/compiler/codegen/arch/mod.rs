@@ -0,0 +1,230 @@
+//! Code-generation for architectures other than x86-64.<br>
+//! x86-64 keeps using the register-allocated [LIR](crate::compiler::codegen::lir), all other
+//! targets share a single [lowering](lower) of the [MIR](crate::compiler::typechecker::mir) which
+//! evaluates every expression into an accumulator and pushes intermediate values, which the
+//! targets keep on the stack or, like [riscv64], in allocated registers.
+//! Each target then only has to describe its register-file, calling-convention,
+//! instruction-selection and assembly-printer by implementing [Machine].<br>
+//! WebAssembly has no registers or jumps so [wasm32] has its own structured lowering.<br>
+//! Independent of the target the MIR can also be printed as [LLVM IR](llvm).
+
+pub mod aarch64;
+pub mod llvm;
+mod lower;
+pub mod riscv64;
+pub mod wasm32;
+
+use crate::compiler::codegen::register::*;
+use crate::compiler::common::{token::TokenKind, types::*};
+use crate::compiler::typechecker::mir::{decl::*, expr::*};
+use crate::compiler::typechecker::ConstLabels;
+
+/// The architecture the compiled program runs on, chosen with `--target=`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    X86_64,
+    Aarch64,
+    Riscv64,
+    Wasm32,
+}
+impl Target {
+    pub fn from_triple(triple: &str) -> Option<Target> {
+        match triple {
+            "x86_64-linux" | "x86_64" => Some(Target::X86_64),
+            "aarch64-linux" | "aarch64" | "arm64" => Some(Target::Aarch64),
+            "riscv64-linux" | "riscv64" => Some(Target::Riscv64),
+            "wasm32" | "wasm32-unknown-unknown" => Some(Target::Wasm32),
+            _ => None,
+        }
+    }
+    /// Cross-targets are assembled and linked with the toolchain prefixed by their triple
+    pub fn toolchain_prefix(&self) -> &'static str {
+        match self {
+            Target::Aarch64 if !cfg!(target_arch = "aarch64") => "aarch64-linux-gnu-",
+            Target::Riscv64 if !cfg!(target_arch = "riscv64") => "riscv64-linux-gnu-",
+            _ => "",
+        }
+    }
+}
+
+/// Generates the assembly for all targets except x86-64
+pub fn translate(
+    target: Target,
+    external_decls: Vec<ExternalDeclaration>,
+    const_labels: ConstLabels,
+) -> String {
+    match target {
+        Target::Aarch64 => {
+            lower::Lower::new(aarch64::Aarch64::default(), const_labels).translate(external_decls)
+        }
+        Target::Riscv64 => {
+            lower::Lower::new(riscv64::Riscv64::default(), const_labels).translate(external_decls)
+        }
+        Target::Wasm32 => wasm32::translate(external_decls, const_labels).wat(),
+        Target::X86_64 => unreachable!("x86-64 is generated using LIR"),
+    }
+}
+
+/// Value a global variable is statically initialized with
+#[derive(Debug, Clone, PartialEq)]
+pub enum Data {
+    // value, size in bytes
+    Int(i64, usize),
+    // amount of bytes set to zero
+    Zero(usize),
+    // label, offset
+    Address(String, i64),
+}
+
+/// The function being called
+pub enum Callee {
+    // calls the label directly
+    Direct(String),
+    // the address of the function is in the accumulator
+    Indirect,
+}
+
+/// Instruction-selection for a single target.<br>
+/// Every expression is evaluated into the accumulator. Binary operations get their left operand
+/// from the secondary register after it was [popped](Machine::pop) off the stack, and store their
+/// result back into the accumulator.
+pub trait Machine {
+    /// Number of arguments passed in registers
+    const ARG_REGS: usize;
+
+    fn string(&mut self, label: usize, value: &str);
+    fn global(&mut self, name: &str, is_static: bool, data: Vec<Data>);
+
+    fn function(&mut self, name: &str, is_static: bool, stack_size: usize);
+    /// Stores the parameter at `index` into its stack-slot at `offset` from the frame-pointer
+    fn param(&mut self, index: usize, offset: isize, ty: &Type);
+    fn epilogue(&mut self, label: usize);
+
+    fn literal(&mut self, value: i64, ty: &Type);
+    fn local_address(&mut self, offset: isize);
+    fn global_address(&mut self, name: &str, is_extern: bool);
+    fn string_address(&mut self, label: usize);
+    fn add_offset(&mut self, offset: usize);
+
+    /// Replaces the address in the accumulator with the value it points to, aggregates stay addresses
+    fn load(&mut self, ty: &Type);
+    /// Stores the accumulator at the address in the secondary register, aggregates are copied
+    fn store(&mut self, ty: &Type);
+    /// Sets `size` bytes at the address in the accumulator to zero
+    fn clear(&mut self, size: usize);
+
+    fn push(&mut self);
+    fn pop(&mut self);
+
+    fn binary(&mut self, op: &TokenKind, ty: &Type);
+    fn compare(&mut self, op: &TokenKind, ty: &Type);
+    fn unary(&mut self, op: &TokenKind, ty: &Type);
+    fn convert(&mut self, from: &Type, to: &Type);
+
+    fn label(&mut self, label: usize);
+    fn jump(&mut self, label: usize);
+    fn branch_zero(&mut self, ty: &Type, label: usize);
+    fn branch_equal(&mut self, value: i64, ty: &Type, label: usize);
+
+    /// Pops `args` arguments off the stack into their designated locations and calls the function
+    fn call(&mut self, callee: Callee, args: usize, return_type: &Type);
+
+    fn finish(self) -> String;
+}
+
+// aggregates and functions are represented by their address instead of their value
+fn is_value(ty: &Type) -> bool {
+    !ty.is_aggregate() && !ty.is_func() && !ty.is_void()
+}
+
+/// Label under which a string-literal is stored
+pub fn string_label(index: usize) -> String {
+    format!(".LS{}", index)
+}
+
+/// Evaluates the initializer of a static variable of type `ty` into the data it is stored as
+fn static_data(ty: &Type, init: Option<Init>, const_labels: &ConstLabels) -> Vec<Data> {
+    match init {
+        Some(Init::Scalar(expr)) => vec![static_value(expr, const_labels)],
+        Some(Init::Aggr(list)) => {
+            let mut data = Vec::new();
+            let mut prev_offset = 0;
+
+            for (expr, offset) in list {
+                let size = expr.qtype.ty.size();
+
+                // fill gap in offset with zero
+                if offset > prev_offset {
+                    data.push(Data::Zero(offset - prev_offset));
+                }
+                data.push(static_value(expr, const_labels));
+                prev_offset = offset + size;
+            }
+
+            // fill remaining fields in type
+            if ty.size() > prev_offset {
+                data.push(Data::Zero(ty.size() - prev_offset));
+            }
+            data
+        }
+        None => vec![Data::Zero(ty.size())],
+    }
+}
+// evaluates constant expressions of static initializers
+fn static_value(expr: Expr, const_labels: &ConstLabels) -> Data {
+    let size = expr.qtype.ty.size();
+
+    match expr.kind {
+        ExprKind::String(name) => Data::Address(string_label(const_labels[&name]), 0),
+        ExprKind::Literal(literal) => Data::Int(literal_value(&literal), size),
+        ExprKind::Cast { expr, .. } => match static_value(*expr, const_labels) {
+            Data::Int(value, _) => Data::Int(value, size),
+            data => data,
+        },
+        ExprKind::Unary { right, .. } => static_value(*right, const_labels),
+        ExprKind::MemberAccess { expr, member } => {
+            let offset = match &expr.qtype.ty {
+                Type::Struct(s) => s.member_offset(&member) as i64,
+                _ => 0,
+            };
+            match static_value(*expr, const_labels) {
+                Data::Address(label, existant_offset) => Data::Address(label, existant_offset + offset),
+                _ => unreachable!("literal can't be struct address"),
+            }
+        }
+        ExprKind::Binary { left, token, right } => {
+            match (static_value(*left, const_labels), static_value(*right, const_labels)) {
+                (Data::Address(label, offset), Data::Int(n, _))
+                | (Data::Int(n, _), Data::Address(label, offset)) => {
+                    let n = if token.kind == TokenKind::Minus { -n } else { n };
+                    Data::Address(label, offset.wrapping_add(n))
+                }
+                _ => unreachable!("non global-constant expr"),
+            }
+        }
+        ExprKind::Ident(var_symbol) => match var_symbol.borrow().get_reg() {
+            Register::Label(LabelRegister::Var(name, ..)) => Data::Address(name, 0),
+            _ => unreachable!(),
+        },
+        _ => unreachable!("non global-constant expr"),
+    }
+}
+
+// functions that are called by name don't need their address loaded first
+fn direct_callee(caller: &Expr) -> Option<String> {
+    match &caller.kind {
+        ExprKind::Ident(symbol) if caller.qtype.ty.is_func() => match symbol.borrow().get_reg() {
+            Register::Label(LabelRegister::Var(name, ..)) => Some(name),
+            _ => None,
+        },
+        ExprKind::Unary { token, right } if token.kind == TokenKind::Amp => direct_callee(right),
+        _ => None,
+    }
+}
+
+fn literal_value(literal: &LiteralKind) -> i64 {
+    match literal {
+        LiteralKind::Signed(n) => *n,
+        LiteralKind::Unsigned(n) => *n as i64,
+    }
+}
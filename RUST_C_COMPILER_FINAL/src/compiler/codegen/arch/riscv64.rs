@@ -0,0 +1,1030 @@
+//! Code-generation for RV64GC following the LP64D
+//! [calling convention](https://github.com/riscv-non-isa/riscv-elf-psabi-doc/blob/master/riscv-cc.adoc).<br>
+//! The accumulator is `a0` and the secondary register `a1`. Values smaller than 64bit are always
+//! kept sign-extended (even `unsigned int`) as required by the ABI, so 32bit operations use the
+//! `*w` instructions. Locals are addressed relative to the frame-pointer `s0`.<br>
+//! Values pushed by the lowering are kept in virtual registers. Once a function is complete they
+//! are [allocated] using linear scan to the caller-saved `t5`-`t6` or the callee-saved `s1`-`s11`,
+//! and spilled to the frame when both classes are exhausted.
+
+use crate::compiler::codegen::arch::*;
+use crate::compiler::common::{token::TokenKind, types::*};
+use crate::compiler::typechecker::align_by;
+
+use std::fmt;
+
+/// Registers grouped by their role in the calling convention
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Reg {
+    Zero,
+    // return address
+    Ra,
+    Sp,
+    // frame-pointer
+    Fp,
+    // argument and return registers a0-a7
+    Arg(u8),
+    // caller-saved temporaries t0-t6
+    Temp(u8),
+    // callee-saved registers s1-s11
+    Saved(u8),
+    // value on the expression-stack which isn't allocated yet
+    Virtual(usize),
+}
+impl fmt::Display for Reg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Reg::Zero => write!(f, "zero"),
+            Reg::Ra => write!(f, "ra"),
+            Reg::Sp => write!(f, "sp"),
+            Reg::Fp => write!(f, "s0"),
+            Reg::Arg(n) => write!(f, "a{}", n),
+            Reg::Temp(n) => write!(f, "t{}", n),
+            Reg::Saved(n) => write!(f, "s{}", n),
+            Reg::Virtual(n) => write!(f, "v{}", n),
+        }
+    }
+}
+
+const ACC: Reg = Reg::Arg(0);
+const SECONDARY: Reg = Reg::Arg(1);
+// holds the address of indirect calls
+const CALLEE: Reg = Reg::Temp(1);
+
+// allocation classes for virtual registers, t0-t4 stay reserved as scratch-registers
+const CALLER_SAVED: [Reg; 2] = [Reg::Temp(5), Reg::Temp(6)];
+const CALLEE_SAVED: [Reg; 11] = [
+    Reg::Saved(1),
+    Reg::Saved(2),
+    Reg::Saved(3),
+    Reg::Saved(4),
+    Reg::Saved(5),
+    Reg::Saved(6),
+    Reg::Saved(7),
+    Reg::Saved(8),
+    Reg::Saved(9),
+    Reg::Saved(10),
+    Reg::Saved(11),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Reg(Reg),
+    Imm(i64),
+    // offset(base)
+    Mem(Reg, i64),
+    Label(String),
+    // %pcrel_hi(label)
+    PcrelHi(String),
+    // %pcrel_lo(label of auipc)
+    PcrelLo(String),
+    // %pcrel_lo(label of auipc)(base)
+    MemPcrelLo(Reg, String),
+    // %got_pcrel_hi(label)
+    GotPcrelHi(String),
+}
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operand::Reg(reg) => write!(f, "{}", reg),
+            Operand::Imm(n) => write!(f, "{}", n),
+            Operand::Mem(base, offset) => write!(f, "{}({})", offset, base),
+            Operand::Label(label) => write!(f, "{}", label),
+            Operand::PcrelHi(label) => write!(f, "%pcrel_hi({})", label),
+            Operand::PcrelLo(label) => write!(f, "%pcrel_lo({})", label),
+            Operand::MemPcrelLo(base, label) => write!(f, "%pcrel_lo({})({})", label, base),
+            Operand::GotPcrelHi(label) => write!(f, "%got_pcrel_hi({})", label),
+        }
+    }
+}
+impl From<Reg> for Operand {
+    fn from(reg: Reg) -> Self {
+        Operand::Reg(reg)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    // switches to section if not already in it, separated by an empty line: `.text`
+    Section(&'static str),
+    // assembler directive: `.globl main`
+    Directive(String),
+    Label(String),
+    // mnemonic with its operands in assembler order
+    Op(&'static str, Vec<Operand>),
+}
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instruction::Section(section) => write!(f, "\n\t{}", section),
+            Instruction::Directive(directive) => write!(f, "\t{}", directive),
+            Instruction::Label(label) => write!(f, "{}:", label),
+            Instruction::Op(mnemonic, operands) if operands.is_empty() => write!(f, "\t{}", mnemonic),
+            Instruction::Op(mnemonic, operands) => write!(
+                f,
+                "\t{:<8}{}",
+                mnemonic,
+                operands
+                    .iter()
+                    .map(|op| op.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+macro_rules! op {
+    ($mnemonic:expr $(, $operand:expr)*) => {
+        Instruction::Op($mnemonic, vec![$(Operand::from($operand)),*])
+    };
+}
+
+#[derive(Default)]
+pub struct Riscv64 {
+    output: Vec<Instruction>,
+
+    // name of the current function, needed for its `.size` directive
+    function: String,
+
+    // section the following instructions are written to
+    section: &'static str,
+
+    // `%pcrel_lo` has to refer to the label of its `auipc`, so every pc-relative address needs one
+    pcrel_index: usize,
+
+    // size of the locals of the current function, its frame is set up once registers are allocated
+    stack_size: usize,
+
+    // index into `output` where the body of the current function starts
+    body_start: usize,
+
+    // virtual registers holding the pushed values, the last one is the top of the stack
+    value_stack: Vec<Reg>,
+
+    // amount of virtual registers used in the current function
+    vreg_count: usize,
+}
+impl Riscv64 {
+    fn emit(&mut self, instruction: Instruction) {
+        self.output.push(instruction)
+    }
+    fn section(&mut self, section: &'static str) {
+        if self.section != section {
+            self.section = section;
+            self.emit(Instruction::Section(section));
+        }
+    }
+    fn directive(&mut self, directive: impl Into<String>) {
+        self.emit(Instruction::Directive(directive.into()))
+    }
+
+    // materializes a 64bit constant with `lui`/`addi`, building bigger constants out of a 32bit
+    // upper part which is shifted into place before adding the lower 12 bits
+    fn li(&mut self, dest: Reg, value: i64) {
+        if let Ok(value) = i32::try_from(value) {
+            let lo = (value << 20) >> 20;
+            let hi = (value as i64 - lo as i64) >> 12;
+
+            if hi == 0 {
+                self.emit(op!("addi", dest, Reg::Zero, Operand::Imm(lo as i64)));
+            } else {
+                self.emit(op!("lui", dest, Operand::Imm(hi & 0xfffff)));
+                if lo != 0 {
+                    self.emit(op!("addiw", dest, dest, Operand::Imm(lo as i64)));
+                }
+            }
+        } else {
+            let lo = (value << 52) >> 52;
+            let hi = value.wrapping_sub(lo) >> 12;
+            let shift = hi.trailing_zeros();
+
+            self.li(dest, hi >> shift);
+            self.emit(op!("slli", dest, dest, Operand::Imm(12 + shift as i64)));
+            if lo != 0 {
+                self.emit(op!("addi", dest, dest, Operand::Imm(lo)));
+            }
+        }
+    }
+    // `addi` immediates only have 12 bits, bigger ones need a register
+    fn add_imm(&mut self, dest: Reg, src: Reg, value: i64) {
+        if (-2048..2048).contains(&value) {
+            self.emit(op!("addi", dest, src, Operand::Imm(value)));
+        } else {
+            self.li(Reg::Temp(0), value);
+            self.emit(op!("add", dest, src, Reg::Temp(0)));
+        }
+    }
+    // loads or stores the register at an offset from the frame-pointer
+    fn frame_access(&mut self, mnemonic: &'static str, reg: Reg, offset: i64) {
+        if (-2048..2048).contains(&offset) {
+            self.emit(op!(mnemonic, reg, Operand::Mem(Reg::Fp, offset)));
+        } else {
+            self.li(Reg::Temp(3), offset);
+            self.emit(op!("add", Reg::Temp(3), Reg::Fp, Reg::Temp(3)));
+            self.emit(op!(mnemonic, reg, Operand::Mem(Reg::Temp(3), 0)));
+        }
+    }
+    // replaces the virtual registers of the instruction with their allocated locations
+    fn resolve(&mut self, instr: Instruction, allocation: &Allocation) {
+        let Instruction::Op(mnemonic, mut operands) = instr else {
+            return self.emit(instr);
+        };
+
+        let mut spilled = None;
+        for operand in operands.iter_mut() {
+            if let Operand::Reg(Reg::Virtual(id)) = operand {
+                match allocation.locations[*id] {
+                    Location::Reg(reg) => *operand = Operand::Reg(reg),
+                    Location::Spilled(slot) => spilled = Some(allocation.spill_offset(slot)),
+                }
+            }
+        }
+        let Some(offset) = spilled else {
+            return self.emit(Instruction::Op(mnemonic, operands));
+        };
+
+        // virtual registers are only ever copied from or to another register or an outgoing argument
+        match (mnemonic, operands.as_slice()) {
+            ("mv", [Operand::Reg(Reg::Virtual(_)), Operand::Reg(src)]) => {
+                self.frame_access("sd", *src, offset)
+            }
+            ("mv", [Operand::Reg(dest), Operand::Reg(Reg::Virtual(_))]) => {
+                self.frame_access("ld", *dest, offset)
+            }
+            ("sd", [Operand::Reg(Reg::Virtual(_)), arg]) => {
+                let arg = arg.clone();
+                self.frame_access("ld", Reg::Temp(0), offset);
+                self.emit(op!("sd", Reg::Temp(0), arg));
+            }
+            _ => unreachable!("spilled register in {} {:?}", mnemonic, operands),
+        }
+    }
+    fn pcrel_label(&mut self) -> String {
+        let label = format!(".Lpcrel{}", self.pcrel_index);
+        self.pcrel_index += 1;
+        self.emit(Instruction::Label(label.clone()));
+        label
+    }
+    fn address_of(&mut self, dest: Reg, label: String) {
+        let pcrel = self.pcrel_label();
+        self.emit(op!("auipc", dest, Operand::PcrelHi(label)));
+        self.emit(op!("addi", dest, dest, Operand::PcrelLo(pcrel)));
+    }
+    fn load_from(&mut self, dest: Reg, base: Reg, ty: &Type) {
+        let mnemonic = match ty {
+            Type::Primitive(Primitive::Char(false)) => "lb",
+            Type::Primitive(Primitive::Char(true)) => "lbu",
+            Type::Primitive(Primitive::Short(false)) => "lh",
+            Type::Primitive(Primitive::Short(true)) => "lhu",
+            // unsigned int is sign-extended as well
+            _ if ty.size() == 4 => "lw",
+            _ => "ld",
+        };
+        self.emit(op!(mnemonic, dest, Operand::Mem(base, 0)));
+    }
+    fn store_to(&mut self, src: Reg, base: Reg, ty: &Type) {
+        let mnemonic = match ty.size() {
+            1 => "sb",
+            2 => "sh",
+            4 => "sw",
+            _ => "sd",
+        };
+        self.emit(op!(mnemonic, src, Operand::Mem(base, 0)));
+    }
+    // shifts the value to the top of the register and back to extend it
+    fn extend(&mut self, bits: i64, is_unsigned: bool) {
+        let shift = 64 - bits;
+        self.emit(op!("slli", ACC, ACC, Operand::Imm(shift)));
+        self.emit(op!(
+            if is_unsigned { "srli" } else { "srai" },
+            ACC,
+            ACC,
+            Operand::Imm(shift)
+        ));
+    }
+    // conditional branches only reach +-4KiB, so they jump over an unconditional jump instead
+    fn branch_unless(&mut self, mnemonic: &'static str, mut operands: Vec<Operand>, index: usize) {
+        operands.push(Operand::Label("1f".to_string()));
+        self.emit(Instruction::Op(mnemonic, operands));
+        self.jump(index);
+        self.emit(Instruction::Label("1".to_string()));
+    }
+    // iterates over `size` bytes starting at the highest address, index is in `t2`
+    fn byte_loop(&mut self, size: usize, body: &[Instruction]) {
+        if size == 0 {
+            return;
+        }
+        self.li(Reg::Temp(2), size as i64);
+        self.emit(Instruction::Label("1".to_string()));
+        self.emit(op!("addi", Reg::Temp(2), Reg::Temp(2), Operand::Imm(-1)));
+        self.output.extend_from_slice(body);
+        self.emit(op!("bnez", Reg::Temp(2), Operand::Label("1b".to_string())));
+    }
+}
+
+// 32bit operations have their own instructions which sign-extend the result
+fn word(ty: &Type, mnemonic: &'static str, word_mnemonic: &'static str) -> &'static str {
+    if ty.size() == 4 && is_value(ty) {
+        word_mnemonic
+    } else {
+        mnemonic
+    }
+}
+
+fn label(index: usize) -> String {
+    format!(".L{}", index)
+}
+
+/// Where a virtual register ends up after allocation
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Location {
+    Reg(Reg),
+    // index of its slot in the frame
+    Spilled(usize),
+}
+
+// live-range of a virtual register, from its definition to its last use
+#[derive(Debug, Clone, Copy)]
+struct Interval {
+    start: usize,
+    end: usize,
+    across_call: bool,
+}
+
+/// Virtual registers of a function body with their allocated locations
+struct Allocation {
+    // indexed by the virtual register
+    locations: Vec<Location>,
+
+    // callee-saved registers that have to be preserved by the function, in order of their slots
+    saved: Vec<Reg>,
+
+    spill_slots: usize,
+
+    // size of the locals of the function, the slots are placed below them
+    stack_size: usize,
+}
+impl Allocation {
+    fn save_offset(&self, index: usize) -> i64 {
+        -((self.stack_size + (index + 1) * 8) as i64)
+    }
+    fn spill_offset(&self, slot: usize) -> i64 {
+        self.save_offset(self.saved.len() + slot)
+    }
+    fn frame_size(&self) -> usize {
+        align_by(self.stack_size + (self.saved.len() + self.spill_slots) * 8, 16)
+    }
+}
+
+/// Linear-scan allocation of the virtual registers in a function body.<br>
+/// Values that are live across a call are restricted to callee-saved registers, all others
+/// prefer the caller-saved ones. When no register of its class is free, either the new value or
+/// the active value which lives the longest is spilled.
+fn allocate(body: &[Instruction], vreg_count: usize, stack_size: usize) -> Allocation {
+    let mut intervals = vec![
+        Interval {
+            start: usize::MAX,
+            end: 0,
+            across_call: false,
+        };
+        vreg_count
+    ];
+    let mut calls = Vec::new();
+
+    for (i, instr) in body.iter().enumerate() {
+        if let Instruction::Op(mnemonic, operands) = instr {
+            if matches!(*mnemonic, "call" | "jalr") {
+                calls.push(i);
+            }
+            for operand in operands {
+                if let Operand::Reg(Reg::Virtual(id)) = operand {
+                    intervals[*id].start = intervals[*id].start.min(i);
+                    intervals[*id].end = intervals[*id].end.max(i);
+                }
+            }
+        }
+    }
+    for interval in intervals.iter_mut() {
+        interval.across_call = calls.iter().any(|call| interval.start < *call && *call < interval.end);
+    }
+
+    let mut locations = vec![Location::Spilled(0); vreg_count];
+    let mut free_slots = Vec::new();
+    let mut spill_slots = 0;
+    // values that are currently live, either in a register or in a slot
+    let mut active: Vec<usize> = Vec::new();
+
+    // virtual registers are numbered in order of their definition so they're already sorted by start
+    for (current, interval) in intervals.iter().enumerate() {
+        active.retain(|vreg| {
+            let is_expired = intervals[*vreg].end < interval.start;
+            if let (true, Location::Spilled(slot)) = (is_expired, locations[*vreg]) {
+                free_slots.push(slot);
+            }
+            !is_expired
+        });
+
+        let class: Vec<Reg> = if interval.across_call {
+            CALLEE_SAVED.to_vec()
+        } else {
+            CALLER_SAVED.iter().chain(CALLEE_SAVED.iter()).copied().collect()
+        };
+        let free = class
+            .iter()
+            .find(|reg| !active.iter().any(|vreg| locations[*vreg] == Location::Reg(**reg)));
+
+        locations[current] = if let Some(reg) = free {
+            Location::Reg(*reg)
+        } else {
+            let slot = free_slots.pop().unwrap_or_else(|| {
+                spill_slots += 1;
+                spill_slots - 1
+            });
+            let victim = active
+                .iter()
+                .copied()
+                .filter(|vreg| matches!(locations[*vreg], Location::Reg(reg) if class.contains(&reg)))
+                .max_by_key(|vreg| intervals[*vreg].end)
+                .filter(|vreg| intervals[*vreg].end > interval.end);
+
+            if let Some(victim) = victim {
+                // the victim hands its register over to the new value
+                std::mem::replace(&mut locations[victim], Location::Spilled(slot))
+            } else {
+                Location::Spilled(slot)
+            }
+        };
+        active.push(current);
+    }
+
+    let saved = CALLEE_SAVED
+        .into_iter()
+        .filter(|reg| locations.contains(&Location::Reg(*reg)))
+        .collect();
+
+    Allocation {
+        locations,
+        saved,
+        spill_slots,
+        stack_size,
+    }
+}
+
+impl Machine for Riscv64 {
+    const ARG_REGS: usize = 8;
+
+    fn string(&mut self, label: usize, value: &str) {
+        self.section(".section .rodata");
+        self.emit(Instruction::Label(string_label(label)));
+        // INFO: use {:?} so escapes aren't applied, same as for x86-64
+        self.directive(format!(".string {:?}", value));
+    }
+    fn global(&mut self, name: &str, is_static: bool, data: Vec<Data>) {
+        self.section(".data");
+        if !is_static {
+            self.directive(format!(".globl {}", name));
+        }
+        self.directive(".p2align 3");
+        self.emit(Instruction::Label(name.to_string()));
+
+        for value in data {
+            let directive = match value {
+                Data::Int(n, size) => {
+                    let (directive, n) = match size {
+                        1 => ("byte", n as u8 as i64),
+                        2 => ("half", n as u16 as i64),
+                        4 => ("word", n as u32 as i64),
+                        _ => ("dword", n),
+                    };
+                    format!(".{} {}", directive, n)
+                }
+                Data::Zero(size) => format!(".zero {}", size),
+                Data::Address(label, 0) => format!(".dword {}", label),
+                Data::Address(label, offset) => format!(".dword {}{:+}", label, offset),
+            };
+            self.directive(directive);
+        }
+    }
+
+    fn function(&mut self, name: &str, is_static: bool, stack_size: usize) {
+        self.function = name.to_string();
+
+        self.section(".text");
+        if !is_static {
+            self.directive(format!(".globl {}", name));
+        }
+        self.directive(".p2align 1");
+        self.directive(format!(".type {}, @function", name));
+        self.emit(Instruction::Label(name.to_string()));
+
+        // prologue is only emitted in the epilogue, once it's known which registers it has to save
+        self.stack_size = stack_size;
+        self.body_start = self.output.len();
+    }
+    fn param(&mut self, index: usize, offset: isize, ty: &Type) {
+        if ty.is_struct() {
+            unimplemented!("currently can't pass structs as arguments")
+        }
+        self.add_imm(Reg::Temp(3), Reg::Fp, offset as i64);
+
+        if index < Self::ARG_REGS {
+            self.store_to(Reg::Arg(index as u8), Reg::Temp(3), ty);
+        } else {
+            // arguments that don't fit into registers are above the saved registers, each in an 8-byte slot
+            let stack_offset = 16 + (index - Self::ARG_REGS) as i64 * 8;
+
+            self.emit(op!("ld", Reg::Temp(4), Operand::Mem(Reg::Fp, stack_offset)));
+            self.store_to(Reg::Temp(4), Reg::Temp(3), ty);
+        }
+    }
+    fn epilogue(&mut self, index: usize) {
+        let body = self.output.split_off(self.body_start);
+        let allocation = allocate(&body, std::mem::take(&mut self.vreg_count), self.stack_size);
+
+        // frame-pointer points to the saved frame-pointer and return-address, same as on aarch64
+        self.emit(op!("addi", Reg::Sp, Reg::Sp, Operand::Imm(-16)));
+        self.emit(op!("sd", Reg::Ra, Operand::Mem(Reg::Sp, 8)));
+        self.emit(op!("sd", Reg::Fp, Operand::Mem(Reg::Sp, 0)));
+        self.emit(op!("mv", Reg::Fp, Reg::Sp));
+        if allocation.frame_size() > 0 {
+            self.add_imm(Reg::Sp, Reg::Sp, -(allocation.frame_size() as i64));
+        }
+        for (i, reg) in allocation.saved.iter().enumerate() {
+            self.frame_access("sd", *reg, allocation.save_offset(i));
+        }
+
+        for instr in body {
+            self.resolve(instr, &allocation);
+        }
+
+        self.label(index);
+        for (i, reg) in allocation.saved.iter().enumerate() {
+            self.frame_access("ld", *reg, allocation.save_offset(i));
+        }
+        self.emit(op!("mv", Reg::Sp, Reg::Fp));
+        self.emit(op!("ld", Reg::Ra, Operand::Mem(Reg::Sp, 8)));
+        self.emit(op!("ld", Reg::Fp, Operand::Mem(Reg::Sp, 0)));
+        self.emit(op!("addi", Reg::Sp, Reg::Sp, Operand::Imm(16)));
+        self.emit(op!("ret"));
+
+        let function = std::mem::take(&mut self.function);
+        self.directive(format!(".size {}, .-{}", function, function));
+    }
+
+    fn literal(&mut self, value: i64, ty: &Type) {
+        // keep the canonical sign-extended form of 32bit values
+        let value = if ty.size() == 4 {
+            value as i32 as i64
+        } else {
+            value
+        };
+        self.li(ACC, value);
+    }
+    fn local_address(&mut self, offset: isize) {
+        self.add_imm(ACC, Reg::Fp, offset as i64);
+    }
+    fn global_address(&mut self, name: &str, is_extern: bool) {
+        if is_extern {
+            // address is only known at runtime and has to be loaded from the global-offset-table
+            let pcrel = self.pcrel_label();
+            self.emit(op!("auipc", ACC, Operand::GotPcrelHi(name.to_string())));
+            self.emit(op!("ld", ACC, Operand::MemPcrelLo(ACC, pcrel)));
+        } else {
+            self.address_of(ACC, name.to_string());
+        }
+    }
+    fn string_address(&mut self, label: usize) {
+        self.address_of(ACC, string_label(label));
+    }
+    fn add_offset(&mut self, offset: usize) {
+        self.add_imm(ACC, ACC, offset as i64);
+    }
+
+    fn load(&mut self, ty: &Type) {
+        if is_value(ty) {
+            self.load_from(ACC, ACC, ty);
+        }
+    }
+    fn store(&mut self, ty: &Type) {
+        if is_value(ty) {
+            self.store_to(ACC, SECONDARY, ty);
+        } else {
+            self.byte_loop(
+                ty.size(),
+                &[
+                    op!("add", Reg::Temp(3), ACC, Reg::Temp(2)),
+                    op!("lbu", Reg::Temp(4), Operand::Mem(Reg::Temp(3), 0)),
+                    op!("add", Reg::Temp(3), SECONDARY, Reg::Temp(2)),
+                    op!("sb", Reg::Temp(4), Operand::Mem(Reg::Temp(3), 0)),
+                ],
+            );
+            self.emit(op!("mv", ACC, SECONDARY));
+        }
+    }
+    fn clear(&mut self, size: usize) {
+        self.byte_loop(
+            size,
+            &[
+                op!("add", Reg::Temp(3), ACC, Reg::Temp(2)),
+                op!("sb", Reg::Zero, Operand::Mem(Reg::Temp(3), 0)),
+            ],
+        );
+    }
+
+    fn push(&mut self) {
+        let vreg = Reg::Virtual(self.vreg_count);
+        self.vreg_count += 1;
+
+        self.value_stack.push(vreg);
+        self.emit(op!("mv", vreg, ACC));
+    }
+    fn pop(&mut self) {
+        let vreg = self.value_stack.pop().expect("pop without push");
+        self.emit(op!("mv", SECONDARY, vreg));
+    }
+
+    fn binary(&mut self, op: &TokenKind, ty: &Type) {
+        let is_unsigned = ty.is_unsigned();
+
+        let mnemonic = match op {
+            TokenKind::Plus => word(ty, "add", "addw"),
+            TokenKind::Minus => word(ty, "sub", "subw"),
+            TokenKind::Star => word(ty, "mul", "mulw"),
+            TokenKind::Slash if is_unsigned => word(ty, "divu", "divuw"),
+            TokenKind::Slash => word(ty, "div", "divw"),
+            TokenKind::Mod if is_unsigned => word(ty, "remu", "remuw"),
+            TokenKind::Mod => word(ty, "rem", "remw"),
+            TokenKind::Amp => "and",
+            TokenKind::Pipe => "or",
+            TokenKind::Xor => "xor",
+            TokenKind::LessLess => word(ty, "sll", "sllw"),
+            TokenKind::GreaterGreater if is_unsigned => word(ty, "srl", "srlw"),
+            TokenKind::GreaterGreater => word(ty, "sra", "sraw"),
+            _ => unreachable!("not a binary operator: {:?}", op),
+        };
+        self.emit(op!(mnemonic, ACC, SECONDARY, ACC));
+    }
+    fn compare(&mut self, op: &TokenKind, ty: &Type) {
+        // sign-extended 32bit values keep their order, so the same comparisons work for all sizes
+        let slt = if ty.is_unsigned() { "sltu" } else { "slt" };
+
+        match op {
+            TokenKind::EqualEqual | TokenKind::BangEqual => {
+                self.emit(op!("sub", ACC, SECONDARY, ACC));
+                let set = if *op == TokenKind::EqualEqual {
+                    "seqz"
+                } else {
+                    "snez"
+                };
+                self.emit(op!(set, ACC, ACC));
+            }
+            TokenKind::Less => self.emit(op!(slt, ACC, SECONDARY, ACC)),
+            TokenKind::Greater => self.emit(op!(slt, ACC, ACC, SECONDARY)),
+            // a <= b is !(b < a)
+            TokenKind::LessEqual | TokenKind::GreaterEqual => {
+                if *op == TokenKind::LessEqual {
+                    self.emit(op!(slt, ACC, ACC, SECONDARY));
+                } else {
+                    self.emit(op!(slt, ACC, SECONDARY, ACC));
+                }
+                self.emit(op!("xori", ACC, ACC, Operand::Imm(1)));
+            }
+            _ => unreachable!("not a comparison operator: {:?}", op),
+        }
+    }
+    fn unary(&mut self, op: &TokenKind, ty: &Type) {
+        match op {
+            TokenKind::Minus => self.emit(op!(word(ty, "neg", "negw"), ACC, ACC)),
+            TokenKind::Tilde => self.emit(op!("not", ACC, ACC)),
+            TokenKind::Bang => self.emit(op!("seqz", ACC, ACC)),
+            TokenKind::Plus => (),
+            _ => unreachable!("not a unary operator: {:?}", op),
+        }
+    }
+    fn convert(&mut self, from: &Type, to: &Type) {
+        if to.is_void() || !is_value(from) {
+            return;
+        }
+
+        match to.size() {
+            1 | 2 if from.size() != to.size() || from.is_unsigned() != to.is_unsigned() => {
+                self.extend(to.size() as i64 * 8, to.is_unsigned())
+            }
+            4 if from.size() == 8 => self.emit(op!("sext.w", ACC, ACC)),
+            // unsigned int is kept sign-extended, so it has to be zero-extended explicitly
+            8 if from.size() == 4 && from.is_unsigned() => self.extend(32, true),
+            _ => (),
+        }
+    }
+
+    fn label(&mut self, index: usize) {
+        self.emit(Instruction::Label(label(index)));
+    }
+    fn jump(&mut self, index: usize) {
+        self.emit(op!("j", Operand::Label(label(index))));
+    }
+    fn branch_zero(&mut self, _ty: &Type, index: usize) {
+        self.branch_unless("bnez", vec![Operand::Reg(ACC)], index);
+    }
+    fn branch_equal(&mut self, value: i64, ty: &Type, index: usize) {
+        let value = if ty.size() == 4 {
+            value as i32 as i64
+        } else {
+            value
+        };
+
+        self.li(SECONDARY, value);
+        self.branch_unless("bne", vec![Operand::Reg(ACC), Operand::Reg(SECONDARY)], index);
+    }
+
+    fn call(&mut self, callee: Callee, args: usize, return_type: &Type) {
+        if let Callee::Indirect = callee {
+            self.emit(op!("mv", CALLEE, ACC));
+        }
+
+        // the first argument was pushed last
+        let args: Vec<Reg> = (0..args)
+            .map(|_| self.value_stack.pop().expect("argument was pushed"))
+            .collect();
+        let (reg_args, stack_args) = args.split_at(args.len().min(Self::ARG_REGS));
+
+        // remaining arguments are passed in 8-byte slots, the stack-pointer stays 16-byte aligned
+        let stack_size = align_by(stack_args.len() * 8, 16) as i64;
+        if stack_size > 0 {
+            self.add_imm(Reg::Sp, Reg::Sp, -stack_size);
+        }
+        for (i, vreg) in stack_args.iter().enumerate() {
+            self.emit(op!("sd", *vreg, Operand::Mem(Reg::Sp, i as i64 * 8)));
+        }
+        for (i, vreg) in reg_args.iter().enumerate() {
+            self.emit(op!("mv", Reg::Arg(i as u8), *vreg));
+        }
+
+        match callee {
+            Callee::Direct(name) => self.emit(op!("call", Operand::Label(name))),
+            Callee::Indirect => self.emit(op!("jalr", CALLEE)),
+        }
+
+        if stack_size > 0 {
+            self.add_imm(Reg::Sp, Reg::Sp, stack_size);
+        }
+        if return_type.is_struct() {
+            unimplemented!("currently can't return structs")
+        }
+    }
+
+    fn finish(self) -> String {
+        self.output
+            .into_iter()
+            .map(|instr| instr.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parser::tests::setup;
+    use crate::compiler::typechecker::TypeChecker;
+
+    fn setup_asm(input: &str) -> String {
+        let parse_tree = setup(input).parse().unwrap();
+        let (mir, const_labels) = TypeChecker::new().check(parse_tree).unwrap();
+
+        translate(Target::Riscv64, mir, const_labels)
+    }
+
+    fn has_tool(name: &str) -> bool {
+        std::process::Command::new(name).arg("--version").output().is_ok()
+    }
+
+    // executes the instructions emitted by `li` to check the value they produce
+    fn materialize(value: i64) -> i64 {
+        let mut machine = Riscv64::default();
+        machine.li(ACC, value);
+
+        let mut reg: i64 = 0;
+        for instr in machine.output {
+            match instr {
+                Instruction::Op("lui", ops) => match ops[1] {
+                    Operand::Imm(n) => reg = ((n << 12) as i32) as i64,
+                    _ => unreachable!(),
+                },
+                Instruction::Op(mnemonic, ops) => {
+                    let Operand::Imm(n) = ops[2] else { unreachable!() };
+                    reg = match mnemonic {
+                        "addi" if ops[1] == Operand::Reg(Reg::Zero) => n,
+                        "addi" => reg.wrapping_add(n),
+                        "addiw" => (reg as i32).wrapping_add(n as i32) as i64,
+                        "slli" => reg << n,
+                        _ => unreachable!("{}", mnemonic),
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+        reg
+    }
+
+    #[test]
+    fn constant_materialization() {
+        for value in [
+            0,
+            -1,
+            2047,
+            -2048,
+            2048,
+            0x7fff_ffff,
+            -0x8000_0000,
+            0x8000_0000,
+            0x1234_5678_9abc_def0,
+            i64::MIN,
+            i64::MAX,
+            -0x12_3456_789a,
+        ] {
+            assert_eq!(materialize(value), value, "{:#x}", value);
+        }
+
+        let actual = setup_asm("long foo() { return 0x12345678; }");
+        assert!(actual.contains("\tlui     a0, 74565\n\taddiw   a0, a0, 1656"));
+    }
+
+    #[test]
+    fn function_frame() {
+        let actual = setup_asm("int add(int a, int b) { return a + b; }");
+        let expected = "
+\t.text
+\t.globl add
+\t.p2align 1
+\t.type add, @function
+add:
+\taddi    sp, sp, -16
+\tsd      ra, 8(sp)
+\tsd      s0, 0(sp)
+\tmv      s0, sp
+\taddi    sp, sp, -16
+\taddi    t3, s0, -4
+\tsw      a0, 0(t3)
+\taddi    t3, s0, -8
+\tsw      a1, 0(t3)
+\taddi    a0, s0, -4
+\tlw      a0, 0(a0)
+\tmv      t5, a0
+\taddi    a0, s0, -8
+\tlw      a0, 0(a0)
+\tmv      a1, t5
+\taddw    a0, a1, a0
+\tj       .L0
+.L0:
+\tmv      sp, s0
+\tld      ra, 8(sp)
+\tld      s0, 0(sp)
+\taddi    sp, sp, 16
+\tret
+\t.size add, .-add";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn stack_arguments() {
+        let actual = setup_asm(
+            "
+long ten(long a, long b, long c, long d, long e, long f, long g, long h, long i, long j);
+long foo() { return ten(1, 2, 3, 4, 5, 6, 7, 8, 9, 10); }
+long ten(long a, long b, long c, long d, long e, long f, long g, long h, long i, long j) { return j; }",
+        );
+
+        for expected in [
+            "\taddi    sp, sp, -16\n\tsd      t6, 0(sp)\n\tsd      t5, 8(sp)\n\tmv      a0, s8",
+            "\tmv      a7, s1\n\tcall    ten\n\taddi    sp, sp, 16",
+            "\tld      t4, 24(s0)",
+        ] {
+            assert!(actual.contains(expected), "missing:\n{}", expected);
+        }
+    }
+
+    #[test]
+    fn register_allocation() {
+        // `a` is live across the call so it needs a callee-saved register, the argument doesn't
+        let actual = setup_asm("int sq(int);\nint foo(int a, int b) { return a + b * sq(a); }");
+
+        for expected in [
+            "\tsd      s1, -24(s0)\n\tsd      s2, -32(s0)",
+            "\tmv      t5, a0\n\tmv      a0, t5\n\tcall    sq\n\tmv      a1, s2",
+            "\tld      s1, -24(s0)\n\tld      s2, -32(s0)\n\tmv      sp, s0",
+        ] {
+            assert!(actual.contains(expected), "missing:\n{}", expected);
+        }
+
+        // once the callee-saved registers are exhausted the values living the longest are spilled
+        let actual = setup_asm(
+            "
+int f(int);
+int g(int, int, int, int, int, int, int, int, int, int, int, int, int, int, int);
+int wide(int x) { return g(f(x), 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15); }",
+        );
+
+        for expected in [
+            "\taddi    sp, sp, -128\n\tsd      s1, -24(s0)",
+            "\taddi    a0, zero, 8\n\tsd      a0, -112(s0)",
+            "\tcall    f\n\tmv      t5, a0\n\taddi    sp, sp, -64\n\tsd      s7, 0(sp)",
+            "\tld      a7, -112(s0)\n\tcall    g",
+            "\tld      s11, -104(s0)",
+        ] {
+            assert!(actual.contains(expected), "missing:\n{}", expected);
+        }
+    }
+
+    #[test]
+    fn signedness() {
+        let actual = setup_asm(
+            "
+unsigned char narrow(unsigned a, unsigned b) { return a / b > a >> 2; }
+int wide(long a, unsigned b) { return (int)a < b % 3; }",
+        );
+
+        for expected in [
+            "\tdivuw   a0, a1, a0",
+            "\tsrlw    a0, a1, a0",
+            "\tsltu    a0, a0, a1",
+            "\tslli    a0, a0, 56\n\tsrli    a0, a0, 56",
+            "\tsext.w  a0, a0",
+            "\tremuw   a0, a1, a0",
+        ] {
+            assert!(actual.contains(expected), "missing:\n{}", expected);
+        }
+    }
+
+    #[test]
+    fn global_addressing() {
+        let actual = setup_asm(
+            "
+extern int ext;
+int arr[3] = {1, 2};
+int *p = &arr[1];
+int foo() { return ext + arr[0]; }",
+        );
+
+        for expected in [
+            "arr:\n\t.word 1\n\t.word 2\n\t.zero 4",
+            "p:\n\t.dword arr+4",
+            ".Lpcrel0:\n\tauipc   a0, %got_pcrel_hi(ext)\n\tld      a0, %pcrel_lo(.Lpcrel0)(a0)",
+            ".Lpcrel1:\n\tauipc   a0, %pcrel_hi(arr)\n\taddi    a0, a0, %pcrel_lo(.Lpcrel1)",
+        ] {
+            assert!(actual.contains(expected), "missing:\n{}", expected);
+        }
+    }
+
+    // only runs where a cross-toolchain and qemu-user are installed
+    #[test]
+    fn run_under_qemu() {
+        if !has_tool("riscv64-linux-gnu-gcc") || !has_tool("qemu-riscv64") {
+            return;
+        }
+        let asm = setup_asm(
+            "
+int printf(const char *fmt, ...);
+struct P { int x; long y; char c; };
+int sum(int n) { return n ? n + sum(n - 1) : 0; }
+long many(long a, long b, long c, long d, long e, long f, long g, long h, long i, long j) {
+    return a - j;
+}
+int main() {
+    struct P p = {1, 2, 'c'};
+    struct P q;
+    unsigned u = -1;
+    long big = 0x123456789ab;
+    int (*fp)(int) = sum;
+    q = p;
+    printf(\"%d %ld %c %d %ld %u %d %lx\\n\", q.x, q.y, q.c, fp(10), many(1,2,3,4,5,6,7,8,9,10), u >> 28, -7 / 2, big);
+    return 0;
+}",
+        );
+
+        let dir = std::env::temp_dir();
+        let asm_file = dir.join("wrecc_riscv64_test.s");
+        let exe_file = dir.join("wrecc_riscv64_test");
+        std::fs::write(&asm_file, asm).unwrap();
+
+        let status = std::process::Command::new("riscv64-linux-gnu-gcc")
+            .arg("-static")
+            .arg(&asm_file)
+            .arg("-o")
+            .arg(&exe_file)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let output = std::process::Command::new("qemu-riscv64")
+            .arg(&exe_file)
+            .output()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "1 2 c 55 -9 15 -3 123456789ab\n"
+        );
+    }
+}
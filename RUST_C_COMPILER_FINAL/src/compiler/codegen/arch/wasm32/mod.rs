@@ -0,0 +1,1881 @@
+//! Lowers the [MIR](crate::compiler::typechecker::mir) into a WebAssembly [Module].<br>
+//! WebAssembly has neither registers nor jumps: values live on an operand-stack, control-flow has
+//! to be structured into nested blocks and only the linear memory is addressable.
+//! So scalar variables whose address is never taken become wasm-locals, while aggregates and
+//! address-taken variables are placed in a frame on a shadow-stack in linear memory which is
+//! addressed through the `__stack_pointer` global.<br>
+//! Loops, if-statements and switches map directly onto blocks. Functions containing `goto` or
+//! case-labels nested inside of other statements fall back to a dispatch-loop which selects the
+//! next basic-block using a `br_table`.<br>
+//! The typechecker lays out types for LP64, so pointers and longs are `i64` values which occupy 8
+//! bytes in memory, and are wrapped to 32-bit addresses when accessed.<br>
+//! Functions which are declared but not defined are imported from the `env` module. Variadic
+//! arguments are passed as a pointer to a buffer on the shadow-stack in which every argument takes
+//! up an 8-byte slot.
+
+mod module;
+#[cfg(test)]
+mod validate;
+
+pub use module::*;
+
+use crate::compiler::codegen::arch::*;
+use crate::compiler::codegen::StaticLabels;
+use crate::compiler::common::{environment::*, token::*};
+use crate::compiler::typechecker::align_by;
+use crate::compiler::typechecker::mir::stmt::*;
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+// addresses below this stay unused so that a null-pointer never points to an object
+const DATA_START: u32 = 1024;
+
+// size of the shadow-stack which is placed after the static data
+const STACK_SIZE: u32 = 64 * 1024;
+
+const PAGE_SIZE: u32 = 64 * 1024;
+
+// size of every slot in the buffer holding the variadic arguments
+const VARARG_SLOT: usize = 8;
+
+// symbols are identified by the address of their entry in the symbol-table
+type SymbolKey = *const RefCell<Symbol>;
+
+fn key(symbol: &SymbolRef) -> SymbolKey {
+    Rc::as_ptr(symbol)
+}
+
+/// Generates a WebAssembly module from the MIR
+pub fn translate(external_decls: Vec<ExternalDeclaration>, const_labels: ConstLabels) -> Module {
+    Wasm32::new(const_labels).translate(external_decls)
+}
+
+struct Wasm32 {
+    module: Module,
+
+    // index of current block-label
+    label_index: usize,
+
+    // map containing strings and their corresponding label-index
+    const_labels: ConstLabels,
+
+    // addresses of global variables and string-literals in linear memory
+    addresses: HashMap<String, u32>,
+
+    // static initializers can refer to variables defined later so they are written last
+    pending_data: Vec<(u32, Vec<Data>)>,
+
+    // same as in the x86-64 codegen, static variables in different scopes get an index appended
+    static_labels: StaticLabels,
+
+    // signatures of all declared functions
+    signatures: HashMap<String, Signature>,
+
+    // functions defined in this file
+    defined: HashSet<String>,
+
+    // functions called or whose address is taken, the undefined ones are imported
+    referenced: Vec<String>,
+
+    // state of the function currently being lowered
+    frame: Frame,
+}
+
+#[derive(Default)]
+struct Frame {
+    // instructions of the function, or of the current basic-block when using the dispatch-loop
+    code: Vec<Instr>,
+
+    // number of parameters, locals are indexed after them
+    params: usize,
+    locals: Vec<ValType>,
+
+    // wasm-locals of variables that don't have to live in linear memory
+    slots: HashMap<SymbolKey, u32>,
+
+    // temporaries of compound-assignments that point to a variable stored in a wasm-local
+    aliases: HashMap<SymbolKey, SymbolRef>,
+
+    address_taken: HashSet<SymbolKey>,
+
+    // wasm-local holding the bottom of the stack-frame, if the function needs one
+    base: Option<u32>,
+    size: u32,
+
+    // loop labels saved so that break and continue jump to them
+    jump_labels: Vec<(Label, Label)>,
+
+    // case/default-blocks get defined in each switch and then popped in order of appearance
+    switch_labels: Vec<usize>,
+
+    dispatch: Option<Dispatch>,
+}
+
+// basic-blocks of an unstructured function, executed by a loop which branches to the block whose
+// index is stored in `pc`
+struct Dispatch {
+    blocks: Vec<Vec<Instr>>,
+    current: usize,
+    pc: u32,
+    repeat: Label,
+}
+
+// where a variable is stored
+enum Slot {
+    Local(u32),
+    // offset from the bottom of the stack-frame
+    Frame(u32),
+}
+
+impl Wasm32 {
+    fn new(const_labels: ConstLabels) -> Self {
+        Wasm32 {
+            module: Module::default(),
+            label_index: 0,
+            const_labels,
+            addresses: HashMap::new(),
+            pending_data: Vec::new(),
+            static_labels: StaticLabels(HashMap::new()),
+            signatures: HashMap::new(),
+            defined: HashSet::new(),
+            referenced: Vec::new(),
+            frame: Frame::default(),
+        }
+    }
+
+    fn translate(mut self, external_decls: Vec<ExternalDeclaration>) -> Module {
+        self.module.data_offset = DATA_START;
+
+        let mut strings: Vec<(String, usize)> = self.const_labels.clone().into_iter().collect();
+        strings.sort_by_key(|(_, label)| *label);
+        for (data, label) in strings {
+            let address = self.allocate(data.len() + 1);
+            let offset = (address - DATA_START) as usize;
+
+            self.module.data[offset..offset + data.len()].copy_from_slice(data.as_bytes());
+            self.addresses.insert(string_label(label), address);
+        }
+
+        // functions can be called before they are defined
+        for decl in external_decls.iter() {
+            if let ExternalDeclaration::Function(func, func_symbol, _) = decl {
+                let signature = signature(function_type(&func_symbol.borrow().qtype.ty));
+
+                self.signatures.insert(func.name.clone(), signature);
+                self.defined.insert(func.name.clone());
+            }
+        }
+
+        for decl in external_decls {
+            match decl {
+                ExternalDeclaration::Declaration(decls) => self.global_declaration(decls),
+                ExternalDeclaration::Function(func, func_symbol, stmts) => {
+                    self.function_definition(func, func_symbol, stmts)
+                }
+            }
+        }
+
+        for (address, data) in std::mem::take(&mut self.pending_data) {
+            self.write_data(address, data);
+        }
+
+        for name in std::mem::take(&mut self.referenced) {
+            if !self.defined.contains(&name) {
+                let type_index = self.module.type_index(self.signatures[&name].clone());
+                self.module.imports.push(Import { name, type_index });
+            }
+        }
+
+        let data_end = DATA_START as usize + self.module.data.len();
+        self.module.stack_pointer = align_by(data_end, 16) as u32 + STACK_SIZE;
+        self.module.pages = self.module.stack_pointer.div_ceil(PAGE_SIZE);
+
+        self.module
+    }
+
+    // reserves zeroed memory for static data and returns its address
+    fn allocate(&mut self, size: usize) -> u32 {
+        let offset = align_by(self.module.data.len(), 8);
+        self.module.data.resize(offset + size, 0);
+
+        DATA_START + offset as u32
+    }
+    fn global_address(&mut self, name: &str, size: usize) -> u32 {
+        match self.addresses.get(name) {
+            Some(address) => *address,
+            None => {
+                let address = self.allocate(size);
+                self.addresses.insert(name.to_string(), address);
+                address
+            }
+        }
+    }
+    fn write_data(&mut self, address: u32, data: Vec<Data>) {
+        let mut offset = (address - DATA_START) as usize;
+
+        for value in data {
+            let (value, size) = match value {
+                Data::Int(value, size) => (value, size),
+                Data::Zero(size) => {
+                    offset += size;
+                    continue;
+                }
+                Data::Address(name, addend) => {
+                    let address = match self.addresses.get(&name) {
+                        Some(address) => *address as i64,
+                        None if self.signatures.contains_key(&name) => self.table_slot(&name),
+                        // extern variable that isn't defined in this file
+                        None => self.global_address(&name, 8) as i64,
+                    };
+                    (address.wrapping_add(addend), 8)
+                }
+            };
+            self.module.data[offset..offset + size].copy_from_slice(&value.to_le_bytes()[..size]);
+            offset += size;
+        }
+    }
+
+    // records that the function has to be defined or imported
+    fn reference(&mut self, name: &str, signature: Signature) {
+        self.signatures.entry(name.to_string()).or_insert(signature);
+
+        if !self.referenced.iter().any(|referenced| referenced == name) {
+            self.referenced.push(name.to_string());
+        }
+    }
+    // function-pointers are indices into the table
+    fn table_slot(&mut self, name: &str) -> i64 {
+        let signature = self.signatures[name].clone();
+        self.reference(name, signature);
+
+        let index = match self.module.table.iter().position(|func| func == name) {
+            Some(index) => index,
+            None => {
+                self.module.table.push(name.to_string());
+                self.module.table.len() - 1
+            }
+        };
+        index as i64 + 1
+    }
+
+    fn global_declaration(&mut self, declarators: Vec<Declarator>) {
+        for declarator in declarators {
+            let var_symbol = declarator.entry.borrow().clone();
+            let ty = var_symbol.qtype.ty.clone();
+
+            if declarator.name == var_symbol.token && !var_symbol.is_extern() {
+                let name = declarator.name.unwrap_string();
+                let label_name = if var_symbol.is_static() {
+                    self.static_labels.update(name)
+                } else {
+                    name
+                };
+
+                self.declare_global_var(label_name, ty, declarator.entry, declarator.init)
+            } else {
+                self.declare_function(&declarator.name.unwrap_string(), &ty);
+
+                // still needs a register when used before its definition
+                if var_symbol.reg.is_none() {
+                    declarator
+                        .entry
+                        .borrow_mut()
+                        .set_reg(Register::Label(LabelRegister::Var(
+                            declarator.name.unwrap_string(),
+                            ty,
+                            var_symbol.is_extern(),
+                        )))
+                }
+            }
+        }
+    }
+    fn declare_global_var(
+        &mut self,
+        label_name: String,
+        ty: Type,
+        var_symbol: SymbolRef,
+        init: Option<Init>,
+    ) {
+        var_symbol
+            .borrow_mut()
+            .set_reg(Register::Label(LabelRegister::Var(
+                label_name.clone(),
+                ty.clone(),
+                false,
+            )));
+
+        let address = self.global_address(&label_name, ty.size());
+        let data = static_data(&ty, init, &self.const_labels);
+
+        self.pending_data.push((address, data));
+    }
+    fn declare_function(&mut self, name: &str, ty: &Type) {
+        if ty.is_func() {
+            self.signatures
+                .entry(name.to_string())
+                .or_insert_with(|| signature(function_type(ty)));
+        }
+    }
+
+    fn function_definition(&mut self, mut func: Function, func_symbol: SymbolRef, stmts: Vec<Stmt>) {
+        func_symbol
+            .borrow_mut()
+            .set_reg(Register::Label(LabelRegister::Var(
+                func.name.clone(),
+                func.return_type.ty.clone(),
+                false,
+            )));
+
+        let mut analysis = Analysis::default();
+        stmts.iter().for_each(|stmt| analysis.stmt(stmt));
+
+        let signature = self.signatures[&func.name].clone();
+        self.frame = Frame {
+            params: signature.params.len(),
+            address_taken: analysis.address_taken,
+            ..Frame::default()
+        };
+
+        if func
+            .params
+            .iter()
+            .chain(analysis.locals.iter())
+            .any(|symbol| self.in_memory(symbol))
+        {
+            let base = self.scratch(ValType::I32);
+            self.frame.size = align_by(func.stack_size, 16) as u32;
+            self.frame.base = Some(base);
+
+            self.emit(Instr::GlobalGet(STACK_POINTER));
+            self.emit(Instr::I32Const(self.frame.size as i32));
+            self.emit(Instr::Op("i32.sub"));
+            self.emit(Instr::LocalTee(base));
+            self.emit(Instr::GlobalSet(STACK_POINTER));
+        }
+
+        for (i, param_symbol) in func.params.clone().into_iter().enumerate() {
+            let ty = param_symbol.borrow().qtype.ty.clone();
+
+            if self.in_memory(&param_symbol) {
+                let Slot::Frame(offset) = self.declare_var(&mut func, param_symbol) else {
+                    unreachable!()
+                };
+                self.frame_address(offset);
+                self.emit(Instr::LocalGet(i as u32));
+                self.store(&ty, 0);
+            } else {
+                self.frame.slots.insert(key(&param_symbol), i as u32);
+            }
+        }
+
+        let mut prologue = Vec::new();
+        if analysis.unstructured {
+            let pc = self.scratch(ValType::I32);
+            let repeat = self.label();
+            self.frame.dispatch = Some(Dispatch {
+                blocks: vec![Vec::new()],
+                current: 0,
+                pc,
+                repeat,
+            });
+
+            for value in func.labels.values_mut() {
+                *value = self.new_block();
+            }
+            prologue = std::mem::take(&mut self.frame.code);
+        }
+
+        for stmt in stmts {
+            self.stmt(&mut func, stmt);
+        }
+
+        // falling off the end of a function returns zero, which is only defined for `main`
+        if let Some(result) = signature.result {
+            self.constant(0, result);
+        }
+        self.ret();
+
+        let body = match self.frame.dispatch.take() {
+            Some(dispatch) => {
+                prologue.append(&mut self.dispatch_loop(dispatch));
+                prologue
+            }
+            None => std::mem::take(&mut self.frame.code),
+        };
+
+        let is_static = {
+            let symbol = func_symbol.borrow();
+            symbol.is_static() || (!symbol.is_extern() && func.is_inline)
+        };
+        let type_index = self.module.type_index(signature);
+        self.module.funcs.push(Func {
+            name: func.name.clone(),
+            type_index,
+            locals: std::mem::take(&mut self.frame.locals),
+            body,
+            export: !is_static,
+        });
+
+        // declare all statically linked declarations that are declared inside of function-body
+        for (label_name, declarator) in std::mem::take(&mut func.static_declarations) {
+            let var_symbol = declarator.entry.borrow().clone();
+
+            if declarator.name == var_symbol.token && !var_symbol.is_extern() {
+                self.declare_global_var(label_name, var_symbol.qtype.ty, declarator.entry, declarator.init)
+            }
+        }
+    }
+
+    fn emit(&mut self, instr: Instr) {
+        self.frame.code.push(instr);
+    }
+    fn label(&mut self) -> Label {
+        self.label_index += 1;
+        self.label_index - 1
+    }
+    // adds a new wasm-local to the current function
+    fn scratch(&mut self, ty: ValType) -> u32 {
+        self.frame.locals.push(ty);
+        (self.frame.params + self.frame.locals.len() - 1) as u32
+    }
+    fn constant(&mut self, value: i64, ty: ValType) {
+        self.emit(match ty {
+            ValType::I32 => Instr::I32Const(value as i32),
+            ValType::I64 => Instr::I64Const(value),
+        })
+    }
+    fn ret(&mut self) {
+        if let Some(base) = self.frame.base {
+            self.emit(Instr::LocalGet(base));
+            self.emit(Instr::I32Const(self.frame.size as i32));
+            self.emit(Instr::Op("i32.add"));
+            self.emit(Instr::GlobalSet(STACK_POINTER));
+        }
+        self.emit(Instr::Return);
+    }
+
+    fn stmt(&mut self, func: &mut Function, statement: Stmt) {
+        match statement {
+            Stmt::Expr(expr) => self.value_as(func, expr, None),
+            Stmt::Declaration(decls) => self.declaration(func, decls),
+            Stmt::Block(statements) => {
+                for stmt in statements {
+                    self.stmt(func, stmt)
+                }
+            }
+            Stmt::Return(expr) => {
+                if let Some(expr) = expr {
+                    self.expr(func, expr);
+                }
+                self.ret();
+            }
+            // debug-information is only emitted for x86-64
+            Stmt::Located(_, body) => self.stmt(func, *body),
+            _ if self.frame.dispatch.is_some() => self.unstructured_stmt(func, statement),
+            _ => self.structured_stmt(func, statement),
+        }
+    }
+    fn structured_stmt(&mut self, func: &mut Function, statement: Stmt) {
+        match statement {
+            Stmt::If(cond, then_branch, else_branch) => {
+                let label = self.label();
+
+                self.condition(func, cond);
+                self.emit(Instr::If(label, None));
+                self.stmt(func, *then_branch);
+
+                if let Some(else_branch) = else_branch {
+                    self.emit(Instr::Else);
+                    self.stmt(func, *else_branch);
+                }
+                self.emit(Instr::End);
+            }
+            Stmt::While(cond, body) => {
+                let end_label = self.label();
+                let cond_label = self.label();
+
+                self.emit(Instr::Block(end_label, None));
+                self.emit(Instr::Loop(cond_label, None));
+                self.exit_unless(func, cond, end_label);
+
+                self.frame.jump_labels.push((end_label, cond_label));
+                self.stmt(func, *body);
+                self.frame.jump_labels.pop();
+
+                self.emit(Instr::Br(cond_label));
+                self.emit(Instr::End);
+                self.emit(Instr::End);
+            }
+            Stmt::Do(body, cond) => {
+                let end_label = self.label();
+                let body_label = self.label();
+                let cond_label = self.label();
+
+                self.emit(Instr::Block(end_label, None));
+                self.emit(Instr::Loop(body_label, None));
+                self.emit(Instr::Block(cond_label, None));
+
+                self.frame.jump_labels.push((end_label, cond_label));
+                self.stmt(func, *body);
+                self.frame.jump_labels.pop();
+
+                self.emit(Instr::End);
+                self.condition(func, cond);
+                self.emit(Instr::BrIf(body_label));
+                self.emit(Instr::End);
+                self.emit(Instr::End);
+            }
+            Stmt::For(init, cond, inc, body) => {
+                let end_label = self.label();
+                let cond_label = self.label();
+                let inc_label = self.label();
+
+                if let Some(init) = init {
+                    self.stmt(func, *init);
+                }
+
+                self.emit(Instr::Block(end_label, None));
+                self.emit(Instr::Loop(cond_label, None));
+                if let Some(cond) = cond {
+                    self.exit_unless(func, cond, end_label);
+                }
+
+                self.emit(Instr::Block(inc_label, None));
+                self.frame.jump_labels.push((end_label, inc_label));
+                self.stmt(func, *body);
+                self.frame.jump_labels.pop();
+                self.emit(Instr::End);
+
+                if let Some(inc) = inc {
+                    self.value_as(func, inc, None);
+                }
+                self.emit(Instr::Br(cond_label));
+                self.emit(Instr::End);
+                self.emit(Instr::End);
+            }
+            Stmt::Break => {
+                let label = self.frame.jump_labels.last().expect("typechecker").0;
+                self.emit(Instr::Br(label))
+            }
+            Stmt::Continue => {
+                let label = self.frame.jump_labels.last().expect("typechecker").1;
+                self.emit(Instr::Br(label))
+            }
+            Stmt::Switch(cond, body) => self.switch_statement(func, cond, *body),
+            _ => unreachable!("only lowered in unstructured functions"),
+        }
+    }
+    // branches out of the enclosing `label` if the condition is zero
+    fn exit_unless(&mut self, func: &mut Function, cond: Expr, label: Label) {
+        self.is_zero(func, cond);
+        self.emit(Instr::BrIf(label));
+    }
+    // every case opens a block around the dispatching comparisons, which is closed by its label:
+    // block $end block $case1 block $case0 (compare and branch) end (case0) end (case1) end
+    fn switch_statement(&mut self, func: &mut Function, cond: Expr, body: Stmt) {
+        let cases = func.switches.pop_front().unwrap();
+        let end_label = self.label();
+        let case_labels: Vec<Label> = (0..cases.borrow().len()).map(|_| self.label()).collect();
+
+        self.emit(Instr::Block(end_label, None));
+        for label in case_labels.iter().rev() {
+            self.emit(Instr::Block(*label, None));
+        }
+
+        let value = self.switch_value(func, cond);
+        let mut default_label = end_label;
+        for (kind, label) in cases.borrow().iter().zip(case_labels.iter()) {
+            match kind {
+                CaseKind::Case(literal) => {
+                    self.compare_case(value, literal);
+                    self.emit(Instr::BrIf(*label));
+                }
+                CaseKind::Default => default_label = *label,
+            }
+        }
+        self.emit(Instr::Br(default_label));
+
+        let continue_label = self
+            .frame
+            .jump_labels
+            .last()
+            .map_or(end_label, |(_, label)| *label);
+        self.frame.jump_labels.push((end_label, continue_label));
+
+        for stmt in switch_body(body) {
+            let mut stmt = stmt;
+            loop {
+                stmt = match stmt {
+                    Stmt::Located(_, body) => *body,
+                    Stmt::Case(body) | Stmt::Default(body) => {
+                        self.emit(Instr::End);
+                        *body
+                    }
+                    stmt => break self.stmt(func, stmt),
+                }
+            }
+        }
+        self.frame.jump_labels.pop();
+        self.emit(Instr::End);
+    }
+    // evaluates the controlling expression of a switch into a wasm-local
+    fn switch_value(&mut self, func: &mut Function, cond: Expr) -> (u32, ValType) {
+        let ty = val_type(&cond.qtype.ty).expect("integer condition");
+        let value = self.scratch(ty);
+
+        self.expr(func, cond);
+        self.emit(Instr::LocalSet(value));
+
+        (value, ty)
+    }
+    fn compare_case(&mut self, (value, ty): (u32, ValType), literal: &LiteralKind) {
+        self.emit(Instr::LocalGet(value));
+        self.constant(literal_value(literal), ty);
+        self.emit(Instr::Op(compare_op(&TokenKind::EqualEqual, ty, false)));
+    }
+
+    fn unstructured_stmt(&mut self, func: &mut Function, statement: Stmt) {
+        match statement {
+            Stmt::If(cond, then_branch, else_branch) => {
+                let done_block = self.new_block();
+                let else_block = if else_branch.is_some() {
+                    self.new_block()
+                } else {
+                    done_block
+                };
+
+                self.jump_unless(func, cond, else_block);
+                self.stmt(func, *then_branch);
+
+                if let Some(else_branch) = else_branch {
+                    self.jump(done_block);
+                    self.place(else_block);
+                    self.stmt(func, *else_branch);
+                }
+                self.place(done_block);
+            }
+            Stmt::While(cond, body) => {
+                let cond_block = self.new_block();
+                let end_block = self.new_block();
+
+                self.frame.jump_labels.push((end_block, cond_block));
+
+                self.place(cond_block);
+                self.jump_unless(func, cond, end_block);
+                self.stmt(func, *body);
+                self.jump(cond_block);
+                self.place(end_block);
+
+                self.frame.jump_labels.pop();
+            }
+            Stmt::Do(body, cond) => {
+                let body_block = self.new_block();
+                let cond_block = self.new_block();
+                let end_block = self.new_block();
+
+                self.frame.jump_labels.push((end_block, cond_block));
+
+                self.place(body_block);
+                self.stmt(func, *body);
+
+                self.place(cond_block);
+                self.jump_unless(func, cond, end_block);
+                self.jump(body_block);
+                self.place(end_block);
+
+                self.frame.jump_labels.pop();
+            }
+            Stmt::For(init, cond, inc, body) => {
+                let cond_block = self.new_block();
+                let inc_block = self.new_block();
+                let end_block = self.new_block();
+
+                self.frame.jump_labels.push((end_block, inc_block));
+                if let Some(init) = init {
+                    self.stmt(func, *init);
+                }
+
+                self.place(cond_block);
+                if let Some(cond) = cond {
+                    self.jump_unless(func, cond, end_block);
+                }
+                self.stmt(func, *body);
+
+                self.place(inc_block);
+                if let Some(inc) = inc {
+                    self.value_as(func, inc, None);
+                }
+                self.jump(cond_block);
+                self.place(end_block);
+
+                self.frame.jump_labels.pop();
+            }
+            Stmt::Break => self.jump(self.frame.jump_labels.last().expect("typechecker").0),
+            Stmt::Continue => self.jump(self.frame.jump_labels.last().expect("typechecker").1),
+            Stmt::Switch(cond, body) => {
+                let cases = func.switches.pop_front().unwrap();
+                let case_blocks: Vec<usize> = (0..cases.borrow().len()).map(|_| self.new_block()).collect();
+                let end_block = self.new_block();
+
+                let value = self.switch_value(func, cond);
+                let mut default_block = end_block;
+                for (kind, block) in cases.borrow().iter().zip(case_blocks.iter()) {
+                    match kind {
+                        CaseKind::Case(literal) => {
+                            let label = self.label();
+
+                            self.compare_case(value, literal);
+                            self.emit(Instr::If(label, None));
+                            self.jump(*block);
+                            self.emit(Instr::End);
+                        }
+                        CaseKind::Default => default_block = *block,
+                    }
+                }
+                self.jump(default_block);
+
+                let continue_block = self
+                    .frame
+                    .jump_labels
+                    .last()
+                    .map_or(end_block, |(_, block)| *block);
+                self.frame.jump_labels.push((end_block, continue_block));
+                self.frame
+                    .switch_labels
+                    .append(&mut case_blocks.into_iter().rev().collect());
+
+                self.stmt(func, *body);
+                self.place(end_block);
+
+                self.frame.jump_labels.pop();
+            }
+            Stmt::Case(body) | Stmt::Default(body) => {
+                let block = self.frame.switch_labels.pop().unwrap();
+                self.place(block);
+                self.stmt(func, *body);
+            }
+            Stmt::Goto(label) => self.jump(func.labels[&label]),
+            Stmt::Label(name, body) => {
+                self.place(func.labels[&name]);
+                self.stmt(func, *body);
+            }
+            _ => unreachable!("already lowered in `stmt`"),
+        }
+    }
+    fn new_block(&mut self) -> usize {
+        let dispatch = self.frame.dispatch.as_mut().expect("unstructured function");
+        dispatch.blocks.push(Vec::new());
+        dispatch.blocks.len() - 1
+    }
+    fn jump(&mut self, block: usize) {
+        let dispatch = self.frame.dispatch.as_ref().expect("unstructured function");
+        let (pc, repeat) = (dispatch.pc, dispatch.repeat);
+
+        self.emit(Instr::I32Const(block as i32));
+        self.emit(Instr::LocalSet(pc));
+        self.emit(Instr::Br(repeat));
+    }
+    fn jump_unless(&mut self, func: &mut Function, cond: Expr, block: usize) {
+        let label = self.label();
+
+        self.is_zero(func, cond);
+        self.emit(Instr::If(label, None));
+        self.jump(block);
+        self.emit(Instr::End);
+    }
+    // ends the current block by jumping to `block` and continues emitting into `block`
+    fn place(&mut self, block: usize) {
+        self.jump(block);
+
+        let dispatch = self.frame.dispatch.as_mut().expect("unstructured function");
+        dispatch.blocks[dispatch.current] = std::mem::take(&mut self.frame.code);
+        dispatch.current = block;
+    }
+    // loop $repeat block $bn .. block $b0 (br_table $b0 .. $bn) end (b0) .. end (bn) end
+    fn dispatch_loop(&mut self, mut dispatch: Dispatch) -> Vec<Instr> {
+        dispatch.blocks[dispatch.current] = std::mem::take(&mut self.frame.code);
+
+        let labels: Vec<Label> = dispatch.blocks.iter().map(|_| self.label()).collect();
+        let mut body = vec![Instr::Loop(dispatch.repeat, None)];
+
+        body.extend(labels.iter().rev().map(|label| Instr::Block(*label, None)));
+        body.push(Instr::LocalGet(dispatch.pc));
+        body.push(Instr::BrTable(labels.clone(), labels[0]));
+
+        // every block ends with a jump or return
+        for block in dispatch.blocks {
+            body.push(Instr::End);
+            body.extend(block);
+        }
+        body.push(Instr::End);
+        body.push(Instr::Unreachable);
+
+        body
+    }
+
+    fn declaration(&mut self, func: &mut Function, declarators: Vec<Declarator>) {
+        for declarator in declarators {
+            let var_symbol = declarator.entry.borrow().clone();
+
+            match var_symbol.storage_class {
+                Some(StorageClass::Extern | StorageClass::Static) => {
+                    let name = declarator.name.unwrap_string();
+                    let label_name = if var_symbol.is_static() {
+                        self.static_labels.update(name)
+                    } else {
+                        name
+                    };
+                    self.declare_function(&label_name, &var_symbol.qtype.ty);
+
+                    declarator
+                        .entry
+                        .borrow_mut()
+                        .set_reg(Register::Label(LabelRegister::Var(
+                            label_name.clone(),
+                            var_symbol.qtype.ty.clone(),
+                            var_symbol.is_extern(),
+                        )));
+                    func.static_declarations.push((label_name, declarator));
+                }
+                None | Some(StorageClass::Auto | StorageClass::Register)
+                    if declarator.name == var_symbol.token && !var_symbol.qtype.ty.is_func() =>
+                {
+                    let ty = var_symbol.qtype.ty;
+
+                    match (
+                        self.declare_var(func, Rc::clone(&declarator.entry)),
+                        declarator.init,
+                    ) {
+                        (Slot::Local(index), Some(Init::Scalar(expr))) => {
+                            self.expr(func, expr);
+                            self.emit(Instr::LocalSet(index));
+                        }
+                        (Slot::Local(index), Some(Init::Aggr(list))) => {
+                            for (expr, _) in list {
+                                self.expr(func, expr);
+                                self.emit(Instr::LocalSet(index));
+                            }
+                        }
+                        (Slot::Frame(offset), Some(Init::Scalar(expr))) => {
+                            self.frame_address(offset);
+                            self.value_as(func, expr, val_type(&ty));
+                            self.store(&ty, 0);
+                        }
+                        (Slot::Frame(offset), Some(Init::Aggr(list))) => {
+                            // first overwrite all entries with 0
+                            self.frame_address(offset);
+                            self.emit(Instr::I32Const(0));
+                            self.emit(Instr::I32Const(ty.size() as i32));
+                            self.emit(Instr::MemoryFill);
+
+                            for (expr, member_offset) in list {
+                                let member_type = expr.qtype.ty.clone();
+
+                                self.frame_address(offset);
+                                self.expr(func, expr);
+                                self.store(&member_type, member_offset as u32);
+                            }
+                        }
+                        (_, None) => (),
+                    }
+                }
+                // only function-declarations can be redeclared inside of a function body
+                _ if var_symbol.reg.is_none() => {
+                    self.declare_function(&declarator.name.unwrap_string(), &var_symbol.qtype.ty);
+
+                    declarator
+                        .entry
+                        .borrow_mut()
+                        .set_reg(Register::Label(LabelRegister::Var(
+                            declarator.name.unwrap_string(),
+                            var_symbol.qtype.ty.clone(),
+                            var_symbol.is_extern(),
+                        )))
+                }
+                _ => (),
+            }
+        }
+    }
+    fn in_memory(&self, symbol: &SymbolRef) -> bool {
+        symbol.borrow().qtype.ty.is_aggregate() || self.frame.address_taken.contains(&key(symbol))
+    }
+    fn declare_var(&mut self, func: &mut Function, var_symbol: SymbolRef) -> Slot {
+        let ty = var_symbol.borrow().qtype.ty.clone();
+
+        if self.in_memory(&var_symbol) {
+            let stack_reg = StackRegister::new(&mut func.current_bp_offset, ty);
+            let offset = (self.frame.size as isize + stack_reg.offset()) as u32;
+
+            var_symbol.borrow_mut().set_reg(Register::Stack(stack_reg));
+
+            Slot::Frame(offset)
+        } else {
+            let index = self.scratch(val_type(&ty).expect("scalar variable"));
+            self.frame.slots.insert(key(&var_symbol), index);
+
+            Slot::Local(index)
+        }
+    }
+    // pushes the bottom of the frame, memory-accesses add `offset` themselves
+    fn frame_address(&mut self, offset: u32) {
+        let base = self
+            .frame
+            .base
+            .expect("function with variables in memory has frame");
+        self.emit(Instr::LocalGet(base));
+
+        if offset != 0 {
+            self.emit(Instr::I32Const(offset as i32));
+            self.emit(Instr::Op("i32.add"));
+        }
+    }
+
+    // returns the wasm-local of a variable that doesn't live in memory
+    fn local_slot(&self, expr: &Expr) -> Option<u32> {
+        let symbol = match &expr.kind {
+            ExprKind::Ident(symbol) => Rc::clone(symbol),
+            ExprKind::Unary { token, right } if token.kind == TokenKind::Star => match &right.kind {
+                ExprKind::Ident(tmp_symbol) => Rc::clone(self.frame.aliases.get(&key(tmp_symbol))?),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        self.frame.slots.get(&key(&symbol)).copied()
+    }
+    // pushes the address of an lvalue as i32 and returns the constant offset to add to it
+    fn address(&mut self, func: &mut Function, expr: Expr) -> u32 {
+        match expr.kind {
+            ExprKind::Ident(var_symbol) => match var_symbol.borrow().get_reg() {
+                Register::Stack(stack_reg) => {
+                    self.frame_address(0);
+                    (self.frame.size as isize + stack_reg.offset()) as u32
+                }
+                Register::Label(LabelRegister::Var(name, ..)) => {
+                    let address = self.global_address(&name, var_symbol.borrow().qtype.ty.size());
+                    self.emit(Instr::I32Const(address as i32));
+                    0
+                }
+                reg => unreachable!("identifier can't be stored in {:?}", reg),
+            },
+            ExprKind::MemberAccess { expr, member } => {
+                let offset = match &expr.qtype.ty {
+                    Type::Struct(s) => s.member_offset(&member),
+                    _ => 0,
+                };
+                self.address(func, *expr) + offset as u32
+            }
+            ExprKind::String(name) => {
+                let address = self.addresses[&string_label(self.const_labels[&name])];
+                self.emit(Instr::I32Const(address as i32));
+                0
+            }
+            // pointers and the values of aggregate expressions are already addresses
+            ExprKind::Unary { token, right } if token.kind == TokenKind::Star => {
+                self.expr(func, *right);
+                self.emit(Instr::Op("i32.wrap_i64"));
+                0
+            }
+            _ if expr.qtype.ty.is_aggregate() => {
+                self.expr(func, expr);
+                self.emit(Instr::Op("i32.wrap_i64"));
+                0
+            }
+            _ => unreachable!("not an lvalue: {:?}", expr.kind),
+        }
+    }
+    // pushes the address of an lvalue as pointer-value
+    fn address_value(&mut self, func: &mut Function, expr: Expr) {
+        let offset = self.address(func, expr);
+        if offset != 0 {
+            self.emit(Instr::I32Const(offset as i32));
+            self.emit(Instr::Op("i32.add"));
+        }
+        self.emit(Instr::Op("i64.extend_i32_u"));
+    }
+    fn load(&mut self, func: &mut Function, expr: Expr) {
+        let ty = expr.qtype.ty.clone();
+
+        if ty.is_aggregate() {
+            self.address_value(func, expr)
+        } else {
+            let offset = self.address(func, expr);
+            self.emit(Instr::Load(load_op(&ty), offset));
+        }
+    }
+    // stores the value on top of the stack at the address below it, aggregates are copied
+    fn store(&mut self, ty: &Type, offset: u32) {
+        if ty.is_aggregate() {
+            self.emit(Instr::Op("i32.wrap_i64"));
+            self.emit(Instr::I32Const(ty.size() as i32));
+            self.emit(Instr::MemoryCopy);
+        } else {
+            self.emit(Instr::Store(store_op(ty), offset));
+        }
+    }
+    fn function_pointer(&mut self, symbol: &SymbolRef) {
+        let Register::Label(LabelRegister::Var(name, ..)) = symbol.borrow().get_reg() else {
+            unreachable!("functions are labels")
+        };
+        self.declare_function(&name, &symbol.borrow().qtype.ty);
+
+        let slot = self.table_slot(&name);
+        self.emit(Instr::I64Const(slot));
+    }
+
+    fn expr(&mut self, func: &mut Function, expr: Expr) {
+        let ty = expr.qtype.ty.clone();
+
+        if let Some(index) = self.local_slot(&expr) {
+            self.emit(Instr::LocalGet(index));
+            return;
+        }
+
+        match expr.kind {
+            ExprKind::Binary { left, token, right } => {
+                let value_type = val_type(&ty);
+
+                self.value_as(func, *left, value_type);
+                self.value_as(func, *right, value_type);
+                self.emit(Instr::Op(binary_op(&token.kind, &ty)));
+            }
+            ExprKind::Comparison { left, token, right } => {
+                let operand_type = left.qtype.ty.clone();
+                let value_type = val_type(&operand_type).expect("scalar operand");
+
+                self.expr(func, *left);
+                self.value_as(func, *right, Some(value_type));
+                self.emit(Instr::Op(compare_op(
+                    &token.kind,
+                    value_type,
+                    operand_type.is_unsigned() || operand_type.is_ptr(),
+                )));
+            }
+            ExprKind::Logical { left, token, right } => {
+                let label = self.label();
+
+                self.condition(func, *left);
+                self.emit(Instr::If(label, Some(ValType::I32)));
+                match token.kind {
+                    TokenKind::AmpAmp => {
+                        self.truth(func, *right);
+                        self.emit(Instr::Else);
+                        self.emit(Instr::I32Const(0));
+                    }
+                    TokenKind::PipePipe => {
+                        self.emit(Instr::I32Const(1));
+                        self.emit(Instr::Else);
+                        self.truth(func, *right);
+                    }
+                    _ => unreachable!(),
+                }
+                self.emit(Instr::End);
+            }
+            ExprKind::Unary { token, right } => match token.kind {
+                TokenKind::Amp if right.qtype.ty.is_func() => self.expr(func, *right),
+                TokenKind::Amp => self.address_value(func, *right),
+                // dereferenced function-pointers are still called through the table
+                TokenKind::Star if ty.is_func() => self.expr(func, *right),
+                TokenKind::Star => self.load(
+                    func,
+                    Expr {
+                        kind: ExprKind::Unary { token, right },
+                        ..expr
+                    },
+                ),
+                TokenKind::Bang => self.is_zero(func, *right),
+                TokenKind::Minus => {
+                    let value_type = val_type(&ty).expect("arithmetic operand");
+
+                    self.constant(0, value_type);
+                    self.value_as(func, *right, Some(value_type));
+                    self.emit(Instr::Op(binary_op(&TokenKind::Minus, &ty)));
+                }
+                TokenKind::Tilde => {
+                    let value_type = val_type(&ty).expect("integer operand");
+
+                    self.value_as(func, *right, Some(value_type));
+                    self.constant(-1, value_type);
+                    self.emit(Instr::Op(binary_op(&TokenKind::Xor, &ty)));
+                }
+                _ => self.expr(func, *right),
+            },
+            ExprKind::Assign { l_expr, r_expr } => {
+                if let Some(index) = self.local_slot(&l_expr) {
+                    self.value_as(func, *r_expr, val_type(&ty));
+                    self.emit(Instr::LocalTee(index));
+                } else if ty.is_aggregate() {
+                    let address = self.scratch(ValType::I64);
+
+                    self.address_value(func, *l_expr);
+                    self.emit(Instr::LocalTee(address));
+                    self.emit(Instr::Op("i32.wrap_i64"));
+                    self.expr(func, *r_expr);
+                    self.store(&ty, 0);
+                    self.emit(Instr::LocalGet(address));
+                } else {
+                    let value_type = val_type(&ty).expect("scalar assignment");
+                    let value = self.scratch(value_type);
+
+                    let offset = self.address(func, *l_expr);
+                    self.value_as(func, *r_expr, Some(value_type));
+                    self.emit(Instr::LocalTee(value));
+                    self.store(&ty, offset);
+                    self.emit(Instr::LocalGet(value));
+                }
+            }
+            ExprKind::CompoundAssign { expr, tmp_symbol } => match compound_target(&expr) {
+                // variables in wasm-locals don't have an address so `*tmp` refers to them directly
+                Some(target) if self.frame.slots.contains_key(&key(&target)) => {
+                    self.frame.aliases.insert(key(&tmp_symbol), target);
+
+                    let ExprKind::Comma { right, .. } = expr.kind else {
+                        unreachable!("compound-assignment is comma-expression")
+                    };
+                    self.expr(func, *right);
+                }
+                _ => {
+                    self.declare_var(func, tmp_symbol);
+                    self.expr(func, *expr);
+                }
+            },
+            ExprKind::Ident(symbol) if ty.is_func() => self.function_pointer(&symbol),
+            ExprKind::Ident(_) | ExprKind::MemberAccess { .. } => self.load(func, expr),
+            ExprKind::Call { caller, args } => self.call(func, *caller, args),
+            ExprKind::Cast { expr, new_type, .. } => {
+                let old_type = expr.qtype.ty.clone();
+
+                self.expr(func, *expr);
+                self.convert(&old_type, &new_type);
+            }
+            ExprKind::Scale {
+                expr,
+                direction,
+                by_amount,
+                ..
+            } => {
+                let value_type = val_type(&expr.qtype.ty).expect("integer operand");
+
+                self.expr(func, *expr);
+                self.constant(by_amount as i64, value_type);
+                self.emit(Instr::Op(match (direction, value_type) {
+                    (ScaleDirection::Up, ValType::I32) => "i32.mul",
+                    (ScaleDirection::Up, ValType::I64) => "i64.mul",
+                    (ScaleDirection::Down, ValType::I32) => "i32.div_s",
+                    (ScaleDirection::Down, ValType::I64) => "i64.div_s",
+                }));
+            }
+            ExprKind::Ternary {
+                cond,
+                true_expr,
+                false_expr,
+            } => {
+                let label = self.label();
+                let value_type = val_type(&ty);
+
+                self.condition(func, *cond);
+                self.emit(Instr::If(label, value_type));
+                self.value_as(func, *true_expr, value_type);
+                self.emit(Instr::Else);
+                self.value_as(func, *false_expr, value_type);
+                self.emit(Instr::End);
+            }
+            ExprKind::Comma { left, right } => {
+                self.value_as(func, *left, None);
+                self.expr(func, *right);
+            }
+            ExprKind::String(name) => {
+                let address = self.addresses[&string_label(self.const_labels[&name])];
+                self.emit(Instr::I64Const(address as i64));
+            }
+            ExprKind::Literal(literal) => {
+                if let Some(value_type) = val_type(&ty) {
+                    self.constant(literal_value(&literal), value_type)
+                }
+            }
+            ExprKind::Nop => (),
+        }
+    }
+    // evaluates the expression and converts its value to `value_type`, dropping it if `None`
+    fn value_as(&mut self, func: &mut Function, expr: Expr, value_type: Option<ValType>) {
+        let ty = expr.qtype.ty.clone();
+        self.expr(func, expr);
+
+        match (val_type(&ty), value_type) {
+            (Some(from), Some(to)) => self.resize(from, to, ty.is_unsigned()),
+            (Some(_), None) => self.emit(Instr::Drop),
+            _ => (),
+        }
+    }
+    fn resize(&mut self, from: ValType, to: ValType, is_unsigned: bool) {
+        match (from, to) {
+            (ValType::I64, ValType::I32) => self.emit(Instr::Op("i32.wrap_i64")),
+            (ValType::I32, ValType::I64) if is_unsigned => self.emit(Instr::Op("i64.extend_i32_u")),
+            (ValType::I32, ValType::I64) => self.emit(Instr::Op("i64.extend_i32_s")),
+            _ => (),
+        }
+    }
+    fn convert(&mut self, from: &Type, to: &Type) {
+        let Some(new_type) = val_type(to) else {
+            // casting to void discards the value
+            if val_type(from).is_some() {
+                self.emit(Instr::Drop);
+            }
+            return;
+        };
+        let old_type = val_type(from).expect("can't convert void");
+        self.resize(old_type, new_type, from.is_unsigned());
+
+        // chars and shorts are kept sign- or zero-extended to 32 bits
+        if to.is_integer() {
+            match (to.size(), to.is_unsigned()) {
+                (1, true) => {
+                    self.emit(Instr::I32Const(0xff));
+                    self.emit(Instr::Op("i32.and"));
+                }
+                (1, false) => self.emit(Instr::Op("i32.extend8_s")),
+                (2, true) => {
+                    self.emit(Instr::I32Const(0xffff));
+                    self.emit(Instr::Op("i32.and"));
+                }
+                (2, false) => self.emit(Instr::Op("i32.extend16_s")),
+                _ => (),
+            }
+        }
+    }
+    // pushes a non-zero i32 if the condition is true
+    fn condition(&mut self, func: &mut Function, cond: Expr) {
+        let value_type = val_type(&cond.qtype.ty);
+
+        self.expr(func, cond);
+        if value_type == Some(ValType::I64) {
+            self.emit(Instr::I64Const(0));
+            self.emit(Instr::Op("i64.ne"));
+        }
+    }
+    // pushes 1 if the condition is true and 0 otherwise
+    fn truth(&mut self, func: &mut Function, cond: Expr) {
+        let value_type = val_type(&cond.qtype.ty).expect("scalar condition");
+
+        self.expr(func, cond);
+        self.constant(0, value_type);
+        self.emit(Instr::Op(compare_op(&TokenKind::BangEqual, value_type, false)));
+    }
+    // pushes 1 if the condition is false and 0 otherwise
+    fn is_zero(&mut self, func: &mut Function, cond: Expr) {
+        let value_type = val_type(&cond.qtype.ty).expect("scalar condition");
+
+        self.expr(func, cond);
+        self.emit(Instr::Op(match value_type {
+            ValType::I32 => "i32.eqz",
+            ValType::I64 => "i64.eqz",
+        }));
+    }
+    fn call(&mut self, func: &mut Function, caller: Expr, mut args: Vec<Expr>) {
+        let func_type = function_type(&caller.qtype.ty).clone();
+        let signature = signature(&func_type);
+
+        let var_args = if func_type.variadic {
+            args.split_off(func_type.params.len().min(args.len()))
+        } else {
+            Vec::new()
+        };
+        for arg in args {
+            self.expr(func, arg);
+        }
+
+        // variadic arguments are stored in a buffer below the stack-pointer
+        let buffer_size = align_by(var_args.len() * VARARG_SLOT, 16) as i32;
+        if func_type.variadic {
+            self.emit(Instr::GlobalGet(STACK_POINTER));
+            self.emit(Instr::I32Const(buffer_size));
+            self.emit(Instr::Op("i32.sub"));
+            self.emit(Instr::GlobalSet(STACK_POINTER));
+
+            for (i, arg) in var_args.into_iter().enumerate() {
+                let ty = arg.qtype.ty.clone();
+
+                self.emit(Instr::GlobalGet(STACK_POINTER));
+                self.expr(func, arg);
+                self.emit(Instr::Store(
+                    match val_type(&ty) {
+                        Some(ValType::I64) => "i64.store",
+                        _ => "i32.store",
+                    },
+                    (i * VARARG_SLOT) as u32,
+                ));
+            }
+            self.emit(Instr::GlobalGet(STACK_POINTER));
+        }
+
+        match direct_callee(&caller) {
+            Some(name) => {
+                self.reference(&name, signature);
+                self.emit(Instr::Call(name));
+            }
+            None => {
+                self.expr(func, caller);
+                self.emit(Instr::Op("i32.wrap_i64"));
+
+                let type_index = self.module.type_index(signature);
+                self.emit(Instr::CallIndirect(type_index));
+            }
+        }
+
+        if func_type.variadic {
+            self.emit(Instr::GlobalGet(STACK_POINTER));
+            self.emit(Instr::I32Const(buffer_size));
+            self.emit(Instr::Op("i32.add"));
+            self.emit(Instr::GlobalSet(STACK_POINTER));
+        }
+    }
+}
+
+// collects the information needed before a function-body can be lowered
+#[derive(Default)]
+struct Analysis {
+    // variables whose address is taken have to live in linear memory
+    address_taken: HashSet<SymbolKey>,
+
+    // automatic variables declared in the function-body
+    locals: Vec<SymbolRef>,
+
+    // goto and case-labels nested inside of other statements can't be expressed using blocks
+    unstructured: bool,
+}
+impl Analysis {
+    fn stmt(&mut self, statement: &Stmt) {
+        match statement {
+            Stmt::Expr(expr) => self.expr(expr),
+            Stmt::Declaration(decls) => {
+                for declarator in decls {
+                    let symbol = declarator.entry.borrow();
+                    if matches!(
+                        symbol.storage_class,
+                        None | Some(StorageClass::Auto | StorageClass::Register)
+                    ) && !symbol.qtype.ty.is_func()
+                    {
+                        self.locals.push(Rc::clone(&declarator.entry));
+                    }
+
+                    match &declarator.init {
+                        Some(Init::Scalar(expr)) => self.expr(expr),
+                        Some(Init::Aggr(list)) => list.iter().for_each(|(expr, _)| self.expr(expr)),
+                        None => (),
+                    }
+                }
+            }
+            Stmt::Block(statements) => statements.iter().for_each(|stmt| self.stmt(stmt)),
+            Stmt::Return(expr) => expr.iter().for_each(|expr| self.expr(expr)),
+            Stmt::If(cond, then_branch, else_branch) => {
+                self.expr(cond);
+                self.stmt(then_branch);
+                else_branch.iter().for_each(|stmt| self.stmt(stmt));
+            }
+            Stmt::While(cond, body) | Stmt::Do(body, cond) => {
+                self.expr(cond);
+                self.stmt(body);
+            }
+            Stmt::For(init, cond, inc, body) => {
+                init.iter().for_each(|stmt| self.stmt(stmt));
+                cond.iter().chain(inc.iter()).for_each(|expr| self.expr(expr));
+                self.stmt(body);
+            }
+            Stmt::Switch(cond, body) => {
+                if top_level_cases(body) != nested_cases(body) {
+                    self.unstructured = true;
+                }
+                self.expr(cond);
+                self.stmt(body);
+            }
+            Stmt::Case(body) | Stmt::Default(body) | Stmt::Located(_, body) => self.stmt(body),
+            Stmt::Goto(_) => self.unstructured = true,
+            Stmt::Label(_, body) => {
+                self.unstructured = true;
+                self.stmt(body);
+            }
+            Stmt::Break | Stmt::Continue => (),
+        }
+    }
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Binary { left, right, .. }
+            | ExprKind::Comparison { left, right, .. }
+            | ExprKind::Logical { left, right, .. }
+            | ExprKind::Comma { left, right } => {
+                self.expr(left);
+                self.expr(right);
+            }
+            ExprKind::Assign { l_expr, r_expr } => {
+                self.expr(l_expr);
+                self.expr(r_expr);
+            }
+            ExprKind::Unary { token, right } => {
+                if let (TokenKind::Amp, ExprKind::Ident(symbol)) = (&token.kind, &right.kind) {
+                    self.address_taken.insert(key(symbol));
+                }
+                self.expr(right);
+            }
+            // the address taken by a compound-assignment isn't observable
+            ExprKind::CompoundAssign { expr, .. } => match (compound_target(expr), &expr.kind) {
+                (Some(_), ExprKind::Comma { right, .. }) => self.expr(right),
+                _ => self.expr(expr),
+            },
+            ExprKind::Call { caller, args } => {
+                self.expr(caller);
+                args.iter().for_each(|arg| self.expr(arg));
+            }
+            ExprKind::Cast { expr, .. }
+            | ExprKind::Scale { expr, .. }
+            | ExprKind::MemberAccess { expr, .. } => self.expr(expr),
+            ExprKind::Ternary {
+                cond,
+                true_expr,
+                false_expr,
+            } => {
+                self.expr(cond);
+                self.expr(true_expr);
+                self.expr(false_expr);
+            }
+            ExprKind::String(_) | ExprKind::Literal(_) | ExprKind::Ident(_) | ExprKind::Nop => (),
+        }
+    }
+}
+
+// `A op= B` is lowered as `tmp = &A, *tmp = *tmp op B`, returns `A` if it is a variable
+fn compound_target(expr: &Expr) -> Option<SymbolRef> {
+    let ExprKind::Comma { left, .. } = &expr.kind else {
+        return None;
+    };
+    let ExprKind::Assign { r_expr, .. } = &left.kind else {
+        return None;
+    };
+    let ExprKind::Unary { right, .. } = &r_expr.kind else {
+        return None;
+    };
+    match &right.kind {
+        ExprKind::Ident(symbol) => Some(Rc::clone(symbol)),
+        _ => None,
+    }
+}
+
+fn switch_body(body: Stmt) -> Vec<Stmt> {
+    match body {
+        Stmt::Located(_, body) => switch_body(*body),
+        Stmt::Block(statements) => statements,
+        stmt => vec![stmt],
+    }
+}
+// case-labels that are statements of the switch-body itself
+fn top_level_cases(body: &Stmt) -> usize {
+    fn labels(stmt: &Stmt) -> usize {
+        match stmt {
+            Stmt::Located(_, body) => labels(body),
+            Stmt::Case(body) | Stmt::Default(body) => 1 + labels(body),
+            _ => 0,
+        }
+    }
+    match body {
+        Stmt::Located(_, body) => top_level_cases(body),
+        Stmt::Block(statements) => statements.iter().map(labels).sum(),
+        stmt => labels(stmt),
+    }
+}
+// all case-labels belonging to the switch
+fn nested_cases(stmt: &Stmt) -> usize {
+    match stmt {
+        Stmt::Case(body) | Stmt::Default(body) => 1 + nested_cases(body),
+        Stmt::Located(_, body)
+        | Stmt::While(_, body)
+        | Stmt::Do(body, _)
+        | Stmt::For(_, _, _, body)
+        | Stmt::Label(_, body) => nested_cases(body),
+        Stmt::If(_, then_branch, else_branch) => {
+            nested_cases(then_branch) + else_branch.as_ref().map_or(0, |stmt| nested_cases(stmt))
+        }
+        Stmt::Block(statements) => statements.iter().map(nested_cases).sum(),
+        _ => 0,
+    }
+}
+
+fn function_type(ty: &Type) -> &FuncType {
+    match ty {
+        Type::Function(func_type) => func_type,
+        Type::Pointer(to) => function_type(&to.ty),
+        _ => unreachable!("not a function: {:?}", ty),
+    }
+}
+fn signature(func_type: &FuncType) -> Signature {
+    let mut params: Vec<ValType> = func_type
+        .params
+        .iter()
+        .map(|param| val_type(&param.ty).expect("parameter can't be void"))
+        .collect();
+
+    // pointer to the buffer holding the variadic arguments
+    if func_type.variadic {
+        params.push(ValType::I32);
+    }
+
+    Signature {
+        params,
+        result: val_type(&func_type.return_type.ty),
+    }
+}
+
+fn val_type(ty: &Type) -> Option<ValType> {
+    if ty.is_void() {
+        None
+    }
+    // aggregates and functions are represented by their address
+    else if ty.is_aggregate() || ty.is_func() || ty.size() == 8 {
+        Some(ValType::I64)
+    } else {
+        Some(ValType::I32)
+    }
+}
+fn load_op(ty: &Type) -> &'static str {
+    match (ty.size(), ty.is_unsigned()) {
+        (1, true) => "i32.load8_u",
+        (1, false) => "i32.load8_s",
+        (2, true) => "i32.load16_u",
+        (2, false) => "i32.load16_s",
+        (4, _) => "i32.load",
+        _ => "i64.load",
+    }
+}
+fn store_op(ty: &Type) -> &'static str {
+    match ty.size() {
+        1 => "i32.store8",
+        2 => "i32.store16",
+        4 => "i32.store",
+        _ => "i64.store",
+    }
+}
+fn binary_op(op: &TokenKind, ty: &Type) -> &'static str {
+    let is_unsigned = ty.is_unsigned();
+    let pick = |i32_op, i64_op| {
+        if val_type(ty) == Some(ValType::I64) {
+            i64_op
+        } else {
+            i32_op
+        }
+    };
+
+    match op {
+        TokenKind::Plus => pick("i32.add", "i64.add"),
+        TokenKind::Minus => pick("i32.sub", "i64.sub"),
+        TokenKind::Star => pick("i32.mul", "i64.mul"),
+        TokenKind::Slash if is_unsigned => pick("i32.div_u", "i64.div_u"),
+        TokenKind::Slash => pick("i32.div_s", "i64.div_s"),
+        TokenKind::Mod if is_unsigned => pick("i32.rem_u", "i64.rem_u"),
+        TokenKind::Mod => pick("i32.rem_s", "i64.rem_s"),
+        TokenKind::Amp => pick("i32.and", "i64.and"),
+        TokenKind::Pipe => pick("i32.or", "i64.or"),
+        TokenKind::Xor => pick("i32.xor", "i64.xor"),
+        TokenKind::LessLess => pick("i32.shl", "i64.shl"),
+        TokenKind::GreaterGreater if is_unsigned => pick("i32.shr_u", "i64.shr_u"),
+        TokenKind::GreaterGreater => pick("i32.shr_s", "i64.shr_s"),
+        _ => unreachable!("not a binary operator: {:?}", op),
+    }
+}
+fn compare_op(op: &TokenKind, ty: ValType, is_unsigned: bool) -> &'static str {
+    let ops = match (ty, op) {
+        (ValType::I32, TokenKind::EqualEqual) => return "i32.eq",
+        (ValType::I32, TokenKind::BangEqual) => return "i32.ne",
+        (ValType::I64, TokenKind::EqualEqual) => return "i64.eq",
+        (ValType::I64, TokenKind::BangEqual) => return "i64.ne",
+        (ValType::I32, _) => [
+            "i32.lt_s", "i32.lt_u", "i32.gt_s", "i32.gt_u", "i32.le_s", "i32.le_u", "i32.ge_s", "i32.ge_u",
+        ],
+        (ValType::I64, _) => [
+            "i64.lt_s", "i64.lt_u", "i64.gt_s", "i64.gt_u", "i64.le_s", "i64.le_u", "i64.ge_s", "i64.ge_u",
+        ],
+    };
+    let index = match op {
+        TokenKind::Less => 0,
+        TokenKind::Greater => 2,
+        TokenKind::LessEqual => 4,
+        TokenKind::GreaterEqual => 6,
+        _ => unreachable!("not a comparison: {:?}", op),
+    };
+    ops[index + is_unsigned as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parser::tests::setup;
+    use crate::compiler::typechecker::TypeChecker;
+
+    fn setup_module(input: &str) -> Module {
+        let parse_tree = setup(input).parse().unwrap();
+        let (mir, const_labels) = TypeChecker::new().check(parse_tree).unwrap();
+
+        translate(mir, const_labels)
+    }
+
+    fn assert_valid(input: &str) -> Module {
+        let module = setup_module(input);
+        if let Err(e) = validate::validate(&module.encode()) {
+            panic!("{}\n{}", e, module.wat());
+        }
+        module
+    }
+
+    fn func<'a>(module: &'a Module, name: &str) -> &'a Func {
+        module.funcs.iter().find(|func| func.name == name).unwrap()
+    }
+
+    const PROGRAM: &str = "
+int printf(const char *fmt, ...);
+struct P { int x; long y; };
+int fib(int n) { return n < 2 ? n : fib(n - 1) + fib(n - 2); }
+int sum(struct P *p) { return p->x + p->y; }
+int apply(int (*f)(int), int v) { return f(v); }
+int sq(int v) { return v * v; }
+int g = 5;
+int *gp = &g;
+char msg[] = \"hi\";
+int classify(int c) {
+    switch (c) {
+    case 1: return 10;
+    case 2:
+    case 3: c += 5; break;
+    default: c = -1;
+    }
+    return c;
+}
+int count(int n) {
+    int i = 0;
+again:
+    i++;
+    if (i < n) goto again;
+    return i;
+}
+int duff(int n) {
+    int r = 0;
+    switch (n % 4) {
+    case 0: do { r++;
+    case 3: r++;
+    case 2: r++;
+    case 1: r++;
+    } while ((n -= 4) > 0);
+    }
+    return r;
+}
+int main() {
+    struct P p = {3, 4};
+    int arr[5];
+    int i;
+    for (i = 0; i < 5; i++) arr[i] = i * i;
+    int x = 0;
+    int *px = &x;
+    *px += 7;
+    printf(\"%d %d %d %d %d\\n\", fib(10), sum(&p), apply(sq, 6), arr[4], x);
+    printf(\"%d %d %d %d %s %d\\n\", classify(1), classify(3), classify(9), count(7), msg, *gp);
+    printf(\"%d %d\\n\", duff(10), duff(3));
+    unsigned char uc = 300; long l = -5;
+    printf(\"%d %ld %d\\n\", uc, l * 3, (short)70000);
+    return 0;
+}
+";
+
+    #[test]
+    fn scalar_function() {
+        let module = assert_valid("int add(int a, long b) { int c = a; return c + b; }");
+
+        assert_eq!(
+            module.wat(),
+            "(module
+  (type $t0 (func (param i32 i64) (result i32)))
+  (table 1 1 funcref)
+  (memory (export \"memory\") 2)
+  (global $__stack_pointer (mut i32) (i32.const 66560))
+  (func $add (export \"add\") (type $t0) (param i32 i64) (result i32)
+    (local i32)
+    local.get 0
+    local.set 2
+    local.get 2
+    i64.extend_i32_s
+    local.get 1
+    i64.add
+    i32.wrap_i64
+    return
+    i32.const 0
+    return))"
+        );
+    }
+
+    #[test]
+    fn address_taken_locals_live_in_frame() {
+        let module = assert_valid(
+            "void inc(int *p) { *p += 1; } int main() { int a = 1; int b = 2; inc(&a); return a + b; }",
+        );
+        let main = func(&module, "main");
+
+        assert_eq!(
+            main.body[..5],
+            [
+                Instr::GlobalGet(STACK_POINTER),
+                Instr::I32Const(16),
+                Instr::Op("i32.sub"),
+                Instr::LocalTee(0),
+                Instr::GlobalSet(STACK_POINTER),
+            ]
+        );
+        // only `b` stays a wasm-local next to the frame-base
+        assert_eq!(main.locals, vec![ValType::I32, ValType::I32]);
+        assert!(!func(&module, "inc")
+            .body
+            .contains(&Instr::GlobalGet(STACK_POINTER)));
+    }
+
+    #[test]
+    fn compound_assign_on_local() {
+        let module = assert_valid("int main() { int a = 1; a += 2; a++; return a; }");
+
+        // doesn't need a frame since the temporary pointer refers to the wasm-local directly
+        assert!(!func(&module, "main")
+            .body
+            .contains(&Instr::GlobalGet(STACK_POINTER)));
+    }
+
+    #[test]
+    fn structured_control_flow() {
+        let module = assert_valid(
+            "int f(int n) { int r = 0; while (n) { if (n == 3) { n--; continue; } for (;;) break; do r++; while (0); n--; } switch (r) { case 1: r = 2; default: return r; } }",
+        );
+        let f = func(&module, "f");
+
+        assert!(!f.body.iter().any(|instr| matches!(instr, Instr::BrTable(..))));
+        assert!(f.body.iter().any(|instr| matches!(instr, Instr::Loop(..))));
+    }
+
+    #[test]
+    fn goto_uses_dispatch_loop() {
+        let module = assert_valid(PROGRAM);
+
+        for name in ["count", "duff"] {
+            assert!(func(&module, name)
+                .body
+                .iter()
+                .any(|instr| matches!(instr, Instr::BrTable(..))));
+        }
+        assert!(!func(&module, "classify")
+            .body
+            .iter()
+            .any(|instr| matches!(instr, Instr::BrTable(..))));
+    }
+
+    #[test]
+    fn function_pointers_use_table() {
+        let module = assert_valid(PROGRAM);
+
+        assert_eq!(module.table, vec!["sq".to_string()]);
+        assert!(func(&module, "apply")
+            .body
+            .iter()
+            .any(|instr| matches!(instr, Instr::CallIndirect(_))));
+    }
+
+    #[test]
+    fn undefined_functions_are_imported() {
+        let module = assert_valid(PROGRAM);
+
+        assert_eq!(module.imports.len(), 1);
+        assert_eq!(module.imports[0].name, "printf");
+        assert_eq!(
+            module.types[module.imports[0].type_index],
+            Signature {
+                params: vec![ValType::I64, ValType::I32],
+                result: Some(ValType::I32),
+            }
+        );
+    }
+
+    #[test]
+    fn static_data() {
+        let module = assert_valid("char *s = \"ab\"; int a[3] = {1, 2}; int *p = &a[1]; static long l = -1;");
+
+        assert_eq!(module.data_offset, DATA_START);
+        assert_eq!(
+            module.data,
+            vec![
+                b'a', b'b', 0, 0, 0, 0, 0, 0, // string
+                0, 4, 0, 0, 0, 0, 0, 0, // s
+                1, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // a
+                20, 4, 0, 0, 0, 0, 0, 0, // p
+                255, 255, 255, 255, 255, 255, 255, 255, // l
+            ]
+        );
+        assert!(module.wat().contains("(data (i32.const 1024) \"ab\\00"));
+    }
+
+    #[test]
+    fn validator_rejects_type_mismatch() {
+        let mut module = setup_module("int f(long a) { return a; }");
+        module.funcs[0]
+            .body
+            .retain(|instr| *instr != Instr::Op("i32.wrap_i64"));
+
+        assert_eq!(
+            validate::validate(&module.encode()),
+            Err("function 0: expected i32 but found i64".to_string())
+        );
+    }
+
+    #[test]
+    fn runs_in_node() {
+        let has_node = std::process::Command::new("node")
+            .arg("--version")
+            .output()
+            .is_ok();
+        if !has_node {
+            return;
+        }
+
+        let dir = std::env::temp_dir().join(format!("wrecc-wasm-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let wasm = dir.join("program.wasm");
+        std::fs::write(&wasm, assert_valid(PROGRAM).encode()).unwrap();
+
+        // minimal printf reading the 8-byte variadic slots
+        let script = "
+const bytes = require('fs').readFileSync(process.argv[1]);
+let memory;
+const string = (p) => { const b = new Uint8Array(memory.buffer); let s = ''; while (b[p]) s += String.fromCharCode(b[p++]); return s; };
+const env = {
+  printf(fmt, args) {
+    const view = new DataView(memory.buffer); let i = 0;
+    const out = string(Number(fmt)).replace(/%(l?)([ds])/g, (_, l, c) => {
+      const slot = args + 8 * i++;
+      if (c === 's') return string(Number(view.getBigInt64(slot, true)));
+      return l ? view.getBigInt64(slot, true).toString() : view.getInt32(slot, true).toString();
+    });
+    process.stdout.write(out);
+    return out.length;
+  },
+};
+const instance = new WebAssembly.Instance(new WebAssembly.Module(bytes), { env });
+memory = instance.exports.memory;
+process.exitCode = instance.exports.main();
+";
+        let output = std::process::Command::new("node")
+            .arg("-e")
+            .arg(script)
+            .arg(&wasm)
+            .output()
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "55 7 36 16 7\n10 8 -1 7 hi 5\n10 3\n44 -15 4464\n",
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert!(output.status.success());
+    }
+}
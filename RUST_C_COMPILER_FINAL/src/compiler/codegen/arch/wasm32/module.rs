@@ -0,0 +1,596 @@
+//! In-memory representation of a WebAssembly module which can either be printed in the `.wat`
+//! text-format or encoded into the `.wasm` binary-format.
+
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValType {
+    I32,
+    I64,
+}
+impl ValType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ValType::I32 => "i32",
+            ValType::I64 => "i64",
+        }
+    }
+    pub fn code(&self) -> u8 {
+        match self {
+            ValType::I32 => 0x7f,
+            ValType::I64 => 0x7e,
+        }
+    }
+}
+
+/// Parameters and result of a function
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Signature {
+    pub params: Vec<ValType>,
+    pub result: Option<ValType>,
+}
+
+/// Identifies a block, loop or if as the target of a branch
+pub type Label = usize;
+
+/// Global holding the top of the shadow-stack in linear memory
+pub const STACK_POINTER: u32 = 0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instr {
+    Block(Label, Option<ValType>),
+    Loop(Label, Option<ValType>),
+    If(Label, Option<ValType>),
+    Else,
+    End,
+    Br(Label),
+    BrIf(Label),
+    // targets and default target
+    BrTable(Vec<Label>, Label),
+    Return,
+    Unreachable,
+    Drop,
+    Call(String),
+    // index into the type-section
+    CallIndirect(usize),
+    LocalGet(u32),
+    LocalSet(u32),
+    LocalTee(u32),
+    GlobalGet(u32),
+    GlobalSet(u32),
+    I32Const(i32),
+    I64Const(i64),
+    // name of the load/store and the constant offset added to the address
+    Load(&'static str, u32),
+    Store(&'static str, u32),
+    // all other instructions without immediates, eg. `i32.add`
+    Op(&'static str),
+    MemoryCopy,
+    MemoryFill,
+}
+
+use ValType::*;
+
+/// Opcode and operand-types of all instructions used without immediates, loads and stores
+/// additionally encode their memory-argument
+pub const OPCODES: &[(&str, u8, &[ValType], &[ValType])] = &[
+    ("i32.load", 0x28, &[I32], &[I32]),
+    ("i64.load", 0x29, &[I32], &[I64]),
+    ("i32.load8_s", 0x2c, &[I32], &[I32]),
+    ("i32.load8_u", 0x2d, &[I32], &[I32]),
+    ("i32.load16_s", 0x2e, &[I32], &[I32]),
+    ("i32.load16_u", 0x2f, &[I32], &[I32]),
+    ("i32.store", 0x36, &[I32, I32], &[]),
+    ("i64.store", 0x37, &[I32, I64], &[]),
+    ("i32.store8", 0x3a, &[I32, I32], &[]),
+    ("i32.store16", 0x3b, &[I32, I32], &[]),
+    ("i32.eqz", 0x45, &[I32], &[I32]),
+    ("i32.eq", 0x46, &[I32, I32], &[I32]),
+    ("i32.ne", 0x47, &[I32, I32], &[I32]),
+    ("i32.lt_s", 0x48, &[I32, I32], &[I32]),
+    ("i32.lt_u", 0x49, &[I32, I32], &[I32]),
+    ("i32.gt_s", 0x4a, &[I32, I32], &[I32]),
+    ("i32.gt_u", 0x4b, &[I32, I32], &[I32]),
+    ("i32.le_s", 0x4c, &[I32, I32], &[I32]),
+    ("i32.le_u", 0x4d, &[I32, I32], &[I32]),
+    ("i32.ge_s", 0x4e, &[I32, I32], &[I32]),
+    ("i32.ge_u", 0x4f, &[I32, I32], &[I32]),
+    ("i64.eqz", 0x50, &[I64], &[I32]),
+    ("i64.eq", 0x51, &[I64, I64], &[I32]),
+    ("i64.ne", 0x52, &[I64, I64], &[I32]),
+    ("i64.lt_s", 0x53, &[I64, I64], &[I32]),
+    ("i64.lt_u", 0x54, &[I64, I64], &[I32]),
+    ("i64.gt_s", 0x55, &[I64, I64], &[I32]),
+    ("i64.gt_u", 0x56, &[I64, I64], &[I32]),
+    ("i64.le_s", 0x57, &[I64, I64], &[I32]),
+    ("i64.le_u", 0x58, &[I64, I64], &[I32]),
+    ("i64.ge_s", 0x59, &[I64, I64], &[I32]),
+    ("i64.ge_u", 0x5a, &[I64, I64], &[I32]),
+    ("i32.add", 0x6a, &[I32, I32], &[I32]),
+    ("i32.sub", 0x6b, &[I32, I32], &[I32]),
+    ("i32.mul", 0x6c, &[I32, I32], &[I32]),
+    ("i32.div_s", 0x6d, &[I32, I32], &[I32]),
+    ("i32.div_u", 0x6e, &[I32, I32], &[I32]),
+    ("i32.rem_s", 0x6f, &[I32, I32], &[I32]),
+    ("i32.rem_u", 0x70, &[I32, I32], &[I32]),
+    ("i32.and", 0x71, &[I32, I32], &[I32]),
+    ("i32.or", 0x72, &[I32, I32], &[I32]),
+    ("i32.xor", 0x73, &[I32, I32], &[I32]),
+    ("i32.shl", 0x74, &[I32, I32], &[I32]),
+    ("i32.shr_s", 0x75, &[I32, I32], &[I32]),
+    ("i32.shr_u", 0x76, &[I32, I32], &[I32]),
+    ("i64.add", 0x7c, &[I64, I64], &[I64]),
+    ("i64.sub", 0x7d, &[I64, I64], &[I64]),
+    ("i64.mul", 0x7e, &[I64, I64], &[I64]),
+    ("i64.div_s", 0x7f, &[I64, I64], &[I64]),
+    ("i64.div_u", 0x80, &[I64, I64], &[I64]),
+    ("i64.rem_s", 0x81, &[I64, I64], &[I64]),
+    ("i64.rem_u", 0x82, &[I64, I64], &[I64]),
+    ("i64.and", 0x83, &[I64, I64], &[I64]),
+    ("i64.or", 0x84, &[I64, I64], &[I64]),
+    ("i64.xor", 0x85, &[I64, I64], &[I64]),
+    ("i64.shl", 0x86, &[I64, I64], &[I64]),
+    ("i64.shr_s", 0x87, &[I64, I64], &[I64]),
+    ("i64.shr_u", 0x88, &[I64, I64], &[I64]),
+    ("i32.wrap_i64", 0xa7, &[I64], &[I32]),
+    ("i64.extend_i32_s", 0xac, &[I32], &[I64]),
+    ("i64.extend_i32_u", 0xad, &[I32], &[I64]),
+    ("i32.extend8_s", 0xc0, &[I32], &[I32]),
+    ("i32.extend16_s", 0xc1, &[I32], &[I32]),
+];
+
+// prefix and sub-opcodes of the bulk-memory instructions
+pub const BULK_PREFIX: u8 = 0xfc;
+pub const MEMORY_COPY: u32 = 10;
+pub const MEMORY_FILL: u32 = 11;
+
+fn opcode(name: &str) -> u8 {
+    OPCODES
+        .iter()
+        .find(|(op, ..)| *op == name)
+        .unwrap_or_else(|| unreachable!("unknown instruction '{}'", name))
+        .1
+}
+
+/// Function imported from the host-environment
+pub struct Import {
+    pub name: String,
+    pub type_index: usize,
+}
+
+pub struct Func {
+    pub name: String,
+    pub type_index: usize,
+    // declared locals following the parameters
+    pub locals: Vec<ValType>,
+    pub body: Vec<Instr>,
+    pub export: bool,
+}
+
+#[derive(Default)]
+pub struct Module {
+    pub types: Vec<Signature>,
+    pub imports: Vec<Import>,
+    pub funcs: Vec<Func>,
+
+    // functions whose address is taken, slot 0 stays empty so that it can be used as null-pointer
+    pub table: Vec<String>,
+
+    // size of linear memory in 64KiB pages
+    pub pages: u32,
+
+    // initial value of the shadow-stack pointer, the stack grows downwards
+    pub stack_pointer: u32,
+
+    // static data and the address it is placed at
+    pub data_offset: u32,
+    pub data: Vec<u8>,
+}
+impl Module {
+    /// Returns the index of the signature in the type-section, adding it if it doesn't exist
+    pub fn type_index(&mut self, signature: Signature) -> usize {
+        match self.types.iter().position(|ty| *ty == signature) {
+            Some(index) => index,
+            None => {
+                self.types.push(signature);
+                self.types.len() - 1
+            }
+        }
+    }
+    // imported functions come before defined ones in the index-space
+    fn func_index(&self, name: &str) -> u32 {
+        if let Some(index) = self.imports.iter().position(|import| import.name == name) {
+            index as u32
+        } else {
+            let index = self
+                .funcs
+                .iter()
+                .position(|func| func.name == name)
+                .expect("all functions are defined or imported");
+            (self.imports.len() + index) as u32
+        }
+    }
+
+    /// Prints the module in the WebAssembly text-format
+    pub fn wat(&self) -> String {
+        let mut out = String::from("(module");
+
+        for (i, ty) in self.types.iter().enumerate() {
+            write!(out, "\n  (type $t{} (func{}))", i, signature_wat(ty)).unwrap();
+        }
+        for import in self.imports.iter() {
+            write!(
+                out,
+                "\n  (import \"env\" \"{}\" (func ${} (type $t{})))",
+                import.name, import.name, import.type_index
+            )
+            .unwrap();
+        }
+
+        let slots = self.table.len() + 1;
+        write!(out, "\n  (table {} {} funcref)", slots, slots).unwrap();
+        write!(out, "\n  (memory (export \"memory\") {})", self.pages).unwrap();
+        write!(
+            out,
+            "\n  (global $__stack_pointer (mut i32) (i32.const {}))",
+            self.stack_pointer
+        )
+        .unwrap();
+        if !self.table.is_empty() {
+            let names: Vec<String> = self.table.iter().map(|name| format!("${}", name)).collect();
+            write!(out, "\n  (elem (i32.const 1) func {})", names.join(" ")).unwrap();
+        }
+
+        for func in self.funcs.iter() {
+            write!(out, "\n  (func ${}", func.name).unwrap();
+            if func.export {
+                write!(out, " (export \"{}\")", func.name).unwrap();
+            }
+            write!(
+                out,
+                " (type $t{}){}",
+                func.type_index,
+                signature_wat(&self.types[func.type_index])
+            )
+            .unwrap();
+
+            if !func.locals.is_empty() {
+                let locals: Vec<&str> = func.locals.iter().map(ValType::name).collect();
+                write!(out, "\n    (local {})", locals.join(" ")).unwrap();
+            }
+
+            let mut depth = 2;
+            for instr in func.body.iter() {
+                if matches!(instr, Instr::Else | Instr::End) {
+                    depth -= 1;
+                }
+                write!(out, "\n{}{}", "  ".repeat(depth), instr_wat(instr)).unwrap();
+                if matches!(
+                    instr,
+                    Instr::Block(..) | Instr::Loop(..) | Instr::If(..) | Instr::Else
+                ) {
+                    depth += 1;
+                }
+            }
+            out.push(')');
+        }
+
+        if !self.data.is_empty() {
+            let bytes: String = self
+                .data
+                .iter()
+                .map(|byte| match *byte {
+                    b'"' | b'\\' => format!("\\{:02x}", byte),
+                    0x20..=0x7e => (*byte as char).to_string(),
+                    _ => format!("\\{:02x}", byte),
+                })
+                .collect();
+            write!(out, "\n  (data (i32.const {}) \"{}\")", self.data_offset, bytes).unwrap();
+        }
+        out.push(')');
+
+        out
+    }
+
+    /// Encodes the module in the WebAssembly binary-format
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+
+        // type-section
+        let mut content = Vec::new();
+        uleb(&mut content, self.types.len() as u64);
+        for ty in self.types.iter() {
+            content.push(0x60);
+            uleb(&mut content, ty.params.len() as u64);
+            content.extend(ty.params.iter().map(ValType::code));
+            uleb(&mut content, ty.result.is_some() as u64);
+            content.extend(ty.result.iter().map(ValType::code));
+        }
+        section(&mut out, 1, content);
+
+        // import-section
+        if !self.imports.is_empty() {
+            let mut content = Vec::new();
+            uleb(&mut content, self.imports.len() as u64);
+            for import in self.imports.iter() {
+                name(&mut content, "env");
+                name(&mut content, &import.name);
+                content.push(0x00);
+                uleb(&mut content, import.type_index as u64);
+            }
+            section(&mut out, 2, content);
+        }
+
+        // function-section
+        let mut content = Vec::new();
+        uleb(&mut content, self.funcs.len() as u64);
+        for func in self.funcs.iter() {
+            uleb(&mut content, func.type_index as u64);
+        }
+        section(&mut out, 3, content);
+
+        // table-section
+        let slots = self.table.len() as u64 + 1;
+        let mut content = vec![0x01, 0x70, 0x01];
+        uleb(&mut content, slots);
+        uleb(&mut content, slots);
+        section(&mut out, 4, content);
+
+        // memory-section
+        let mut content = vec![0x01, 0x00];
+        uleb(&mut content, self.pages as u64);
+        section(&mut out, 5, content);
+
+        // global-section
+        let mut content = vec![0x01, ValType::I32.code(), 0x01, 0x41];
+        sleb(&mut content, self.stack_pointer as i32 as i64);
+        content.push(0x0b);
+        section(&mut out, 6, content);
+
+        // export-section
+        let exports: Vec<&Func> = self.funcs.iter().filter(|func| func.export).collect();
+        let mut content = Vec::new();
+        uleb(&mut content, exports.len() as u64 + 1);
+        name(&mut content, "memory");
+        content.extend([0x02, 0x00]);
+        for func in exports {
+            name(&mut content, &func.name);
+            content.push(0x00);
+            uleb(&mut content, self.func_index(&func.name) as u64);
+        }
+        section(&mut out, 7, content);
+
+        // element-section
+        if !self.table.is_empty() {
+            let mut content = vec![0x01, 0x00, 0x41, 0x01, 0x0b];
+            uleb(&mut content, self.table.len() as u64);
+            for func in self.table.iter() {
+                uleb(&mut content, self.func_index(func) as u64);
+            }
+            section(&mut out, 9, content);
+        }
+
+        // code-section
+        let mut content = Vec::new();
+        uleb(&mut content, self.funcs.len() as u64);
+        for func in self.funcs.iter() {
+            let body = self.encode_func(func);
+            uleb(&mut content, body.len() as u64);
+            content.extend(body);
+        }
+        section(&mut out, 10, content);
+
+        // data-section
+        if !self.data.is_empty() {
+            let mut content = vec![0x01, 0x00, 0x41];
+            sleb(&mut content, self.data_offset as i64);
+            content.push(0x0b);
+            uleb(&mut content, self.data.len() as u64);
+            content.extend(self.data.iter());
+            section(&mut out, 11, content);
+        }
+
+        out
+    }
+    fn encode_func(&self, func: &Func) -> Vec<u8> {
+        let mut out = Vec::new();
+
+        // locals are declared as runs of the same type
+        let mut runs: Vec<(u64, ValType)> = Vec::new();
+        for ty in func.locals.iter() {
+            match runs.last_mut() {
+                Some((count, run_type)) if run_type == ty => *count += 1,
+                _ => runs.push((1, *ty)),
+            }
+        }
+        uleb(&mut out, runs.len() as u64);
+        for (count, ty) in runs {
+            uleb(&mut out, count);
+            out.push(ty.code());
+        }
+
+        // branches refer to their target by its nesting-depth
+        let mut labels: Vec<Label> = Vec::new();
+        let depth = |labels: &Vec<Label>, label: &Label| {
+            let position = labels
+                .iter()
+                .rposition(|l| l == label)
+                .expect("branch-target encloses branch");
+            (labels.len() - 1 - position) as u64
+        };
+        let block_type = |out: &mut Vec<u8>, ty: &Option<ValType>| {
+            out.push(ty.map_or(0x40, |ty| ty.code()));
+        };
+
+        for instr in func.body.iter() {
+            match instr {
+                Instr::Block(label, ty) | Instr::Loop(label, ty) | Instr::If(label, ty) => {
+                    out.push(match instr {
+                        Instr::Block(..) => 0x02,
+                        Instr::Loop(..) => 0x03,
+                        _ => 0x04,
+                    });
+                    block_type(&mut out, ty);
+                    labels.push(*label);
+                }
+                Instr::Else => out.push(0x05),
+                Instr::End => {
+                    out.push(0x0b);
+                    labels.pop();
+                }
+                Instr::Br(label) => {
+                    out.push(0x0c);
+                    uleb(&mut out, depth(&labels, label));
+                }
+                Instr::BrIf(label) => {
+                    out.push(0x0d);
+                    uleb(&mut out, depth(&labels, label));
+                }
+                Instr::BrTable(targets, default) => {
+                    out.push(0x0e);
+                    uleb(&mut out, targets.len() as u64);
+                    for label in targets {
+                        uleb(&mut out, depth(&labels, label));
+                    }
+                    uleb(&mut out, depth(&labels, default));
+                }
+                Instr::Return => out.push(0x0f),
+                Instr::Unreachable => out.push(0x00),
+                Instr::Drop => out.push(0x1a),
+                Instr::Call(name) => {
+                    out.push(0x10);
+                    uleb(&mut out, self.func_index(name) as u64);
+                }
+                Instr::CallIndirect(type_index) => {
+                    out.push(0x11);
+                    uleb(&mut out, *type_index as u64);
+                    out.push(0x00);
+                }
+                Instr::LocalGet(index) | Instr::LocalSet(index) | Instr::LocalTee(index) => {
+                    out.push(match instr {
+                        Instr::LocalGet(_) => 0x20,
+                        Instr::LocalSet(_) => 0x21,
+                        _ => 0x22,
+                    });
+                    uleb(&mut out, *index as u64);
+                }
+                Instr::GlobalGet(index) | Instr::GlobalSet(index) => {
+                    out.push(if matches!(instr, Instr::GlobalGet(_)) {
+                        0x23
+                    } else {
+                        0x24
+                    });
+                    uleb(&mut out, *index as u64);
+                }
+                Instr::I32Const(value) => {
+                    out.push(0x41);
+                    sleb(&mut out, *value as i64);
+                }
+                Instr::I64Const(value) => {
+                    out.push(0x42);
+                    sleb(&mut out, *value);
+                }
+                Instr::Load(name, offset) | Instr::Store(name, offset) => {
+                    out.push(opcode(name));
+                    // types are packed so no alignment can be assumed
+                    out.push(0x00);
+                    uleb(&mut out, *offset as u64);
+                }
+                Instr::Op(name) => out.push(opcode(name)),
+                Instr::MemoryCopy => {
+                    out.push(BULK_PREFIX);
+                    uleb(&mut out, MEMORY_COPY as u64);
+                    out.extend([0x00, 0x00]);
+                }
+                Instr::MemoryFill => {
+                    out.push(BULK_PREFIX);
+                    uleb(&mut out, MEMORY_FILL as u64);
+                    out.push(0x00);
+                }
+            }
+        }
+        out.push(0x0b);
+
+        out
+    }
+}
+
+fn signature_wat(ty: &Signature) -> String {
+    let mut out = String::new();
+    if !ty.params.is_empty() {
+        let params: Vec<&str> = ty.params.iter().map(ValType::name).collect();
+        write!(out, " (param {})", params.join(" ")).unwrap();
+    }
+    if let Some(result) = ty.result {
+        write!(out, " (result {})", result.name()).unwrap();
+    }
+    out
+}
+
+fn instr_wat(instr: &Instr) -> String {
+    let result = |ty: &Option<ValType>| ty.map_or(String::new(), |ty| format!(" (result {})", ty.name()));
+
+    match instr {
+        Instr::Block(label, ty) => format!("block $L{}{}", label, result(ty)),
+        Instr::Loop(label, ty) => format!("loop $L{}{}", label, result(ty)),
+        Instr::If(label, ty) => format!("if $L{}{}", label, result(ty)),
+        Instr::Else => "else".to_string(),
+        Instr::End => "end".to_string(),
+        Instr::Br(label) => format!("br $L{}", label),
+        Instr::BrIf(label) => format!("br_if $L{}", label),
+        Instr::BrTable(targets, default) => {
+            let targets: Vec<String> = targets.iter().map(|label| format!("$L{}", label)).collect();
+            format!("br_table {} $L{}", targets.join(" "), default)
+        }
+        Instr::Return => "return".to_string(),
+        Instr::Unreachable => "unreachable".to_string(),
+        Instr::Drop => "drop".to_string(),
+        Instr::Call(name) => format!("call ${}", name),
+        Instr::CallIndirect(type_index) => format!("call_indirect (type $t{})", type_index),
+        Instr::LocalGet(index) => format!("local.get {}", index),
+        Instr::LocalSet(index) => format!("local.set {}", index),
+        Instr::LocalTee(index) => format!("local.tee {}", index),
+        Instr::GlobalGet(_) => "global.get $__stack_pointer".to_string(),
+        Instr::GlobalSet(_) => "global.set $__stack_pointer".to_string(),
+        Instr::I32Const(value) => format!("i32.const {}", value),
+        Instr::I64Const(value) => format!("i64.const {}", value),
+        Instr::Load(name, 0) | Instr::Store(name, 0) => name.to_string(),
+        Instr::Load(name, offset) | Instr::Store(name, offset) => format!("{} offset={}", name, offset),
+        Instr::Op(name) => name.to_string(),
+        Instr::MemoryCopy => "memory.copy".to_string(),
+        Instr::MemoryFill => "memory.fill".to_string(),
+    }
+}
+
+fn section(out: &mut Vec<u8>, id: u8, content: Vec<u8>) {
+    out.push(id);
+    uleb(out, content.len() as u64);
+    out.extend(content);
+}
+fn name(out: &mut Vec<u8>, name: &str) {
+    uleb(out, name.len() as u64);
+    out.extend(name.bytes());
+}
+
+pub fn uleb(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            break;
+        }
+        out.push(byte | 0x80);
+    }
+}
+pub fn sleb(out: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            out.push(byte);
+            break;
+        }
+        out.push(byte | 0x80);
+    }
+}
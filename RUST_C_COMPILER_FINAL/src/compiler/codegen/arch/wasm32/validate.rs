@@ -0,0 +1,552 @@
+//! Parses a binary module and validates it as described in the WebAssembly specification, so
+//! that the generated modules can be checked without an external runtime.<br>
+//! Only the subset of the format that the [backend](super) emits is supported.
+
+use super::module::*;
+
+/// Returns an error describing the first malformed or invalid part of the module
+pub fn validate(bytes: &[u8]) -> Result<(), String> {
+    let mut reader = Reader { bytes, pos: 0 };
+    if reader.take(8)? != [0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00] {
+        return Err("invalid magic-number or version".to_string());
+    }
+
+    let mut context = Context::default();
+    let mut func_types = Vec::new();
+    let mut last_id = 0;
+
+    while !reader.is_empty() {
+        let id = reader.byte()?;
+        let size = reader.uleb()? as usize;
+        let mut section = Reader {
+            bytes: reader.take(size)?,
+            pos: 0,
+        };
+
+        if id <= last_id {
+            return Err(format!("section {} out of order", id));
+        }
+        last_id = id;
+
+        match id {
+            1 => {
+                for _ in 0..section.uleb()? {
+                    if section.byte()? != 0x60 {
+                        return Err("expected function-type".to_string());
+                    }
+                    let params = section.val_types()?;
+                    let results = section.val_types()?;
+                    if results.len() > 1 {
+                        return Err("multiple results".to_string());
+                    }
+                    context.types.push((params, results));
+                }
+            }
+            2 => {
+                for _ in 0..section.uleb()? {
+                    section.name()?;
+                    section.name()?;
+                    if section.byte()? != 0x00 {
+                        return Err("only functions can be imported".to_string());
+                    }
+                    let type_index = context.type_index(section.uleb()?)?;
+                    context.funcs.push(type_index);
+                }
+            }
+            3 => {
+                for _ in 0..section.uleb()? {
+                    let type_index = context.type_index(section.uleb()?)?;
+                    context.funcs.push(type_index);
+                    func_types.push(type_index);
+                }
+            }
+            4 => {
+                if section.uleb()? != 1 || section.byte()? != 0x70 {
+                    return Err("expected single funcref-table".to_string());
+                }
+                context.table = Some(section.limits()?);
+            }
+            5 => {
+                if section.uleb()? != 1 {
+                    return Err("expected single memory".to_string());
+                }
+                context.memory = Some(section.limits()?);
+            }
+            6 => {
+                for _ in 0..section.uleb()? {
+                    let ty = section.val_type()?;
+                    let mutable = match section.byte()? {
+                        0x00 => false,
+                        0x01 => true,
+                        byte => return Err(format!("invalid mutability {:#x}", byte)),
+                    };
+                    if section.const_expr()? != ty {
+                        return Err("global initialized with wrong type".to_string());
+                    }
+                    context.globals.push((ty, mutable));
+                }
+            }
+            7 => {
+                let mut names = Vec::new();
+                for _ in 0..section.uleb()? {
+                    let name = section.name()?;
+                    if names.contains(&name) {
+                        return Err(format!("duplicate export '{}'", name));
+                    }
+                    let kind = section.byte()?;
+                    let index = section.uleb()? as usize;
+                    let exists = match kind {
+                        0x00 => index < context.funcs.len(),
+                        0x01 => context.table.is_some() && index == 0,
+                        0x02 => context.memory.is_some() && index == 0,
+                        0x03 => index < context.globals.len(),
+                        _ => return Err(format!("invalid export-kind {:#x}", kind)),
+                    };
+                    if !exists {
+                        return Err(format!("export '{}' refers to unknown index", name));
+                    }
+                    names.push(name);
+                }
+            }
+            9 => {
+                let slots = context.table.ok_or("elements without table")?;
+                for _ in 0..section.uleb()? {
+                    if section.uleb()? != 0 {
+                        return Err("only active elements are supported".to_string());
+                    }
+                    let offset = section.const_offset()?;
+                    let count = section.uleb()?;
+                    if offset + count > slots as u64 {
+                        return Err("elements out of table-bounds".to_string());
+                    }
+                    for _ in 0..count {
+                        context.func_type(section.uleb()?)?;
+                    }
+                }
+            }
+            10 => {
+                if section.uleb()? as usize != func_types.len() {
+                    return Err("function- and code-section have different lengths".to_string());
+                }
+                for (i, type_index) in func_types.iter().enumerate() {
+                    let size = section.uleb()? as usize;
+                    let mut body = Reader {
+                        bytes: section.take(size)?,
+                        pos: 0,
+                    };
+                    validate_func(&context, *type_index, &mut body)
+                        .map_err(|e| format!("function {}: {}", i, e))?;
+                }
+            }
+            11 => {
+                let pages = context.memory.ok_or("data without memory")?;
+                for _ in 0..section.uleb()? {
+                    if section.uleb()? != 0 {
+                        return Err("only active data is supported".to_string());
+                    }
+                    let offset = section.const_offset()?;
+                    let len = section.uleb()?;
+                    section.take(len as usize)?;
+                    if offset + len > pages as u64 * 64 * 1024 {
+                        return Err("data out of memory-bounds".to_string());
+                    }
+                }
+            }
+            _ => return Err(format!("unexpected section {}", id)),
+        }
+
+        if !section.is_empty() {
+            return Err(format!("section {} has trailing bytes", id));
+        }
+    }
+
+    if last_id < 10 && !func_types.is_empty() {
+        return Err("functions without code".to_string());
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+struct Context {
+    types: Vec<(Vec<ValType>, Vec<ValType>)>,
+    // type-index of every function, imports first
+    funcs: Vec<usize>,
+    // minimum size of table and memory
+    table: Option<u32>,
+    memory: Option<u32>,
+    globals: Vec<(ValType, bool)>,
+}
+impl Context {
+    fn type_index(&self, index: u64) -> Result<usize, String> {
+        if (index as usize) < self.types.len() {
+            Ok(index as usize)
+        } else {
+            Err(format!("unknown type {}", index))
+        }
+    }
+    fn func_type(&self, index: u64) -> Result<&(Vec<ValType>, Vec<ValType>), String> {
+        let type_index = self
+            .funcs
+            .get(index as usize)
+            .ok_or(format!("unknown function {}", index))?;
+        Ok(&self.types[*type_index])
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+impl<'a> Reader<'a> {
+    fn is_empty(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+    fn byte(&mut self) -> Result<u8, String> {
+        let byte = *self.bytes.get(self.pos).ok_or("unexpected end")?;
+        self.pos += 1;
+        Ok(byte)
+    }
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.pos + len > self.bytes.len() {
+            return Err("unexpected end".to_string());
+        }
+        self.pos += len;
+        Ok(&self.bytes[self.pos - len..self.pos])
+    }
+    fn uleb(&mut self) -> Result<u64, String> {
+        let mut result = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            result |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+        Err("integer too long".to_string())
+    }
+    fn sleb(&mut self) -> Result<i64, String> {
+        let mut result = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            result |= ((byte & 0x7f) as i64) << shift;
+            if byte & 0x80 == 0 {
+                if shift < 57 && byte & 0x40 != 0 {
+                    result |= -1 << (shift + 7);
+                }
+                return Ok(result);
+            }
+        }
+        Err("integer too long".to_string())
+    }
+    fn name(&mut self) -> Result<String, String> {
+        let len = self.uleb()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| "name isn't utf-8".to_string())
+    }
+    fn val_type(&mut self) -> Result<ValType, String> {
+        match self.byte()? {
+            0x7f => Ok(ValType::I32),
+            0x7e => Ok(ValType::I64),
+            byte => Err(format!("unsupported value-type {:#x}", byte)),
+        }
+    }
+    fn val_types(&mut self) -> Result<Vec<ValType>, String> {
+        (0..self.uleb()?).map(|_| self.val_type()).collect()
+    }
+    fn limits(&mut self) -> Result<u32, String> {
+        let min = match self.byte()? {
+            0x00 => self.uleb()?,
+            0x01 => {
+                let (min, max) = (self.uleb()?, self.uleb()?);
+                if min > max {
+                    return Err("minimum of limits exceeds maximum".to_string());
+                }
+                min
+            }
+            byte => return Err(format!("invalid limits {:#x}", byte)),
+        };
+        Ok(min as u32)
+    }
+    fn const_expr(&mut self) -> Result<ValType, String> {
+        let ty = match self.byte()? {
+            0x41 => ValType::I32,
+            0x42 => ValType::I64,
+            byte => return Err(format!("unsupported constant-expression {:#x}", byte)),
+        };
+        self.sleb()?;
+        if self.byte()? != 0x0b {
+            return Err("constant-expression not terminated".to_string());
+        }
+        Ok(ty)
+    }
+    fn const_offset(&mut self) -> Result<u64, String> {
+        let pos = self.pos;
+        if self.const_expr()? != ValType::I32 {
+            return Err("offset has to be i32".to_string());
+        }
+        let mut offset = Reader {
+            bytes: &self.bytes[pos + 1..],
+            pos: 0,
+        };
+        Ok(offset.sleb()? as u32 as u64)
+    }
+}
+
+struct ControlFrame {
+    is_loop: bool,
+    is_if: bool,
+    results: Vec<ValType>,
+    height: usize,
+    unreachable: bool,
+}
+
+// operand-stack where unknown values of unreachable code are `None`
+struct Validator {
+    values: Vec<Option<ValType>>,
+    frames: Vec<ControlFrame>,
+}
+impl Validator {
+    fn push(&mut self, ty: ValType) {
+        self.values.push(Some(ty))
+    }
+    fn pop(&mut self) -> Result<Option<ValType>, String> {
+        let frame = self.frames.last().ok_or("instruction after end of function")?;
+        if self.values.len() == frame.height {
+            if frame.unreachable {
+                return Ok(None);
+            }
+            return Err("operand-stack underflow".to_string());
+        }
+        Ok(self.values.pop().unwrap())
+    }
+    fn pop_expect(&mut self, expected: ValType) -> Result<(), String> {
+        match self.pop()? {
+            Some(actual) if actual != expected => Err(format!(
+                "expected {} but found {}",
+                expected.name(),
+                actual.name()
+            )),
+            _ => Ok(()),
+        }
+    }
+    fn pop_all(&mut self, types: &[ValType]) -> Result<(), String> {
+        types.iter().rev().try_for_each(|ty| self.pop_expect(*ty))
+    }
+    fn push_frame(&mut self, is_loop: bool, is_if: bool, results: Vec<ValType>) {
+        self.frames.push(ControlFrame {
+            is_loop,
+            is_if,
+            results,
+            height: self.values.len(),
+            unreachable: false,
+        })
+    }
+    // checks that the frame left exactly its results on the stack
+    fn pop_frame(&mut self) -> Result<ControlFrame, String> {
+        let results = self.frames.last().ok_or("unbalanced end")?.results.clone();
+        self.pop_all(&results)?;
+
+        let frame = self.frames.pop().unwrap();
+        if self.values.len() != frame.height {
+            return Err("values remaining at end of block".to_string());
+        }
+        Ok(frame)
+    }
+    fn set_unreachable(&mut self) {
+        let frame = self.frames.last_mut().unwrap();
+        self.values.truncate(frame.height);
+        frame.unreachable = true;
+    }
+    // branching to a loop restarts it so it expects no values
+    fn label_types(&self, depth: u64) -> Result<Vec<ValType>, String> {
+        let frame = self
+            .frames
+            .iter()
+            .rev()
+            .nth(depth as usize)
+            .ok_or(format!("unknown label-depth {}", depth))?;
+        Ok(if frame.is_loop {
+            Vec::new()
+        } else {
+            frame.results.clone()
+        })
+    }
+}
+
+fn validate_func(context: &Context, type_index: usize, body: &mut Reader) -> Result<(), String> {
+    let (params, results) = &context.types[type_index];
+    let mut locals = params.clone();
+    for _ in 0..body.uleb()? {
+        let count = body.uleb()?;
+        let ty = body.val_type()?;
+        locals.extend(std::iter::repeat_n(ty, count as usize));
+    }
+
+    let mut validator = Validator {
+        values: Vec::new(),
+        frames: Vec::new(),
+    };
+    validator.push_frame(false, false, results.clone());
+
+    let block_type = |body: &mut Reader| -> Result<Vec<ValType>, String> {
+        match body.byte()? {
+            0x40 => Ok(Vec::new()),
+            0x7f => Ok(vec![ValType::I32]),
+            0x7e => Ok(vec![ValType::I64]),
+            byte => Err(format!("unsupported block-type {:#x}", byte)),
+        }
+    };
+    let local = |body: &mut Reader| -> Result<ValType, String> {
+        let index = body.uleb()?;
+        locals
+            .get(index as usize)
+            .copied()
+            .ok_or(format!("unknown local {}", index))
+    };
+    let global = |body: &mut Reader| -> Result<(ValType, bool), String> {
+        let index = body.uleb()?;
+        context
+            .globals
+            .get(index as usize)
+            .copied()
+            .ok_or(format!("unknown global {}", index))
+    };
+    let memory = || {
+        context
+            .memory
+            .ok_or("memory-instruction without memory".to_string())
+    };
+
+    while !validator.frames.is_empty() {
+        let opcode = body.byte()?;
+        match opcode {
+            0x00 => validator.set_unreachable(),
+            0x01 => (),
+            0x02 | 0x03 => {
+                let results = block_type(body)?;
+                validator.push_frame(opcode == 0x03, false, results);
+            }
+            0x04 => {
+                let results = block_type(body)?;
+                validator.pop_expect(ValType::I32)?;
+                validator.push_frame(false, true, results);
+            }
+            0x05 => {
+                let frame = validator.pop_frame()?;
+                if !frame.is_if {
+                    return Err("else outside of if".to_string());
+                }
+                validator.push_frame(false, false, frame.results);
+            }
+            0x0b => {
+                let frame = validator.pop_frame()?;
+                // without an else-branch the condition being false produces no values
+                if frame.is_if && !frame.results.is_empty() {
+                    return Err("if with result but without else".to_string());
+                }
+                frame.results.into_iter().for_each(|ty| validator.push(ty));
+            }
+            0x0c => {
+                let types = validator.label_types(body.uleb()?)?;
+                validator.pop_all(&types)?;
+                validator.set_unreachable();
+            }
+            0x0d => {
+                let types = validator.label_types(body.uleb()?)?;
+                validator.pop_expect(ValType::I32)?;
+                validator.pop_all(&types)?;
+                types.into_iter().for_each(|ty| validator.push(ty));
+            }
+            0x0e => {
+                let targets = (0..body.uleb()?)
+                    .map(|_| body.uleb())
+                    .collect::<Result<Vec<_>, _>>()?;
+                let types = validator.label_types(body.uleb()?)?;
+                for target in targets {
+                    if validator.label_types(target)?.len() != types.len() {
+                        return Err("br_table targets have different arity".to_string());
+                    }
+                }
+                validator.pop_expect(ValType::I32)?;
+                validator.pop_all(&types)?;
+                validator.set_unreachable();
+            }
+            0x0f => {
+                validator.pop_all(results)?;
+                validator.set_unreachable();
+            }
+            0x10 => {
+                let (params, results) = context.func_type(body.uleb()?)?;
+                validator.pop_all(params)?;
+                results.iter().for_each(|ty| validator.push(*ty));
+            }
+            0x11 => {
+                let (params, results) = &context.types[context.type_index(body.uleb()?)?];
+                if body.byte()? != 0x00 || context.table.is_none() {
+                    return Err("call_indirect without table".to_string());
+                }
+                validator.pop_expect(ValType::I32)?;
+                validator.pop_all(params)?;
+                results.iter().for_each(|ty| validator.push(*ty));
+            }
+            0x1a => {
+                validator.pop()?;
+            }
+            0x20 => validator.push(local(body)?),
+            0x21 => validator.pop_expect(local(body)?)?,
+            0x22 => {
+                let ty = local(body)?;
+                validator.pop_expect(ty)?;
+                validator.push(ty);
+            }
+            0x23 => validator.push(global(body)?.0),
+            0x24 => {
+                let (ty, mutable) = global(body)?;
+                if !mutable {
+                    return Err("global.set of immutable global".to_string());
+                }
+                validator.pop_expect(ty)?;
+            }
+            0x41 => {
+                body.sleb()?;
+                validator.push(ValType::I32);
+            }
+            0x42 => {
+                body.sleb()?;
+                validator.push(ValType::I64);
+            }
+            BULK_PREFIX => {
+                let immediates = match body.uleb()? as u32 {
+                    MEMORY_COPY => 2,
+                    MEMORY_FILL => 1,
+                    op => return Err(format!("unsupported bulk-instruction {}", op)),
+                };
+                memory()?;
+                for _ in 0..immediates {
+                    if body.byte()? != 0x00 {
+                        return Err("bulk-instruction refers to unknown memory".to_string());
+                    }
+                }
+                validator.pop_all(&[ValType::I32, ValType::I32, ValType::I32])?;
+            }
+            _ => {
+                let (name, _, operands, results) = OPCODES
+                    .iter()
+                    .find(|(_, code, ..)| *code == opcode)
+                    .ok_or(format!("unsupported opcode {:#x}", opcode))?;
+
+                if name.contains(".load") || name.contains(".store") {
+                    memory()?;
+                    body.uleb()?;
+                    body.uleb()?;
+                }
+                validator.pop_all(operands)?;
+                results.iter().for_each(|ty| validator.push(*ty));
+            }
+        }
+    }
+
+    if !body.is_empty() {
+        return Err("instructions after end of function".to_string());
+    }
+    Ok(())
+}
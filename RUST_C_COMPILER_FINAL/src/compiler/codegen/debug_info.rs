@@ -0,0 +1,579 @@
+//! Collects information about functions, variables and their types during codegen and emits them as
+//! [DWARF](https://dwarfstd.org/doc/DWARF4.pdf) `.debug_info` and `.debug_abbrev` sections.<br>
+//! The line-table itself is generated by the assembler from the `.file` and `.loc` directives.
+
+use crate::compiler::common::types::*;
+use crate::compiler::typechecker::mir::decl::Function;
+use std::path::PathBuf;
+
+// tags
+const DW_TAG_ARRAY_TYPE: u16 = 0x01;
+const DW_TAG_ENUMERATION_TYPE: u16 = 0x04;
+const DW_TAG_FORMAL_PARAMETER: u16 = 0x05;
+const DW_TAG_MEMBER: u16 = 0x0d;
+const DW_TAG_POINTER_TYPE: u16 = 0x0f;
+const DW_TAG_COMPILE_UNIT: u16 = 0x11;
+const DW_TAG_STRUCTURE_TYPE: u16 = 0x13;
+const DW_TAG_SUBROUTINE_TYPE: u16 = 0x15;
+const DW_TAG_UNION_TYPE: u16 = 0x17;
+const DW_TAG_UNSPECIFIED_PARAMETERS: u16 = 0x18;
+const DW_TAG_SUBRANGE_TYPE: u16 = 0x21;
+const DW_TAG_BASE_TYPE: u16 = 0x24;
+const DW_TAG_CONST_TYPE: u16 = 0x26;
+const DW_TAG_ENUMERATOR: u16 = 0x28;
+const DW_TAG_SUBPROGRAM: u16 = 0x2e;
+const DW_TAG_VARIABLE: u16 = 0x34;
+const DW_TAG_VOLATILE_TYPE: u16 = 0x35;
+const DW_TAG_RESTRICT_TYPE: u16 = 0x37;
+
+// attributes
+const DW_AT_LOCATION: u16 = 0x02;
+const DW_AT_NAME: u16 = 0x03;
+const DW_AT_BYTE_SIZE: u16 = 0x0b;
+const DW_AT_STMT_LIST: u16 = 0x10;
+const DW_AT_LOW_PC: u16 = 0x11;
+const DW_AT_HIGH_PC: u16 = 0x12;
+const DW_AT_LANGUAGE: u16 = 0x13;
+const DW_AT_COMP_DIR: u16 = 0x1b;
+const DW_AT_CONST_VALUE: u16 = 0x1c;
+const DW_AT_PRODUCER: u16 = 0x25;
+const DW_AT_PROTOTYPED: u16 = 0x27;
+const DW_AT_COUNT: u16 = 0x37;
+const DW_AT_DATA_MEMBER_LOCATION: u16 = 0x38;
+const DW_AT_DECL_FILE: u16 = 0x3a;
+const DW_AT_DECL_LINE: u16 = 0x3b;
+const DW_AT_DECLARATION: u16 = 0x3c;
+const DW_AT_ENCODING: u16 = 0x3e;
+const DW_AT_EXTERNAL: u16 = 0x3f;
+const DW_AT_FRAME_BASE: u16 = 0x40;
+const DW_AT_TYPE: u16 = 0x49;
+
+// forms
+const DW_FORM_ADDR: u8 = 0x01;
+const DW_FORM_STRING: u8 = 0x08;
+const DW_FORM_SDATA: u8 = 0x0d;
+const DW_FORM_UDATA: u8 = 0x0f;
+const DW_FORM_REF4: u8 = 0x13;
+const DW_FORM_SEC_OFFSET: u8 = 0x17;
+const DW_FORM_EXPRLOC: u8 = 0x18;
+const DW_FORM_FLAG_PRESENT: u8 = 0x19;
+
+// base-type encodings
+const DW_ATE_SIGNED: u64 = 0x05;
+const DW_ATE_SIGNED_CHAR: u64 = 0x06;
+const DW_ATE_UNSIGNED: u64 = 0x07;
+const DW_ATE_UNSIGNED_CHAR: u64 = 0x08;
+
+// location operations
+const DW_OP_ADDR: u8 = 0x03;
+const DW_OP_REG6: u8 = 0x56;
+const DW_OP_FBREG: u8 = 0x91;
+
+const DW_LANG_C99: u64 = 0x0c;
+
+/// Where a variable is stored during its lifetime
+#[derive(Debug)]
+pub enum VarLocation {
+    /// Offset from the base-pointer
+    Stack(isize),
+    /// Label of statically allocated variable
+    Label(String),
+}
+
+/// A parameter, local- or global-variable
+#[derive(Debug)]
+pub struct Variable {
+    pub name: String,
+    pub qtype: QualType,
+    /// Index of the file in the line-table and the line where the variable was declared
+    pub decl: (usize, i32),
+    pub location: VarLocation,
+    pub is_external: bool,
+}
+
+#[derive(Debug)]
+struct Subprogram {
+    name: String,
+    decl: (usize, i32),
+    return_type: QualType,
+    variadic: bool,
+    is_external: bool,
+    end_label: usize,
+    params: Vec<Variable>,
+    locals: Vec<Variable>,
+}
+
+/// Debugging information of a whole translation-unit
+#[derive(Debug)]
+pub struct DebugInfo {
+    // file that is being compiled
+    filename: PathBuf,
+
+    // all files referenced in `.file` directives, their line-table index is their position + 1
+    files: Vec<PathBuf>,
+
+    // labels enclosing all of the generated code
+    text_labels: (usize, usize),
+
+    // statement that was last located so that loops can map their condition back to it
+    pub location: Option<(usize, i32, i32)>,
+
+    globals: Vec<Variable>,
+    functions: Vec<Subprogram>,
+
+    // variables of the function that is currently being generated, starting with its parameters
+    vars: Vec<Variable>,
+}
+impl DebugInfo {
+    pub fn new(filename: PathBuf, text_labels: (usize, usize)) -> Self {
+        DebugInfo {
+            filename,
+            text_labels,
+            files: Vec::new(),
+            location: None,
+            globals: Vec::new(),
+            functions: Vec::new(),
+            vars: Vec::new(),
+        }
+    }
+    pub fn filename(&self) -> &PathBuf {
+        &self.filename
+    }
+    pub fn text_labels(&self) -> (usize, usize) {
+        self.text_labels
+    }
+    /// Returns the line-table index of the file and if it has to be declared first
+    pub fn file_index(&mut self, filename: &PathBuf) -> (usize, bool) {
+        match self.files.iter().position(|file| file == filename) {
+            Some(index) => (index + 1, false),
+            None => {
+                self.files.push(filename.clone());
+                (self.files.len(), true)
+            }
+        }
+    }
+    pub fn add_global(&mut self, var: Variable) {
+        self.globals.push(var)
+    }
+    pub fn add_local(&mut self, var: Variable) {
+        self.vars.push(var)
+    }
+    /// Adds function that was just generated together with all the variables declared since the last function
+    pub fn add_function(&mut self, func: &Function, decl: (usize, i32), is_external: bool, end_label: usize) {
+        let mut params = std::mem::take(&mut self.vars);
+        let locals = params.split_off(func.params.len());
+
+        self.functions.push(Subprogram {
+            name: func.name.clone(),
+            decl,
+            return_type: func.return_type.clone(),
+            variadic: func.variadic,
+            is_external,
+            end_label,
+            params,
+            locals,
+        });
+    }
+
+    pub fn as_string(self) -> String {
+        let text_end = self.text_labels.1;
+        let mut types = TypeTable::new();
+
+        let mut unit = Die::new(DW_TAG_COMPILE_UNIT)
+            .attr(
+                DW_AT_PRODUCER,
+                Value::String(format!("wrecc {}", env!("CARGO_PKG_VERSION"))),
+            )
+            .attr(DW_AT_LANGUAGE, Value::Udata(DW_LANG_C99))
+            .attr(DW_AT_NAME, Value::String(self.filename.display().to_string()))
+            .attr(
+                DW_AT_COMP_DIR,
+                Value::String(
+                    std::env::current_dir()
+                        .map(|dir| dir.display().to_string())
+                        .unwrap_or_default(),
+                ),
+            )
+            .attr(DW_AT_LOW_PC, Value::Addr(format!("L{}", self.text_labels.0)))
+            .attr(DW_AT_HIGH_PC, Value::Addr(format!("L{}", text_end)))
+            .attr(DW_AT_STMT_LIST, Value::SecOffset("Ldebug_line0".to_string()));
+
+        for var in self.globals {
+            unit.children.push(var_die(DW_TAG_VARIABLE, var, &mut types));
+        }
+        for func in self.functions {
+            let mut die = Die::new(DW_TAG_SUBPROGRAM)
+                .attr(DW_AT_NAME, Value::String(func.name.clone()))
+                .attr(DW_AT_DECL_FILE, Value::Udata(func.decl.0 as u64))
+                .attr(DW_AT_DECL_LINE, Value::Udata(func.decl.1 as u64))
+                .attr(DW_AT_PROTOTYPED, Value::Flag)
+                .maybe_attr(DW_AT_TYPE, types.get(&func.return_type).map(Value::Ref))
+                .attr(DW_AT_LOW_PC, Value::Addr(func.name))
+                .attr(DW_AT_HIGH_PC, Value::Addr(format!("L{}", func.end_label)))
+                .attr(DW_AT_FRAME_BASE, Value::Expr(vec![DW_OP_REG6]));
+            if func.is_external {
+                die = die.attr(DW_AT_EXTERNAL, Value::Flag);
+            }
+
+            for param in func.params {
+                die.children
+                    .push(var_die(DW_TAG_FORMAL_PARAMETER, param, &mut types));
+            }
+            if func.variadic {
+                die.children.push(Die::new(DW_TAG_UNSPECIFIED_PARAMETERS));
+            }
+            for local in func.locals {
+                die.children.push(var_die(DW_TAG_VARIABLE, local, &mut types));
+            }
+            unit.children.push(die);
+        }
+        unit.children.append(&mut types.dies);
+
+        let mut abbrevs = Vec::new();
+        let mut info = String::new();
+        unit.write(&mut abbrevs, &mut info);
+
+        let mut abbrev_section = String::from("\t.section .debug_abbrev,\"\",@progbits\nLdebug_abbrev0:\n");
+        for (i, (tag, has_children, attrs)) in abbrevs.iter().enumerate() {
+            abbrev_section.push_str(&format!(
+                "\t.uleb128 {}\n\t.uleb128 {:#x}\n\t.byte   {}\n",
+                i + 1,
+                tag,
+                *has_children as u8
+            ));
+            for (attr, form) in attrs {
+                abbrev_section.push_str(&format!("\t.uleb128 {:#x}\n\t.uleb128 {:#x}\n", attr, form));
+            }
+            abbrev_section.push_str("\t.byte   0\n\t.byte   0\n");
+        }
+        abbrev_section.push_str("\t.byte   0");
+
+        format!(
+            "\t.text\nL{}:\n\
+             \t.section .debug_info,\"\",@progbits\n\
+             Ldebug_info0:\n\
+             \t.long   Ldebug_info_end - Ldebug_info_start\n\
+             Ldebug_info_start:\n\
+             \t.short  4\n\
+             \t.long   Ldebug_abbrev0\n\
+             \t.byte   8\n\
+             {}\
+             Ldebug_info_end:\n\
+             {}\n\
+             \t.section .debug_line,\"\",@progbits\n\
+             Ldebug_line0:",
+            text_end, info, abbrev_section
+        )
+    }
+}
+
+fn var_die(tag: u16, var: Variable, types: &mut TypeTable) -> Die {
+    let location = match var.location {
+        VarLocation::Stack(offset) => {
+            let mut expr = vec![DW_OP_FBREG];
+            expr.append(&mut sleb128(offset as i64));
+            Value::Expr(expr)
+        }
+        VarLocation::Label(label) => Value::ExprAddr(label),
+    };
+    let die = Die::new(tag)
+        .attr(DW_AT_NAME, Value::String(var.name))
+        .attr(DW_AT_DECL_FILE, Value::Udata(var.decl.0 as u64))
+        .attr(DW_AT_DECL_LINE, Value::Udata(var.decl.1 as u64))
+        .maybe_attr(DW_AT_TYPE, types.get(&var.qtype).map(Value::Ref))
+        .attr(DW_AT_LOCATION, location);
+
+    if var.is_external {
+        die.attr(DW_AT_EXTERNAL, Value::Flag)
+    } else {
+        die
+    }
+}
+
+// all types referenced by the other entries; every type is only emitted once
+struct TypeTable {
+    dies: Vec<Die>,
+    ids: Vec<(QualType, usize)>,
+    id_counter: usize,
+}
+impl TypeTable {
+    fn new() -> Self {
+        TypeTable {
+            dies: Vec::new(),
+            ids: Vec::new(),
+            id_counter: 0,
+        }
+    }
+    // returns the id of the entry describing the type, `void` is described by omitting the type
+    fn get(&mut self, qtype: &QualType) -> Option<usize> {
+        if qtype.ty.is_void() && qtype.qualifiers.is_empty() {
+            return None;
+        }
+        if let Some((_, id)) = self.ids.iter().find(|(existing, _)| existing == qtype) {
+            return Some(*id);
+        }
+
+        // register id before describing the type so that self-referential structs can refer to it
+        let id = self.id_counter;
+        self.id_counter += 1;
+        self.ids.push((qtype.clone(), id));
+
+        let mut die = if !qtype.qualifiers.is_empty() {
+            self.qualified(qtype)
+        } else {
+            self.unqualified(&qtype.ty)
+        };
+        die.id = Some(id);
+        self.dies.push(die);
+
+        Some(id)
+    }
+    // removes one qualifier at a time so that `const volatile int` becomes const -> volatile -> int
+    fn qualified(&mut self, qtype: &QualType) -> Die {
+        let mut inner = qtype.clone();
+        let tag = if inner.qualifiers.is_const {
+            inner.qualifiers.is_const = false;
+            DW_TAG_CONST_TYPE
+        } else if inner.qualifiers.is_volatile {
+            inner.qualifiers.is_volatile = false;
+            DW_TAG_VOLATILE_TYPE
+        } else {
+            inner.qualifiers.is_restrict = false;
+            DW_TAG_RESTRICT_TYPE
+        };
+
+        Die::new(tag).maybe_attr(DW_AT_TYPE, self.get(&inner).map(Value::Ref))
+    }
+    fn unqualified(&mut self, ty: &Type) -> Die {
+        match ty {
+            Type::Primitive(primitive) => {
+                let encoding = match primitive {
+                    Primitive::Char(false) => DW_ATE_SIGNED_CHAR,
+                    Primitive::Char(true) => DW_ATE_UNSIGNED_CHAR,
+                    _ if primitive.is_unsigned() => DW_ATE_UNSIGNED,
+                    _ => DW_ATE_SIGNED,
+                };
+                Die::new(DW_TAG_BASE_TYPE)
+                    .attr(DW_AT_NAME, Value::String(primitive.fmt().to_string()))
+                    .attr(DW_AT_BYTE_SIZE, Value::Udata(primitive.size() as u64))
+                    .attr(DW_AT_ENCODING, Value::Udata(encoding))
+            }
+            Type::Pointer(to) => Die::new(DW_TAG_POINTER_TYPE)
+                .attr(DW_AT_BYTE_SIZE, Value::Udata(ty.size() as u64))
+                .maybe_attr(DW_AT_TYPE, self.get(to).map(Value::Ref)),
+            Type::Array(of, size) => {
+                let index_type = QualType::new(Type::Primitive(Primitive::Long(true)));
+                let mut range = Die::new(DW_TAG_SUBRANGE_TYPE)
+                    .maybe_attr(DW_AT_TYPE, self.get(&index_type).map(Value::Ref));
+                if let ArraySize::Known(amount) = size {
+                    range = range.attr(DW_AT_COUNT, Value::Udata(*amount as u64));
+                }
+
+                let mut die =
+                    Die::new(DW_TAG_ARRAY_TYPE).maybe_attr(DW_AT_TYPE, self.get(of).map(Value::Ref));
+                die.children.push(range);
+                die
+            }
+            Type::Struct(s) | Type::Union(s) => {
+                let (tag, is_union) = if matches!(ty, Type::Union(_)) {
+                    (DW_TAG_UNION_TYPE, true)
+                } else {
+                    (DW_TAG_STRUCTURE_TYPE, false)
+                };
+                let mut die = Die::new(tag);
+                if let StructKind::Named(name, _) = s {
+                    die = die.attr(DW_AT_NAME, Value::String(name.clone()));
+                }
+                if !s.is_complete() {
+                    return die.attr(DW_AT_DECLARATION, Value::Flag);
+                }
+                die = die.attr(DW_AT_BYTE_SIZE, Value::Udata(ty.size() as u64));
+
+                let mut offset = 0;
+                for (member_type, name) in s.members().iter() {
+                    die.children.push(
+                        Die::new(DW_TAG_MEMBER)
+                            .attr(DW_AT_NAME, Value::String(name.unwrap_string()))
+                            .maybe_attr(DW_AT_TYPE, self.get(member_type).map(Value::Ref))
+                            .attr(DW_AT_DATA_MEMBER_LOCATION, Value::Udata(offset as u64)),
+                    );
+                    if !is_union {
+                        offset += member_type.ty.size();
+                    }
+                }
+                die
+            }
+            Type::Enum(name, constants) => {
+                let int_type = QualType::new(Type::Primitive(Primitive::Int(false)));
+                let mut die = Die::new(DW_TAG_ENUMERATION_TYPE);
+                if let Some(name) = name {
+                    die = die.attr(DW_AT_NAME, Value::String(name.clone()));
+                }
+                die = die
+                    .attr(DW_AT_BYTE_SIZE, Value::Udata(ty.size() as u64))
+                    .maybe_attr(DW_AT_TYPE, self.get(&int_type).map(Value::Ref));
+
+                for (name, value) in constants {
+                    die.children.push(
+                        Die::new(DW_TAG_ENUMERATOR)
+                            .attr(DW_AT_NAME, Value::String(name.unwrap_string()))
+                            .attr(DW_AT_CONST_VALUE, Value::Sdata(*value as i64)),
+                    );
+                }
+                die
+            }
+            Type::Function(func_type) => {
+                let mut die = Die::new(DW_TAG_SUBROUTINE_TYPE)
+                    .attr(DW_AT_PROTOTYPED, Value::Flag)
+                    .maybe_attr(DW_AT_TYPE, self.get(&func_type.return_type).map(Value::Ref));
+
+                for param in func_type.params.iter() {
+                    die.children.push(
+                        Die::new(DW_TAG_FORMAL_PARAMETER)
+                            .maybe_attr(DW_AT_TYPE, self.get(param).map(Value::Ref)),
+                    );
+                }
+                if func_type.variadic {
+                    die.children.push(Die::new(DW_TAG_UNSPECIFIED_PARAMETERS));
+                }
+                die
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Value {
+    String(String),
+    Udata(u64),
+    Sdata(i64),
+    Flag,
+    // label whose address is the value
+    Addr(String),
+    // label inside of another debug-section
+    SecOffset(String),
+    // id of another entry in the same compile-unit
+    Ref(usize),
+    // location-expression
+    Expr(Vec<u8>),
+    // location-expression with the address of a label
+    ExprAddr(String),
+}
+impl Value {
+    fn form(&self) -> u8 {
+        match self {
+            Value::String(_) => DW_FORM_STRING,
+            Value::Udata(_) => DW_FORM_UDATA,
+            Value::Sdata(_) => DW_FORM_SDATA,
+            Value::Flag => DW_FORM_FLAG_PRESENT,
+            Value::Addr(_) => DW_FORM_ADDR,
+            Value::SecOffset(_) => DW_FORM_SEC_OFFSET,
+            Value::Ref(_) => DW_FORM_REF4,
+            Value::Expr(_) | Value::ExprAddr(_) => DW_FORM_EXPRLOC,
+        }
+    }
+    fn as_string(&self) -> String {
+        match self {
+            Value::String(s) => format!("\t.string {:?}\n", s),
+            Value::Udata(n) => format!("\t.uleb128 {}\n", n),
+            Value::Sdata(n) => format!("\t.sleb128 {}\n", n),
+            Value::Flag => String::new(),
+            Value::Addr(label) => format!("\t.quad   {}\n", label),
+            Value::SecOffset(label) => format!("\t.long   {}\n", label),
+            Value::Ref(id) => format!("\t.long   Ldie{} - Ldebug_info0\n", id),
+            Value::Expr(bytes) => format!(
+                "\t.uleb128 {}\n\t.byte   {}\n",
+                bytes.len(),
+                bytes
+                    .iter()
+                    .map(|b| format!("{:#x}", b))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Value::ExprAddr(label) => {
+                format!("\t.uleb128 9\n\t.byte   {:#x}\n\t.quad   {}\n", DW_OP_ADDR, label)
+            }
+        }
+    }
+}
+
+// tag, if entry has children, attributes and their forms
+type Abbrev = (u16, bool, Vec<(u16, u8)>);
+
+// debugging information entry
+#[derive(Debug)]
+struct Die {
+    // only entries that are referenced by other entries need an id
+    id: Option<usize>,
+    tag: u16,
+    attrs: Vec<(u16, Value)>,
+    children: Vec<Die>,
+}
+impl Die {
+    fn new(tag: u16) -> Self {
+        Die {
+            id: None,
+            tag,
+            attrs: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+    fn attr(mut self, attr: u16, value: Value) -> Self {
+        self.attrs.push((attr, value));
+        self
+    }
+    fn maybe_attr(self, attr: u16, value: Option<Value>) -> Self {
+        match value {
+            Some(value) => self.attr(attr, value),
+            None => self,
+        }
+    }
+    // writes the entry into the info-section, adding its abbreviation if there isn't a matching one yet
+    fn write(&self, abbrevs: &mut Vec<Abbrev>, info: &mut String) {
+        let abbrev: Abbrev = (
+            self.tag,
+            !self.children.is_empty(),
+            self.attrs
+                .iter()
+                .map(|(attr, value)| (*attr, value.form()))
+                .collect(),
+        );
+        let code = match abbrevs.iter().position(|existing| *existing == abbrev) {
+            Some(index) => index + 1,
+            None => {
+                abbrevs.push(abbrev);
+                abbrevs.len()
+            }
+        };
+
+        if let Some(id) = self.id {
+            info.push_str(&format!("Ldie{}:\n", id));
+        }
+        info.push_str(&format!("\t.uleb128 {}\n", code));
+        for (_, value) in self.attrs.iter() {
+            info.push_str(&value.as_string());
+        }
+
+        if !self.children.is_empty() {
+            for child in self.children.iter() {
+                child.write(abbrevs, info);
+            }
+            info.push_str("\t.byte   0\n");
+        }
+    }
+}
+
+fn sleb128(mut value: i64) -> Vec<u8> {
+    let mut result = Vec::new();
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        let done = (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0);
+        result.push(if done { byte } else { byte | 0x80 });
+
+        if done {
+            return result;
+        }
+    }
+}
@@ -1,6 +1,6 @@
 //! Low-level Intermediate representation as an x86-64 assembly instruction abstraction to simplify register allocation
 
-use crate::compiler::codegen::register::*;
+use crate::compiler::codegen::{debug_info::DebugInfo, register::*};
 use crate::compiler::common::types::*;
 
 /// INFO: Needs owned register-values so that later register transformations like type-casts don't change previous references
@@ -18,16 +18,23 @@ pub enum Lir {
     Jmp(usize),
     // jump condition, label index
     JmpCond(&'static str, usize), // maybe encode conditions into enum
+    // index into table, register holding table address, table label index
+    JmpTable(Register, Register, usize),
+    // table label index, label index for every entry
+    JumpTableDeclaration(usize, Vec<usize>),
 
     Push(Register),
     Pop(Register),
 
     Call(Register),
+    // jumps to a function instead of calling it, so that it reuses the callers return-address
+    TailJmp(Register),
 
     // Function stuff
     // usize to allocate/deallocate stack-space
     FuncSetup(String, usize, bool),
-    FuncTeardown(usize),
+    // usize to deallocate stack-space, if teardown is followed by a tail-call instead of `ret`
+    FuncTeardown(usize, bool),
     SaveRegs,
     RestoreRegs,
     AddSp(usize),
@@ -61,11 +68,19 @@ pub enum Lir {
 
     // unary
     Neg(Register),
+
+    // debug-information
+    // index in line-table, filename
+    FileDeclaration(usize, String),
+    // file index, line, column of the following instructions
+    Loc(usize, i32, i32),
+    // sections describing all functions, variables and their types
+    DebugInfo(DebugInfo),
 }
 impl Lir {
     pub fn get_regs_mut(&mut self) -> (Option<&mut Register>, Option<&mut Register>) {
         match self {
-            Lir::Call(reg) | Lir::Push(reg) | Lir::Pop(reg) => (None, Some(reg)),
+            Lir::Call(reg) | Lir::TailJmp(reg) | Lir::Push(reg) | Lir::Pop(reg) => (None, Some(reg)),
             Lir::Mov(left, right)
             | Lir::Movs(left, right)
             | Lir::Movz(left, right)
@@ -77,6 +92,7 @@ impl Lir {
             | Lir::Or(left, right)
             | Lir::And(left, right)
             | Lir::Load(left, right)
+            | Lir::JmpTable(left, right, _)
             | Lir::Shift(_, left, right) => (Some(left), Some(right)),
             Lir::Neg(reg) | Lir::Not(reg) | Lir::Div(reg) => (None, Some(reg)),
             // global initializer can only have static-registers and no temporaries
@@ -114,6 +130,32 @@ impl Lir {
             Lir::LabelDefinition(label_index) => format!("L{}:", label_index),
             Lir::Jmp(label_index) => format!("\tjmp     L{}", label_index),
             Lir::JmpCond(cond, label_index) => format!("\tj{}     L{}", cond, label_index),
+            // entries are relative to the table so that it works in position-independent executables
+            Lir::JmpTable(index, table, label_index) => format!(
+                "\tleaq    L{}(%rip), {}\n\tmovslq  ({},{},4), {}\n\taddq    {}, {}\n\tjmp     *{}",
+                label_index,
+                table.base_name(),
+                table.base_name(),
+                index.base_name(),
+                index.base_name(),
+                table.base_name(),
+                index.base_name(),
+                index.base_name()
+            ),
+            Lir::JumpTableDeclaration(label_index, labels) => format!(
+                "\t{}\n\t.align 4\nL{}:\n{}\n\t.text",
+                if cfg!(target_os = "macos") {
+                    ".const"
+                } else {
+                    ".section .rodata"
+                },
+                label_index,
+                labels
+                    .iter()
+                    .map(|label| format!("\t.long   L{}-L{}", label, label_index))
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            ),
             Lir::FuncSetup(name, stack_size, is_static) => {
                 let name = maybe_prefix_underscore(&name);
                 let mut result = format!(
@@ -135,7 +177,10 @@ impl Lir {
                 }
                 result
             }
-            Lir::FuncTeardown(stack_size) => match stack_size {
+            // INFO: tail-call teardowns can be emitted before all registers of the function are spilled,
+            // so the stack-size isn't final yet and the stack-pointer is restored from the base-pointer instead
+            Lir::FuncTeardown(_, true) => String::from("\tmovq    %rbp, %rsp\n\tpopq    %rbp"),
+            Lir::FuncTeardown(stack_size, false) => match stack_size {
                 0 => String::from("\tpopq    %rbp\n\tret"),
                 n => format!(
                     "\taddq    ${},%rsp\n\tpopq    %rbp\n\tret",
@@ -157,6 +202,16 @@ impl Lir {
 
                 format!("\tcall    {}", reg_name)
             }
+            Lir::TailJmp(mut reg) => {
+                let reg_name = if reg.is_lval() {
+                    reg.set_value_kind(crate::compiler::typechecker::mir::expr::ValueKind::Rvalue);
+                    format!("*{}", reg.base_name())
+                } else {
+                    reg.base_name()
+                };
+
+                format!("\tjmp     {}", reg_name)
+            }
             Lir::Mov(from, to) => format!(
                 "\tmov{}    {}, {}",
                 to.get_type().suffix(),
@@ -253,6 +308,9 @@ impl Lir {
             Lir::Rep => "\trep     stosb".to_string(),
             Lir::Not(reg) => format!("\tnot{}    {}", reg.get_type().suffix(), reg.name()),
             Lir::Neg(reg) => format!("\tneg{}    {}", reg.get_type().suffix(), reg.name()),
+            Lir::FileDeclaration(index, filename) => format!("\t.file {} {:?}", index, filename),
+            Lir::Loc(file, line, column) => format!("\t.loc {} {} {}", file, line, column),
+            Lir::DebugInfo(debug_info) => debug_info.as_string(),
             Lir::SaveRegs | Lir::RestoreRegs => unreachable!("will be replaced in register-allocation"),
         }
     }
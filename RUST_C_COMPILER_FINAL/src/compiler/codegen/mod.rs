@@ -2,16 +2,19 @@
 //! Also builds live-intervals for every virtual-register to be filled in by real scratch-registers
 //! during [Register Allocation](register_allocation)
 
+pub mod debug_info;
 pub mod lir;
 pub mod register;
 pub mod register_allocation;
+pub mod arch;
 
-use crate::compiler::codegen::{lir::*, register::*, register_allocation::*};
+use crate::compiler::codegen::{debug_info::*, lir::*, register::*, register_allocation::*};
 use crate::compiler::common::{environment::SymbolRef, token::*, types::*};
 use crate::compiler::typechecker::mir::{decl::*, expr::*, stmt::*};
 use crate::compiler::typechecker::{align_by, create_label, ConstLabels};
 
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::rc::Rc;
 
 // converts a register into a scratch-register if it matches the pattern
@@ -68,19 +71,39 @@ pub struct Compiler {
     // case/default-labels get defined in each switch and then the
     // respective case/default-statements pop them in order of appearance
     switch_labels: Vec<usize>,
+
+    // optimization level passed with `-O`
+    opt_level: u8,
+
+    // if calls in tail-position of the current function can reuse its stack-frame
+    allow_tail_calls: bool,
+
+    // only collected when compiling with `-g`
+    debug_info: Option<DebugInfo>,
 }
 impl Compiler {
-    pub fn new(const_labels: ConstLabels) -> Self {
+    pub fn new(const_labels: ConstLabels, opt_level: u8, debug_file: Option<PathBuf>) -> Self {
+        let mut label_index = 0;
+        let debug_info = debug_file.map(|file| {
+            DebugInfo::new(
+                file,
+                (create_label(&mut label_index), create_label(&mut label_index)),
+            )
+        });
+
         Compiler {
             const_labels,
+            opt_level,
+            label_index,
+            debug_info,
             output: Vec::with_capacity(100),
             live_intervals: HashMap::with_capacity(30),
             static_labels: StaticLabels(HashMap::new()),
             interval_counter: 0,
             instr_counter: 0,
-            label_index: 0,
             jump_labels: Vec::new(),
             switch_labels: Vec::new(),
+            allow_tail_calls: false,
         }
     }
 
@@ -88,15 +111,81 @@ impl Compiler {
         mut self,
         external_decls: Vec<ExternalDeclaration>,
     ) -> (Vec<Lir>, HashMap<usize, IntervalEntry>) {
+        self.cg_debug_start();
         self.cg_const_labels();
         self.cg_external_decls(external_decls);
 
+        if let Some(debug_info) = self.debug_info.take() {
+            self.write_out(Lir::DebugInfo(debug_info));
+        }
+
         (self.output, self.live_intervals)
     }
     fn write_out(&mut self, instruction: Lir) {
         self.instr_counter += 1;
         self.output.push(instruction)
     }
+    // the compiled file is declared first so that it has index 1 in the line-table
+    fn cg_debug_start(&mut self) {
+        if let Some(debug_info) = &self.debug_info {
+            let filename = debug_info.filename().clone();
+            let text_start = debug_info.text_labels().0;
+
+            self.cg_file(&filename);
+            self.write_out(Lir::LabelDefinition(text_start));
+        }
+    }
+    // returns the line-table index of the file, declaring it if it is used for the first time
+    fn cg_file(&mut self, filename: &PathBuf) -> usize {
+        let (index, is_new) = self
+            .debug_info
+            .as_mut()
+            .expect("only called when debug-info is enabled")
+            .file_index(filename);
+
+        if is_new {
+            self.write_out(Lir::FileDeclaration(index, filename.display().to_string()));
+        }
+        index
+    }
+    // maps the following instructions to the token's position in the source-file
+    fn cg_location(&mut self, token: &Token) {
+        if self.debug_info.is_none() {
+            return;
+        }
+        let location = (self.cg_file(&token.filename), token.line_index, token.column);
+
+        self.debug_info.as_mut().unwrap().location = Some(location);
+        self.write_out(Lir::Loc(location.0, location.1, location.2));
+    }
+    fn current_location(&self) -> Option<(usize, i32, i32)> {
+        self.debug_info.as_ref().and_then(|debug_info| debug_info.location)
+    }
+    // loop-conditions are evaluated after the body but still belong to the loop-statement
+    fn restore_location(&mut self, location: Option<(usize, i32, i32)>) {
+        if let Some((file, line, column)) = location {
+            self.write_out(Lir::Loc(file, line, column));
+        }
+    }
+    // describes a variable for the debug-information if it is enabled
+    fn debug_variable(&mut self, var_symbol: &SymbolRef, location: VarLocation) -> Option<Variable> {
+        self.debug_info.as_ref()?;
+
+        let symbol = var_symbol.borrow();
+        // temporaries created by the compiler aren't named by an identifier
+        let TokenKind::Ident(name) = &symbol.token.kind else {
+            return None;
+        };
+        let file = self.cg_file(&symbol.token.filename);
+
+        Some(Variable {
+            name: name.clone(),
+            qtype: symbol.qtype.clone(),
+            decl: (file, symbol.token.line_index),
+            is_external: matches!(location, VarLocation::Label(_)) && !symbol.is_static(),
+            location,
+        })
+    }
     fn cg_const_labels(&mut self) {
         for (data, label_index) in self.const_labels.clone().into_iter() {
             self.write_out(Lir::StringDeclaration(label_index, data));
@@ -141,6 +230,10 @@ impl Compiler {
             Stmt::Case(body) | Stmt::Default(body) => self.case_statement(func, *body),
             Stmt::Goto(label) => self.goto_statement(func, label),
             Stmt::Label(name, body) => self.label_statement(func, name, *body),
+            Stmt::Located(token, body) => {
+                self.cg_location(&token);
+                self.visit_stmt(func, *body)
+            }
         }
     }
     fn goto_statement(&mut self, func: &Function, label: String) {
@@ -162,20 +255,14 @@ impl Compiler {
             .map(|_| create_label(&mut self.label_index))
             .collect();
 
-        let mut cond_reg = self.execute_expr(func, cond);
+        let cond_reg = self.execute_expr(func, cond);
+        let cond_reg = convert_reg!(self, cond_reg, Register::Literal(..));
 
         let mut default_label = None;
+        let mut cases = Vec::new();
         for (kind, label) in switch_labels.borrow().iter().zip(switch_jump_labels.clone()) {
             match kind {
-                CaseKind::Case(case_value) => {
-                    cond_reg = convert_reg!(self, cond_reg, Register::Literal(..));
-
-                    self.write_out(Lir::Cmp(
-                        Register::Literal(case_value.clone(), Type::Primitive(Primitive::Int(false))),
-                        cond_reg.clone(),
-                    ));
-                    self.write_out(Lir::JmpCond("e", label));
-                }
+                CaseKind::Case(case_value) => cases.push((case_value.clone(), label)),
                 CaseKind::Default => default_label = Some(label),
             }
         }
@@ -186,20 +273,111 @@ impl Compiler {
             .append(&mut switch_jump_labels.into_iter().rev().collect());
 
         // default label has to be jumped to at the end (even if there are cases following it) if no other cases match
-        if let Some(label) = default_label {
-            self.write_out(Lir::Jmp(label));
+        let default_label = default_label.unwrap_or(end_label);
+
+        cases.sort_by_key(|(value, _)| case_value(value));
+
+        if cases.len() < MIN_SWITCH_CASES {
+            self.cg_switch_chain(&cond_reg, &cases, default_label);
+            self.free(cond_reg);
+        } else if is_dense(&cases) {
+            self.cg_switch_table(cond_reg, &cases, default_label);
         } else {
-            self.write_out(Lir::Jmp(end_label));
+            let is_signed = !cond_reg.get_type().is_unsigned();
+            self.cg_switch_tree(&cond_reg, &cases, default_label, is_signed);
+            self.free(cond_reg);
         }
 
-        self.free(cond_reg);
-
         self.visit_stmt(func, body);
 
         self.write_out(Lir::LabelDefinition(end_label));
 
         self.jump_labels.pop();
     }
+    // compares the condition against every case one after another
+    fn cg_switch_chain(&mut self, cond_reg: &Register, cases: &[(LiteralKind, usize)], default_label: usize) {
+        for (value, label) in cases {
+            self.cg_case_cmp(value, cond_reg);
+            self.write_out(Lir::JmpCond("e", *label));
+        }
+        self.write_out(Lir::Jmp(default_label));
+    }
+    // binary search over the sorted cases so that only log(n) comparisons are needed
+    fn cg_switch_tree(
+        &mut self,
+        cond_reg: &Register,
+        cases: &[(LiteralKind, usize)],
+        default_label: usize,
+        is_signed: bool,
+    ) {
+        if cases.len() < MIN_SWITCH_CASES {
+            return self.cg_switch_chain(cond_reg, cases, default_label);
+        }
+        let middle = cases.len() / 2;
+        let (value, label) = &cases[middle];
+        let lower_label = create_label(&mut self.label_index);
+
+        self.cg_case_cmp(value, cond_reg);
+        self.write_out(Lir::JmpCond("e", *label));
+        self.write_out(Lir::JmpCond(if is_signed { "l" } else { "b" }, lower_label));
+
+        self.cg_switch_tree(cond_reg, &cases[middle + 1..], default_label, is_signed);
+
+        self.write_out(Lir::LabelDefinition(lower_label));
+        self.cg_switch_tree(cond_reg, &cases[..middle], default_label, is_signed);
+    }
+    // jumps to the case at index `cond - min` in a table of all labels between the smallest and biggest case
+    fn cg_switch_table(&mut self, cond_reg: Register, cases: &[(LiteralKind, usize)], default_label: usize) {
+        let min = case_value(&cases.first().expect("checked in is_dense()").0);
+        let max = case_value(&cases.last().expect("checked in is_dense()").0);
+        let long_type = Type::Primitive(Primitive::Long(false));
+
+        // index has to be 64bit to be used in the address calculation
+        let cond_reg = self.convert_to_rval(cond_reg);
+        let mut index_reg = if cond_reg.get_type().size() < 8 {
+            self.cg_extend(cond_reg, long_type.clone())
+        } else {
+            convert_reg!(self, cond_reg, Register::Stack(..) | Register::Label(..))
+        };
+        index_reg.set_type(long_type.clone());
+
+        if min != 0 {
+            self.write_out(Lir::Sub(
+                Register::Literal(LiteralKind::Signed(min as i64), long_type.clone()),
+                index_reg.clone(),
+            ));
+        }
+        // conditions smaller than min wrap around when compared unsigned so a single check is enough
+        self.write_out(Lir::Cmp(
+            Register::Literal(LiteralKind::Signed((max - min) as i64), long_type),
+            index_reg.clone(),
+        ));
+        self.write_out(Lir::JmpCond("a", default_label));
+
+        let mut table = vec![default_label; (max - min + 1) as usize];
+        for (value, label) in cases {
+            table[(case_value(value) - min) as usize] = *label;
+        }
+        let table_label = create_label(&mut self.label_index);
+        let table_reg = Register::Temp(TempRegister::new(
+            Type::Pointer(Box::new(QualType::new(Type::Primitive(Primitive::Int(false))))),
+            &mut self.interval_counter,
+            self.instr_counter,
+        ));
+
+        self.write_out(Lir::JmpTable(index_reg.clone(), table_reg.clone(), table_label));
+        self.write_out(Lir::JumpTableDeclaration(table_label, table));
+
+        self.free(index_reg);
+        self.free(table_reg);
+    }
+    fn cg_case_cmp(&mut self, value: &LiteralKind, cond_reg: &Register) {
+        // 64bit case-values have to be moved into a register first
+        let value_reg = self.cg_literal(value.clone(), cond_reg.get_type());
+
+        self.write_out(Lir::Cmp(value_reg.clone(), cond_reg.clone()));
+        self.free(value_reg);
+    }
     fn case_statement(&mut self, func: &mut Function, body: Stmt) {
         let label = self.switch_labels.pop().unwrap();
 
@@ -212,12 +390,14 @@ impl Compiler {
         let cond_label = create_label(&mut self.label_index);
         let end_label = create_label(&mut self.label_index);
 
+        let location = self.current_location();
         self.jump_labels.push((end_label, cond_label));
 
         self.write_out(Lir::LabelDefinition(body_label));
         self.visit_stmt(func, body);
 
         self.write_out(Lir::LabelDefinition(cond_label));
+        self.restore_location(location);
         let mut cond_reg = self.execute_expr(func, cond);
         cond_reg = self.convert_to_rval(cond_reg);
         cond_reg = convert_reg!(self, cond_reg, Register::Literal(..));
@@ -252,6 +432,7 @@ impl Compiler {
         let inc_label = create_label(&mut self.label_index);
         let end_label = create_label(&mut self.label_index);
 
+        let location = self.current_location();
         self.jump_labels.push((end_label, inc_label));
         if let Some(init) = init {
             self.visit_stmt(func, *init);
@@ -262,6 +443,7 @@ impl Compiler {
         self.visit_stmt(func, body);
 
         self.write_out(Lir::LabelDefinition(inc_label));
+        self.restore_location(location);
 
         if let Some(inc) = inc {
             let reg = self.execute_expr(func, inc);
@@ -295,6 +477,7 @@ impl Compiler {
         let cond_label = create_label(&mut self.label_index);
         let end_label = create_label(&mut self.label_index);
 
+        let location = self.current_location();
         self.jump_labels.push((end_label, cond_label));
 
         self.write_out(Lir::Jmp(cond_label));
@@ -303,6 +486,7 @@ impl Compiler {
         self.visit_stmt(func, body);
 
         self.write_out(Lir::LabelDefinition(cond_label));
+        self.restore_location(location);
 
         let mut cond_reg = self.execute_expr(func, cond);
         cond_reg = self.convert_to_rval(cond_reg);
@@ -357,6 +541,17 @@ impl Compiler {
         let function_epilogue = func.epilogue_index;
 
         match value {
+            Some(expr) if self.allow_tail_calls && is_tail_call(&expr) => {
+                let (caller, args) = match expr.kind {
+                    ExprKind::Call { caller, args } => (caller, args),
+                    ExprKind::Cast { expr, .. } => match expr.kind {
+                        ExprKind::Call { caller, args } => (caller, args),
+                        _ => unreachable!("checked in is_tail_call()"),
+                    },
+                    _ => unreachable!("checked in is_tail_call()"),
+                };
+                self.cg_tail_call(func, *caller, args);
+            }
             Some(expr) => {
                 let return_value = self.execute_expr(func, expr);
                 self.write_out(Lir::Mov(
@@ -382,6 +577,9 @@ impl Compiler {
                     name
                 };
 
+                if let Some(var) = self.debug_variable(&declarator.entry, VarLocation::Label(label_name.clone())) {
+                    self.debug_info.as_mut().unwrap().add_global(var);
+                }
                 self.declare_global_var(label_name, ty, declarator.entry, declarator.init)
             }
             // if variable is declared but its initialization is after the first use then
@@ -511,6 +709,13 @@ impl Compiler {
                             var_symbol.is_extern(),
                         )));
 
+                    if var_symbol.is_static() && declarator.name == var_symbol.token {
+                        if let Some(var) =
+                            self.debug_variable(&declarator.entry, VarLocation::Label(label_name.clone()))
+                        {
+                            self.debug_info.as_mut().unwrap().add_local(var);
+                        }
+                    }
                     func.static_declarations.push((label_name, declarator));
                 }
                 None | Some(StorageClass::Auto | StorageClass::Register)
@@ -538,8 +743,11 @@ impl Compiler {
         let ty = var_symbol.borrow().qtype.ty.clone();
         let size = align(ty.size(), &ty);
 
-        let reg = Register::Stack(StackRegister::new(&mut func.current_bp_offset, ty));
-        var_symbol.borrow_mut().set_reg(reg);
+        let stack_reg = StackRegister::new(&mut func.current_bp_offset, ty);
+        if let Some(var) = self.debug_variable(&var_symbol, VarLocation::Stack(stack_reg.offset())) {
+            self.debug_info.as_mut().unwrap().add_local(var);
+        }
+        var_symbol.borrow_mut().set_reg(Register::Stack(stack_reg));
 
         if let Some(init) = init {
             match init {
@@ -631,16 +839,24 @@ impl Compiler {
 
         func.epilogue_index = create_label(&mut self.label_index);
 
+        // once the stack-frame is torn down pointers to locals would dangle in the callee
+        self.allow_tail_calls = self.opt_level >= 1 && !takes_local_address(&func.params, &stmts);
+
         // create a label for all goto-labels inside a function
         for value in func.labels.values_mut() {
             *value = create_label(&mut self.label_index);
         }
 
         // generate function code
-        self.cg_func_preamble(&mut func, func_symbol);
+        self.cg_location(&func_symbol.borrow().token.clone());
+        self.cg_func_preamble(&mut func, Rc::clone(&func_symbol));
         self.cg_stmts(&mut func, stmts);
         self.cg_func_postamble(&func);
 
+        if self.debug_info.is_some() {
+            self.debug_function(&func, func_symbol);
+        }
+
         // declare all statically linked declarations that are declared inside of function-body
         for (label_name, declarator) in func.static_declarations {
             let var_symbol = declarator.entry.borrow().clone();
@@ -655,6 +871,21 @@ impl Compiler {
             }
         }
     }
+    // marks the end of the function and adds it together with all its variables to the debug-information
+    fn debug_function(&mut self, func: &Function, func_symbol: SymbolRef) {
+        let end_label = create_label(&mut self.label_index);
+        self.write_out(Lir::LabelDefinition(end_label));
+
+        let symbol = func_symbol.borrow();
+        let file = self.cg_file(&symbol.token.filename);
+
+        self.debug_info.as_mut().unwrap().add_function(
+            func,
+            (file, symbol.token.line_index),
+            !symbol.is_static() && (symbol.is_extern() || !func.is_inline),
+            end_label,
+        );
+    }
     fn cg_func_preamble(&mut self, func: &mut Function, func_symbol: SymbolRef) {
         self.write_out(Lir::FuncSetup(
             func.name.clone(),
@@ -690,7 +921,7 @@ impl Compiler {
     fn cg_func_postamble(&mut self, func: &Function) {
         self.write_out(Lir::LabelDefinition(func.epilogue_index));
 
-        self.write_out(Lir::FuncTeardown(func.stack_size))
+        self.write_out(Lir::FuncTeardown(func.stack_size, false))
     }
 
     pub fn block(&mut self, func: &mut Function, statements: Vec<Stmt>) {
@@ -1001,8 +1232,11 @@ impl Compiler {
         value_reg
     }
     fn cg_cast_up(&mut self, func: &mut Function, expr: Expr, new_type: Type) -> Register {
-        let mut value_reg = self.execute_expr(func, expr);
+        let value_reg = self.execute_expr(func, expr);
 
+        self.cg_extend(value_reg, new_type)
+    }
+    fn cg_extend(&mut self, mut value_reg: Register, new_type: Type) -> Register {
         if matches!(
             value_reg,
             Register::Temp(..) | Register::Stack(..) | Register::Label(..)
@@ -1129,6 +1363,34 @@ impl Compiler {
             Register::Void
         }
     }
+    // arguments are already checked to fit into the argument-registers so nothing has to be pushed
+    // and the callee can return directly to the caller of the current function
+    fn cg_tail_call(&mut self, func: &mut Function, caller: Expr, args: Vec<Expr>) {
+        let mut arg_regs = Vec::new();
+
+        for (i, expr) in args.into_iter().enumerate().rev() {
+            let reg = self.execute_expr(func, expr);
+            let arg = Register::Arg(ArgRegister::new(
+                i,
+                reg.get_type(),
+                &mut self.interval_counter,
+                self.instr_counter,
+            ));
+            self.write_out(Lir::Mov(reg.clone(), arg.clone()));
+
+            arg_regs.push(arg);
+            self.free(reg);
+        }
+
+        let caller = self.execute_expr(func, caller);
+        self.write_out(Lir::FuncTeardown(func.stack_size, true));
+        self.write_out(Lir::TailJmp(caller.clone()));
+        self.free(caller);
+
+        for reg in arg_regs {
+            self.free(reg);
+        }
+    }
     fn remove_spilled_args(&mut self, args_len: usize) {
         let spilled_args = args_len as isize - ARG_REGS.len() as isize;
         let alignment_offset = if spilled_args % 2 != 0 { 8 } else { 0 };
@@ -1541,6 +1803,173 @@ impl Compiler {
     }
 }
 
+// switches with less cases are lowered to a chain of compare-and-jumps
+const MIN_SWITCH_CASES: usize = 4;
+
+// biggest jump-table that is generated for a switch
+const MAX_JUMP_TABLE_LEN: i128 = 4096;
+
+fn case_value(value: &LiteralKind) -> i128 {
+    match value {
+        LiteralKind::Signed(n) => *n as i128,
+        LiteralKind::Unsigned(n) => *n as i128,
+    }
+}
+// a switch gets a jump-table if at least 40% of the table-entries are cases and the case-values
+// can be used as immediates
+fn is_dense(cases: &[(LiteralKind, usize)]) -> bool {
+    let (Some((min, _)), Some((max, _))) = (cases.first(), cases.last()) else {
+        return false;
+    };
+    let (min, max) = (case_value(min), case_value(max));
+    let len = max - min + 1;
+
+    i32::try_from(min).is_ok()
+        && len <= MAX_JUMP_TABLE_LEN
+        && cases.len() as i128 * 10 >= len * 4
+}
+
+// a returned call can be turned into a jump if its result doesn't have to be extended afterwards
+// and all of its arguments are passed in registers
+fn is_tail_call(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Call { args, .. } => args.len() <= ARG_REGS.len(),
+        ExprKind::Cast {
+            expr,
+            direction: CastDirection::Down | CastDirection::Equal,
+            ..
+        } => matches!(&expr.kind, ExprKind::Call { args, .. } if args.len() <= ARG_REGS.len()),
+        _ => false,
+    }
+}
+
+// checks if the address of a parameter or local variable can escape the function
+fn takes_local_address(params: &[SymbolRef], stmts: &[Stmt]) -> bool {
+    let mut locals: Vec<SymbolRef> = params.to_vec();
+    stmts.iter().for_each(|stmt| collect_locals(stmt, &mut locals));
+
+    stmts.iter().any(|stmt| stmt_takes_address(stmt, &locals))
+}
+fn collect_locals(stmt: &Stmt, locals: &mut Vec<SymbolRef>) {
+    match stmt {
+        Stmt::Declaration(decls) => {
+            for declarator in decls {
+                let symbol = declarator.entry.borrow();
+                if matches!(
+                    symbol.storage_class,
+                    None | Some(StorageClass::Auto | StorageClass::Register)
+                ) && !symbol.qtype.ty.is_func()
+                {
+                    locals.push(Rc::clone(&declarator.entry));
+                }
+            }
+        }
+        Stmt::Block(stmts) => stmts.iter().for_each(|stmt| collect_locals(stmt, locals)),
+        Stmt::If(_, then_branch, else_branch) => {
+            collect_locals(then_branch, locals);
+            if let Some(else_branch) = else_branch {
+                collect_locals(else_branch, locals);
+            }
+        }
+        Stmt::For(init, _, _, body) => {
+            if let Some(init) = init {
+                collect_locals(init, locals);
+            }
+            collect_locals(body, locals);
+        }
+        Stmt::While(_, body)
+        | Stmt::Do(body, _)
+        | Stmt::Switch(_, body)
+        | Stmt::Case(body)
+        | Stmt::Default(body)
+        | Stmt::Label(_, body)
+        | Stmt::Located(_, body) => collect_locals(body, locals),
+        Stmt::Expr(_) | Stmt::Return(_) | Stmt::Break | Stmt::Continue | Stmt::Goto(_) => (),
+    }
+}
+fn stmt_takes_address(stmt: &Stmt, locals: &[SymbolRef]) -> bool {
+    let any_expr = |exprs: &[&Expr]| exprs.iter().any(|expr| expr_takes_address(expr, locals));
+
+    match stmt {
+        Stmt::Declaration(decls) => decls.iter().any(|declarator| match &declarator.init {
+            Some(Init::Scalar(expr)) => any_expr(&[expr]),
+            Some(Init::Aggr(list)) => list.iter().any(|(expr, _)| any_expr(&[expr])),
+            None => false,
+        }),
+        Stmt::Expr(expr) | Stmt::Return(Some(expr)) => any_expr(&[expr]),
+        Stmt::Block(stmts) => stmts.iter().any(|stmt| stmt_takes_address(stmt, locals)),
+        Stmt::If(cond, then_branch, else_branch) => {
+            any_expr(&[cond])
+                || stmt_takes_address(then_branch, locals)
+                || else_branch
+                    .as_ref()
+                    .is_some_and(|else_branch| stmt_takes_address(else_branch, locals))
+        }
+        Stmt::While(cond, body) | Stmt::Do(body, cond) | Stmt::Switch(cond, body) => {
+            any_expr(&[cond]) || stmt_takes_address(body, locals)
+        }
+        Stmt::For(init, cond, inc, body) => {
+            init.as_ref().is_some_and(|init| stmt_takes_address(init, locals))
+                || cond.as_ref().is_some_and(|cond| any_expr(&[cond]))
+                || inc.as_ref().is_some_and(|inc| any_expr(&[inc]))
+                || stmt_takes_address(body, locals)
+        }
+        Stmt::Case(body) | Stmt::Default(body) | Stmt::Label(_, body) | Stmt::Located(_, body) => {
+            stmt_takes_address(body, locals)
+        }
+        Stmt::Return(None) | Stmt::Break | Stmt::Continue | Stmt::Goto(_) => false,
+    }
+}
+fn expr_takes_address(expr: &Expr, locals: &[SymbolRef]) -> bool {
+    match &expr.kind {
+        ExprKind::Unary { token, right } if token.kind == TokenKind::Amp => {
+            refers_to_local(right, locals) || expr_takes_address(right, locals)
+        }
+        // `A op= B` is desugared into `tmp = &A, *tmp = *tmp op B` where the address of A only
+        // lives in the local tmp-variable
+        ExprKind::CompoundAssign { expr, .. } => match &expr.kind {
+            ExprKind::Comma { left, right } => match &left.kind {
+                ExprKind::Assign { r_expr, .. } => match &r_expr.kind {
+                    ExprKind::Unary { right: l_expr, .. } => {
+                        expr_takes_address(l_expr, locals) || expr_takes_address(right, locals)
+                    }
+                    _ => unreachable!("compound-assign always takes address of left side"),
+                },
+                _ => unreachable!("compound-assign always starts with assign to tmp"),
+            },
+            _ => unreachable!("compound-assign always desugared into comma"),
+        },
+        ExprKind::Binary { left, right, .. }
+        | ExprKind::Logical { left, right, .. }
+        | ExprKind::Comparison { left, right, .. }
+        | ExprKind::Comma { left, right }
+        | ExprKind::Assign { l_expr: left, r_expr: right } => {
+            expr_takes_address(left, locals) || expr_takes_address(right, locals)
+        }
+        ExprKind::Unary { right: expr, .. }
+        | ExprKind::Cast { expr, .. }
+        | ExprKind::Scale { expr, .. }
+        | ExprKind::MemberAccess { expr, .. } => expr_takes_address(expr, locals),
+        ExprKind::Call { caller, args } => {
+            expr_takes_address(caller, locals) || args.iter().any(|arg| expr_takes_address(arg, locals))
+        }
+        ExprKind::Ternary { cond, true_expr, false_expr } => {
+            expr_takes_address(cond, locals)
+                || expr_takes_address(true_expr, locals)
+                || expr_takes_address(false_expr, locals)
+        }
+        ExprKind::String(_) | ExprKind::Literal(_) | ExprKind::Ident(_) | ExprKind::Nop => false,
+    }
+}
+// if the lvalue is located in the stack-frame of the current function
+fn refers_to_local(expr: &Expr, locals: &[SymbolRef]) -> bool {
+    match &expr.kind {
+        ExprKind::Ident(symbol) => locals.iter().any(|local| Rc::ptr_eq(local, symbol)),
+        ExprKind::MemberAccess { expr, .. } | ExprKind::Cast { expr, .. } => refers_to_local(expr, locals),
+        _ => false,
+    }
+}
+
 pub fn align(offset: usize, ty: &Type) -> usize {
     let size = match ty {
         Type::Array(of, _) => of.ty.size(),
@@ -1548,3 +1977,261 @@ pub fn align(offset: usize, ty: &Type) -> usize {
     };
     align_by(offset, size)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parser::tests::setup;
+    use crate::compiler::typechecker::TypeChecker;
+
+    fn setup_asm(input: &str, opt_level: u8) -> Vec<String> {
+        let parse_tree = setup(input).parse().unwrap();
+        let (mir, const_labels) = TypeChecker::new().check(parse_tree).unwrap();
+        let (lir, live_intervals) = Compiler::new(const_labels, opt_level, None).translate(mir);
+
+        RegisterAllocation::new(live_intervals)
+            .generate(lir)
+            .into_iter()
+            .map(|instr| instr.as_string())
+            .collect()
+    }
+
+    #[test]
+    fn tail_call() {
+        let input = "
+long sum(long n, long acc) {
+    if (n == 0) return acc;
+    return sum(n - 1, acc + n);
+}";
+        let actual = setup_asm(input, 1);
+
+        assert!(actual.contains(&"\tjmp     sum".to_string()));
+        assert!(!actual.iter().any(|instr| instr.contains("call")));
+
+        let actual = setup_asm(input, 0);
+        assert!(actual.contains(&"\tcall    sum".to_string()));
+    }
+
+    #[test]
+    fn no_tail_call_with_local_address() {
+        let actual = setup_asm(
+            "
+int foo(int *p);
+int bar(int n) {
+    int arr[2];
+    return foo(arr);
+}
+int baz(int n) {
+    return foo(&n);
+}",
+            1,
+        );
+
+        assert!(!actual.iter().any(|instr| instr.contains("jmp     *")));
+    }
+
+    #[test]
+    fn no_tail_call_with_pushed_args_or_extension() {
+        let actual = setup_asm(
+            "
+int seven(int a, int b, int c, int d, int e, int f, int g);
+int foo() { return seven(1, 2, 3, 4, 5, 6, 7); }
+char small(int n);
+int bar(int n) { return small(n); }
+int baz(int n) { n += 1; return bar(n); }",
+            1,
+        );
+
+        assert!(!actual.iter().any(|instr| instr.contains("jmp     *")));
+        assert!(actual.contains(&"\tjmp     bar".to_string()));
+    }
+
+    #[test]
+    fn switch_lowering() {
+        let actual = setup_asm(
+            "
+int dense(int n) {
+    switch (n) { case 1: case 2: case 3: case 5: return 1; }
+    return 0;
+}",
+            0,
+        );
+        assert!(actual.iter().any(|instr| instr.contains("jmp     *%")));
+        assert!(actual.iter().any(|instr| instr.contains(".long   L")));
+
+        let actual = setup_asm(
+            "
+int sparse(int n) {
+    switch (n) { case 1: case 20: case 300: case 4000: case 50000: return 1; }
+    return 0;
+}",
+            0,
+        );
+        assert!(!actual.iter().any(|instr| instr.contains(".long")));
+        assert_eq!(actual.iter().filter(|instr| instr.starts_with("\tjl")).count(), 1);
+
+        let actual = setup_asm(
+            "
+int tiny(unsigned int n) {
+    switch (n) { case 1: case 2: return 1; }
+    return 0;
+}",
+            0,
+        );
+        assert!(!actual.iter().any(|instr| instr.contains(".long")));
+        assert_eq!(actual.iter().filter(|instr| instr.starts_with("\tje")).count(), 2);
+    }
+
+    fn cases(values: &[i64]) -> Vec<(LiteralKind, usize)> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(label, value)| (LiteralKind::Signed(*value), label))
+            .collect()
+    }
+
+    fn has_tool(name: &str) -> bool {
+        std::process::Command::new(name).arg("--version").output().is_ok()
+    }
+
+    #[test]
+    fn switch_density() {
+        assert!(is_dense(&cases(&[1, 2, 3, 4])));
+        assert!(is_dense(&cases(&[-2, -1, 0, 1, 2])));
+        // exactly 40% of the table-entries are cases
+        assert!(is_dense(&cases(&[0, 1, 2, 9])));
+        assert!(!is_dense(&cases(&[0, 1, 2, 10])));
+        assert!(!is_dense(&cases(&[1, 20, 300, 4000, 50000])));
+
+        // tables are capped in length and need the case-values as immediates
+        let long_table: Vec<i64> = (0..2000).map(|n| n * 2).chain([MAX_JUMP_TABLE_LEN as i64 * 2]).collect();
+        assert!(!is_dense(&cases(&long_table)));
+        let big = i32::MAX as i64;
+        assert!(!is_dense(&cases(&[big + 1, big + 2, big + 3, big + 4])));
+        assert!(!is_dense(&[]));
+    }
+
+    #[test]
+    fn switch_strategy_selection() {
+        let switch_fn = |values: &[i64]| {
+            let cases: String = values.iter().map(|value| format!("case {}: return {};", value, value)).collect();
+            format!("int f(int n) {{ switch (n) {{ {} default: return -100; }} }}", cases)
+        };
+        let count = |asm: &[String], prefix: &str| {
+            asm.iter().flat_map(|instr| instr.lines()).filter(|line| line.starts_with(prefix)).count()
+        };
+
+        // dense: a single bounds-check and an indirect jump through the table
+        let actual = setup_asm(&switch_fn(&[-2, -1, 0, 1, 2, 3]), 0);
+        assert_eq!(count(&actual, "\tjmp     *%"), 1);
+        assert_eq!(count(&actual, "\tja"), 1);
+        assert_eq!(count(&actual, "\tje"), 0);
+
+        // sparse: a binary decision tree instead of one compare per case
+        // of 9 cases; both halves are split again before they get small enough for a chain
+        let actual = setup_asm(&switch_fn(&[-5000, -40, 3, 700, 9000, 123456, 7000000, 8000000, 9000000]), 0);
+        assert_eq!(count(&actual, "\tjmp     *%"), 0);
+        assert!(!actual.iter().any(|instr| instr.contains(".long")));
+        assert_eq!(count(&actual, "\tjl"), 3);
+        assert_eq!(count(&actual, "\tje"), 9);
+
+        // tiny: a compare-chain even though the cases are dense
+        let actual = setup_asm(&switch_fn(&[1, 2, 3]), 0);
+        assert_eq!(count(&actual, "\tjmp     *%"), 0);
+        assert_eq!(count(&actual, "\tjl"), 0);
+        assert_eq!(count(&actual, "\tje"), 3);
+    }
+
+    // only runs where a native toolchain is installed
+    #[test]
+    fn switch_default_fallthrough() {
+        if !has_tool("cc") {
+            return;
+        }
+        let asm = setup_asm(
+            "
+int printf(const char *fmt, ...);
+int dense(int n) {
+    switch (n) { case -2: return 1; case -1: return 2; case 0: return 3; case 1: return 4; case 3: return 5; default: return 0; }
+}
+int dense_offset(unsigned int n) {
+    switch (n) { case 10: return 1; case 11: return 2; case 12: return 3; case 13: return 4; }
+    return 0;
+}
+int sparse(long n) {
+    switch (n) { case -5000: return 1; case 3: return 2; case 700: return 3; case 9000: return 4; case 123456: return 5; default: return 0; }
+}
+int tiny(int n) {
+    switch (n) { case 1: return 1; case 2: return 2; default: return 0; }
+}
+int main() {
+    printf(\"%d %d %d %d %d %d\\n\", dense(-3), dense(-2), dense(2), dense(3), dense(4), dense(-2147483647 - 1));
+    printf(\"%d %d %d %d\\n\", dense_offset(9), dense_offset(10), dense_offset(13), dense_offset(4294967295u));
+    printf(\"%d %d %d %d %d\\n\", sparse(-5001), sparse(-5000), sparse(4), sparse(123456), sparse(9999999999));
+    printf(\"%d %d %d\\n\", tiny(-1), tiny(2), tiny(3));
+    return 0;
+}",
+            0,
+        )
+        .join("\n");
+
+        let dir = std::env::temp_dir();
+        let asm_file = dir.join("wrecc_switch_test.s");
+        let exe_file = dir.join("wrecc_switch_test");
+        std::fs::write(&asm_file, asm + "\n").unwrap();
+
+        let status = std::process::Command::new("cc")
+            .arg("-Wl,-z,noexecstack")
+            .arg(&asm_file)
+            .arg("-o")
+            .arg(&exe_file)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let output = std::process::Command::new(&exe_file).output().unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "0 1 0 5 0 0\n0 1 4 0\n0 1 0 5 0\n0 2 0\n"
+        );
+    }
+
+    #[test]
+    fn debug_info() {
+        let input = "
+struct point { int x; struct point *next; };
+int global;
+int foo(int n) {
+    struct point p;
+    while (n > 0)
+        n--;
+    return n;
+}";
+        let parse_tree = setup(input).parse().unwrap();
+        let (mir, const_labels) = TypeChecker::new().check(parse_tree).unwrap();
+        let (lir, live_intervals) = Compiler::new(const_labels, 0, Some(PathBuf::new())).translate(mir);
+        let actual: Vec<String> = RegisterAllocation::new(live_intervals)
+            .generate(lir)
+            .into_iter()
+            .map(|instr| instr.as_string())
+            .collect();
+
+        assert!(actual.contains(&"\t.file 1 \"\"".to_string()));
+        assert!(actual.contains(&"\t.loc 1 4 5".to_string()));
+        // loop-condition is mapped back to the while-statement
+        assert_eq!(actual.iter().filter(|instr| *instr == "\t.loc 1 6 11").count(), 2);
+
+        let debug_info = actual.last().unwrap();
+        for expected in [
+            ".string \"foo\"",
+            ".string \"global\"",
+            ".string \"point\"",
+            ".string \"next\"",
+            // `n` is stored at -4(%rbp) and `p` is aligned to -24(%rbp)
+            ".byte   0x91, 0x7c",
+            ".byte   0x91, 0x68",
+        ] {
+            assert!(debug_info.contains(expected), "missing {}", expected);
+        }
+    }
+}
@@ -214,12 +214,16 @@ impl StackRegister {
             ty: Type::Primitive(Primitive::Long(true)),
         }
     }
-    pub fn name(&self) -> String {
+    // offset relative to the base-pointer
+    pub fn offset(&self) -> isize {
         match self.kind {
-            StackKind::Signed => format!("-{}(%rbp)", self.bp_offset),
-            StackKind::Unsigned => format!("{}(%rbp)", self.bp_offset),
+            StackKind::Signed => -(self.bp_offset as isize),
+            StackKind::Unsigned => self.bp_offset as isize,
         }
     }
+    pub fn name(&self) -> String {
+        format!("{}(%rbp)", self.offset())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -266,6 +270,9 @@ impl TempRegister {
             (Some(TempKind::Scratch(reg)), ValueKind::Rvalue) => reg.name(&self.ty),
             (Some(TempKind::Scratch(..)), ValueKind::Lvalue) => self.base_name(),
             (Some(TempKind::Spilled(reg)), ..) => reg.name(),
+            // only printed like this when dumping the LIR before register allocation
+            (None, ValueKind::Rvalue) => format!("%t{}", self.id),
+            (None, ValueKind::Lvalue) => format!("(%t{})", self.id),
             _ => unreachable!("register should always be filled by allocator"),
         }
     }
@@ -277,6 +284,8 @@ impl TempRegister {
                 format!("({})", reg.base_name())
             }
             (Some(TempKind::Spilled(reg)), ..) => reg.name(),
+            (None, ValueKind::Rvalue) => format!("%t{}", self.id),
+            (None, ValueKind::Lvalue) => format!("(%t{})", self.id),
             _ => unreachable!(),
         }
     }
@@ -88,7 +88,7 @@ impl RegisterAllocation {
 
                     result.push(instr);
                 }
-                Lir::FuncTeardown(stack_size) => {
+                Lir::FuncTeardown(stack_size, false) => {
                     // when function is done update stack-size if registers where spilled to stack
                     if *stack_size != self.spill_bp_offset {
                         *stack_size = self.spill_bp_offset;
@@ -460,7 +460,7 @@ mod tests {
     impl Lir {
         fn get_regs(&self) -> (Option<&Register>, Option<&Register>) {
             match self {
-                Lir::Call(reg) | Lir::Push(reg) | Lir::Pop(reg) => (None, Some(reg)),
+                Lir::Call(reg) | Lir::TailJmp(reg) | Lir::Push(reg) | Lir::Pop(reg) => (None, Some(reg)),
                 Lir::Mov(left, right)
                 | Lir::Movs(left, right)
                 | Lir::Movz(left, right)
@@ -472,6 +472,7 @@ mod tests {
                 | Lir::Or(left, right)
                 | Lir::And(left, right)
                 | Lir::Load(left, right)
+                | Lir::JmpTable(left, right, _)
                 | Lir::Shift(_, left, right) => (Some(left), Some(right)),
                 Lir::Neg(reg) | Lir::Not(reg) | Lir::Div(reg) => (None, Some(reg)),
                 Lir::GlobalInit(..) => (None, None),
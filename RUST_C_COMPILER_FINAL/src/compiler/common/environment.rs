@@ -194,18 +194,67 @@ impl<T> NameSpace<T> {
     }
 }
 
+/// An identifier in the source that was declared as or resolved to an entry in the symbol-table
+#[derive(Clone, Debug)]
+pub struct IndexEntry<T> {
+    pub token: Token,
+    pub target: Rc<RefCell<T>>,
+    pub is_declaration: bool,
+    pub is_global: bool,
+}
+
+/// All identifiers the environment saw, in the order they appeared in the source.<br>
+/// Only recorded when requested since the compiler itself doesn't need it, but editor-tooling
+/// uses it to map every identifier back to its declaration.
+#[derive(Clone, Debug, Default)]
+pub struct SymbolIndex {
+    pub symbols: Vec<IndexEntry<Symbol>>,
+    pub tags: Vec<IndexEntry<Tags>>,
+}
+
 /// The environment is made up of two seperate namespaces, one for storing symbols and another for
 /// storing tags when the user declares a type
 #[derive(Debug)]
 pub struct Environment {
     symbols: NameSpace<Symbol>,
     tags: NameSpace<Tags>,
+
+    // lookups only take `&self` so the index has to be mutable through a shared reference
+    index: Option<RefCell<SymbolIndex>>,
 }
 impl Environment {
     pub fn new() -> Self {
         Environment {
             symbols: NameSpace::new(),
             tags: NameSpace::new(),
+            index: None,
+        }
+    }
+    /// Starts recording every declared and resolved identifier into a [SymbolIndex]
+    pub fn record_index(&mut self) {
+        self.index = Some(RefCell::new(SymbolIndex::default()));
+    }
+    pub fn take_index(&mut self) -> Option<SymbolIndex> {
+        self.index.take().map(RefCell::into_inner)
+    }
+    fn index_symbol(&self, token: &Token, target: &SymbolRef, is_declaration: bool, is_global: bool) {
+        if let Some(index) = &self.index {
+            index.borrow_mut().symbols.push(IndexEntry {
+                token: token.clone(),
+                target: Rc::clone(target),
+                is_declaration,
+                is_global,
+            });
+        }
+    }
+    fn index_tag(&self, token: &Token, target: &Rc<RefCell<Tags>>, is_declaration: bool) {
+        if let Some(index) = &self.index {
+            index.borrow_mut().tags.push(IndexEntry {
+                token: token.clone(),
+                target: Rc::clone(target),
+                is_declaration,
+                is_global: self.is_global(),
+            });
         }
     }
     pub fn is_global(&self) -> bool {
@@ -220,25 +269,30 @@ impl Environment {
         self.tags.exit();
     }
     pub fn declare_symbol(&mut self, var_name: &Token, symbol: Symbol) -> Result<SymbolRef, Error> {
-        if let Some(existing_symbol) = self.symbols.get_current(&var_name.unwrap_string()) {
-            return self.check_redef(var_name, symbol, existing_symbol);
-        }
+        let entry = if let Some(existing_symbol) = self.symbols.get_current(&var_name.unwrap_string()) {
+            self.check_redef(var_name, symbol, existing_symbol)?
+        } else {
+            self.symbols.declare(var_name.unwrap_string(), symbol)
+        };
+        self.index_symbol(var_name, &entry, true, self.is_global());
 
-        Ok(self.symbols.declare(var_name.unwrap_string(), symbol))
+        Ok(entry)
     }
     // only used for functions since they have to be inserted before params but need params to be
     // already parsed
     pub fn declare_global(&mut self, var_name: &Token, symbol: Symbol) -> Result<SymbolRef, Error> {
         let global_scope = self.symbols.elems.get_mut(0).expect("always have a global scope");
 
-        if let Some(existing_symbol) = global_scope.get(&var_name.unwrap_string()).map(Rc::clone) {
-            return self.check_redef(var_name, symbol, existing_symbol);
-        }
-
-        let func = Rc::new(RefCell::new(symbol));
-        global_scope.insert(var_name.unwrap_string(), Rc::clone(&func));
+        let func = if let Some(existing_symbol) = global_scope.get(&var_name.unwrap_string()).map(Rc::clone) {
+            self.check_redef(var_name, symbol, existing_symbol)?
+        } else {
+            let func = Rc::new(RefCell::new(symbol));
+            global_scope.insert(var_name.unwrap_string(), Rc::clone(&func));
+            func
+        };
+        self.index_symbol(var_name, &func, true, true);
 
-        Ok(Rc::clone(&func))
+        Ok(func)
     }
 
     fn check_storage_class_mismatch(
@@ -349,7 +403,7 @@ impl Environment {
 
     pub fn declare_type(&mut self, var_name: &Token, tag: Tags) -> Result<Rc<RefCell<Tags>>, Error> {
         let name = var_name.unwrap_string();
-        match self.tags.get_current(&name) {
+        let tag = match self.tags.get_current(&name) {
             Some(existing_tag)
                 if existing_tag.borrow().is_complete() || existing_tag.borrow().in_definition() =>
             {
@@ -357,30 +411,37 @@ impl Environment {
             }
             Some(existing_tag) if matches!(*existing_tag.borrow(), Tags::Aggregate(_)) => {
                 // if tag is being defined then set other_tag to being defined
-                let Tags::Aggregate(other_s) = &*existing_tag.borrow_mut() else {
-                    unreachable!("just checked if match aggregate")
-                };
-                if let Tags::Aggregate(s) = tag {
+                if let (Tags::Aggregate(other_s), Tags::Aggregate(s)) = (&*existing_tag.borrow(), tag) {
                     if s.in_definition() {
                         other_s.being_defined();
                     }
                 }
-                return Ok(Rc::clone(&existing_tag));
+                existing_tag
             }
-            Some(existing_tag) => return Ok(existing_tag),
-            _ => (),
-        }
-        Ok(self.tags.declare(name, tag))
+            Some(existing_tag) => existing_tag,
+            None => self.tags.declare(name, tag),
+        };
+        self.index_tag(var_name, &tag, true);
+
+        Ok(tag)
     }
     pub fn get_symbol(&self, var_name: &Token) -> Result<SymbolRef, Error> {
-        self.symbols
+        let symbol = self
+            .symbols
             .get(var_name.unwrap_string())
-            .ok_or_else(|| Error::new(var_name, ErrorKind::UndeclaredSymbol(var_name.unwrap_string())))
+            .ok_or_else(|| Error::new(var_name, ErrorKind::UndeclaredSymbol(var_name.unwrap_string())))?;
+        self.index_symbol(var_name, &symbol, false, self.is_global());
+
+        Ok(symbol)
     }
     pub fn get_type(&self, var_name: &Token) -> Result<Rc<RefCell<Tags>>, Error> {
-        self.tags
+        let tag = self
+            .tags
             .get(var_name.unwrap_string())
-            .ok_or_else(|| Error::new(var_name, ErrorKind::UndeclaredType(var_name.unwrap_string())))
+            .ok_or_else(|| Error::new(var_name, ErrorKind::UndeclaredType(var_name.unwrap_string())))?;
+        self.index_tag(var_name, &tag, false);
+
+        Ok(tag)
     }
 
     pub fn check_remaining_tentatives(&self) -> Result<(), Vec<Error>> {
@@ -39,6 +39,20 @@ impl WreccError {
         }
     }
 }
+/// Prints the warnings found by the [analyzer](crate::compiler::analyzer) followed by their count
+pub fn print_warnings(warnings: &[Error], no_color: bool) {
+    if warnings.is_empty() {
+        return;
+    }
+    for w in warnings {
+        w.print_warning(no_color);
+    }
+    eprintln!(
+        "{} warning{} generated.",
+        warnings.len(),
+        if warnings.len() > 1 { "s" } else { "" }
+    );
+}
 impl From<Vec<Error>> for WreccError {
     fn from(compiler_errors: Vec<Error>) -> WreccError {
         WreccError::Comp(compiler_errors)
@@ -143,6 +157,37 @@ pub enum ErrorKind {
     UndeclaredSymbol(String),
     StorageClassMismatch(String, &'static str, &'static str),
 
+    // interpreter errors
+    OutOfBounds(i64, usize),
+    PointerOutOfBounds(i64, usize),
+    InvalidAccess(u64),
+    NullDeref,
+    UseAfterFree,
+    UseAfterReturn,
+    DoubleFree,
+    InvalidFree(u64),
+    LiteralModification,
+    SignedOverflow(QualType),
+    DivisionByZero,
+    InvalidShift(i64, QualType),
+    OverlappingCopy,
+    InvalidCall(u64),
+    UnknownFunction(String),
+    UndefinedReference(String),
+    StackOverflow(usize),
+    InvalidFormat(String),
+
+    // analyzer warnings
+    UninitializedUse(String),
+    MaybeUninitializedUse(String),
+    NullPointerDeref(String),
+    IndexOutOfBounds(i64, usize),
+    ZeroDivisor(String),
+    UnreachableCode,
+    UnknownConversion(String),
+    FormatArgCount(String, usize, usize),
+    FormatArgType(String, &'static str, usize, QualType),
+
     // preprocessor errors
     InvalidDirective(String),
     InvalidHeader(String),
@@ -459,6 +504,88 @@ impl ErrorKind {
                 format!("functions cannot return type '{}'", qtype)
             }
 
+            ErrorKind::OutOfBounds(offset, size) => format!(
+                "undefined behaviour: out-of-bounds access at offset {} of object with size {}",
+                offset, size
+            ),
+            ErrorKind::PointerOutOfBounds(offset, size) => format!(
+                "undefined behaviour: pointer arithmetic results in offset {} of object with size {}",
+                offset, size
+            ),
+            ErrorKind::InvalidAccess(address) => {
+                format!("undefined behaviour: access of invalid address {:#x}", address)
+            }
+            ErrorKind::NullDeref => "undefined behaviour: null pointer dereference".to_string(),
+            ErrorKind::UseAfterFree => "undefined behaviour: use of memory after it was freed".to_string(),
+            ErrorKind::UseAfterReturn => {
+                "undefined behaviour: use of stack memory after its function returned".to_string()
+            }
+            ErrorKind::DoubleFree => "undefined behaviour: memory freed twice".to_string(),
+            ErrorKind::InvalidFree(address) => format!(
+                "undefined behaviour: freeing address {:#x} which wasn't returned by malloc",
+                address
+            ),
+            ErrorKind::LiteralModification => {
+                "undefined behaviour: modification of string-literal".to_string()
+            }
+            ErrorKind::SignedOverflow(qtype) => {
+                format!("undefined behaviour: signed integer overflow with type '{}'", qtype)
+            }
+            ErrorKind::DivisionByZero => "undefined behaviour: division by zero".to_string(),
+            ErrorKind::InvalidShift(amount, qtype) => format!(
+                "undefined behaviour: shift amount {} is out of range for type '{}'",
+                amount, qtype
+            ),
+            ErrorKind::OverlappingCopy => {
+                "undefined behaviour: memcpy with overlapping source and destination".to_string()
+            }
+            ErrorKind::InvalidCall(address) => format!(
+                "undefined behaviour: call of address {:#x} which isn't a function",
+                address
+            ),
+            ErrorKind::UnknownFunction(name) => {
+                format!("function '{}' has no definition and isn't provided by the interpreter", name)
+            }
+            ErrorKind::UndefinedReference(name) => format!("undefined reference to '{}'", name),
+            ErrorKind::StackOverflow(depth) => {
+                format!("stack overflow, exceeded maximum call depth of {}", depth)
+            }
+            ErrorKind::InvalidFormat(conversion) => {
+                format!("unsupported conversion '{}' in format-string", conversion)
+            }
+
+            ErrorKind::UninitializedUse(name) => format!("variable '{}' is used uninitialized", name),
+            ErrorKind::MaybeUninitializedUse(name) => {
+                format!("variable '{}' may be used uninitialized", name)
+            }
+            ErrorKind::NullPointerDeref(name) => {
+                format!("dereference of null pointer, '{}' is null here", name)
+            }
+            ErrorKind::IndexOutOfBounds(index, length) => format!(
+                "array index {} is out of bounds for array with {} elements",
+                index, length
+            ),
+            ErrorKind::ZeroDivisor(name) => format!("division by zero, '{}' is zero here", name),
+            ErrorKind::UnreachableCode => "code will never be executed".to_string(),
+            ErrorKind::UnknownConversion(conversion) => {
+                format!("unknown conversion '{}' in format-string", conversion)
+            }
+            ErrorKind::FormatArgCount(func, expected, actual) => format!(
+                "format-string of '{}' expects {} argument{}, but {} {} given",
+                func,
+                expected,
+                if *expected == 1 { "" } else { "s" },
+                actual,
+                if *actual == 1 { "is" } else { "are" }
+            ),
+            ErrorKind::FormatArgType(conversion, expected, index, qtype) => format!(
+                "'{}' expects argument of type '{}', but {} argument has type '{}'",
+                conversion,
+                expected,
+                num_to_ord(*index),
+                qtype
+            ),
+
             ErrorKind::InvalidHeader(s) => format!("'{}' is not a valid header file", s),
             ErrorKind::InvalidDirective(s) => {
                 format!("'#{}' is not a valid preprocessor directive", s)
@@ -554,6 +681,14 @@ impl Error {
     /// Prints the error to `stderr` using all of its location information.<br>
     /// If `no_color` is specified then only prints without any highlighting and color codes.
     pub fn print_error(&self, no_color: bool) {
+        self.print("error", Color::Red, no_color)
+    }
+    /// Prints the error like [print_error](Error::print_error) but as a warning, used for the
+    /// diagnostics of the [analyzer](crate::compiler::analyzer) which don't stop compilation
+    pub fn print_warning(&self, no_color: bool) {
+        self.print("warning", Color::Magenta, no_color)
+    }
+    fn print(&self, severity: &str, color: Color, no_color: bool) {
         let included = if let Some(Some("h")) = self.filename.extension().map(|s| s.to_str()) {
             "included file "
         } else {
@@ -561,7 +696,7 @@ impl Error {
         };
         eprintln!(
             "{}: {}",
-            color_text("error", Color::Red, true, no_color),
+            color_text(severity, color, true, no_color),
             color_text(&self.kind.message(), Color::White, true, no_color),
         );
 
@@ -593,7 +728,7 @@ impl Error {
             for _ in 1..self.column as usize + line_length {
                 eprint!(" ");
             }
-            eprintln!("{}", color_text("^", Color::Red, true, no_color));
+            eprintln!("{}", color_text("^", color, true, no_color));
         }
     }
 }
@@ -604,8 +739,10 @@ pub trait Location {
     fn line_string(&self) -> String;
     fn filename(&self) -> PathBuf;
 }
+#[derive(Clone, Copy)]
 enum Color {
     Red,
+    Magenta,
     Blue,
     White,
 }
@@ -613,6 +750,7 @@ impl Color {
     fn code(&self) -> usize {
         match self {
             Color::Red => 31,
+            Color::Magenta => 35,
             Color::Blue => 34,
             Color::White => 37,
         }
@@ -202,7 +202,7 @@ impl Display for TokenKind {
             f,
             "{}",
             match self {
-                TokenKind::LeftParen => "')'",
+                TokenKind::LeftParen => "'('",
                 TokenKind::RightParen => "')'",
                 TokenKind::LeftBrace => "'{'",
                 TokenKind::RightBrace => "'}'",
@@ -289,6 +289,17 @@ impl Display for TokenKind {
     }
 }
 
+/// A comment retained by the [scanner](crate::compiler::scanner::Scanner::retain_comments),
+/// attached to the token following it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trivia {
+    pub text: String,
+    pub line_index: i32,
+    pub column: i32,
+    // comment is on the same line as the token preceding it, eg: `x = 1; // comment`
+    pub trailing: bool,
+}
+
 #[derive(Debug, Clone)]
 pub struct Token {
     pub kind: TokenKind,
@@ -296,6 +307,8 @@ pub struct Token {
     pub column: i32,
     pub line_string: String,
     pub filename: PathBuf,
+    // comments directly preceding the token, only retained when requested
+    pub trivia: Vec<Trivia>,
 }
 impl Token {
     pub fn new(
@@ -311,6 +324,7 @@ impl Token {
             column,
             line_string,
             filename,
+            trivia: Vec::new(),
         }
     }
     pub fn default(kind: TokenKind) -> Self {
@@ -320,6 +334,7 @@ impl Token {
             line_string: "".to_string(),
             filename: PathBuf::new(),
             column: -1,
+            trivia: Vec::new(),
         }
     }
     pub fn unwrap_string(&self) -> String {
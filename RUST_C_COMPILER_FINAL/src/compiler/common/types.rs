@@ -479,7 +479,7 @@ impl Primitive {
             Primitive::Long(true)
         }
     }
-    fn fmt(&self) -> &str {
+    pub fn fmt(&self) -> &str {
         match self {
             Primitive::Void => "void",
             Primitive::Char(false) => "char",
@@ -0,0 +1,278 @@
+//! The functions from the C standard library that interpreted programs can call without being
+//! linked against libc.<br>
+//! Every argument is passed as the 64-bit value it was evaluated to, pointers being addresses
+//! into the interpreter's [Memory].
+
+use crate::compiler::common::error::ErrorKind;
+use crate::compiler::interpreter::memory::*;
+
+use std::io::Write;
+
+/// Calls the libc-function `name`, returns `None` if it isn't supported
+pub fn call(
+    name: &str,
+    args: &[i64],
+    memory: &mut Memory,
+    output: &mut dyn Write,
+) -> Option<Result<i64, ErrorKind>> {
+    let arg = |index: usize| args.get(index).copied().unwrap_or(0);
+
+    Some(match name {
+        "printf" => printf(arg(0) as u64, &args[1.min(args.len())..], memory).map(|bytes| {
+            let _ = output.write_all(&bytes);
+            bytes.len() as i64
+        }),
+        "puts" => memory.string(arg(0) as u64).map(|mut bytes| {
+            bytes.push(b'\n');
+            let _ = output.write_all(&bytes);
+            bytes.len() as i64
+        }),
+        "putchar" => {
+            let _ = output.write_all(&[arg(0) as u8]);
+            Ok(arg(0) as u8 as i64)
+        }
+        "malloc" => Ok(memory.allocate(arg(0) as usize, ObjectKind::Heap) as i64),
+        "calloc" => Ok(memory.allocate((arg(0) * arg(1)) as usize, ObjectKind::Heap) as i64),
+        "free" if arg(0) == 0 => Ok(0),
+        "free" => memory.free(arg(0) as u64).map(|_| 0),
+        "strlen" => memory.string(arg(0) as u64).map(|bytes| bytes.len() as i64),
+        "memcpy" => {
+            let (dest, src, size) = (arg(0) as u64, arg(1) as u64, arg(2) as u64);
+            if dest < src + size && src < dest + size {
+                return Some(Err(ErrorKind::OverlappingCopy));
+            }
+            copy(memory, dest, src, size as usize).map(|_| dest as i64)
+        }
+        "memset" => {
+            let bytes = vec![arg(1) as u8; arg(2) as usize];
+            memory.write(arg(0) as u64, &bytes).map(|_| arg(0))
+        }
+        _ => return None,
+    })
+}
+
+pub fn copy(memory: &mut Memory, dest: u64, src: u64, size: usize) -> Result<(), ErrorKind> {
+    let bytes = memory.read(src, size)?.to_vec();
+    memory.write(dest, &bytes)
+}
+
+// formats the variadic arguments like glibc's printf
+fn printf(format: u64, args: &[i64], memory: &Memory) -> Result<Vec<u8>, ErrorKind> {
+    let format = memory.string(format)?;
+    let mut args = args.iter().copied();
+    let mut output = Vec::new();
+
+    let mut chars = format.iter().copied().peekable();
+    while let Some(c) = chars.next() {
+        if c != b'%' {
+            output.push(c);
+            continue;
+        }
+        let mut spec = Spec::default();
+
+        while let Some(flag) = chars.next_if(|c| b"-+ #0".contains(c)) {
+            match flag {
+                b'-' => spec.left = true,
+                b'+' => spec.sign = Some(b'+'),
+                b' ' => spec.sign = spec.sign.or(Some(b' ')),
+                b'#' => spec.alternate = true,
+                _ => spec.zero = true,
+            }
+        }
+        if chars.next_if_eq(&b'*').is_some() {
+            let width = args.next().unwrap_or(0) as i32;
+            spec.left |= width < 0;
+            spec.width = width.unsigned_abs() as usize;
+        } else {
+            spec.width = number(&mut chars);
+        }
+        if chars.next_if_eq(&b'.').is_some() {
+            spec.precision = if chars.next_if_eq(&b'*').is_some() {
+                usize::try_from(args.next().unwrap_or(0) as i32).ok()
+            } else {
+                Some(number(&mut chars))
+            };
+        }
+
+        // values are truncated to the size given by the length-modifier
+        let mut size = 4;
+        while let Some(length) = chars.next_if(|c| b"hlzjt".contains(c)) {
+            size = match length {
+                b'h' if size == 2 => 1,
+                b'h' => 2,
+                _ => 8,
+            };
+        }
+
+        let conversion = chars.next().unwrap_or(b'%');
+        let value = if conversion == b'%' {
+            0
+        } else {
+            args.next().unwrap_or(0)
+        };
+        let signed = match size {
+            1 => value as i8 as i64,
+            2 => value as i16 as i64,
+            4 => value as i32 as i64,
+            _ => value,
+        };
+        let unsigned = match size {
+            1 => value as u8 as u64,
+            2 => value as u16 as u64,
+            4 => value as u32 as u64,
+            _ => value as u64,
+        };
+
+        let formatted = match conversion {
+            b'd' | b'i' => {
+                let sign = if signed < 0 { Some(b'-') } else { spec.sign };
+                spec.number(sign, "", signed.unsigned_abs().to_string())
+            }
+            b'u' => spec.number(None, "", unsigned.to_string()),
+            b'x' => spec.number(None, "0x", format!("{:x}", unsigned)),
+            b'X' => spec.number(None, "0X", format!("{:X}", unsigned)),
+            b'o' => spec.number(None, "0", format!("{:o}", unsigned)),
+            b'p' if value == 0 => spec.pad(b"(nil)".to_vec()),
+            b'p' => spec.pad(format!("{:#x}", value).into_bytes()),
+            b'c' => spec.pad(vec![value as u8]),
+            b's' => {
+                let mut string = memory.string(value as u64)?;
+                if let Some(precision) = spec.precision {
+                    string.truncate(precision);
+                }
+                spec.pad(string)
+            }
+            b'%' => vec![b'%'],
+            c => return Err(ErrorKind::InvalidFormat(format!("%{}", c as char))),
+        };
+        output.extend(formatted);
+    }
+
+    Ok(output)
+}
+
+fn number(chars: &mut std::iter::Peekable<impl Iterator<Item = u8>>) -> usize {
+    let mut n = 0;
+    while let Some(digit) = chars.next_if(u8::is_ascii_digit) {
+        n = n * 10 + (digit - b'0') as usize;
+    }
+    n
+}
+
+#[derive(Default)]
+struct Spec {
+    left: bool,
+    sign: Option<u8>,
+    alternate: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+impl Spec {
+    fn number(&self, sign: Option<u8>, prefix: &str, mut digits: String) -> Vec<u8> {
+        if let Some(precision) = self.precision {
+            // zero with a precision of zero prints no digits
+            if precision == 0 && digits == "0" {
+                digits.clear();
+            }
+            digits = format!("{:0>1$}", digits, precision);
+        }
+        let prefix = if self.alternate && !digits.trim_start_matches('0').is_empty() {
+            prefix
+        } else {
+            ""
+        };
+
+        let mut start = Vec::new();
+        start.extend(sign);
+        start.extend(prefix.bytes());
+
+        if self.zero && !self.left && self.precision.is_none() {
+            let zeros = self.width.saturating_sub(start.len() + digits.len());
+            start.extend(std::iter::repeat_n(b'0', zeros));
+        }
+        start.extend(digits.bytes());
+
+        self.pad(start)
+    }
+    fn pad(&self, mut bytes: Vec<u8>) -> Vec<u8> {
+        let padding = std::iter::repeat_n(b' ', self.width.saturating_sub(bytes.len()));
+        if self.left {
+            bytes.extend(padding);
+            bytes
+        } else {
+            padding.chain(bytes).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_printf(format: &str, args: &[i64]) -> String {
+        let mut memory = Memory::new();
+        let format = memory.literal(format.as_bytes());
+
+        String::from_utf8(printf(format, args, &memory).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(setup_printf("%d %i %u", &[-5, 7, -1]), "-5 7 4294967295");
+        assert_eq!(setup_printf("%ld %lu", &[-5, -1]), "-5 18446744073709551615");
+        assert_eq!(setup_printf("%hhd %hu", &[255, 65537]), "-1 1");
+        assert_eq!(
+            setup_printf("[%5d|%-5d|%05d|%+d]", &[42, 42, -42, 42]),
+            "[   42|42   |-0042|+42]"
+        );
+        assert_eq!(
+            setup_printf("%x %X %#x %#o %o", &[255, 255, 255, 8, 0]),
+            "ff FF 0xff 010 0"
+        );
+        assert_eq!(setup_printf("%.3d|%*d|%.0d", &[7, 4, 1, 0]), "007|   1|");
+        assert_eq!(setup_printf("%c%c%%", &[b'o' as i64, b'k' as i64]), "ok%");
+        assert_eq!(setup_printf("%p %p", &[0, 0x10]), "(nil) 0x10");
+    }
+
+    #[test]
+    fn strings() {
+        let mut memory = Memory::new();
+        let string = memory.literal(b"hello");
+        let format = memory.literal(b"[%s|%.2s|%-6s|%7s]");
+        let args = [string as i64; 4];
+
+        assert_eq!(
+            printf(format, &args, &memory).unwrap(),
+            b"[hello|he|hello |  hello]"
+        );
+        assert_eq!(printf(format, &[0], &memory), Err(ErrorKind::NullDeref));
+    }
+
+    #[test]
+    fn memory_functions() {
+        let mut memory = Memory::new();
+        let mut output = Vec::new();
+        let string = memory.literal(b"abc") as i64;
+        let heap = call("malloc", &[8], &mut memory, &mut output).unwrap().unwrap();
+
+        assert_eq!(call("strlen", &[string], &mut memory, &mut output), Some(Ok(3)));
+        assert_eq!(
+            call("memcpy", &[heap, string, 4], &mut memory, &mut output),
+            Some(Ok(heap))
+        );
+        assert_eq!(call("puts", &[heap], &mut memory, &mut output), Some(Ok(4)));
+        assert_eq!(
+            call("memcpy", &[heap + 1, heap, 4], &mut memory, &mut output),
+            Some(Err(ErrorKind::OverlappingCopy))
+        );
+        assert_eq!(call("free", &[heap], &mut memory, &mut output), Some(Ok(0)));
+        assert_eq!(
+            call("strlen", &[heap], &mut memory, &mut output),
+            Some(Err(ErrorKind::UseAfterFree))
+        );
+        assert_eq!(call("fopen", &[], &mut memory, &mut output), None);
+
+        assert_eq!(output, b"abc\n");
+    }
+}
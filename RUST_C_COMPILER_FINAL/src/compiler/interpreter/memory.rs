@@ -0,0 +1,270 @@
+//! Byte-addressable memory made up of separate objects.<br>
+//! Every object gets its own range of addresses which is never reused, so that a pointer always
+//! refers to the object it was derived from and accesses through dangling pointers can be detected.
+//! Objects are separated by unused gaps so that accesses just outside of an object aren't
+//! mistaken for accesses of its neighbour.
+
+use crate::compiler::common::error::ErrorKind;
+
+use std::collections::BTreeMap;
+
+// accesses of addresses in the first page are all treated as null-pointer dereferences
+const NULL_PAGE: u64 = 0x1000;
+const FIRST_ADDRESS: u64 = 0x10000;
+
+// unused addresses between two objects
+const GAP: u64 = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObjectKind {
+    Global,
+    // string-literals can't be modified
+    Literal,
+    Stack,
+    Heap,
+    // functions have an address but can't be accessed
+    Function(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Live,
+    // heap-memory that was freed
+    Freed,
+    // stack-memory of a function that already returned
+    Released,
+}
+
+struct Object {
+    // bytes are dropped once the object is dead
+    bytes: Vec<u8>,
+    size: usize,
+    kind: ObjectKind,
+    state: State,
+}
+
+pub struct Memory {
+    objects: BTreeMap<u64, Object>,
+    next_address: u64,
+}
+impl Memory {
+    pub fn new() -> Self {
+        Memory {
+            objects: BTreeMap::new(),
+            next_address: FIRST_ADDRESS,
+        }
+    }
+
+    /// Creates a new zero-initialized object and returns its address
+    pub fn allocate(&mut self, size: usize, kind: ObjectKind) -> u64 {
+        self.insert(vec![0; size], kind)
+    }
+    /// Creates a read-only object holding the nul-terminated string
+    pub fn literal(&mut self, string: &[u8]) -> u64 {
+        let mut bytes = string.to_vec();
+        bytes.push(0);
+
+        self.insert(bytes, ObjectKind::Literal)
+    }
+    fn insert(&mut self, bytes: Vec<u8>, kind: ObjectKind) -> u64 {
+        let address = self.next_address;
+        let size = bytes.len();
+        self.next_address += (size as u64 + GAP + 15) & !15;
+
+        self.objects.insert(
+            address,
+            Object {
+                bytes,
+                size,
+                kind,
+                state: State::Live,
+            },
+        );
+        address
+    }
+
+    /// Frees an object returned by [Memory::allocate] on the heap
+    pub fn free(&mut self, address: u64) -> Result<(), ErrorKind> {
+        match self.objects.get_mut(&address) {
+            Some(object) if object.kind == ObjectKind::Heap && object.state == State::Freed => {
+                Err(ErrorKind::DoubleFree)
+            }
+            Some(object) if object.kind == ObjectKind::Heap => {
+                object.state = State::Freed;
+                object.bytes = Vec::new();
+                Ok(())
+            }
+            _ => Err(ErrorKind::InvalidFree(address)),
+        }
+    }
+
+    /// Marks the stack-object as dead once its function returns
+    pub fn release(&mut self, address: u64) {
+        let object = self.objects.get_mut(&address).unwrap();
+        object.state = State::Released;
+        object.bytes = Vec::new();
+    }
+
+    /// Returns the name of the function at the address
+    pub fn function(&self, address: u64) -> Option<&str> {
+        match self.objects.get(&address) {
+            Some(Object {
+                kind: ObjectKind::Function(name),
+                ..
+            }) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Returns the base and size of the object the address points into or one past the end of
+    pub fn bounds(&self, address: u64) -> Option<(u64, usize)> {
+        match self.objects.range(..=address).next_back() {
+            Some((base, object))
+                if address - base <= object.size as u64 && !matches!(object.kind, ObjectKind::Function(_)) =>
+            {
+                Some((*base, object.size))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn read(&self, address: u64, size: usize) -> Result<&[u8], ErrorKind> {
+        let (base, object) = self.find(address, size)?;
+        let offset = (address - base) as usize;
+
+        Ok(&object.bytes[offset..offset + size])
+    }
+
+    pub fn write(&mut self, address: u64, bytes: &[u8]) -> Result<(), ErrorKind> {
+        let (base, object) = self.find(address, bytes.len())?;
+        if object.kind == ObjectKind::Literal {
+            return Err(ErrorKind::LiteralModification);
+        }
+        let offset = (address - base) as usize;
+
+        let object = self.objects.get_mut(&base).unwrap();
+        object.bytes[offset..offset + bytes.len()].copy_from_slice(bytes);
+
+        Ok(())
+    }
+
+    /// Reads the nul-terminated string starting at the address, without the nul-terminator
+    pub fn string(&self, mut address: u64) -> Result<Vec<u8>, ErrorKind> {
+        let mut string = Vec::new();
+        loop {
+            match self.read(address, 1)?[0] {
+                0 => return Ok(string),
+                byte => string.push(byte),
+            }
+            address += 1;
+        }
+    }
+
+    // finds the live object which contains all `size` bytes starting at the address
+    fn find(&self, address: u64, size: usize) -> Result<(u64, &Object), ErrorKind> {
+        if address < NULL_PAGE {
+            return Err(ErrorKind::NullDeref);
+        }
+
+        let previous = self.objects.range(..=address).next_back();
+        if let Some((base, object)) = previous {
+            let offset = address - base;
+
+            if offset + size as u64 <= object.size as u64 {
+                return match (&object.kind, object.state) {
+                    (ObjectKind::Function(_), _) => Err(ErrorKind::InvalidAccess(address)),
+                    (_, State::Live) => Ok((*base, object)),
+                    (_, State::Freed) => Err(ErrorKind::UseAfterFree),
+                    (_, State::Released) => Err(ErrorKind::UseAfterReturn),
+                };
+            }
+        }
+
+        // accesses outside of all objects are reported relative to the closest one
+        let next = self.objects.range(address + 1..).next();
+        let closest = match (previous, next) {
+            (Some((base, object)), Some((next_base, _)))
+                if address.saturating_sub(base + object.size as u64) <= next_base - address =>
+            {
+                Some((base, object))
+            }
+            (_, Some(next)) => Some(next),
+            (previous, None) => previous,
+        };
+
+        match closest {
+            Some((base, object))
+                if (address as i64 - *base as i64).unsigned_abs() < object.size as u64 + GAP
+                    && object.state == State::Live
+                    && !matches!(object.kind, ObjectKind::Function(_)) =>
+            {
+                Err(ErrorKind::OutOfBounds(address as i64 - *base as i64, object.size))
+            }
+            _ => Err(ErrorKind::InvalidAccess(address)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds() {
+        let mut memory = Memory::new();
+        let a = memory.allocate(8, ObjectKind::Stack);
+        let b = memory.allocate(4, ObjectKind::Heap);
+
+        memory.write(a + 4, &[1, 2, 3, 4]).unwrap();
+        assert_eq!(memory.read(a, 8).unwrap(), &[0, 0, 0, 0, 1, 2, 3, 4]);
+
+        assert_eq!(memory.read(a + 6, 4), Err(ErrorKind::OutOfBounds(6, 8)));
+        assert_eq!(memory.read(b - 4, 4), Err(ErrorKind::OutOfBounds(-4, 4)));
+        assert_eq!(memory.read(b + 4, 1), Err(ErrorKind::OutOfBounds(4, 4)));
+        assert_eq!(memory.read(a - 4, 4), Err(ErrorKind::OutOfBounds(-4, 8)));
+        assert_eq!(memory.read(0, 4), Err(ErrorKind::NullDeref));
+        assert_eq!(memory.read(16, 4), Err(ErrorKind::NullDeref));
+        assert_eq!(
+            memory.read(b + 0x10000, 4),
+            Err(ErrorKind::InvalidAccess(b + 0x10000))
+        );
+
+        assert_eq!(memory.bounds(a + 8), Some((a, 8)));
+        assert_eq!(memory.bounds(b + 2), Some((b, 4)));
+        assert_eq!(memory.bounds(a + 9), None);
+        assert_eq!(memory.bounds(0), None);
+    }
+
+    #[test]
+    fn dead_objects() {
+        let mut memory = Memory::new();
+        let stack = memory.allocate(4, ObjectKind::Stack);
+        let heap = memory.allocate(4, ObjectKind::Heap);
+
+        memory.release(stack);
+        assert_eq!(memory.read(stack, 4), Err(ErrorKind::UseAfterReturn));
+
+        assert_eq!(memory.free(heap + 1), Err(ErrorKind::InvalidFree(heap + 1)));
+        memory.free(heap).unwrap();
+        assert_eq!(memory.write(heap, &[1]), Err(ErrorKind::UseAfterFree));
+        assert_eq!(memory.free(heap), Err(ErrorKind::DoubleFree));
+        assert_eq!(memory.free(stack), Err(ErrorKind::InvalidFree(stack)));
+    }
+
+    #[test]
+    fn literals_and_functions() {
+        let mut memory = Memory::new();
+        let literal = memory.literal(b"hi");
+        let function = memory.allocate(1, ObjectKind::Function("main".to_string()));
+
+        assert_eq!(memory.string(literal).unwrap(), b"hi");
+        assert_eq!(memory.string(literal + 2).unwrap(), b"");
+        assert_eq!(
+            memory.write(literal, b"a"),
+            Err(ErrorKind::LiteralModification)
+        );
+        assert_eq!(memory.function(function), Some("main"));
+        assert_eq!(memory.function(literal), None);
+        assert_eq!(memory.read(function, 1), Err(ErrorKind::InvalidAccess(function)));
+    }
+}
@@ -0,0 +1,1045 @@
+//! Runs the typechecked [MIR](crate::compiler::typechecker::mir) directly, so that programs can
+//! be executed without an assembler or linker.<br>
+//! Memory is byte-addressable and laid out like in the compiled program, but every variable,
+//! string-literal and allocation is a separate [object](memory) so that out-of-bounds accesses,
+//! use-after-free and null-pointer dereferences are caught instead of silently corrupting memory.
+//! Together with signed overflow and division by zero they are reported as errors at the
+//! location where the undefined behaviour happened.<br>
+//! Functions which aren't defined in the program are looked up in a small [libc]-shim.
+
+mod libc;
+mod memory;
+
+use crate::compiler::common::{environment::*, error::*, token::*, types::*};
+use crate::compiler::typechecker::mir::{decl::*, expr::*, stmt::*};
+use crate::compiler::typechecker::ConstLabels;
+use memory::*;
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::Write;
+use std::rc::Rc;
+
+/// Calls nested deeper than this are reported as a stack overflow
+pub const MAX_CALL_DEPTH: usize = 10000;
+
+// variables are identified by the address of their entry in the symbol-table
+type SymbolKey = *const RefCell<Symbol>;
+// statements targeted by jumps are identified by their address
+type StmtKey = *const Stmt;
+
+/// Runs the `main` function of the program, writing everything it prints into `output`.<br>
+/// Returns the exit-code of the program or the error describing the undefined behaviour.
+pub fn run(
+    external_decls: Vec<ExternalDeclaration>,
+    const_labels: ConstLabels,
+    output: &mut dyn Write,
+) -> Result<i32, WreccError> {
+    let mut interpreter = Interpreter::new(const_labels, output);
+    let result = interpreter.run(external_decls);
+    let _ = interpreter.output.flush();
+
+    match result {
+        Ok(code) | Err(Halt::Exit(code)) => Ok(code),
+        Err(Halt::Error(error)) => Err(WreccError::Comp(vec![*error])),
+        Err(Halt::NoMain) => Err(WreccError::Sys(
+            "program has no 'main' function to run".to_string(),
+        )),
+    }
+}
+
+// stops the execution of the program
+enum Halt {
+    // the program called `exit()`
+    Exit(i32),
+    // boxed so that the frames of the recursive evaluation stay small
+    Error(Box<Error>),
+    NoMain,
+}
+impl From<Error> for Halt {
+    fn from(error: Error) -> Halt {
+        Halt::Error(Box::new(error))
+    }
+}
+
+// how execution continues after a statement
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(i64),
+    Goto(StmtKey),
+}
+
+struct FunctionDef {
+    params: Vec<SymbolRef>,
+    return_type: Type,
+    body: Vec<Stmt>,
+
+    // statements of all labels in the function
+    labels: HashMap<String, StmtKey>,
+
+    // all automatic variables declared in the function
+    locals: HashSet<SymbolKey>,
+}
+
+// the case/default-statements of a switch in order of appearance
+struct Switch {
+    cases: Rc<RefCell<Vec<CaseKind>>>,
+    targets: Vec<StmtKey>,
+}
+
+struct Frame {
+    function: Rc<FunctionDef>,
+    locals: HashMap<SymbolKey, u64>,
+
+    // objects released when the function returns
+    objects: Vec<u64>,
+}
+
+struct Interpreter<'a> {
+    memory: Memory,
+    output: &'a mut dyn Write,
+
+    functions: HashMap<String, Rc<FunctionDef>>,
+    switches: HashMap<StmtKey, Switch>,
+
+    // addresses of global variables and functions by their name
+    globals: HashMap<String, u64>,
+    // static variables declared inside of functions
+    statics: HashMap<SymbolKey, u64>,
+    strings: HashMap<String, u64>,
+
+    frames: Vec<Frame>,
+
+    // start of the current statement, used for errors that don't have a more precise location
+    location: Token,
+}
+
+impl<'a> Interpreter<'a> {
+    fn new(const_labels: ConstLabels, output: &'a mut dyn Write) -> Self {
+        let mut memory = Memory::new();
+        let strings = const_labels
+            .into_keys()
+            .map(|string| {
+                let address = memory.literal(string.as_bytes());
+                (string, address)
+            })
+            .collect();
+
+        Interpreter {
+            memory,
+            output,
+            functions: HashMap::new(),
+            switches: HashMap::new(),
+            globals: HashMap::new(),
+            statics: HashMap::new(),
+            strings,
+            frames: Vec::new(),
+            location: Token::default(TokenKind::Semicolon),
+        }
+    }
+
+    fn run(&mut self, external_decls: Vec<ExternalDeclaration>) -> Result<i32, Halt> {
+        // all objects with static storage are allocated before any of them is initialized
+        let mut inits = Vec::new();
+        for decl in external_decls {
+            match decl {
+                ExternalDeclaration::Declaration(decls) => {
+                    for declarator in decls {
+                        let symbol = declarator.entry.borrow();
+                        if symbol.qtype.ty.is_func() || symbol.is_extern() {
+                            continue;
+                        }
+                        let name = declarator.name.unwrap_string();
+                        let address = match self.globals.get(&name) {
+                            Some(address) => *address,
+                            None => {
+                                let address =
+                                    self.memory.allocate(symbol.qtype.ty.size(), ObjectKind::Global);
+                                self.globals.insert(name, address);
+                                address
+                            }
+                        };
+                        if let Some(init) = declarator.init {
+                            inits.push((address, symbol.qtype.ty.clone(), init, declarator.name.clone()));
+                        }
+                    }
+                }
+                ExternalDeclaration::Function(mut func, _, mut body) => {
+                    let mut labels = HashMap::new();
+                    let mut locals = HashSet::new();
+                    let mut switch = None;
+                    for stmt in body.iter_mut() {
+                        self.prepare(
+                            stmt,
+                            &mut func.switches,
+                            &mut switch,
+                            &mut labels,
+                            &mut locals,
+                            &mut inits,
+                        );
+                    }
+
+                    self.functions.insert(
+                        func.name,
+                        Rc::new(FunctionDef {
+                            params: func.params,
+                            return_type: func.return_type.ty,
+                            body,
+                            labels,
+                            locals,
+                        }),
+                    );
+                }
+            }
+        }
+
+        for (address, ty, init, location) in inits {
+            self.location = location;
+            self.initialize(address, &ty, &init)?;
+        }
+
+        let Some(main) = self.functions.get("main").cloned() else {
+            return Err(Halt::NoMain);
+        };
+        let mut args = Vec::new();
+        if main.params.len() >= 2 {
+            let name = self.memory.literal(b"a.out");
+            let argv = self.memory.allocate(16, ObjectKind::Global);
+            self.memory.write(argv, &name.to_le_bytes()).unwrap();
+
+            args = vec![1, argv as i64];
+        }
+
+        Ok(self.function_call("main", args)? as i32)
+    }
+
+    // collects everything needed to execute the statement:
+    // statics, automatic variables, labels and the targets of switch-statements
+    fn prepare(
+        &mut self,
+        stmt: &mut Stmt,
+        switches: &mut VecDeque<Rc<RefCell<Vec<CaseKind>>>>,
+        current_switch: &mut Option<StmtKey>,
+        labels: &mut HashMap<String, StmtKey>,
+        locals: &mut HashSet<SymbolKey>,
+        inits: &mut Vec<(u64, Type, Init, Token)>,
+    ) {
+        let key = stmt as *const Stmt;
+
+        match stmt {
+            Stmt::Declaration(decls) => {
+                for declarator in decls {
+                    let symbol = declarator.entry.borrow();
+                    match symbol.storage_class {
+                        Some(StorageClass::Static) if declarator.name == symbol.token => {
+                            let address = self.memory.allocate(symbol.qtype.ty.size(), ObjectKind::Global);
+                            self.statics.insert(Rc::as_ptr(&declarator.entry), address);
+
+                            if let Some(init) = declarator.init.take() {
+                                inits.push((address, symbol.qtype.ty.clone(), init, declarator.name.clone()));
+                            }
+                        }
+                        None | Some(StorageClass::Auto | StorageClass::Register) => {
+                            locals.insert(Rc::as_ptr(&declarator.entry));
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            Stmt::Block(stmts) => {
+                for stmt in stmts {
+                    self.prepare(stmt, switches, current_switch, labels, locals, inits);
+                }
+            }
+            Stmt::If(_, then_branch, else_branch) => {
+                self.prepare(then_branch, switches, current_switch, labels, locals, inits);
+                if let Some(else_branch) = else_branch {
+                    self.prepare(else_branch, switches, current_switch, labels, locals, inits);
+                }
+            }
+            Stmt::For(init, _, _, body) => {
+                if let Some(init) = init {
+                    self.prepare(init, switches, current_switch, labels, locals, inits);
+                }
+                self.prepare(body, switches, current_switch, labels, locals, inits);
+            }
+            Stmt::While(_, body) | Stmt::Do(body, _) | Stmt::Located(_, body) => {
+                self.prepare(body, switches, current_switch, labels, locals, inits);
+            }
+            Stmt::Switch(_, body) => {
+                let cases = switches.pop_front().unwrap();
+                self.switches.insert(
+                    key,
+                    Switch {
+                        cases,
+                        targets: Vec::new(),
+                    },
+                );
+
+                let outer_switch = current_switch.replace(key);
+                self.prepare(body, switches, current_switch, labels, locals, inits);
+                *current_switch = outer_switch;
+            }
+            Stmt::Case(body) | Stmt::Default(body) => {
+                let switch = current_switch.expect("typechecker");
+                self.switches.get_mut(&switch).unwrap().targets.push(key);
+
+                self.prepare(body, switches, current_switch, labels, locals, inits);
+            }
+            Stmt::Label(name, body) => {
+                labels.insert(name.clone(), key);
+                self.prepare(body, switches, current_switch, labels, locals, inits);
+            }
+            _ => (),
+        }
+    }
+
+    fn error(&self, location: &Token, kind: ErrorKind) -> Halt {
+        Halt::Error(Box::new(Error::new(location, kind)))
+    }
+
+    fn function_call(&mut self, name: &str, args: Vec<i64>) -> Result<i64, Halt> {
+        let Some(function) = self.functions.get(name).cloned() else {
+            if name == "exit" {
+                return Err(Halt::Exit(args.first().copied().unwrap_or(0) as i32));
+            }
+            return match libc::call(name, &args, &mut self.memory, self.output) {
+                Some(result) => result.map_err(|kind| self.error(&self.location, kind)),
+                None => Err(self.error(&self.location, ErrorKind::UnknownFunction(name.to_string()))),
+            };
+        };
+        if self.frames.len() == MAX_CALL_DEPTH {
+            return Err(self.error(&self.location, ErrorKind::StackOverflow(MAX_CALL_DEPTH)));
+        }
+
+        self.frames.push(Frame {
+            function: Rc::clone(&function),
+            locals: HashMap::new(),
+            objects: Vec::new(),
+        });
+        for (param, value) in function.params.iter().zip(args) {
+            let ty = param.borrow().qtype.ty.clone();
+            let address = self.local(param);
+
+            // aggregates are passed by their address but have to be copied
+            if ty.is_aggregate() {
+                self.copy(address, value as u64, ty.size())?;
+            } else {
+                self.write(address, value, &ty, &self.location.clone())?;
+            }
+        }
+
+        let location = self.location.clone();
+        let mut target = None;
+        let value = loop {
+            match self.block(&function.body, &mut target)? {
+                // jumps start searching for their label from the top of the function
+                Flow::Goto(label) => target = Some(label),
+                Flow::Return(value) => break value,
+                _ => break 0,
+            }
+        };
+        self.location = location;
+
+        // returned aggregates have to outlive the callee's frame
+        let frame = self.frames.pop().unwrap();
+        let value = if function.return_type.is_aggregate() {
+            let size = function.return_type.size();
+            let address = self.memory.allocate(size, ObjectKind::Stack);
+            self.copy(address, value as u64, size)?;
+
+            if let Some(caller) = self.frames.last_mut() {
+                caller.objects.push(address);
+            }
+            address as i64
+        } else {
+            value
+        };
+        for object in frame.objects {
+            self.memory.release(object);
+        }
+
+        Ok(value)
+    }
+
+    // returns the address of an automatic variable in the current frame, allocating it on first use
+    fn local(&mut self, symbol: &SymbolRef) -> u64 {
+        let frame = self.frames.last_mut().unwrap();
+        if let Some(address) = frame.locals.get(&Rc::as_ptr(symbol)) {
+            return *address;
+        }
+
+        let address = self
+            .memory
+            .allocate(symbol.borrow().qtype.ty.size(), ObjectKind::Stack);
+        frame.locals.insert(Rc::as_ptr(symbol), address);
+        frame.objects.push(address);
+
+        address
+    }
+    fn variable(&mut self, symbol: &SymbolRef) -> Result<u64, Halt> {
+        let key = Rc::as_ptr(symbol);
+        if let Some(frame) = self.frames.last() {
+            if let Some(address) = frame.locals.get(&key) {
+                return Ok(*address);
+            }
+            // variables whose declaration was skipped by a jump
+            if frame.function.locals.contains(&key) {
+                return Ok(self.local(symbol));
+            }
+        }
+        if let Some(address) = self.statics.get(&key) {
+            return Ok(*address);
+        }
+
+        let name = symbol.borrow().token.unwrap_string();
+        match self.globals.get(&name) {
+            Some(address) => Ok(*address),
+            None => Err(self.error(&self.location, ErrorKind::UndefinedReference(name))),
+        }
+    }
+    fn function_address(&mut self, name: String) -> u64 {
+        if let Some(address) = self.globals.get(&name) {
+            return *address;
+        }
+        let address = self.memory.allocate(1, ObjectKind::Function(name.clone()));
+        self.globals.insert(name, address);
+
+        address
+    }
+
+    fn read(&self, address: u64, ty: &Type, location: &Token) -> Result<i64, Halt> {
+        let bytes = self
+            .memory
+            .read(address, ty.size())
+            .map_err(|kind| self.error(location, kind))?;
+
+        let mut value = [0; 8];
+        value[..bytes.len()].copy_from_slice(bytes);
+        Ok(normalize(i64::from_le_bytes(value), ty))
+    }
+    fn write(&mut self, address: u64, value: i64, ty: &Type, location: &Token) -> Result<(), Halt> {
+        self.memory
+            .write(address, &value.to_le_bytes()[..ty.size()])
+            .map_err(|kind| self.error(location, kind))
+    }
+    fn copy(&mut self, dest: u64, src: u64, size: usize) -> Result<(), Halt> {
+        libc::copy(&mut self.memory, dest, src, size).map_err(|kind| self.error(&self.location, kind))
+    }
+    // stores the value of the expression at the address, aggregates are copied
+    fn store(&mut self, address: u64, expr: &Expr) -> Result<i64, Halt> {
+        let value = self.expr(expr)?;
+        let ty = &expr.qtype.ty;
+
+        if ty.is_aggregate() {
+            self.copy(address, value as u64, ty.size())?;
+        } else {
+            self.write(address, value, ty, &self.location.clone())?;
+        }
+        Ok(value)
+    }
+    fn initialize(&mut self, address: u64, ty: &Type, init: &Init) -> Result<(), Halt> {
+        match init {
+            Init::Scalar(expr) => {
+                self.store(address, expr)?;
+            }
+            Init::Aggr(list) => {
+                // first overwrite all entries with 0
+                self.memory.write(address, &vec![0; ty.size()]).unwrap();
+
+                for (expr, offset) in list {
+                    self.store(address + *offset as u64, expr)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn block(&mut self, stmts: &[Stmt], target: &mut Option<StmtKey>) -> Result<Flow, Halt> {
+        for stmt in stmts {
+            match self.stmt(stmt, target)? {
+                Flow::Normal => (),
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Normal)
+    }
+    // executes the statement; while jumping to a `target` statements are skipped until it is found
+    fn stmt(&mut self, stmt: &Stmt, target: &mut Option<StmtKey>) -> Result<Flow, Halt> {
+        if *target == Some(stmt as *const Stmt) {
+            *target = None;
+        }
+        let jumping = target.is_some();
+
+        match stmt {
+            Stmt::Located(token, body) => {
+                if !jumping {
+                    self.location = token.clone();
+                }
+                self.stmt(body, target)
+            }
+            Stmt::Expr(expr) => {
+                if !jumping {
+                    self.expr(expr)?;
+                }
+                Ok(Flow::Normal)
+            }
+            Stmt::Declaration(decls) => {
+                if !jumping {
+                    self.declaration(decls)?;
+                }
+                Ok(Flow::Normal)
+            }
+            Stmt::Block(stmts) => self.block(stmts, target),
+            Stmt::If(cond, then_branch, else_branch) => {
+                if jumping {
+                    let flow = self.stmt(then_branch, target)?;
+                    return match else_branch {
+                        Some(else_branch) if target.is_some() => self.stmt(else_branch, target),
+                        _ => Ok(flow),
+                    };
+                }
+
+                if self.condition(cond)? {
+                    self.stmt(then_branch, target)
+                } else if let Some(else_branch) = else_branch {
+                    self.stmt(else_branch, target)
+                } else {
+                    Ok(Flow::Normal)
+                }
+            }
+            Stmt::While(cond, body) => self.repeat(Some(cond), None, body, target, true),
+            Stmt::Do(body, cond) => self.repeat(Some(cond), None, body, target, false),
+            Stmt::For(init, cond, inc, body) => {
+                if let (Some(init), false) = (init, jumping) {
+                    self.stmt(init, target)?;
+                }
+                self.repeat(cond.as_ref(), inc.as_ref(), body, target, true)
+            }
+            Stmt::Return(expr) if !jumping => Ok(Flow::Return(match expr {
+                Some(expr) => self.expr(expr)?,
+                None => 0,
+            })),
+            Stmt::Break if !jumping => Ok(Flow::Break),
+            Stmt::Continue if !jumping => Ok(Flow::Continue),
+            Stmt::Switch(cond, body) => {
+                if !jumping {
+                    let value = self.expr(cond)?;
+                    let switch = &self.switches[&(stmt as *const Stmt)];
+                    let cases = switch.cases.borrow();
+
+                    let index = cases
+                        .iter()
+                        .position(|kind| match kind {
+                            CaseKind::Case(literal) => {
+                                normalize(literal_value(literal), &cond.qtype.ty) == value
+                            }
+                            CaseKind::Default => false,
+                        })
+                        .or_else(|| cases.iter().position(|kind| *kind == CaseKind::Default));
+
+                    match index {
+                        Some(index) => *target = Some(switch.targets[index]),
+                        None => return Ok(Flow::Normal),
+                    }
+                }
+
+                match self.stmt(body, target)? {
+                    Flow::Break => Ok(Flow::Normal),
+                    flow => Ok(flow),
+                }
+            }
+            Stmt::Case(body) | Stmt::Default(body) | Stmt::Label(_, body) => self.stmt(body, target),
+            Stmt::Goto(label) if !jumping => {
+                let function = &self.frames.last().unwrap().function;
+                Ok(Flow::Goto(function.labels[label]))
+            }
+            Stmt::Return(_) | Stmt::Break | Stmt::Continue | Stmt::Goto(_) => Ok(Flow::Normal),
+        }
+    }
+    // runs a loop, the condition is only checked before the first iteration if `check_first` is set
+    fn repeat(
+        &mut self,
+        cond: Option<&Expr>,
+        inc: Option<&Expr>,
+        body: &Stmt,
+        target: &mut Option<StmtKey>,
+        check_first: bool,
+    ) -> Result<Flow, Halt> {
+        let mut check = check_first;
+        loop {
+            // jumps into the loop-body skip the condition
+            if let (Some(cond), true, None) = (cond, check, &target) {
+                if !self.condition(cond)? {
+                    return Ok(Flow::Normal);
+                }
+            }
+            check = true;
+
+            match self.stmt(body, target)? {
+                Flow::Break => return Ok(Flow::Normal),
+                // the target of the jump isn't inside of this loop
+                Flow::Normal | Flow::Continue if target.is_some() => return Ok(Flow::Normal),
+                Flow::Normal | Flow::Continue => (),
+                flow => return Ok(flow),
+            }
+
+            if let Some(inc) = inc {
+                self.expr(inc)?;
+            }
+        }
+    }
+    fn declaration(&mut self, decls: &[Declarator]) -> Result<(), Halt> {
+        for declarator in decls {
+            let symbol = declarator.entry.borrow().clone();
+
+            // statics and externs aren't stored in the frame
+            if matches!(
+                symbol.storage_class,
+                None | Some(StorageClass::Auto | StorageClass::Register)
+            ) && declarator.name == symbol.token
+                && !symbol.qtype.ty.is_func()
+            {
+                let address = self.local(&declarator.entry);
+
+                if let Some(init) = &declarator.init {
+                    self.initialize(address, &symbol.qtype.ty, init)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn condition(&mut self, expr: &Expr) -> Result<bool, Halt> {
+        Ok(self.expr(expr)? != 0)
+    }
+    // returns the value of the expression, aggregates evaluate to their address
+    fn expr(&mut self, expr: &Expr) -> Result<i64, Halt> {
+        let ty = &expr.qtype.ty;
+
+        Ok(match &expr.kind {
+            ExprKind::Binary { left, token, right } => self.binary(left, token, right, ty)?,
+            ExprKind::Comparison { left, token, right } => {
+                let operand_type = &left.qtype.ty;
+                let left = self.expr(left)?;
+                let right = normalize(self.expr(right)?, operand_type);
+
+                let ordering = if operand_type.is_unsigned() {
+                    (left as u64).cmp(&(right as u64))
+                } else {
+                    left.cmp(&right)
+                };
+                (match token.kind {
+                    TokenKind::EqualEqual => ordering.is_eq(),
+                    TokenKind::BangEqual => ordering.is_ne(),
+                    TokenKind::Less => ordering.is_lt(),
+                    TokenKind::Greater => ordering.is_gt(),
+                    TokenKind::LessEqual => ordering.is_le(),
+                    TokenKind::GreaterEqual => ordering.is_ge(),
+                    _ => unreachable!("not a comparison: {:?}", token.kind),
+                }) as i64
+            }
+            ExprKind::Logical { left, token, right } => {
+                let left = self.condition(left)?;
+                (match token.kind {
+                    TokenKind::AmpAmp => left && self.condition(right)?,
+                    TokenKind::PipePipe => left || self.condition(right)?,
+                    _ => unreachable!("not a logical operator: {:?}", token.kind),
+                }) as i64
+            }
+            ExprKind::Unary { token, right } => match token.kind {
+                TokenKind::Amp => self.address(right)? as i64,
+                // dereferenced function-pointers are still called through the pointer
+                TokenKind::Star if ty.is_func() => self.expr(right)?,
+                TokenKind::Star => self.load(expr)?,
+                TokenKind::Bang => !self.condition(right)? as i64,
+                TokenKind::Minus => {
+                    let value = self.expr(right)?;
+                    if !ty.is_unsigned() && value == ty.min() {
+                        return Err(self.error(token, ErrorKind::SignedOverflow(expr.qtype.clone())));
+                    }
+                    normalize(value.wrapping_neg(), ty)
+                }
+                TokenKind::Tilde => normalize(!self.expr(right)?, ty),
+                _ => self.expr(right)?,
+            },
+            ExprKind::Assign { l_expr, r_expr } => {
+                let address = self.address(l_expr)?;
+                let value = self.store(address, r_expr)?;
+
+                if ty.is_aggregate() {
+                    address as i64
+                } else {
+                    value
+                }
+            }
+            ExprKind::CompoundAssign { expr, tmp_symbol } => {
+                self.local(tmp_symbol);
+                self.expr(expr)?
+            }
+            ExprKind::Ident(_) | ExprKind::MemberAccess { .. } => self.load(expr)?,
+            ExprKind::Call { caller, args } => {
+                let mut values = Vec::new();
+                for arg in args {
+                    values.push(self.expr(arg)?);
+                }
+                let name = match direct_callee(caller) {
+                    Some(name) => name,
+                    None => {
+                        let address = self.expr(caller)? as u64;
+                        match self.memory.function(address) {
+                            Some(name) => name.to_string(),
+                            None => return Err(self.error(&self.location, ErrorKind::InvalidCall(address))),
+                        }
+                    }
+                };
+                self.function_call(&name, values)?
+            }
+            ExprKind::Cast { new_type, expr, .. } => normalize(self.expr(expr)?, new_type),
+            ExprKind::Scale {
+                expr,
+                direction,
+                by_amount,
+                ..
+            } => {
+                let value = self.expr(expr)?;
+                match direction {
+                    ScaleDirection::Up => normalize(value.wrapping_mul(*by_amount as i64), ty),
+                    ScaleDirection::Down => value / *by_amount as i64,
+                }
+            }
+            ExprKind::Ternary {
+                cond,
+                true_expr,
+                false_expr,
+            } => {
+                if self.condition(cond)? {
+                    self.expr(true_expr)?
+                } else {
+                    self.expr(false_expr)?
+                }
+            }
+            ExprKind::Comma { left, right } => {
+                self.expr(left)?;
+                self.expr(right)?
+            }
+            ExprKind::String(name) => self.strings[name] as i64,
+            ExprKind::Literal(literal) => normalize(literal_value(literal), ty),
+            ExprKind::Nop => 0,
+        })
+    }
+    fn binary(&mut self, left_expr: &Expr, token: &Token, right_expr: &Expr, ty: &Type) -> Result<i64, Halt> {
+        let left = self.expr(left_expr)?;
+        let right = self.expr(right_expr)?;
+
+        // the result of pointer-arithmetic has to stay within the object the pointer points into
+        if ty.is_ptr() && matches!(token.kind, TokenKind::Plus | TokenKind::Minus) {
+            let ptr = if left_expr.qtype.ty.is_ptr() { left } else { right };
+            if let Some((base, size)) = self.memory.bounds(ptr as u64) {
+                let result = if token.kind == TokenKind::Plus {
+                    left.wrapping_add(right)
+                } else {
+                    left.wrapping_sub(right)
+                };
+                let offset = result.wrapping_sub(base as i64);
+                if offset < 0 || offset > size as i64 {
+                    return Err(self.error(token, ErrorKind::PointerOutOfBounds(offset, size)));
+                }
+                return Ok(result);
+            }
+        }
+        let overflow = || self.error(token, ErrorKind::SignedOverflow(QualType::new(ty.clone())));
+
+        let value = match token.kind {
+            TokenKind::Plus | TokenKind::Minus | TokenKind::Star => {
+                let (left, right) = (left as i128, right as i128);
+                let result = match token.kind {
+                    TokenKind::Plus => left + right,
+                    TokenKind::Minus => left - right,
+                    _ => left * right,
+                };
+                if !ty.is_unsigned() && (result < ty.min() as i128 || result > ty.max() as i128) {
+                    return Err(overflow());
+                }
+                result as i64
+            }
+            TokenKind::Slash | TokenKind::Mod if right == 0 => {
+                return Err(self.error(token, ErrorKind::DivisionByZero))
+            }
+            TokenKind::Slash | TokenKind::Mod if ty.is_unsigned() => {
+                let (left, right) = (left as u64, right as u64);
+                (if token.kind == TokenKind::Slash {
+                    left / right
+                } else {
+                    left % right
+                }) as i64
+            }
+            TokenKind::Slash | TokenKind::Mod => {
+                if left == ty.min() && right == -1 {
+                    return Err(overflow());
+                }
+                if token.kind == TokenKind::Slash {
+                    left / right
+                } else {
+                    left % right
+                }
+            }
+            TokenKind::Amp => left & right,
+            TokenKind::Pipe => left | right,
+            TokenKind::Xor => left ^ right,
+            TokenKind::LessLess | TokenKind::GreaterGreater => {
+                if right < 0 || right >= ty.size() as i64 * 8 {
+                    return Err(self.error(token, ErrorKind::InvalidShift(right, QualType::new(ty.clone()))));
+                }
+                match token.kind {
+                    TokenKind::LessLess => left << right,
+                    _ if ty.is_unsigned() => ((left as u64) >> right) as i64,
+                    _ => left >> right,
+                }
+            }
+            _ => unreachable!("not a binary operator: {:?}", token.kind),
+        };
+
+        Ok(normalize(value, ty))
+    }
+
+    // returns the address of an lvalue
+    fn address(&mut self, expr: &Expr) -> Result<u64, Halt> {
+        Ok(match &expr.kind {
+            ExprKind::Ident(symbol) if expr.qtype.ty.is_func() => {
+                let name = symbol.borrow().token.unwrap_string();
+                self.function_address(name)
+            }
+            ExprKind::Ident(symbol) => self.variable(symbol)?,
+            ExprKind::Unary { token, right } if token.kind == TokenKind::Star => self.expr(right)? as u64,
+            ExprKind::MemberAccess { expr, member } => {
+                let base = self.address(expr)?;
+                match &expr.qtype.ty {
+                    Type::Struct(s) => base.wrapping_add(s.member_offset(member) as u64),
+                    // all members of a union start at its address
+                    _ => base,
+                }
+            }
+            ExprKind::String(name) => self.strings[name],
+            // value of aggregate expressions is already their address
+            _ if expr.qtype.ty.is_aggregate() => self.expr(expr)? as u64,
+            _ => unreachable!("not an lvalue: {:?}", expr.kind),
+        })
+    }
+    fn load(&mut self, expr: &Expr) -> Result<i64, Halt> {
+        let address = self.address(expr)?;
+
+        if is_value(&expr.qtype.ty) {
+            let location = self.location(expr).clone();
+            self.read(address, &expr.qtype.ty, &location)
+        } else {
+            Ok(address as i64)
+        }
+    }
+    // the most precise location of an expression
+    fn location<'b>(&'b self, expr: &'b Expr) -> &'b Token {
+        match &expr.kind {
+            ExprKind::Binary { token, .. }
+            | ExprKind::Unary { token, .. }
+            | ExprKind::Comparison { token, .. }
+            | ExprKind::Logical { token, .. }
+            | ExprKind::Scale { token, .. } => token,
+            ExprKind::MemberAccess { expr, .. } | ExprKind::Cast { expr, .. } => self.location(expr),
+            _ => &self.location,
+        }
+    }
+}
+
+// aggregates and functions are represented by their address instead of their value
+fn is_value(ty: &Type) -> bool {
+    !ty.is_aggregate() && !ty.is_func() && !ty.is_void()
+}
+
+// truncates the value to the size of the type and sign- or zero-extends it back to 64 bits
+fn normalize(value: i64, ty: &Type) -> i64 {
+    if !is_value(ty) {
+        return value;
+    }
+    match (ty.size(), ty.is_unsigned()) {
+        (1, false) => value as i8 as i64,
+        (1, true) => value as u8 as i64,
+        (2, false) => value as i16 as i64,
+        (2, true) => value as u16 as i64,
+        (4, false) => value as i32 as i64,
+        (4, true) => value as u32 as i64,
+        _ => value,
+    }
+}
+
+fn literal_value(literal: &LiteralKind) -> i64 {
+    match literal {
+        LiteralKind::Signed(n) => *n,
+        LiteralKind::Unsigned(n) => *n as i64,
+    }
+}
+
+// functions that are called by name
+fn direct_callee(caller: &Expr) -> Option<String> {
+    match &caller.kind {
+        ExprKind::Ident(symbol) if caller.qtype.ty.is_func() => Some(symbol.borrow().token.unwrap_string()),
+        ExprKind::Unary { token, right } if token.kind == TokenKind::Amp => direct_callee(right),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parser::tests::setup;
+    use crate::compiler::typechecker::TypeChecker;
+
+    fn setup_run(input: &str) -> Result<(i32, String), (i32, i32, ErrorKind)> {
+        let parse_tree = setup(input).parse().unwrap();
+        let (mir, const_labels) = TypeChecker::new().check(parse_tree).unwrap();
+        let mut output = Vec::new();
+
+        match run(mir, const_labels, &mut output) {
+            Ok(code) => Ok((code, String::from_utf8(output).unwrap())),
+            Err(WreccError::Comp(mut errors)) => {
+                let error = errors.remove(0);
+                Err((error.line_index, error.column, error.kind))
+            }
+            Err(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn exit_codes_and_output() {
+        assert_eq!(setup_run("int main() { return 3; }"), Ok((3, String::new())));
+        assert_eq!(
+            setup_run("int printf(char *, ...); void exit(int); int main() { printf(\"%d-%s\\n\", 12, \"ab\"); exit(7); return 0; }"),
+            Ok((7, "12-ab\n".to_string()))
+        );
+        assert_eq!(
+            setup_run("int puts(char *); int main(int argc, char **argv) { puts(argv[0]); return argc; }"),
+            Ok((1, "a.out\n".to_string()))
+        );
+    }
+
+    #[test]
+    fn control_flow() {
+        let actual = setup_run(
+            "
+int fib(int n) { return n < 2 ? n : fib(n - 1) + fib(n - 2); }
+int main() {
+  int sum = 0;
+  for (int i = 0; i < 10; i++) {
+    if (i == 2) continue;
+    switch (i % 3) {
+      case 0: sum += 1;
+      case 1: sum += 10; break;
+      default: sum += 100;
+    }
+  }
+  int n = 0;
+again:
+  if (++n < 5) goto again;
+  return sum + n + fib(10);
+}",
+        );
+
+        assert_eq!(actual, Ok((274 + 5 + 55, String::new())));
+    }
+
+    #[test]
+    fn memory() {
+        let actual = setup_run(
+            "
+void *malloc(long);
+void free(void *);
+struct P { char c; long l; };
+struct P make(long l) { struct P p; p.c = 'x'; p.l = l; return p; }
+int counter() { static int n; return ++n; }
+int apply(int (*f)(int), int x) { return f(x); }
+int twice(int x) { return 2 * x; }
+int main() {
+  struct P p = make(40);
+  long *heap = malloc(2 * sizeof(long));
+  heap[1] = p.l;
+  counter();
+  int result = heap[1] + counter() + apply(twice, 0);
+  free(heap);
+  return result;
+}",
+        );
+
+        assert_eq!(actual, Ok((42, String::new())));
+    }
+
+    #[test]
+    fn undefined_behaviour() {
+        assert_eq!(
+            setup_run("int main() { int a[2]; return a[2]; }"),
+            Err((1, 32, ErrorKind::OutOfBounds(8, 8)))
+        );
+        assert_eq!(
+            setup_run("void *malloc(long); void free(void *);\nint main() { int *p = malloc(4); free(p); return *p; }"),
+            Err((2, 50, ErrorKind::UseAfterFree))
+        );
+        assert_eq!(
+            setup_run("int *f() { int a = 1; return &a; }\nint main() { return *f(); }"),
+            Err((2, 21, ErrorKind::UseAfterReturn))
+        );
+        assert_eq!(
+            setup_run("int main() { int *p = 0; return *p; }"),
+            Err((1, 33, ErrorKind::NullDeref))
+        );
+        assert_eq!(
+            setup_run("int main() { int a = 2147483647; return a + 1; }"),
+            Err((
+                1,
+                43,
+                ErrorKind::SignedOverflow(QualType::new(Type::Primitive(Primitive::Int(false))))
+            ))
+        );
+        assert_eq!(
+            setup_run("int main() { int a = 0; return 5 / a; }"),
+            Err((1, 34, ErrorKind::DivisionByZero))
+        );
+        assert_eq!(
+            setup_run("int main() { char *s = \"abc\"; s[0] = 'x'; return 0; }"),
+            Err((1, 32, ErrorKind::LiteralModification))
+        );
+        assert_eq!(
+            setup_run("void *malloc(long); void free(void *);\nint main() { int *p = malloc(4); free(p); free(p); return 0; }"),
+            Err((2, 43, ErrorKind::DoubleFree))
+        );
+        assert_eq!(
+            setup_run("int main() { int a[2]; int *p = a + 5; return 0; }"),
+            Err((1, 35, ErrorKind::PointerOutOfBounds(20, 8)))
+        );
+        assert_eq!(
+            setup_run("int main() { int a[2]; int *p = a; p = p - 1; return 0; }"),
+            Err((1, 42, ErrorKind::PointerOutOfBounds(-4, 8)))
+        );
+        assert_eq!(
+            setup_run("int main() { int a[2]; int *p = a; p += 3; return 0; }"),
+            Err((1, 38, ErrorKind::PointerOutOfBounds(12, 8)))
+        );
+        // pointing one past the end of an object is allowed
+        assert_eq!(
+            setup_run("int main() { int a[2]; int *p = a + 2; p--; return p - a; }"),
+            Ok((1, String::new()))
+        );
+    }
+
+    #[test]
+    fn stack_overflow() {
+        // the test-thread's default stack is too small for the maximum call depth
+        let actual = std::thread::Builder::new()
+            .stack_size(1 << 30)
+            .spawn(|| setup_run("int f(int n) { return f(n + 1); }\nint main() { return f(0); }"))
+            .unwrap()
+            .join()
+            .unwrap();
+
+        assert_eq!(actual, Err((1, 16, ErrorKind::StackOverflow(MAX_CALL_DEPTH))));
+    }
+}
@@ -1,7 +1,11 @@
-//! [Scanner](scanner) -> [Parser](parser) -> [Typechecker](typechecker) -> [Codegen](codegen)
+//! [Scanner](scanner) -> [Parser](parser) -> [Typechecker](typechecker) -> [Codegen](codegen)<br>
+//! Instead of generating code the typechecked program can also be run by the [interpreter].<br>
+//! The [analyzer] can check the typechecked program for likely bugs, reporting them as warnings.
 
+pub mod analyzer;
 pub mod codegen;
 pub mod common;
+pub mod interpreter;
 pub mod parser;
 pub mod scanner;
 pub mod typechecker;
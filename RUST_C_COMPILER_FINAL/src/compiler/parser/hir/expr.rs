@@ -78,6 +78,30 @@ pub enum ExprKind {
     Nop,
 }
 
+impl ExprKind {
+    /// Left-most token in an expression, used to locate the statement it is part of
+    pub fn first_token(&self) -> Option<&Token> {
+        match self {
+            ExprKind::Binary { left, .. }
+            | ExprKind::Logical { left, .. }
+            | ExprKind::Comparison { left, .. }
+            | ExprKind::PostUnary { left, .. }
+            | ExprKind::Comma { left, .. } => left.first_token(),
+            ExprKind::Assign { l_expr, .. } | ExprKind::CompoundAssign { l_expr, .. } => l_expr.first_token(),
+            ExprKind::Call { caller, .. } => caller.first_token(),
+            ExprKind::MemberAccess { expr, .. } => expr.first_token(),
+            ExprKind::Ternary { cond, .. } => cond.first_token(),
+            ExprKind::Unary { token, .. }
+            | ExprKind::Cast { token, .. }
+            | ExprKind::SizeofType { token, .. }
+            | ExprKind::SizeofExpr { token, .. }
+            | ExprKind::String(token)
+            | ExprKind::Ident(token) => Some(token),
+            ExprKind::Char(_) | ExprKind::Number(..) | ExprKind::Nop => None,
+        }
+    }
+}
+
 pub trait PrintIndent {
     fn print_indent(&self, indent_level: usize) -> String;
 }
@@ -28,6 +28,32 @@ pub enum Stmt {
     Label(Token, Box<Stmt>),
 }
 
+impl Stmt {
+    /// Token at the start of a statement, so that the generated code can be mapped back to its source-location
+    pub fn first_token(&self) -> Option<&Token> {
+        match self {
+            Stmt::Declaration(decl) => decl
+                .declarators
+                .iter()
+                .find_map(|(declarator, _)| declarator.name.as_ref()),
+            Stmt::Expr(expr) => expr.first_token(),
+            Stmt::Block(_) => None,
+            Stmt::If(token, ..)
+            | Stmt::While(token, ..)
+            | Stmt::Do(token, ..)
+            | Stmt::For(token, ..)
+            | Stmt::Return(token, _)
+            | Stmt::Break(token)
+            | Stmt::Continue(token)
+            | Stmt::Switch(token, ..)
+            | Stmt::Case(token, ..)
+            | Stmt::Default(token, _)
+            | Stmt::Goto(token)
+            | Stmt::Label(token, _) => Some(token),
+        }
+    }
+}
+
 // provides printable default for empty iterator produced string
 pub trait OrEmpty {
     fn or_empty(self, indent_level: usize) -> String;
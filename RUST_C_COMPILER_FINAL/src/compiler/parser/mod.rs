@@ -1408,7 +1408,6 @@ pub mod tests {
     use super::*;
     use crate::compiler::scanner::Scanner;
     use crate::preprocess;
-    use std::collections::HashMap;
     use std::path::Path;
     use std::path::PathBuf;
 
@@ -1423,7 +1422,6 @@ pub mod tests {
             Path::new(""),
             &Vec::new(),
             &Vec::new(),
-            &HashMap::new(),
             input.to_string(),
         )
         .unwrap();
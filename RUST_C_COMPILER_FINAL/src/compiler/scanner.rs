@@ -13,6 +13,15 @@ pub struct Scanner<'a> {
 
     // Reserved keywords which cannot be an identifier
     keywords: HashMap<&'a str, TokenKind>,
+
+    // Attach comments as trivia to the following token instead of dropping them
+    retain_comments: bool,
+
+    // Retained comments not yet attached to a token
+    trivia: Vec<Trivia>,
+
+    // Line of the last scanned token, to know if a comment trails it
+    last_line: Option<i32>,
 }
 impl<'a> Scanner<'a> {
     pub fn new(source: Vec<PPToken>) -> Self {
@@ -52,8 +61,21 @@ impl<'a> Scanner<'a> {
                 ("return", TokenKind::Return),
                 ("goto", TokenKind::Goto),
             ]),
+            retain_comments: false,
+            trivia: Vec::new(),
+            last_line: None,
         }
     }
+    /// Attaches [comments](PPKind::Comment) as [Trivia] to the token following them.<br>
+    /// Comments are only scanned if the [preprocessor-scanner keeps them](crate::preprocessor::scanner::Scanner::keep_comments).
+    pub fn retain_comments(mut self) -> Self {
+        self.retain_comments = true;
+        self
+    }
+    /// The retained comments following the last token
+    pub fn trailing_trivia(&mut self) -> Vec<Trivia> {
+        std::mem::take(&mut self.trivia)
+    }
 
     fn matches(&mut self, expected: char) -> bool {
         match self.source.peek() {
@@ -78,6 +100,9 @@ impl<'a> Scanner<'a> {
         let mut tokens = ScanResult(Vec::new());
 
         while let Some(pp_token) = self.source.next() {
+            let scanned = tokens.0.len();
+            let line = pp_token.line;
+
             match pp_token.kind {
                 PPKind::Other(c) => {
                     match c {
@@ -251,8 +276,22 @@ impl<'a> Scanner<'a> {
                     }
                 }
                 PPKind::Hash => errors.push(Error::new(&pp_token, ErrorKind::UnexpectedChar('#'))),
+                PPKind::Comment(text) => {
+                    if self.retain_comments {
+                        self.trivia.push(Trivia {
+                            text,
+                            line_index: pp_token.line,
+                            column: pp_token.column,
+                            trailing: self.last_line == Some(pp_token.line),
+                        });
+                    }
+                }
                 PPKind::Whitespace(_) | PPKind::Newline => (),
             }
+            if let Some(token) = tokens.0.get_mut(scanned) {
+                token.trivia.append(&mut self.trivia);
+                self.last_line = Some(line);
+            }
         }
         if errors.is_empty() {
             Ok(tokens.0)
@@ -440,7 +479,6 @@ mod tests {
             Path::new(""),
             &Vec::new(),
             &Vec::new(),
-            &HashMap::new(),
             input.to_string(),
         )
         .unwrap();
@@ -456,7 +494,6 @@ mod tests {
             Path::new(""),
             &Vec::new(),
             &Vec::new(),
-            &HashMap::new(),
             input.to_string(),
         )
         .unwrap();
@@ -474,6 +511,7 @@ mod tests {
             column,
             line_string: line_string.to_string(),
             filename: PathBuf::new(),
+            trivia: Vec::new(),
         }
     }
 
@@ -508,6 +546,36 @@ mod tests {
         assert_eq!(actual, expected);
     }
     #[test]
+    fn retains_comments() {
+        let pp_tokens = crate::preprocessor::scanner::Scanner::new("int x; // a\n/* b */\nint y; /* c */".to_string())
+            .keep_comments()
+            .scan_token()
+            .into_iter()
+            .map(|t| PPToken {
+                kind: t.kind,
+                column: t.column,
+                line: t.line,
+                line_string: t.line_string,
+                filename: PathBuf::new(),
+            })
+            .collect();
+        let mut scanner = Scanner::new(pp_tokens).retain_comments();
+        let tokens = scanner.scan_token().unwrap();
+
+        let trivia = |text: &str, line_index, column, trailing| Trivia {
+            text: text.to_string(),
+            line_index,
+            column,
+            trailing,
+        };
+        assert_eq!(tokens.len(), 6);
+        assert_eq!(
+            tokens[3].trivia,
+            vec![trivia("// a", 1, 8, true), trivia("/* b */", 2, 1, false)]
+        );
+        assert_eq!(scanner.trailing_trivia(), vec![trivia("/* c */", 3, 8, true)]);
+    }
+    #[test]
     fn token_basic_math_expression() {
         let actual = setup("3 + 1 / -4");
         let expected = vec![
@@ -1,3 +1,4 @@
+use crate::compiler::common::token::Token;
 use crate::compiler::typechecker::mir::decl::Declarator;
 use crate::compiler::typechecker::mir::expr::*;
 
@@ -17,4 +18,6 @@ pub enum Stmt {
     Default(Box<Stmt>),
     Goto(String),
     Label(String, Box<Stmt>),
+    // token at the start of the statement, used to emit debug line-information
+    Located(Token, Box<Stmt>),
 }
@@ -21,6 +21,8 @@ use std::collections::HashMap;
 use std::rc::Rc;
 
 pub type ConstLabels = HashMap<String, usize>;
+// the result of typechecking a whole program
+pub type CheckResult = Result<(Vec<mir::decl::ExternalDeclaration>, ConstLabels), Vec<Error>>;
 
 pub struct TypeChecker {
     // symbol table to store all tags and symbols
@@ -50,6 +52,21 @@ impl TypeChecker {
             Err(e) => Err(e.flatten_multiple()),
         }
     }
+    /// Checks the program like [TypeChecker::check] while also recording the [SymbolIndex] of all
+    /// identifiers, which is returned even if the program contains errors
+    pub fn check_indexed(
+        mut self,
+        external_decls: Vec<hir::decl::ExternalDeclaration>,
+    ) -> (CheckResult, SymbolIndex) {
+        self.env.record_index();
+        let result = self.check_declarations(external_decls);
+        let index = self.env.take_index().unwrap_or_default();
+
+        match result {
+            Ok(mir) => (Ok((mir, self.const_labels)), index),
+            Err(e) => (Err(e.flatten_multiple()), index),
+        }
+    }
     fn check_declarations(
         &mut self,
         external_decls: Vec<hir::decl::ExternalDeclaration>,
@@ -1199,6 +1216,19 @@ impl TypeChecker {
         &mut self,
         func: &mut mir::decl::Function,
         statement: hir::stmt::Stmt,
+    ) -> Result<mir::stmt::Stmt, Error> {
+        match statement.first_token().cloned() {
+            Some(token) => Ok(mir::stmt::Stmt::Located(
+                token,
+                Box::new(self.statement(func, statement)?),
+            )),
+            None => self.statement(func, statement),
+        }
+    }
+    fn statement(
+        &mut self,
+        func: &mut mir::decl::Function,
+        statement: hir::stmt::Stmt,
     ) -> Result<mir::stmt::Stmt, Error> {
         match statement {
             hir::stmt::Stmt::Declaration(decls) => self
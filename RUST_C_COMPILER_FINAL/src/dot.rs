@@ -0,0 +1,593 @@
+//! Renders the syntax-tree and the control-flow-graphs of a file as [Graphviz](https://graphviz.org)
+//! `.dot` files, which can be turned into diagrams using `dot -Tsvg file.dot > file.svg`.<br>
+//! The syntax-tree is the [HIR](crate::compiler::parser::hir) with the declared types of its
+//! identifiers, while the control-flow-graphs are built from the typechecked
+//! [MIR](crate::compiler::typechecker::mir) and show the type of every statement's expression.
+
+use crate::compiler::common::{environment::SymbolIndex, error::*};
+use crate::compiler::parser::Parser;
+use crate::compiler::typechecker::mir::{decl::*, expr::*, stmt::*};
+use crate::compiler::typechecker::TypeChecker;
+use crate::dump::{lexeme, Dump, Node};
+use crate::Session;
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// Renders the parse-tree of the file, labeling identifiers with their types
+pub fn ast(session: &Session, file: &Path) -> Result<String, WreccError> {
+    let parse_tree = session.hir(file)?;
+
+    // the typechecker consumes the parse-tree so it gets its own copy
+    let (result, index) = TypeChecker::new().check_indexed(Parser::new(session.tokens(file)?).parse()?);
+    result?;
+
+    let mut graph = Graph::new("ast");
+    let root = graph.node(&["TranslationUnit".to_string()]);
+    let types = Types::new(&index);
+
+    for decl in parse_tree.iter() {
+        if let Some(child) = graph.tree(&decl.dump(), &types) {
+            graph.edge(root, child, None);
+        }
+    }
+
+    Ok(graph.finish())
+}
+
+/// Renders a control-flow-graph for every function-definition in the file
+pub fn cfg(session: &Session, file: &Path) -> Result<String, WreccError> {
+    let (mir, _) = session.mir(file)?;
+    let mut graph = Graph::new("cfg");
+
+    for decl in mir {
+        if let ExternalDeclaration::Function(mut func, symbol, body) = decl {
+            let signature = format!("{} : {}", func.name, symbol.borrow().qtype);
+            writeln!(
+                graph.output,
+                "    subgraph cluster_{} {{\n        label={};",
+                func.name,
+                escape(&signature)
+            )
+            .unwrap();
+
+            let mut builder = CfgBuilder::new(&mut graph, &mut func.switches);
+            let params: Vec<String> = func
+                .params
+                .iter()
+                .map(|param| format!("{} {}", param.borrow().qtype, lexeme(&param.borrow().token)))
+                .collect();
+            builder.emit(format!("{}({})", func.name, params.join(", ")));
+
+            let exit = builder.block(vec!["exit".to_string()]);
+            builder.exit = exit;
+            for stmt in body {
+                builder.stmt(stmt);
+            }
+            builder.jump(exit, None);
+            builder.finish();
+
+            graph.output.push_str("    }\n");
+        }
+    }
+
+    Ok(graph.finish())
+}
+
+// maps the location of a declared or used identifier to its type
+struct Types(HashMap<(PathBuf, i32, i32), String>);
+impl Types {
+    fn new(index: &SymbolIndex) -> Self {
+        Types(
+            index
+                .symbols
+                .iter()
+                .map(|entry| {
+                    let token = &entry.token;
+                    (
+                        (token.filename.clone(), token.line_index, token.column),
+                        entry.target.borrow().qtype.to_string(),
+                    )
+                })
+                .collect(),
+        )
+    }
+    fn label(&self, node: &Node) -> String {
+        match node {
+            Node::Ident(token) => {
+                match self
+                    .0
+                    .get(&(token.filename.clone(), token.line_index, token.column))
+                {
+                    Some(qtype) => format!("{} : {}", lexeme(token), qtype),
+                    None => lexeme(token),
+                }
+            }
+            Node::Str(s) => s.clone(),
+            Node::Int(n) => n.to_string(),
+            Node::Bool(b) => b.to_string(),
+            Node::Null | Node::List(_) | Node::Record(..) => unreachable!("not a scalar"),
+        }
+    }
+}
+
+struct Graph {
+    output: String,
+    node_count: usize,
+}
+impl Graph {
+    fn new(name: &str) -> Self {
+        let mut output = format!("digraph {} {{\n", name);
+        output.push_str("    node [shape=box, fontname=\"monospace\"];\n    ordering=out;\n");
+
+        Graph {
+            output,
+            node_count: 0,
+        }
+    }
+    fn finish(mut self) -> String {
+        self.output.push_str("}\n");
+        self.output
+    }
+
+    // adds a node whose label is made up of left-aligned lines and returns its id
+    fn node(&mut self, lines: &[String]) -> usize {
+        let id = self.node_count;
+        self.node_count += 1;
+
+        let label: String = lines.iter().map(|line| escape_line(line) + "\\l").collect();
+        writeln!(self.output, "    n{} [label=\"{}\"];", id, label).unwrap();
+
+        id
+    }
+    fn edge(&mut self, from: usize, to: usize, label: Option<&str>) {
+        match label {
+            Some(label) => writeln!(self.output, "    n{} -> n{} [label={}];", from, to, escape(label)),
+            None => writeln!(self.output, "    n{} -> n{};", from, to),
+        }
+        .unwrap()
+    }
+
+    // records become nodes labeled with their scalar fields and nested records become their children
+    fn tree(&mut self, node: &Node, types: &Types) -> Option<usize> {
+        match node {
+            Node::Null => None,
+            Node::List(_) => unreachable!("lists are expanded by their parent"),
+            Node::Record(name, fields) => {
+                let mut lines = vec![name.to_string()];
+                let mut children = Vec::new();
+
+                for (key, field) in fields {
+                    match field {
+                        Node::Null => (),
+                        Node::Record(..) => children.push((*key, field)),
+                        Node::List(elems) => children.extend(elems.iter().map(|elem| (*key, elem))),
+                        scalar => lines.push(format!("{}: {}", key, types.label(scalar))),
+                    }
+                }
+
+                let id = self.node(&lines);
+                for (key, child) in children {
+                    if let Some(child) = self.tree(child, types) {
+                        self.edge(id, child, Some(key));
+                    }
+                }
+                Some(id)
+            }
+            scalar => Some(self.node(&[types.label(scalar)])),
+        }
+    }
+}
+
+fn escape_line(line: &str) -> String {
+    line.replace('\\', "\\\\").replace('"', "\\\"")
+}
+fn escape(label: &str) -> String {
+    format!("\"{}\"", escape_line(label))
+}
+
+// splits the statements of a function into basic-blocks
+struct CfgBuilder<'a> {
+    graph: &'a mut Graph,
+
+    // the block statements are currently added to, `None` after a jump
+    current: Option<usize>,
+    exit: usize,
+
+    // targets of `break` and `continue` of the enclosing loops and switches
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+
+    // the dispatching block of the enclosing switches and their remaining case-values
+    switch_heads: Vec<(usize, VecDeque<CaseKind>)>,
+    switches: &'a mut VecDeque<std::rc::Rc<std::cell::RefCell<Vec<CaseKind>>>>,
+
+    labels: HashMap<String, usize>,
+
+    // statements are collected until their block is finished
+    lines: HashMap<usize, Vec<String>>,
+    edges: Vec<(usize, usize, Option<String>)>,
+}
+impl<'a> CfgBuilder<'a> {
+    fn new(
+        graph: &'a mut Graph,
+        switches: &'a mut VecDeque<std::rc::Rc<std::cell::RefCell<Vec<CaseKind>>>>,
+    ) -> Self {
+        CfgBuilder {
+            graph,
+            current: None,
+            exit: 0,
+            breaks: Vec::new(),
+            continues: Vec::new(),
+            switch_heads: Vec::new(),
+            switches,
+            labels: HashMap::new(),
+            lines: HashMap::new(),
+            edges: Vec::new(),
+        }
+    }
+
+    fn block(&mut self, lines: Vec<String>) -> usize {
+        let id = self.graph.node_count;
+        self.graph.node_count += 1;
+        self.lines.insert(id, lines);
+
+        id
+    }
+    // adds a line to the current block, starting a new unreachable block after a jump
+    fn emit(&mut self, line: String) {
+        let current = match self.current {
+            Some(current) => current,
+            None => self.block(Vec::new()),
+        };
+        self.lines.get_mut(&current).unwrap().push(line);
+        self.current = Some(current);
+    }
+    fn edge(&mut self, from: usize, to: usize, label: Option<&str>) {
+        self.edges.push((from, to, label.map(str::to_string)));
+    }
+    // ends the current block with a jump to `to`
+    fn jump(&mut self, to: usize, label: Option<&str>) {
+        if let Some(current) = self.current.take() {
+            self.edge(current, to, label);
+        }
+    }
+    // starts a new block which the current block falls through to
+    fn start(&mut self, block: usize) {
+        self.jump(block, None);
+        self.current = Some(block);
+    }
+    fn label(&mut self, name: &str) -> usize {
+        if let Some(block) = self.labels.get(name) {
+            return *block;
+        }
+        let block = self.block(Vec::new());
+        self.labels.insert(name.to_string(), block);
+
+        block
+    }
+
+    fn stmt(&mut self, stmt: Stmt) {
+        match stmt {
+            Stmt::Declaration(declarators) => {
+                for declarator in declarators {
+                    let entry = declarator.entry.borrow();
+                    let name = lexeme(&declarator.name);
+                    let line = match &declarator.init {
+                        Some(Init::Scalar(init)) => format!("{} {} = {}", entry.qtype, name, expr(init)),
+                        Some(Init::Aggr(inits)) => {
+                            let inits: Vec<String> = inits.iter().map(|(init, _)| expr(init)).collect();
+                            format!("{} {} = {{{}}}", entry.qtype, name, inits.join(", "))
+                        }
+                        None => format!("{} {}", entry.qtype, name),
+                    };
+                    drop(entry);
+                    self.emit(line);
+                }
+            }
+            Stmt::Expr(e) => self.emit(typed(&e)),
+            Stmt::Block(body) => body.into_iter().for_each(|stmt| self.stmt(stmt)),
+            Stmt::If(cond, then_branch, else_branch) => {
+                self.emit(format!("if {}", typed(&cond)));
+                let cond = self.current.take().unwrap();
+                let join = self.block(Vec::new());
+
+                let then_block = self.block(Vec::new());
+                self.edge(cond, then_block, Some("true"));
+                self.current = Some(then_block);
+                self.stmt(*then_branch);
+                self.jump(join, None);
+
+                if let Some(else_branch) = else_branch {
+                    let else_block = self.block(Vec::new());
+                    self.edge(cond, else_block, Some("false"));
+                    self.current = Some(else_block);
+                    self.stmt(*else_branch);
+                    self.jump(join, None);
+                } else {
+                    self.edge(cond, join, Some("false"));
+                }
+                self.current = Some(join);
+            }
+            Stmt::While(cond, body) => {
+                let head = self.block(vec![format!("while {}", typed(&cond))]);
+                let body_block = self.block(Vec::new());
+                let exit = self.block(Vec::new());
+
+                self.start(head);
+                self.jump(body_block, Some("true"));
+                self.edge(head, exit, Some("false"));
+
+                self.current = Some(body_block);
+                self.loop_body(*body, exit, head);
+                self.jump(head, None);
+                self.current = Some(exit);
+            }
+            Stmt::Do(body, cond) => {
+                let body_block = self.block(Vec::new());
+                let cond_block = self.block(vec![format!("do-while {}", typed(&cond))]);
+                let exit = self.block(Vec::new());
+
+                self.start(body_block);
+                self.loop_body(*body, exit, cond_block);
+                self.start(cond_block);
+                self.jump(body_block, Some("true"));
+                self.edge(cond_block, exit, Some("false"));
+                self.current = Some(exit);
+            }
+            Stmt::For(init, cond, inc, body) => {
+                if let Some(init) = init {
+                    self.stmt(*init);
+                }
+                let head = self.block(vec![match &cond {
+                    Some(cond) => format!("for {}", typed(cond)),
+                    None => "for (;;)".to_string(),
+                }]);
+                let body_block = self.block(Vec::new());
+                let inc_block = self.block(inc.iter().map(typed).collect());
+                let exit = self.block(Vec::new());
+
+                self.start(head);
+                self.jump(body_block, Some("true"));
+                if cond.is_some() {
+                    self.edge(head, exit, Some("false"));
+                }
+
+                self.current = Some(body_block);
+                self.loop_body(*body, exit, inc_block);
+                self.start(inc_block);
+                self.jump(head, None);
+                self.current = Some(exit);
+            }
+            Stmt::Return(value) => {
+                self.emit(match value {
+                    Some(value) => format!("return {}", typed(&value)),
+                    None => "return".to_string(),
+                });
+                self.jump(self.exit, None);
+            }
+            Stmt::Break => {
+                self.emit("break".to_string());
+                self.jump(*self.breaks.last().unwrap(), None);
+            }
+            Stmt::Continue => {
+                self.emit("continue".to_string());
+                self.jump(*self.continues.last().unwrap(), None);
+            }
+            Stmt::Switch(cond, body) => {
+                self.emit(format!("switch {}", typed(&cond)));
+                let head = self.current.take().unwrap();
+                let exit = self.block(Vec::new());
+
+                let cases: VecDeque<CaseKind> = self.switches.pop_front().unwrap().take().into();
+                let has_default = cases.contains(&CaseKind::Default);
+
+                self.switch_heads.push((head, cases));
+                self.breaks.push(exit);
+                self.stmt(*body);
+                self.jump(exit, None);
+                self.breaks.pop();
+                self.switch_heads.pop();
+
+                if !has_default {
+                    self.edge(head, exit, Some("default"));
+                }
+                self.current = Some(exit);
+            }
+            Stmt::Case(body) | Stmt::Default(body) => {
+                let (head, cases) = self.switch_heads.last_mut().unwrap();
+                let head = *head;
+                let label = match cases.pop_front() {
+                    Some(CaseKind::Case(value)) => format!("case {}", value.to_string()),
+                    _ => "default".to_string(),
+                };
+
+                let case_block = self.block(Vec::new());
+                self.start(case_block);
+                self.edge(head, case_block, Some(&label));
+                self.stmt(*body);
+            }
+            Stmt::Goto(label) => {
+                self.emit(format!("goto {}", label));
+                let target = self.label(&label);
+                self.jump(target, None);
+            }
+            Stmt::Label(name, body) => {
+                let block = self.label(&name);
+                self.start(block);
+                self.emit(format!("{}:", name));
+                self.stmt(*body);
+            }
+            Stmt::Located(_, body) => self.stmt(*body),
+        }
+    }
+    fn loop_body(&mut self, body: Stmt, break_target: usize, continue_target: usize) {
+        self.breaks.push(break_target);
+        self.continues.push(continue_target);
+        self.stmt(body);
+        self.breaks.pop();
+        self.continues.pop();
+    }
+
+    // blocks are only written once all their statements are known
+    fn finish(self) {
+        let mut blocks: Vec<(usize, Vec<String>)> = self.lines.into_iter().collect();
+        blocks.sort_by_key(|(id, _)| *id);
+
+        for (id, lines) in blocks {
+            let label: String = lines.iter().map(|line| escape_line(line) + "\\l").collect();
+            writeln!(self.graph.output, "        n{} [label=\"{}\"];", id, label).unwrap();
+        }
+        for (from, to, label) in self.edges {
+            match label {
+                Some(label) => writeln!(
+                    self.graph.output,
+                    "        n{} -> n{} [label={}];",
+                    from,
+                    to,
+                    escape(&label)
+                ),
+                None => writeln!(self.graph.output, "        n{} -> n{};", from, to),
+            }
+            .unwrap()
+        }
+    }
+}
+
+// an expression followed by its type
+fn typed(e: &Expr) -> String {
+    format!("{} : {}", expr(e), e.qtype)
+}
+
+fn expr(e: &Expr) -> String {
+    match &e.kind {
+        ExprKind::Binary { left, token, right }
+        | ExprKind::Logical { left, token, right }
+        | ExprKind::Comparison { left, token, right } => {
+            format!("({} {} {})", expr(left), lexeme(token), expr(right))
+        }
+        ExprKind::Unary { token, right } => format!("{}{}", lexeme(token), expr(right)),
+        ExprKind::Assign { l_expr, r_expr } => format!("{} = {}", expr(l_expr), expr(r_expr)),
+        ExprKind::CompoundAssign { expr: inner, .. } => compound_assign(inner).unwrap_or_else(|| expr(inner)),
+        ExprKind::Call { caller, args } => {
+            let args: Vec<String> = args.iter().map(expr).collect();
+            format!("{}({})", expr(caller), args.join(", "))
+        }
+        ExprKind::Cast { expr: inner, .. } => format!("({}){}", e.qtype, expr(inner)),
+        ExprKind::Scale {
+            by_amount,
+            direction,
+            expr: inner,
+            ..
+        } => {
+            let op = match direction {
+                ScaleDirection::Up => '*',
+                ScaleDirection::Down => '/',
+            };
+            format!("({} {} {})", expr(inner), op, by_amount)
+        }
+        ExprKind::MemberAccess { member, expr: inner } => format!("{}.{}", expr(inner), member),
+        ExprKind::Ternary {
+            cond,
+            true_expr,
+            false_expr,
+        } => {
+            format!("({} ? {} : {})", expr(cond), expr(true_expr), expr(false_expr))
+        }
+        ExprKind::Comma { left, right } => format!("({}, {})", expr(left), expr(right)),
+        ExprKind::String(s) => format!("{:?}", s),
+        ExprKind::Literal(literal) => literal.to_string(),
+        ExprKind::Ident(symbol) => lexeme(&symbol.borrow().token),
+        ExprKind::Nop => String::new(),
+    }
+}
+
+// `A op= B` is desugared into `tmp = &A, *tmp = *tmp op B` by the typechecker, which is turned back
+// into its source-form
+fn compound_assign(e: &Expr) -> Option<String> {
+    let ExprKind::Comma { left, right } = &e.kind else {
+        return None;
+    };
+    let (ExprKind::Assign { r_expr: address, .. }, ExprKind::Assign { r_expr: value, .. }) =
+        (&left.kind, &right.kind)
+    else {
+        return None;
+    };
+    let ExprKind::Unary { right: target, .. } = &uncast(address).kind else {
+        return None;
+    };
+    let ExprKind::Binary {
+        token,
+        right: operand,
+        ..
+    } = &uncast(value).kind
+    else {
+        return None;
+    };
+
+    Some(format!("({} {}= {})", expr(target), lexeme(token), expr(operand)))
+}
+fn uncast(mut e: &Expr) -> &Expr {
+    while let ExprKind::Cast { expr, .. } = &e.kind {
+        e = expr;
+    }
+    e
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CompileOptions;
+
+    fn setup(input: &str) -> Session {
+        let mut session = Session::new(CompileOptions::new());
+        session.add_file("main.c", input);
+        session
+    }
+
+    #[test]
+    fn typed_ast() {
+        let session = setup("int f(char c) { return c + 1; }");
+        let actual = ast(&session, Path::new("main.c")).unwrap();
+
+        assert!(actual.starts_with("digraph ast {\n"));
+        assert!(actual.contains("n0 [label=\"TranslationUnit\\l\"];"));
+        assert!(actual.contains("[label=\"Function\\lname: f : int (char)\\l\"];"));
+        assert!(actual.contains("[label=\"Ident\\lname: c : char\\l\"];"));
+        assert!(actual.contains("[label=\"Binary\\lop: +\\l\"];"));
+        assert!(actual.contains("[label=\"body\"];"));
+    }
+
+    #[test]
+    fn control_flow() {
+        let session = setup(
+            "int f(int n) {
+               int sum = 0;
+               while (n) {
+                 if (n == 3) break;
+                 sum += n--;
+               }
+               switch (sum) {
+                 case 1: return 1;
+                 default: goto end;
+               }
+             end:
+               return sum;
+             }",
+        );
+        let actual = cfg(&session, Path::new("main.c")).unwrap();
+
+        let edges = |label: &str| actual.matches(&format!("[label=\"{}\"];", label)).count();
+        assert_eq!(edges("true"), 2);
+        assert_eq!(edges("false"), 2);
+        assert_eq!(edges("case 1"), 1);
+        assert_eq!(edges("default"), 1);
+
+        assert!(actual.contains("subgraph cluster_f {\n        label=\"f : int (int)\";"));
+        assert!(actual.contains("[label=\"f(int n)\\lint sum = 0\\l\"];"));
+        assert!(actual.contains("if (n == 3) : int\\l"));
+        assert!(actual.contains("end:\\lreturn sum : int\\l"));
+        assert!(actual.contains("(sum += ((n -= 1) + 1)) : int\\l"));
+    }
+}
@@ -0,0 +1,1300 @@
+//! Serializes the output of every stage of the pipeline, so that its intermediate representations
+//! can be inspected when learning about or debugging the compiler.<br>
+//! The `text` format is the natural textual form of a stage, while `json` and `sexp` serialize
+//! every token and syntax-element with all of its fields, including the resolved types of the MIR.
+
+use crate::compiler::codegen::lir::Lir;
+use crate::compiler::codegen::register::*;
+use crate::compiler::common::{error::*, token, types::*};
+use crate::compiler::parser::hir;
+use crate::compiler::typechecker::mir;
+use crate::preprocessor::PPToken;
+use crate::Session;
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// Stage of the pipeline whose output can be dumped using `--dump=<stage>`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stage {
+    Tokens,
+    Pp,
+    Hir,
+    Mir,
+    Lir,
+    Asm,
+}
+impl Stage {
+    pub fn from_name(name: &str) -> Option<Stage> {
+        match name {
+            "tokens" => Some(Stage::Tokens),
+            "pp" => Some(Stage::Pp),
+            "hir" => Some(Stage::Hir),
+            "mir" => Some(Stage::Mir),
+            "lir" => Some(Stage::Lir),
+            "asm" => Some(Stage::Asm),
+            _ => None,
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::Tokens => "tokens",
+            Stage::Pp => "pp",
+            Stage::Hir => "hir",
+            Stage::Mir => "mir",
+            Stage::Lir => "lir",
+            Stage::Asm => "asm",
+        }
+    }
+}
+
+/// Format of a dumped stage, set using `--dump-format=<format>`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+    Sexp,
+}
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "text" => Some(Format::Text),
+            "json" => Some(Format::Json),
+            "sexp" => Some(Format::Sexp),
+            _ => None,
+        }
+    }
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Text => "txt",
+            Format::Json => "json",
+            Format::Sexp => "sexp",
+        }
+    }
+}
+
+/// The file a stage is dumped into, eg: `main.c` => `main.mir.json`
+pub fn output_path(file: &Path, stage: Stage, format: Format) -> PathBuf {
+    file.with_extension(format!("{}.{}", stage.name(), format.extension()))
+}
+
+/// Runs the pipeline up to and including `stage` and serializes its output
+pub fn dump(session: &Session, file: &Path, stage: Stage, format: Format) -> Result<String, WreccError> {
+    let nodes: Vec<Node> = match (stage, format) {
+        (Stage::Tokens, Format::Text) => {
+            return Ok(session
+                .tokens(file)?
+                .iter()
+                .map(|t| {
+                    format!(
+                        "{}:{} {} {}\n",
+                        t.line_index,
+                        t.column,
+                        variant_name(&t.kind),
+                        lexeme(t)
+                    )
+                })
+                .collect())
+        }
+        (Stage::Pp, Format::Text) => {
+            return Ok(session
+                .preprocess(file)?
+                .iter()
+                .map(|t| t.kind.to_string())
+                .collect());
+        }
+        (Stage::Hir, Format::Text) => {
+            return Ok(session
+                .hir(file)?
+                .iter()
+                .map(|decl| format!("{}\n", decl))
+                .collect());
+        }
+        (Stage::Lir, Format::Text) => {
+            return Ok(session
+                .unallocated_lir(file)?
+                .into_iter()
+                .map(|instr| lir_line(instr) + "\n")
+                .collect())
+        }
+        (Stage::Asm, Format::Text) => return session.assembly(file),
+
+        (Stage::Tokens, _) => session.tokens(file)?.iter().map(Dump::dump).collect(),
+        (Stage::Pp, _) => session.preprocess(file)?.iter().map(Dump::dump).collect(),
+        (Stage::Hir, _) => session.hir(file)?.iter().map(Dump::dump).collect(),
+        (Stage::Mir, _) => session.mir(file)?.0.iter().map(Dump::dump).collect(),
+        (Stage::Lir, _) => session.unallocated_lir(file)?.iter().map(Dump::dump).collect(),
+        (Stage::Asm, _) => session
+            .assembly(file)?
+            .lines()
+            .map(|line| Node::Str(line.to_string()))
+            .collect(),
+    };
+    let nodes = Node::List(nodes);
+
+    Ok(match format {
+        Format::Text => nodes.text(),
+        Format::Json => nodes.json(),
+        Format::Sexp => nodes.sexp(),
+    })
+}
+
+// saving the caller-saved registers around a call is only a placeholder until register-allocation
+// knows which registers are in use, so it is written as a pseudo-instruction
+fn lir_line(instr: Lir) -> String {
+    match instr {
+        Lir::SaveRegs => "\tsave-regs".to_string(),
+        Lir::RestoreRegs => "\trestore-regs".to_string(),
+        instr => instr.as_string(),
+    }
+}
+
+/// Serialized value of a token or syntax-element
+#[derive(Clone, Debug)]
+pub enum Node {
+    Null,
+    Bool(bool),
+    Int(i128),
+    Str(String),
+    /// Name of a symbol, serialized as a string but keeping its location so that its type can be
+    /// looked up in a [SymbolIndex](crate::compiler::common::environment::SymbolIndex)
+    Ident(token::Token),
+    List(Vec<Node>),
+    /// Named element and its fields
+    Record(&'static str, Vec<(&'static str, Node)>),
+}
+impl Node {
+    fn is_leaf(&self) -> bool {
+        match self {
+            Node::List(nodes) => nodes.is_empty(),
+            Node::Record(..) => false,
+            _ => true,
+        }
+    }
+    // nodes are printed on a single line if they don't contain other nested nodes
+    fn is_flat(&self) -> bool {
+        match self {
+            Node::List(nodes) => nodes.iter().all(Node::is_leaf),
+            Node::Record(_, fields) => fields.iter().all(|(_, n)| n.is_leaf()),
+            _ => true,
+        }
+    }
+    fn scalar(&self) -> String {
+        match self {
+            Node::Null => "null".to_string(),
+            Node::Bool(b) => b.to_string(),
+            Node::Int(n) => n.to_string(),
+            Node::Str(s) => quote(s),
+            Node::Ident(token) => quote(&lexeme(token)),
+            Node::List(nodes) if nodes.is_empty() => "[]".to_string(),
+            Node::List(_) | Node::Record(..) => unreachable!("not a scalar"),
+        }
+    }
+
+    pub fn json(&self) -> String {
+        let mut output = String::new();
+        self.write_json(&mut output, 0);
+        output + "\n"
+    }
+    fn write_json(&self, output: &mut String, indent: usize) {
+        let (open, close, members) = match self {
+            Node::List(nodes) if !nodes.is_empty() => {
+                ('[', ']', nodes.iter().map(|n| (None, n.clone())).collect())
+            }
+            // the name of a record is written as its first member
+            Node::Record(name, fields) => (
+                '{',
+                '}',
+                std::iter::once((Some("kind"), Node::Str(name.to_string())))
+                    .chain(fields.iter().map(|(key, n)| (Some(*key), n.clone())))
+                    .collect::<Vec<_>>(),
+            ),
+            _ => return output.push_str(&self.scalar()),
+        };
+        let is_flat = self.is_flat();
+
+        output.push(open);
+        for (i, (key, node)) in members.into_iter().enumerate() {
+            if is_flat {
+                output.push_str(if i > 0 { ", " } else { "" });
+            } else {
+                write!(output, "{}\n{}", if i > 0 { "," } else { "" }, pad(indent + 1)).unwrap();
+            }
+            if let Some(key) = key {
+                write!(output, "{}: ", quote(key)).unwrap();
+            }
+            node.write_json(output, indent + 1);
+        }
+        if !is_flat {
+            write!(output, "\n{}", pad(indent)).unwrap();
+        }
+        output.push(close);
+    }
+
+    pub fn sexp(&self) -> String {
+        let mut output = String::new();
+        self.write_sexp(&mut output, 0);
+        output + "\n"
+    }
+    // unlike the other formats s-expressions are aligned by columns instead of indentation-levels,
+    // they follow the scheme conventions of `()` for null and `#t`/`#f` for booleans
+    fn write_sexp(&self, output: &mut String, column: usize) {
+        match self {
+            Node::Null => output.push_str("()"),
+            Node::Bool(true) => output.push_str("#t"),
+            Node::Bool(false) => output.push_str("#f"),
+            Node::List(nodes) => {
+                output.push('(');
+                for (i, node) in nodes.iter().enumerate() {
+                    if i > 0 {
+                        if self.is_flat() {
+                            output.push(' ');
+                        } else {
+                            write!(output, "\n{}", " ".repeat(column + 1)).unwrap();
+                        }
+                    }
+                    node.write_sexp(output, column + 1);
+                }
+                output.push(')');
+            }
+            Node::Record(name, fields) => {
+                write!(output, "({}", name).unwrap();
+                for (key, node) in fields {
+                    if self.is_flat() {
+                        output.push(' ');
+                    } else {
+                        write!(output, "\n{}", " ".repeat(column + 2)).unwrap();
+                    }
+                    write!(output, ":{} ", key).unwrap();
+                    node.write_sexp(output, column + key.len() + 4);
+                }
+                output.push(')');
+            }
+            _ => output.push_str(&self.scalar()),
+        }
+    }
+
+    /// Indented tree of the nodes, used for the `text` format of the MIR
+    pub fn text(&self) -> String {
+        let mut output = String::new();
+        match self {
+            Node::List(nodes) => nodes.iter().for_each(|n| {
+                n.write_text(&mut output, 0);
+                output.push('\n');
+            }),
+            _ => {
+                self.write_text(&mut output, 0);
+                output.push('\n');
+            }
+        }
+        output
+    }
+    fn write_text(&self, output: &mut String, indent: usize) {
+        match self {
+            Node::Record(name, fields) => {
+                output.push_str(name);
+                for (key, node) in fields {
+                    write!(output, "\n{}{}:", pad(indent + 1), key).unwrap();
+                    if !matches!(node, Node::List(nodes) if !nodes.is_empty()) {
+                        output.push(' ');
+                    }
+                    node.write_text(output, indent + 1);
+                }
+            }
+            Node::List(nodes) if nodes.is_empty() => output.push_str("[]"),
+            Node::List(nodes) => {
+                for node in nodes {
+                    write!(output, "\n{}- ", pad(indent + 1)).unwrap();
+                    node.write_text(output, indent + 2);
+                }
+            }
+            _ => output.push_str(&self.scalar()),
+        }
+    }
+}
+
+fn pad(indent: usize) -> String {
+    "  ".repeat(indent)
+}
+pub(crate) fn quote(s: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(quoted, "\\u{:04x}", c as u32).unwrap(),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Converts a token or syntax-element into a [Node]
+pub trait Dump {
+    fn dump(&self) -> Node;
+}
+impl<T: Dump> Dump for Vec<T> {
+    fn dump(&self) -> Node {
+        Node::List(self.iter().map(Dump::dump).collect())
+    }
+}
+impl<T: Dump> Dump for Option<T> {
+    fn dump(&self) -> Node {
+        self.as_ref().map_or(Node::Null, Dump::dump)
+    }
+}
+impl<T: Dump> Dump for Box<T> {
+    fn dump(&self) -> Node {
+        self.as_ref().dump()
+    }
+}
+impl Dump for QualType {
+    fn dump(&self) -> Node {
+        Node::Str(self.to_string())
+    }
+}
+
+// the variant-name of a token without its payload, eg: `Ident("x")` => `Ident`
+fn variant_name(kind: &impl std::fmt::Debug) -> String {
+    let debug = format!("{:?}", kind);
+    debug.split('(').next().unwrap_or_default().to_string()
+}
+// the source-text of a token
+pub(crate) fn lexeme(token: &token::Token) -> String {
+    match &token.kind {
+        token::TokenKind::Ident(s) | token::TokenKind::String(s) => s.clone(),
+        token::TokenKind::CharLit(c) => c.to_string(),
+        token::TokenKind::Number(n, ..) => n.to_string(),
+        kind => kind.to_string().trim_matches('\'').to_string(),
+    }
+}
+fn lexeme_node(token: &token::Token) -> Node {
+    Node::Str(lexeme(token))
+}
+
+impl Dump for token::Token {
+    fn dump(&self) -> Node {
+        Node::Record(
+            "Token",
+            vec![
+                ("kind", Node::Str(variant_name(&self.kind))),
+                ("text", lexeme_node(self)),
+                ("line", Node::Int(self.line_index.into())),
+                ("column", Node::Int(self.column.into())),
+            ],
+        )
+    }
+}
+impl Dump for PPToken {
+    fn dump(&self) -> Node {
+        Node::Record(
+            "PPToken",
+            vec![
+                ("kind", Node::Str(variant_name(&self.kind))),
+                ("text", Node::Str(self.kind.to_string())),
+                ("line", Node::Int(self.line.into())),
+                ("column", Node::Int(self.column.into())),
+            ],
+        )
+    }
+}
+
+impl Dump for hir::decl::ExternalDeclaration {
+    fn dump(&self) -> Node {
+        match self {
+            hir::decl::ExternalDeclaration::Declaration(decl) => decl.dump(),
+            hir::decl::ExternalDeclaration::Function(func, body) => Node::Record(
+                "Function",
+                vec![
+                    ("decl_specs", func.decl_specs.dump()),
+                    ("name", Node::Ident(func.name.clone())),
+                    ("modifiers", func.modifiers.dump()),
+                    ("body", body.dump()),
+                ],
+            ),
+        }
+    }
+}
+impl Dump for hir::decl::Declaration {
+    fn dump(&self) -> Node {
+        let declarators = self
+            .declarators
+            .iter()
+            .map(|(declarator, init)| {
+                Node::Record(
+                    "InitDeclarator",
+                    vec![("declarator", declarator.dump()), ("init", init.dump())],
+                )
+            })
+            .collect();
+
+        Node::Record(
+            "Declaration",
+            vec![
+                ("decl_specs", self.decl_specs.dump()),
+                ("declarators", Node::List(declarators)),
+            ],
+        )
+    }
+}
+impl Dump for hir::decl::DeclSpecs {
+    fn dump(&self) -> Node {
+        Node::Record(
+            "DeclSpecs",
+            vec![
+                (
+                    "storage_classes",
+                    Node::List(
+                        self.storage_classes
+                            .iter()
+                            .map(|s| lexeme_node(&s.token))
+                            .collect(),
+                    ),
+                ),
+                ("qualifiers", self.qualifiers.dump()),
+                ("specifiers", self.specifiers.dump()),
+                ("is_inline", Node::Bool(self.is_inline)),
+            ],
+        )
+    }
+}
+impl Dump for hir::decl::Qualifier {
+    fn dump(&self) -> Node {
+        lexeme_node(&self.token)
+    }
+}
+impl Dump for hir::decl::DeclType {
+    fn dump(&self) -> Node {
+        Node::Record(
+            "DeclType",
+            vec![
+                ("qualifiers", self.qualifiers.dump()),
+                ("specifiers", self.specifiers.dump()),
+                ("modifiers", self.modifiers.dump()),
+            ],
+        )
+    }
+}
+impl Dump for hir::decl::Specifier {
+    fn dump(&self) -> Node {
+        use hir::decl::SpecifierKind;
+
+        let name = |name: &Option<token::Token>| name.as_ref().map_or(Node::Null, lexeme_node);
+        match &self.kind {
+            SpecifierKind::Struct(tag, members) => {
+                Node::Record("Struct", vec![("tag", name(tag)), ("members", members.dump())])
+            }
+            SpecifierKind::Union(tag, members) => {
+                Node::Record("Union", vec![("tag", name(tag)), ("members", members.dump())])
+            }
+            SpecifierKind::Enum(tag, constants) => {
+                let constants = constants.as_ref().map_or(Node::Null, |constants| {
+                    Node::List(
+                        constants
+                            .iter()
+                            .map(|(constant, value)| {
+                                Node::Record(
+                                    "EnumConstant",
+                                    vec![("name", Node::Ident(constant.clone())), ("value", value.dump())],
+                                )
+                            })
+                            .collect(),
+                    )
+                });
+                Node::Record("Enum", vec![("tag", name(tag)), ("constants", constants)])
+            }
+            SpecifierKind::UserType => Node::Record("Typedef", vec![("name", lexeme_node(&self.token))]),
+            _ => lexeme_node(&self.token),
+        }
+    }
+}
+impl Dump for hir::decl::MemberDecl {
+    fn dump(&self) -> Node {
+        let declarators = self
+            .declarators
+            .iter()
+            .map(|declarator| {
+                Node::Record(
+                    "MemberDeclarator",
+                    vec![
+                        ("name", lexeme_node(&declarator.name)),
+                        ("modifiers", declarator.modifiers.dump()),
+                    ],
+                )
+            })
+            .collect();
+
+        Node::Record(
+            "MemberDecl",
+            vec![
+                ("qualifiers", self.qualifiers.dump()),
+                ("specifiers", self.specifiers.dump()),
+                ("declarators", Node::List(declarators)),
+            ],
+        )
+    }
+}
+impl Dump for hir::decl::DeclModifier {
+    fn dump(&self) -> Node {
+        match self {
+            hir::decl::DeclModifier::Pointer(qualifiers) => {
+                Node::Record("Pointer", vec![("qualifiers", qualifiers.dump())])
+            }
+            hir::decl::DeclModifier::Array(_, size) => Node::Record("Array", vec![("size", size.dump())]),
+            hir::decl::DeclModifier::Function { params, variadic, .. } => Node::Record(
+                "Function",
+                vec![("params", params.dump()), ("variadic", Node::Bool(*variadic))],
+            ),
+        }
+    }
+}
+impl Dump for hir::decl::ParamDecl {
+    fn dump(&self) -> Node {
+        Node::Record(
+            "Param",
+            vec![
+                ("decl_specs", self.decl_specs.dump()),
+                ("declarator", self.declarator.dump()),
+            ],
+        )
+    }
+}
+impl Dump for hir::decl::Declarator {
+    fn dump(&self) -> Node {
+        Node::Record(
+            "Declarator",
+            vec![
+                ("name", self.name.clone().map_or(Node::Null, Node::Ident)),
+                ("modifiers", self.modifiers.dump()),
+            ],
+        )
+    }
+}
+impl Dump for hir::decl::Init {
+    fn dump(&self) -> Node {
+        let designator = self.designator.as_ref().map_or(Node::Null, |designator| {
+            Node::List(
+                designator
+                    .iter()
+                    .map(|d| match &d.kind {
+                        hir::decl::DesignatorKind::Array(index) => {
+                            Node::Record("Index", vec![("index", index.dump())])
+                        }
+                        hir::decl::DesignatorKind::Member(member) => {
+                            Node::Record("Member", vec![("member", Node::Str(member.clone()))])
+                        }
+                    })
+                    .collect(),
+            )
+        });
+        let value = match &self.kind {
+            hir::decl::InitKind::Scalar(expr) => expr.dump(),
+            hir::decl::InitKind::Aggr(inits) => inits.dump(),
+        };
+
+        Node::Record("Init", vec![("designator", designator), ("value", value)])
+    }
+}
+
+impl Dump for hir::expr::ExprKind {
+    fn dump(&self) -> Node {
+        use hir::expr::ExprKind;
+
+        match self {
+            ExprKind::Binary { left, token, right } => Node::Record(
+                "Binary",
+                vec![
+                    ("op", lexeme_node(token)),
+                    ("left", left.dump()),
+                    ("right", right.dump()),
+                ],
+            ),
+            ExprKind::Logical { left, token, right } => Node::Record(
+                "Logical",
+                vec![
+                    ("op", lexeme_node(token)),
+                    ("left", left.dump()),
+                    ("right", right.dump()),
+                ],
+            ),
+            ExprKind::Comparison { left, token, right } => Node::Record(
+                "Comparison",
+                vec![
+                    ("op", lexeme_node(token)),
+                    ("left", left.dump()),
+                    ("right", right.dump()),
+                ],
+            ),
+            ExprKind::Unary { token, right } => {
+                Node::Record("Unary", vec![("op", lexeme_node(token)), ("expr", right.dump())])
+            }
+            ExprKind::PostUnary { token, left } => Node::Record(
+                "PostUnary",
+                vec![("op", lexeme_node(token)), ("expr", left.dump())],
+            ),
+            ExprKind::Assign { l_expr, r_expr, .. } => {
+                Node::Record("Assign", vec![("left", l_expr.dump()), ("right", r_expr.dump())])
+            }
+            ExprKind::CompoundAssign {
+                l_expr,
+                token,
+                r_expr,
+            } => Node::Record(
+                "CompoundAssign",
+                vec![
+                    ("op", lexeme_node(token)),
+                    ("left", l_expr.dump()),
+                    ("right", r_expr.dump()),
+                ],
+            ),
+            ExprKind::Call { caller, args, .. } => {
+                Node::Record("Call", vec![("caller", caller.dump()), ("args", args.dump())])
+            }
+            ExprKind::Cast { decl_type, expr, .. } => {
+                Node::Record("Cast", vec![("type", decl_type.dump()), ("expr", expr.dump())])
+            }
+            ExprKind::MemberAccess { token, member, expr } => Node::Record(
+                "MemberAccess",
+                vec![
+                    ("op", lexeme_node(token)),
+                    ("member", lexeme_node(member)),
+                    ("expr", expr.dump()),
+                ],
+            ),
+            ExprKind::Ternary {
+                cond,
+                true_expr,
+                false_expr,
+                ..
+            } => Node::Record(
+                "Ternary",
+                vec![
+                    ("cond", cond.dump()),
+                    ("true_expr", true_expr.dump()),
+                    ("false_expr", false_expr.dump()),
+                ],
+            ),
+            ExprKind::Comma { left, right } => {
+                Node::Record("Comma", vec![("left", left.dump()), ("right", right.dump())])
+            }
+            ExprKind::SizeofType { decl_type, .. } => {
+                Node::Record("SizeofType", vec![("type", decl_type.dump())])
+            }
+            ExprKind::SizeofExpr { expr, .. } => Node::Record("SizeofExpr", vec![("expr", expr.dump())]),
+            ExprKind::String(token) => Node::Record("String", vec![("value", lexeme_node(token))]),
+            ExprKind::Char(c) => Node::Record("Char", vec![("value", Node::Str(c.to_string()))]),
+            ExprKind::Number(n, radix, suffix) => Node::Record(
+                "Number",
+                vec![
+                    ("value", Node::Int((*n).into())),
+                    ("radix", Node::Str(radix.to_string().to_string())),
+                    (
+                        "suffix",
+                        suffix
+                            .as_ref()
+                            .map_or(Node::Null, |s| Node::Str(format!("{:?}", s))),
+                    ),
+                ],
+            ),
+            ExprKind::Ident(token) => Node::Record("Ident", vec![("name", Node::Ident(token.clone()))]),
+            ExprKind::Nop => Node::Record("Nop", vec![]),
+        }
+    }
+}
+
+impl Dump for hir::stmt::Stmt {
+    fn dump(&self) -> Node {
+        use hir::stmt::Stmt;
+
+        match self {
+            Stmt::Declaration(decl) => decl.dump(),
+            Stmt::Expr(expr) => Node::Record("Expr", vec![("expr", expr.dump())]),
+            Stmt::Block(body) => Node::Record("Block", vec![("body", body.dump())]),
+            Stmt::If(_, cond, then, otherwise) => Node::Record(
+                "If",
+                vec![
+                    ("cond", cond.dump()),
+                    ("then", then.dump()),
+                    ("else", otherwise.dump()),
+                ],
+            ),
+            Stmt::While(_, cond, body) => {
+                Node::Record("While", vec![("cond", cond.dump()), ("body", body.dump())])
+            }
+            Stmt::Do(_, body, cond) => Node::Record("Do", vec![("body", body.dump()), ("cond", cond.dump())]),
+            Stmt::For(_, init, cond, inc, body) => Node::Record(
+                "For",
+                vec![
+                    ("init", init.dump()),
+                    ("cond", cond.dump()),
+                    ("inc", inc.dump()),
+                    ("body", body.dump()),
+                ],
+            ),
+            Stmt::Return(_, expr) => Node::Record("Return", vec![("expr", expr.dump())]),
+            Stmt::Break(_) => Node::Record("Break", vec![]),
+            Stmt::Continue(_) => Node::Record("Continue", vec![]),
+            Stmt::Switch(_, cond, body) => {
+                Node::Record("Switch", vec![("cond", cond.dump()), ("body", body.dump())])
+            }
+            Stmt::Case(_, value, body) => {
+                Node::Record("Case", vec![("value", value.dump()), ("body", body.dump())])
+            }
+            Stmt::Default(_, body) => Node::Record("Default", vec![("body", body.dump())]),
+            Stmt::Goto(label) => Node::Record("Goto", vec![("label", lexeme_node(label))]),
+            Stmt::Label(label, body) => Node::Record(
+                "Label",
+                vec![("label", lexeme_node(label)), ("body", body.dump())],
+            ),
+        }
+    }
+}
+
+impl Dump for mir::decl::ExternalDeclaration {
+    fn dump(&self) -> Node {
+        match self {
+            mir::decl::ExternalDeclaration::Declaration(declarators) => {
+                Node::Record("Declaration", vec![("declarators", declarators.dump())])
+            }
+            mir::decl::ExternalDeclaration::Function(func, symbol, body) => {
+                let params = func
+                    .params
+                    .iter()
+                    .map(|param| {
+                        let param = param.borrow();
+                        Node::Record(
+                            "Param",
+                            vec![("name", lexeme_node(&param.token)), ("type", param.qtype.dump())],
+                        )
+                    })
+                    .collect();
+
+                Node::Record(
+                    "Function",
+                    vec![
+                        ("name", Node::Str(func.name.clone())),
+                        ("type", symbol.borrow().qtype.dump()),
+                        ("params", Node::List(params)),
+                        ("variadic", Node::Bool(func.variadic)),
+                        ("is_inline", Node::Bool(func.is_inline)),
+                        ("stack_size", Node::Int(func.stack_size as i128)),
+                        ("body", body.dump()),
+                    ],
+                )
+            }
+        }
+    }
+}
+impl Dump for mir::decl::Declarator {
+    fn dump(&self) -> Node {
+        let entry = self.entry.borrow();
+        let init = match &self.init {
+            Some(mir::decl::Init::Scalar(expr)) => expr.dump(),
+            Some(mir::decl::Init::Aggr(elements)) => Node::List(
+                elements
+                    .iter()
+                    .map(|(expr, offset)| {
+                        Node::Record(
+                            "Element",
+                            vec![("offset", Node::Int(*offset as i128)), ("value", expr.dump())],
+                        )
+                    })
+                    .collect(),
+            ),
+            None => Node::Null,
+        };
+
+        Node::Record(
+            "Declarator",
+            vec![
+                ("name", lexeme_node(&self.name)),
+                ("type", entry.qtype.dump()),
+                (
+                    "storage_class",
+                    entry
+                        .storage_class
+                        .as_ref()
+                        .map_or(Node::Null, |s| Node::Str(s.to_string().to_string())),
+                ),
+                ("init", init),
+            ],
+        )
+    }
+}
+
+impl Dump for mir::expr::Expr {
+    fn dump(&self) -> Node {
+        use mir::expr::{CastDirection, ExprKind, ScaleDirection, ValueKind};
+
+        let (name, mut fields) = match &self.kind {
+            ExprKind::Binary { left, token, right } => (
+                "Binary",
+                vec![
+                    ("op", lexeme_node(token)),
+                    ("left", left.dump()),
+                    ("right", right.dump()),
+                ],
+            ),
+            ExprKind::Logical { left, token, right } => (
+                "Logical",
+                vec![
+                    ("op", lexeme_node(token)),
+                    ("left", left.dump()),
+                    ("right", right.dump()),
+                ],
+            ),
+            ExprKind::Comparison { left, token, right } => (
+                "Comparison",
+                vec![
+                    ("op", lexeme_node(token)),
+                    ("left", left.dump()),
+                    ("right", right.dump()),
+                ],
+            ),
+            ExprKind::Unary { token, right } => {
+                ("Unary", vec![("op", lexeme_node(token)), ("expr", right.dump())])
+            }
+            ExprKind::Assign { l_expr, r_expr } => {
+                ("Assign", vec![("left", l_expr.dump()), ("right", r_expr.dump())])
+            }
+            ExprKind::CompoundAssign { expr, .. } => ("CompoundAssign", vec![("expr", expr.dump())]),
+            ExprKind::Call { caller, args } => {
+                ("Call", vec![("caller", caller.dump()), ("args", args.dump())])
+            }
+            ExprKind::Cast { direction, expr, .. } => {
+                let direction = match direction {
+                    CastDirection::Up => "up",
+                    CastDirection::Down => "down",
+                    CastDirection::Equal => "equal",
+                };
+                (
+                    "Cast",
+                    vec![
+                        ("direction", Node::Str(direction.to_string())),
+                        ("expr", expr.dump()),
+                    ],
+                )
+            }
+            ExprKind::Scale {
+                token,
+                by_amount,
+                direction,
+                expr,
+            } => {
+                let direction = match direction {
+                    ScaleDirection::Up => "up",
+                    ScaleDirection::Down => "down",
+                };
+                (
+                    "Scale",
+                    vec![
+                        ("op", lexeme_node(token)),
+                        ("by_amount", Node::Int(*by_amount as i128)),
+                        ("direction", Node::Str(direction.to_string())),
+                        ("expr", expr.dump()),
+                    ],
+                )
+            }
+            ExprKind::MemberAccess { member, expr } => (
+                "MemberAccess",
+                vec![("member", Node::Str(member.clone())), ("expr", expr.dump())],
+            ),
+            ExprKind::Ternary {
+                cond,
+                true_expr,
+                false_expr,
+            } => (
+                "Ternary",
+                vec![
+                    ("cond", cond.dump()),
+                    ("true_expr", true_expr.dump()),
+                    ("false_expr", false_expr.dump()),
+                ],
+            ),
+            ExprKind::Comma { left, right } => {
+                ("Comma", vec![("left", left.dump()), ("right", right.dump())])
+            }
+            ExprKind::String(s) => ("String", vec![("value", Node::Str(s.clone()))]),
+            ExprKind::Literal(literal) => {
+                let value = match literal {
+                    LiteralKind::Signed(n) => (*n).into(),
+                    LiteralKind::Unsigned(n) => (*n).into(),
+                };
+                ("Literal", vec![("value", Node::Int(value))])
+            }
+            ExprKind::Ident(symbol) => ("Ident", vec![("name", lexeme_node(&symbol.borrow().token))]),
+            ExprKind::Nop => ("Nop", vec![]),
+        };
+        let value_kind = match self.value_kind {
+            ValueKind::Lvalue => "lvalue",
+            ValueKind::Rvalue => "rvalue",
+        };
+        fields.push(("type", self.qtype.dump()));
+        fields.push(("value_kind", Node::Str(value_kind.to_string())));
+
+        Node::Record(name, fields)
+    }
+}
+
+impl Dump for mir::stmt::Stmt {
+    fn dump(&self) -> Node {
+        use mir::stmt::Stmt;
+
+        match self {
+            Stmt::Declaration(declarators) => {
+                Node::Record("Declaration", vec![("declarators", declarators.dump())])
+            }
+            Stmt::Expr(expr) => Node::Record("Expr", vec![("expr", expr.dump())]),
+            Stmt::Block(body) => Node::Record("Block", vec![("body", body.dump())]),
+            Stmt::If(cond, then, otherwise) => Node::Record(
+                "If",
+                vec![
+                    ("cond", cond.dump()),
+                    ("then", then.dump()),
+                    ("else", otherwise.dump()),
+                ],
+            ),
+            Stmt::While(cond, body) => {
+                Node::Record("While", vec![("cond", cond.dump()), ("body", body.dump())])
+            }
+            Stmt::Do(body, cond) => Node::Record("Do", vec![("body", body.dump()), ("cond", cond.dump())]),
+            Stmt::For(init, cond, inc, body) => Node::Record(
+                "For",
+                vec![
+                    ("init", init.dump()),
+                    ("cond", cond.dump()),
+                    ("inc", inc.dump()),
+                    ("body", body.dump()),
+                ],
+            ),
+            Stmt::Return(expr) => Node::Record("Return", vec![("expr", expr.dump())]),
+            Stmt::Break => Node::Record("Break", vec![]),
+            Stmt::Continue => Node::Record("Continue", vec![]),
+            Stmt::Switch(cond, body) => {
+                Node::Record("Switch", vec![("cond", cond.dump()), ("body", body.dump())])
+            }
+            Stmt::Case(body) => Node::Record("Case", vec![("body", body.dump())]),
+            Stmt::Default(body) => Node::Record("Default", vec![("body", body.dump())]),
+            Stmt::Goto(label) => Node::Record("Goto", vec![("label", Node::Str(label.clone()))]),
+            Stmt::Label(label, body) => Node::Record(
+                "Label",
+                vec![("label", Node::Str(label.clone())), ("body", body.dump())],
+            ),
+            // the location is only used for debug-information
+            Stmt::Located(_, stmt) => stmt.dump(),
+        }
+    }
+}
+
+// operands are serialized with their virtual register before register allocation and with the
+// physical register they were assigned to afterwards
+impl Dump for Register {
+    fn dump(&self) -> Node {
+        match self {
+            Register::Temp(reg) => {
+                let location = match &reg.reg {
+                    Some(TempKind::Scratch(scratch)) => Node::Str(scratch.base_name().to_string()),
+                    Some(TempKind::Spilled(stack)) => Node::Str(stack.name()),
+                    Some(TempKind::Pushed(index)) => Node::Int(*index as i128),
+                    None => Node::Null,
+                };
+                let value_kind = match reg.value_kind {
+                    mir::expr::ValueKind::Lvalue => "lvalue",
+                    mir::expr::ValueKind::Rvalue => "rvalue",
+                };
+                Node::Record(
+                    "Temp",
+                    vec![
+                        ("id", Node::Int(reg.id as i128)),
+                        ("type", type_node(&reg.ty)),
+                        ("value_kind", Node::Str(value_kind.to_string())),
+                        ("reg", location),
+                    ],
+                )
+            }
+            Register::Stack(reg) => Node::Record(
+                "Stack",
+                vec![
+                    ("offset", Node::Int(reg.offset() as i128)),
+                    ("type", type_node(&self.get_type())),
+                ],
+            ),
+            Register::Label(reg) => Node::Record(
+                "Label",
+                vec![
+                    ("name", Node::Str(self.name())),
+                    ("type", type_node(&reg.get_type())),
+                ],
+            ),
+            Register::Arg(reg) => Node::Record(
+                "Arg",
+                vec![
+                    ("id", Node::Int(reg.id as i128)),
+                    ("type", type_node(&reg.ty)),
+                    ("reg", Node::Str(reg.reg.base_name().to_string())),
+                ],
+            ),
+            Register::Return(ty) => Node::Record(
+                "Return",
+                vec![("type", type_node(ty)), ("reg", Node::Str(ty.return_reg()))],
+            ),
+            Register::Literal(n, ty) => {
+                let value = match n.wrap(ty) {
+                    LiteralKind::Signed(n) => Node::Int(n.into()),
+                    LiteralKind::Unsigned(n) => Node::Int(n.into()),
+                };
+                Node::Record("Literal", vec![("value", value), ("type", type_node(ty))])
+            }
+            Register::Void => Node::Record("Void", vec![]),
+        }
+    }
+}
+fn type_node(ty: &Type) -> Node {
+    QualType::new(ty.clone()).dump()
+}
+
+impl Dump for Lir {
+    fn dump(&self) -> Node {
+        let label = |index: &usize| Node::Int(*index as i128);
+        let operands = |name, from: &Register, to: &Register| {
+            Node::Record(name, vec![("from", from.dump()), ("to", to.dump())])
+        };
+
+        match self {
+            Lir::GlobalDeclaration(name, is_aligned, is_static) => Node::Record(
+                "GlobalDeclaration",
+                vec![
+                    ("name", Node::Str(name.clone())),
+                    ("is_aligned", Node::Bool(*is_aligned)),
+                    ("is_static", Node::Bool(*is_static)),
+                ],
+            ),
+            Lir::GlobalInit(ty, value) => Node::Record(
+                "GlobalInit",
+                vec![("type", type_node(ty)), ("value", Node::Str(value.name()))],
+            ),
+            Lir::StringDeclaration(index, value) => Node::Record(
+                "StringDeclaration",
+                vec![("label", label(index)), ("value", Node::Str(value.clone()))],
+            ),
+            Lir::LabelDefinition(index) => {
+                Node::Record("LabelDefinition", vec![("label", label(index))])
+            }
+            Lir::Jmp(index) => Node::Record("Jmp", vec![("label", label(index))]),
+            Lir::JmpCond(cond, index) => Node::Record(
+                "JmpCond",
+                vec![("cond", Node::Str(cond.to_string())), ("label", label(index))],
+            ),
+            Lir::JmpTable(index, table, table_label) => Node::Record(
+                "JmpTable",
+                vec![
+                    ("index", index.dump()),
+                    ("table", table.dump()),
+                    ("label", label(table_label)),
+                ],
+            ),
+            Lir::JumpTableDeclaration(index, entries) => Node::Record(
+                "JumpTableDeclaration",
+                vec![
+                    ("label", label(index)),
+                    ("entries", Node::List(entries.iter().map(label).collect())),
+                ],
+            ),
+            Lir::Push(reg) => Node::Record("Push", vec![("reg", reg.dump())]),
+            Lir::Pop(reg) => Node::Record("Pop", vec![("reg", reg.dump())]),
+            Lir::Call(reg) => Node::Record("Call", vec![("reg", reg.dump())]),
+            Lir::TailJmp(reg) => Node::Record("TailJmp", vec![("reg", reg.dump())]),
+            Lir::FuncSetup(name, stack_size, is_static) => Node::Record(
+                "FuncSetup",
+                vec![
+                    ("name", Node::Str(name.clone())),
+                    ("stack_size", Node::Int(*stack_size as i128)),
+                    ("is_static", Node::Bool(*is_static)),
+                ],
+            ),
+            Lir::FuncTeardown(stack_size, is_tail_call) => Node::Record(
+                "FuncTeardown",
+                vec![
+                    ("stack_size", Node::Int(*stack_size as i128)),
+                    ("is_tail_call", Node::Bool(*is_tail_call)),
+                ],
+            ),
+            Lir::SaveRegs => Node::Record("SaveRegs", vec![]),
+            Lir::RestoreRegs => Node::Record("RestoreRegs", vec![]),
+            Lir::AddSp(size) => Node::Record("AddSp", vec![("size", Node::Int(*size as i128))]),
+            Lir::SubSp(size) => Node::Record("SubSp", vec![("size", Node::Int(*size as i128))]),
+            Lir::Mov(from, to) => operands("Mov", from, to),
+            Lir::Movs(from, to) => operands("Movs", from, to),
+            Lir::Movz(from, to) => operands("Movz", from, to),
+            Lir::Cmp(from, to) => operands("Cmp", from, to),
+            Lir::Sub(from, to) => operands("Sub", from, to),
+            Lir::Add(from, to) => operands("Add", from, to),
+            Lir::Imul(from, to) => operands("Imul", from, to),
+            Lir::Load(from, to) => operands("Load", from, to),
+            Lir::Xor(from, to) => operands("Xor", from, to),
+            Lir::Or(from, to) => operands("Or", from, to),
+            Lir::And(from, to) => operands("And", from, to),
+            Lir::Div(reg) => Node::Record("Div", vec![("reg", reg.dump())]),
+            Lir::Not(reg) => Node::Record("Not", vec![("reg", reg.dump())]),
+            Lir::Neg(reg) => Node::Record("Neg", vec![("reg", reg.dump())]),
+            Lir::Shift(direction, from, to) => Node::Record(
+                "Shift",
+                vec![
+                    ("direction", Node::Str(direction.to_string())),
+                    ("from", from.dump()),
+                    ("to", to.dump()),
+                ],
+            ),
+            Lir::Set(cond) => Node::Record("Set", vec![("cond", Node::Str(cond.to_string()))]),
+            Lir::Rep => Node::Record("Rep", vec![]),
+            Lir::FileDeclaration(index, filename) => Node::Record(
+                "FileDeclaration",
+                vec![("index", label(index)), ("filename", Node::Str(filename.clone()))],
+            ),
+            Lir::Loc(file, line, column) => Node::Record(
+                "Loc",
+                vec![
+                    ("file", label(file)),
+                    ("line", Node::Int((*line).into())),
+                    ("column", Node::Int((*column).into())),
+                ],
+            ),
+            // the debug-sections are only generated when writing the assembly
+            Lir::DebugInfo(_) => Node::Record("DebugInfo", vec![]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CompileOptions;
+
+    fn setup(input: &str, stage: Stage, format: Format) -> String {
+        let mut session = Session::new(CompileOptions::new());
+        session.add_file("main.c", input);
+
+        dump(&session, Path::new("main.c"), stage, format).unwrap()
+    }
+
+    #[test]
+    fn tokens() {
+        let actual = setup("int x = 0x1f;", Stage::Tokens, Format::Text);
+        let expected = "1:1 Int int\n1:5 Ident x\n1:7 Equal =\n1:9 Number 31\n1:13 Semicolon ;\n";
+        assert_eq!(actual, expected);
+
+        let actual = setup("#define N 1\nN", Stage::Pp, Format::Sexp);
+        assert_eq!(
+            actual,
+            "((PPToken :kind \"Newline\" :text \"\\n\" :line 1 :column 12)\n (PPToken :kind \"Number\" :text \"1\" :line 2 :column 1))\n"
+        );
+    }
+
+    #[test]
+    fn hir_json() {
+        let actual = setup("int x = -1;", Stage::Hir, Format::Json);
+        let expected = r#"[
+  {
+    "kind": "Declaration",
+    "decl_specs": {
+      "kind": "DeclSpecs",
+      "storage_classes": [],
+      "qualifiers": [],
+      "specifiers": ["int"],
+      "is_inline": false
+    },
+    "declarators": [
+      {
+        "kind": "InitDeclarator",
+        "declarator": {"kind": "Declarator", "name": "x", "modifiers": []},
+        "init": {
+          "kind": "Init",
+          "designator": null,
+          "value": {
+            "kind": "Unary",
+            "op": "-",
+            "expr": {"kind": "Number", "value": 1, "radix": "decimal", "suffix": null}
+          }
+        }
+      }
+    ]
+  }
+]
+"#;
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn mir_types() {
+        let actual = setup("long f(char c) { return c; }", Stage::Mir, Format::Text);
+        let expected = "Function
+  name: \"f\"
+  type: \"long (char)\"
+  params:
+    - Param
+        name: \"c\"
+        type: \"char\"
+  variadic: false
+  is_inline: false
+  stack_size: 1
+  body:
+    - Return
+        expr: Cast
+          direction: \"up\"
+          expr: Ident
+            name: \"c\"
+            type: \"char\"
+            value_kind: \"lvalue\"
+          type: \"long\"
+          value_kind: \"lvalue\"
+";
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn virtual_registers() {
+        let actual = setup("int f(int a) { return a * 2; }", Stage::Lir, Format::Json);
+
+        let temp = r#""to": {"kind": "Temp", "id": 2, "type": "int", "value_kind": "rvalue", "reg": null}"#;
+        let arg = r#""from": {"kind": "Arg", "id": 1, "type": "int", "reg": "%rdi"}"#;
+        assert!(actual.contains(temp));
+        assert!(actual.contains(arg));
+        assert!(Node::Str("a\"b".to_string()).json() == "\"a\\\"b\"\n");
+    }
+
+    #[test]
+    fn lir_sexp() {
+        let actual = setup("int f(int a) { return a * 2; }", Stage::Lir, Format::Sexp);
+
+        assert!(actual.starts_with("((FuncSetup :name \"f\" :stack_size 4 :is_static #f)\n"));
+        assert!(actual.contains(":from (Literal :value 2 :type \"int\")"));
+        assert!(actual.contains(":to (Temp :id 2 :type \"int\" :value_kind \"rvalue\" :reg ())"));
+
+        let node = Node::Record(
+            "R",
+            vec![("null", Node::Null), ("yes", Node::Bool(true)), ("no", Node::Bool(false))],
+        );
+        assert_eq!(node.sexp(), "(R :null () :yes #t :no #f)\n");
+    }
+
+    #[test]
+    fn calls_before_register_allocation() {
+        let input = "int g(int a);\nint f(int a) { return g(a) + 1; }";
+
+        let actual = setup(input, Stage::Lir, Format::Text);
+        assert!(actual.contains("save-regs"));
+        assert!(actual.contains("restore-regs"));
+        assert!(actual.contains("call"));
+
+        for format in [Format::Json, Format::Sexp] {
+            let actual = setup(input, Stage::Lir, format);
+
+            assert!(actual.contains("SaveRegs"));
+            assert!(actual.contains("RestoreRegs"));
+            assert!(actual.contains("Call"));
+        }
+    }
+}
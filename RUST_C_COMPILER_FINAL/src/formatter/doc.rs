@@ -0,0 +1,181 @@
+//! Documents describing the possible layouts of formatted code, printed so that they fit into a
+//! maximum line-width as described in Wadler's "A prettier printer".<br>
+//! A [group](Doc::Group) is printed on a single line if it fits, otherwise all of its
+//! [lines](Doc::Line) are broken.
+
+pub enum Doc {
+    Text(String),
+    // printed without indentation at the start of a line, used for preprocessor-directives
+    Raw(String),
+    // the string if the enclosing group fits on the line, otherwise a newline
+    Line(&'static str),
+    // always a newline, which breaks all enclosing groups
+    HardLine,
+    Indent(Box<Doc>),
+    Group(Box<Doc>),
+    Concat(Vec<Doc>),
+}
+
+pub fn text(s: impl Into<String>) -> Doc {
+    Doc::Text(s.into())
+}
+// a space or a newline
+pub fn line() -> Doc {
+    Doc::Line(" ")
+}
+// nothing or a newline
+pub fn softline() -> Doc {
+    Doc::Line("")
+}
+pub fn indent(doc: Doc) -> Doc {
+    Doc::Indent(Box::new(doc))
+}
+pub fn group(doc: Doc) -> Doc {
+    Doc::Group(Box::new(doc))
+}
+pub fn concat(docs: Vec<Doc>) -> Doc {
+    Doc::Concat(docs)
+}
+pub fn join(docs: Vec<Doc>, separator: impl Fn() -> Doc) -> Doc {
+    let mut result = Vec::new();
+    for (i, doc) in docs.into_iter().enumerate() {
+        if i > 0 {
+            result.push(separator());
+        }
+        result.push(doc);
+    }
+    Doc::Concat(result)
+}
+// `(a, b, c)` or if it doesn't fit every element on its own indented line
+pub fn list(open: &'static str, docs: Vec<Doc>, close: &'static str) -> Doc {
+    if docs.is_empty() {
+        return text(format!("{}{}", open, close));
+    }
+    group(concat(vec![
+        text(open),
+        indent(concat(vec![
+            softline(),
+            join(docs, || concat(vec![text(","), line()])),
+        ])),
+        softline(),
+        text(close),
+    ]))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+/// Lays out the document using `indent` for every level of indentation, which takes up
+/// `indent_width` columns
+pub fn print(doc: &Doc, line_width: usize, indent: &str, indent_width: usize) -> String {
+    let mut output = String::new();
+    let mut column = 0;
+    // indentation is only written once text follows, so that empty lines stay empty
+    let mut pending_indent = None;
+    let mut stack = vec![(0, Mode::Break, doc)];
+
+    while let Some((level, mode, doc)) = stack.pop() {
+        match doc {
+            Doc::Text(s) if s.is_empty() => (),
+            Doc::Text(s) => {
+                if let Some(level) = pending_indent.take() {
+                    output.push_str(&indent.repeat(level));
+                }
+                output.push_str(s);
+                column = advance(column, s);
+            }
+            Doc::Raw(s) => {
+                pending_indent = None;
+                output.push_str(s);
+                column = advance(0, s);
+            }
+            Doc::Line(s) if mode == Mode::Flat => {
+                output.push_str(s);
+                column += s.len();
+            }
+            Doc::Line(_) | Doc::HardLine => {
+                output.push('\n');
+                pending_indent = Some(level);
+                column = level * indent_width;
+            }
+            Doc::Indent(doc) => stack.push((level + 1, mode, doc)),
+            Doc::Group(doc) => {
+                let remaining = line_width as isize - column as isize;
+                let mode = if fits(remaining, (level, Mode::Flat, doc), &stack) {
+                    Mode::Flat
+                } else {
+                    Mode::Break
+                };
+                stack.push((level, mode, doc));
+            }
+            Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (level, mode, doc))),
+        }
+    }
+    output
+}
+
+fn advance(column: usize, s: &str) -> usize {
+    match s.rfind('\n') {
+        Some(newline) => s[newline + 1..].chars().count(),
+        None => column + s.chars().count(),
+    }
+}
+
+// whether the document fits into the remaining width when printed flat, together with everything
+// following it up to the next newline
+fn fits(mut remaining: isize, next: (usize, Mode, &Doc), rest: &[(usize, Mode, &Doc)]) -> bool {
+    let mut stack = vec![next];
+    let mut rest = rest.iter().rev();
+
+    while remaining >= 0 {
+        let Some((level, mode, doc)) = stack.pop().or_else(|| rest.next().copied()) else {
+            return true;
+        };
+        match doc {
+            Doc::Text(s) if s.contains('\n') => return mode == Mode::Break,
+            Doc::Text(s) => remaining -= s.chars().count() as isize,
+            Doc::Raw(_) => return true,
+            Doc::Line(s) if mode == Mode::Flat => remaining -= s.len() as isize,
+            Doc::Line(_) | Doc::HardLine => return mode == Mode::Break,
+            Doc::Indent(doc) | Doc::Group(doc) => stack.push((level, mode, doc)),
+            Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (level, mode, doc))),
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(args: &[&str]) -> Doc {
+        concat(vec![
+            text("f"),
+            list("(", args.iter().map(|arg| text(*arg)).collect(), ")"),
+            text(";"),
+        ])
+    }
+
+    #[test]
+    fn fitting_group() {
+        assert_eq!(print(&call(&["a", "b"]), 10, "    ", 4), "f(a, b);");
+        assert_eq!(print(&call(&["a", "b"]), 7, "    ", 4), "f(\n    a,\n    b\n);");
+        assert_eq!(print(&call(&[]), 1, "    ", 4), "f();");
+    }
+
+    #[test]
+    fn hardline_breaks_group() {
+        let doc = indent(concat(vec![
+            text("{"),
+            Doc::HardLine,
+            group(concat(vec![text("x"), line(), Doc::HardLine, text("y")])),
+            Doc::HardLine,
+            Doc::HardLine,
+            Doc::Raw("#define A".to_string()),
+        ]));
+        assert_eq!(print(&doc, 80, "\t", 4), "{\n\tx\n\n\ty\n\n#define A");
+    }
+}
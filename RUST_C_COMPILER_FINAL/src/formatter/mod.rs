@@ -0,0 +1,1388 @@
+//! Formats C source-code by printing its [HIR](crate::compiler::parser::hir) in a consistent style.<br>
+//! Comments are [retained](crate::compiler::scanner::Scanner::retain_comments) by the scanner and
+//! printed before the declaration or statement following them, or at the end of the line they
+//! trail. Preprocessor-directives are kept unchanged but aren't evaluated, so a file can only be
+//! formatted if it also parses without its includes and macros.
+
+mod doc;
+
+use crate::compiler::common::{error::*, token::*};
+use crate::compiler::parser::{hir::decl::*, hir::expr::*, hir::stmt::*, Parser};
+use crate::compiler::scanner::Scanner;
+use crate::dump::lexeme;
+use crate::preprocessor::{scanner::Scanner as PPScanner, scanner::TokenKind as PPKind, PPToken};
+use doc::*;
+
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BraceStyle {
+    /// Opening braces at the end of the line, eg: `if (x) {`
+    SameLine,
+    /// Opening braces on their own line
+    NextLine,
+}
+impl BraceStyle {
+    pub fn from_name(name: &str) -> Option<BraceStyle> {
+        match name {
+            "same-line" => Some(BraceStyle::SameLine),
+            "next-line" => Some(BraceStyle::NextLine),
+            _ => None,
+        }
+    }
+}
+
+/// The style of the formatted code, set using the builder-methods
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    indent_width: usize,
+    use_tabs: bool,
+    brace_style: BraceStyle,
+    line_width: usize,
+}
+impl FormatOptions {
+    /// The default style indents by 4 spaces, keeps braces on the same line and lines below
+    /// 80 columns
+    pub fn new() -> Self {
+        FormatOptions {
+            indent_width: 4,
+            use_tabs: false,
+            brace_style: BraceStyle::SameLine,
+            line_width: 80,
+        }
+    }
+    /// The columns per level of indentation, which is also the width of a tab
+    pub fn indent_width(mut self, indent_width: usize) -> Self {
+        self.indent_width = indent_width;
+        self
+    }
+    pub fn use_tabs(mut self, use_tabs: bool) -> Self {
+        self.use_tabs = use_tabs;
+        self
+    }
+    pub fn brace_style(mut self, brace_style: BraceStyle) -> Self {
+        self.brace_style = brace_style;
+        self
+    }
+    /// The width lines are broken at if possible
+    pub fn line_width(mut self, line_width: usize) -> Self {
+        self.line_width = line_width;
+        self
+    }
+}
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Formats the source-code of the file, returning errors if it doesn't parse
+pub fn format(filename: &Path, source: &str, options: &FormatOptions) -> Result<String, WreccError> {
+    let (pp_tokens, directives) = split_directives(filename, source);
+
+    let mut scanner = Scanner::new(pp_tokens).retain_comments();
+    let tokens = scanner.scan_token()?;
+
+    let mut trivia: Vec<Extra> = tokens
+        .iter()
+        .flat_map(|token| token.trivia.iter().cloned())
+        .chain(scanner.trailing_trivia())
+        .map(Extra::Comment)
+        .chain(directives)
+        .collect();
+    trivia.sort_by_key(Extra::location);
+
+    let mut printer = Printer::new(options, &tokens, trivia);
+    let external_declarations = Parser::new(tokens).parse()?;
+    let doc = printer.translation_unit(&external_declarations);
+
+    let indent = if options.use_tabs {
+        "\t".to_string()
+    } else {
+        " ".repeat(options.indent_width)
+    };
+    let mut output = print(&doc, options.line_width, &indent, options.indent_width);
+    if !output.is_empty() {
+        output.push('\n');
+    }
+
+    Ok(output)
+}
+
+// Removes the preprocessor-directives from the tokens, keeping their unchanged source-text
+fn split_directives(filename: &Path, source: &str) -> (Vec<PPToken>, Vec<Extra>) {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut tokens = PPScanner::new(source.to_string())
+        .keep_comments()
+        .scan_token()
+        .into_iter();
+    let mut result = Vec::new();
+    let mut directives = Vec::new();
+    let mut line_start = true;
+
+    while let Some(token) = tokens.next() {
+        match token.kind {
+            PPKind::Hash if line_start => {
+                // directive ends at the first newline that isn't escaped or part of a comment
+                let last_line = tokens
+                    .find(|t| t.kind == PPKind::Newline)
+                    .map_or(lines.len(), |newline| newline.line as usize);
+                let text = lines[token.line as usize - 1..last_line.max(token.line as usize)]
+                    .iter()
+                    .map(|line| line.trim_end())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+
+                directives.push(Extra::Directive(token.line, text.trim_start().to_string()));
+                continue;
+            }
+            PPKind::Newline => line_start = true,
+            PPKind::Whitespace(_) | PPKind::Comment(_) => (),
+            _ => line_start = false,
+        }
+        result.push(PPToken {
+            kind: token.kind,
+            column: token.column,
+            line: token.line,
+            line_string: token.line_string,
+            filename: filename.to_path_buf(),
+        });
+    }
+
+    (result, directives)
+}
+
+// Source-text which isn't part of the HIR
+enum Extra {
+    Comment(Trivia),
+    Directive(i32, String),
+}
+impl Extra {
+    fn location(&self) -> (i32, i32) {
+        match self {
+            Extra::Comment(trivia) => (trivia.line_index, trivia.column),
+            Extra::Directive(line, _) => (*line, 1),
+        }
+    }
+    fn last_line(&self) -> i32 {
+        let (line, text) = match self {
+            Extra::Comment(trivia) => (trivia.line_index, &trivia.text),
+            Extra::Directive(line, text) => (*line, text),
+        };
+        line + text.matches('\n').count() as i32
+    }
+}
+
+#[derive(PartialEq)]
+enum Blank {
+    // no blank line before the element, eg: a closing brace
+    Never,
+    // a single blank line if there is at least one in the source
+    Source,
+    // always separated by a blank line, eg: function-definitions
+    Always,
+}
+
+// operator precedences, a higher value binds tighter
+const COMMA: u8 = 1;
+const ASSIGN: u8 = 2;
+const TERNARY: u8 = 3;
+const UNARY: u8 = 14;
+const POSTFIX: u8 = 15;
+const PRIMARY: u8 = 16;
+
+fn binary_precedence(kind: &TokenKind) -> u8 {
+    match kind {
+        TokenKind::PipePipe => 4,
+        TokenKind::AmpAmp => 5,
+        TokenKind::Pipe => 6,
+        TokenKind::Xor => 7,
+        TokenKind::Amp => 8,
+        TokenKind::EqualEqual | TokenKind::BangEqual => 9,
+        TokenKind::Less | TokenKind::LessEqual | TokenKind::Greater | TokenKind::GreaterEqual => 10,
+        TokenKind::LessLess | TokenKind::GreaterGreater => 11,
+        TokenKind::Plus | TokenKind::Minus => 12,
+        _ => 13,
+    }
+}
+
+fn as_binary(expr: &ExprKind) -> Option<(&ExprKind, &Token, &ExprKind)> {
+    match expr {
+        ExprKind::Binary { left, token, right }
+        | ExprKind::Comparison { left, token, right }
+        | ExprKind::Logical { left, token, right } => Some((left, token, right)),
+        _ => None,
+    }
+}
+
+// the parser desugars `a[i]` into `*(a + i)`, where both operators are located at the bracket
+fn as_index<'a>(token: &Token, right: &'a ExprKind) -> Option<(&'a ExprKind, &'a ExprKind)> {
+    match right {
+        ExprKind::Binary {
+            left,
+            token: plus,
+            right,
+        } if (plus.line_index, plus.column) == (token.line_index, token.column) => Some((left, right)),
+        _ => None,
+    }
+}
+
+// the token an expression starts with, seeing through the desugared index- and arrow-operators
+fn expr_start(expr: &ExprKind) -> Option<&Token> {
+    match expr {
+        ExprKind::Unary { token, right } => match as_index(token, right) {
+            Some((left, _)) => expr_start(left),
+            None => Some(token),
+        },
+        ExprKind::MemberAccess { token, expr, .. } => match (&token.kind, expr.as_ref()) {
+            (TokenKind::Arrow, ExprKind::Unary { right, .. }) => expr_start(right),
+            _ => expr_start(expr),
+        },
+        ExprKind::CompoundAssign { token, .. }
+            if matches!(token.kind, TokenKind::PlusPlus | TokenKind::MinusMinus) =>
+        {
+            Some(token)
+        }
+        ExprKind::Binary { left, .. }
+        | ExprKind::Logical { left, .. }
+        | ExprKind::Comparison { left, .. }
+        | ExprKind::PostUnary { left, .. }
+        | ExprKind::Comma { left, .. }
+        | ExprKind::Assign { l_expr: left, .. }
+        | ExprKind::CompoundAssign { l_expr: left, .. }
+        | ExprKind::Call { caller: left, .. }
+        | ExprKind::Ternary { cond: left, .. } => expr_start(left),
+        ExprKind::Cast { token, .. }
+        | ExprKind::SizeofType { token, .. }
+        | ExprKind::SizeofExpr { token, .. }
+        | ExprKind::String(token)
+        | ExprKind::Ident(token) => Some(token),
+        ExprKind::Char(_) | ExprKind::Number(..) | ExprKind::Nop => None,
+    }
+}
+
+// the first character of an expression if it is a prefix-operator
+fn prefix_char(expr: &ExprKind) -> Option<char> {
+    match expr {
+        ExprKind::Unary { token, right } if as_index(token, right).is_none() => lexeme(token).chars().next(),
+        ExprKind::CompoundAssign { token, .. }
+            if matches!(token.kind, TokenKind::PlusPlus | TokenKind::MinusMinus) =>
+        {
+            lexeme(token).chars().next()
+        }
+        _ => None,
+    }
+}
+
+fn location(token: &Token) -> (i32, i32) {
+    (token.line_index, token.column)
+}
+
+fn number(n: u64, radix: &Radix, suffix: &Option<IntSuffix>) -> String {
+    let n = match radix {
+        Radix::Decimal => n.to_string(),
+        Radix::Hex => format!("0x{:x}", n),
+        Radix::Octal => format!("0{:o}", n),
+    };
+    let suffix = match suffix {
+        None => "",
+        Some(IntSuffix::U) => "u",
+        Some(IntSuffix::L) => "l",
+        Some(IntSuffix::UL) => "ul",
+        Some(IntSuffix::LL) => "ll",
+        Some(IntSuffix::ULL) => "ull",
+    };
+    n + suffix
+}
+
+// writes the character as it has to appear in a literal delimited by `quote`
+fn escape(output: &mut String, c: char, quote: char, next: Option<char>) {
+    match c {
+        '\n' => output.push_str("\\n"),
+        '\t' => output.push_str("\\t"),
+        '\r' => output.push_str("\\r"),
+        '\\' => output.push_str("\\\\"),
+        '\x07' => output.push_str("\\a"),
+        '\x08' => output.push_str("\\b"),
+        '\x0B' => output.push_str("\\v"),
+        '\x0C' => output.push_str("\\f"),
+        '\0' if !matches!(next, Some('0'..='7')) => output.push_str("\\0"),
+        c if c == quote => write!(output, "\\{}", c).unwrap(),
+        // non-ascii characters in char-literals can only come from escapes
+        c if c.is_ascii_control() || (quote == '\'' && !c.is_ascii()) => {
+            write!(output, "\\{:03o}", c as u32).unwrap()
+        }
+        c => output.push(c),
+    }
+}
+fn string_literal(s: &str) -> String {
+    let mut output = String::from('"');
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        escape(&mut output, c, '"', chars.peek().copied());
+    }
+    output.push('"');
+    output
+}
+fn char_literal(c: char) -> String {
+    let mut output = String::from('\'');
+    escape(&mut output, c, '\'', None);
+    output.push('\'');
+    output
+}
+
+fn is_blank(doc: &Doc) -> bool {
+    matches!(doc, Doc::Concat(docs) if docs.is_empty())
+}
+fn blank(lines: &mut Vec<Doc>) {
+    if matches!(lines.last(), Some(doc) if !is_blank(doc)) {
+        lines.push(Doc::Concat(Vec::new()));
+    }
+}
+fn braces(lines: Vec<Doc>) -> Doc {
+    if lines.is_empty() {
+        return text("{}");
+    }
+    concat(vec![
+        text("{"),
+        indent(concat(vec![Doc::HardLine, join(lines, || Doc::HardLine)])),
+        Doc::HardLine,
+        text("}"),
+    ])
+}
+
+struct Printer<'a> {
+    options: &'a FormatOptions,
+
+    // comments and directives that still have to be printed, in source-order
+    trivia: VecDeque<Extra>,
+
+    // locations of all tokens, to find the token preceding an element
+    tokens: Vec<(i32, i32, TokenKind)>,
+
+    // locations of the opening and closing braces of compound-statements and of
+    // struct/union/enum bodies, in the order they are printed
+    blocks: VecDeque<((i32, i32), (i32, i32))>,
+    bodies: VecDeque<((i32, i32), (i32, i32))>,
+}
+impl<'a> Printer<'a> {
+    fn new(options: &'a FormatOptions, tokens: &[Token], trivia: Vec<Extra>) -> Self {
+        let mut printer = Printer {
+            options,
+            trivia: trivia.into(),
+            tokens: tokens
+                .iter()
+                .map(|t| (t.line_index, t.column, t.kind.clone()))
+                .collect(),
+            blocks: VecDeque::new(),
+            bodies: VecDeque::new(),
+        };
+        printer.match_braces();
+        printer
+    }
+
+    // since the HIR doesn't contain the braces of blocks they are matched here, so that comments
+    // at the end of a block are printed before its closing brace
+    fn match_braces(&mut self) {
+        enum Brace {
+            Init,
+            Block(usize),
+            Body(usize),
+        }
+        let mut open = Vec::new();
+
+        for (i, (line, column, kind)) in self.tokens.iter().enumerate() {
+            let previous = |n: usize| i.checked_sub(n).map(|i| &self.tokens[i].2);
+            match kind {
+                TokenKind::LeftBrace => {
+                    let brace = if matches!(open.last(), Some(Brace::Init))
+                        || matches!(previous(1), Some(TokenKind::Equal))
+                    {
+                        Brace::Init
+                    } else if matches!(
+                        previous(1),
+                        Some(TokenKind::Struct | TokenKind::Union | TokenKind::Enum)
+                    ) || matches!(previous(1), Some(TokenKind::Ident(_)))
+                        && matches!(
+                            previous(2),
+                            Some(TokenKind::Struct | TokenKind::Union | TokenKind::Enum)
+                        )
+                    {
+                        self.bodies.push_back(((*line, *column), (i32::MAX, 0)));
+                        Brace::Body(self.bodies.len() - 1)
+                    } else {
+                        self.blocks.push_back(((*line, *column), (i32::MAX, 0)));
+                        Brace::Block(self.blocks.len() - 1)
+                    };
+                    open.push(brace);
+                }
+                TokenKind::RightBrace => match open.pop() {
+                    Some(Brace::Block(i)) => self.blocks[i].1 = (*line, *column),
+                    Some(Brace::Body(i)) => self.bodies[i].1 = (*line, *column),
+                    Some(Brace::Init) | None => (),
+                },
+                _ => (),
+            }
+        }
+    }
+
+    // index of the first token at or after the location
+    fn token_index(&self, location: (i32, i32)) -> usize {
+        self.tokens
+            .partition_point(|(line, column, _)| (*line, *column) < location)
+    }
+
+    // prints the comments and directives preceding the element at `location` into `lines`,
+    // keeping a single blank line where the source has one
+    fn flush(&mut self, lines: &mut Vec<Doc>, location: (i32, i32), blank_line: Blank) {
+        // parentheses and `inline` aren't part of the HIR but still belong to the element
+        let mut index = self.token_index(location);
+        while index > 0 && matches!(self.tokens[index - 1].2, TokenKind::LeftParen | TokenKind::Inline) {
+            index -= 1;
+        }
+        let start = self
+            .tokens
+            .get(index)
+            .map_or(location, |(line, column, _)| (*line, *column));
+
+        let mut last_line = index.checked_sub(1).map(|i| self.tokens[i].0);
+        let mut separate = blank_line == Blank::Always;
+        let gap = |last_line: Option<i32>, line: i32| matches!(last_line, Some(last) if line > last + 1);
+
+        while self.trivia.front().is_some_and(|extra| extra.location() < start) {
+            let extra = self.trivia.pop_front().unwrap();
+
+            match &extra {
+                Extra::Comment(trivia)
+                    if trivia.trailing && matches!(lines.last(), Some(doc) if !is_blank(doc)) =>
+                {
+                    let last = lines.pop().unwrap();
+                    lines.push(concat(vec![
+                        last,
+                        Doc::Raw(format!(" {}", trivia.text.trim_end())),
+                    ]));
+                }
+                _ => {
+                    if separate || gap(last_line, extra.location().0) {
+                        blank(lines);
+                    }
+                    separate = false;
+                    lines.push(match &extra {
+                        Extra::Comment(trivia) => text(trivia.text.trim_end()),
+                        Extra::Directive(_, directive) => Doc::Raw(directive.clone()),
+                    });
+                }
+            }
+            last_line = last_line.max(Some(extra.last_line()));
+        }
+
+        if separate || (blank_line != Blank::Never && gap(last_line, start.0)) {
+            blank(lines);
+        }
+    }
+
+    fn translation_unit(&mut self, external_declarations: &[ExternalDeclaration]) -> Doc {
+        let mut lines = Vec::new();
+        let mut after_function = false;
+
+        for external_declaration in external_declarations {
+            let (location, is_function) = match external_declaration {
+                ExternalDeclaration::Declaration(decl) => (self.declaration_start(decl), false),
+                ExternalDeclaration::Function(func, _) => (
+                    self.specifiers_start(&func.decl_specs)
+                        .or(Some(location(&func.name))),
+                    true,
+                ),
+            };
+            let blank_line = if is_function || after_function {
+                Blank::Always
+            } else {
+                Blank::Source
+            };
+            if let Some(location) = location {
+                self.flush(&mut lines, location, blank_line);
+            }
+
+            lines.push(match external_declaration {
+                ExternalDeclaration::Declaration(decl) => concat(vec![self.declaration(decl), text(";")]),
+                ExternalDeclaration::Function(func, body) => self.function(func, body),
+            });
+            after_function = is_function;
+        }
+        self.flush(&mut lines, (i32::MAX, 0), Blank::Never);
+
+        join(lines, || Doc::HardLine)
+    }
+
+    fn specifiers_start(&self, decl_specs: &DeclSpecs) -> Option<(i32, i32)> {
+        let storage_classes = decl_specs.storage_classes.iter().map(|s| location(&s.token));
+        let qualifiers = decl_specs.qualifiers.iter().map(|q| location(&q.token));
+        let specifiers = decl_specs.specifiers.iter().map(|s| location(&s.token));
+
+        storage_classes.chain(qualifiers).chain(specifiers).min()
+    }
+    fn declaration_start(&self, decl: &Declaration) -> Option<(i32, i32)> {
+        self.specifiers_start(&decl.decl_specs).or_else(|| {
+            decl.declarators
+                .iter()
+                .find_map(|(declarator, _)| declarator.name.as_ref().map(location))
+        })
+    }
+    fn statement_start(&self, stmt: &Stmt) -> Option<(i32, i32)> {
+        match stmt {
+            Stmt::Declaration(decl) => self.declaration_start(decl),
+            Stmt::Expr(expr) => expr_start(expr).map(location),
+            Stmt::Block(_) => self.blocks.front().map(|(open, _)| *open),
+            // located at the parenthesis following the keyword
+            Stmt::For(token, ..) | Stmt::While(token, ..) => {
+                let index = self.token_index(location(token)).checked_sub(1)?;
+                self.tokens.get(index).map(|(line, column, _)| (*line, *column))
+            }
+            _ => stmt.first_token().map(location),
+        }
+    }
+
+    fn braced(&self, head: Doc, block: Doc) -> Doc {
+        match self.options.brace_style {
+            BraceStyle::SameLine => concat(vec![head, text(" "), block]),
+            BraceStyle::NextLine => concat(vec![head, Doc::HardLine, block]),
+        }
+    }
+    // separates a block from the keyword following it, eg: `} else {`
+    fn after_block(&self, stmt: &Stmt) -> Doc {
+        match (stmt, self.options.brace_style) {
+            (Stmt::Block(_), BraceStyle::SameLine) => text(" "),
+            _ => Doc::HardLine,
+        }
+    }
+
+    fn function(&mut self, func: &FuncDecl, body: &[Stmt]) -> Doc {
+        let decl_specs = self.decl_specs(&func.decl_specs);
+        let (declarator, _) = self.declarator(Some(&func.name), &func.modifiers);
+        let block = self.block(body);
+
+        self.braced(concat(vec![decl_specs, text(" "), declarator]), block)
+    }
+
+    fn declaration(&mut self, decl: &Declaration) -> Doc {
+        let decl_specs = self.decl_specs(&decl.decl_specs);
+        if decl.declarators.is_empty() {
+            return decl_specs;
+        }
+
+        let mut declarators = Vec::new();
+        for (declarator, init) in &decl.declarators {
+            let (declarator, _) = self.declarator(declarator.name.as_ref(), &declarator.modifiers);
+            declarators.push(match init {
+                Some(init) => concat(vec![declarator, text(" = "), self.init(init)]),
+                None => declarator,
+            });
+        }
+
+        concat(vec![decl_specs, text(" "), join(declarators, || text(", "))])
+    }
+
+    fn init(&mut self, init: &Init) -> Doc {
+        let value = match &init.kind {
+            InitKind::Scalar(expr) => self.expr(expr, ASSIGN),
+            InitKind::Aggr(inits) => {
+                let inits = inits.iter().map(|init| self.init(init)).collect();
+                list("{", inits, "}")
+            }
+        };
+        let Some(designators) = &init.designator else {
+            return value;
+        };
+
+        let mut result = Vec::new();
+        for designator in designators {
+            result.push(match &designator.kind {
+                DesignatorKind::Array(expr) => concat(vec![text("["), self.expr(expr, COMMA), text("]")]),
+                DesignatorKind::Member(member) => text(format!(".{}", member)),
+            });
+        }
+        result.extend([text(" = "), value]);
+
+        concat(result)
+    }
+
+    fn decl_specs(&mut self, decl_specs: &DeclSpecs) -> Doc {
+        let mut words: Vec<Doc> = decl_specs
+            .storage_classes
+            .iter()
+            .map(|storage_class| text(lexeme(&storage_class.token)))
+            .collect();
+        if decl_specs.is_inline {
+            words.push(text("inline"));
+        }
+        words.push(self.type_specifiers(&decl_specs.qualifiers, &decl_specs.specifiers));
+
+        join(words, || text(" "))
+    }
+
+    fn type_specifiers(&mut self, qualifiers: &[Qualifier], specifiers: &[Specifier]) -> Doc {
+        let mut words: Vec<Doc> = qualifiers
+            .iter()
+            .map(|qualifier| text(lexeme(&qualifier.token)))
+            .collect();
+
+        for specifier in specifiers {
+            words.push(match &specifier.kind {
+                SpecifierKind::Struct(name, members) | SpecifierKind::Union(name, members) => {
+                    let head = match name {
+                        Some(name) => text(format!("{} {}", lexeme(&specifier.token), lexeme(name))),
+                        None => text(lexeme(&specifier.token)),
+                    };
+                    match members {
+                        Some(members) => {
+                            let body = self.members(members);
+                            self.braced(head, body)
+                        }
+                        None => head,
+                    }
+                }
+                SpecifierKind::Enum(name, constants) => {
+                    let head = match name {
+                        Some(name) => text(format!("enum {}", lexeme(name))),
+                        None => text("enum"),
+                    };
+                    match constants {
+                        Some(constants) => {
+                            let body = self.enum_constants(constants);
+                            self.braced(head, body)
+                        }
+                        None => head,
+                    }
+                }
+                _ => text(lexeme(&specifier.token)),
+            });
+        }
+
+        join(words, || text(" "))
+    }
+
+    fn members(&mut self, members: &[MemberDecl]) -> Doc {
+        let (_, close) = self.bodies.pop_front().unwrap_or_default();
+        let mut lines = Vec::new();
+
+        for member in members {
+            let start = member
+                .qualifiers
+                .iter()
+                .map(|q| location(&q.token))
+                .chain(member.specifiers.iter().map(|s| location(&s.token)))
+                .min();
+            if let Some(start) = start {
+                self.flush(&mut lines, start, Blank::Source);
+            }
+
+            let specifiers = self.type_specifiers(&member.qualifiers, &member.specifiers);
+            let mut declarators = Vec::new();
+            for MemberDeclarator { name, modifiers } in &member.declarators {
+                declarators.push(self.declarator(Some(name), modifiers).0);
+            }
+
+            lines.push(concat(vec![
+                specifiers,
+                text(" "),
+                join(declarators, || text(", ")),
+                text(";"),
+            ]));
+        }
+        self.flush(&mut lines, close, Blank::Never);
+
+        braces(lines)
+    }
+
+    fn enum_constants(&mut self, constants: &[(Token, Option<ExprKind>)]) -> Doc {
+        let (_, close) = self.bodies.pop_front().unwrap_or_default();
+        let mut lines = Vec::new();
+
+        for (i, (name, value)) in constants.iter().enumerate() {
+            self.flush(&mut lines, location(name), Blank::Source);
+
+            let mut constant = vec![text(lexeme(name))];
+            if let Some(value) = value {
+                constant.extend([text(" = "), self.expr(value, TERNARY)]);
+            }
+            // comma has to precede comments trailing the constant
+            if i + 1 < constants.len() {
+                constant.push(text(","));
+            }
+            lines.push(concat(constant));
+        }
+        self.flush(&mut lines, close, Blank::Never);
+
+        braces(lines)
+    }
+
+    // Prints the declarator starting at its name with the outermost modifier.
+    // Also returns whether it has to be separated from its type by a space.
+    fn declarator(&mut self, name: Option<&Token>, modifiers: &[DeclModifier]) -> (Doc, bool) {
+        let mut doc = text(name.map(lexeme).unwrap_or_default());
+        let mut is_empty = name.is_none();
+        let mut is_pointer = false;
+        let mut spaced = name.is_some();
+
+        for modifier in modifiers.iter().rev() {
+            match modifier {
+                DeclModifier::Pointer(qualifiers) => {
+                    let mut pointer = String::from("*");
+                    let qualifiers: Vec<String> = qualifiers.iter().map(|q| lexeme(&q.token)).collect();
+                    pointer.push_str(&qualifiers.join(" "));
+                    if !qualifiers.is_empty() && !is_empty {
+                        pointer.push(' ');
+                    }
+
+                    doc = concat(vec![text(pointer), doc]);
+                    is_pointer = true;
+                    spaced = true;
+                }
+                DeclModifier::Array(_, size) => {
+                    let size = match size {
+                        Some(size) => self.expr(size, ASSIGN),
+                        None => text(""),
+                    };
+                    doc = self.suffix(doc, is_pointer, concat(vec![text("["), size, text("]")]));
+                    is_pointer = false;
+                    spaced &= !is_empty;
+                }
+                DeclModifier::Function { params, variadic, .. } => {
+                    let mut docs = Vec::new();
+                    for ParamDecl {
+                        decl_specs,
+                        declarator,
+                    } in params
+                    {
+                        let decl_specs = self.decl_specs(decl_specs);
+                        docs.push(
+                            match self.declarator(declarator.name.as_ref(), &declarator.modifiers) {
+                                (_, _) if declarator.name.is_none() && declarator.modifiers.is_empty() => {
+                                    decl_specs
+                                }
+                                (declarator, true) => concat(vec![decl_specs, text(" "), declarator]),
+                                (declarator, false) => concat(vec![decl_specs, declarator]),
+                            },
+                        );
+                    }
+                    if *variadic {
+                        docs.push(text("..."));
+                    }
+                    doc = self.suffix(doc, is_pointer, list("(", docs, ")"));
+                    is_pointer = false;
+                    spaced &= !is_empty;
+                }
+            }
+            is_empty = false;
+        }
+
+        (doc, spaced)
+    }
+    // appends an array- or function-suffix, pointers have to be parenthesized since the suffix
+    // binds tighter, eg: `(*f)(int)`
+    fn suffix(&self, doc: Doc, is_pointer: bool, suffix: Doc) -> Doc {
+        if is_pointer {
+            concat(vec![text("("), doc, text(")"), suffix])
+        } else {
+            concat(vec![doc, suffix])
+        }
+    }
+
+    fn type_name(&mut self, decl_type: &DeclType) -> Doc {
+        let specifiers = self.type_specifiers(&decl_type.qualifiers, &decl_type.specifiers);
+        match self.declarator(None, &decl_type.modifiers) {
+            _ if decl_type.modifiers.is_empty() => specifiers,
+            (declarator, true) => concat(vec![specifiers, text(" "), declarator]),
+            (declarator, false) => concat(vec![specifiers, declarator]),
+        }
+    }
+
+    fn block(&mut self, stmts: &[Stmt]) -> Doc {
+        let (_, close) = self.blocks.pop_front().unwrap_or_default();
+        let mut lines = Vec::new();
+        // the label of the current case in a switch and the statements following it
+        let mut arm: Option<(Doc, Vec<Doc>)> = None;
+
+        for stmt in stmts {
+            let start = self.statement_start(stmt);
+
+            if let Stmt::Case(..) | Stmt::Default(..) = stmt {
+                if let Some(arm) = arm.take() {
+                    lines.push(case_arm(arm));
+                }
+                if let Some(start) = start {
+                    self.flush(&mut lines, start, Blank::Source);
+                }
+
+                let (labels, body) = self.case_labels(stmt);
+                // flushed after the labels so that comments can trail them
+                let mut arm_lines = vec![labels];
+                if let Some(start) = self.statement_start(body) {
+                    self.flush(&mut arm_lines, start, Blank::Never);
+                }
+                let labels = arm_lines.remove(0);
+                arm_lines.push(self.stmt(body));
+                arm = Some((labels, arm_lines));
+            } else {
+                let lines = arm.as_mut().map_or(&mut lines, |(_, lines)| lines);
+                if let Some(start) = start {
+                    self.flush(lines, start, Blank::Source);
+                }
+                lines.push(self.stmt(stmt));
+            }
+        }
+        match arm {
+            Some((labels, mut arm_lines)) => {
+                self.flush(&mut arm_lines, close, Blank::Never);
+                lines.push(case_arm((labels, arm_lines)));
+            }
+            None => self.flush(&mut lines, close, Blank::Never),
+        }
+
+        braces(lines)
+    }
+
+    // the labels of directly nested cases, eg: `case 1: case 2: stmt`, and their statement
+    fn case_labels<'s>(&mut self, mut stmt: &'s Stmt) -> (Doc, &'s Stmt) {
+        let mut labels = Vec::new();
+        loop {
+            match stmt {
+                Stmt::Case(_, value, body) => {
+                    labels.push(concat(vec![text("case "), self.expr(value, TERNARY), text(":")]));
+                    stmt = body;
+                }
+                Stmt::Default(_, body) => {
+                    labels.push(text("default:"));
+                    stmt = body;
+                }
+                _ => return (join(labels, || Doc::HardLine), stmt),
+            }
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Doc {
+        match stmt {
+            Stmt::Declaration(decl) => concat(vec![self.declaration(decl), text(";")]),
+            Stmt::Expr(ExprKind::Nop) => text(";"),
+            Stmt::Expr(expr) => concat(vec![self.expr(expr, COMMA), text(";")]),
+            Stmt::Block(stmts) => self.block(stmts),
+            Stmt::If(_, cond, then_branch, else_branch) => {
+                let head = concat(vec![text("if ("), self.expr(cond, COMMA), text(")")]);
+                let then_doc = self.body(head, then_branch);
+
+                match else_branch {
+                    Some(else_branch) => {
+                        let separator = self.after_block(then_branch);
+                        let else_doc = match else_branch.as_ref() {
+                            Stmt::If(..) => concat(vec![text("else "), self.stmt(else_branch)]),
+                            _ => self.body(text("else"), else_branch),
+                        };
+                        concat(vec![then_doc, separator, else_doc])
+                    }
+                    None => then_doc,
+                }
+            }
+            Stmt::While(_, cond, body) => {
+                let head = concat(vec![text("while ("), self.expr(cond, COMMA), text(")")]);
+                self.body(head, body)
+            }
+            Stmt::Do(_, body, cond) => {
+                let body_doc = self.body(text("do"), body);
+                concat(vec![
+                    body_doc,
+                    self.after_block(body),
+                    text("while ("),
+                    self.expr(cond, COMMA),
+                    text(");"),
+                ])
+            }
+            Stmt::For(_, init, cond, inc, body) => {
+                let mut head = vec![text("for (")];
+                match init.as_deref() {
+                    Some(Stmt::Declaration(decl)) => head.push(self.declaration(decl)),
+                    Some(Stmt::Expr(expr)) => head.push(self.expr(expr, COMMA)),
+                    _ => (),
+                }
+                head.push(text(";"));
+                if let Some(cond) = cond {
+                    head.extend([text(" "), self.expr(cond, COMMA)]);
+                }
+                head.push(text(";"));
+                if let Some(inc) = inc {
+                    head.extend([text(" "), self.expr(inc, COMMA)]);
+                }
+                head.push(text(")"));
+
+                self.body(concat(head), body)
+            }
+            Stmt::Return(_, expr) => match expr {
+                Some(expr) => concat(vec![text("return "), self.expr(expr, COMMA), text(";")]),
+                None => text("return;"),
+            },
+            Stmt::Break(_) => text("break;"),
+            Stmt::Continue(_) => text("continue;"),
+            Stmt::Switch(_, cond, body) => {
+                let head = concat(vec![text("switch ("), self.expr(cond, COMMA), text(")")]);
+                self.body(head, body)
+            }
+            Stmt::Case(..) | Stmt::Default(..) => {
+                let (labels, body) = self.case_labels(stmt);
+                let body = self.stmt(body);
+                case_arm((labels, vec![body]))
+            }
+            Stmt::Goto(label) => text(format!("goto {};", lexeme(label))),
+            Stmt::Label(label, body) => concat(vec![
+                text(format!("{}:", lexeme(label))),
+                Doc::HardLine,
+                self.stmt(body),
+            ]),
+        }
+    }
+
+    // the statement following a head like `while (x)`, where blocks are placed according to the
+    // brace-style and other statements are indented on the next line
+    fn body(&mut self, head: Doc, body: &Stmt) -> Doc {
+        match body {
+            Stmt::Block(stmts) => {
+                let block = self.block(stmts);
+                self.braced(head, block)
+            }
+            Stmt::Expr(ExprKind::Nop) => concat(vec![head, text(";")]),
+            _ => concat(vec![head, indent(concat(vec![Doc::HardLine, self.stmt(body)]))]),
+        }
+    }
+
+    // parenthesizes the expression if it binds weaker than `min_precedence`
+    fn expr(&mut self, expr: &ExprKind, min_precedence: u8) -> Doc {
+        let (doc, precedence) = self.expr_precedence(expr);
+        if precedence < min_precedence {
+            concat(vec![text("("), doc, text(")")])
+        } else {
+            doc
+        }
+    }
+
+    fn expr_precedence(&mut self, expr: &ExprKind) -> (Doc, u8) {
+        match expr {
+            ExprKind::Binary { token, .. }
+            | ExprKind::Comparison { token, .. }
+            | ExprKind::Logical { token, .. } => {
+                let precedence = binary_precedence(&token.kind);
+
+                // operands of the same precedence are broken onto new lines together
+                let mut first = expr;
+                let mut operands = Vec::new();
+                while let Some((left, token, right)) = as_binary(first) {
+                    if binary_precedence(&token.kind) != precedence {
+                        break;
+                    }
+                    operands.push((token, right));
+                    first = left;
+                }
+
+                let first = self.expr(first, precedence);
+                let mut rest = Vec::new();
+                for (token, right) in operands.into_iter().rev() {
+                    rest.extend([
+                        text(format!(" {}", lexeme(token))),
+                        line(),
+                        self.expr(right, precedence + 1),
+                    ]);
+                }
+
+                (group(concat(vec![first, indent(concat(rest))])), precedence)
+            }
+            ExprKind::Unary { token, right } => match as_index(token, right) {
+                Some((left, index)) => (
+                    concat(vec![
+                        self.expr(left, POSTFIX),
+                        text("["),
+                        self.expr(index, COMMA),
+                        text("]"),
+                    ]),
+                    POSTFIX,
+                ),
+                None => (self.prefix(token, right), UNARY),
+            },
+            ExprKind::CompoundAssign { l_expr, token, .. }
+                if matches!(token.kind, TokenKind::PlusPlus | TokenKind::MinusMinus) =>
+            {
+                (self.prefix(token, l_expr), UNARY)
+            }
+            ExprKind::Assign {
+                l_expr,
+                token,
+                r_expr,
+            }
+            | ExprKind::CompoundAssign {
+                l_expr,
+                token,
+                r_expr,
+            } => (
+                concat(vec![
+                    self.expr(l_expr, UNARY),
+                    text(format!(" {} ", lexeme(token))),
+                    self.expr(r_expr, ASSIGN),
+                ]),
+                ASSIGN,
+            ),
+            ExprKind::Ternary {
+                cond,
+                true_expr,
+                false_expr,
+                ..
+            } => {
+                let cond = self.expr(cond, TERNARY + 1);
+                let true_expr = self.expr(true_expr, COMMA);
+                let false_expr = self.expr(false_expr, TERNARY);
+
+                (
+                    group(concat(vec![
+                        cond,
+                        indent(concat(vec![
+                            line(),
+                            text("? "),
+                            true_expr,
+                            line(),
+                            text(": "),
+                            false_expr,
+                        ])),
+                    ])),
+                    TERNARY,
+                )
+            }
+            ExprKind::Comma { left, right } => (
+                group(concat(vec![
+                    self.expr(left, COMMA),
+                    text(","),
+                    line(),
+                    self.expr(right, ASSIGN),
+                ])),
+                COMMA,
+            ),
+            ExprKind::Call { caller, args, .. } => {
+                let caller = self.expr(caller, POSTFIX);
+                let args = args.iter().map(|arg| self.expr(arg, ASSIGN)).collect();
+
+                (concat(vec![caller, list("(", args, ")")]), POSTFIX)
+            }
+            ExprKind::Cast { decl_type, expr, .. } => (
+                concat(vec![
+                    text("("),
+                    self.type_name(decl_type),
+                    text(")"),
+                    self.expr(expr, UNARY),
+                ]),
+                UNARY,
+            ),
+            ExprKind::PostUnary { token, left } => (
+                concat(vec![self.expr(left, POSTFIX), text(lexeme(token))]),
+                POSTFIX,
+            ),
+            ExprKind::MemberAccess { token, member, expr } => {
+                let expr = match (&token.kind, expr.as_ref()) {
+                    (TokenKind::Arrow, ExprKind::Unary { right, .. }) => right,
+                    _ => expr,
+                };
+                (
+                    concat(vec![
+                        self.expr(expr, POSTFIX),
+                        text(format!("{}{}", lexeme(token), lexeme(member))),
+                    ]),
+                    POSTFIX,
+                )
+            }
+            ExprKind::SizeofType { decl_type, .. } => (
+                concat(vec![text("sizeof("), self.type_name(decl_type), text(")")]),
+                UNARY,
+            ),
+            ExprKind::SizeofExpr { expr, .. } => (
+                concat(vec![text("sizeof("), self.expr(expr, COMMA), text(")")]),
+                UNARY,
+            ),
+            ExprKind::String(token) => (text(string_literal(&token.unwrap_string())), PRIMARY),
+            ExprKind::Char(c) => (text(char_literal(*c)), PRIMARY),
+            ExprKind::Number(n, radix, suffix) => (text(number(*n, radix, suffix)), PRIMARY),
+            ExprKind::Ident(token) => (text(lexeme(token)), PRIMARY),
+            ExprKind::Nop => (text(""), PRIMARY),
+        }
+    }
+
+    fn prefix(&mut self, token: &Token, operand: &ExprKind) -> Doc {
+        let operator = lexeme(token);
+        // `- -x` mustn't become `--x`
+        let separator = match (operator.chars().last(), prefix_char(operand)) {
+            (Some(last), Some(first)) if last == first && matches!(first, '+' | '-' | '&') => " ",
+            _ => "",
+        };
+
+        concat(vec![text(operator + separator), self.expr(operand, UNARY)])
+    }
+}
+
+fn case_arm((labels, lines): (Doc, Vec<Doc>)) -> Doc {
+    if lines.is_empty() {
+        return labels;
+    }
+    concat(vec![
+        labels,
+        indent(concat(vec![Doc::HardLine, join(lines, || Doc::HardLine)])),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup(input: &str, options: &FormatOptions) -> String {
+        format(Path::new("main.c"), input, options).unwrap()
+    }
+    fn assert_idempotent(input: &str, options: &FormatOptions) {
+        let formatted = setup(input, options);
+        assert_eq!(setup(&formatted, options), formatted);
+    }
+
+    static PROGRAM: &str = "\
+#include <stdio.h>
+#define MAX(a, b) ((a) > (b) ? \\
+                   (a) : (b))
+/* a point */
+struct point { int x; int y; // coords
+};
+typedef struct point Point;
+enum color { RED, GREEN = 2 };
+int (*callback)(int, char **);
+static const char *names[2] = {\"a\\n\", \"b\\\"\"};
+int add(int a,int b){return a+b;} // trailing
+int main(int argc, char **argv)
+{
+  Point p = {.x = 1, .y = 2}; int i;
+  // loop
+
+
+  for(i=0;i<10;i++) { if (i%2==0) continue; else if (i == 3) break; else { p.x += i; } }
+  Point *pp = &p;
+  pp->y = names[1][0] * (p.x + 3) - -p.y;
+  while (i) i--;
+  do { i++; } while (i < 3);
+  switch (i) { case 1: case 2: i = 0; break; default: ; }
+  long l = 0xFFul + 017 + sizeof(int) + sizeof p + (long)(char)'\\'';
+  printf(\"%d %d %d\\n\", argc, (int)l, add(aaaaaaaaaaaaaaaaaaaaaaaaa, bbbbbbbbbbbbbbbbbbbbbbbbbbb));
+  goto end;
+end:
+  return i > 0 ? 1 : 0; /* done */
+  // last
+}
+";
+
+    #[test]
+    fn default_style() {
+        let expected = "\
+#include <stdio.h>
+#define MAX(a, b) ((a) > (b) ? \\
+                   (a) : (b))
+/* a point */
+struct point {
+    int x;
+    int y; // coords
+};
+typedef struct point Point;
+enum color {
+    RED,
+    GREEN = 2
+};
+int (*callback)(int, char **);
+static const char *names[2] = {\"a\\n\", \"b\\\"\"};
+
+int add(int a, int b) {
+    return a + b;
+} // trailing
+
+int main(int argc, char **argv) {
+    Point p = {.x = 1, .y = 2};
+    int i;
+    // loop
+
+    for (i = 0; i < 10; i++) {
+        if (i % 2 == 0)
+            continue;
+        else if (i == 3)
+            break;
+        else {
+            p.x += i;
+        }
+    }
+    Point *pp = &p;
+    pp->y = names[1][0] * (p.x + 3) - -p.y;
+    while (i)
+        i--;
+    do {
+        i++;
+    } while (i < 3);
+    switch (i) {
+        case 1:
+        case 2:
+            i = 0;
+            break;
+        default:
+            ;
+    }
+    long l = 0xfful + 017 + sizeof(int) + sizeof(p) + (long)(char)'\\'';
+    printf(
+        \"%d %d %d\\n\",
+        argc,
+        (int)l,
+        add(aaaaaaaaaaaaaaaaaaaaaaaaa, bbbbbbbbbbbbbbbbbbbbbbbbbbb)
+    );
+    goto end;
+    end:
+    return i > 0 ? 1 : 0; /* done */
+    // last
+}
+";
+        assert_eq!(setup(PROGRAM, &FormatOptions::new()), expected);
+    }
+
+    #[test]
+    fn brace_style_and_indentation() {
+        let options = FormatOptions::new()
+            .brace_style(BraceStyle::NextLine)
+            .use_tabs(true);
+        let actual = setup(
+            "int f(int x) { if (x) { return 1; } else { do x--; while (x); } return 0; }",
+            &options,
+        );
+        let expected = "\
+int f(int x)
+{
+\tif (x)
+\t{
+\t\treturn 1;
+\t}
+\telse
+\t{
+\t\tdo
+\t\t\tx--;
+\t\twhile (x);
+\t}
+\treturn 0;
+}
+";
+        assert_eq!(actual, expected);
+
+        let actual = setup("struct s { int a; };", &FormatOptions::new().indent_width(2));
+        assert_eq!(actual, "struct s {\n  int a;\n};\n");
+    }
+
+    #[test]
+    fn line_width() {
+        let input = "int x = first_operand + second_operand * (third_operand - fourth_operand) + fifth;";
+        let expected = "\
+int x = first_operand +
+    second_operand * (third_operand - fourth_operand) +
+    fifth;
+";
+        assert_eq!(setup(input, &FormatOptions::new().line_width(60)), expected);
+        assert_eq!(
+            setup(input, &FormatOptions::new().line_width(100)),
+            format!("{}\n", input)
+        );
+    }
+
+    #[test]
+    fn declarators() {
+        let input = "void (*signal(int sig, void (*func)(int)))(int); int *const a, * volatile *b; char (*c)[4], *d[4];\
+                     int e = sizeof(int *[3]) + sizeof(char (*)(int)) + (unsigned long)- -a + - --*b;";
+        let expected = "\
+void (*signal(int sig, void (*func)(int)))(int);
+int *const a, *volatile *b;
+char (*c)[4], *d[4];
+int e = sizeof(int *[3]) + sizeof(char (*)(int)) + (unsigned long)- -a + - --*b;
+";
+        assert_eq!(setup(input, &FormatOptions::new()), expected);
+    }
+
+    #[test]
+    fn precedence() {
+        let input = "int f() { x = a - (b - c) + (a - b) - c; x = (a ? b : c) ? d : e ? f : g; x = (a = b) + (c += 1); f((1, 2), *p++, (*p)++, &a[0]); }";
+        let expected = "\
+int f() {
+    x = a - (b - c) + (a - b) - c;
+    x = (a ? b : c) ? d : e ? f : g;
+    x = (a = b) + (c += 1);
+    f((1, 2), *p++, (*p)++, &a[0]);
+}
+";
+        assert_eq!(setup(input, &FormatOptions::new()), expected);
+    }
+
+    #[test]
+    fn comments_in_blocks() {
+        let input = "\
+int f() {
+    int a = g(1, /* inline */ 2);
+    if (a) { /* empty */ }
+    switch (a) {
+    case 1: // one
+        a++;
+        // end of case
+    }
+    return a;
+    // end of function
+}
+";
+        let expected = "\
+int f() {
+    int a = g(1, 2); /* inline */
+    if (a) {
+        /* empty */
+    }
+    switch (a) {
+        case 1: // one
+            a++;
+            // end of case
+    }
+    return a;
+    // end of function
+}
+";
+        assert_eq!(setup(input, &FormatOptions::new()), expected);
+    }
+
+    #[test]
+    fn idempotent() {
+        let options = [
+            FormatOptions::new(),
+            FormatOptions::new()
+                .brace_style(BraceStyle::NextLine)
+                .indent_width(2),
+            FormatOptions::new().use_tabs(true).line_width(30),
+            FormatOptions::new().line_width(1),
+        ];
+        let inputs = [
+            PROGRAM,
+            "",
+            "// only a comment\n",
+            "#ifdef A\nint x;\n#else\nlong x;\n#endif\n",
+            "int f(int n, ...) {} int g(void) { for (;;) {} while (1); return -1; }",
+            "int x; // a\n\n\n/* b\n   c */ int y; /* d */ int z;\n",
+            "char *s = \"tab\\there\\x01\\0end\\\\\"; char c = '\\xff';",
+        ];
+
+        for options in &options {
+            for input in inputs {
+                assert_idempotent(input, options);
+            }
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(matches!(
+            format(Path::new("main.c"), "size_t x;", &FormatOptions::new()),
+            Err(WreccError::Comp(_))
+        ));
+    }
+}
@@ -0,0 +1,332 @@
+//! The `wrecc` compiler as a library, so that tools other than the command-line driver can reuse
+//! its pipeline.<br>
+//! A [Session] compiles files with the same [CompileOptions] and can stop after every stage of
+//! the pipeline, returning tokens, the [HIR](compiler::parser::hir), the
+//! [MIR](compiler::typechecker::mir), the [LIR](compiler::codegen::lir) or assembly-text.
+//! Files can also be added to the session's in-memory file-system, so that neither the compiled
+//! file nor its includes have to exist on disk.
+//!
+//! ```
+//! use wrecc_compiler::{CompileOptions, Session};
+//!
+//! let mut session = Session::new(CompileOptions::new().define("N", "3"));
+//! session.add_file("lib.h", "int twice(int n) { return 2 * n; }");
+//! session.add_file("main.c", "#include <lib.h>\nint main() { return twice(N); }");
+//!
+//! let assembly = session.assembly("main.c".as_ref()).unwrap();
+//! assert!(assembly.contains("twice:"));
+//! ```
+
+pub mod assembler;
+pub mod compiler;
+pub mod dot;
+pub mod dump;
+pub mod formatter;
+pub mod preprocessor;
+
+use compiler::{
+    codegen::arch, codegen::arch::Target, codegen::lir::Lir, codegen::register_allocation::*, codegen::*,
+    common::error::*, common::token::Token, parser::*, scanner::*, typechecker::*,
+};
+use preprocessor::{scanner::Scanner as PPScanner, *};
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Preprocesses given input file by converting String into preprocessor-tokens.<br>
+pub fn preprocess(
+    filename: &Path,
+    user_include_dirs: &Vec<PathBuf>,
+    defines: &Vec<(String, String)>,
+    //standard_headers: &HashMap<PathBuf, &'static str>,
+    source: String,
+) -> Result<Vec<PPToken>, WreccError> {
+    preprocess_with_files(filename, user_include_dirs, defines, &HashMap::new(), source)
+}
+
+fn preprocess_with_files(
+    filename: &Path,
+    user_include_dirs: &Vec<PathBuf>,
+    defines: &Vec<(String, String)>,
+    virtual_files: &HashMap<PathBuf, String>,
+    source: String,
+) -> Result<Vec<PPToken>, WreccError> {
+    let tokens = PPScanner::new(source).scan_token();
+    let include_depth = 0;
+
+    // INFO: convert all cli-passed defines to #defines as if they were in regular source file
+    // to properly error check them
+    let mut dummy_defines = String::new();
+    for (macro_name, value) in defines {
+        dummy_defines.push_str(&format!("#define {} {}\n", macro_name, value));
+    }
+    let (_, defines) = Preprocessor::new(
+        &PathBuf::from("command-line-argument"),
+        PPScanner::new(dummy_defines).scan_token(),
+        HashMap::new(),
+        user_include_dirs,
+        //standard_headers,
+        virtual_files,
+        include_depth,
+    )
+    .start()
+    .map_err(|errors| WreccError::Cli(errors.iter().map(|e| e.kind.message()).collect()))?;
+
+    Ok(Preprocessor::new(
+        filename,
+        tokens,
+        defines,
+        user_include_dirs,
+        //standard_headers,
+        virtual_files,
+        include_depth,
+    )
+    .start()
+    .map(|(tokens, _)| tokens)?)
+}
+
+/// The options shared by all files compiled in a [Session], set using the builder-methods
+#[derive(Debug, Clone)]
+pub struct CompileOptions {
+    include_dirs: Vec<PathBuf>,
+    defines: Vec<(String, String)>,
+    opt_level: u8,
+    target: Target,
+    debug_info: bool,
+    dump_ast: bool,
+}
+impl CompileOptions {
+    /// The default options compile unoptimized x86-64 code without debug-information
+    pub fn new() -> Self {
+        CompileOptions {
+            include_dirs: Vec::new(),
+            defines: Vec::new(),
+            opt_level: 0,
+            target: Target::X86_64,
+            debug_info: false,
+            dump_ast: false,
+        }
+    }
+    /// Adds a directory to search for `#include`'d headers, like `-I`
+    pub fn include_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.include_dirs.push(dir.into());
+        self
+    }
+    /// Defines a macro before preprocessing, like `-D`
+    pub fn define(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.defines.push((name.into(), value.into()));
+        self
+    }
+    pub fn opt_level(mut self, opt_level: u8) -> Self {
+        self.opt_level = opt_level;
+        self
+    }
+    pub fn target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+    /// Includes debug-information for the compiled file in the LIR and assembly, like `-g`
+    pub fn debug_info(mut self, debug_info: bool) -> Self {
+        self.debug_info = debug_info;
+        self
+    }
+    /// Prints the parsed [HIR](compiler::parser::hir) to stderr
+    pub fn dump_ast(mut self, dump_ast: bool) -> Self {
+        self.dump_ast = dump_ast;
+        self
+    }
+
+    pub fn get_target(&self) -> Target {
+        self.target
+    }
+}
+impl Default for CompileOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Compiles files with the same [CompileOptions].<br>
+/// Every stage runs all stages before it, starting by reading the file from the in-memory
+/// file-system or the disk.
+pub struct Session {
+    options: CompileOptions,
+
+    // files that only exist in memory
+    files: HashMap<PathBuf, String>,
+}
+impl Session {
+    pub fn new(options: CompileOptions) -> Self {
+        Session { options, files: HashMap::new() }
+    }
+    pub fn options(&self) -> &CompileOptions {
+        &self.options
+    }
+
+    /// Adds a file to the in-memory file-system, which takes precedence over files on disk.<br>
+    /// Headers in it can also be included by their path alone, independent of the including file.
+    pub fn add_file(&mut self, path: impl Into<PathBuf>, source: impl Into<String>) -> &mut Self {
+        self.files.insert(path.into(), source.into());
+        self
+    }
+
+    /// Returns the contents of the file
+    pub fn source(&self, file: &Path) -> Result<String, WreccError> {
+        match self.files.get(file) {
+            Some(source) => Ok(source.clone()),
+            None => std::fs::read_to_string(file)
+                .map_err(|_| WreccError::Sys(format!("could not find file: '{}'", file.display()))),
+        }
+    }
+
+    /// Runs the preprocessor on the file
+    pub fn preprocess(&self, file: &Path) -> Result<Vec<PPToken>, WreccError> {
+        preprocess_with_files(
+            file,
+            &self.options.include_dirs,
+            &self.options.defines,
+            &self.files,
+            self.source(file)?,
+        )
+    }
+
+    /// Scans the preprocessed file into tokens
+    pub fn tokens(&self, file: &Path) -> Result<Vec<Token>, WreccError> {
+        Ok(Scanner::new(self.preprocess(file)?).scan_token()?)
+    }
+
+    /// Parses the file into the [HIR](compiler::parser::hir)
+    pub fn hir(&self, file: &Path) -> Result<Vec<hir::decl::ExternalDeclaration>, WreccError> {
+        let parse_tree = Parser::new(self.tokens(file)?).parse()?;
+
+        if self.options.dump_ast {
+            parse_tree.iter().for_each(|decl| eprintln!("{}", decl));
+        }
+
+        Ok(parse_tree)
+    }
+
+    /// Typechecks the file into the [MIR](compiler::typechecker::mir),
+    /// which is shared by all backends except the x86-64 one
+    pub fn mir(&self, file: &Path) -> Result<(Vec<mir::decl::ExternalDeclaration>, ConstLabels), WreccError> {
+        Ok(TypeChecker::new().check(self.hir(file)?)?)
+    }
+
+    /// Typechecks the file and returns the warnings found by the [analyzer](compiler::analyzer)
+    pub fn analyze(&self, file: &Path) -> Result<Vec<Error>, WreccError> {
+        let (mir, _) = self.mir(file)?;
+
+        Ok(compiler::analyzer::analyze(&mir))
+    }
+
+    /// Compiles the file to register-allocated x86-64 [LIR](compiler::codegen::lir::Lir)
+    pub fn lir(&self, file: &Path) -> Result<Vec<Lir>, WreccError> {
+        let (mir, const_labels) = self.mir(file)?;
+        let debug_file = self.options.debug_info.then(|| file.to_path_buf());
+
+        let (lir, live_intervals) = Compiler::new(const_labels, self.options.opt_level, debug_file).translate(mir);
+
+        Ok(RegisterAllocation::new(live_intervals).generate(lir))
+    }
+
+    /// Compiles the file to x86-64 [LIR](compiler::codegen::lir::Lir) before register allocation,
+    /// where temporaries are still virtual registers
+    pub fn unallocated_lir(&self, file: &Path) -> Result<Vec<Lir>, WreccError> {
+        let (mir, const_labels) = self.mir(file)?;
+        let debug_file = self.options.debug_info.then(|| file.to_path_buf());
+
+        Ok(Compiler::new(const_labels, self.options.opt_level, debug_file).translate(mir).0)
+    }
+
+    /// Compiles the file to assembly for the [target](Target) of the options.<br>
+    /// For wasm32 the assembly is the module in WebAssembly text-format.
+    pub fn assembly(&self, file: &Path) -> Result<String, WreccError> {
+        if self.options.target != Target::X86_64 {
+            let (mir, const_labels) = self.mir(file)?;
+
+            return Ok(arch::translate(self.options.target, mir, const_labels));
+        }
+
+        let output = self
+            .lir(file)?
+            .into_iter()
+            .map(|instr| instr.as_string())
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stages() {
+        let mut session = Session::new(CompileOptions::new());
+        session.add_file("main.c", "int main() { return 1 + 2; }");
+        let file = Path::new("main.c");
+
+        assert_eq!(session.tokens(file).unwrap().len(), 11);
+        assert_eq!(session.hir(file).unwrap().len(), 1);
+        assert_eq!(session.mir(file).unwrap().0.len(), 1);
+        assert!(matches!(session.lir(file).unwrap().first(), Some(Lir::FuncSetup(name, ..)) if name == "main"));
+        assert!(session.assembly(file).unwrap().contains("main:"));
+
+        let mut session = Session::new(CompileOptions::new().target(Target::Aarch64));
+        session.add_file("main.c", "int main() { return 1 + 2; }");
+        assert!(session.assembly(file).unwrap().contains("main:"));
+        assert!(matches!(session.assembly(Path::new("missing.c")), Err(WreccError::Sys(_))));
+    }
+
+    #[test]
+    fn virtual_includes() {
+        let mut session = Session::new(CompileOptions::new().include_dir("/inc").define("VALUE", "4"));
+        session
+            .add_file("/src/local.h", "#define LOCAL 1")
+            .add_file("/inc/system.h", "#define SYSTEM 2")
+            .add_file("named.h", "#define NAMED 3")
+            .add_file(
+                "/src/main.c",
+                "#include \"local.h\"\n#include <system.h>\n#include <named.h>\nLOCAL SYSTEM NAMED VALUE",
+            );
+
+        let tokens: String = session
+            .preprocess(Path::new("/src/main.c"))
+            .unwrap()
+            .iter()
+            .map(|t| t.kind.to_string())
+            .collect();
+        assert_eq!(tokens.split_whitespace().collect::<Vec<_>>(), ["1", "2", "3", "4"]);
+    }
+
+    #[test]
+    fn structured_errors() {
+        let mut session = Session::new(CompileOptions::new());
+        session.add_file("main.c", "int main() {\n  return x;\n}");
+
+        let Err(WreccError::Comp(errors)) = session.mir(Path::new("main.c")) else {
+            unreachable!("undeclared symbol")
+        };
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].line_index, errors[0].column), (2, 10));
+        assert_eq!(errors[0].kind, ErrorKind::UndeclaredSymbol("x".to_string()));
+        assert_eq!(errors[0].filename, PathBuf::from("main.c"));
+    }
+
+    #[test]
+    fn analyze() {
+        let mut session = Session::new(CompileOptions::new());
+        session.add_file("main.c", "int main() {\n  int x;\n  return x;\n}");
+        let file = Path::new("main.c");
+
+        let warnings = session.analyze(file).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!((warnings[0].line_index, warnings[0].column), (3, 3));
+        assert_eq!(warnings[0].kind, ErrorKind::UninitializedUse("x".to_string()));
+
+        // warnings don't stop compilation
+        assert!(session.assembly(file).unwrap().contains("main:"));
+    }
+}
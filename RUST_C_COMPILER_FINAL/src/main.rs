@@ -1,102 +1,150 @@
 mod cli_options;
 mod temp_file;
 
-pub mod compiler;
-pub mod preprocessor;
-
 use cli_options::*;
 use temp_file::*;
-use compiler::{
-    codegen::register_allocation::*, codegen::*, common::error::*, parser::*, scanner::*, typechecker::*,
-};
-use preprocessor::{scanner::Scanner as PPScanner, *};
+use wrecc_compiler::compiler::{codegen::arch, codegen::arch::Target, codegen::lir::Lir, common::error::*, interpreter};
+use wrecc_compiler::preprocessor::PPToken;
+use wrecc_compiler::*;
 
-use std::collections::HashMap;
 use std::fs;
-use std::io::Write;
+use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
-/// Preprocesses given input file by converting String into preprocessor-tokens.<br>
-pub fn preprocess(
-    filename: &Path,
-    user_include_dirs: &Vec<PathBuf>,
-    defines: &Vec<(String, String)>,
-    //standard_headers: &HashMap<PathBuf, &'static str>,
-    source: String,
-) -> Result<Vec<PPToken>, WreccError> {
-    let tokens = PPScanner::new(source).scan_token();
-    let include_depth = 0;
-
-    // INFO: convert all cli-passed defines to #defines as if they were in regular source file
-    // to properly error check them
-    let mut dummy_defines = String::new();
-    for (macro_name, value) in defines {
-        dummy_defines.push_str(&format!("#define {} {}\n", macro_name, value));
-    }
-    let (_, defines) = Preprocessor::new(
-        &PathBuf::from("command-line-argument"),
-        PPScanner::new(dummy_defines).scan_token(),
-        HashMap::new(),
-        user_include_dirs,
-        //standard_headers,
-        include_depth,
-    )
-    .start()
-    .map_err(|errors| WreccError::Cli(errors.iter().map(|e| e.kind.message()).collect()))?;
-
-    Ok(Preprocessor::new(
-        filename,
-        tokens,
-        defines,
-        user_include_dirs,
-        //standard_headers,
-        include_depth,
-    )
-    .start()
-    .map(|(tokens, _)| tokens)?)
-}
-
-/// Compiles preprocessor-tokens to a x86-64 string, using functionality defined in [compiler]
-pub fn compile(source: Vec<PPToken>, dump_ast: bool) -> Result<String, WreccError> {
-    let tokens = Scanner::new(source).scan_token()?;
+fn generate_asm_file(options: &CliOptions, file: &Path, output: String) -> Result<OutFile, WreccError> {
+    let extension = match options.target {
+        Target::Wasm32 => "wat",
+        _ => "s",
+    };
+    let output_path = output_path(file, &options.output_path, options.compile_only, extension);
 
-    let parse_tree = Parser::new(tokens).parse()?;
+    let mut output_file = std::fs::File::create(output_path.get()).map_err(|_| {
+        WreccError::Sys(format!("could not create file '{}'", output_path.get().display()))
+    })?;
 
-    if dump_ast {
-        parse_tree.iter().for_each(|decl| eprintln!("{}", decl));
+    if writeln!(output_file, "{}", output).is_err() {
+        Err(WreccError::Sys(format!(
+            "could not write to file '{}'",
+            output_path.get().display()
+        )))
+    } else {
+        Ok(output_path)
     }
+}
 
-    let (mir, const_labels) = TypeChecker::new().check(parse_tree)?;
+fn generate_object_file(options: &CliOptions, file: &Path, lir: Vec<Lir>) -> Result<OutFile, WreccError> {
+    let output_path = output_path(file, &options.output_path, options.no_link, "o");
 
-    let (lir, live_intervals) = Compiler::new(const_labels).translate(mir);
+    fs::write(output_path.get(), assembler::assemble(lir)).map_err(|_| {
+        WreccError::Sys(format!(
+            "could not write to file '{}'",
+            output_path.get().display()
+        ))
+    })?;
 
-    let asm = RegisterAllocation::new(live_intervals).generate(lir);
+    Ok(output_path)
+}
 
-    let output = asm
-        .into_iter()
-        .map(|instr| instr.as_string())
-        .collect::<Vec<String>>()
-        .join("\n");
+fn generate_llvm_file(options: &CliOptions, session: &Session, file: &Path) -> Result<(), WreccError> {
+    let output_path = output_path(file, &options.output_path, true, "ll");
+    let (mir, const_labels) = session.mir(file)?;
 
-    Ok(output)
+    fs::write(
+        output_path.get(),
+        arch::llvm::translate(options.target, mir, const_labels),
+    )
+    .map_err(|_| {
+        WreccError::Sys(format!(
+            "could not write to file '{}'",
+            output_path.get().display()
+        ))
+    })
 }
 
-fn generate_asm_file(options: &CliOptions, file: &Path, output: String) -> Result<OutFile, WreccError> {
-    let output_path = output_path(file, &options.output_path, options.compile_only, "s");
+fn generate_dot_file(options: &CliOptions, session: &Session, file: &Path, emit: Emit) -> Result<(), WreccError> {
+    let output_path = output_path(file, &options.output_path, true, "dot");
+    let graph = match emit {
+        Emit::DotAst => dot::ast(session, file)?,
+        Emit::DotCfg => dot::cfg(session, file)?,
+        Emit::Llvm => unreachable!("not a graph"),
+    };
 
-    let mut output_file = std::fs::File::create(output_path.get()).map_err(|_| {
-        WreccError::Sys(format!("could not create file '{}'", output_path.get().display()))
-    })?;
+    fs::write(output_path.get(), graph).map_err(|_| {
+        WreccError::Sys(format!(
+            "could not write to file '{}'",
+            output_path.get().display()
+        ))
+    })
+}
 
-    if writeln!(output_file, "{}", output).is_err() {
-        Err(WreccError::Sys(format!(
+// wasm-modules are complete programs so they aren't assembled or linked
+fn generate_wasm_file(options: &CliOptions, session: &Session, file: &Path) -> Result<(), WreccError> {
+    let output_path = output_path(file, &options.output_path, true, "wasm");
+    let (mir, const_labels) = session.mir(file)?;
+
+    fs::write(
+        output_path.get(),
+        arch::wasm32::translate(mir, const_labels).encode(),
+    )
+    .map_err(|_| {
+        WreccError::Sys(format!(
             "could not write to file '{}'",
             output_path.get().display()
-        )))
-    } else {
-        Ok(output_path)
+        ))
+    })
+}
+
+fn assemble(options: &CliOptions, file: &Path, asm_file: OutFile) -> Result<OutFile, WreccError> {
+    let output_path = output_path(file, &options.output_path, options.no_link, "o");
+
+    let assembler = format!("{}as", options.target.toolchain_prefix());
+    let output = Command::new(&assembler)
+        .arg(asm_file.get())
+        .arg("-o")
+        .arg(output_path.get())
+        .output()
+        .map_err(|_| WreccError::Sys(format!("could not invoke assembler '{}'", assembler)))?;
+
+    if !output.status.success() {
+        return Err(WreccError::Sys(format!(
+            "assembler failed:\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
     }
+
+    Ok(output_path)
+}
+
+fn link(options: &CliOptions, object_files: Vec<OutFile>) -> Result<(), WreccError> {
+    let linker = match options.target.toolchain_prefix() {
+        "" => "cc".to_string(),
+        prefix => format!("{}gcc", prefix),
+    };
+    let mut cmd = Command::new(&linker);
+    cmd.arg("-o")
+        .arg(options.output_path.clone().unwrap_or(PathBuf::from("a.out")))
+        .args(object_files.iter().map(|file| file.get()));
+
+    for path in options.lib_paths.iter() {
+        cmd.arg(format!("-L{}", path.display()));
+    }
+    for lib in options.shared_libs.iter() {
+        cmd.arg(format!("-l{}", lib));
+    }
+
+    let output = cmd
+        .output()
+        .map_err(|_| WreccError::Sys(format!("could not invoke linker '{}'", linker)))?;
+
+    if !output.status.success() {
+        return Err(WreccError::Sys(format!(
+            "linker failed:\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+
+    Ok(())
 }
 
 fn output_path(
@@ -112,11 +160,6 @@ fn output_path(
     }
 }
 
-fn read_input_file(file: &Path) -> Result<String, WreccError> {
-    fs::read_to_string(file)
-        .map_err(|_| WreccError::Sys(format!("could not find file: '{}'", file.display())))
-}
-
 fn print_pp(pp_source: Vec<PPToken>, options: &CliOptions) -> Result<(), WreccError> {
     let pp_string: String = pp_source.iter().map(|s| s.kind.to_string()).collect();
 
@@ -138,49 +181,144 @@ fn print_pp(pp_source: Vec<PPToken>, options: &CliOptions) -> Result<(), WreccEr
     }
 }
 
-fn process_file(options: &CliOptions) -> Result<Option<OutFile>, WreccError> {
-    let file = Path::new("../prueba.txt");
+// writes every stage selected with `--dump` into its own file
+fn write_dumps(options: &CliOptions, session: &Session, file: &Path) -> Result<(), WreccError> {
+    for stage in options.dump_stages.iter() {
+        let output = dump::dump(session, file, *stage, options.dump_format)?;
+        let dump_path = dump::output_path(file, *stage, options.dump_format);
+
+        fs::write(&dump_path, output).map_err(|_| {
+            WreccError::Sys(format!("could not write to file '{}'", dump_path.display()))
+        })?;
+    }
+
+    Ok(())
+}
+
+// prints the warnings of the analyzer, which don't stop compilation
+fn analyze(options: &CliOptions, session: &Session, file: &Path) -> Result<(), WreccError> {
+    if options.analyze && !options.preprocess_only {
+        print_warnings(&session.analyze(file)?, options.no_color);
+    }
 
-    let source = read_input_file(&file)?;
+    Ok(())
+}
 
-    let pp_source = preprocess(
-        &file,
-        &Vec::new(),
-        &Vec::new(),
-        //standard_headers,
-        source,
-    )?;
+fn process_file(options: &CliOptions, session: &Session, file: &Path) -> Result<Option<OutFile>, WreccError> {
+    write_dumps(options, session, file)?;
+    analyze(options, session, file)?;
 
-    //print_pp(pp_source, &options)?;
+    if options.preprocess_only {
+        print_pp(session.preprocess(file)?, options)?;
+        return Ok(None);
+    }
 
-    let asm_source = compile(pp_source, false)?;
+    match options.emit {
+        Some(Emit::Llvm) => {
+            generate_llvm_file(options, session, file)?;
+            return Ok(None);
+        }
+        Some(emit) => {
+            generate_dot_file(options, session, file, emit)?;
+            return Ok(None);
+        }
+        None => (),
+    }
 
-    let _asm_file = generate_asm_file(&options, file, asm_source)?;
+    if options.target == Target::Wasm32 && !options.compile_only {
+        generate_wasm_file(options, session, file)?;
+        return Ok(None);
+    }
 
-    Ok(None)
+    let object_file = if options.integrated_as && !options.compile_only {
+        generate_object_file(options, file, session.lir(file)?)?
+    } else {
+        let asm_file = generate_asm_file(options, file, session.assembly(file)?)?;
+
+        if options.compile_only {
+            return Ok(None);
+        }
+
+        assemble(options, file, asm_file)?
+    };
+
+    if options.no_link {
+        return Ok(None);
+    }
+
+    Ok(Some(object_file))
+}
+
+// the interpreter recurses for every nested call of the program so it needs a bigger stack
+const INTERPRETER_STACK_SIZE: usize = 1 << 30;
+
+/// Runs the single input file in the [interpreter] and returns the exit-code of the program
+fn run_program(options: &CliOptions) -> Result<i32, WreccError> {
+    let file = options.files[0].clone();
+    let session = Session::new(options.compile_options());
+    write_dumps(options, &session, &file)?;
+    analyze(options, &session, &file)?;
+
+    if options.preprocess_only {
+        print_pp(session.preprocess(&file)?, options)?;
+        return Ok(0);
+    }
+
+    std::thread::Builder::new()
+        .stack_size(INTERPRETER_STACK_SIZE)
+        .spawn(move || {
+            let (mir, const_labels) = session.mir(&file)?;
+
+            interpreter::run(mir, const_labels, &mut BufWriter::new(std::io::stdout().lock()))
+        })
+        .map_err(|_| WreccError::Sys("could not start interpreter".to_string()))?
+        .join()
+        .expect("interpreter panicked")
 }
 
 fn run(options: CliOptions) -> Result<(), Vec<WreccError>> {
+    let session = Session::new(options.compile_options());
+    let mut object_files = Vec::new();
     let mut errors = Vec::new();
 
-    match process_file(&options) {
-        Ok(_) => (),
-        Err(e) => errors.push(e),
+    for file in options.files.iter() {
+        match process_file(&options, &session, file) {
+            Ok(Some(object_file)) => object_files.push(object_file),
+            Ok(None) => (),
+            Err(e) => errors.push(e),
+        }
     }
 
     if !errors.is_empty() {
         return Err(errors);
     }
 
+    if !object_files.is_empty() {
+        link(&options, object_files).map_err(|e| vec![e])?;
+    }
+
     Ok(())
 }
 
 fn rm()  -> Result<(), ()>{
-    let mut options = CliOptions::new();
-    options.output_path = Some(PathBuf::from("../resultado.txt"));
+    // without any arguments the example-file is compiled
+    let options = if std::env::args().len() > 1 {
+        CliOptions::parse().map_err(|e| e.print(false))?
+    } else {
+        let mut options = CliOptions::new();
+        options.files = vec![PathBuf::from("../prueba.txt")];
+        options.output_path = Some(PathBuf::from("../resultado.txt"));
+        options.compile_only = true;
+        options
+    };
 
     let no_color = options.no_color;
 
+    if options.run {
+        let code = run_program(&options).map_err(|e| e.print(no_color))?;
+        std::process::exit(code);
+    }
+
     run(options).map_err(|errs| errs.into_iter().map(|e| e.print(no_color)).collect())
 }
 
@@ -82,6 +82,9 @@ pub struct Preprocessor<'a> {
     // standard header files are supported
     //standard_headers: &'a HashMap<PathBuf, &'static str>,
 
+    /// Files which only exist in memory, they are searched before the file-system
+    virtual_files: &'a HashMap<PathBuf, String>,
+
     /// Current number of nest-depth, to stop recursion stack-overflow during `#include`
     include_depth: usize,
 
@@ -96,6 +99,7 @@ impl<'a> Preprocessor<'a> {
         defines: Defines,
         user_include_dirs: &'a Vec<PathBuf>,
         //standard_headers: &'a HashMap<PathBuf, &'static str>,
+        virtual_files: &'a HashMap<PathBuf, String>,
         include_depth: usize,
     ) -> Self {
         Preprocessor {
@@ -104,6 +108,7 @@ impl<'a> Preprocessor<'a> {
             include_depth,
             user_include_dirs,
             //standard_headers,
+            virtual_files,
             defines,
             ifs: Vec::new(),
             max_include_depth: 200,
@@ -117,6 +122,7 @@ impl<'a> Preprocessor<'a> {
             self.defines.clone(),
             self.user_include_dirs,
             //self.standard_headers,
+            self.virtual_files,
             self.include_depth + 1,
         )
         .map_err(Error::new_multiple)?;
@@ -214,14 +220,14 @@ impl<'a> Preprocessor<'a> {
                 .parent()
                 .expect("empty filename")
                 .join(&file_path);
-            if let Ok(data) = fs::read_to_string(&file_path) {
+            if let Some(data) = self.read_file(&file_path) {
                 return Ok((file_path, data));
             }
         }
 
         for sys_path in self.user_include_dirs {
             let abs_system_path = sys_path.join(&file_path);
-            if let Ok(data) = fs::read_to_string(abs_system_path) {
+            if let Some(data) = self.read_file(&abs_system_path) {
                 return Ok((file_path, data));
             }
         }
@@ -229,12 +235,22 @@ impl<'a> Preprocessor<'a> {
         /*if let Some(data) = self.standard_headers.get(&file_path) {
             return Ok((file_path, data.to_string()));
         }*/
+        // virtual files can also be included by their name alone, like standard headers
+        if let Some(data) = self.virtual_files.get(&file_path) {
+            return Ok((file_path, data.clone()));
+        }
 
         Err(Error::new(
             &PPToken::from(&token, self.filename),
             ErrorKind::InvalidHeader(file_path.to_string_lossy().to_string()),
         ))
     }
+    fn read_file(&self, file_path: &Path) -> Option<String> {
+        match self.virtual_files.get(file_path) {
+            Some(data) => Some(data.clone()),
+            None => fs::read_to_string(file_path).ok(),
+        }
+    }
     fn define(&mut self, directive: Token) -> Result<(), Error> {
         self.skip_whitespace()?;
 
@@ -779,6 +795,7 @@ fn preprocess_included(
     defines: Defines,
     user_include_dirs: &Vec<PathBuf>,
     //standard_headers: &HashMap<PathBuf, &'static str>,
+    virtual_files: &HashMap<PathBuf, String>,
     include_depth: usize,
 ) -> Result<(Vec<PPToken>, Defines), Vec<Error>> {
     let tokens = PPScanner::new(source).scan_token();
@@ -789,6 +806,7 @@ fn preprocess_included(
         defines,
         user_include_dirs,
         //standard_headers,
+        virtual_files,
         include_depth,
     )
     .start()
@@ -889,8 +907,8 @@ mod tests {
                 .map(|(k, v)| (k.to_string(), scan(v)))
                 .collect();
             let v = Vec::new();
-            let h = HashMap::new();
-            let pp = Preprocessor::new(Path::new(""), Vec::new(), defined.clone(), &v, &h, 0);
+            let files = HashMap::new();
+            let pp = Preprocessor::new(Path::new(""), Vec::new(), defined.clone(), &v, &files, 0);
 
             let mut result = HashMap::new();
             for (name, _) in defined {
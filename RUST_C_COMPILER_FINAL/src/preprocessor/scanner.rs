@@ -1,6 +1,6 @@
 //! Scans input into [preprocessor-tokens](TokenKind) with corresponding locations in the input file.<br>
-//! Comments will be skipped and multiline-strings collapsed into single line, while still
-//! maintaining correct location.<br>
+//! Comments will be skipped, unless [kept](Scanner::keep_comments), and multiline-strings collapsed
+//! into single line, while still maintaining correct location.<br>
 //! Doesn't emit any errors.
 
 use crate::compiler::parser::double_peek::DoublePeek;
@@ -27,6 +27,7 @@ pub enum TokenKind {
     Ident(String),
     Number(String, String),
     Whitespace(String),
+    Comment(String),
     Other(char),
 }
 
@@ -59,7 +60,8 @@ impl Token {
             TokenKind::String(s)
             | TokenKind::CharLit(s)
             | TokenKind::Ident(s)
-            | TokenKind::Whitespace(s) => s.len(),
+            | TokenKind::Whitespace(s)
+            | TokenKind::Comment(s) => s.len(),
             TokenKind::Number(num, suffix) => num.len() + suffix.len(),
         }
     }
@@ -103,7 +105,8 @@ impl ToString for TokenKind {
             TokenKind::String(s)
             | TokenKind::CharLit(s)
             | TokenKind::Ident(s)
-            | TokenKind::Whitespace(s) => s.to_string(),
+            | TokenKind::Whitespace(s)
+            | TokenKind::Comment(s) => s.to_string(),
             TokenKind::Other(c) => c.to_string(),
             TokenKind::Number(num, suffix) => num.to_string() + suffix,
         }
@@ -117,6 +120,9 @@ pub struct Scanner {
     column: i32,
     line: i32,
     raw_source: Vec<String>,
+
+    // emit comments as tokens instead of skipping them
+    keep_comments: bool,
 }
 impl Scanner {
     pub fn new(source: String) -> Scanner {
@@ -131,6 +137,7 @@ impl Scanner {
                 .split('\n')
                 .map(|s| s.to_string())
                 .collect::<Vec<String>>(),
+            keep_comments: false,
             source: DoublePeek::new(source.chars().collect::<Vec<char>>()),
             directives: HashMap::from([
                 ("include", TokenKind::Include),
@@ -147,6 +154,11 @@ impl Scanner {
             ]),
         }
     }
+    /// Emits comments as [TokenKind::Comment] so that tools working on the source-text can keep them
+    pub fn keep_comments(mut self) -> Scanner {
+        self.keep_comments = true;
+        self
+    }
     pub fn scan_token(mut self) -> Vec<Token> {
         let mut result = Vec::new();
 
@@ -176,18 +188,26 @@ impl Scanner {
                 }
                 '/' if matches!(self.source.peek(), Some('/')) => {
                     self.source.next();
-                    let (_, (newlines, col)) =
+                    let (comment, (newlines, col)) =
                         self.consume_until("//", |ch, _| ch == '\n' && ch == '\0', false);
 
-                    self.line += newlines;
-                    self.column = col;
+                    if self.keep_comments {
+                        self.add_token(&mut result, TokenKind::Comment(comment), Some((newlines, col)));
+                    } else {
+                        self.line += newlines;
+                        self.column = col;
+                    }
                 }
                 '/' if matches!(self.source.peek(), Some('*')) => {
                     self.source.next();
-                    let (newlines, col) = self.multiline_comment();
+                    let (comment, (newlines, col)) = self.multiline_comment();
 
-                    self.line += newlines;
-                    self.column = col;
+                    if self.keep_comments {
+                        self.add_token(&mut result, TokenKind::Comment(comment), Some((newlines, col)));
+                    } else {
+                        self.line += newlines;
+                        self.column = col;
+                    }
                 }
                 _ if c.is_alphabetic() || c == '_' => {
                     let (ident, loc) = self.consume_until(
@@ -287,7 +307,8 @@ impl Scanner {
         }
         (result, (newlines, column))
     }
-    fn multiline_comment(&mut self) -> (i32, i32) {
+    fn multiline_comment(&mut self) -> (String, (i32, i32)) {
+        let mut comment = String::from("/*");
         let (mut newlines, mut column) = (0, self.column + 2);
 
         while let Some(peeked) = self.source.peek() {
@@ -295,6 +316,7 @@ impl Scanner {
                 '*' if matches!(self.source.double_peek(), Some('/')) => {
                     self.source.next();
                     self.source.next();
+                    comment.push_str("*/");
                     column += 2;
                     break;
                 }
@@ -305,11 +327,11 @@ impl Scanner {
                     } else {
                         column += 1;
                     }
-                    self.source.next();
+                    comment.push(self.source.next().unwrap());
                 }
             }
         }
-        (newlines, column)
+        (comment, (newlines, column))
     }
     fn add_token(&mut self, result: &mut Vec<Token>, kind: TokenKind, location: Option<(i32, i32)>) {
         let token = Token {
@@ -366,6 +388,26 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn kept_comments() {
+        let actual = Scanner::new("a /* one\ntwo */ b // three\nc".to_string())
+            .keep_comments()
+            .scan_token()
+            .into_iter()
+            .filter(|t| !matches!(t.kind, TokenKind::Whitespace(_)))
+            .map(|t| (t.kind, t.line, t.column))
+            .collect::<Vec<_>>();
+        let expected = vec![
+            (TokenKind::Ident("a".to_string()), 1, 1),
+            (TokenKind::Comment("/* one\ntwo */".to_string()), 1, 3),
+            (TokenKind::Ident("b".to_string()), 2, 8),
+            (TokenKind::Comment("// three".to_string()), 2, 10),
+            (TokenKind::Newline, 2, 18),
+            (TokenKind::Ident("c".to_string()), 3, 1),
+        ];
+
+        assert_eq!(actual, expected);
+    }
     #[test]
     fn string() {
         let actual = setup_tokenkind("#define \"some/* #*/define\"");
@@ -0,0 +1,336 @@
+use crate::{
+    lexer::{Token, TokenKind},
+    CompilerError,
+};
+
+// Analizador sintáctico LL(1) por descenso recursivo: cada regla decide qué hacer viendo solo
+// el siguiente token, y los errores se reportan en la línea original del token que falla
+pub fn check(tokens: &[Token]) -> Result<(), CompilerError> {
+    let mut checker = Checker { tokens, position: 0 };
+    while checker.peek().is_some() {
+        checker.statement()?;
+    }
+    Ok(())
+}
+
+struct Checker<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl Checker<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_kind(&self) -> Option<TokenKind> {
+        self.peek().map(|token| token.kind)
+    }
+
+    fn advance(&mut self) -> Option<&Token> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        token
+    }
+
+    // Error en el token actual, o en la última línea si el archivo terminó antes de tiempo
+    fn error(&self) -> CompilerError {
+        match self.peek() {
+            Some(token) if token.kind == TokenKind::Reserved => {
+                CompilerError::InvalidToken(token.text.clone(), token.span.line)
+            }
+            Some(token) => CompilerError::InvalidSyntax(token.span.line),
+            None => CompilerError::InvalidSyntax(self.tokens.last().map_or(1, |token| token.span.line)),
+        }
+    }
+
+    fn expect(&mut self, kind: TokenKind) -> Result<&Token, CompilerError> {
+        if self.peek_kind() == Some(kind) {
+            Ok(self.advance().unwrap())
+        } else {
+            Err(self.error())
+        }
+    }
+
+    fn accept(&mut self, kind: TokenKind) -> bool {
+        if self.peek_kind() == Some(kind) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn statement(&mut self) -> Result<(), CompilerError> {
+        match self.peek_kind() {
+            Some(TokenKind::DataType) => self.declaration(),
+            Some(TokenKind::If) => self.if_statement(),
+            Some(TokenKind::While) => {
+                self.advance();
+                self.condition()?;
+                self.block()
+            }
+            Some(TokenKind::Do) => {
+                self.advance();
+                self.block()?;
+                self.expect(TokenKind::While)?;
+                self.condition()?;
+                self.expect(TokenKind::Semicolon).map(|_| ())
+            }
+            Some(TokenKind::Return) => {
+                self.advance();
+                if self.peek_kind() != Some(TokenKind::Semicolon) {
+                    self.expression()?;
+                }
+                self.expect(TokenKind::Semicolon).map(|_| ())
+            }
+            Some(TokenKind::Identifier) => self.identifier_statement(),
+            Some(TokenKind::LBrace) => self.block(),
+            Some(TokenKind::Semicolon) => {
+                self.advance();
+                Ok(())
+            }
+            _ => Err(self.error()),
+        }
+    }
+
+    // `int a = 1, b;` o la definición de una función `int f(int a, char b) { ... }`
+    fn declaration(&mut self) -> Result<(), CompilerError> {
+        self.expect(TokenKind::DataType)?;
+        self.expect(TokenKind::Identifier)?;
+
+        if self.accept(TokenKind::LParen) {
+            self.parameters()?;
+            return self.block();
+        }
+
+        loop {
+            if self.accept(TokenKind::Assign) {
+                self.expression()?;
+            }
+            if !self.accept(TokenKind::Comma) {
+                break;
+            }
+            self.expect(TokenKind::Identifier)?;
+        }
+        self.expect(TokenKind::Semicolon).map(|_| ())
+    }
+
+    // Los parámetros después del "(", hasta el ")" inclusive
+    fn parameters(&mut self) -> Result<(), CompilerError> {
+        if self.accept(TokenKind::RParen) {
+            return Ok(());
+        }
+        loop {
+            let is_void = self.expect(TokenKind::DataType)?.text == "void";
+            // `f(void)` no recibe parámetros
+            if is_void && self.accept(TokenKind::RParen) {
+                return Ok(());
+            }
+            self.expect(TokenKind::Identifier)?;
+            if !self.accept(TokenKind::Comma) {
+                break;
+            }
+        }
+        self.expect(TokenKind::RParen).map(|_| ())
+    }
+
+    fn if_statement(&mut self) -> Result<(), CompilerError> {
+        self.expect(TokenKind::If)?;
+        self.condition()?;
+        self.block()?;
+        if self.accept(TokenKind::Else) {
+            if self.peek_kind() == Some(TokenKind::If) {
+                return self.if_statement();
+            }
+            return self.block();
+        }
+        Ok(())
+    }
+
+    // Asignación `a = b;`, `a += b;`, `a++;` o llamada a función `f(a, b);`
+    fn identifier_statement(&mut self) -> Result<(), CompilerError> {
+        self.expect(TokenKind::Identifier)?;
+        match self.peek_kind() {
+            Some(TokenKind::Assign) => {
+                self.advance();
+                self.expression()?;
+            }
+            Some(TokenKind::CompoundAssign) => {
+                let operator = self.advance().unwrap().text.clone();
+                if operator != "++" && operator != "--" {
+                    self.expression()?;
+                }
+            }
+            Some(TokenKind::LParen) => {
+                self.advance();
+                self.arguments()?;
+            }
+            _ => return Err(self.error()),
+        }
+        self.expect(TokenKind::Semicolon).map(|_| ())
+    }
+
+    fn condition(&mut self) -> Result<(), CompilerError> {
+        self.expect(TokenKind::LParen)?;
+        self.expression()?;
+        self.expect(TokenKind::RParen).map(|_| ())
+    }
+
+    fn block(&mut self) -> Result<(), CompilerError> {
+        self.expect(TokenKind::LBrace)?;
+        while self.peek_kind() != Some(TokenKind::RBrace) {
+            if self.peek().is_none() {
+                return Err(self.error());
+            }
+            self.statement()?;
+        }
+        self.advance();
+        Ok(())
+    }
+
+    // Solo se valida la forma de la expresión, así que todos los operadores binarios son iguales
+    fn expression(&mut self) -> Result<(), CompilerError> {
+        self.unary()?;
+        while self.is_binary_operator() {
+            self.advance();
+            self.unary()?;
+        }
+        Ok(())
+    }
+
+    fn is_binary_operator(&self) -> bool {
+        match self.peek() {
+            Some(token) => match token.kind {
+                TokenKind::Operator | TokenKind::Bitwise => token.text != "!" && token.text != "~",
+                TokenKind::Logical | TokenKind::Comparison => true,
+                _ => false,
+            },
+            None => false,
+        }
+    }
+
+    fn unary(&mut self) -> Result<(), CompilerError> {
+        if self.peek().is_some_and(|token| matches!(token.text.as_str(), "!" | "-" | "~")) {
+            self.advance();
+            return self.unary();
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<(), CompilerError> {
+        match self.peek_kind() {
+            Some(TokenKind::Identifier) => {
+                self.advance();
+                if self.accept(TokenKind::LParen) {
+                    self.arguments()?;
+                }
+                Ok(())
+            }
+            Some(TokenKind::Number | TokenKind::Chr | TokenKind::Str) => {
+                self.advance();
+                Ok(())
+            }
+            Some(TokenKind::LParen) => self.condition(),
+            _ => Err(self.error()),
+        }
+    }
+
+    // Los argumentos después del "(", hasta el ")" inclusive
+    fn arguments(&mut self) -> Result<(), CompilerError> {
+        if self.accept(TokenKind::RParen) {
+            return Ok(());
+        }
+        self.expression()?;
+        while self.accept(TokenKind::Comma) {
+            self.expression()?;
+        }
+        self.expect(TokenKind::RParen).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::tokenize;
+
+    fn compile(source: &str) -> Result<(), CompilerError> {
+        check(&tokenize(source)?)
+    }
+
+    fn syntax_error_line(source: &str) -> u16 {
+        match compile(source) {
+            Err(CompilerError::InvalidSyntax(line)) => line,
+            other => panic!("expected a syntax error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn prueba() {
+        compile(include_str!("../../prueba.txt")).unwrap();
+    }
+
+    #[test]
+    fn declarations_and_assignments() {
+        compile("char c = 'a';\nint b, b1 = 2 * (b + 1), b2 = f(b, 'x');\nfloat x;\nx = 0.01;\nb += 3;\nb--;").unwrap();
+    }
+
+    #[test]
+    fn control_structures() {
+        let source = r#"
+            void funA (int c, int j, char opc){
+                if (opc != 'y'){
+                    return;
+                } else if (c + j < 10 && !(c == 1)){
+                    while (j<10){
+                        printf("%d", c+j);
+                        j++;
+                    }
+                } else {
+                    do { j = j - 1; } while (j > 0);
+                }
+            }
+        "#;
+        compile(source).unwrap();
+    }
+
+    #[test]
+    fn several_statements_in_one_line() {
+        compile("int main(void) { int a; a = 1; if (a) { a = 2; } return a; }").unwrap();
+        assert_eq!(syntax_error_line("int main(void) { int a; a = ; }"), 1);
+    }
+
+    #[test]
+    fn missing_semicolon_is_reported_at_the_next_token() {
+        assert_eq!(syntax_error_line("int a = 1;\n\nb = 2\nc = 3;"), 4);
+    }
+
+    #[test]
+    fn line_numbers_count_comments() {
+        let source = "//The main function\nint main() {\n    /* a\n     b */\n    int x = ;\n}";
+        assert_eq!(syntax_error_line(source), 5);
+    }
+
+    #[test]
+    fn unclosed_block() {
+        assert_eq!(syntax_error_line("int main() {\n  int a;\n\n"), 2);
+    }
+
+    #[test]
+    fn else_without_if() {
+        assert_eq!(syntax_error_line("int a;\nelse { a = 1; }"), 2);
+    }
+
+    #[test]
+    fn unsupported_reserved_word() {
+        match compile("int a;\nfor (a = 0; a < 3; a++) {}") {
+            Err(CompilerError::InvalidToken(token, line)) => assert_eq!((token.as_str(), line), ("for", 2)),
+            other => panic!("expected an invalid token, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn invalid_token() {
+        assert!(matches!(compile("int a;\n\nint b = a # 2;"), Err(CompilerError::InvalidToken(_, 3))));
+    }
+}
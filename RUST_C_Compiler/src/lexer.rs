@@ -0,0 +1,323 @@
+use crate::CompilerError;
+use std::{iter::Peekable, str::Chars};
+
+// Posición original del token en el archivo, empezando en 1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub line: u16,
+    pub column: u16,
+}
+
+// Las mismas familias que antes se reconocían con expresiones regulares
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenKind {
+    Str,
+    Chr,
+    While,
+    If,
+    Else,
+    Do,
+    Return,
+    DataType,
+    // Palabras reservadas fuera del subconjunto de C soportado
+    Reserved,
+    Identifier,
+    Number,
+    CompoundAssign,
+    Operator,
+    Logical,
+    Bitwise,
+    Comparison,
+    Assign,
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    Comma,
+    Dot,
+    Semicolon,
+    Colon,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+    pub span: Span,
+}
+
+const RESERVED_WORDS: &[&str] = &[
+    "auto", "long", "switch", "break", "enum", "register", "typedef", "case", "extern", "union",
+    "short", "unsigned", "const", "for", "signed", "continue", "goto", "sizeof", "volatile",
+    "default", "static", "struct", "_Packed",
+];
+
+// Los identificadores de C solo son significativos hasta 31 caracteres
+const MAX_IDENTIFIER_LENGTH: usize = 31;
+
+// Operadores ordenados de mayor a menor longitud para tomar siempre el más largo
+const OPERATORS: &[(&str, TokenKind)] = &[
+    ("+=", TokenKind::CompoundAssign),
+    ("-=", TokenKind::CompoundAssign),
+    ("*=", TokenKind::CompoundAssign),
+    ("/=", TokenKind::CompoundAssign),
+    ("++", TokenKind::CompoundAssign),
+    ("--", TokenKind::CompoundAssign),
+    ("&&", TokenKind::Logical),
+    ("||", TokenKind::Logical),
+    ("<<", TokenKind::Bitwise),
+    (">>", TokenKind::Bitwise),
+    ("==", TokenKind::Comparison),
+    ("!=", TokenKind::Comparison),
+    ("<=", TokenKind::Comparison),
+    (">=", TokenKind::Comparison),
+    ("<>", TokenKind::Comparison),
+    ("+", TokenKind::Operator),
+    ("-", TokenKind::Operator),
+    ("*", TokenKind::Operator),
+    ("/", TokenKind::Operator),
+    ("%", TokenKind::Operator),
+    ("^", TokenKind::Operator),
+    ("!", TokenKind::Operator),
+    ("&", TokenKind::Bitwise),
+    ("|", TokenKind::Bitwise),
+    ("~", TokenKind::Bitwise),
+    ("<", TokenKind::Comparison),
+    (">", TokenKind::Comparison),
+    ("=", TokenKind::Assign),
+    ("(", TokenKind::LParen),
+    (")", TokenKind::RParen),
+    ("{", TokenKind::LBrace),
+    ("}", TokenKind::RBrace),
+    ("[", TokenKind::LBracket),
+    ("]", TokenKind::RBracket),
+    (",", TokenKind::Comma),
+    (".", TokenKind::Dot),
+    (";", TokenKind::Semicolon),
+    (":", TokenKind::Colon),
+];
+
+// Convierte el archivo completo en tokens, sin perder la línea original de cada uno
+pub fn tokenize(source: &str) -> Result<Vec<Token>, CompilerError> {
+    let mut lexer = Lexer {
+        chars: source.chars().peekable(),
+        span: Span { line: 1, column: 1 },
+    };
+    let mut tokens = Vec::new();
+    while let Some(token) = lexer.next_token()? {
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+struct Lexer<'a> {
+    chars: Peekable<Chars<'a>>,
+    span: Span,
+}
+
+impl Lexer<'_> {
+    fn advance(&mut self) -> Option<char> {
+        let ch = self.chars.next()?;
+        if ch == '\n' {
+            self.span.line += 1;
+            self.span.column = 1;
+        } else {
+            self.span.column += 1;
+        }
+        Some(ch)
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    // Ignora espacios y comentarios, que pueden ocupar varias líneas
+    fn skip_whitespace_and_comments(&mut self) {
+        while let Some(ch) = self.peek() {
+            if ch.is_whitespace() {
+                self.advance();
+                continue;
+            }
+            if ch != '/' {
+                return;
+            }
+
+            let mut lookahead = self.chars.clone();
+            lookahead.next();
+            match lookahead.next() {
+                Some('/') => {
+                    while self.peek().is_some_and(|ch| ch != '\n') {
+                        self.advance();
+                    }
+                }
+                Some('*') => {
+                    self.advance();
+                    self.advance();
+                    let mut previous = ' ';
+                    while let Some(ch) = self.advance() {
+                        if previous == '*' && ch == '/' {
+                            break;
+                        }
+                        previous = ch;
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, CompilerError> {
+        self.skip_whitespace_and_comments();
+        let span = self.span;
+        let ch = match self.peek() {
+            Some(ch) => ch,
+            None => return Ok(None),
+        };
+
+        let (kind, text) = if ch.is_alphabetic() || ch == '_' {
+            let word = self.take_while(|ch| ch.is_alphanumeric() || ch == '_');
+            if word.chars().count() > MAX_IDENTIFIER_LENGTH {
+                return Err(CompilerError::InvalidToken(word, span.line));
+            }
+            (word_kind(&word), word)
+        } else if ch.is_ascii_digit() {
+            let mut number = self.take_while(|ch| ch.is_ascii_digit());
+            if self.peek() == Some('.') {
+                self.advance();
+                number.push('.');
+                number.push_str(&self.take_while(|ch| ch.is_ascii_digit()));
+            }
+            (TokenKind::Number, number)
+        } else if ch == '"' || ch == '\'' {
+            let literal = self.quoted(ch, span)?;
+            let kind = if ch == '"' { TokenKind::Str } else { TokenKind::Chr };
+            (kind, literal)
+        } else {
+            let rest: String = self.chars.clone().take(2).collect();
+            match OPERATORS.iter().find(|(operator, _)| rest.starts_with(operator)) {
+                Some((operator, kind)) => {
+                    for _ in 0..operator.len() {
+                        self.advance();
+                    }
+                    (*kind, operator.to_string())
+                }
+                None => return Err(CompilerError::InvalidToken(ch.to_string(), span.line)),
+            }
+        };
+
+        Ok(Some(Token { kind, text, span }))
+    }
+
+    fn take_while(&mut self, condition: impl Fn(char) -> bool) -> String {
+        let mut text = String::new();
+        while let Some(ch) = self.peek().filter(|ch| condition(*ch)) {
+            text.push(ch);
+            self.advance();
+        }
+        text
+    }
+
+    // Una cadena o un caracter con sus comillas, que debe cerrarse en la misma línea
+    fn quoted(&mut self, quote: char, span: Span) -> Result<String, CompilerError> {
+        let mut literal = String::new();
+        literal.push(quote);
+        self.advance();
+
+        let mut length = 0;
+        loop {
+            match self.advance() {
+                Some(ch) if ch == quote => break,
+                Some('\\') => match self.advance() {
+                    Some(ch) if ch != '\n' => {
+                        literal.push('\\');
+                        literal.push(ch);
+                    }
+                    _ => return Err(CompilerError::InvalidToken(literal, span.line)),
+                },
+                Some(ch) if ch != '\n' => literal.push(ch),
+                _ => return Err(CompilerError::InvalidToken(literal, span.line)),
+            }
+            length += 1;
+        }
+        literal.push(quote);
+
+        if quote == '\'' && length != 1 {
+            return Err(CompilerError::InvalidToken(literal, span.line));
+        }
+        Ok(literal)
+    }
+}
+
+fn word_kind(word: &str) -> TokenKind {
+    match word {
+        "while" => TokenKind::While,
+        "if" => TokenKind::If,
+        "else" => TokenKind::Else,
+        "do" => TokenKind::Do,
+        "return" => TokenKind::Return,
+        "int" | "char" | "float" | "double" | "void" => TokenKind::DataType,
+        _ if RESERVED_WORDS.contains(&word) => TokenKind::Reserved,
+        _ => TokenKind::Identifier,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<TokenKind> {
+        tokenize(source).unwrap().into_iter().map(|token| token.kind).collect()
+    }
+
+    #[test]
+    fn multi_character_operators() {
+        assert_eq!(
+            kinds("a<=b && c!=d || e==f"),
+            vec![
+                TokenKind::Identifier,
+                TokenKind::Comparison,
+                TokenKind::Identifier,
+                TokenKind::Logical,
+                TokenKind::Identifier,
+                TokenKind::Comparison,
+                TokenKind::Identifier,
+                TokenKind::Logical,
+                TokenKind::Identifier,
+                TokenKind::Comparison,
+                TokenKind::Identifier,
+            ]
+        );
+    }
+
+    #[test]
+    fn literals_keep_their_text() {
+        let tokens = tokenize(r#"printf("a \"b\" {;}", 'c', 0.01);"#).unwrap();
+        assert_eq!(tokens[2].kind, TokenKind::Str);
+        assert_eq!(tokens[2].text, r#""a \"b\" {;}""#);
+        assert_eq!(tokens[4].kind, TokenKind::Chr);
+        assert_eq!(tokens[6].kind, TokenKind::Number);
+        assert_eq!(tokens[6].text, "0.01");
+    }
+
+    #[test]
+    fn spans_skip_comments() {
+        let tokens = tokenize("//int a;\n/* b\n c */ int\n  x;").unwrap();
+        assert_eq!(tokens[0].span, Span { line: 3, column: 7 });
+        assert_eq!(tokens[1].span, Span { line: 4, column: 3 });
+    }
+
+    #[test]
+    fn unknown_character() {
+        match tokenize("int a;\nint b = 3 @ 4;") {
+            Err(CompilerError::InvalidToken(token, line)) => assert_eq!((token.as_str(), line), ("@", 2)),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unterminated_string() {
+        assert!(matches!(tokenize("\n\nprintf(\"abc);\n"), Err(CompilerError::InvalidToken(_, 3))));
+    }
+}
@@ -1,536 +1,551 @@
 mod node_dict;
 mod compiler_error;
+mod postfix_generator;
+mod postfix_interpreter;
+mod tokenizer;
 
 use node_dict::TokenType;
 use compiler_error::CompilerError;
+use tokenizer::Token;
 
-use regex::Regex;
-use std::{fs::File, io::{self, BufRead}, iter::Peekable, ops::AddAssign, path::Path, str::SplitWhitespace};
+use std::{collections::HashMap, fs, io, path::Path};
 
-// Check if a token is a valid identifier
-fn is_identifier(token: &str) -> bool {
-    let identifier_re = Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_]*$").unwrap();
-    identifier_re.is_match(token)
+// Read the whole file
+fn read_file(file_path: &str) -> io::Result<String> {
+    fs::read_to_string(Path::new(file_path))
 }
 
-// Check if a token is a number
-fn is_number(token: &str) -> bool {
-    let number_re = Regex::new(r"^\d+(\.\d+)?$").unwrap();
-    number_re.is_match(token)
-}
-
-// Tokenize a single line of code
-fn tokenize_line(line: &str, in_multiline_comment: &mut bool) -> Vec<(String, TokenType)> {
-    let mut tokens = Vec::new();
-    let mut current_token = String::new();
-    let mut chars = line.chars().peekable();
-    let mut in_string = false;
-    let mut in_char = false;
-    let mut escape = false;
-
-    while let Some(&ch) = chars.peek() {
-        if *in_multiline_comment {
-            if ch == '*' {
-                chars.next();
-                if let Some(&'/') = chars.peek() {
-                    *in_multiline_comment = false;
-                }
-            }
-            chars.next();
-            continue;
-        }
-
-        if in_string {
-            // current_token.push(ch); //Uncomment in case of needin' the quotes in the token type
-            if ch == '"' && !escape {
-                in_string = false;
-                tokens.push((current_token.clone(), TokenType::String));
-                current_token.clear();
-            } else {
-                current_token.push(ch); // Remove this in case of needin' the quotes in the token type
-            }
-            escape = ch == '\\' && !escape;
-            chars.next();
-            continue;
-        }
-
-        if in_char {
-            // current_token.push(ch); //Uncomment in case of needin' the quotes in the token type
-            if ch == '\'' && !escape {
-                in_char = false;
-                tokens.push((current_token.clone(), TokenType::Char));
-                current_token.clear();
-            } else {
-                current_token.push(ch); // Remove this in case of needin' the quotes in the token type
-            }
-            escape = ch == '\\' && !escape;
-            chars.next();
-            continue;
-        }
-
-        match ch {
-            '/' => {
-                chars.next();
-                if let Some(&next_ch) = chars.peek() {
-                    if next_ch == '*' {
-                        *in_multiline_comment = true;
-                        chars.next();
-                        continue;
-                    } else if next_ch == '/' {
-                        break; // Ignore the rest of the line
-                    } else {
-                        current_token.push('/');
-                        current_token.push(next_ch);
-                        chars.next();
-                    }
-                }
-            }
-            '"' => {
-                if !current_token.is_empty() {
-                    if node_dict::RESERVED_WORDS.contains(&current_token.as_str()) {
-                        //tokens.push((current_token.clone(), TokenType::ReservedWord)); //TODO: Function to differentiate between reserved words
-                    } else if is_identifier(&current_token) {
-                        tokens.push((current_token.clone(), TokenType::Identifier));
-                    } else if is_number(&current_token) { 
-                        //tokens.push((current_token.clone(), TokenType::Number)); //TODO: Function to differentiate between int, float and double
-                    } else {
-                        //tokens.push((current_token.clone(), TokenType::Other));
-                    }
-                    current_token.clear();
-                }
-                //current_token.push(ch); //Uncomment in case of needin' the quotes in the token type
-                in_string = true;
-                chars.next();
-            }
-            '\'' => {
-                if !current_token.is_empty() {
-                    if node_dict::RESERVED_WORDS.contains(&current_token.as_str()) {
-                        //tokens.push((current_token.clone(), TokenType::ReservedWord)); //TODO: Function to differentiate between reserved words
-                    } else if is_identifier(&current_token) {
-                        tokens.push((current_token.clone(), TokenType::Identifier));
-                    } else if is_number(&current_token) {
-                        //tokens.push((current_token.clone(), TokenType::Number)); //TODO: Function to differentiate between int, float and double
-                    } else {
-                        //tokens.push((current_token.clone(), TokenType::Other));
-                    }
-                    current_token.clear();
-                }
-                //current_token.push(ch); //Uncomment in case of needin' the quotes in the token type
-                in_char = true;
-                chars.next();
-            }
-            _ if ch.is_whitespace() => {
-                if !current_token.is_empty() {
-                    if node_dict::RESERVED_WORDS.contains(&current_token.as_str()) {
-                        //tokens.push((current_token.clone(), TokenType::ReservedWord)); // TODO Function to differentiate between reserved words
-                    } else if is_identifier(&current_token) {
-                        tokens.push((current_token.clone(), TokenType::Identifier));
-                    } else if is_number(&current_token) {
-                        //tokens.push((current_token.clone(), TokenType::Number));//todo: function to differentiate between int, float and double
-                    } else {
-                        //tokens.push((current_token.clone(), TokenType::Other));
-                    }
-                    current_token.clear();
-                }
-                //tokens.push((" ".to_owned(), TokenType::Space)); // Add space as a token
-                chars.next();
-                continue;
-            }
-            _ if ch.is_digit(10)
-                || (ch == '.' && current_token.chars().all(|c| c.is_digit(10))) =>
-            {
-                current_token.push(ch);
-                chars.next();
-                continue;
-            }
-            _ if ch.is_alphanumeric() || ch == '_' => {
-                if !current_token.is_empty()
-                    && !current_token
-                        .chars()
-                        .all(|c| c.is_alphanumeric() || c == '_')
-                {
-                    if is_number(&current_token) {
-                        //tokens.push((current_token.clone(), TokenType::Number)); //todo: Function to differentiate between int, float and double
-                    } else if is_identifier(&current_token) {
-                        tokens.push((current_token.clone(), TokenType::Identifier));
-                    } else {
-                        //tokens.push((current_token.clone(), TokenType::Other));
-                    }
-                    current_token.clear();
-                }
-                current_token.push(ch);
-                chars.next();
-                continue;
-            }
-            _ => {
-                if !current_token.is_empty() {
-                    if is_number(&current_token) {
-                        //tokens.push((current_token.clone(), TokenType::Number)); //TODO: Function to differentiate between int, float and double
-                    } else if is_identifier(&current_token) {
-                        tokens.push((current_token.clone(), TokenType::Identifier));
-                    } else {
-                        //tokens.push((current_token.clone(), TokenType::Other));
-                    }
-                    current_token.clear();
-                }
-
-                match ch {
-                    '=' => tokens.push((ch.to_string(), TokenType::Assign)),
-                    ';' => tokens.push((ch.to_string(), TokenType::Semicolon)),
-                    ':' => tokens.push((ch.to_string(), TokenType::Colon)),
-                    ',' => tokens.push((ch.to_string(), TokenType::Comma)),
-                    '(' => tokens.push((ch.to_string(), TokenType::LParen)),
-                    ')' => tokens.push((ch.to_string(), TokenType::RParen)),
-                    '{' => tokens.push((ch.to_string(), TokenType::LBrack)),
-                    '}' => tokens.push((ch.to_string(), TokenType::RBrack)),
-                    '\\' => tokens.push(("\\".to_owned(), TokenType::Backslash)),
-                    // TODO ADD OPERATORS ONE BY ONE
-                    _ => {
-                        let mut operator = String::new();
-                        operator.push(ch);
-                        chars.next();
-
-                        while let Some(&next_ch) = chars.peek() {
-                            if next_ch.is_alphanumeric() || next_ch == '_' || next_ch == ' ' {
-                                break;
-                            }
-
-
-                            operator.push(next_ch);
-                            if !node_dict::OPERATORS_AND_SYMBOLS.contains(&operator.as_str()) {
-                                operator.pop();
-                                break;
-                            }
-                            chars.next();
-                        }
-
-                        if node_dict::OPERATORS_AND_SYMBOLS.contains(&operator.as_str()) {
-                            //tokens.push((operator.clone(), TokenType::Operator));
-                        } else {
-                            //tokens.push((operator.clone(), TokenType::Other));
-                        }
-                        continue;
-                    }
-                }
-                chars.next();
-            }
-        }
-    }
-
-    if !current_token.is_empty() {
-        if node_dict::RESERVED_WORDS.contains(&current_token.as_str()) {
-            //tokens.push((current_token.clone(), TokenType::ReservedWord)); // TODO Function to differentiate between reserved words
-        } else if is_identifier(&current_token) {
-            tokens.push((current_token.clone(), TokenType::Identifier));
-        } else if is_number(&current_token) {
-            //tokens.push((current_token.clone(), TokenType::Number)); // TODO Function to differentiate between int, float and double
-        } /*else {
-            tokens.push((current_token.clone(), TokenType::Other)); // TODO Error handling
-        }*/
-    }
-
-    tokens
-}
-
-// Read the file and return lines
-fn read_file(file_path: &str) -> io::Result<Vec<String>> {
-    let path = Path::new(file_path);
-    let file = File::open(&path)?;
-    let lines = io::BufReader::new(file).lines();
-    lines.collect()
+// AST Node definitions
+#[derive(Debug)]
+enum Expression {
+    Literal {
+        value: String,
+        literal_type: TokenType,
+    },
+    Variable(String),
+    Unary {
+        operator: TokenType,
+        operand: Box<Expression>,
+    },
+    Binary {
+        operator: TokenType,
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+    Call {
+        func_name: String,
+        arguments: Vec<Expression>,
+    },
 }
 
-// AST Node definitions
 #[derive(Debug)]
 enum ASTNode {
     VariableDeclaration {
-        var_type: String,
+        var_type: TokenType,
         var_name: Vec<String>,
-        var_value: Vec<Option<String>>,
+        var_value: Vec<Option<Expression>>,
     },
     VariableAssignment {
         var_name: String,
-        value: String,
+        value: Expression,
     },
     FunctionDefinition {
-        return_type: String,
+        return_type: TokenType,
         func_name: String,
-        parameters: Vec<(String, String)>,
+        parameters: Vec<(TokenType, String)>,
         body: Vec<ASTNode>,
     },
     WhileLoop {
-        condition: String,
+        condition: Expression,
+        body: Vec<ASTNode>,
+    },
+    ForLoop {
+        // a declaration, assignment or expression
+        init: Option<Box<ASTNode>>,
+        condition: Option<Expression>,
+        update: Option<Box<ASTNode>>,
         body: Vec<ASTNode>,
     },
     IfStatement {
-        condition: String,
+        condition: Expression,
         body: Vec<ASTNode>,
+        // an `else if` is an else body holding only the nested if
         else_body: Option<Vec<ASTNode>>,
     },
+    Return {
+        value: Option<Expression>,
+    },
     Block(Vec<ASTNode>),
-    Expression(String),
+    Expression(Expression),
+}
+
+fn is_type_keyword(token_type: TokenType) -> bool {
+    matches!(
+        token_type,
+        TokenType::Integer | TokenType::Char | TokenType::Float | TokenType::Double | TokenType::Void
+    )
 }
 
 // Parser definition
 struct Parser {
-    tokens: Peekable<std::vec::IntoIter<(String, TokenType)>>,
+    tokens: Vec<Token>,
+    position: usize,
+    precedence: HashMap<String, u8>,
+    errors: Vec<CompilerError>,
 }
 
 impl Parser {
-    fn new(tokens: Vec<(String, TokenType)>) -> Self {
+    fn new(tokens: Vec<Token>) -> Self {
         Parser {
-            tokens: tokens.into_iter().peekable(),
+            tokens,
+            position: 0,
+            precedence: node_dict::build_precedence(),
+            errors: Vec::new(),
+        }
+    }
+
+    fn parse(&mut self) -> Result<Vec<ASTNode>, Vec<CompilerError>> {
+        let ast = self.parse_statements(false);
+        if self.errors.is_empty() {
+            Ok(ast)
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_type(&self) -> Option<TokenType> {
+        self.peek().map(|token| token.token_type)
+    }
+
+    fn peek_second_type(&self) -> Option<TokenType> {
+        self.tokens.get(self.position + 1).map(|token| token.token_type)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    // The error for the current token, or for the last line at the end of the file
+    fn error(&self) -> CompilerError {
+        match self.peek() {
+            Some(token) => CompilerError::InvalidToken(token.text.clone(), token.line, token.column),
+            None => CompilerError::InvalidSyntax(self.tokens.last().map_or(1, |token| token.line)),
         }
     }
 
-    fn parse(&mut self) -> Vec<ASTNode> {
-        let mut ast = Vec::new();
-        while let Some(node) = self.parse_statement() {
-            ast.push(node);
+    fn expect(&mut self, token_type: TokenType) -> Result<String, CompilerError> {
+        if self.peek_type() == Some(token_type) {
+            Ok(self.next().unwrap().text)
+        } else {
+            Err(self.error())
+        }
+    }
+
+    // Statements up to the end of the file, or up to the "}" of the block when `in_block`
+    fn parse_statements(&mut self, in_block: bool) -> Vec<ASTNode> {
+        let mut statements = Vec::new();
+        while let Some(token_type) = self.peek_type() {
+            if token_type == TokenType::RBrace {
+                if in_block {
+                    break;
+                }
+                self.errors.push(self.error());
+                self.next();
+                continue;
+            }
+            match self.parse_statement() {
+                Ok(Some(statement)) => statements.push(statement),
+                Ok(None) => {}
+                Err(error) => {
+                    self.errors.push(error);
+                    self.synchronize();
+                }
+            }
         }
-        ast
+        statements
     }
 
-    fn parse_statement(&mut self) -> Option<ASTNode> {
-        if let Some((token, token_type)) = self.tokens.peek() {
+    // Skips the rest of a statement with a syntax error
+    fn synchronize(&mut self) {
+        while let Some(token_type) = self.peek_type() {
             match token_type {
-                TokenType::Integer | TokenType::Float | TokenType::Double | TokenType::Char => self.parse_declaration(),
-                //TokenType::Void => self.parse_void_declaration(),
-                TokenType::While => self.parse_while_loop(),
-                TokenType::If => self.parse_if_statement(),
-                TokenType::Identifier => self.parse_variable_assignment(),
-                TokenType::LBrace => self.parse_block(),
-                TokenType::RBrace => {
-                    self.tokens.next();
-                    None
-                },
+                TokenType::Semicolon => {
+                    self.next();
+                    return;
+                }
+                TokenType::RBrace => return,
                 _ => {
-                    self.tokens.next();
-                    None
+                    self.next();
                 }
             }
-        } else {
-            None
         }
     }
 
-    fn parse_declaration(&mut self) -> Option<ASTNode> {
-        let var_type = self.tokens.next()?.0;
-        let mut var_name = Vec::new();
-        let mut var_value = Vec::new();
-
-        //int a=8, b, c=9+5+10-12;
+    fn parse_statement(&mut self) -> Result<Option<ASTNode>, CompilerError> {
+        let token_type = self.peek_type().ok_or(self.error())?;
+        let statement = match token_type {
+            _ if is_type_keyword(token_type) => self.parse_declaration()?,
+            TokenType::While => self.parse_while_loop()?,
+            TokenType::For => self.parse_for_loop()?,
+            TokenType::If => self.parse_if_statement()?,
+            TokenType::Return => self.parse_return()?,
+            TokenType::LBrace => ASTNode::Block(self.parse_block()?),
+            // an empty statement
+            TokenType::Semicolon => {
+                self.next();
+                return Ok(None);
+            }
+            _ => {
+                let statement = self.parse_simple_statement()?;
+                self.expect(TokenType::Semicolon)?;
+                statement
+            }
+        };
+        Ok(Some(statement))
+    }
 
-        // Expect a semicolon
-        loop {
-            let x = self.tokens.next();
-            match x {
-                Some ((token, TokenType::Semicolon)) => {
-                    break;
-                },
-                Some ((token, TokenType::Identifier)) => {
-                    var_name.push(token);
-                    let (auxTok, auxTokType) = self.tokens.peek().unwrap();
-                    if let TokenType::Comma = auxTokType {
-                        var_value.push(None);
-                        continue;
-                    } else if let TokenType::Assign = auxTokType {
-                        self.tokens.next();
-                        let mut value = String::new();
-                        while let Some((token, token_type)) = self.tokens.peek() {
-                            if let TokenType::Comma = token_type {
-                                break;
-                            } else {
-                                value.push_str(token);
-                                self.tokens.next();
-                            }
-                        }
-                        var_value.push(Some(value));
-                    } else {
-                        var_value.push(None);
-                    }
-                },
-                _ => {
-                    return None; // TODO Enviar error sintáctico
-                }
+    // An assignment, an increment or decrement, or an expression, without the ";" ending it
+    fn parse_simple_statement(&mut self) -> Result<ASTNode, CompilerError> {
+        let token_type = self.peek_type().ok_or(self.error())?;
+        let second_type = self.peek_second_type();
+        match (token_type, second_type) {
+            (TokenType::Identifier, Some(TokenType::Assign)) => self.parse_variable_assignment(),
+            // `i++` and `++i` are both `i = i + 1` since their value isn't used
+            (TokenType::Identifier, Some(TokenType::Increment | TokenType::Decrement)) => {
+                let var_name = self.expect(TokenType::Identifier)?;
+                let operator = self.next().unwrap().token_type;
+                Ok(Self::step(var_name, operator))
+            }
+            (TokenType::Increment | TokenType::Decrement, _) => {
+                self.next();
+                Ok(Self::step(self.expect(TokenType::Identifier)?, token_type))
             }
+            _ => Ok(ASTNode::Expression(self.parse_expression()?)),
+        }
+    }
 
-            // TODO funcion para detectar valores
+    // The assignment adding or subtracting one for the `++` or `--` operator
+    fn step(var_name: String, operator: TokenType) -> ASTNode {
+        let operator = if operator == TokenType::Increment { TokenType::Plus } else { TokenType::Minus };
+        let value = Expression::Binary {
+            operator,
+            left: Box::new(Expression::Variable(var_name.clone())),
+            right: Box::new(Expression::Literal {
+                value: "1".to_string(),
+                literal_type: TokenType::IntegerLiteral,
+            }),
+        };
+        ASTNode::VariableAssignment { var_name, value }
+    }
 
-            /*match x {
-                Some ((token, TokenType::)) => var_name.push(token),
-                _ => None, // TODO Enviar error sintáctico
-            }*/
+    // `return;` or `return value;`
+    fn parse_return(&mut self) -> Result<ASTNode, CompilerError> {
+        self.expect(TokenType::Return)?;
+        let value = match self.peek_type() {
+            Some(TokenType::Semicolon) => None,
+            _ => Some(self.parse_expression()?),
+        };
+        self.expect(TokenType::Semicolon)?;
+        Ok(ASTNode::Return { value })
+    }
 
+    fn parse_type(&mut self) -> Result<TokenType, CompilerError> {
+        match self.peek_type() {
+            Some(token_type) if is_type_keyword(token_type) => {
+                self.next();
+                Ok(token_type)
+            }
+            _ => Err(self.error()),
         }
+    }
 
-        /*if let Some((_, TokenType::Semicolon)) = self.tokens.next() {
-            Some(ASTNode::VariableDeclaration { var_type, var_name })
-        } else {
-            None
-        }*/
+    // `int a = 8, b, c = 9 + 5;` or a function definition like `int f(int a, char b) { ... }`
+    fn parse_declaration(&mut self) -> Result<ASTNode, CompilerError> {
+        let var_type = self.parse_type()?;
+        let mut var_name = Vec::new();
+        let mut var_value = Vec::new();
 
-        //println!("Self Tokens: {:?}", self.tokens.next());
-        println!("Var_Type: {:?}; Var_Name: {:?}", var_type, var_name);
+        loop {
+            var_name.push(self.expect(TokenType::Identifier)?);
 
-        //println!("Next {:?}", self.tokens.next());
+            if var_name.len() == 1 && self.peek_type() == Some(TokenType::LParen) {
+                return self.parse_function_definition(var_type, var_name.remove(0));
+            }
 
-        Some(ASTNode::VariableDeclaration {
+            if self.peek_type() == Some(TokenType::Assign) {
+                self.next();
+                var_value.push(Some(self.parse_expression()?));
+            } else {
+                var_value.push(None);
+            }
+
+            match self.peek_type() {
+                Some(TokenType::Comma) => {
+                    self.next();
+                }
+                Some(TokenType::Semicolon) => {
+                    self.next();
+                    break;
+                }
+                _ => return Err(self.error()),
+            }
+        }
+
+        Ok(ASTNode::VariableDeclaration {
             var_type,
             var_name,
             var_value,
         })
     }
 
-    fn parse_variable_assignment(&mut self) -> Option<ASTNode> {
-        let var_name = self.tokens.next()?.0;
+    fn parse_function_definition(&mut self, return_type: TokenType, func_name: String) -> Result<ASTNode, CompilerError> {
+        self.expect(TokenType::LParen)?;
 
-        // Expect an assignment operator
-        if node_dict::isOperator(self.tokens.next().unwrap().1) {
-            let mut value = String::new();
-            while let Some((token, token_type)) = self.tokens.peek() {
-                if let TokenType::Semicolon = token_type {
-                    break;
-                } else {
-                    value.push_str(token);
-                    self.tokens.next();
-                }
+        let mut parameters = Vec::new();
+        // `f(void)` takes no parameters like `f()`
+        if self.peek_type() == Some(TokenType::Void) && self.peek_second_type() == Some(TokenType::RParen)
+        {
+            self.next();
+        }
+        while self.peek_type() != Some(TokenType::RParen) {
+            if !parameters.is_empty() {
+                self.expect(TokenType::Comma)?;
             }
-            // Expect a semicolon
-            self.tokens.next();
-            Some(ASTNode::VariableAssignment { var_name, value })
-        } else {
-            None
+            let param_type = self.parse_type()?;
+            parameters.push((param_type, self.expect(TokenType::Identifier)?));
         }
+        self.expect(TokenType::RParen)?;
+
+        Ok(ASTNode::FunctionDefinition {
+            return_type,
+            func_name,
+            parameters,
+            body: self.parse_block()?,
+        })
     }
 
-    fn parse_while_loop(&mut self) -> Option<ASTNode> {
-        self.tokens.next(); // Consume 'while'
+    // `a = 8` without the ";"
+    fn parse_variable_assignment(&mut self) -> Result<ASTNode, CompilerError> {
+        let var_name = self.expect(TokenType::Identifier)?;
+        self.expect(TokenType::Assign)?;
+        let value = self.parse_expression()?;
+        Ok(ASTNode::VariableAssignment { var_name, value })
+    }
 
-        // Expect a condition in parentheses
-        let mut condition = String::new();
-        if let Some((_, TokenType::LParen)) = self.tokens.next() {
-            while let Some((token, token_type)) = self.tokens.peek() {
-                if let TokenType::RParen = token_type {
-                    self.tokens.next(); // Consume closing parenthesis
-                    break;
-                } else {
-                    condition.push_str(token);
-                    self.tokens.next();
-                }
-            }
-        }
+    fn parse_condition(&mut self) -> Result<Expression, CompilerError> {
+        self.expect(TokenType::LParen)?;
+        let condition = self.parse_expression()?;
+        self.expect(TokenType::RParen)?;
+        Ok(condition)
+    }
 
-        // Parse the body of the loop
-        if let Some(ASTNode::Block(body)) = self.parse_block() {
-            Some(ASTNode::WhileLoop { condition, body })
-        } else {
-            None
-        }
+    fn parse_while_loop(&mut self) -> Result<ASTNode, CompilerError> {
+        self.expect(TokenType::While)?;
+        let condition = self.parse_condition()?;
+        Ok(ASTNode::WhileLoop {
+            condition,
+            body: self.parse_block()?,
+        })
     }
 
-    fn parse_if_statement(&mut self) -> Option<ASTNode> {
-        self.tokens.next(); // Consume 'if'
+    // `for (int i = 0; i < 3; i = i + 1) { ... }` where every part of the header can be left out
+    fn parse_for_loop(&mut self) -> Result<ASTNode, CompilerError> {
+        self.expect(TokenType::For)?;
+        self.expect(TokenType::LParen)?;
 
-        // Expect a condition in parentheses
-        let mut condition = String::new();
-        if let Some((_, TokenType::LParen)) = self.tokens.next() {
-            while let Some((token, token_type)) = self.tokens.peek() {
-                if let TokenType::RParen = token_type {
-                    self.tokens.next(); // Consume closing parenthesis
-                    break;
-                } else {
-                    condition.push_str(token);
-                    self.tokens.next();
-                }
+        let init = match self.peek_type() {
+            // a declaration ends with its own ";"
+            Some(token_type) if is_type_keyword(token_type) => Some(Box::new(self.parse_declaration()?)),
+            _ => {
+                let init = self.parse_for_clause(TokenType::Semicolon)?;
+                self.expect(TokenType::Semicolon)?;
+                init
             }
+        };
+        let condition = match self.peek_type() {
+            Some(TokenType::Semicolon) => None,
+            _ => Some(self.parse_expression()?),
+        };
+        self.expect(TokenType::Semicolon)?;
+        let update = self.parse_for_clause(TokenType::RParen)?;
+        self.expect(TokenType::RParen)?;
+
+        Ok(ASTNode::ForLoop {
+            init,
+            condition,
+            update,
+            body: self.parse_block()?,
+        })
+    }
+
+    // The statement in the header of a for loop, or `None` if it's empty up to `end`
+    fn parse_for_clause(&mut self, end: TokenType) -> Result<Option<Box<ASTNode>>, CompilerError> {
+        match self.peek_type() {
+            Some(token_type) if token_type == end => Ok(None),
+            _ => Ok(Some(Box::new(self.parse_simple_statement()?))),
         }
+    }
 
-        // Parse the body of the if statement
-        let body = if let Some(ASTNode::Block(body)) = self.parse_block() {
-            body
-        } else {
-            return None;
-        };
+    fn parse_if_statement(&mut self) -> Result<ASTNode, CompilerError> {
+        self.expect(TokenType::If)?;
+        let condition = self.parse_condition()?;
+        let body = self.parse_block()?;
 
-        // Check for an else statement
-        let else_body = if let Some((else_token, _)) = self.tokens.peek() {
-            if else_token == "else" {
-                self.tokens.next(); // Consume 'else'
-                if let Some(ASTNode::Block(else_body)) = self.parse_block() {
-                    Some(else_body)
-                } else {
-                    None
-                }
+        let else_body = if self.peek_type() == Some(TokenType::Else) {
+            self.next();
+            if self.peek_type() == Some(TokenType::If) {
+                Some(vec![self.parse_if_statement()?])
             } else {
-                None
+                Some(self.parse_block()?)
             }
         } else {
             None
         };
 
-        Some(ASTNode::IfStatement {
+        Ok(ASTNode::IfStatement {
             condition,
             body,
             else_body,
         })
     }
 
-    fn parse_block(&mut self) -> Option<ASTNode> {
-        if let Some((token, _)) = self.tokens.next() {
-            if token != "{" {
-                return None; // If the next token is not '{', return None
-            }
-        }
+    fn parse_block(&mut self) -> Result<Vec<ASTNode>, CompilerError> {
+        self.expect(TokenType::LBrace)?;
+        let statements = self.parse_statements(true);
+        self.expect(TokenType::RBrace)?;
+        Ok(statements)
+    }
 
-        let mut statements = Vec::new();
-        while let Some((_token, token_type)) = self.tokens.peek() {
-            if let TokenType::RBrace = token_type {
-                self.tokens.next(); // Consume '}'
+    fn parse_expression(&mut self) -> Result<Expression, CompilerError> {
+        self.parse_binary(0)
+    }
+
+    // Binary operators by precedence climbing, all of them are left-associative
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<Expression, CompilerError> {
+        let mut left = self.parse_unary()?;
+
+        while let Some(token) = self.peek() {
+            let operator = token.token_type;
+            let precedence = self.precedence.get(&token.text).copied().unwrap_or(0);
+            if !node_dict::is_operator(operator) || operator == TokenType::Assign || precedence <= min_precedence {
                 break;
             }
-            if let Some(statement) = self.parse_statement() {
-                statements.push(statement);
-            } else {
-                self.tokens.next();
-            }
+            self.next();
+            let right = self.parse_binary(precedence)?;
+            left = Expression::Binary {
+                operator,
+                left: Box::new(left),
+                right: Box::new(right),
+            };
         }
 
-        Some(ASTNode::Block(statements))
+        Ok(left)
     }
 
-    fn parse_expression(&mut self) -> Option<ASTNode> {
-        let mut expression = String::new();
-        while let Some((token, token_type)) = self.tokens.peek() {
-            if let TokenType::Semicolon = token_type {
-                break;
-            } else {
-                expression.push_str(token);
-                self.tokens.next();
+    fn parse_unary(&mut self) -> Result<Expression, CompilerError> {
+        if self.peek_type() == Some(TokenType::Minus) {
+            self.next();
+            return Ok(Expression::Unary {
+                operator: TokenType::Minus,
+                operand: Box::new(self.parse_unary()?),
+            });
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expression, CompilerError> {
+        let Token { text: token, token_type, .. } = self.peek().cloned().ok_or(self.error())?;
+        match token_type {
+            TokenType::Identifier | TokenType::Print => {
+                self.next();
+                if self.peek_type() != Some(TokenType::LParen) {
+                    return if token_type == TokenType::Identifier {
+                        Ok(Expression::Variable(token))
+                    } else {
+                        Err(self.error())
+                    };
+                }
+                self.next();
+
+                let mut arguments = Vec::new();
+                while self.peek_type() != Some(TokenType::RParen) {
+                    if !arguments.is_empty() {
+                        self.expect(TokenType::Comma)?;
+                    }
+                    arguments.push(self.parse_expression()?);
+                }
+                self.expect(TokenType::RParen)?;
+
+                Ok(Expression::Call {
+                    func_name: token,
+                    arguments,
+                })
+            }
+            TokenType::IntegerLiteral | TokenType::FloatLiteral | TokenType::CharLiteral | TokenType::StringLiteral => {
+                self.next();
+                Ok(Expression::Literal {
+                    value: token,
+                    literal_type: token_type,
+                })
             }
+            TokenType::LParen => {
+                self.next();
+                let expression = self.parse_expression()?;
+                self.expect(TokenType::RParen)?;
+                Ok(expression)
+            }
+            _ => Err(self.error()),
         }
-        self.tokens.next(); // Consume semicolon
-        Some(ASTNode::Expression(expression))
     }
 }
 
 // Process the code file
 fn process_code(file_path: &str) {
-    let mut in_multiline_comment = false;
-
     match read_file(file_path) {
-        Ok(lines) => {
-            let mut tokens = Vec::new();
-            for (line_number, line) in lines.iter().enumerate() {
-                let line_tokens = tokenize_line(&line, &mut in_multiline_comment);
-                println!("Line {}: Tokens: {:?}", line_number + 1, line_tokens);
-                tokens.extend(line_tokens);
+        Ok(source) => {
+            let (tokens, lexical_errors) = tokenizer::tokenize(&source);
+            for line_tokens in tokens.chunk_by(|a, b| a.line == b.line) {
+                let pairs: Vec<(&str, TokenType)> =
+                    line_tokens.iter().map(|token| (token.text.as_str(), token.token_type)).collect();
+                println!("Line {}: Tokens: {:?}", line_tokens[0].line, pairs);
+            }
+            for error in lexical_errors.iter() {
+                println!("Lexical error: {}", error);
+            }
+            // the parser would only report misleading errors around the invalid tokens left out
+            if !lexical_errors.is_empty() {
+                return;
             }
 
             let mut parser = Parser::new(tokens);
-            let ast = parser.parse();
+            let ast = match parser.parse() {
+                Ok(ast) => {
+                    println!("AST: {:?}", ast);
+                    ast
+                }
+                Err(errors) => {
+                    for error in errors.iter() {
+                        println!("Syntax error: {}", error);
+                    }
+                    println!("Failed to parse input: {} syntax errors", errors.len());
+                    return;
+                }
+            };
+
+            let postfix = postfix_generator::generate_postfix(&ast);
+            let postfix_text: Vec<String> = postfix.iter().map(postfix_generator::token_display).collect();
+            println!("Postfix: {}", postfix_text.join(" "));
 
-            println!("AST: {:?}", ast);
+            println!("Output:");
+            if let Err(e) = postfix_interpreter::run(&postfix, &mut io::stdout()) {
+                println!("Runtime error: {}", e);
+            }
         }
         Err(e) => eprintln!("Error reading the file: {}", e),
     }
@@ -541,3 +556,58 @@ fn main() {
     let file_path = "../prueba.txt"; // Modify this path to point to your test file
     process_code(file_path);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Result<Vec<ASTNode>, Vec<CompilerError>> {
+        let (tokens, errors) = tokenizer::tokenize(source);
+        assert!(errors.is_empty(), "{:?}", errors);
+        Parser::new(tokens).parse()
+    }
+
+    #[test]
+    fn for_loop() {
+        let ast = parse("for (int i = 0; i < 3; i = i + 1) { s = s + i; }").unwrap();
+        match ast.as_slice() {
+            [ASTNode::ForLoop { init: Some(init), condition: Some(_), update: Some(update), body }] => {
+                assert!(matches!(init.as_ref(), ASTNode::VariableDeclaration { .. }));
+                assert!(matches!(update.as_ref(), ASTNode::VariableAssignment { .. }));
+                assert_eq!(body.len(), 1);
+            }
+            ast => panic!("expected a for loop, found {:?}", ast),
+        }
+
+        // every part of the header can be left out
+        let ast = parse("for (i = 0; ; ) { } for (; i < 3; i) { }").unwrap();
+        assert!(matches!(
+            ast.as_slice(),
+            [
+                ASTNode::ForLoop { init: Some(_), condition: None, update: None, .. },
+                ASTNode::ForLoop { init: None, condition: Some(_), update: Some(_), .. },
+            ]
+        ));
+    }
+
+    #[test]
+    fn for_loop_errors() {
+        let errors = parse("for (int i = 0; i < 3) { }\nfor i = 0; { }").unwrap_err();
+        let errors: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+        assert_eq!(errors, [
+            "Invalid token \")\" at line: 1, column: 22",
+            // the recovery skips to the end of the block, which is then unmatched
+            "Invalid token \"}\" at line: 1, column: 26",
+            "Invalid token \"i\" at line: 2, column: 5",
+        ]);
+    }
+
+    #[test]
+    fn default_input() {
+        // the file `main` runs goes through every stage without errors
+        let ast = parse(&read_file("../prueba.txt").unwrap()).unwrap();
+        let mut output = Vec::new();
+        postfix_interpreter::run(&postfix_generator::generate_postfix(&ast), &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "Hello world!\n7891011\n9 18 0 x 102\n");
+    }
+}
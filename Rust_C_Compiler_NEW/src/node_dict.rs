@@ -1,22 +1,30 @@
+use std::collections::HashMap;
+
 // Token types
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TokenType {
     // Variables
     Identifier,
     
     // Data types
-    Integer, String, Char, Float, Double, Void,
+    Integer, Char, Float, Double, Void,
+
+    // Literals
+    IntegerLiteral, FloatLiteral, StringLiteral, CharLiteral,
 
     // Reserved Words
-    If, Print, Else,
+    If, Print, Else, Return,
     While, For, And, Or,
-    True, False, Assign,
+    Assign,
 
     // Symbols
-    Plus, Minus, Mult, Division,
-    Equals, More, MoreEquals, Less, LessEquals,
+    Plus, Minus, Mult, Division, Increment, Decrement,
+    Equals, NotEquals, More, MoreEquals, Less, LessEquals,
     LParen, RParen, LBrace, RBrace, LBrack, RBrack, Semicolon,
-    Comment, MultilineComment, Backslash, Comma, Colon
+    Comma, Colon,
+
+    // Call of a function in the postfix code, the text is the name of the function
+    Call,
 }
 
 // List of C reserved words
@@ -70,67 +78,129 @@ pub const RESERVED_WORDS: &[&str] = &[
 // List of operators and symbols
 pub const OPERATORS_AND_SYMBOLS: &[&str] = &[
     "+", "-", "*", "/", "%", "=", "==", "!=", ">", "<", ">=", "<=", "&&", "||", "!", "&", "|", "^",
-    "~", "<<", ">>", "++", "--", "->", ".", ",", ";", ":", "(", ")", "{", "}",
+    "~", "<<", ">>", "++", "--", "->", ".", ",", ";", ":", "(", ")", "{", "}", "[", "]",
 ];
 
-pub fn isOperator(tokenType: TokenType) -> bool {
-    match tokenType {
+pub fn is_operator(token_type: TokenType) -> bool {
+    matches!(
+        token_type,
         TokenType::Plus
         | TokenType::Minus
         | TokenType::Mult
         | TokenType::Division
         | TokenType::Assign
         | TokenType::Equals
+        | TokenType::NotEquals
         | TokenType::More
         | TokenType::MoreEquals
         | TokenType::Less
         | TokenType::LessEquals
         | TokenType::And
-        | TokenType::Or => true,
-        _ => false,
-    }
+        | TokenType::Or
+    )
 }
 
-pub fn isNumber(tokenType: TokenType) -> bool {
-    match tokenType {
-        TokenType::Integer
-        | TokenType::Float => true,
-        _ => false,
+// Token type of the reserved words and builtins the parser knows about
+pub fn reserved_type(word: &str) -> Option<TokenType> {
+    match word {
+        "int" => Some(TokenType::Integer),
+        "char" => Some(TokenType::Char),
+        "float" => Some(TokenType::Float),
+        "double" => Some(TokenType::Double),
+        "void" => Some(TokenType::Void),
+        "if" => Some(TokenType::If),
+        "else" => Some(TokenType::Else),
+        "while" => Some(TokenType::While),
+        "for" => Some(TokenType::For),
+        "return" => Some(TokenType::Return),
+        // printf is the print statement of the language
+        "printf" => Some(TokenType::Print),
+        _ => None,
     }
 }
 
-pub fn isDataType(tokenType: TokenType) -> bool {
-    match tokenType {
-        TokenType::String
-        | TokenType::Identifier
-        | TokenType::Integer
-        | TokenType::True
-        | TokenType::False => true,
-        _ => false,
+pub fn operator_type(operator: &str) -> Option<TokenType> {
+    match operator {
+        "=" => Some(TokenType::Assign),
+        "+" => Some(TokenType::Plus),
+        "-" => Some(TokenType::Minus),
+        "++" => Some(TokenType::Increment),
+        "--" => Some(TokenType::Decrement),
+        "*" => Some(TokenType::Mult),
+        "/" => Some(TokenType::Division),
+        "==" => Some(TokenType::Equals),
+        "!=" => Some(TokenType::NotEquals),
+        ">" => Some(TokenType::More),
+        ">=" => Some(TokenType::MoreEquals),
+        "<" => Some(TokenType::Less),
+        "<=" => Some(TokenType::LessEquals),
+        "&&" => Some(TokenType::And),
+        "||" => Some(TokenType::Or),
+        _ => None,
     }
 }
 
-pub fn isReserved(tokenType: TokenType) -> bool {
-    match tokenType {
-        /*TokenType::Var
-        | TokenType::Set
-        | */TokenType::If
-        | TokenType::Print
-        | TokenType::True
-        | TokenType::False
-        | TokenType::Else
-        | TokenType::While
-        | TokenType::For => true,
-        _ => false,
+// Text of the token types that are always written the same way
+pub fn token_text(token_type: TokenType) -> &'static str {
+    match token_type {
+        TokenType::Integer => "int",
+        TokenType::Char => "char",
+        TokenType::Float => "float",
+        TokenType::Double => "double",
+        TokenType::Void => "void",
+        TokenType::If => "if",
+        TokenType::Else => "else",
+        TokenType::While => "while",
+        TokenType::For => "for",
+        TokenType::Return => "return",
+        TokenType::Print => "printf",
+        TokenType::Assign => "=",
+        TokenType::Plus => "+",
+        TokenType::Minus => "-",
+        TokenType::Mult => "*",
+        TokenType::Division => "/",
+        TokenType::Equals => "==",
+        TokenType::NotEquals => "!=",
+        TokenType::More => ">",
+        TokenType::MoreEquals => ">=",
+        TokenType::Less => "<",
+        TokenType::LessEquals => "<=",
+        TokenType::And => "&&",
+        TokenType::Or => "||",
+        TokenType::Semicolon => ";",
+        TokenType::Comma => ",",
+        _ => "",
     }
 }
 
-pub fn isControl(tokenType: TokenType) -> bool {
-    match tokenType {
-        TokenType::If
-        | TokenType::Else
-        | TokenType::While
-        | TokenType::For => true,
-        _ => false,
-    }
+/// Builds a precedence table for C operators.
+pub fn build_precedence() -> HashMap<String, u8> {
+    let mut precedence = HashMap::new();
+    precedence.insert("(".to_string(), 0);
+    precedence.insert(")".to_string(), 0);
+    // assignment binds the loosest of all operators
+    precedence.insert("=".to_string(), 0);
+    precedence.insert("||".to_string(), 1);
+    precedence.insert("&&".to_string(), 2);
+    precedence.insert("|".to_string(), 3);
+    precedence.insert("^".to_string(), 4);
+    precedence.insert("&".to_string(), 5);
+    precedence.insert("==".to_string(), 6);
+    precedence.insert("!=".to_string(), 6);
+    precedence.insert("<".to_string(), 7);
+    precedence.insert("<=".to_string(), 7);
+    precedence.insert(">".to_string(), 7);
+    precedence.insert(">=".to_string(), 7);
+    precedence.insert("<<".to_string(), 8);
+    precedence.insert(">>".to_string(), 8);
+    precedence.insert("+".to_string(), 9);
+    precedence.insert("-".to_string(), 9);
+    precedence.insert("*".to_string(), 10);
+    precedence.insert("/".to_string(), 10);
+    precedence.insert("%".to_string(), 10);
+    precedence.insert("!".to_string(), 11);
+    precedence.insert("~".to_string(), 11);
+    precedence.insert("++".to_string(), 12);
+    precedence.insert("--".to_string(), 12);
+    precedence
 }
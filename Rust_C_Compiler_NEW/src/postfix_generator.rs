@@ -0,0 +1,246 @@
+use crate::node_dict::{self, TokenType};
+use crate::{ASTNode, Expression};
+
+// Converts the AST into postfix notation. Reserved words come before what they apply to and every
+// control structure is closed by a ";" after its condition and after each of its blocks.
+pub fn generate_postfix(ast: &[ASTNode]) -> Vec<(String, TokenType)> {
+    let mut postfix = Vec::new();
+    for node in ast {
+        statement(node, &mut postfix);
+    }
+    postfix
+}
+
+// Text of a postfix token as it's shown, a call `g(a, b)` is shown as `a b 2 CALL(g)`
+pub fn token_display((token, token_type): &(String, TokenType)) -> String {
+    match token_type {
+        TokenType::StringLiteral => format!("{:?}", token),
+        TokenType::CharLiteral => format!("'{}'", token.escape_default()),
+        TokenType::Call => format!("CALL({})", token),
+        _ => token.clone(),
+    }
+}
+
+fn push(postfix: &mut Vec<(String, TokenType)>, token_type: TokenType) {
+    postfix.push((node_dict::token_text(token_type).to_string(), token_type));
+}
+
+fn statement(node: &ASTNode, postfix: &mut Vec<(String, TokenType)>) {
+    match node {
+        ASTNode::VariableDeclaration {
+            var_type,
+            var_name,
+            var_value,
+        } => {
+            push(postfix, *var_type);
+            for (i, (name, value)) in var_name.iter().zip(var_value).enumerate() {
+                if i > 0 {
+                    push(postfix, TokenType::Comma);
+                }
+                postfix.push((name.clone(), TokenType::Identifier));
+                if let Some(value) = value {
+                    expression(value, postfix);
+                    push(postfix, TokenType::Assign);
+                }
+            }
+            push(postfix, TokenType::Semicolon);
+        }
+        // `type name type parameter , ... ; body ;`
+        ASTNode::FunctionDefinition {
+            return_type,
+            func_name,
+            parameters,
+            body,
+        } => {
+            push(postfix, *return_type);
+            postfix.push((func_name.clone(), TokenType::Identifier));
+            // a type after the name tells the header apart from a declaration, even without parameters
+            if parameters.is_empty() {
+                push(postfix, TokenType::Void);
+            }
+            for (i, (param_type, param_name)) in parameters.iter().enumerate() {
+                if i > 0 {
+                    push(postfix, TokenType::Comma);
+                }
+                push(postfix, *param_type);
+                postfix.push((param_name.clone(), TokenType::Identifier));
+            }
+            push(postfix, TokenType::Semicolon);
+            block(body, postfix);
+            push(postfix, TokenType::Semicolon);
+        }
+        ASTNode::WhileLoop { condition, body } => {
+            push(postfix, TokenType::While);
+            expression(condition, postfix);
+            push(postfix, TokenType::Semicolon);
+            block(body, postfix);
+            push(postfix, TokenType::Semicolon);
+        }
+        // `for init ; condition ; update ; body ;`, where a declaration in init ends with its own ";"
+        ASTNode::ForLoop {
+            init,
+            condition,
+            update,
+            body,
+        } => {
+            push(postfix, TokenType::For);
+            match init {
+                Some(init) => statement(init, postfix),
+                None => push(postfix, TokenType::Semicolon),
+            }
+            if let Some(condition) = condition {
+                expression(condition, postfix);
+            }
+            push(postfix, TokenType::Semicolon);
+            if let Some(update) = update {
+                clause(update, postfix);
+            }
+            push(postfix, TokenType::Semicolon);
+            block(body, postfix);
+            push(postfix, TokenType::Semicolon);
+        }
+        // the block of an if followed by `else` isn't closed, the else closes itself and the if
+        ASTNode::IfStatement {
+            condition,
+            body,
+            else_body,
+        } => {
+            push(postfix, TokenType::If);
+            expression(condition, postfix);
+            push(postfix, TokenType::Semicolon);
+            block(body, postfix);
+            if let Some(else_body) = else_body {
+                push(postfix, TokenType::Else);
+                block(else_body, postfix);
+                push(postfix, TokenType::Semicolon);
+            }
+            push(postfix, TokenType::Semicolon);
+        }
+        ASTNode::Return { value } => {
+            push(postfix, TokenType::Return);
+            if let Some(value) = value {
+                expression(value, postfix);
+            }
+            push(postfix, TokenType::Semicolon);
+        }
+        ASTNode::Block(body) => block(body, postfix),
+        ASTNode::VariableAssignment { .. } | ASTNode::Expression(_) => {
+            clause(node, postfix);
+            push(postfix, TokenType::Semicolon);
+        }
+    }
+}
+
+fn block(body: &[ASTNode], postfix: &mut Vec<(String, TokenType)>) {
+    for node in body {
+        statement(node, postfix);
+    }
+}
+
+// An assignment or expression without the ";" ending it
+fn clause(node: &ASTNode, postfix: &mut Vec<(String, TokenType)>) {
+    match node {
+        ASTNode::VariableAssignment { var_name, value } => {
+            postfix.push((var_name.clone(), TokenType::Identifier));
+            expression(value, postfix);
+            push(postfix, TokenType::Assign);
+        }
+        ASTNode::Expression(expr) => expression(expr, postfix),
+        _ => statement(node, postfix),
+    }
+}
+
+fn expression(expr: &Expression, postfix: &mut Vec<(String, TokenType)>) {
+    match expr {
+        Expression::Literal { value, literal_type } => postfix.push((value.clone(), *literal_type)),
+        Expression::Variable(name) => postfix.push((name.clone(), TokenType::Identifier)),
+        // all operators are binary in postfix, so `-a` becomes `0 a -`
+        Expression::Unary { operator, operand } => {
+            postfix.push(("0".to_string(), TokenType::IntegerLiteral));
+            expression(operand, postfix);
+            push(postfix, *operator);
+        }
+        Expression::Binary { operator, left, right } => {
+            expression(left, postfix);
+            expression(right, postfix);
+            push(postfix, *operator);
+        }
+        // the arguments are followed by their count and the call, so that calls can be nested
+        Expression::Call { func_name, arguments } => {
+            for argument in arguments {
+                expression(argument, postfix);
+            }
+            postfix.push((arguments.len().to_string(), TokenType::IntegerLiteral));
+            postfix.push((func_name.clone(), TokenType::Call));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tokenizer, Parser};
+
+    fn postfix(source: &str) -> String {
+        let ast = Parser::new(tokenizer::tokenize(source).0).parse().unwrap();
+        let postfix: Vec<String> = generate_postfix(&ast).iter().map(token_display).collect();
+        postfix.join(" ")
+    }
+
+    #[test]
+    fn expressions() {
+        assert_eq!(postfix("x = a + b * c;"), "x a b c * + = ;");
+        assert_eq!(postfix("x = (a + b) * -c;"), "x a b + 0 c - * = ;");
+        assert_eq!(postfix("x = a < b && c != d || e;"), "x a b < c d != && e || = ;");
+        assert_eq!(postfix("int a = 8, b, c = 9 + 5;"), "int a 8 = , b , c 9 5 + = ;");
+    }
+
+    #[test]
+    fn calls() {
+        assert_eq!(postfix("printf(\"%d\", a - 1);"), "\"%d\" a 1 - 2 CALL(printf) ;");
+        assert_eq!(postfix("f();"), "0 CALL(f) ;");
+        assert_eq!(postfix("x = f(a, g(b, c)) + 1;"), "x a b c 2 CALL(g) 2 CALL(f) 1 + = ;");
+        assert_eq!(postfix("x = 2 * f(g(a) - 1, 'c');"), "x 2 a 1 CALL(g) 1 - 'c' 2 CALL(f) * = ;");
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(postfix("int f(int a, char b) { a = b; }"), "int f int a , char b ; a b = ; ;");
+        assert_eq!(postfix("int main(void) { }"), "int main void ; ;");
+        assert_eq!(postfix("int f(void) { return a + 1; } void g(void) { return; }"), [
+            "int f void ; return a 1 + ; ;",
+            "void g void ; return ; ;",
+        ].join(" "));
+    }
+
+    #[test]
+    fn increments() {
+        // `++` and `--` are statements which assign the variable plus or minus one
+        assert_eq!(postfix("i++; --i;"), "i i 1 + = ; i i 1 - = ;");
+        assert_eq!(postfix("for (i = 0; i < 3; ++i) { }"), "for i 0 = ; i 3 < ; i i 1 + = ; ;");
+    }
+
+    #[test]
+    fn if_else() {
+        assert_eq!(postfix("if (a) { x = 1; }"), "if a ; x 1 = ; ;");
+        assert_eq!(postfix("if (a) { x = 1; } else { x = 2; }"), "if a ; x 1 = ; else x 2 = ; ; ;");
+        assert_eq!(
+            postfix("if (a) { x = 1; } else if (b) { x = 2; } else { x = 3; }"),
+            "if a ; x 1 = ; else if b ; x 2 = ; else x 3 = ; ; ; ; ;"
+        );
+    }
+
+    #[test]
+    fn nested_control_structures() {
+        assert_eq!(
+            postfix("while (i < 3) { if (i == 1) { x = i; } else { while (j) { j = j - 1; } } i = i + 1; }"),
+            "while i 3 < ; if i 1 == ; x i = ; else while j ; j j 1 - = ; ; ; ; i i 1 + = ; ;"
+        );
+        assert_eq!(
+            postfix("for (int i = 0; i < 3; i = i + 1) { for (j = 0; j < i; j = j + 1) { s = s + j; } }"),
+            "for int i 0 = ; i 3 < ; i i 1 + = ; for j 0 = ; j i < ; j j 1 + = ; s s j + = ; ; ;"
+        );
+        // a block on its own doesn't need to be closed
+        assert_eq!(postfix("for (;;) { { x = 1; } }"), "for ; ; ; x 1 = ; ;");
+    }
+}
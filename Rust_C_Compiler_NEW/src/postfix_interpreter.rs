@@ -0,0 +1,735 @@
+use crate::node_dict::{self, TokenType};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+
+// Runs the postfix code of `postfix_generator::generate_postfix`, which is first translated into
+// instructions for a stack machine where the control structures become jumps. What the program
+// prints goes to `output`.
+pub fn run(postfix: &[(String, TokenType)], output: &mut impl Write) -> Result<(), String> {
+    let instructions = Translator::new(postfix).translate()?;
+    Machine::new(output).execute(&instructions)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Integer(i64),
+    // floats and doubles are both stored as f64
+    Real(f64),
+    Char(char),
+    String(String),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Integer(value) => write!(f, "{}", value),
+            Value::Real(value) => write!(f, "{}", value),
+            Value::Char(value) => write!(f, "{}", value),
+            Value::String(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl Value {
+    fn as_real(&self) -> Result<f64, String> {
+        match self {
+            Value::Integer(value) => Ok(*value as f64),
+            Value::Real(value) => Ok(*value),
+            Value::Char(value) => Ok(*value as u32 as f64),
+            Value::String(value) => Err(format!("Expected a number but found \"{}\"", value)),
+        }
+    }
+
+    fn as_integer(&self) -> Result<i64, String> {
+        match self {
+            Value::Real(value) => Ok(*value as i64),
+            value => Ok(value.as_real()? as i64),
+        }
+    }
+
+    fn is_true(&self) -> Result<bool, String> {
+        Ok(self.as_real()? != 0.0)
+    }
+
+    // Converts the value for a variable declared with `token_type`
+    fn convert(self, token_type: TokenType) -> Result<Value, String> {
+        match (token_type, self) {
+            (TokenType::Integer, value) => Ok(Value::Integer(value.as_integer()?)),
+            (TokenType::Float | TokenType::Double, value) => Ok(Value::Real(value.as_real()?)),
+            (TokenType::Char, value) => {
+                let code = value.as_integer()?;
+                char::from_u32(code as u32)
+                    .map(Value::Char)
+                    .ok_or(format!("{} is not a valid char", code))
+            }
+            (token_type, _) => Err(format!("Can't store a value in a variable of type {:?}", token_type)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Operand {
+    Value(Value),
+    // variables are only looked up by the operator using them, since an assignment needs the name
+    Variable(String),
+}
+
+#[derive(Debug, Clone)]
+struct Function {
+    parameters: Vec<(String, TokenType)>,
+    return_type: TokenType,
+    // index of the first instruction of the body
+    entry: usize,
+}
+
+#[derive(Debug, Clone)]
+enum Instruction {
+    Push(Operand),
+    Operator(TokenType),
+    Declare(String, TokenType),
+    // defines the function whose body follows and jumps over it
+    Function(String, Function, usize),
+    // pops the number of arguments and then the arguments, and pushes the result
+    Call(String),
+    // returns from the function, with the value on the stack if there is one
+    Return(bool),
+    // empties the stack at the end of a statement
+    Discard,
+    Jump(usize),
+    JumpIfFalse(usize),
+}
+
+// Translates the postfix tokens into instructions. Every statement ends with a ";" and so does
+// the condition of a control structure, a block ends with a ";" that doesn't end any statement.
+struct Translator<'a> {
+    tokens: &'a [(String, TokenType)],
+    position: usize,
+    instructions: Vec<Instruction>,
+}
+
+impl<'a> Translator<'a> {
+    fn new(tokens: &'a [(String, TokenType)]) -> Self {
+        Translator { tokens, position: 0, instructions: Vec::new() }
+    }
+
+    fn translate(mut self) -> Result<Vec<Instruction>, String> {
+        while self.position < self.tokens.len() {
+            self.statement()?;
+        }
+        Ok(self.instructions)
+    }
+
+    fn peek(&self) -> Option<TokenType> {
+        self.tokens.get(self.position).map(|(_, token_type)| *token_type)
+    }
+
+    fn is_type_keyword(token: &(String, TokenType)) -> bool {
+        matches!(
+            token.1,
+            TokenType::Integer | TokenType::Char | TokenType::Float | TokenType::Double | TokenType::Void
+        )
+    }
+
+    fn statement(&mut self) -> Result<(), String> {
+        let token = &self.tokens[self.position];
+        match token.1 {
+            TokenType::If => self.if_statement(),
+            TokenType::While => self.while_loop(),
+            TokenType::For => self.for_loop(),
+            TokenType::Return => {
+                self.position += 1;
+                let value = self.segment(&[TokenType::Semicolon]);
+                self.position += 1;
+                self.expression(&value)?;
+                self.instructions.push(Instruction::Return(!value.is_empty()));
+                Ok(())
+            }
+            _ if Self::is_type_keyword(token) => self.declaration(),
+            _ => {
+                let expression = self.segment(&[TokenType::Semicolon]);
+                self.position += 1;
+                self.expression(&expression)?;
+                self.instructions.push(Instruction::Discard);
+                Ok(())
+            }
+        }
+    }
+
+    // Statements up to the ";" ending the block, or up to the `else` after the block of an if
+    fn block(&mut self) -> Result<(), String> {
+        loop {
+            match self.peek() {
+                Some(TokenType::Semicolon) => {
+                    self.position += 1;
+                    return Ok(());
+                }
+                Some(TokenType::Else) => return Ok(()),
+                None => return Err("Unterminated block".to_string()),
+                Some(_) => self.statement()?,
+            }
+        }
+    }
+
+    fn if_statement(&mut self) -> Result<(), String> {
+        self.position += 1;
+        self.condition()?;
+        let to_else = self.jump_placeholder(true);
+        self.block()?;
+
+        if self.peek() == Some(TokenType::Else) {
+            self.position += 1;
+            let to_end = self.jump_placeholder(false);
+            self.patch(to_else);
+            self.block()?;
+            // the ";" ending the whole if
+            if self.peek() == Some(TokenType::Semicolon) {
+                self.position += 1;
+            }
+            self.patch(to_end);
+        } else {
+            self.patch(to_else);
+        }
+        Ok(())
+    }
+
+    fn while_loop(&mut self) -> Result<(), String> {
+        self.position += 1;
+        let start = self.instructions.len();
+        self.condition()?;
+        let to_end = self.jump_placeholder(true);
+        self.block()?;
+        self.instructions.push(Instruction::Jump(start));
+        self.patch(to_end);
+        Ok(())
+    }
+
+    fn for_loop(&mut self) -> Result<(), String> {
+        self.position += 1;
+        match self.tokens.get(self.position) {
+            Some(token) if Self::is_type_keyword(token) => self.declaration()?,
+            _ => {
+                let init = self.segment(&[TokenType::Semicolon]);
+                self.position += 1;
+                self.expression(&init)?;
+                self.instructions.push(Instruction::Discard);
+            }
+        }
+
+        let start = self.instructions.len();
+        let condition = self.segment(&[TokenType::Semicolon]);
+        self.position += 1;
+        // without a condition the loop never ends
+        let to_end = if condition.is_empty() {
+            None
+        } else {
+            self.expression(&condition)?;
+            Some(self.jump_placeholder(true))
+        };
+
+        // the update comes before the body but runs after it
+        let update = self.segment(&[TokenType::Semicolon]);
+        self.position += 1;
+        self.block()?;
+        self.expression(&update)?;
+        self.instructions.push(Instruction::Discard);
+        self.instructions.push(Instruction::Jump(start));
+
+        if let Some(to_end) = to_end {
+            self.patch(to_end);
+        }
+        Ok(())
+    }
+
+    // `type name value = , name ;` or a function definition
+    fn declaration(&mut self) -> Result<(), String> {
+        let token_type = self.tokens[self.position].1;
+        self.position += 1;
+
+        let declarators = self.list()?;
+        if declarators.iter().any(|declarator| declarator.iter().skip(1).any(Self::is_type_keyword)) {
+            return self.function(token_type, declarators);
+        }
+        for declarator in declarators {
+            match declarator.first() {
+                Some((name, TokenType::Identifier)) => {
+                    self.instructions.push(Instruction::Declare(name.clone(), token_type));
+                }
+                _ => return Err("Expected a variable name in declaration".to_string()),
+            }
+            if declarator.len() > 1 {
+                self.expression(&declarator)?;
+                self.instructions.push(Instruction::Discard);
+            }
+        }
+        Ok(())
+    }
+
+    // The header `type name type parameter , ... ;` or `type name void ;` was already read, the body
+    // follows and ends with an implicit return
+    fn function(&mut self, return_type: TokenType, header: Vec<Vec<(String, TokenType)>>) -> Result<(), String> {
+        let mut header = header.into_iter();
+        let first = header.next().unwrap_or_default();
+        let (name, first_parameter) = match first.split_first() {
+            Some(((name, TokenType::Identifier), parameter)) => (name.clone(), parameter.to_vec()),
+            _ => return Err("Expected a function name".to_string()),
+        };
+
+        let mut parameters = Vec::new();
+        let declarators: Vec<_> = std::iter::once(first_parameter).chain(header).collect();
+        for declarator in declarators.iter() {
+            match declarator.as_slice() {
+                [(_, TokenType::Void)] if declarators.len() == 1 => {}
+                [token, (parameter, TokenType::Identifier)] if Self::is_type_keyword(token) => {
+                    parameters.push((parameter.clone(), token.1));
+                }
+                _ => return Err(format!("Invalid parameter in the definition of \"{}\"", name)),
+            }
+        }
+
+        let definition = self.instructions.len();
+        let entry = definition + 1;
+        self.instructions.push(Instruction::Function(name, Function { parameters, return_type, entry }, 0));
+        self.block()?;
+        self.instructions.push(Instruction::Return(false));
+
+        let end = self.instructions.len();
+        if let Instruction::Function(_, _, to) = &mut self.instructions[definition] {
+            *to = end;
+        }
+        Ok(())
+    }
+
+    fn condition(&mut self) -> Result<(), String> {
+        let condition = self.segment(&[TokenType::Semicolon]);
+        self.position += 1;
+        if condition.is_empty() {
+            return Err("Missing condition".to_string());
+        }
+        self.expression(&condition)
+    }
+
+    // The expressions separated by "," up to the ";"
+    fn list(&mut self) -> Result<Vec<Vec<(String, TokenType)>>, String> {
+        let mut list = Vec::new();
+        loop {
+            list.push(self.segment(&[TokenType::Comma, TokenType::Semicolon]));
+            match self.peek() {
+                Some(TokenType::Comma) => self.position += 1,
+                Some(TokenType::Semicolon) => {
+                    self.position += 1;
+                    return Ok(list);
+                }
+                _ => return Err("Expected ';' at the end of the program".to_string()),
+            }
+        }
+    }
+
+    // The tokens up to one of the `ends`, which isn't consumed
+    fn segment(&mut self, ends: &[TokenType]) -> Vec<(String, TokenType)> {
+        let start = self.position;
+        while self.peek().is_some_and(|token_type| !ends.contains(&token_type)) {
+            self.position += 1;
+        }
+        self.tokens[start..self.position].to_vec()
+    }
+
+    fn expression(&mut self, tokens: &[(String, TokenType)]) -> Result<(), String> {
+        for (text, token_type) in tokens {
+            let operand = match token_type {
+                TokenType::Identifier => Operand::Variable(text.clone()),
+                TokenType::IntegerLiteral => Operand::Value(Value::Integer(
+                    text.parse().map_err(|_| format!("Invalid integer \"{}\"", text))?,
+                )),
+                TokenType::FloatLiteral => Operand::Value(Value::Real(
+                    text.parse().map_err(|_| format!("Invalid number \"{}\"", text))?,
+                )),
+                TokenType::CharLiteral => match text.chars().next() {
+                    Some(value) => Operand::Value(Value::Char(value)),
+                    None => return Err("Empty char literal".to_string()),
+                },
+                TokenType::StringLiteral => Operand::Value(Value::String(text.clone())),
+                TokenType::Call => {
+                    self.instructions.push(Instruction::Call(text.clone()));
+                    continue;
+                }
+                token_type if node_dict::is_operator(*token_type) => {
+                    self.instructions.push(Instruction::Operator(*token_type));
+                    continue;
+                }
+                _ => return Err(format!("Unexpected \"{}\" in expression", text)),
+            };
+            self.instructions.push(Instruction::Push(operand));
+        }
+        Ok(())
+    }
+
+    fn jump_placeholder(&mut self, conditional: bool) -> usize {
+        self.instructions.push(if conditional { Instruction::JumpIfFalse(0) } else { Instruction::Jump(0) });
+        self.instructions.len() - 1
+    }
+
+    // Makes the jump at `index` target the next instruction
+    fn patch(&mut self, index: usize) {
+        let target = self.instructions.len();
+        match &mut self.instructions[index] {
+            Instruction::Jump(to) | Instruction::JumpIfFalse(to) => *to = target,
+            _ => unreachable!("only jumps are patched"),
+        }
+    }
+}
+
+struct Variable {
+    token_type: TokenType,
+    value: Value,
+}
+
+// A running call of a function
+struct Frame {
+    name: String,
+    return_type: TokenType,
+    // the parameters and the variables declared in the body
+    variables: HashMap<String, Variable>,
+    return_to: usize,
+    // the operands of the caller on the stack stay below it
+    stack_base: usize,
+}
+
+struct Machine<'a, W: Write> {
+    stack: Vec<Operand>,
+    // the variables declared outside of the functions
+    variables: HashMap<String, Variable>,
+    functions: HashMap<String, Function>,
+    frames: Vec<Frame>,
+    output: &'a mut W,
+}
+
+impl<'a, W: Write> Machine<'a, W> {
+    fn new(output: &'a mut W) -> Self {
+        Machine {
+            stack: Vec::new(),
+            variables: HashMap::new(),
+            functions: HashMap::new(),
+            frames: Vec::new(),
+            output,
+        }
+    }
+
+    // The variables of the function running, or the global ones outside of the functions
+    fn scope(&mut self) -> &mut HashMap<String, Variable> {
+        match self.frames.last_mut() {
+            Some(frame) => &mut frame.variables,
+            None => &mut self.variables,
+        }
+    }
+
+    // Variables of the function running hide the global ones
+    fn variable(&mut self, name: &str) -> Result<&mut Variable, String> {
+        let variables = match self.frames.last_mut() {
+            Some(frame) if frame.variables.contains_key(name) => &mut frame.variables,
+            _ => &mut self.variables,
+        };
+        variables.get_mut(name).ok_or(format!("Undeclared variable \"{}\"", name))
+    }
+
+    fn stack_base(&self) -> usize {
+        self.frames.last().map_or(0, |frame| frame.stack_base)
+    }
+
+    fn execute(&mut self, instructions: &[Instruction]) -> Result<(), String> {
+        let mut pc = 0;
+        while let Some(instruction) = instructions.get(pc) {
+            pc += 1;
+            match instruction {
+                Instruction::Push(operand) => self.stack.push(operand.clone()),
+                Instruction::Operator(TokenType::Assign) => {
+                    let value = self.pop_value()?;
+                    let name = match self.stack.pop() {
+                        Some(Operand::Variable(name)) => name,
+                        _ => return Err("Can only assign to a variable".to_string()),
+                    };
+                    let variable = self.variable(&name)?;
+                    variable.value = value.convert(variable.token_type)?;
+                    let value = variable.value.clone();
+                    self.stack.push(Operand::Value(value));
+                }
+                Instruction::Operator(operator) => {
+                    let right = self.pop_value()?;
+                    let left = self.pop_value()?;
+                    self.stack.push(Operand::Value(binary(*operator, left, right)?));
+                }
+                Instruction::Declare(name, token_type) => {
+                    let value = match token_type {
+                        TokenType::Float | TokenType::Double => Value::Real(0.0),
+                        TokenType::Char => Value::Char('\0'),
+                        _ => Value::Integer(0),
+                    };
+                    self.scope().insert(name.clone(), Variable { token_type: *token_type, value });
+                }
+                Instruction::Call(name) => {
+                    let count = self.pop_value()?.as_integer()?;
+                    let mut arguments = Vec::new();
+                    for _ in 0..count {
+                        arguments.push(self.pop_value()?);
+                    }
+                    arguments.reverse();
+                    if name == "printf" {
+                        // printf returns the number of characters printed
+                        let text = format(&arguments)?;
+                        write!(self.output, "{}", text).map_err(|e| e.to_string())?;
+                        self.stack.push(Operand::Value(Value::Integer(text.chars().count() as i64)));
+                    } else {
+                        pc = self.call(name, arguments, pc)?;
+                    }
+                }
+                Instruction::Function(name, function, end) => {
+                    self.functions.insert(name.clone(), function.clone());
+                    pc = *end;
+                }
+                Instruction::Return(has_value) => {
+                    let value = if *has_value { Some(self.pop_value()?) } else { None };
+                    let frame = self.frames.pop().ok_or("Return outside of a function")?;
+                    self.stack.truncate(frame.stack_base);
+                    match (value, frame.return_type) {
+                        (None, TokenType::Void) => {}
+                        (Some(_), TokenType::Void) => {
+                            return Err(format!("Function \"{}\" can't return a value", frame.name));
+                        }
+                        (Some(value), return_type) => {
+                            self.stack.push(Operand::Value(value.convert(return_type)?));
+                        }
+                        (None, _) => return Err(format!("Function \"{}\" has to return a value", frame.name)),
+                    }
+                    pc = frame.return_to;
+                }
+                Instruction::Discard => {
+                    let base = self.stack_base();
+                    if self.stack.len() > base + 1 {
+                        return Err("Invalid expression, operands are left over".to_string());
+                    }
+                    self.stack.truncate(base);
+                }
+                Instruction::Jump(to) => pc = *to,
+                Instruction::JumpIfFalse(to) => {
+                    if !self.pop_value()?.is_true()? {
+                        pc = *to;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Starts running the function with the parameters bound to the arguments, returns where it starts
+    fn call(&mut self, name: &str, arguments: Vec<Value>, return_to: usize) -> Result<usize, String> {
+        let function = self.functions.get(name).ok_or(format!("Undefined function \"{}\"", name))?;
+        if arguments.len() != function.parameters.len() {
+            return Err(format!(
+                "Function \"{}\" takes {} arguments but {} were given",
+                name,
+                function.parameters.len(),
+                arguments.len()
+            ));
+        }
+
+        let mut variables = HashMap::new();
+        for ((parameter, token_type), value) in function.parameters.iter().zip(arguments) {
+            let value = value.convert(*token_type)?;
+            variables.insert(parameter.clone(), Variable { token_type: *token_type, value });
+        }
+        let entry = function.entry;
+        self.frames.push(Frame {
+            name: name.to_string(),
+            return_type: function.return_type,
+            variables,
+            return_to,
+            stack_base: self.stack.len(),
+        });
+        Ok(entry)
+    }
+
+    fn pop_value(&mut self) -> Result<Value, String> {
+        if self.stack.len() <= self.stack_base() {
+            return Err("Missing operand".to_string());
+        }
+        match self.stack.pop() {
+            Some(Operand::Value(value)) => Ok(value),
+            Some(Operand::Variable(name)) => Ok(self.variable(&name)?.value.clone()),
+            None => Err("Missing operand".to_string()),
+        }
+    }
+}
+
+fn binary(operator: TokenType, left: Value, right: Value) -> Result<Value, String> {
+    let is_integer = |value: &Value| matches!(value, Value::Integer(_) | Value::Char(_));
+    let truth = |condition: bool| Value::Integer(condition as i64);
+
+    if matches!(operator, TokenType::And | TokenType::Or) {
+        let (left, right) = (left.is_true()?, right.is_true()?);
+        return Ok(truth(if operator == TokenType::And { left && right } else { left || right }));
+    }
+
+    // arithmetic stays in integers unless one of the operands is a float
+    if is_integer(&left) && is_integer(&right) {
+        let (left, right) = (left.as_integer()?, right.as_integer()?);
+        return Ok(match operator {
+            TokenType::Plus => Value::Integer(left.wrapping_add(right)),
+            TokenType::Minus => Value::Integer(left.wrapping_sub(right)),
+            TokenType::Mult => Value::Integer(left.wrapping_mul(right)),
+            TokenType::Division if right == 0 => return Err("Division by zero".to_string()),
+            TokenType::Division => Value::Integer(left / right),
+            _ => truth(compare(operator, left.cmp(&right))?),
+        });
+    }
+
+    let (left, right) = (left.as_real()?, right.as_real()?);
+    Ok(match operator {
+        TokenType::Plus => Value::Real(left + right),
+        TokenType::Minus => Value::Real(left - right),
+        TokenType::Mult => Value::Real(left * right),
+        TokenType::Division => Value::Real(left / right),
+        _ => truth(compare(operator, left.partial_cmp(&right).ok_or("Can't compare NaN")?)?),
+    })
+}
+
+fn compare(operator: TokenType, ordering: std::cmp::Ordering) -> Result<bool, String> {
+    use std::cmp::Ordering::*;
+    Ok(match operator {
+        TokenType::Equals => ordering == Equal,
+        TokenType::NotEquals => ordering != Equal,
+        TokenType::Less => ordering == Less,
+        TokenType::LessEquals => ordering != Greater,
+        TokenType::More => ordering == Greater,
+        TokenType::MoreEquals => ordering != Less,
+        operator => return Err(format!("Unknown operator {:?}", operator)),
+    })
+}
+
+// Formats the arguments of a printf, where the first one is the format string with `%d`, `%f`, `%c`
+// or `%s` for each following argument
+fn format(arguments: &[Value]) -> Result<String, String> {
+    let (format, mut values) = match arguments.split_first() {
+        Some((Value::String(format), values)) => (format, values.iter()),
+        _ => return Err("The first argument of printf has to be a string".to_string()),
+    };
+
+    let mut output = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch != '%' {
+            output.push(ch);
+            continue;
+        }
+        // an optional precision like `%.2f`
+        let mut precision = String::new();
+        while let Some(&digit) = chars.peek().filter(|c| c.is_ascii_digit() || **c == '.') {
+            precision.push(digit);
+            chars.next();
+        }
+        let conversion = chars.next().ok_or("Incomplete conversion at the end of the printf format")?;
+        if conversion == '%' {
+            output.push('%');
+            continue;
+        }
+
+        let value = values.next().ok_or("Too few arguments for the printf format")?;
+        match conversion {
+            'd' | 'i' => output.push_str(&value.as_integer()?.to_string()),
+            'f' => {
+                let precision = precision.trim_start_matches('.').parse().unwrap_or(6);
+                output.push_str(&format!("{:.*}", precision, value.as_real()?));
+            }
+            'c' => output.push(char::from_u32(value.as_integer()? as u32).unwrap_or('?')),
+            's' => output.push_str(&value.to_string()),
+            other => return Err(format!("Unknown conversion '%{}' in printf format", other)),
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{postfix_generator, tokenizer, Parser};
+
+    // The output of the program, or the runtime error ending it
+    fn run_source(source: &str) -> Result<String, String> {
+        let ast = Parser::new(tokenizer::tokenize(source).0).parse().unwrap();
+        let mut output = Vec::new();
+        run(&postfix_generator::generate_postfix(&ast), &mut output)?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(run_source("printf(\"%d %d %d\", 1 + 2 * 3, (1 + 2) * 3, 7 / 2 - -1);").unwrap(), "7 9 4");
+        // a float operand makes the whole operation a float one
+        assert_eq!(run_source("printf(\"%f %d\", 7 / 2.0, 1 < 2 && 2 >= 3);").unwrap(), "3.500000 0");
+        assert_eq!(run_source("printf(\"%d\", 1 / (2 - 2));"), Err("Division by zero".to_string()));
+    }
+
+    #[test]
+    fn typed_declarations() {
+        let source = "int i = 2.9; float f = 1; char c = 97; double d;\nprintf(\"%d %f %c %f\", i, f, c, d);";
+        assert_eq!(run_source(source).unwrap(), "2 1.000000 a 0.000000");
+        // assignments convert to the type of the variable like the initialization
+        let source = "int i; i = 'b' + 1; char c = 'a'; c = c + 2; printf(\"%d %c\", i, c);";
+        assert_eq!(run_source(source).unwrap(), "99 c");
+        assert_eq!(run_source("int i = \"text\";"), Err("Expected a number but found \"text\"".to_string()));
+        assert_eq!(run_source("i = 1;"), Err("Undeclared variable \"i\"".to_string()));
+    }
+
+    #[test]
+    fn control_structures() {
+        let source = "int s = 0, i = 0;
+            while (i < 5) {
+                if (i == 1) { s = s + 10; } else if (i == 3) { s = s + 100; } else { s = s + 1; }
+                i = i + 1;
+            }
+            printf(\"%d \", s);
+            for (int j = 0; j < 3; j = j + 1) { for (int k = 0; k <= j; k = k + 1) { printf(\"%d\", k); } }";
+        assert_eq!(run_source(source).unwrap(), "113 001012");
+    }
+
+    #[test]
+    fn printf_formatting() {
+        let source = "double d = 3.14159; char c = 'x';
+            printf(\"%.2f|%.0f|%c|%d|%s|%s|100%%\\n\", d, d, c, c, \"str\", 5);";
+        assert_eq!(run_source(source).unwrap(), "3.14|3|x|120|str|5|100%\n");
+        assert_eq!(run_source("printf(\"%d %d\", 1);"), Err("Too few arguments for the printf format".to_string()));
+        assert_eq!(run_source("printf(\"%q\", 1);"), Err("Unknown conversion '%q' in printf format".to_string()));
+        assert_eq!(run_source("printf(1);"), Err("The first argument of printf has to be a string".to_string()));
+    }
+
+    #[test]
+    fn functions() {
+        // parameters and local variables hide the global ones
+        let source = "int n = 0, k = 7;
+            void repeat(int k, char c) { while (k > 0) { printf(\"%c\", c); k = k - 1; n = n + 1; } }
+            repeat(3, 'a'); repeat(2, 98); printf(\" %d %d\", n, k);";
+        assert_eq!(run_source(source).unwrap(), "aaabb 5 7");
+        let source = "void count(int i) { if (i > 0) { count(i - 1); printf(\"%d\", i); } } count(3);";
+        assert_eq!(run_source(source).unwrap(), "123");
+
+        let source = "int fib(int n) { if (n < 2) { return n; } return fib(n - 1) + fib(n - 2); }
+            char next(char c) { return c + 1; } void stop(int i) { while (1) { if (i == 0) { return; } i--; } }
+            stop(5); printf(\"%d %c\", fib(10), next('a'));";
+        assert_eq!(run_source(source).unwrap(), "55 b");
+
+        assert_eq!(run_source("f(1);"), Err("Undefined function \"f\"".to_string()));
+        assert_eq!(run_source("return 1;"), Err("Return outside of a function".to_string()));
+        assert_eq!(run_source("void v(void) { return 1; } v();"), Err("Function \"v\" can't return a value".to_string()));
+        assert_eq!(
+            run_source("void g(int a) { } g();"),
+            Err("Function \"g\" takes 1 arguments but 0 were given".to_string())
+        );
+        assert_eq!(run_source("int h(void) { } int x = h();"), Err("Function \"h\" has to return a value".to_string()));
+        assert_eq!(run_source("void u(void) { y = 1; } u();"), Err("Undeclared variable \"y\"".to_string()));
+    }
+
+    #[test]
+    fn printf_in_expressions() {
+        let source = "int n = printf(\"ab\") + printf(\"c\"); if (printf(\"\") == 0) { printf(\" %d\", n); }";
+        assert_eq!(run_source(source).unwrap(), "abc 3");
+    }
+}
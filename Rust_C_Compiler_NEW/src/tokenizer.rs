@@ -0,0 +1,325 @@
+use crate::compiler_error::CompilerError;
+use crate::node_dict::{self, TokenType};
+use std::iter::Peekable;
+use std::str::Chars;
+
+#[derive(Debug, Clone)]
+pub struct Token {
+    // the value of a string or char literal, with its escapes already replaced
+    pub text: String,
+    pub token_type: TokenType,
+    pub line: u16,
+    pub column: u16,
+}
+
+// Tokenizes the whole source at once, so comments and strings can span several lines
+pub fn tokenize(source: &str) -> (Vec<Token>, Vec<CompilerError>) {
+    let mut tokenizer = Tokenizer {
+        chars: source.chars().peekable(),
+        line: 1,
+        column: 1,
+        tokens: Vec::new(),
+        errors: Vec::new(),
+    };
+    tokenizer.run();
+    (tokenizer.tokens, tokenizer.errors)
+}
+
+struct Tokenizer<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: u16,
+    column: u16,
+    tokens: Vec<Token>,
+    errors: Vec<CompilerError>,
+}
+
+impl Tokenizer<'_> {
+    fn next(&mut self) -> Option<char> {
+        let ch = self.chars.next()?;
+        if ch == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(ch)
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn next_if(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn push(&mut self, text: String, token_type: TokenType, line: u16, column: u16) {
+        self.tokens.push(Token { text, token_type, line, column });
+    }
+
+    fn run(&mut self) {
+        while let Some(ch) = self.peek() {
+            let (line, column) = (self.line, self.column);
+
+            if ch.is_whitespace() {
+                self.next();
+            } else if ch.is_ascii_digit() {
+                self.number(line, column);
+            } else if ch.is_alphabetic() || ch == '_' {
+                self.word(line, column);
+            } else if ch == '"' || ch == '\'' {
+                self.next();
+                self.literal(ch, line, column);
+            } else if ch == '/' {
+                self.next();
+                if self.next_if('/') {
+                    while self.peek().is_some_and(|ch| ch != '\n') {
+                        self.next();
+                    }
+                } else if self.next_if('*') {
+                    self.multiline_comment(line, column);
+                } else {
+                    self.push("/".to_string(), TokenType::Division, line, column);
+                }
+            } else if ch == '\\' {
+                // a line continuation outside of a string only joins the lines
+                self.next();
+                if !self.next_if('\n') {
+                    self.errors.push(CompilerError::InvalidToken(ch.to_string(), line, column));
+                }
+            } else {
+                self.symbol(line, column);
+            }
+        }
+    }
+
+    // A comment after its opening `/*`, which is reported at its start if it isn't closed
+    fn multiline_comment(&mut self, line: u16, column: u16) {
+        while let Some(ch) = self.next() {
+            if ch == '*' && self.next_if('/') {
+                return;
+            }
+        }
+        self.errors.push(CompilerError::InvalidToken("/*".to_string(), line, column));
+    }
+
+    fn number(&mut self, line: u16, column: u16) {
+        let mut number = String::new();
+        while let Some(ch) = self.peek().filter(|ch| ch.is_ascii_digit() || *ch == '.') {
+            // only the first "." belongs to the number
+            if ch == '.' && number.contains('.') {
+                break;
+            }
+            number.push(ch);
+            self.next();
+        }
+
+        let token_type = if number.contains('.') { TokenType::FloatLiteral } else { TokenType::IntegerLiteral };
+        self.push(number, token_type, line, column);
+    }
+
+    fn word(&mut self, line: u16, column: u16) {
+        let mut word = String::new();
+        while let Some(ch) = self.peek().filter(|ch| ch.is_alphanumeric() || *ch == '_') {
+            word.push(ch);
+            self.next();
+        }
+
+        if let Some(token_type) = node_dict::reserved_type(&word) {
+            self.push(word, token_type, line, column);
+        } else if node_dict::RESERVED_WORDS.contains(&word.as_str()) {
+            // reserved words without a token type aren't supported by the parser yet
+            self.errors.push(CompilerError::InvalidToken(word, line, column));
+        } else {
+            self.push(word, TokenType::Identifier, line, column);
+        }
+    }
+
+    // A string or char literal after its opening `quote`
+    fn literal(&mut self, quote: char, line: u16, column: u16) {
+        let mut value = String::new();
+        loop {
+            match self.next() {
+                Some(ch) if ch == quote => break,
+                Some('\\') => match self.next() {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some('r') => value.push('\r'),
+                    Some('0') => value.push('\0'),
+                    // the literal continues on the next line
+                    Some('\n') => {}
+                    Some(ch) => value.push(ch),
+                    None => value.push('\\'),
+                },
+                Some(ch) if ch != '\n' => value.push(ch),
+                // the line or file ended before the closing quote
+                _ => {
+                    self.errors.push(CompilerError::InvalidToken(format!("{}{}", quote, value), line, column));
+                    return;
+                }
+            }
+        }
+
+        if quote == '"' {
+            self.push(value, TokenType::StringLiteral, line, column);
+        } else if value.chars().count() == 1 {
+            self.push(value, TokenType::CharLiteral, line, column);
+        } else {
+            self.errors.push(CompilerError::InvalidToken(format!("'{}'", value), line, column));
+        }
+    }
+
+    // The longest operator or symbol starting here, like `==` instead of `=`
+    fn symbol(&mut self, line: u16, column: u16) {
+        let mut operator = String::new();
+        while let Some(ch) = self.peek() {
+            operator.push(ch);
+            if !node_dict::OPERATORS_AND_SYMBOLS.contains(&operator.as_str()) {
+                operator.pop();
+                break;
+            }
+            self.next();
+        }
+
+        if operator.is_empty() {
+            let ch = self.next().unwrap();
+            self.errors.push(CompilerError::InvalidToken(ch.to_string(), line, column));
+            return;
+        }
+
+        let token_type = match operator.as_str() {
+            ";" => Some(TokenType::Semicolon),
+            ":" => Some(TokenType::Colon),
+            "," => Some(TokenType::Comma),
+            "(" => Some(TokenType::LParen),
+            ")" => Some(TokenType::RParen),
+            "{" => Some(TokenType::LBrace),
+            "}" => Some(TokenType::RBrace),
+            "[" => Some(TokenType::LBrack),
+            "]" => Some(TokenType::RBrack),
+            _ => node_dict::operator_type(&operator),
+        };
+        match token_type {
+            Some(token_type) => self.push(operator, token_type, line, column),
+            // operators without a token type aren't supported by the parser yet
+            None => self.errors.push(CompilerError::InvalidToken(operator, line, column)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(source: &str) -> Vec<(String, TokenType)> {
+        let (tokens, errors) = tokenize(source);
+        assert!(errors.is_empty(), "{:?}", errors);
+        tokens.into_iter().map(|token| (token.text, token.token_type)).collect()
+    }
+
+    fn errors(source: &str) -> Vec<String> {
+        tokenize(source).1.iter().map(|error| error.to_string()).collect()
+    }
+
+    fn token(text: &str, token_type: TokenType) -> (String, TokenType) {
+        (text.to_string(), token_type)
+    }
+
+    #[test]
+    fn literal_kinds() {
+        assert_eq!(tokens("x = 12 + 3.5 - 'c' + \"s\";"), [
+            token("x", TokenType::Identifier),
+            token("=", TokenType::Assign),
+            token("12", TokenType::IntegerLiteral),
+            token("+", TokenType::Plus),
+            token("3.5", TokenType::FloatLiteral),
+            token("-", TokenType::Minus),
+            token("c", TokenType::CharLiteral),
+            token("+", TokenType::Plus),
+            token("s", TokenType::StringLiteral),
+            token(";", TokenType::Semicolon),
+        ]);
+        // only the first "." belongs to the number
+        let (numbers, _) = tokenize("1.2.3");
+        assert_eq!((numbers[0].text.as_str(), numbers[1].text.as_str()), ("1.2", "3"));
+        assert_eq!(tokens("int double_it printf")[1], token("double_it", TokenType::Identifier));
+        assert_eq!(tokens("a <= b != c")[1], token("<=", TokenType::LessEquals));
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(tokens(r#""a\tb\n\"q\" \\ \0""#), [token("a\tb\n\"q\" \\ \0", TokenType::StringLiteral)]);
+        assert_eq!(tokens(r"'\n' '\'' '\\'"), [
+            token("\n", TokenType::CharLiteral),
+            token("'", TokenType::CharLiteral),
+            token("\\", TokenType::CharLiteral),
+        ]);
+    }
+
+    #[test]
+    fn line_continuations() {
+        // inside of a literal the line break is left out, outside of one the lines are joined
+        assert_eq!(tokens("\"ab\\\ncd\"\nx \\\n= 1;"), [
+            token("abcd", TokenType::StringLiteral),
+            token("x", TokenType::Identifier),
+            token("=", TokenType::Assign),
+            token("1", TokenType::IntegerLiteral),
+            token(";", TokenType::Semicolon),
+        ]);
+        let (continued, _) = tokenize("\"ab\\\ncd\"\nx \\\n= 1;");
+        let positions: Vec<(u16, u16)> = continued.iter().map(|token| (token.line, token.column)).collect();
+        assert_eq!(positions, [(1, 1), (3, 1), (4, 1), (4, 3), (4, 4)]);
+    }
+
+    #[test]
+    fn comments() {
+        let source = "a // b\n/* c\n d */ e /* f */ / g";
+        let positions: Vec<(String, u16, u16)> =
+            tokenize(source).0.into_iter().map(|token| (token.text, token.line, token.column)).collect();
+        assert_eq!(positions, [
+            ("a".to_string(), 1, 1),
+            ("e".to_string(), 3, 7),
+            ("/".to_string(), 3, 17),
+            ("g".to_string(), 3, 19),
+        ]);
+    }
+
+    #[test]
+    fn error_positions() {
+        assert_eq!(errors("int a = 1;\n  b = @ + 'ab';\n\"open"), [
+            "Invalid token \"@\" at line: 2, column: 7",
+            "Invalid token \"'ab'\" at line: 2, column: 11",
+            "Invalid token \"\"open\" at line: 3, column: 1",
+        ]);
+        assert_eq!(errors("x = 1 \\ 2;"), ["Invalid token \"\\\" at line: 1, column: 7"]);
+        assert_eq!(errors("a;\n  /* open\n*"), ["Invalid token \"/*\" at line: 2, column: 3"]);
+    }
+
+    #[test]
+    fn unsupported_tokens() {
+        // operators and reserved words the parser doesn't know are reported instead of left out
+        assert_eq!(errors("j << 1;\nbreak 10 % 3;"), [
+            "Invalid token \"<<\" at line: 1, column: 3",
+            "Invalid token \"break\" at line: 2, column: 1",
+            "Invalid token \"%\" at line: 2, column: 10",
+        ]);
+        assert_eq!(tokens("return i++ --")[1..], [
+            token("i", TokenType::Identifier),
+            token("++", TokenType::Increment),
+            token("--", TokenType::Decrement),
+        ]);
+        assert_eq!(errors("do { p->x = !a & ~b; } while (s.y);"), [
+            "Invalid token \"do\" at line: 1, column: 1",
+            "Invalid token \"->\" at line: 1, column: 7",
+            "Invalid token \"!\" at line: 1, column: 13",
+            "Invalid token \"&\" at line: 1, column: 16",
+            "Invalid token \"~\" at line: 1, column: 18",
+            "Invalid token \".\" at line: 1, column: 32",
+        ]);
+    }
+}